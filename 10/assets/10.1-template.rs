@@ -0,0 +1,489 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+        Hash,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The stage a lottery is in, which follows from the block number.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Phase {
+    /// Tickets can be bought with a commitment.
+    Commit,
+    /// The players reveal the secrets behind their commitments.
+    Reveal,
+    /// The winner has been drawn and can claim the pot.
+    Finished,
+}
+
+/// Events deposited by the lottery contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    TicketBought {
+        player: AccountId,
+    },
+    TicketRevealed {
+        player: AccountId,
+    },
+    PotClaimed {
+        winner: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the lottery contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum LotteryError {
+    /// The call is not allowed in the current phase.
+    WrongPhase,
+    /// The native balance sent along is not the ticket price.
+    WrongPrice,
+    /// Every player can only buy one ticket.
+    AlreadyCommitted,
+    /// The caller has no ticket left to reveal.
+    NoCommitment,
+    /// The secret does not match the commitment of the caller.
+    WrongSecret,
+    /// Only the winner can claim the pot.
+    NotWinner,
+    /// The pot has already been paid out.
+    AlreadyClaimed,
+}
+
+/// Deposits a lottery event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Returns the BLAKE2b-256 hash of `data`.
+fn hash_of(data: &[u8]) -> Hash {
+    let hash = blake2_rfc::blake2b::blake2b(32, &[], data);
+    Hash::decode(&mut &hash.as_bytes()[..]).expect("a BLAKE2b-256 hash has the size of a Hash")
+}
+
+/// Returns the commitment `player` buys a ticket with to later reveal `secret`.
+///
+/// The player is part of the commitment, so nobody can copy the commitment
+/// of someone else and reveal their secret after them.
+fn commitment_of(player: AccountId, secret: Hash) -> Hash {
+    // ACTION: Return the `hash_of` the SCALE encoded tuple `(player, secret)`
+    Hash::default()
+}
+
+contract! {
+    /// A lottery whose winner is drawn from secrets chosen by the players.
+    ///
+    /// During the commit phase, players buy a ticket with native balance and
+    /// a commitment to a secret. During the reveal phase, they reveal their
+    /// secrets, which are all mixed into the seed the winner is drawn with.
+    /// Nobody knows the seed before the last secret has been revealed, and
+    /// players who do not reveal theirs cannot win, but their ticket still
+    /// counts towards the pot.
+    struct Lottery {
+        /// The account which deployed the lottery and gets the pot if nobody
+        /// reveals their secret.
+        owner: storage::Value<AccountId>,
+        /// The native balance a ticket costs.
+        ticket_price: storage::Value<Balance>,
+        /// The last block in which tickets can be bought.
+        commit_end: storage::Value<BlockNumber>,
+        /// The last block in which secrets can be revealed.
+        reveal_end: storage::Value<BlockNumber>,
+        /// The commitments of the players who have not revealed their secret yet.
+        commitments: storage::HashMap<AccountId, Hash>,
+        /// The players who revealed their secret, and can win.
+        revealed: storage::Vec<AccountId>,
+        /// The seed the winner is drawn with.
+        seed: storage::Value<Hash>,
+        /// The native balance paid for all tickets.
+        pot: storage::Value<Balance>,
+        /// Whether the pot has been paid out.
+        claimed: storage::Value<bool>,
+    }
+
+    impl Deploy for Lottery {
+        fn deploy(&mut self, ticket_price: Balance, commit_duration: BlockNumber, reveal_duration: BlockNumber) {
+            let commit_end = env::block_number() + commit_duration;
+            self.owner.set(env.caller());
+            self.ticket_price.set(ticket_price);
+            self.commit_end.set(commit_end);
+            self.reveal_end.set(commit_end + reveal_duration);
+            self.seed.set(hash_of(&(env::address(), env::block_number()).encode()));
+            self.pot.set(0);
+            self.claimed.set(false);
+        }
+    }
+
+    impl Lottery {
+        /// Returns the native balance a ticket costs.
+        pub(external) fn ticket_price(&self) -> Balance {
+            let ticket_price = *self.ticket_price;
+            env.println(&format!("Lottery::ticket_price = {:?}", ticket_price));
+            ticket_price
+        }
+
+        /// Returns the native balance paid for all tickets.
+        pub(external) fn pot(&self) -> Balance {
+            let pot = *self.pot;
+            env.println(&format!("Lottery::pot = {:?}", pot));
+            pot
+        }
+
+        /// Returns the phase the lottery is in.
+        pub(external) fn phase(&self) -> Phase {
+            let phase = self.phase_at(env.block_number());
+            env.println(&format!("Lottery::phase = {:?}", phase));
+            phase
+        }
+
+        /// Returns the number of players who revealed their secret.
+        pub(external) fn revealed_count(&self) -> u32 {
+            let revealed_count = self.revealed.len();
+            env.println(&format!("Lottery::revealed_count = {:?}", revealed_count));
+            revealed_count
+        }
+
+        /// Returns the seed the winner is drawn with, which changes with every
+        /// revealed secret.
+        pub(external) fn seed(&self) -> Hash {
+            let seed = *self.seed;
+            env.println(&format!("Lottery::seed = {:?}", seed));
+            seed
+        }
+
+        /// Returns the winner once the reveal phase has ended, unless nobody
+        /// revealed their secret.
+        pub(external) fn winner(&self) -> Option<AccountId> {
+            let winner = self.winner_or_none();
+            env.println(&format!("Lottery::winner = {:?}", winner));
+            winner
+        }
+
+        /// Buys a ticket with the native balance sent along with the call.
+        ///
+        /// The `commitment` is the `commitment_of` the caller and a secret
+        /// only they know, computed off-chain. They reveal the secret later.
+        pub(external) fn buy_ticket(&mut self, commitment: Hash) -> Result<(), LotteryError> {
+            if self.phase_at(env.block_number()) != Phase::Commit {
+                return Err(LotteryError::WrongPhase)
+            }
+            if value_transferred() != *self.ticket_price {
+                return Err(LotteryError::WrongPrice)
+            }
+            let player = env.caller();
+            // ACTION: Return `Err(LotteryError::AlreadyCommitted)` if the `player` has a commitment
+            // ACTION: Store the `commitment` of the `player`, and add the ticket price to the `pot`
+            deposit_event(Event::TicketBought {
+                player: player
+            });
+            Ok(())
+        }
+
+        /// Reveals the `secret` behind the commitment of the caller, and mixes
+        /// it into the seed.
+        pub(external) fn reveal(&mut self, secret: Hash) -> Result<(), LotteryError> {
+            if self.phase_at(env.block_number()) != Phase::Reveal {
+                return Err(LotteryError::WrongPhase)
+            }
+            let player = env.caller();
+            // ACTION: Get the `commitment` of the `player`, or return `Err(LotteryError::NoCommitment)`
+            // ACTION: Return `Err(LotteryError::WrongSecret)` unless it is the `commitment_of` the
+            //         `player` and the `secret`
+            // ACTION: Remove the `commitment`, and `push` the `player` to the `revealed` players
+            // ACTION: Set the `seed` to the `hash_of` the old `seed` and the `secret`
+            //   HINT: Hash the SCALE encoded tuple `(seed, secret)`
+            deposit_event(Event::TicketRevealed {
+                player: player
+            });
+            Ok(())
+        }
+
+        /// Pays the pot to the winner.
+        ///
+        /// If nobody revealed their secret, there is no winner, and the owner
+        /// can claim the pot instead.
+        pub(external) fn claim(&mut self) -> Result<Balance, LotteryError> {
+            if self.phase_at(env.block_number()) != Phase::Finished {
+                return Err(LotteryError::WrongPhase)
+            }
+            if *self.claimed {
+                return Err(LotteryError::AlreadyClaimed)
+            }
+            let winner = self.winner_or_none().unwrap_or(*self.owner);
+            if env.caller() != winner {
+                return Err(LotteryError::NotWinner)
+            }
+            let value = *self.pot;
+            self.claimed.set(true);
+            assert!(transfer_native(winner, value), "the payout failed");
+            deposit_event(Event::PotClaimed {
+                winner: winner,
+                value: value
+            });
+            Ok(value)
+        }
+    }
+
+    impl Lottery {
+        /// Returns the phase of the lottery in block `now`.
+        fn phase_at(&self, now: BlockNumber) -> Phase {
+            // ACTION: Return the `Phase` the lottery is in at block `now`
+            //   HINT: The lottery is in the `Commit` phase up to `commit_end`, and in the `Reveal`
+            //         phase up to `reveal_end`
+            Phase::Commit
+        }
+
+        /// Draws the winner from the seed once the reveal phase has ended.
+        ///
+        /// The first 8 bytes of the seed pick one of the players who revealed
+        /// their secret.
+        fn winner_or_none(&self) -> Option<AccountId> {
+            if self.phase_at(env::block_number()) != Phase::Finished || self.revealed.len() == 0 {
+                return None
+            }
+            let random = u64::decode(&mut &(*self.seed).encode()[..]).expect("a Hash is longer than a u64");
+            let index = (random % u64::from(self.revealed.len())) as u32;
+            self.revealed.get(index).cloned()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Returns a secret made of the byte `byte`.
+    fn secret(byte: u8) -> Hash {
+        Hash::decode(&mut &[byte; 32][..]).unwrap()
+    }
+
+    /// Buys a ticket for `player`, paying `value`, with a commitment to `secret`.
+    fn buy(lottery: &mut Lottery, player: AccountId, value: Balance, secret: Hash) -> Result<(), LotteryError> {
+        env::test::set_caller(player);
+        set_value_transferred(value);
+        let result = lottery.buy_ticket(commitment_of(player, secret));
+        set_value_transferred(0);
+        result
+    }
+
+    /// Reveals `secret` for `player`.
+    fn reveal(lottery: &mut Lottery, player: AccountId, secret: Hash) -> Result<(), LotteryError> {
+        env::test::set_caller(player);
+        lottery.reveal(secret)
+    }
+
+    /// Deploys a lottery at block 0 with tickets for 10, which sells tickets
+    /// until block 100 and accepts secrets until block 150.
+    fn deploy_mock() -> Lottery {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        Lottery::deploy_mock(10, 100, 50)
+    }
+
+    #[test]
+    fn phases_follow_the_block_number() {
+        let lottery = deploy_mock();
+        assert_eq!(lottery.phase(), Phase::Commit);
+        env::test::set_block_number(100);
+        assert_eq!(lottery.phase(), Phase::Commit);
+        env::test::set_block_number(101);
+        assert_eq!(lottery.phase(), Phase::Reveal);
+        env::test::set_block_number(150);
+        assert_eq!(lottery.phase(), Phase::Reveal);
+        env::test::set_block_number(151);
+        assert_eq!(lottery.phase(), Phase::Finished);
+    }
+
+    #[test]
+    fn tickets_cost_the_ticket_price() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 9, secret(1)), Err(LotteryError::WrongPrice));
+        assert_eq!(buy(&mut lottery, bob, 11, secret(1)), Err(LotteryError::WrongPrice));
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        assert_eq!(buy(&mut lottery, bob, 10, secret(2)), Err(LotteryError::AlreadyCommitted));
+        assert_eq!(buy(&mut lottery, charlie, 10, secret(2)), Ok(()));
+        assert_eq!(lottery.pot(), 20);
+    }
+
+    #[test]
+    fn calls_are_limited_to_their_phase() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Err(LotteryError::WrongPhase));
+        assert_eq!(lottery.claim(), Err(LotteryError::WrongPhase));
+        env::test::set_block_number(101);
+        assert_eq!(buy(&mut lottery, charlie, 10, secret(2)), Err(LotteryError::WrongPhase));
+        assert_eq!(lottery.winner(), None);
+        assert_eq!(lottery.claim(), Err(LotteryError::WrongPhase));
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Ok(()));
+        env::test::set_block_number(151);
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Err(LotteryError::WrongPhase));
+        assert_eq!(lottery.winner(), Some(bob));
+    }
+
+    #[test]
+    fn reveal_checks_the_commitment() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        env::test::set_block_number(101);
+        assert_eq!(reveal(&mut lottery, bob, secret(2)), Err(LotteryError::WrongSecret));
+        // Charlie saw Bob's secret, but cannot reveal it without a ticket
+        assert_eq!(reveal(&mut lottery, charlie, secret(1)), Err(LotteryError::NoCommitment));
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Ok(()));
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Err(LotteryError::NoCommitment));
+        assert_eq!(lottery.revealed_count(), 1);
+    }
+
+    #[test]
+    fn every_secret_changes_the_seed() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        assert_eq!(buy(&mut lottery, charlie, 10, secret(2)), Ok(()));
+        env::test::set_block_number(101);
+        let initial = lottery.seed();
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Ok(()));
+        let after_bob = lottery.seed();
+        assert_eq!(after_bob, hash_of(&(initial, secret(1)).encode()));
+        assert_eq!(reveal(&mut lottery, charlie, secret(2)), Ok(()));
+        assert_eq!(lottery.seed(), hash_of(&(after_bob, secret(2)).encode()));
+        assert_ne!(lottery.seed(), after_bob);
+    }
+
+    #[test]
+    fn non_revealers_forfeit_their_ticket() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        assert_eq!(buy(&mut lottery, charlie, 10, secret(2)), Ok(()));
+        assert_eq!(buy(&mut lottery, dave, 10, secret(3)), Ok(()));
+        env::test::set_block_number(101);
+        assert_eq!(reveal(&mut lottery, bob, secret(1)), Ok(()));
+        env::test::set_block_number(151);
+        // Charlie and Dave did not reveal, so Bob wins the whole pot
+        assert_eq!(lottery.winner(), Some(bob));
+        env::test::set_caller(charlie);
+        assert_eq!(lottery.claim(), Err(LotteryError::NotWinner));
+        assert_eq!(reveal(&mut lottery, dave, secret(3)), Err(LotteryError::WrongPhase));
+        env::test::set_caller(bob);
+        assert_eq!(lottery.claim(), Ok(30));
+        assert_eq!(lottery.claim(), Err(LotteryError::AlreadyClaimed));
+        assert_eq!(payouts(), vec![(bob, 30)]);
+    }
+
+    #[test]
+    fn winner_is_one_of_the_revealers() {
+        let players: Vec<AccountId> = (1..5).map(|byte| AccountId::try_from([byte; 32]).unwrap()).collect();
+        let mut lottery = deploy_mock();
+        for (index, player) in players.iter().enumerate() {
+            assert_eq!(buy(&mut lottery, *player, 10, secret(index as u8)), Ok(()));
+        }
+        env::test::set_block_number(101);
+        // The last player does not reveal
+        for (index, player) in players.iter().enumerate().take(3) {
+            assert_eq!(reveal(&mut lottery, *player, secret(index as u8)), Ok(()));
+        }
+        env::test::set_block_number(151);
+        let winner = lottery.winner().unwrap();
+        assert!(players[..3].contains(&winner));
+        env::test::set_caller(winner);
+        assert_eq!(lottery.claim(), Ok(40));
+        assert_eq!(payouts(), vec![(winner, 40)]);
+    }
+
+    #[test]
+    fn owner_claims_the_pot_without_revealers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let mut lottery = deploy_mock();
+        assert_eq!(buy(&mut lottery, bob, 10, secret(1)), Ok(()));
+        env::test::set_block_number(151);
+        assert_eq!(lottery.winner(), None);
+        env::test::set_caller(bob);
+        assert_eq!(lottery.claim(), Err(LotteryError::NotWinner));
+        env::test::set_caller(alice);
+        assert_eq!(lottery.claim(), Ok(10));
+        assert_eq!(payouts(), vec![(alice, 10)]);
+    }
+}
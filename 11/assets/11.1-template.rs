@@ -0,0 +1,541 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of the ERC20 token.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The gas limit of cross-contract calls made by the payment splitter.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the payment splitter contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    PaymentReceived {
+        from: AccountId,
+        value: Balance,
+    },
+    PaymentReleased {
+        payee: AccountId,
+        value: Balance,
+    },
+    TokenPaymentReleased {
+        token: AccountId,
+        payee: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the payment splitter contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum SplitterError {
+    /// The caller has no shares in the payments.
+    NotPayee,
+    /// Everything the caller is due has already been released.
+    NothingDue,
+}
+
+/// Deposits a payment splitter event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+contract! {
+    /// Splits the payments it receives between a fixed group of payees.
+    ///
+    /// Every payee owns a number of shares, and is due the same part of
+    /// every payment. Payments are not paid out as they arrive. Instead,
+    /// every payee releases what they are due whenever they like, both in
+    /// native balance and in any ERC20 token sent to the splitter.
+    struct PaymentSplitter {
+        /// The accounts the payments are split between.
+        payees: storage::Value<Vec<AccountId>>,
+        /// The shares of each payee.
+        shares: storage::HashMap<AccountId, Balance>,
+        /// The sum of the shares of all payees.
+        total_shares: storage::Value<Balance>,
+        /// The native balance received so far.
+        total_received: storage::Value<Balance>,
+        /// The native balance released so far.
+        total_released: storage::Value<Balance>,
+        /// The native balance released to each payee so far.
+        released: storage::HashMap<AccountId, Balance>,
+        /// The tokens released so far: token -> released
+        token_total_released: storage::HashMap<AccountId, Balance>,
+        /// The tokens released to each payee so far: (token, payee) -> released
+        token_released: storage::HashMap<(AccountId, AccountId), Balance>,
+    }
+
+    impl Deploy for PaymentSplitter {
+        fn deploy(&mut self, payees: Vec<AccountId>, shares: Vec<Balance>) {
+            assert_eq!(payees.len(), shares.len(), "every payee needs shares");
+            assert!(!payees.is_empty(), "there must be at least one payee");
+            let mut total_shares = 0;
+            for (index, (payee, payee_shares)) in payees.iter().zip(shares.iter()).enumerate() {
+                assert!(!payees[..index].contains(payee), "every payee must be unique");
+                assert!(*payee_shares > 0, "every payee needs shares");
+                self.shares.insert(*payee, *payee_shares);
+                total_shares += payee_shares;
+            }
+            self.payees.set(payees);
+            self.total_shares.set(total_shares);
+            self.total_received.set(0);
+            self.total_released.set(0);
+        }
+    }
+
+    impl PaymentSplitter {
+        /// Returns the accounts the payments are split between.
+        pub(external) fn payees(&self) -> Vec<AccountId> {
+            let payees = (*self.payees).clone();
+            env.println(&format!("PaymentSplitter::payees = {:?}", payees));
+            payees
+        }
+
+        /// Returns the shares of `payee`.
+        pub(external) fn shares_of(&self, payee: AccountId) -> Balance {
+            let shares = self.shares_of_or_zero(&payee);
+            env.println(&format!("PaymentSplitter::shares_of(payee = {:?}) = {:?}", payee, shares));
+            shares
+        }
+
+        /// Returns the sum of the shares of all payees.
+        pub(external) fn total_shares(&self) -> Balance {
+            let total_shares = *self.total_shares;
+            env.println(&format!("PaymentSplitter::total_shares = {:?}", total_shares));
+            total_shares
+        }
+
+        /// Returns the native balance received so far.
+        pub(external) fn total_received(&self) -> Balance {
+            let total_received = *self.total_received;
+            env.println(&format!("PaymentSplitter::total_received = {:?}", total_received));
+            total_received
+        }
+
+        /// Returns the native balance released so far.
+        pub(external) fn total_released(&self) -> Balance {
+            let total_released = *self.total_released;
+            env.println(&format!("PaymentSplitter::total_released = {:?}", total_released));
+            total_released
+        }
+
+        /// Returns the native balance released to `payee` so far.
+        pub(external) fn released(&self, payee: AccountId) -> Balance {
+            let released = self.released_or_zero(&payee);
+            env.println(&format!("PaymentSplitter::released(payee = {:?}) = {:?}", payee, released));
+            released
+        }
+
+        /// Returns the tokens of `token` released to `payee` so far.
+        pub(external) fn token_released(&self, token: AccountId, payee: AccountId) -> Balance {
+            let released = self.token_released_or_zero(&token, &payee);
+            env.println(&format!(
+                "PaymentSplitter::token_released(token = {:?}, payee = {:?}) = {:?}",
+                token, payee, released
+            ));
+            released
+        }
+
+        /// Returns the native balance `payee` can release right now.
+        pub(external) fn releasable(&self, payee: AccountId) -> Balance {
+            let releasable = self.pending_payment(&payee, *self.total_received, self.released_or_zero(&payee));
+            env.println(&format!("PaymentSplitter::releasable(payee = {:?}) = {:?}", payee, releasable));
+            releasable
+        }
+
+        /// Receives the native balance sent along with the call as a payment.
+        ///
+        /// Native balance sent to the splitter in any other way is not split.
+        pub(external) fn receive(&mut self) {
+            let value = value_transferred();
+            self.total_received += value;
+            deposit_event(Event::PaymentReceived {
+                from: env.caller(),
+                value: value
+            });
+        }
+
+        /// Pays out the native balance the caller is due.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn release(&mut self) -> Result<Balance, SplitterError> {
+            let payee = env.caller();
+            if self.shares_of_or_zero(&payee) == 0 {
+                return Err(SplitterError::NotPayee)
+            }
+            // ACTION: Get the `payment` the `payee` is due with `pending_payment`
+            // ACTION: `if` the `payment` is 0, exit early and return `Err(SplitterError::NothingDue)`
+            // ACTION: Add the `payment` to what has been `released` to the `payee`, and to `total_released`
+            // ACTION: Pay out the `payment` with `transfer_native`, and `assert!` that it worked
+            deposit_event(Event::PaymentReleased {
+                payee: payee,
+                value: payment
+            });
+            Ok(payment)
+        }
+
+        /// Pays out the tokens of the ERC20 `token` the caller is due.
+        ///
+        /// Tokens are split as soon as they are transferred to the splitter.
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn release_token(&mut self, token: AccountId) -> Result<Balance, SplitterError> {
+            let payee = env.caller();
+            if self.shares_of_or_zero(&payee) == 0 {
+                return Err(SplitterError::NotPayee)
+            }
+            // ACTION: Ask the `token` for the `balance` of the splitter with `call_contract`
+            //   HINT: Use `BALANCE_OF_SELECTOR` with the encoded `env.address()`
+            // ACTION: Get the `payment` the `payee` is due with `pending_payment`
+            //   HINT: The splitter has received its `balance` plus the tokens released so far
+            if payment == 0 {
+                return Err(SplitterError::NothingDue)
+            }
+            self.token_released.insert((token, payee), released + payment);
+            self.token_total_released.insert(token, total_released + payment);
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> = call_contract(token, TRANSFER_SELECTOR, &(payee, payment).encode());
+            assert_eq!(result, Some(Ok(())), "the token payout failed");
+            deposit_event(Event::TokenPaymentReleased {
+                token: token,
+                payee: payee,
+                value: payment
+            });
+            Ok(payment)
+        }
+    }
+
+    impl PaymentSplitter {
+        /// Returns the shares of `payee`, or 0 if they are not a payee.
+        fn shares_of_or_zero(&self, payee: &AccountId) -> Balance {
+            *self.shares.get(payee).unwrap_or(&0)
+        }
+
+        /// Returns the native balance released to `payee`, or 0 if none was.
+        fn released_or_zero(&self, payee: &AccountId) -> Balance {
+            *self.released.get(payee).unwrap_or(&0)
+        }
+
+        /// Returns the tokens of `token` released, or 0 if none were.
+        fn token_total_released_or_zero(&self, token: &AccountId) -> Balance {
+            *self.token_total_released.get(token).unwrap_or(&0)
+        }
+
+        /// Returns the tokens of `token` released to `payee`, or 0 if none were.
+        fn token_released_or_zero(&self, token: &AccountId, payee: &AccountId) -> Balance {
+            *self.token_released.get(&(*token, *payee)).unwrap_or(&0)
+        }
+
+        /// Returns what `payee` is due out of `total_received`, minus what
+        /// has already been `released` to them.
+        ///
+        /// The part of a payee is rounded down, so a few units of every
+        /// payment can be left over until later payments make up the
+        /// difference.
+        fn pending_payment(&self, payee: &AccountId, total_received: Balance, released: Balance) -> Balance {
+            // ACTION: Return the part of `total_received` the shares of the `payee` are worth,
+            //         minus what has already been `released` to them
+            //   HINT: Multiply before you divide by `total_shares`, to round only once
+            0
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the splitter through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..]).unwrap();
+                    Some(balances.get(&owner).cloned().unwrap_or(0).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    let balance = balances.get(&env::address()).cloned().unwrap_or(0);
+                    if balance < value {
+                        return Some(Err::<(), u8>(0).encode())
+                    }
+                    balances.insert(env::address(), balance - value);
+                    *balances.entry(to).or_insert(0) += value;
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Returns Alice, Bob and Charlie, the payees of most tests.
+    fn payees() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a splitter which gives Alice 1 share, Bob 2 and Charlie 3.
+    fn deploy_mock() -> PaymentSplitter {
+        let (alice, bob, charlie) = payees();
+        env::test::set_caller(alice);
+        PaymentSplitter::deploy_mock(vec![alice, bob, charlie], vec![1, 2, 3])
+    }
+
+    /// Pays `value` of native balance into the splitter.
+    fn pay(splitter: &mut PaymentSplitter, value: Balance) {
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        env::test::set_caller(dave);
+        set_value_transferred(value);
+        splitter.receive();
+        set_value_transferred(0);
+    }
+
+    /// Releases the native balance `payee` is due.
+    fn release(splitter: &mut PaymentSplitter, payee: AccountId) -> Result<Balance, SplitterError> {
+        env::test::set_caller(payee);
+        splitter.release()
+    }
+
+    #[test]
+    #[should_panic(expected = "every payee needs shares")]
+    fn deploy_fails_on_missing_shares() {
+        let (alice, bob, _) = payees();
+        PaymentSplitter::deploy_mock(vec![alice, bob], vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "every payee needs shares")]
+    fn deploy_fails_on_zero_shares() {
+        let (alice, bob, _) = payees();
+        PaymentSplitter::deploy_mock(vec![alice, bob], vec![1, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "every payee must be unique")]
+    fn deploy_fails_on_duplicate_payees() {
+        let (alice, bob, _) = payees();
+        PaymentSplitter::deploy_mock(vec![alice, bob, alice], vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn payments_are_split_by_shares() {
+        let (alice, bob, charlie) = payees();
+        let mut splitter = deploy_mock();
+        assert_eq!(splitter.total_shares(), 6);
+        pay(&mut splitter, 600);
+        assert_eq!(splitter.total_received(), 600);
+        assert_eq!(splitter.releasable(alice), 100);
+        assert_eq!(splitter.releasable(bob), 200);
+        assert_eq!(splitter.releasable(charlie), 300);
+        assert_eq!(release(&mut splitter, bob), Ok(200));
+        assert_eq!(release(&mut splitter, charlie), Ok(300));
+        assert_eq!(release(&mut splitter, alice), Ok(100));
+        assert_eq!(splitter.total_released(), 600);
+        assert_eq!(payouts(), vec![(bob, 200), (charlie, 300), (alice, 100)]);
+    }
+
+    #[test]
+    fn release_is_idempotent() {
+        let (_, bob, _) = payees();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut splitter = deploy_mock();
+        assert_eq!(release(&mut splitter, bob), Err(SplitterError::NothingDue));
+        pay(&mut splitter, 600);
+        assert_eq!(release(&mut splitter, bob), Ok(200));
+        assert_eq!(release(&mut splitter, bob), Err(SplitterError::NothingDue));
+        assert_eq!(splitter.released(bob), 200);
+        // Only new payments can be released again
+        pay(&mut splitter, 300);
+        assert_eq!(release(&mut splitter, bob), Ok(100));
+        assert_eq!(release(&mut splitter, bob), Err(SplitterError::NothingDue));
+        assert_eq!(splitter.released(bob), 300);
+        assert_eq!(release(&mut splitter, dave), Err(SplitterError::NotPayee));
+        assert_eq!(payouts(), vec![(bob, 200), (bob, 100)]);
+    }
+
+    #[test]
+    fn rounding_never_pays_out_more_than_received() {
+        let (alice, bob, charlie) = payees();
+        let mut splitter = deploy_mock();
+        // 10 / 6 is not a whole number, so every part is rounded down
+        pay(&mut splitter, 10);
+        assert_eq!(release(&mut splitter, alice), Ok(1));
+        assert_eq!(release(&mut splitter, bob), Ok(3));
+        assert_eq!(release(&mut splitter, charlie), Ok(5));
+        // The unit left over is paid out once later payments make up the rest
+        pay(&mut splitter, 2);
+        assert_eq!(release(&mut splitter, alice), Ok(1));
+        assert_eq!(release(&mut splitter, bob), Ok(1));
+        assert_eq!(release(&mut splitter, charlie), Ok(1));
+        let paid_out: Balance = payouts().iter().map(|(_, value)| value).sum();
+        assert_eq!(paid_out, 12);
+    }
+
+    #[test]
+    fn late_releases_get_the_same_amount() {
+        let (alice, bob, _) = payees();
+        let mut splitter = deploy_mock();
+        for _ in 0..3 {
+            pay(&mut splitter, 60);
+            assert_eq!(release(&mut splitter, alice), Ok(10));
+        }
+        // Bob releases all three payments at once
+        assert_eq!(release(&mut splitter, bob), Ok(60));
+        assert_eq!(splitter.released(alice), 30);
+        assert_eq!(splitter.releasable(alice), 0);
+    }
+
+    #[test]
+    fn tokens_are_split_by_shares() {
+        let (alice, bob, charlie) = payees();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        let mut splitter = deploy_mock();
+        balances.borrow_mut().insert(env::address(), 60);
+        env::test::set_caller(bob);
+        assert_eq!(splitter.release_token(token), Ok(20));
+        assert_eq!(splitter.release_token(token), Err(SplitterError::NothingDue));
+        // Tokens sent after Bob's release are split as well
+        balances.borrow_mut().insert(env::address(), 40 + 6);
+        assert_eq!(splitter.release_token(token), Ok(2));
+        env::test::set_caller(charlie);
+        assert_eq!(splitter.release_token(token), Ok(33));
+        env::test::set_caller(alice);
+        assert_eq!(splitter.release_token(token), Ok(11));
+        assert_eq!(splitter.token_released(token, bob), 22);
+        assert_eq!(balances.borrow()[&bob], 22);
+        assert_eq!(balances.borrow()[&charlie], 33);
+        assert_eq!(balances.borrow()[&alice], 11);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+        // Native payments are tracked separately
+        assert_eq!(splitter.releasable(alice), 0);
+        assert_eq!(payouts(), vec![]);
+    }
+}
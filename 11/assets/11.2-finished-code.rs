@@ -0,0 +1,540 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The identifier of a stream.
+type StreamId = u32;
+
+/// Tokens paid from a sender to a recipient bit by bit, over a range of blocks.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Stream {
+    /// The account which funded the stream.
+    sender: AccountId,
+    /// The account the tokens are streamed to.
+    recipient: AccountId,
+    /// The tokens streamed in total.
+    deposit: Balance,
+    /// The block after which the tokens start streaming.
+    start: BlockNumber,
+    /// The block by which all tokens have been streamed.
+    stop: BlockNumber,
+    /// The tokens the recipient has withdrawn so far.
+    withdrawn: Balance,
+}
+
+/// Events deposited by the streaming contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    StreamCreated {
+        id: StreamId,
+        sender: AccountId,
+        recipient: AccountId,
+        deposit: Balance,
+    },
+    Withdrawn {
+        id: StreamId,
+        amount: Balance,
+    },
+    StreamCanceled {
+        id: StreamId,
+        sender_amount: Balance,
+        recipient_amount: Balance,
+    },
+}
+
+/// Errors which can occur when calling the streaming contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum StreamError {
+    /// No open stream has the given id.
+    UnknownStream,
+    /// A stream cannot start in the past, and must stop after it starts.
+    InvalidSchedule,
+    /// The recipient cannot be the sender or the contract itself.
+    InvalidRecipient,
+    /// A stream must stream some tokens.
+    ZeroDeposit,
+    /// Only the recipient can withdraw from a stream.
+    NotRecipient,
+    /// Only the sender or the recipient can cancel a stream.
+    NotParty,
+    /// The recipient has not been streamed that many tokens yet.
+    InsufficientBalance,
+    /// The token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits a streaming event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the tokens of `stream` which have been streamed by block `now`.
+///
+/// Nothing is streamed up to the `start` block, and everything by the `stop`
+/// block. In between, the tokens are streamed at the same rate every block,
+/// rounded down.
+fn streamed_amount(stream: &Stream, now: BlockNumber) -> Balance {
+    if now <= stream.start {
+        return 0
+    }
+    if now >= stream.stop {
+        return stream.deposit
+    }
+    let duration = Balance::from(stream.stop - stream.start);
+    let elapsed = Balance::from(now - stream.start);
+    // Split `deposit` to avoid overflowing on large streams.
+    stream.deposit / duration * elapsed + stream.deposit % duration * elapsed / duration
+}
+
+contract! {
+    /// Streams ERC20 tokens from senders to recipients, block by block.
+    ///
+    /// A sender deposits all tokens of a stream up front. From its start to
+    /// its stop, the tokens belong to the recipient bit by bit, and the
+    /// recipient can withdraw whatever has been streamed so far at any time.
+    /// Either of them can cancel a stream, which pays each their part.
+    struct Streaming {
+        /// The ERC20 token which is streamed.
+        token: storage::Value<AccountId>,
+        /// The number of streams created, which is also the next id.
+        stream_count: storage::Value<StreamId>,
+        /// The open streams by id.
+        streams: storage::HashMap<StreamId, Stream>,
+    }
+
+    impl Deploy for Streaming {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.stream_count.set(0);
+        }
+    }
+
+    impl Streaming {
+        /// Returns the open stream with the given id.
+        pub(external) fn stream(&self, id: StreamId) -> Option<Stream> {
+            let stream = self.streams.get(&id).cloned();
+            env.println(&format!("Streaming::stream(id = {:?}) = {:?}", id, stream));
+            stream
+        }
+
+        /// Returns the tokens of the stream `id` which belong to `account`
+        /// right now, and have not been withdrawn.
+        ///
+        /// The recipient owns what has been streamed, and the sender the rest.
+        pub(external) fn balance_of(&self, id: StreamId, account: AccountId) -> Balance {
+            let balance = match self.streams.get(&id) {
+                Some(stream) => {
+                    let streamed = streamed_amount(stream, env.block_number());
+                    if account == stream.recipient {
+                        streamed - stream.withdrawn
+                    } else if account == stream.sender {
+                        stream.deposit - streamed
+                    } else {
+                        0
+                    }
+                }
+                None => 0,
+            };
+            env.println(&format!("Streaming::balance_of(id = {:?}, account = {:?}) = {:?}", id, account, balance));
+            balance
+        }
+
+        /// Opens a stream of `deposit` tokens from the caller to `recipient`,
+        /// from block `start` to block `stop`.
+        ///
+        /// The caller must have approved the contract to transfer the deposit.
+        pub(external) fn create_stream(
+            &mut self,
+            recipient: AccountId,
+            deposit: Balance,
+            start: BlockNumber,
+            stop: BlockNumber,
+        ) -> Result<StreamId, StreamError> {
+            let sender = env.caller();
+            if recipient == sender || recipient == env.address() {
+                return Err(StreamError::InvalidRecipient)
+            }
+            if deposit == 0 {
+                return Err(StreamError::ZeroDeposit)
+            }
+            if start < env.block_number() || stop <= start {
+                return Err(StreamError::InvalidSchedule)
+            }
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(sender, env.address(), deposit).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(StreamError::TransferFailed),
+            }
+            let id = *self.stream_count;
+            self.stream_count += 1;
+            self.streams.insert(id, Stream {
+                sender: sender,
+                recipient: recipient,
+                deposit: deposit,
+                start: start,
+                stop: stop,
+                withdrawn: 0
+            });
+            deposit_event(Event::StreamCreated {
+                id: id,
+                sender: sender,
+                recipient: recipient,
+                deposit: deposit
+            });
+            Ok(id)
+        }
+
+        /// Withdraws `amount` of the streamed tokens to the recipient.
+        ///
+        /// The stream is closed once everything has been withdrawn. If the
+        /// token refuses the transfer, the whole call is reverted.
+        pub(external) fn withdraw(&mut self, id: StreamId, amount: Balance) -> Result<(), StreamError> {
+            let mut stream = self.streams.get(&id).cloned().ok_or(StreamError::UnknownStream)?;
+            if env.caller() != stream.recipient {
+                return Err(StreamError::NotRecipient)
+            }
+            if amount > streamed_amount(&stream, env.block_number()) - stream.withdrawn {
+                return Err(StreamError::InsufficientBalance)
+            }
+            stream.withdrawn += amount;
+            if stream.withdrawn == stream.deposit {
+                self.streams.remove(&id);
+            } else {
+                self.streams.insert(id, stream);
+            }
+            self.transfer_tokens(stream.recipient, amount);
+            deposit_event(Event::Withdrawn {
+                id: id,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Closes the stream, paying the recipient what has been streamed and
+        /// not withdrawn, and the sender the rest.
+        ///
+        /// If the token refuses a transfer, the whole call is reverted.
+        pub(external) fn cancel(&mut self, id: StreamId) -> Result<(), StreamError> {
+            let stream = self.streams.get(&id).cloned().ok_or(StreamError::UnknownStream)?;
+            let caller = env.caller();
+            if caller != stream.sender && caller != stream.recipient {
+                return Err(StreamError::NotParty)
+            }
+            let streamed = streamed_amount(&stream, env.block_number());
+            let recipient_amount = streamed - stream.withdrawn;
+            let sender_amount = stream.deposit - streamed;
+            self.streams.remove(&id);
+            if recipient_amount > 0 {
+                self.transfer_tokens(stream.recipient, recipient_amount);
+            }
+            if sender_amount > 0 {
+                self.transfer_tokens(stream.sender, sender_amount);
+            }
+            deposit_event(Event::StreamCanceled {
+                id: id,
+                sender_amount: sender_amount,
+                recipient_amount: recipient_amount
+            });
+            Ok(())
+        }
+    }
+
+    impl Streaming {
+        /// Transfers `amount` tokens held by the contract to `to`.
+        ///
+        /// Panics if the token refuses the transfer.
+        fn transfer_tokens(&self, to: AccountId, amount: Balance) {
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> = call_contract(*self.token, TRANSFER_SELECTOR, &(to, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be transferred");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the streaming contract through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Returns a stream of `deposit` tokens from Alice to Bob.
+    fn stream_of(deposit: Balance, start: BlockNumber, stop: BlockNumber) -> Stream {
+        Stream {
+            sender: AccountId::try_from([0x0; 32]).unwrap(),
+            recipient: AccountId::try_from([0x1; 32]).unwrap(),
+            deposit: deposit,
+            start: start,
+            stop: stop,
+            withdrawn: 0,
+        }
+    }
+
+    /// Deploys a streaming contract at block 0, gives Alice 10000 tokens, and
+    /// has her open a stream of 1000 tokens to Bob from block 100 to 200.
+    fn deploy_mock() -> (Streaming, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 10000);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut streaming = Streaming::deploy_mock(token);
+        assert_eq!(streaming.create_stream(bob, 1000, 100, 200), Ok(0));
+        (streaming, balances)
+    }
+
+    #[test]
+    fn streamed_amount_grows_every_block() {
+        let stream = stream_of(1000, 100, 200);
+        assert_eq!(streamed_amount(&stream, 0), 0);
+        assert_eq!(streamed_amount(&stream, 100), 0);
+        assert_eq!(streamed_amount(&stream, 101), 10);
+        assert_eq!(streamed_amount(&stream, 125), 250);
+        assert_eq!(streamed_amount(&stream, 150), 500);
+        assert_eq!(streamed_amount(&stream, 199), 990);
+        assert_eq!(streamed_amount(&stream, 200), 1000);
+        assert_eq!(streamed_amount(&stream, 1000), 1000);
+    }
+
+    #[test]
+    fn streamed_amount_rounds_down() {
+        // 100 tokens over 3 blocks do not divide evenly
+        let stream = stream_of(100, 0, 3);
+        assert_eq!(streamed_amount(&stream, 1), 33);
+        assert_eq!(streamed_amount(&stream, 2), 66);
+        assert_eq!(streamed_amount(&stream, 3), 100);
+    }
+
+    #[test]
+    fn streamed_amount_does_not_overflow() {
+        let stream = stream_of(Balance::max_value(), 0, 2);
+        assert_eq!(streamed_amount(&stream, 1), Balance::max_value() / 2);
+    }
+
+    #[test]
+    fn create_stream_pulls_the_deposit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (streaming, balances) = deploy_mock();
+        assert_eq!(balances.borrow()[&alice], 9000);
+        assert_eq!(balances.borrow()[&env::address()], 1000);
+        assert_eq!(streaming.stream(0), Some(stream_of(1000, 100, 200)));
+        assert_eq!(streaming.balance_of(0, alice), 1000);
+        assert_eq!(streaming.balance_of(0, bob), 0);
+    }
+
+    #[test]
+    fn create_stream_fails_for_invalid_streams() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut streaming, _) = deploy_mock();
+        env::test::set_block_number(50);
+        assert_eq!(streaming.create_stream(alice, 1000, 100, 200), Err(StreamError::InvalidRecipient));
+        assert_eq!(streaming.create_stream(env::address(), 1000, 100, 200), Err(StreamError::InvalidRecipient));
+        assert_eq!(streaming.create_stream(bob, 0, 100, 200), Err(StreamError::ZeroDeposit));
+        assert_eq!(streaming.create_stream(bob, 1000, 49, 200), Err(StreamError::InvalidSchedule));
+        assert_eq!(streaming.create_stream(bob, 1000, 100, 100), Err(StreamError::InvalidSchedule));
+        assert_eq!(streaming.create_stream(bob, 10000, 100, 200), Err(StreamError::TransferFailed));
+        assert_eq!(streaming.create_stream(bob, 1000, 50, 51), Ok(1));
+    }
+
+    #[test]
+    fn recipient_withdraws_what_has_been_streamed() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut streaming, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(streaming.withdraw(0, 1), Err(StreamError::InsufficientBalance));
+        env::test::set_block_number(130);
+        assert_eq!(streaming.balance_of(0, bob), 300);
+        assert_eq!(streaming.balance_of(0, alice), 700);
+        assert_eq!(streaming.withdraw(0, 301), Err(StreamError::InsufficientBalance));
+        assert_eq!(streaming.withdraw(0, 200), Ok(()));
+        assert_eq!(streaming.balance_of(0, bob), 100);
+        env::test::set_block_number(175);
+        assert_eq!(streaming.balance_of(0, bob), 550);
+        assert_eq!(streaming.withdraw(0, 550), Ok(()));
+        assert_eq!(streaming.withdraw(0, 1), Err(StreamError::InsufficientBalance));
+        assert_eq!(balances.borrow()[&bob], 750);
+        // Only the recipient can withdraw
+        env::test::set_caller(alice);
+        assert_eq!(streaming.withdraw(0, 0), Err(StreamError::NotRecipient));
+    }
+
+    #[test]
+    fn stream_closes_once_fully_withdrawn() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut streaming, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        env::test::set_block_number(150);
+        assert_eq!(streaming.withdraw(0, 500), Ok(()));
+        env::test::set_block_number(250);
+        assert_eq!(streaming.balance_of(0, bob), 500);
+        assert_eq!(streaming.withdraw(0, 500), Ok(()));
+        assert_eq!(streaming.stream(0), None);
+        assert_eq!(streaming.withdraw(0, 0), Err(StreamError::UnknownStream));
+        assert_eq!(balances.borrow()[&bob], 1000);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+
+    #[test]
+    fn cancel_splits_the_deposit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut streaming, balances) = deploy_mock();
+        env::test::set_block_number(140);
+        env::test::set_caller(bob);
+        assert_eq!(streaming.withdraw(0, 100), Ok(()));
+        env::test::set_caller(charlie);
+        assert_eq!(streaming.cancel(0), Err(StreamError::NotParty));
+        env::test::set_caller(alice);
+        assert_eq!(streaming.cancel(0), Ok(()));
+        // Bob gets the 300 streamed but not withdrawn, and Alice the other 600
+        assert_eq!(balances.borrow()[&bob], 400);
+        assert_eq!(balances.borrow()[&alice], 9600);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+        assert_eq!(streaming.stream(0), None);
+        assert_eq!(streaming.cancel(0), Err(StreamError::UnknownStream));
+    }
+
+    #[test]
+    fn cancel_works_for_either_party_at_any_time() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut streaming, balances) = deploy_mock();
+        assert_eq!(streaming.create_stream(bob, 1000, 100, 200), Ok(1));
+        assert_eq!(streaming.create_stream(bob, 1000, 100, 200), Ok(2));
+        // Before the start, everything goes back to the sender
+        env::test::set_block_number(100);
+        env::test::set_caller(bob);
+        assert_eq!(streaming.cancel(0), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 8000);
+        assert_eq!(balances.borrow().get(&bob), None);
+        // After the stop, everything goes to the recipient
+        env::test::set_block_number(200);
+        assert_eq!(streaming.cancel(1), Ok(()));
+        assert_eq!(balances.borrow()[&bob], 1000);
+        env::test::set_block_number(199);
+        env::test::set_caller(alice);
+        assert_eq!(streaming.cancel(2), Ok(()));
+        assert_eq!(balances.borrow()[&bob], 1990);
+        assert_eq!(balances.borrow()[&alice], 8010);
+    }
+}
@@ -0,0 +1,442 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The identifier of a plan.
+type PlanId = u32;
+
+/// A subscription offered by a merchant.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Plan {
+    /// The account which is paid for the subscription.
+    merchant: AccountId,
+    /// The tokens charged every period.
+    amount: Balance,
+    /// The blocks between two charges.
+    period: BlockNumber,
+}
+
+/// Events deposited by the subscription contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    PlanCreated {
+        plan: PlanId,
+        merchant: AccountId,
+        amount: Balance,
+        period: BlockNumber,
+    },
+    Subscribed {
+        plan: PlanId,
+        subscriber: AccountId,
+    },
+    Charged {
+        plan: PlanId,
+        subscriber: AccountId,
+        amount: Balance,
+    },
+    Canceled {
+        plan: PlanId,
+        subscriber: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the subscription contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum SubscriptionError {
+    /// No plan with the given id has been created.
+    UnknownPlan,
+    /// A plan must have a period of at least one block.
+    ZeroPeriod,
+    /// The caller is already subscribed to the plan.
+    AlreadySubscribed,
+    /// The account is not subscribed to the plan.
+    NotSubscribed,
+    /// Only the merchant of a plan can charge its subscribers.
+    NotMerchant,
+    /// The period since the last charge has not passed yet.
+    TooEarly,
+    /// The token refused to move the tokens from the subscriber.
+    PaymentFailed,
+}
+
+/// Deposits a subscription event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Lets merchants charge their subscribers in ERC20 tokens, once per period.
+    ///
+    /// Subscribers approve the contract to transfer their tokens, and
+    /// merchants pull the payments from them. The contract makes sure a
+    /// merchant can only charge the amount of the plan, and only once every
+    /// period, for as long as the subscriber has not canceled.
+    struct Subscriptions {
+        /// The ERC20 token subscriptions are paid in.
+        token: storage::Value<AccountId>,
+        /// The number of plans created, which is also the next id.
+        plan_count: storage::Value<PlanId>,
+        /// The plans by id.
+        plans: storage::HashMap<PlanId, Plan>,
+        /// The block of the last charge of every subscription: (plan, subscriber) -> block
+        last_charged: storage::HashMap<(PlanId, AccountId), BlockNumber>,
+    }
+
+    impl Deploy for Subscriptions {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.plan_count.set(0);
+        }
+    }
+
+    impl Subscriptions {
+        /// Returns the plan with the given id, if it has been created.
+        pub(external) fn plan(&self, plan: PlanId) -> Option<Plan> {
+            let result = self.plans.get(&plan).cloned();
+            env.println(&format!("Subscriptions::plan(plan = {:?}) = {:?}", plan, result));
+            result
+        }
+
+        /// Returns the first block in which `subscriber` can be charged for
+        /// `plan` again, if they are subscribed.
+        pub(external) fn next_charge_at(&self, plan: PlanId, subscriber: AccountId) -> Option<BlockNumber> {
+            let next_charge_at = match (self.plans.get(&plan), self.last_charged.get(&(plan, subscriber))) {
+                (Some(details), Some(last_charged)) => Some(last_charged + details.period),
+                _ => None,
+            };
+            env.println(&format!(
+                "Subscriptions::next_charge_at(plan = {:?}, subscriber = {:?}) = {:?}",
+                plan, subscriber, next_charge_at
+            ));
+            next_charge_at
+        }
+
+        /// Creates a plan which pays the caller `amount` tokens every `period` blocks.
+        pub(external) fn create_plan(
+            &mut self,
+            amount: Balance,
+            period: BlockNumber,
+        ) -> Result<PlanId, SubscriptionError> {
+            if period == 0 {
+                return Err(SubscriptionError::ZeroPeriod)
+            }
+            let merchant = env.caller();
+            let plan = *self.plan_count;
+            self.plan_count += 1;
+            self.plans.insert(plan, Plan {
+                merchant: merchant,
+                amount: amount,
+                period: period
+            });
+            deposit_event(Event::PlanCreated {
+                plan: plan,
+                merchant: merchant,
+                amount: amount,
+                period: period
+            });
+            Ok(plan)
+        }
+
+        /// Subscribes the caller to `plan`, and pays for the first period.
+        ///
+        /// The caller must have approved the contract to transfer the tokens
+        /// of the first and every later period.
+        pub(external) fn subscribe(&mut self, plan: PlanId) -> Result<(), SubscriptionError> {
+            let details = self.plans.get(&plan).cloned().ok_or(SubscriptionError::UnknownPlan)?;
+            let subscriber = env.caller();
+            if self.last_charged.get(&(plan, subscriber)).is_some() {
+                return Err(SubscriptionError::AlreadySubscribed)
+            }
+            self.pay(&details, subscriber)?;
+            self.last_charged.insert((plan, subscriber), env.block_number());
+            deposit_event(Event::Subscribed {
+                plan: plan,
+                subscriber: subscriber
+            });
+            deposit_event(Event::Charged {
+                plan: plan,
+                subscriber: subscriber,
+                amount: details.amount
+            });
+            Ok(())
+        }
+
+        /// Charges `subscriber` for the next period of `plan`.
+        ///
+        /// Only the merchant of the plan can charge, and only once a full
+        /// period has passed since the last charge.
+        pub(external) fn charge(&mut self, plan: PlanId, subscriber: AccountId) -> Result<(), SubscriptionError> {
+            let details = self.plans.get(&plan).cloned().ok_or(SubscriptionError::UnknownPlan)?;
+            if env.caller() != details.merchant {
+                return Err(SubscriptionError::NotMerchant)
+            }
+            // ACTION: Get the block the `subscriber` was `last_charged` in, or return
+            //         `Err(SubscriptionError::NotSubscribed)`
+            // ACTION: Return `Err(SubscriptionError::TooEarly)` if a full `period` has not passed
+            //         since then
+            // ACTION: `pay` for the next period, and return early if it failed
+            // ACTION: Store the current block as the block the `subscriber` was `last_charged` in
+            deposit_event(Event::Charged {
+                plan: plan,
+                subscriber: subscriber,
+                amount: details.amount
+            });
+            Ok(())
+        }
+
+        /// Cancels the subscription of the caller to `plan`.
+        ///
+        /// The period which has already been paid for is not refunded.
+        pub(external) fn cancel(&mut self, plan: PlanId) -> Result<(), SubscriptionError> {
+            let subscriber = env.caller();
+            // ACTION: `remove` the subscription, or return `Err(SubscriptionError::NotSubscribed)`
+            //         if there was none
+            deposit_event(Event::Canceled {
+                plan: plan,
+                subscriber: subscriber
+            });
+            Ok(())
+        }
+    }
+
+    impl Subscriptions {
+        /// Moves the amount of the plan `details` from `subscriber` to the merchant.
+        fn pay(&self, details: &Plan, subscriber: AccountId) -> Result<(), SubscriptionError> {
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> = call_contract(
+                *self.token,
+                TRANSFER_FROM_SELECTOR,
+                &(subscriber, details.merchant, details.amount).encode(),
+            );
+            // ACTION: Return `Ok(())` if the token returned `Some(Ok(_))`, and
+            //         `Err(SubscriptionError::PaymentFailed)` otherwise
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the subscription contract through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    let balance_from = balances.get(&from).cloned().unwrap_or(0);
+                    if balance_from < value {
+                        return Some(Err::<Balance, u8>(0).encode())
+                    }
+                    balances.insert(from, balance_from - value);
+                    *balances.entry(to).or_insert(0) += value;
+                    Some(Ok::<Balance, u8>(value).encode())
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Deploys a subscription contract at block 0, has Alice create a plan of
+    /// 10 tokens every 100 blocks, and gives Bob 35 tokens.
+    fn deploy_mock() -> (Subscriptions, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(bob, 35);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut subscriptions = Subscriptions::deploy_mock(token);
+        assert_eq!(subscriptions.create_plan(10, 100), Ok(0));
+        (subscriptions, balances)
+    }
+
+    /// Charges `subscriber` for `plan` as Alice, the merchant.
+    fn charge(subscriptions: &mut Subscriptions, plan: PlanId, subscriber: AccountId) -> Result<(), SubscriptionError> {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        subscriptions.charge(plan, subscriber)
+    }
+
+    #[test]
+    fn create_plan_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let (mut subscriptions, _) = deploy_mock();
+        assert_eq!(subscriptions.plan(0), Some(Plan { merchant: alice, amount: 10, period: 100 }));
+        assert_eq!(subscriptions.create_plan(10, 0), Err(SubscriptionError::ZeroPeriod));
+        assert_eq!(subscriptions.create_plan(50, 1000), Ok(1));
+        assert_eq!(subscriptions.plan(2), None);
+    }
+
+    #[test]
+    fn subscribe_pays_the_first_period() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut subscriptions, balances) = deploy_mock();
+        env::test::set_block_number(5);
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.subscribe(1), Err(SubscriptionError::UnknownPlan));
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        assert_eq!(subscriptions.subscribe(0), Err(SubscriptionError::AlreadySubscribed));
+        assert_eq!(balances.borrow()[&alice], 10);
+        assert_eq!(balances.borrow()[&bob], 25);
+        assert_eq!(subscriptions.next_charge_at(0, bob), Some(105));
+    }
+
+    #[test]
+    fn early_charges_are_rejected() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut subscriptions, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        env::test::set_block_number(99);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Err(SubscriptionError::TooEarly));
+        env::test::set_block_number(100);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Ok(()));
+        // Charging twice in the same period fails
+        assert_eq!(charge(&mut subscriptions, 0, bob), Err(SubscriptionError::TooEarly));
+        // A late charge starts the next period from the block it was made in
+        env::test::set_block_number(250);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Ok(()));
+        assert_eq!(subscriptions.next_charge_at(0, bob), Some(350));
+        env::test::set_block_number(349);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Err(SubscriptionError::TooEarly));
+        assert_eq!(balances.borrow()[&alice], 30);
+        assert_eq!(balances.borrow()[&bob], 5);
+    }
+
+    #[test]
+    fn only_the_merchant_can_charge_subscribers() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut subscriptions, _) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        env::test::set_block_number(100);
+        env::test::set_caller(charlie);
+        assert_eq!(subscriptions.charge(0, bob), Err(SubscriptionError::NotMerchant));
+        assert_eq!(charge(&mut subscriptions, 0, charlie), Err(SubscriptionError::NotSubscribed));
+        assert_eq!(charge(&mut subscriptions, 1, bob), Err(SubscriptionError::UnknownPlan));
+    }
+
+    #[test]
+    fn failed_payments_can_be_retried() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut subscriptions, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        balances.borrow_mut().insert(bob, 5);
+        env::test::set_block_number(100);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Err(SubscriptionError::PaymentFailed));
+        assert_eq!(subscriptions.next_charge_at(0, bob), Some(100));
+        balances.borrow_mut().insert(bob, 10);
+        env::test::set_block_number(120);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Ok(()));
+        assert_eq!(subscriptions.next_charge_at(0, bob), Some(220));
+    }
+
+    #[test]
+    fn cancel_stops_the_charges() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut subscriptions, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.cancel(0), Err(SubscriptionError::NotSubscribed));
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        env::test::set_block_number(50);
+        assert_eq!(subscriptions.cancel(0), Ok(()));
+        assert_eq!(subscriptions.next_charge_at(0, bob), None);
+        env::test::set_block_number(100);
+        assert_eq!(charge(&mut subscriptions, 0, bob), Err(SubscriptionError::NotSubscribed));
+        // The merchant cannot cancel on behalf of the subscriber
+        env::test::set_caller(bob);
+        assert_eq!(subscriptions.subscribe(0), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(subscriptions.cancel(0), Err(SubscriptionError::NotSubscribed));
+        assert_eq!(balances.borrow()[&alice], 20);
+        // Subscribing again starts a new period
+        assert_eq!(subscriptions.next_charge_at(0, bob), Some(200));
+    }
+}
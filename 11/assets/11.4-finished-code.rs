@@ -0,0 +1,388 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Events deposited by the crowdfunding campaign.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Pledged {
+        contributor: AccountId,
+        value: Balance,
+    },
+    Withdrawn {
+        creator: AccountId,
+        value: Balance,
+    },
+    Refunded {
+        contributor: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the crowdfunding campaign.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum CrowdfundingError {
+    /// Nothing was pledged.
+    ZeroAmount,
+    /// The deadline has passed, so no more pledges are accepted.
+    CampaignEnded,
+    /// The deadline has not passed yet.
+    CampaignNotEnded,
+    /// Only the creator can withdraw the pledges.
+    NotCreator,
+    /// The pledges fell short of the goal, so they can only be refunded.
+    GoalNotReached,
+    /// The pledges reached the goal, so they can only be withdrawn.
+    GoalReached,
+    /// The creator has already withdrawn the pledges.
+    AlreadyWithdrawn,
+    /// The caller has nothing to refund.
+    NoPledge,
+}
+
+/// Deposits a crowdfunding event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Raises native balance for its creator, all or nothing.
+    ///
+    /// Contributors pledge until the `deadline`. If the pledges reach the
+    /// `goal` by then, the creator withdraws all of them. Otherwise, every
+    /// contributor takes their pledge back.
+    struct Crowdfunding {
+        /// The account which started the campaign and receives the pledges.
+        creator: storage::Value<AccountId>,
+        /// The native balance the campaign needs to succeed.
+        goal: storage::Value<Balance>,
+        /// The last block in which pledges are accepted.
+        deadline: storage::Value<BlockNumber>,
+        /// The total of all pledges, including refunded ones.
+        raised: storage::Value<Balance>,
+        /// Whether the creator has withdrawn the pledges.
+        withdrawn: storage::Value<bool>,
+        /// The pledges of each contributor which have not been refunded.
+        pledges: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for Crowdfunding {
+        fn deploy(&mut self, goal: Balance, deadline: BlockNumber) {
+            assert!(goal > 0, "the goal must not be zero");
+            assert!(deadline >= env::block_number(), "the deadline must not have passed");
+            self.creator.set(env.caller());
+            self.goal.set(goal);
+            self.deadline.set(deadline);
+            self.raised.set(0);
+            self.withdrawn.set(false);
+        }
+    }
+
+    impl Crowdfunding {
+        /// Returns the native balance the campaign needs to succeed.
+        pub(external) fn goal(&self) -> Balance {
+            let goal = *self.goal;
+            env.println(&format!("Crowdfunding::goal = {:?}", goal));
+            goal
+        }
+
+        /// Returns the last block in which pledges are accepted.
+        pub(external) fn deadline(&self) -> BlockNumber {
+            let deadline = *self.deadline;
+            env.println(&format!("Crowdfunding::deadline = {:?}", deadline));
+            deadline
+        }
+
+        /// Returns the total of all pledges.
+        pub(external) fn raised(&self) -> Balance {
+            let raised = *self.raised;
+            env.println(&format!("Crowdfunding::raised = {:?}", raised));
+            raised
+        }
+
+        /// Returns the pledge of `contributor` which has not been refunded.
+        pub(external) fn pledge_of(&self, contributor: AccountId) -> Balance {
+            let pledge = self.pledge_of_or_zero(&contributor);
+            env.println(&format!("Crowdfunding::pledge_of(contributor = {:?}) = {:?}", contributor, pledge));
+            pledge
+        }
+
+        /// Pledges the native balance sent along with the call.
+        ///
+        /// Pledges are accepted up to and including the deadline, even once
+        /// the goal has been reached.
+        pub(external) fn pledge(&mut self) -> Result<(), CrowdfundingError> {
+            if env.block_number() > *self.deadline {
+                return Err(CrowdfundingError::CampaignEnded)
+            }
+            let value = value_transferred();
+            if value == 0 {
+                return Err(CrowdfundingError::ZeroAmount)
+            }
+            let contributor = env.caller();
+            let pledge = self.pledge_of_or_zero(&contributor);
+            self.pledges.insert(contributor, pledge + value);
+            self.raised += value;
+            deposit_event(Event::Pledged {
+                contributor: contributor,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Pays all pledges out to the creator, once the campaign has ended
+        /// and reached its goal.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn withdraw(&mut self) -> Result<Balance, CrowdfundingError> {
+            let creator = env.caller();
+            if creator != *self.creator {
+                return Err(CrowdfundingError::NotCreator)
+            }
+            self.ensure_ended()?;
+            let raised = *self.raised;
+            if raised < *self.goal {
+                return Err(CrowdfundingError::GoalNotReached)
+            }
+            if *self.withdrawn {
+                return Err(CrowdfundingError::AlreadyWithdrawn)
+            }
+            self.withdrawn.set(true);
+            assert!(transfer_native(creator, raised), "the payout failed");
+            deposit_event(Event::Withdrawn {
+                creator: creator,
+                value: raised
+            });
+            Ok(raised)
+        }
+
+        /// Pays the pledge of the caller back to them, once the campaign has
+        /// ended without reaching its goal.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn refund(&mut self) -> Result<Balance, CrowdfundingError> {
+            self.ensure_ended()?;
+            if *self.raised >= *self.goal {
+                return Err(CrowdfundingError::GoalReached)
+            }
+            let contributor = env.caller();
+            let pledge = self.pledge_of_or_zero(&contributor);
+            if pledge == 0 {
+                return Err(CrowdfundingError::NoPledge)
+            }
+            self.pledges.remove(&contributor);
+            assert!(transfer_native(contributor, pledge), "the payout failed");
+            deposit_event(Event::Refunded {
+                contributor: contributor,
+                value: pledge
+            });
+            Ok(pledge)
+        }
+    }
+
+    impl Crowdfunding {
+        /// Returns the pledge of the AccountId or 0 if there is none.
+        fn pledge_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.pledges.get(of).unwrap_or(&0)
+        }
+
+        /// Returns an error unless the deadline has passed.
+        fn ensure_ended(&self) -> Result<(), CrowdfundingError> {
+            if env::block_number() <= *self.deadline {
+                return Err(CrowdfundingError::CampaignNotEnded)
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Returns Alice, the creator, and Bob and Charlie, the contributors of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a campaign by Alice at block 0, which raises 100 until block 50.
+    fn deploy_mock() -> Crowdfunding {
+        let (alice, _, _) = accounts();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        Crowdfunding::deploy_mock(100, 50)
+    }
+
+    /// Pledges `value` for `contributor`.
+    fn pledge(campaign: &mut Crowdfunding, contributor: AccountId, value: Balance) -> Result<(), CrowdfundingError> {
+        env::test::set_caller(contributor);
+        set_value_transferred(value);
+        let result = campaign.pledge();
+        set_value_transferred(0);
+        result
+    }
+
+    #[test]
+    #[should_panic(expected = "the deadline must not have passed")]
+    fn deploy_fails_on_passed_deadline() {
+        env::test::set_block_number(10);
+        Crowdfunding::deploy_mock(100, 9);
+    }
+
+    #[test]
+    fn pledges_are_accepted_until_the_deadline() {
+        let (_, bob, charlie) = accounts();
+        let mut campaign = deploy_mock();
+        assert_eq!(pledge(&mut campaign, bob, 0), Err(CrowdfundingError::ZeroAmount));
+        assert_eq!(pledge(&mut campaign, bob, 30), Ok(()));
+        env::test::set_block_number(50);
+        assert_eq!(pledge(&mut campaign, bob, 20), Ok(()));
+        assert_eq!(pledge(&mut campaign, charlie, 10), Ok(()));
+        env::test::set_block_number(51);
+        assert_eq!(pledge(&mut campaign, charlie, 10), Err(CrowdfundingError::CampaignEnded));
+        assert_eq!(campaign.pledge_of(bob), 50);
+        assert_eq!(campaign.pledge_of(charlie), 10);
+        assert_eq!(campaign.raised(), 60);
+    }
+
+    #[test]
+    fn nothing_is_paid_before_the_deadline() {
+        let (alice, bob, _) = accounts();
+        let mut campaign = deploy_mock();
+        assert_eq!(pledge(&mut campaign, bob, 100), Ok(()));
+        env::test::set_block_number(50);
+        // Reaching the goal early does not end the campaign
+        env::test::set_caller(alice);
+        assert_eq!(campaign.withdraw(), Err(CrowdfundingError::CampaignNotEnded));
+        env::test::set_caller(bob);
+        assert_eq!(campaign.refund(), Err(CrowdfundingError::CampaignNotEnded));
+        assert_eq!(payouts(), vec![]);
+    }
+
+    #[test]
+    fn exact_goal_can_be_withdrawn() {
+        let (alice, bob, charlie) = accounts();
+        let mut campaign = deploy_mock();
+        assert_eq!(pledge(&mut campaign, bob, 60), Ok(()));
+        assert_eq!(pledge(&mut campaign, charlie, 40), Ok(()));
+        env::test::set_block_number(51);
+        // Contributors cannot back out of a successful campaign
+        assert_eq!(campaign.refund(), Err(CrowdfundingError::GoalReached));
+        assert_eq!(campaign.withdraw(), Err(CrowdfundingError::NotCreator));
+        env::test::set_caller(alice);
+        assert_eq!(campaign.withdraw(), Ok(100));
+        assert_eq!(campaign.withdraw(), Err(CrowdfundingError::AlreadyWithdrawn));
+        assert_eq!(payouts(), vec![(alice, 100)]);
+    }
+
+    #[test]
+    fn over_goal_is_withdrawn_in_full() {
+        let (alice, bob, charlie) = accounts();
+        let mut campaign = deploy_mock();
+        assert_eq!(pledge(&mut campaign, bob, 80), Ok(()));
+        assert_eq!(pledge(&mut campaign, charlie, 70), Ok(()));
+        assert_eq!(campaign.raised(), 150);
+        env::test::set_block_number(60);
+        env::test::set_caller(bob);
+        assert_eq!(campaign.refund(), Err(CrowdfundingError::GoalReached));
+        env::test::set_caller(alice);
+        assert_eq!(campaign.withdraw(), Ok(150));
+        assert_eq!(payouts(), vec![(alice, 150)]);
+    }
+
+    #[test]
+    fn missed_goal_is_refunded() {
+        let (alice, bob, charlie) = accounts();
+        let mut campaign = deploy_mock();
+        assert_eq!(pledge(&mut campaign, bob, 60), Ok(()));
+        assert_eq!(pledge(&mut campaign, charlie, 39), Ok(()));
+        env::test::set_block_number(51);
+        env::test::set_caller(alice);
+        assert_eq!(campaign.withdraw(), Err(CrowdfundingError::GoalNotReached));
+        assert_eq!(campaign.refund(), Err(CrowdfundingError::NoPledge));
+        env::test::set_caller(bob);
+        assert_eq!(campaign.refund(), Ok(60));
+        assert_eq!(campaign.refund(), Err(CrowdfundingError::NoPledge));
+        env::test::set_caller(charlie);
+        assert_eq!(campaign.refund(), Ok(39));
+        assert_eq!(payouts(), vec![(bob, 60), (charlie, 39)]);
+        assert_eq!(campaign.pledge_of(bob), 0);
+    }
+}
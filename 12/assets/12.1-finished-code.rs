@@ -0,0 +1,948 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The selector of the `price` message of the price source.
+const PRICE_SELECTOR: [u8; 4] = [0xD4, 0xBD, 0x7B, 0xC1];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The precision of prices, rates and the borrow index, which are
+/// fixed-point numbers with 18 decimals.
+const PRECISION: Balance = 1_000_000_000_000_000_000;
+
+/// The extra collateral in percent a liquidator receives for repaying a debt.
+const LIQUIDATION_BONUS: u32 = 10;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// The debt of a borrower at the time it last changed.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Loan {
+    /// The debt, including the interest up to the last change.
+    principal: Balance,
+    /// The borrow index at the last change.
+    index: Balance,
+}
+
+/// Events deposited by the lending pool.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Deposited {
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    },
+    Withdrawn {
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    },
+    CollateralDeposited {
+        account: AccountId,
+        value: Balance,
+    },
+    CollateralWithdrawn {
+        account: AccountId,
+        value: Balance,
+    },
+    Borrowed {
+        account: AccountId,
+        amount: Balance,
+    },
+    Repaid {
+        account: AccountId,
+        amount: Balance,
+    },
+    Liquidated {
+        borrower: AccountId,
+        liquidator: AccountId,
+        debt: Balance,
+        collateral: Balance,
+    },
+}
+
+/// Errors which can occur when calling the lending pool.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum LendingError {
+    /// Nothing was deposited, withdrawn, borrowed or repaid.
+    ZeroAmount,
+    /// The account does not hold enough shares.
+    InsufficientShares,
+    /// The pool does not hold enough tokens which have not been borrowed.
+    InsufficientLiquidity,
+    /// The collateral of the account would not cover its debt.
+    InsufficientCollateral,
+    /// The account has no debt.
+    NoDebt,
+    /// The collateral of the borrower still covers its debt.
+    NotLiquidatable,
+    /// The price source did not return a usable price.
+    PriceUnavailable,
+    /// The token refused to move the tokens into the pool.
+    TransferFailed,
+}
+
+/// Deposits a lending pool event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns `value * numerator / denominator`, rounded down.
+///
+/// `value` is split to avoid overflowing on large amounts.
+fn mul_div(value: Balance, numerator: Balance, denominator: Balance) -> Balance {
+    value / denominator * numerator + value % denominator * numerator / denominator
+}
+
+/// Returns the part of the pool's tokens which has been borrowed, with
+/// `PRECISION`.
+fn utilization(cash: Balance, total_borrows: Balance) -> Balance {
+    if total_borrows == 0 {
+        return 0
+    }
+    mul_div(total_borrows, PRECISION, cash + total_borrows)
+}
+
+/// Returns the interest charged per block at `utilization`, with `PRECISION`.
+///
+/// The rate starts at `base_rate` and grows by `rate_slope` for every 100%
+/// of utilization, so borrowing gets more expensive the less is left.
+fn borrow_rate(utilization: Balance, base_rate: Balance, rate_slope: Balance) -> Balance {
+    base_rate + mul_div(utilization, rate_slope, PRECISION)
+}
+
+contract! {
+    /// Lends ERC20 tokens deposited by lenders to borrowers who lock native
+    /// balance as collateral.
+    ///
+    /// Lenders receive shares of the pool, which grow in value as borrowers
+    /// pay interest. The interest rate depends on how much of the pool is
+    /// borrowed. Borrowers can borrow up to `collateral_factor` percent of
+    /// the value of their collateral. Once their debt grows beyond
+    /// `liquidation_threshold` percent of it, anyone can repay the debt and
+    /// take the collateral, with a bonus.
+    struct LendingPool {
+        /// The ERC20 token which is lent.
+        token: storage::Value<AccountId>,
+        /// The contract which reports the price of the collateral in tokens.
+        price_source: storage::Value<AccountId>,
+        /// The percentage of the value of the collateral which can be borrowed.
+        collateral_factor: storage::Value<u32>,
+        /// The percentage of the value of the collateral above which a debt
+        /// can be liquidated.
+        liquidation_threshold: storage::Value<u32>,
+        /// The interest rate per block without any borrows.
+        base_rate: storage::Value<Balance>,
+        /// The growth of the interest rate per block from 0% to 100% utilization.
+        rate_slope: storage::Value<Balance>,
+        /// The tokens in the pool which have not been borrowed.
+        cash: storage::Value<Balance>,
+        /// The total debt of all borrowers, including interest.
+        total_borrows: storage::Value<Balance>,
+        /// How much a debt of 1 has grown since the pool was deployed, with `PRECISION`.
+        borrow_index: storage::Value<Balance>,
+        /// The block up to which interest has been accrued.
+        last_accrual: storage::Value<BlockNumber>,
+        /// The total number of shares of the lenders.
+        total_shares: storage::Value<Balance>,
+        /// The shares of each lender.
+        shares: storage::HashMap<AccountId, Balance>,
+        /// The native balance locked by each borrower.
+        collateral: storage::HashMap<AccountId, Balance>,
+        /// The loan of each borrower.
+        loans: storage::HashMap<AccountId, Loan>,
+    }
+
+    impl Deploy for LendingPool {
+        fn deploy(
+            &mut self,
+            token: AccountId,
+            price_source: AccountId,
+            collateral_factor: u32,
+            liquidation_threshold: u32,
+            base_rate: Balance,
+            rate_slope: Balance,
+        ) {
+            assert!(liquidation_threshold < 100, "debts must be liquidated before they exceed their collateral");
+            assert!(collateral_factor < liquidation_threshold, "new loans must not be liquidatable");
+            self.token.set(token);
+            self.price_source.set(price_source);
+            self.collateral_factor.set(collateral_factor);
+            self.liquidation_threshold.set(liquidation_threshold);
+            self.base_rate.set(base_rate);
+            self.rate_slope.set(rate_slope);
+            self.cash.set(0);
+            self.total_borrows.set(0);
+            self.borrow_index.set(PRECISION);
+            self.last_accrual.set(env::block_number());
+            self.total_shares.set(0);
+        }
+    }
+
+    impl LendingPool {
+        /// Returns the tokens in the pool which have not been borrowed.
+        pub(external) fn cash(&self) -> Balance {
+            let cash = *self.cash;
+            env.println(&format!("LendingPool::cash = {:?}", cash));
+            cash
+        }
+
+        /// Returns the total debt of all borrowers, including the interest
+        /// up to now.
+        pub(external) fn total_borrows(&self) -> Balance {
+            let (total_borrows, _) = self.accrued();
+            env.println(&format!("LendingPool::total_borrows = {:?}", total_borrows));
+            total_borrows
+        }
+
+        /// Returns the interest charged per block right now, with 18 decimals.
+        pub(external) fn current_rate(&self) -> Balance {
+            let (total_borrows, _) = self.accrued();
+            let rate = borrow_rate(utilization(*self.cash, total_borrows), *self.base_rate, *self.rate_slope);
+            env.println(&format!("LendingPool::current_rate = {:?}", rate));
+            rate
+        }
+
+        /// Returns the shares of `account`.
+        pub(external) fn shares_of(&self, account: AccountId) -> Balance {
+            let shares = self.shares_of_or_zero(&account);
+            env.println(&format!("LendingPool::shares_of(account = {:?}) = {:?}", account, shares));
+            shares
+        }
+
+        /// Returns the tokens the shares of `account` are worth right now.
+        pub(external) fn balance_of(&self, account: AccountId) -> Balance {
+            let (total_borrows, _) = self.accrued();
+            let balance = self.shares_to_tokens(self.shares_of_or_zero(&account), total_borrows);
+            env.println(&format!("LendingPool::balance_of(account = {:?}) = {:?}", account, balance));
+            balance
+        }
+
+        /// Returns the native balance locked by `account`.
+        pub(external) fn collateral_of(&self, account: AccountId) -> Balance {
+            let collateral = self.collateral_of_or_zero(&account);
+            env.println(&format!("LendingPool::collateral_of(account = {:?}) = {:?}", account, collateral));
+            collateral
+        }
+
+        /// Returns the debt of `account`, including the interest up to now.
+        pub(external) fn debt_of(&self, account: AccountId) -> Balance {
+            let (_, borrow_index) = self.accrued();
+            let debt = self.debt_at(&account, borrow_index);
+            env.println(&format!("LendingPool::debt_of(account = {:?}) = {:?}", account, debt));
+            debt
+        }
+
+        /// Deposits `amount` tokens of the caller, and returns the shares
+        /// minted for them.
+        ///
+        /// The caller must have approved the pool to transfer the tokens.
+        pub(external) fn deposit(&mut self, amount: Balance) -> Result<Balance, LendingError> {
+            self.accrue_interest();
+            let shares = match *self.total_shares {
+                0 => amount,
+                total_shares => mul_div(amount, total_shares, *self.cash + *self.total_borrows),
+            };
+            if shares == 0 {
+                return Err(LendingError::ZeroAmount)
+            }
+            let account = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(account, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(LendingError::TransferFailed),
+            }
+            let balance = self.shares_of_or_zero(&account);
+            self.shares.insert(account, balance + shares);
+            self.total_shares += shares;
+            self.cash += amount;
+            deposit_event(Event::Deposited {
+                account: account,
+                amount: amount,
+                shares: shares
+            });
+            Ok(shares)
+        }
+
+        /// Burns `shares` of the caller, and returns the tokens they were worth.
+        ///
+        /// Tokens which have been borrowed cannot be withdrawn until they are
+        /// repaid. If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn withdraw(&mut self, shares: Balance) -> Result<Balance, LendingError> {
+            self.accrue_interest();
+            let account = env.caller();
+            let balance = self.shares_of_or_zero(&account);
+            if balance < shares {
+                return Err(LendingError::InsufficientShares)
+            }
+            let amount = self.shares_to_tokens(shares, *self.total_borrows);
+            if amount == 0 {
+                return Err(LendingError::ZeroAmount)
+            }
+            if amount > *self.cash {
+                return Err(LendingError::InsufficientLiquidity)
+            }
+            self.shares.insert(account, balance - shares);
+            self.total_shares -= shares;
+            self.cash -= amount;
+            self.transfer_tokens(account, amount);
+            deposit_event(Event::Withdrawn {
+                account: account,
+                amount: amount,
+                shares: shares
+            });
+            Ok(amount)
+        }
+
+        /// Locks the native balance sent along with the call as collateral of
+        /// the caller.
+        pub(external) fn deposit_collateral(&mut self) {
+            let account = env.caller();
+            let value = value_transferred();
+            let collateral = self.collateral_of_or_zero(&account);
+            self.collateral.insert(account, collateral + value);
+            deposit_event(Event::CollateralDeposited {
+                account: account,
+                value: value
+            });
+        }
+
+        /// Pays `value` of the collateral of the caller back to them, as long
+        /// as the rest still covers their debt.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn withdraw_collateral(&mut self, value: Balance) -> Result<(), LendingError> {
+            self.accrue_interest();
+            let account = env.caller();
+            let collateral = self.collateral_of_or_zero(&account);
+            if collateral < value {
+                return Err(LendingError::InsufficientCollateral)
+            }
+            let debt = self.debt_at(&account, *self.borrow_index);
+            if debt > 0 && debt > self.borrow_limit(collateral - value)? {
+                return Err(LendingError::InsufficientCollateral)
+            }
+            self.collateral.insert(account, collateral - value);
+            assert!(transfer_native(account, value), "the payout failed");
+            deposit_event(Event::CollateralWithdrawn {
+                account: account,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Lends `amount` tokens to the caller, as long as their collateral
+        /// covers their debt afterwards.
+        ///
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn borrow(&mut self, amount: Balance) -> Result<(), LendingError> {
+            if amount == 0 {
+                return Err(LendingError::ZeroAmount)
+            }
+            self.accrue_interest();
+            if amount > *self.cash {
+                return Err(LendingError::InsufficientLiquidity)
+            }
+            let account = env.caller();
+            let borrow_index = *self.borrow_index;
+            let debt = self.debt_at(&account, borrow_index) + amount;
+            if debt > self.borrow_limit(self.collateral_of_or_zero(&account))? {
+                return Err(LendingError::InsufficientCollateral)
+            }
+            self.loans.insert(account, Loan {
+                principal: debt,
+                index: borrow_index
+            });
+            self.total_borrows += amount;
+            self.cash -= amount;
+            self.transfer_tokens(account, amount);
+            deposit_event(Event::Borrowed {
+                account: account,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Repays up to `amount` tokens of the debt of the caller, and
+        /// returns the tokens repaid.
+        ///
+        /// The caller must have approved the pool to transfer the tokens.
+        pub(external) fn repay(&mut self, amount: Balance) -> Result<Balance, LendingError> {
+            self.accrue_interest();
+            let account = env.caller();
+            let borrow_index = *self.borrow_index;
+            let debt = self.debt_at(&account, borrow_index);
+            if debt == 0 {
+                return Err(LendingError::NoDebt)
+            }
+            let amount = if amount > debt { debt } else { amount };
+            if amount == 0 {
+                return Err(LendingError::ZeroAmount)
+            }
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(account, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(LendingError::TransferFailed),
+            }
+            self.reduce_debt(account, debt - amount, amount);
+            deposit_event(Event::Repaid {
+                account: account,
+                amount: amount
+            });
+            Ok(amount)
+        }
+
+        /// Repays the whole debt of `borrower` with tokens of the caller, and
+        /// pays the caller its value in collateral plus `LIQUIDATION_BONUS`.
+        ///
+        /// Only possible once the debt is above `liquidation_threshold`
+        /// percent of the value of the collateral. If the collateral is worth
+        /// less than that, the caller gets all of it. The caller must have
+        /// approved the pool to transfer the tokens.
+        pub(external) fn liquidate(&mut self, borrower: AccountId) -> Result<Balance, LendingError> {
+            self.accrue_interest();
+            let debt = self.debt_at(&borrower, *self.borrow_index);
+            if debt == 0 {
+                return Err(LendingError::NoDebt)
+            }
+            let collateral = self.collateral_of_or_zero(&borrower);
+            let price = self.price()?;
+            let value = mul_div(collateral, price, PRECISION);
+            if debt <= mul_div(value, Balance::from(*self.liquidation_threshold), 100) {
+                return Err(LendingError::NotLiquidatable)
+            }
+            let liquidator = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(liquidator, env.address(), debt).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(LendingError::TransferFailed),
+            }
+            self.reduce_debt(borrower, 0, debt);
+            let bonus_value = mul_div(debt, Balance::from(100 + LIQUIDATION_BONUS), 100);
+            let seized = match mul_div(bonus_value, PRECISION, price) {
+                seized if seized < collateral => seized,
+                _ => collateral,
+            };
+            self.collateral.insert(borrower, collateral - seized);
+            assert!(transfer_native(liquidator, seized), "the payout failed");
+            deposit_event(Event::Liquidated {
+                borrower: borrower,
+                liquidator: liquidator,
+                debt: debt,
+                collateral: seized
+            });
+            Ok(seized)
+        }
+    }
+
+    impl LendingPool {
+        /// Returns the shares of the AccountId or 0 if there are none.
+        fn shares_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.shares.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the collateral of the AccountId or 0 if there is none.
+        fn collateral_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.collateral.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the debt of `account` at `borrow_index`, rounded down.
+        ///
+        /// The debt has grown by the same factor as the borrow index since
+        /// the loan last changed.
+        fn debt_at(&self, account: &AccountId, borrow_index: Balance) -> Balance {
+            match self.loans.get(account) {
+                Some(loan) => mul_div(loan.principal, borrow_index, loan.index),
+                None => 0,
+            }
+        }
+
+        /// Returns the tokens `shares` are worth with `total_borrows`, rounded down.
+        fn shares_to_tokens(&self, shares: Balance, total_borrows: Balance) -> Balance {
+            match *self.total_shares {
+                0 => 0,
+                total_shares => mul_div(shares, *self.cash + total_borrows, total_shares),
+            }
+        }
+
+        /// Returns the total borrows and the borrow index including the
+        /// interest since `last_accrual`.
+        ///
+        /// The interest rate of a block depends on the utilization at its
+        /// start. Within a single accrual, the interest is simple, but it
+        /// compounds from one accrual to the next.
+        fn accrued(&self) -> (Balance, Balance) {
+            let blocks = Balance::from(env::block_number() - *self.last_accrual);
+            let rate = borrow_rate(utilization(*self.cash, *self.total_borrows), *self.base_rate, *self.rate_slope);
+            let factor = rate * blocks;
+            let total_borrows = *self.total_borrows + mul_div(*self.total_borrows, factor, PRECISION);
+            let borrow_index = *self.borrow_index + mul_div(*self.borrow_index, factor, PRECISION);
+            (total_borrows, borrow_index)
+        }
+
+        /// Adds the interest since `last_accrual` to the total borrows and
+        /// the borrow index.
+        ///
+        /// The interest goes to the lenders, whose shares are now worth more.
+        fn accrue_interest(&mut self) {
+            let (total_borrows, borrow_index) = self.accrued();
+            self.total_borrows.set(total_borrows);
+            self.borrow_index.set(borrow_index);
+            self.last_accrual.set(env::block_number());
+        }
+
+        /// Sets the debt of `account` to `debt`, after `repaid` tokens have
+        /// come back to the pool.
+        fn reduce_debt(&mut self, account: AccountId, debt: Balance, repaid: Balance) {
+            if debt == 0 {
+                self.loans.remove(&account);
+            } else {
+                self.loans.insert(account, Loan {
+                    principal: debt,
+                    index: *self.borrow_index
+                });
+            }
+            // Debts are rounded down separately, so their sum can be a little
+            // less than `total_borrows`, but never more.
+            self.total_borrows.set(self.total_borrows.saturating_sub(repaid));
+            self.cash += repaid;
+        }
+
+        /// Returns the tokens which can be borrowed against `collateral`.
+        fn borrow_limit(&self, collateral: Balance) -> Result<Balance, LendingError> {
+            let value = mul_div(collateral, self.price()?, PRECISION);
+            Ok(mul_div(value, Balance::from(*self.collateral_factor), 100))
+        }
+
+        /// Returns the price of one unit of collateral in tokens, with `PRECISION`.
+        fn price(&self) -> Result<Balance, LendingError> {
+            match call_contract(*self.price_source, PRICE_SELECTOR, &[]) {
+                Some(price) if price > 0 => Ok(price),
+                _ => Err(LendingError::PriceUnavailable),
+            }
+        }
+
+        /// Transfers `amount` tokens held by the pool to `to`.
+        ///
+        /// Panics if the token refuses the transfer.
+        fn transfer_tokens(&self, to: AccountId, amount: Balance) {
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> = call_contract(*self.token, TRANSFER_SELECTOR, &(to, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be transferred");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the pool through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    /// The price reported by a mock price source.
+    type Price = Rc<RefCell<Balance>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the pool first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Registers a mock price source at `account` and returns its price,
+    /// which the tests can change at will.
+    fn register_price_source(account: AccountId, price: Balance) -> Price {
+        let price = Rc::new(RefCell::new(price));
+        let state = price.clone();
+        register_contract(account, move |selector, _| {
+            match selector {
+                PRICE_SELECTOR => Some(state.borrow().encode()),
+                _ => None,
+            }
+        });
+        price
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Returns Alice, Bob and Charlie, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a pool at block 0 which lends 50% of the value of the
+    /// collateral, liquidates above 75%, and charges no interest without
+    /// borrows and 1% per block at 100% utilization.
+    ///
+    /// A unit of collateral is worth 2 tokens. Alice, Bob and Charlie get
+    /// 1000 tokens each, and Alice deposits hers into the pool.
+    fn deploy_mock() -> (LendingPool, Balances, Price) {
+        let (alice, bob, charlie) = accounts();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let oracle = AccountId::try_from([0x8; 32]).unwrap();
+        let balances = register_token(token);
+        let price = register_price_source(oracle, 2 * PRECISION);
+        for account in [alice, bob, charlie].iter() {
+            balances.borrow_mut().insert(*account, 1000);
+        }
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut pool = LendingPool::deploy_mock(token, oracle, 50, 75, 0, PRECISION / 100);
+        assert_eq!(pool.deposit(1000), Ok(1000));
+        (pool, balances, price)
+    }
+
+    /// Locks `value` of native balance as collateral of `account`.
+    fn deposit_collateral(pool: &mut LendingPool, account: AccountId, value: Balance) {
+        env::test::set_caller(account);
+        set_value_transferred(value);
+        pool.deposit_collateral();
+        set_value_transferred(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "new loans must not be liquidatable")]
+    fn deploy_fails_on_collateral_factor_above_threshold() {
+        let account = AccountId::try_from([0x7; 32]).unwrap();
+        LendingPool::deploy_mock(account, account, 80, 75, 0, 0);
+    }
+
+    #[test]
+    fn mul_div_does_not_overflow() {
+        assert_eq!(mul_div(Balance::max_value(), PRECISION / 2, PRECISION), Balance::max_value() / 2);
+        assert_eq!(mul_div(10, 1, 3), 3);
+    }
+
+    #[test]
+    fn borrow_rate_grows_with_utilization() {
+        let slope = PRECISION / 100;
+        assert_eq!(utilization(1000, 0), 0);
+        assert_eq!(utilization(500, 500), PRECISION / 2);
+        assert_eq!(utilization(0, 500), PRECISION);
+        assert_eq!(borrow_rate(0, 7, slope), 7);
+        assert_eq!(borrow_rate(PRECISION / 2, 0, slope), slope / 2);
+        assert_eq!(borrow_rate(PRECISION, PRECISION / 1000, slope), PRECISION / 1000 + slope);
+    }
+
+    #[test]
+    fn deposit_and_withdraw_without_borrows() {
+        let (alice, bob, _) = accounts();
+        let (mut pool, balances, _) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(pool.deposit(500), Ok(500));
+        assert_eq!(pool.deposit(0), Err(LendingError::ZeroAmount));
+        assert_eq!(pool.deposit(501), Err(LendingError::TransferFailed));
+        assert_eq!(pool.cash(), 1500);
+        assert_eq!(pool.withdraw(501), Err(LendingError::InsufficientShares));
+        assert_eq!(pool.withdraw(200), Ok(200));
+        assert_eq!(pool.balance_of(bob), 300);
+        assert_eq!(pool.balance_of(alice), 1000);
+        assert_eq!(balances.borrow()[&bob], 700);
+    }
+
+    #[test]
+    fn borrow_is_limited_by_the_collateral_factor() {
+        let (_, bob, _) = accounts();
+        let (mut pool, balances, _) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(pool.borrow(1), Err(LendingError::InsufficientCollateral));
+        // 300 units of collateral are worth 600 tokens, so Bob can borrow 300
+        deposit_collateral(&mut pool, bob, 300);
+        assert_eq!(pool.borrow(301), Err(LendingError::InsufficientCollateral));
+        assert_eq!(pool.borrow(200), Ok(()));
+        assert_eq!(pool.borrow(101), Err(LendingError::InsufficientCollateral));
+        assert_eq!(pool.borrow(100), Ok(()));
+        assert_eq!(pool.debt_of(bob), 300);
+        assert_eq!(pool.cash(), 700);
+        assert_eq!(balances.borrow()[&bob], 1300);
+        // The collateral cannot be withdrawn while it backs the debt
+        assert_eq!(pool.withdraw_collateral(1), Err(LendingError::InsufficientCollateral));
+    }
+
+    #[test]
+    fn borrow_is_limited_by_the_cash() {
+        let (alice, bob, _) = accounts();
+        let (mut pool, _, _) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 5000);
+        assert_eq!(pool.borrow(1001), Err(LendingError::InsufficientLiquidity));
+        assert_eq!(pool.borrow(1000), Ok(()));
+        // Alice cannot withdraw tokens which have been borrowed
+        env::test::set_caller(alice);
+        assert_eq!(pool.withdraw(1), Err(LendingError::InsufficientLiquidity));
+    }
+
+    #[test]
+    fn interest_accrues_with_utilization() {
+        let (alice, bob, _) = accounts();
+        let (mut pool, _, _) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 1000);
+        assert_eq!(pool.borrow(500), Ok(()));
+        // At 50% utilization, the rate is 0.5% per block
+        assert_eq!(pool.current_rate(), PRECISION / 200);
+        env::test::set_block_number(10);
+        assert_eq!(pool.debt_of(bob), 525);
+        assert_eq!(pool.total_borrows(), 525);
+        // The interest goes to Alice, the only lender
+        assert_eq!(pool.balance_of(alice), 1025);
+    }
+
+    #[test]
+    fn interest_compounds_between_accruals() {
+        let (_, bob, charlie) = accounts();
+        let (mut pool, _, _) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 1000);
+        assert_eq!(pool.borrow(500), Ok(()));
+        env::test::set_block_number(10);
+        // Any call accrues the interest of the first 10 blocks
+        env::test::set_caller(charlie);
+        assert_eq!(pool.deposit(500), Ok(487));
+        assert_eq!(pool.total_borrows(), 525);
+        // Utilization is now 525 / 1525, so the rate went down
+        let rate = borrow_rate(utilization(1000, 525), 0, PRECISION / 100);
+        assert_eq!(pool.current_rate(), rate);
+        assert!(rate < PRECISION / 200);
+        // The next 10 blocks charge 3.44% on 525 instead of 5% on 500
+        env::test::set_block_number(20);
+        assert_eq!(pool.debt_of(bob), 543);
+    }
+
+    #[test]
+    fn repay_settles_debt_and_interest() {
+        let (alice, bob, _) = accounts();
+        let (mut pool, balances, _) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 1000);
+        assert_eq!(pool.borrow(500), Ok(()));
+        env::test::set_block_number(10);
+        assert_eq!(pool.repay(25), Ok(25));
+        assert_eq!(pool.debt_of(bob), 500);
+        // Repaying more than the debt only repays the debt
+        assert_eq!(pool.repay(1000), Ok(500));
+        assert_eq!(pool.debt_of(bob), 0);
+        assert_eq!(pool.repay(1), Err(LendingError::NoDebt));
+        assert_eq!(balances.borrow()[&bob], 975);
+        assert_eq!(pool.withdraw_collateral(1000), Ok(()));
+        assert_eq!(payouts(), vec![(bob, 1000)]);
+        // Alice withdraws the deposit with the interest
+        env::test::set_caller(alice);
+        assert_eq!(pool.withdraw(1000), Ok(1025));
+        assert_eq!(pool.cash(), 0);
+    }
+
+    #[test]
+    fn liquidation_starts_at_the_threshold() {
+        let (_, bob, charlie) = accounts();
+        let (mut pool, balances, price) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 300);
+        assert_eq!(pool.borrow(300), Ok(()));
+        env::test::set_caller(charlie);
+        assert_eq!(pool.liquidate(charlie), Err(LendingError::NoDebt));
+        assert_eq!(pool.liquidate(bob), Err(LendingError::NotLiquidatable));
+        // At a price of 1.5, 300 tokens of debt are 66.7% of 450
+        *price.borrow_mut() = PRECISION / 2 * 3;
+        assert_eq!(pool.liquidate(bob), Err(LendingError::NotLiquidatable));
+        // At 1.3, they are 76.9% of 390
+        *price.borrow_mut() = PRECISION / 10 * 13;
+        assert_eq!(pool.liquidate(bob), Ok(253));
+        // Charlie paid 300 tokens for 330 tokens worth of collateral
+        assert_eq!(balances.borrow()[&charlie], 700);
+        assert_eq!(payouts(), vec![(charlie, 253)]);
+        assert_eq!(pool.debt_of(bob), 0);
+        assert_eq!(pool.collateral_of(bob), 47);
+        assert_eq!(pool.cash(), 1000);
+    }
+
+    #[test]
+    fn interest_can_make_a_loan_liquidatable() {
+        let (_, bob, charlie) = accounts();
+        let (mut pool, _, _) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 300);
+        assert_eq!(pool.borrow(300), Ok(()));
+        // At 30% utilization, the debt grows by 0.3% per block, and reaches
+        // 75% of the 600 tokens the collateral is worth after 167 blocks
+        env::test::set_block_number(50);
+        assert_eq!(pool.debt_of(bob), 345);
+        env::test::set_block_number(167);
+        assert_eq!(pool.debt_of(bob), 450);
+        env::test::set_caller(charlie);
+        assert_eq!(pool.liquidate(bob), Err(LendingError::NotLiquidatable));
+        // One block later, 452 tokens of debt buy 497 tokens worth of collateral
+        env::test::set_block_number(168);
+        assert_eq!(pool.debt_of(bob), 452);
+        assert_eq!(pool.liquidate(bob), Ok(248));
+        assert_eq!(payouts(), vec![(charlie, 248)]);
+    }
+
+    #[test]
+    fn underwater_loans_give_all_collateral() {
+        let (_, bob, charlie) = accounts();
+        let (mut pool, _, price) = deploy_mock();
+        deposit_collateral(&mut pool, bob, 300);
+        assert_eq!(pool.borrow(300), Ok(()));
+        // The collateral is only worth 150 tokens now
+        *price.borrow_mut() = PRECISION / 2;
+        env::test::set_caller(charlie);
+        assert_eq!(pool.liquidate(bob), Ok(300));
+        assert_eq!(pool.collateral_of(bob), 0);
+    }
+}
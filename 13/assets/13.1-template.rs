@@ -0,0 +1,224 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Hash,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the DNS registry.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Register {
+        name: Hash,
+        from: AccountId,
+    },
+    SetAddress {
+        name: Hash,
+        from: AccountId,
+        old_address: Option<AccountId>,
+        new_address: AccountId,
+    },
+    Transfer {
+        name: Hash,
+        from: AccountId,
+        to: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the DNS registry.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum DnsError {
+    /// The name has already been registered.
+    NameAlreadyExists,
+    /// The name has not been registered.
+    NameNotFound,
+    /// Only the owner of the name can change it.
+    CallerIsNotOwner,
+}
+
+/// Deposits a DNS event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Maps names to the addresses they stand for.
+    ///
+    /// Names are hashes, so they have a fixed size no matter how long the
+    /// name behind them is. The first account to register a name owns it,
+    /// and is the only one who can point it to an address or hand it over.
+    struct Dns {
+        /// The address each name points to.
+        name_to_address: storage::HashMap<Hash, AccountId>,
+        /// The owner of each registered name.
+        name_to_owner: storage::HashMap<Hash, AccountId>,
+    }
+
+    impl Deploy for Dns {
+        fn deploy(&mut self) {}
+    }
+
+    impl Dns {
+        /// Returns the address `name` points to, or `None` if it has not been set.
+        pub(external) fn get_address(&self, name: Hash) -> Option<AccountId> {
+            let address = self.name_to_address.get(&name).cloned();
+            env.println(&format!("Dns::get_address(name = {:?}) = {:?}", name, address));
+            address
+        }
+
+        /// Returns the owner of `name`, or `None` if it has not been registered.
+        pub(external) fn get_owner(&self, name: Hash) -> Option<AccountId> {
+            let owner = self.name_to_owner.get(&name).cloned();
+            env.println(&format!("Dns::get_owner(name = {:?}) = {:?}", name, owner));
+            owner
+        }
+
+        /// Registers `name` with the caller as its owner.
+        ///
+        /// The name does not point to any address until its owner sets one.
+        pub(external) fn register(&mut self, name: Hash) -> Result<(), DnsError> {
+            // ACTION: Return `Err(DnsError::NameAlreadyExists)` if `name` already has an owner
+            let caller = env.caller();
+            // ACTION: `insert` the caller as the owner of `name`
+            deposit_event(Event::Register {
+                name: name,
+                from: caller
+            });
+            Ok(())
+        }
+
+        /// Points `name` to `new_address`.
+        ///
+        /// Only the owner of the name can set its address.
+        pub(external) fn set_address(&mut self, name: Hash, new_address: AccountId) -> Result<(), DnsError> {
+            let caller = env.caller();
+            // ACTION: `ensure_owner` of the name is the caller, and return early if not
+            // ACTION: `insert` the `new_address` of `name`, and keep the `old_address`
+            //   HINT: `insert` returns the value it replaced, if any
+            deposit_event(Event::SetAddress {
+                name: name,
+                from: caller,
+                old_address: old_address,
+                new_address: new_address
+            });
+            Ok(())
+        }
+
+        /// Hands `name` over to `to`.
+        ///
+        /// Only the owner of the name can transfer it. The address of the
+        /// name stays the same until the new owner changes it.
+        pub(external) fn transfer(&mut self, name: Hash, to: AccountId) -> Result<(), DnsError> {
+            let caller = env.caller();
+            // ACTION: `ensure_owner` of the name is the caller, and return early if not
+            // ACTION: `insert` `to` as the new owner of `name`
+            deposit_event(Event::Transfer {
+                name: name,
+                from: caller,
+                to: to
+            });
+            Ok(())
+        }
+    }
+
+    impl Dns {
+        /// Returns an error unless `name` is registered and owned by `caller`.
+        fn ensure_owner(&self, name: &Hash, caller: &AccountId) -> Result<(), DnsError> {
+            // ACTION: Return `Err(DnsError::NameNotFound)` if `name` has no owner,
+            //         `Err(DnsError::CallerIsNotOwner)` if its owner is not `caller`, and `Ok(())` otherwise
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns a name made of the byte `byte`.
+    fn name(byte: u8) -> Hash {
+        Hash::decode(&mut &[byte; 32][..]).unwrap()
+    }
+
+    /// Returns Alice, Bob and Charlie, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    #[test]
+    fn register_works() {
+        let (alice, bob, _) = accounts();
+        let mut dns = Dns::deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(dns.get_owner(name(1)), None);
+        assert_eq!(dns.register(name(1)), Ok(()));
+        assert_eq!(dns.get_owner(name(1)), Some(alice));
+        assert_eq!(dns.get_address(name(1)), None);
+        // Nobody can register a name twice
+        assert_eq!(dns.register(name(1)), Err(DnsError::NameAlreadyExists));
+        env::test::set_caller(bob);
+        assert_eq!(dns.register(name(1)), Err(DnsError::NameAlreadyExists));
+        assert_eq!(dns.register(name(2)), Ok(()));
+        assert_eq!(dns.get_owner(name(2)), Some(bob));
+    }
+
+    #[test]
+    fn set_address_works() {
+        let (alice, bob, charlie) = accounts();
+        let mut dns = Dns::deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(dns.set_address(name(1), bob), Err(DnsError::NameNotFound));
+        assert_eq!(dns.register(name(1)), Ok(()));
+        assert_eq!(dns.set_address(name(1), bob), Ok(()));
+        assert_eq!(dns.get_address(name(1)), Some(bob));
+        assert_eq!(dns.set_address(name(1), charlie), Ok(()));
+        assert_eq!(dns.get_address(name(1)), Some(charlie));
+    }
+
+    #[test]
+    fn set_address_fails_for_others() {
+        let (alice, bob, _) = accounts();
+        let mut dns = Dns::deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(dns.register(name(1)), Ok(()));
+        assert_eq!(dns.set_address(name(1), alice), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(dns.set_address(name(1), bob), Err(DnsError::CallerIsNotOwner));
+        assert_eq!(dns.get_address(name(1)), Some(alice));
+    }
+
+    #[test]
+    fn transfer_works() {
+        let (alice, bob, charlie) = accounts();
+        let mut dns = Dns::deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(dns.transfer(name(1), bob), Err(DnsError::NameNotFound));
+        assert_eq!(dns.register(name(1)), Ok(()));
+        assert_eq!(dns.set_address(name(1), alice), Ok(()));
+        assert_eq!(dns.transfer(name(1), bob), Ok(()));
+        assert_eq!(dns.get_owner(name(1)), Some(bob));
+        // The address stays until the new owner changes it
+        assert_eq!(dns.get_address(name(1)), Some(alice));
+        // Alice lost control over the name
+        assert_eq!(dns.set_address(name(1), charlie), Err(DnsError::CallerIsNotOwner));
+        assert_eq!(dns.transfer(name(1), charlie), Err(DnsError::CallerIsNotOwner));
+        env::test::set_caller(bob);
+        assert_eq!(dns.set_address(name(1), bob), Ok(()));
+        assert_eq!(dns.get_address(name(1)), Some(bob));
+    }
+}
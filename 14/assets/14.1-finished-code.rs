@@ -0,0 +1,381 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Hash,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the identity registry.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    AttesterAdded {
+        attester: AccountId,
+    },
+    AttesterRemoved {
+        attester: AccountId,
+    },
+    ClaimSet {
+        account: AccountId,
+        key: Hash,
+        value: Hash,
+    },
+    ClaimRemoved {
+        account: AccountId,
+        key: Hash,
+    },
+    Attested {
+        attester: AccountId,
+        subject: AccountId,
+        key: Hash,
+        value: Hash,
+    },
+    Revoked {
+        attester: AccountId,
+        subject: AccountId,
+        key: Hash,
+    },
+}
+
+/// Errors which can occur when calling the identity registry.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum IdentityError {
+    /// Only the admin can add and remove attesters.
+    NotAdmin,
+    /// Only attesters can attest claims.
+    NotAttester,
+    /// The account has made no claim under the key.
+    ClaimNotFound,
+    /// The attester has not attested the claim.
+    AttestationNotFound,
+}
+
+/// Deposits an identity event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Lets accounts make claims about their identity, and attesters
+    /// confirm them.
+    ///
+    /// A claim maps a key, like the hash of "email", to the hash of a value.
+    /// Anyone can claim anything about themselves, so a claim alone proves
+    /// nothing. Attesters chosen by the admin check claims off-chain, and
+    /// attest the ones they found to be true.
+    struct IdentityRegistry {
+        /// The account which adds and removes attesters.
+        admin: storage::Value<AccountId>,
+        /// Whether an account is an attester.
+        attesters: storage::HashMap<AccountId, bool>,
+        /// The value each account claims under each key: (account, key) -> value
+        claims: storage::HashMap<(AccountId, Hash), Hash>,
+        /// The value each attester attested for each claim: (subject, key, attester) -> value
+        attestations: storage::HashMap<(AccountId, Hash, AccountId), Hash>,
+    }
+
+    impl Deploy for IdentityRegistry {
+        fn deploy(&mut self) {
+            self.admin.set(env.caller());
+        }
+    }
+
+    impl IdentityRegistry {
+        /// Returns the account which adds and removes attesters.
+        pub(external) fn admin(&self) -> AccountId {
+            let admin = *self.admin;
+            env.println(&format!("IdentityRegistry::admin = {:?}", admin));
+            admin
+        }
+
+        /// Returns `true` if `account` is an attester.
+        pub(external) fn is_attester(&self, account: AccountId) -> bool {
+            let attester = self.is_attester_or_false(&account);
+            env.println(&format!("IdentityRegistry::is_attester(account = {:?}) = {:?}", account, attester));
+            attester
+        }
+
+        /// Returns the value `account` claims under `key`, if any.
+        pub(external) fn get_claim(&self, account: AccountId, key: Hash) -> Option<Hash> {
+            let value = self.claims.get(&(account, key)).cloned();
+            env.println(&format!(
+                "IdentityRegistry::get_claim(account = {:?}, key = {:?}) = {:?}",
+                account, key, value
+            ));
+            value
+        }
+
+        /// Returns the value `attester` attested for the claim of `subject`
+        /// under `key`, if any.
+        ///
+        /// The attestation may be about an older value of the claim.
+        pub(external) fn get_attestation(&self, subject: AccountId, key: Hash, attester: AccountId) -> Option<Hash> {
+            let value = self.attestations.get(&(subject, key, attester)).cloned();
+            env.println(&format!(
+                "IdentityRegistry::get_attestation(subject = {:?}, key = {:?}, attester = {:?}) = {:?}",
+                subject, key, attester, value
+            ));
+            value
+        }
+
+        /// Returns `true` if `attester` is an attester and attested the
+        /// current claim of `subject` under `key`.
+        pub(external) fn is_attested(&self, subject: AccountId, key: Hash, attester: AccountId) -> bool {
+            let attested = self.is_attested_impl(&subject, &key, &attester);
+            env.println(&format!(
+                "IdentityRegistry::is_attested(subject = {:?}, key = {:?}, attester = {:?}) = {:?}",
+                subject, key, attester, attested
+            ));
+            attested
+        }
+
+        /// Makes `account` an attester.
+        ///
+        /// Only the admin can add attesters.
+        pub(external) fn add_attester(&mut self, account: AccountId) -> Result<(), IdentityError> {
+            self.ensure_admin(&env.caller())?;
+            self.attesters.insert(account, true);
+            deposit_event(Event::AttesterAdded {
+                attester: account
+            });
+            Ok(())
+        }
+
+        /// Stops `account` from being an attester.
+        ///
+        /// Only the admin can remove attesters. The attestations of a removed
+        /// attester stay in storage, but no longer count.
+        pub(external) fn remove_attester(&mut self, account: AccountId) -> Result<(), IdentityError> {
+            self.ensure_admin(&env.caller())?;
+            self.attesters.remove(&account);
+            deposit_event(Event::AttesterRemoved {
+                attester: account
+            });
+            Ok(())
+        }
+
+        /// Claims `value` under `key` for the caller, replacing any earlier claim.
+        ///
+        /// Attestations of an earlier value no longer count for the new one.
+        pub(external) fn set_claim(&mut self, key: Hash, value: Hash) {
+            let account = env.caller();
+            self.claims.insert((account, key), value);
+            deposit_event(Event::ClaimSet {
+                account: account,
+                key: key,
+                value: value
+            });
+        }
+
+        /// Removes the claim of the caller under `key`.
+        pub(external) fn remove_claim(&mut self, key: Hash) -> Result<(), IdentityError> {
+            let account = env.caller();
+            if self.claims.remove(&(account, key)).is_none() {
+                return Err(IdentityError::ClaimNotFound)
+            }
+            deposit_event(Event::ClaimRemoved {
+                account: account,
+                key: key
+            });
+            Ok(())
+        }
+
+        /// Attests the current claim of `subject` under `key` as the caller.
+        ///
+        /// Only attesters can attest claims.
+        pub(external) fn attest(&mut self, subject: AccountId, key: Hash) -> Result<(), IdentityError> {
+            let attester = env.caller();
+            if !self.is_attester_or_false(&attester) {
+                return Err(IdentityError::NotAttester)
+            }
+            let value = *self.claims.get(&(subject, key)).ok_or(IdentityError::ClaimNotFound)?;
+            self.attestations.insert((subject, key, attester), value);
+            deposit_event(Event::Attested {
+                attester: attester,
+                subject: subject,
+                key: key,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Revokes the attestation of the caller for the claim of `subject`
+        /// under `key`.
+        ///
+        /// Attesters who have been removed can still revoke what they attested.
+        pub(external) fn revoke(&mut self, subject: AccountId, key: Hash) -> Result<(), IdentityError> {
+            let attester = env.caller();
+            if self.attestations.remove(&(subject, key, attester)).is_none() {
+                return Err(IdentityError::AttestationNotFound)
+            }
+            deposit_event(Event::Revoked {
+                attester: attester,
+                subject: subject,
+                key: key
+            });
+            Ok(())
+        }
+    }
+
+    impl IdentityRegistry {
+        /// Returns an error unless `caller` is the admin.
+        fn ensure_admin(&self, caller: &AccountId) -> Result<(), IdentityError> {
+            if *caller != *self.admin {
+                return Err(IdentityError::NotAdmin)
+            }
+            Ok(())
+        }
+
+        /// Returns whether the AccountId is an attester or `false` if it never was one.
+        fn is_attester_or_false(&self, account: &AccountId) -> bool {
+            *self.attesters.get(account).unwrap_or(&false)
+        }
+
+        /// Returns whether `attester` is an attester and attested the value
+        /// `subject` currently claims under `key`.
+        fn is_attested_impl(&self, subject: &AccountId, key: &Hash, attester: &AccountId) -> bool {
+            if !self.is_attester_or_false(attester) {
+                return false
+            }
+            match (self.claims.get(&(*subject, *key)), self.attestations.get(&(*subject, *key, *attester))) {
+                (Some(claimed), Some(attested)) => claimed == attested,
+                _ => false,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns a hash made of the byte `byte`, used for keys and values.
+    fn hash(byte: u8) -> Hash {
+        Hash::decode(&mut &[byte; 32][..]).unwrap()
+    }
+
+    /// Returns Alice, Bob and Charlie, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a registry with Alice as its admin and Bob as an attester.
+    /// Charlie claims the value 2 under the key 1.
+    fn deploy_mock() -> IdentityRegistry {
+        let (alice, bob, charlie) = accounts();
+        env::test::set_caller(alice);
+        let mut registry = IdentityRegistry::deploy_mock();
+        assert_eq!(registry.add_attester(bob), Ok(()));
+        env::test::set_caller(charlie);
+        registry.set_claim(hash(1), hash(2));
+        registry
+    }
+
+    #[test]
+    fn claims_work() {
+        let (alice, _, charlie) = accounts();
+        let mut registry = deploy_mock();
+        assert_eq!(registry.get_claim(charlie, hash(1)), Some(hash(2)));
+        assert_eq!(registry.get_claim(alice, hash(1)), None);
+        registry.set_claim(hash(1), hash(3));
+        assert_eq!(registry.get_claim(charlie, hash(1)), Some(hash(3)));
+        assert_eq!(registry.remove_claim(hash(1)), Ok(()));
+        assert_eq!(registry.get_claim(charlie, hash(1)), None);
+        assert_eq!(registry.remove_claim(hash(1)), Err(IdentityError::ClaimNotFound));
+    }
+
+    #[test]
+    fn only_admin_manages_attesters() {
+        let (alice, bob, charlie) = accounts();
+        let mut registry = deploy_mock();
+        assert_eq!(registry.admin(), alice);
+        assert!(registry.is_attester(bob));
+        assert!(!registry.is_attester(charlie));
+        env::test::set_caller(bob);
+        assert_eq!(registry.add_attester(charlie), Err(IdentityError::NotAdmin));
+        assert_eq!(registry.remove_attester(bob), Err(IdentityError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(registry.add_attester(charlie), Ok(()));
+        assert!(registry.is_attester(charlie));
+        assert_eq!(registry.remove_attester(charlie), Ok(()));
+        assert!(!registry.is_attester(charlie));
+    }
+
+    #[test]
+    fn only_attesters_can_attest() {
+        let (alice, bob, charlie) = accounts();
+        let mut registry = deploy_mock();
+        // Even the admin is no attester unless it adds itself
+        env::test::set_caller(alice);
+        assert_eq!(registry.attest(charlie, hash(1)), Err(IdentityError::NotAttester));
+        env::test::set_caller(charlie);
+        assert_eq!(registry.attest(charlie, hash(1)), Err(IdentityError::NotAttester));
+        env::test::set_caller(bob);
+        assert_eq!(registry.attest(charlie, hash(9)), Err(IdentityError::ClaimNotFound));
+        assert_eq!(registry.attest(charlie, hash(1)), Ok(()));
+        assert_eq!(registry.get_attestation(charlie, hash(1), bob), Some(hash(2)));
+        assert!(registry.is_attested(charlie, hash(1), bob));
+        assert!(!registry.is_attested(charlie, hash(1), alice));
+    }
+
+    #[test]
+    fn changed_claims_lose_their_attestations() {
+        let (_, bob, charlie) = accounts();
+        let mut registry = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(registry.attest(charlie, hash(1)), Ok(()));
+        env::test::set_caller(charlie);
+        registry.set_claim(hash(1), hash(3));
+        assert!(!registry.is_attested(charlie, hash(1), bob));
+        // Going back to the attested value makes the attestation count again
+        registry.set_claim(hash(1), hash(2));
+        assert!(registry.is_attested(charlie, hash(1), bob));
+        assert_eq!(registry.remove_claim(hash(1)), Ok(()));
+        assert!(!registry.is_attested(charlie, hash(1), bob));
+    }
+
+    #[test]
+    fn revoke_works() {
+        let (_, bob, charlie) = accounts();
+        let mut registry = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(registry.revoke(charlie, hash(1)), Err(IdentityError::AttestationNotFound));
+        assert_eq!(registry.attest(charlie, hash(1)), Ok(()));
+        // Nobody can revoke the attestations of somebody else
+        env::test::set_caller(charlie);
+        assert_eq!(registry.revoke(charlie, hash(1)), Err(IdentityError::AttestationNotFound));
+        env::test::set_caller(bob);
+        assert_eq!(registry.revoke(charlie, hash(1)), Ok(()));
+        assert_eq!(registry.get_attestation(charlie, hash(1), bob), None);
+        assert!(!registry.is_attested(charlie, hash(1), bob));
+    }
+
+    #[test]
+    fn removed_attesters_no_longer_count() {
+        let (alice, bob, charlie) = accounts();
+        let mut registry = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(registry.attest(charlie, hash(1)), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(registry.remove_attester(bob), Ok(()));
+        assert!(!registry.is_attested(charlie, hash(1), bob));
+        env::test::set_caller(bob);
+        assert_eq!(registry.attest(charlie, hash(1)), Err(IdentityError::NotAttester));
+        // Removed attesters can still revoke their attestations
+        assert_eq!(registry.revoke(charlie, hash(1)), Ok(()));
+    }
+}
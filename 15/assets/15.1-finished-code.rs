@@ -0,0 +1,364 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The number of reports kept for every symbol, including the latest.
+const HISTORY_LENGTH: u32 = 8;
+
+/// The ticker of an asset, padded with zeros, like `*b"DOT\0\0\0\0\0"`.
+type Symbol = [u8; 8];
+
+/// A price reported to the oracle.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Report {
+    /// The price, with as many decimals as the consumers expect.
+    price: Balance,
+    /// The block the price was observed in.
+    timestamp: BlockNumber,
+    /// The reporter which pushed the price.
+    reporter: AccountId,
+}
+
+/// Events deposited by the price oracle.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    ReporterAdded {
+        reporter: AccountId,
+    },
+    ReporterRemoved {
+        reporter: AccountId,
+    },
+    PriceReported {
+        symbol: Symbol,
+        reporter: AccountId,
+        price: Balance,
+        timestamp: BlockNumber,
+    },
+}
+
+/// Errors which can occur when calling the price oracle.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum OracleError {
+    /// Only the admin can add and remove reporters.
+    NotAdmin,
+    /// Only reporters can report prices.
+    NotReporter,
+    /// A price of 0 is never valid.
+    ZeroPrice,
+    /// The timestamp lies in the future.
+    InvalidTimestamp,
+    /// The timestamp is older than the latest report.
+    OutdatedReport,
+    /// No price has been reported for the symbol.
+    UnknownSymbol,
+    /// The latest price is older than `max_age` blocks.
+    StalePrice,
+}
+
+/// Deposits a price oracle event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Stores prices pushed by a set of reporters, for other contracts to read.
+    ///
+    /// Reporters observe prices off-chain, and report them together with
+    /// the block they observed them in. The oracle keeps the last
+    /// `HISTORY_LENGTH` reports of every symbol. `get_price` only returns
+    /// prices which are at most `max_age` blocks old, so consumers never act
+    /// on a price nobody has confirmed in a while.
+    struct PriceOracle {
+        /// The account which adds and removes reporters.
+        admin: storage::Value<AccountId>,
+        /// The maximum age in blocks of a price returned by `get_price`.
+        max_age: storage::Value<BlockNumber>,
+        /// Whether an account is a reporter.
+        reporters: storage::HashMap<AccountId, bool>,
+        /// The number of reports of each symbol so far.
+        report_count: storage::HashMap<Symbol, u32>,
+        /// The last reports of each symbol: (symbol, report number % HISTORY_LENGTH) -> report
+        history: storage::HashMap<(Symbol, u32), Report>,
+    }
+
+    impl Deploy for PriceOracle {
+        fn deploy(&mut self, max_age: BlockNumber) {
+            self.admin.set(env.caller());
+            self.max_age.set(max_age);
+        }
+    }
+
+    impl PriceOracle {
+        /// Returns the maximum age in blocks of a price returned by `get_price`.
+        pub(external) fn max_age(&self) -> BlockNumber {
+            let max_age = *self.max_age;
+            env.println(&format!("PriceOracle::max_age = {:?}", max_age));
+            max_age
+        }
+
+        /// Returns `true` if `account` is a reporter.
+        pub(external) fn is_reporter(&self, account: AccountId) -> bool {
+            let reporter = self.is_reporter_or_false(&account);
+            env.println(&format!("PriceOracle::is_reporter(account = {:?}) = {:?}", account, reporter));
+            reporter
+        }
+
+        /// Returns the latest price of `symbol`.
+        ///
+        /// Fails with `StalePrice` if it was observed more than `max_age` blocks ago.
+        pub(external) fn get_price(&self, symbol: Symbol) -> Result<Balance, OracleError> {
+            let price = match self.latest(&symbol) {
+                None => Err(OracleError::UnknownSymbol),
+                Some(report) if env.block_number() - report.timestamp > *self.max_age => Err(OracleError::StalePrice),
+                Some(report) => Ok(report.price),
+            };
+            env.println(&format!("PriceOracle::get_price(symbol = {:?}) = {:?}", symbol, price));
+            price
+        }
+
+        /// Returns the report `back` reports before the latest report of `symbol`.
+        ///
+        /// The latest report is 0 reports back. Returns `None` if there is
+        /// no such report, or it is no longer kept.
+        pub(external) fn get_report(&self, symbol: Symbol, back: u32) -> Option<Report> {
+            let report = self.report_at(&symbol, back);
+            env.println(&format!("PriceOracle::get_report(symbol = {:?}, back = {:?}) = {:?}", symbol, back, report));
+            report
+        }
+
+        /// Makes `account` a reporter.
+        ///
+        /// Only the admin can add reporters.
+        pub(external) fn add_reporter(&mut self, account: AccountId) -> Result<(), OracleError> {
+            self.ensure_admin(&env.caller())?;
+            self.reporters.insert(account, true);
+            deposit_event(Event::ReporterAdded {
+                reporter: account
+            });
+            Ok(())
+        }
+
+        /// Stops `account` from being a reporter.
+        ///
+        /// Only the admin can remove reporters. Prices they reported stay valid
+        /// until they are stale.
+        pub(external) fn remove_reporter(&mut self, account: AccountId) -> Result<(), OracleError> {
+            self.ensure_admin(&env.caller())?;
+            self.reporters.remove(&account);
+            deposit_event(Event::ReporterRemoved {
+                reporter: account
+            });
+            Ok(())
+        }
+
+        /// Reports `price` for `symbol`, as observed in the block `timestamp`.
+        ///
+        /// Only reporters can report prices. The timestamp must not lie in
+        /// the future, nor before the timestamp of the latest report.
+        pub(external) fn report(
+            &mut self,
+            symbol: Symbol,
+            price: Balance,
+            timestamp: BlockNumber,
+        ) -> Result<(), OracleError> {
+            let reporter = env.caller();
+            if !self.is_reporter_or_false(&reporter) {
+                return Err(OracleError::NotReporter)
+            }
+            if price == 0 {
+                return Err(OracleError::ZeroPrice)
+            }
+            if timestamp > env.block_number() {
+                return Err(OracleError::InvalidTimestamp)
+            }
+            if let Some(latest) = self.latest(&symbol) {
+                if timestamp < latest.timestamp {
+                    return Err(OracleError::OutdatedReport)
+                }
+            }
+            let count = self.report_count_or_zero(&symbol);
+            self.history.insert((symbol, count % HISTORY_LENGTH), Report {
+                price: price,
+                timestamp: timestamp,
+                reporter: reporter
+            });
+            self.report_count.insert(symbol, count + 1);
+            deposit_event(Event::PriceReported {
+                symbol: symbol,
+                reporter: reporter,
+                price: price,
+                timestamp: timestamp
+            });
+            Ok(())
+        }
+    }
+
+    impl PriceOracle {
+        /// Returns an error unless `caller` is the admin.
+        fn ensure_admin(&self, caller: &AccountId) -> Result<(), OracleError> {
+            if *caller != *self.admin {
+                return Err(OracleError::NotAdmin)
+            }
+            Ok(())
+        }
+
+        /// Returns whether the AccountId is a reporter or `false` if it never was one.
+        fn is_reporter_or_false(&self, account: &AccountId) -> bool {
+            *self.reporters.get(account).unwrap_or(&false)
+        }
+
+        /// Returns the number of reports of the symbol or 0 if there are none.
+        fn report_count_or_zero(&self, symbol: &Symbol) -> u32 {
+            *self.report_count.get(symbol).unwrap_or(&0)
+        }
+
+        /// Returns the latest report of `symbol`, if any.
+        fn latest(&self, symbol: &Symbol) -> Option<Report> {
+            self.report_at(symbol, 0)
+        }
+
+        /// Returns the report `back` reports before the latest report of
+        /// `symbol`, if it is still kept.
+        fn report_at(&self, symbol: &Symbol, back: u32) -> Option<Report> {
+            let count = self.report_count_or_zero(symbol);
+            if back >= count || back >= HISTORY_LENGTH {
+                return None
+            }
+            self.history.get(&(*symbol, (count - 1 - back) % HISTORY_LENGTH)).cloned()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const DOT: Symbol = *b"DOT\0\0\0\0\0";
+    const BTC: Symbol = *b"BTC\0\0\0\0\0";
+
+    /// Returns Alice, Bob and Charlie, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys an oracle at block 100 with Alice as its admin and Bob as a
+    /// reporter, which considers prices stale after 10 blocks.
+    fn deploy_mock() -> PriceOracle {
+        let (alice, bob, _) = accounts();
+        env::test::set_caller(alice);
+        env::test::set_block_number(100);
+        let mut oracle = PriceOracle::deploy_mock(10);
+        assert_eq!(oracle.add_reporter(bob), Ok(()));
+        oracle
+    }
+
+    #[test]
+    fn only_admin_manages_reporters() {
+        let (alice, bob, charlie) = accounts();
+        let mut oracle = deploy_mock();
+        assert!(oracle.is_reporter(bob));
+        assert!(!oracle.is_reporter(charlie));
+        env::test::set_caller(bob);
+        assert_eq!(oracle.add_reporter(charlie), Err(OracleError::NotAdmin));
+        assert_eq!(oracle.remove_reporter(bob), Err(OracleError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(oracle.add_reporter(charlie), Ok(()));
+        assert_eq!(oracle.remove_reporter(bob), Ok(()));
+        assert!(oracle.is_reporter(charlie));
+        assert!(!oracle.is_reporter(bob));
+    }
+
+    #[test]
+    fn only_reporters_can_report() {
+        let (alice, bob, _) = accounts();
+        let mut oracle = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(oracle.report(DOT, 5, 100), Err(OracleError::NotReporter));
+        env::test::set_caller(bob);
+        assert_eq!(oracle.get_price(DOT), Err(OracleError::UnknownSymbol));
+        assert_eq!(oracle.report(DOT, 5, 100), Ok(()));
+        assert_eq!(oracle.get_price(DOT), Ok(5));
+        assert_eq!(oracle.get_price(BTC), Err(OracleError::UnknownSymbol));
+        // Removed reporters cannot report anymore
+        env::test::set_caller(alice);
+        assert_eq!(oracle.remove_reporter(bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(oracle.report(DOT, 6, 100), Err(OracleError::NotReporter));
+        assert_eq!(oracle.get_price(DOT), Ok(5));
+    }
+
+    #[test]
+    fn report_checks_price_and_timestamp() {
+        let (_, bob, _) = accounts();
+        let mut oracle = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(oracle.report(DOT, 0, 100), Err(OracleError::ZeroPrice));
+        assert_eq!(oracle.report(DOT, 5, 101), Err(OracleError::InvalidTimestamp));
+        assert_eq!(oracle.report(DOT, 5, 95), Ok(()));
+        assert_eq!(oracle.report(DOT, 6, 94), Err(OracleError::OutdatedReport));
+        assert_eq!(oracle.report(DOT, 6, 95), Ok(()));
+        // Every symbol has its own latest report
+        assert_eq!(oracle.report(BTC, 7, 90), Ok(()));
+        assert_eq!(oracle.get_price(DOT), Ok(6));
+        assert_eq!(oracle.get_price(BTC), Ok(7));
+    }
+
+    #[test]
+    fn prices_become_stale() {
+        let (_, bob, _) = accounts();
+        let mut oracle = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(oracle.report(DOT, 5, 95), Ok(()));
+        // The age is counted from the timestamp, not from the report
+        env::test::set_block_number(105);
+        assert_eq!(oracle.get_price(DOT), Ok(5));
+        env::test::set_block_number(106);
+        assert_eq!(oracle.get_price(DOT), Err(OracleError::StalePrice));
+        // The report stays available without the staleness check
+        assert_eq!(oracle.get_report(DOT, 0).map(|report| report.price), Some(5));
+        assert_eq!(oracle.report(DOT, 6, 106), Ok(()));
+        assert_eq!(oracle.get_price(DOT), Ok(6));
+    }
+
+    #[test]
+    fn history_keeps_the_last_reports() {
+        let (_, bob, _) = accounts();
+        let mut oracle = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(oracle.get_report(DOT, 0), None);
+        for price in 1..=10 {
+            assert_eq!(oracle.report(DOT, price, 100), Ok(()));
+        }
+        assert_eq!(
+            oracle.get_report(DOT, 0),
+            Some(Report {
+                price: 10,
+                timestamp: 100,
+                reporter: bob
+            })
+        );
+        assert_eq!(oracle.get_report(DOT, 1).map(|report| report.price), Some(9));
+        assert_eq!(oracle.get_report(DOT, 7).map(|report| report.price), Some(3));
+        // Older reports have been overwritten
+        assert_eq!(oracle.get_report(DOT, 8), None);
+    }
+}
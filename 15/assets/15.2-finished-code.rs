@@ -0,0 +1,414 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a round of the aggregator.
+type RoundId = u32;
+
+/// A round in which the oracles submit a price.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Round {
+    /// The block the round started in.
+    started_at: BlockNumber,
+    /// The number of prices submitted so far.
+    submissions: u32,
+    /// The median of the submitted prices, once there is one.
+    answer: Option<Balance>,
+}
+
+/// Events deposited by the median aggregator.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    RoundStarted {
+        round: RoundId,
+        started_at: BlockNumber,
+    },
+    PriceSubmitted {
+        round: RoundId,
+        oracle: AccountId,
+        price: Balance,
+    },
+    RoundAnswered {
+        round: RoundId,
+        answer: Balance,
+    },
+    RoundAbandoned {
+        round: RoundId,
+    },
+}
+
+/// Errors which can occur when calling the median aggregator.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum AggregatorError {
+    /// Only the oracles can submit prices.
+    NotOracle,
+    /// Prices can only be submitted to the current round.
+    WrongRound,
+    /// Every oracle can only submit one price per round.
+    AlreadySubmitted,
+    /// A price of 0 is never valid.
+    ZeroPrice,
+    /// The current round can still be answered.
+    RoundNotTimedOut,
+}
+
+/// Deposits a median aggregator event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns whether `quorum` submissions can be reached with `oracles` oracles.
+fn is_valid_quorum(quorum: u32, oracles: usize) -> bool {
+    quorum > 0 && quorum as usize <= oracles
+}
+
+/// Sorts `prices` and returns their median.
+///
+/// With an even number of prices, the median is the mean of the two in the
+/// middle, rounded down. `prices` must not be empty.
+fn median(prices: &mut [Balance]) -> Balance {
+    prices.sort_unstable();
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        return prices[middle]
+    }
+    let (low, high) = (prices[middle - 1], prices[middle]);
+    // Halving first avoids overflowing on large prices.
+    low / 2 + high / 2 + (low % 2 + high % 2) / 2
+}
+
+/// Returns whether `price` deviates from `median` by at most `max_deviation` percent.
+fn is_within(price: Balance, median: Balance, max_deviation: u32) -> bool {
+    let difference = if price > median { price - median } else { median - price };
+    let max_deviation = Balance::from(max_deviation);
+    difference <= median / 100 * max_deviation + median % 100 * max_deviation / 100
+}
+
+contract! {
+    /// Combines the prices submitted by a fixed set of oracles into their median.
+    ///
+    /// The oracles submit one price per round. Once `quorum` of them have
+    /// submitted, prices which deviate from the median by more than
+    /// `max_deviation` percent are discarded as outliers. If at least
+    /// `quorum` prices remain, their median becomes the answer of the round,
+    /// and the next round starts. A round without an answer can be
+    /// abandoned after `timeout` blocks.
+    struct MedianAggregator {
+        /// The accounts which can submit prices.
+        oracles: storage::Value<Vec<AccountId>>,
+        /// The number of prices needed to answer a round, after discarding outliers.
+        quorum: storage::Value<u32>,
+        /// The percentage by which a price may deviate from the median.
+        max_deviation: storage::Value<u32>,
+        /// The number of blocks after which a round can be abandoned.
+        timeout: storage::Value<BlockNumber>,
+        /// The round prices are submitted to.
+        current_round: storage::Value<RoundId>,
+        /// The last round which has been answered.
+        latest_answered: storage::Value<Option<RoundId>>,
+        /// The rounds started so far.
+        rounds: storage::HashMap<RoundId, Round>,
+        /// The prices submitted to each round: (round, oracle) -> price
+        submissions: storage::HashMap<(RoundId, AccountId), Balance>,
+    }
+
+    impl Deploy for MedianAggregator {
+        fn deploy(&mut self, oracles: Vec<AccountId>, quorum: u32, max_deviation: u32, timeout: BlockNumber) {
+            assert!(is_valid_quorum(quorum, oracles.len()), "the quorum cannot be reached");
+            for (index, oracle) in oracles.iter().enumerate() {
+                assert!(!oracles[..index].contains(oracle), "every oracle must be unique");
+            }
+            self.oracles.set(oracles);
+            self.quorum.set(quorum);
+            self.max_deviation.set(max_deviation);
+            self.timeout.set(timeout);
+            self.latest_answered.set(None);
+            self.start_round(0);
+        }
+    }
+
+    impl MedianAggregator {
+        /// Returns the oracles which can submit prices.
+        pub(external) fn oracles(&self) -> Vec<AccountId> {
+            let oracles = (*self.oracles).clone();
+            env.println(&format!("MedianAggregator::oracles = {:?}", oracles));
+            oracles
+        }
+
+        /// Returns the round prices are submitted to.
+        pub(external) fn current_round(&self) -> RoundId {
+            let current_round = *self.current_round;
+            env.println(&format!("MedianAggregator::current_round = {:?}", current_round));
+            current_round
+        }
+
+        /// Returns the round with the given id, if it has been started.
+        pub(external) fn round(&self, id: RoundId) -> Option<Round> {
+            let round = self.rounds.get(&id).cloned();
+            env.println(&format!("MedianAggregator::round(id = {:?}) = {:?}", id, round));
+            round
+        }
+
+        /// Returns the price `oracle` submitted to the round `id`, if any.
+        pub(external) fn submission(&self, id: RoundId, oracle: AccountId) -> Option<Balance> {
+            let price = self.submissions.get(&(id, oracle)).cloned();
+            env.println(&format!("MedianAggregator::submission(id = {:?}, oracle = {:?}) = {:?}", id, oracle, price));
+            price
+        }
+
+        /// Returns the answer of the last round which has been answered, if any.
+        pub(external) fn latest_answer(&self) -> Option<Balance> {
+            let answer = match *self.latest_answered {
+                Some(id) => self.rounds.get(&id).and_then(|round| round.answer),
+                None => None,
+            };
+            env.println(&format!("MedianAggregator::latest_answer = {:?}", answer));
+            answer
+        }
+
+        /// Submits `price` to the round `id` as the caller.
+        ///
+        /// Only oracles can submit prices, once per round, and only to the
+        /// current round. Once enough prices agree, the round is answered
+        /// and the next one starts.
+        pub(external) fn submit(&mut self, id: RoundId, price: Balance) -> Result<(), AggregatorError> {
+            let oracle = env.caller();
+            if !self.oracles.contains(&oracle) {
+                return Err(AggregatorError::NotOracle)
+            }
+            if id != *self.current_round {
+                return Err(AggregatorError::WrongRound)
+            }
+            if price == 0 {
+                return Err(AggregatorError::ZeroPrice)
+            }
+            if self.submissions.get(&(id, oracle)).is_some() {
+                return Err(AggregatorError::AlreadySubmitted)
+            }
+            self.submissions.insert((id, oracle), price);
+            let mut round = *self.rounds.get(&id).expect("the current round has been started");
+            round.submissions += 1;
+            self.rounds.insert(id, round);
+            deposit_event(Event::PriceSubmitted {
+                round: id,
+                oracle: oracle,
+                price: price
+            });
+            if round.submissions >= *self.quorum {
+                self.try_answer(id, round);
+            }
+            Ok(())
+        }
+
+        /// Abandons the current round without an answer, and starts the next one.
+        ///
+        /// Anyone can abandon a round, but only once `timeout` blocks have
+        /// passed since it started.
+        pub(external) fn abandon_round(&mut self) -> Result<(), AggregatorError> {
+            let id = *self.current_round;
+            let round = *self.rounds.get(&id).expect("the current round has been started");
+            if env.block_number() < round.started_at + *self.timeout {
+                return Err(AggregatorError::RoundNotTimedOut)
+            }
+            deposit_event(Event::RoundAbandoned {
+                round: id
+            });
+            self.start_round(id + 1);
+            Ok(())
+        }
+    }
+
+    impl MedianAggregator {
+        /// Starts the round `id` in the current block, and makes it the current round.
+        fn start_round(&mut self, id: RoundId) {
+            let started_at = env::block_number();
+            self.rounds.insert(id, Round {
+                started_at: started_at,
+                submissions: 0,
+                answer: None
+            });
+            self.current_round.set(id);
+            deposit_event(Event::RoundStarted {
+                round: id,
+                started_at: started_at
+            });
+        }
+
+        /// Answers the round `id` with the median of its prices which are not
+        /// outliers, if at least `quorum` of them remain.
+        ///
+        /// Otherwise, the round stays open for the oracles which have not
+        /// submitted yet.
+        fn try_answer(&mut self, id: RoundId, mut round: Round) {
+            let mut prices: Vec<Balance> = self
+                .oracles
+                .iter()
+                .filter_map(|oracle| self.submissions.get(&(id, *oracle)).cloned())
+                .collect();
+            let middle = median(&mut prices);
+            let max_deviation = *self.max_deviation;
+            let mut agreeing: Vec<Balance> =
+                prices.into_iter().filter(|price| is_within(*price, middle, max_deviation)).collect();
+            if (agreeing.len() as u32) < *self.quorum {
+                return
+            }
+            let answer = median(&mut agreeing);
+            round.answer = Some(answer);
+            self.rounds.insert(id, round);
+            self.latest_answered.set(Some(id));
+            deposit_event(Event::RoundAnswered {
+                round: id,
+                answer: answer
+            });
+            self.start_round(id + 1);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns the account of the oracle with the given number.
+    fn oracle(number: u8) -> AccountId {
+        AccountId::try_from([number; 32]).unwrap()
+    }
+
+    /// Deploys an aggregator at block 0 with the oracles 1 to 5, which
+    /// needs 3 prices within 10% of the median, and times out after 20 blocks.
+    fn deploy_mock() -> MedianAggregator {
+        env::test::set_block_number(0);
+        let oracles = (1..=5).map(oracle).collect();
+        MedianAggregator::deploy_mock(oracles, 3, 10, 20)
+    }
+
+    /// Submits `price` to the round `id` as the oracle with the given number.
+    fn submit(
+        aggregator: &mut MedianAggregator,
+        number: u8,
+        id: RoundId,
+        price: Balance,
+    ) -> Result<(), AggregatorError> {
+        env::test::set_caller(oracle(number));
+        aggregator.submit(id, price)
+    }
+
+    #[test]
+    #[should_panic(expected = "the quorum cannot be reached")]
+    fn deploy_fails_on_unreachable_quorum() {
+        MedianAggregator::deploy_mock(vec![oracle(1), oracle(2)], 3, 10, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "every oracle must be unique")]
+    fn deploy_fails_on_duplicate_oracles() {
+        MedianAggregator::deploy_mock(vec![oracle(1), oracle(2), oracle(1)], 2, 10, 20);
+    }
+
+    #[test]
+    fn median_works() {
+        assert_eq!(median(&mut [3, 1, 2]), 2);
+        assert_eq!(median(&mut [4, 1, 3, 2]), 2);
+        assert_eq!(median(&mut [5]), 5);
+        assert_eq!(median(&mut [Balance::max_value(), Balance::max_value()]), Balance::max_value());
+        assert!(is_within(110, 100, 10));
+        assert!(is_within(90, 100, 10));
+        assert!(!is_within(111, 100, 10));
+        assert!(!is_within(89, 100, 10));
+    }
+
+    #[test]
+    fn submit_checks_the_caller_and_round() {
+        let mut aggregator = deploy_mock();
+        assert_eq!(submit(&mut aggregator, 6, 0, 100), Err(AggregatorError::NotOracle));
+        assert_eq!(submit(&mut aggregator, 1, 1, 100), Err(AggregatorError::WrongRound));
+        assert_eq!(submit(&mut aggregator, 1, 0, 0), Err(AggregatorError::ZeroPrice));
+        assert_eq!(submit(&mut aggregator, 1, 0, 100), Ok(()));
+        assert_eq!(submit(&mut aggregator, 1, 0, 101), Err(AggregatorError::AlreadySubmitted));
+        assert_eq!(aggregator.submission(0, oracle(1)), Some(100));
+        assert_eq!(aggregator.round(0).map(|round| round.submissions), Some(1));
+    }
+
+    #[test]
+    fn quorum_answers_with_the_median() {
+        let mut aggregator = deploy_mock();
+        assert_eq!(submit(&mut aggregator, 1, 0, 100), Ok(()));
+        assert_eq!(submit(&mut aggregator, 2, 0, 108), Ok(()));
+        assert_eq!(aggregator.latest_answer(), None);
+        assert_eq!(submit(&mut aggregator, 3, 0, 95), Ok(()));
+        assert_eq!(aggregator.latest_answer(), Some(100));
+        assert_eq!(aggregator.round(0).and_then(|round| round.answer), Some(100));
+        // The next round started, and the old one takes no more prices
+        assert_eq!(aggregator.current_round(), 1);
+        assert_eq!(submit(&mut aggregator, 4, 0, 100), Err(AggregatorError::WrongRound));
+        assert_eq!(submit(&mut aggregator, 4, 1, 120), Ok(()));
+    }
+
+    #[test]
+    fn outliers_are_discarded() {
+        let mut aggregator = deploy_mock();
+        assert_eq!(submit(&mut aggregator, 1, 0, 100), Ok(()));
+        assert_eq!(submit(&mut aggregator, 2, 0, 102), Ok(()));
+        // 1000 is far from the median of 102, so only 2 prices agree
+        assert_eq!(submit(&mut aggregator, 3, 0, 1000), Ok(()));
+        assert_eq!(aggregator.latest_answer(), None);
+        assert_eq!(aggregator.current_round(), 0);
+        // With 98, the median is 101, and 3 prices agree without 1000
+        assert_eq!(submit(&mut aggregator, 4, 0, 98), Ok(()));
+        assert_eq!(aggregator.latest_answer(), Some(100));
+        assert_eq!(aggregator.current_round(), 1);
+    }
+
+    #[test]
+    fn disagreeing_oracles_leave_the_round_open() {
+        let mut aggregator = deploy_mock();
+        assert_eq!(submit(&mut aggregator, 1, 0, 100), Ok(()));
+        assert_eq!(submit(&mut aggregator, 2, 0, 150), Ok(()));
+        assert_eq!(submit(&mut aggregator, 3, 0, 200), Ok(()));
+        assert_eq!(submit(&mut aggregator, 4, 0, 250), Ok(()));
+        assert_eq!(submit(&mut aggregator, 5, 0, 300), Ok(()));
+        assert_eq!(aggregator.latest_answer(), None);
+        assert_eq!(aggregator.round(0).map(|round| round.submissions), Some(5));
+    }
+
+    #[test]
+    fn missing_oracles_time_out_the_round() {
+        let mut aggregator = deploy_mock();
+        assert_eq!(submit(&mut aggregator, 1, 0, 100), Ok(()));
+        assert_eq!(submit(&mut aggregator, 2, 0, 101), Ok(()));
+        env::test::set_block_number(5);
+        assert_eq!(submit(&mut aggregator, 3, 0, 102), Ok(()));
+        assert_eq!(aggregator.latest_answer(), Some(101));
+        assert_eq!(aggregator.round(1).map(|round| round.started_at), Some(5));
+        // Only 2 oracles show up for round 1
+        assert_eq!(submit(&mut aggregator, 1, 1, 110), Ok(()));
+        assert_eq!(submit(&mut aggregator, 2, 1, 111), Ok(()));
+        env::test::set_block_number(24);
+        assert_eq!(aggregator.abandon_round(), Err(AggregatorError::RoundNotTimedOut));
+        env::test::set_block_number(25);
+        assert_eq!(aggregator.abandon_round(), Ok(()));
+        assert_eq!(aggregator.current_round(), 2);
+        assert_eq!(aggregator.round(1).and_then(|round| round.answer), None);
+        // The answer of round 0 is still the latest one
+        assert_eq!(aggregator.latest_answer(), Some(101));
+        assert_eq!(submit(&mut aggregator, 3, 1, 112), Err(AggregatorError::WrongRound));
+    }
+}
@@ -0,0 +1,586 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+        Hash,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Returns the BLAKE2b-256 hash of `data`.
+fn hash_of(data: &[u8]) -> Hash {
+    let hash = blake2_rfc::blake2b::blake2b(32, &[], data);
+    Hash::decode(&mut &hash.as_bytes()[..]).expect("a BLAKE2b-256 hash has the size of a Hash")
+}
+
+/// Returns the hashlock which `preimage` unlocks.
+fn hashlock_of(preimage: Hash) -> Hash {
+    // ACTION: Return the `hash_of` the encoded `preimage`
+    Hash::default()
+}
+
+/// The identifier of a swap locked in the contract.
+type SwapId = u32;
+
+/// What a swap locks.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Asset {
+    /// Native balance.
+    Native,
+    /// Tokens of the ERC20 token at the given address.
+    Token(AccountId),
+}
+
+/// Funds locked until the recipient reveals the preimage of the hashlock,
+/// or the timelock passes.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Swap {
+    /// The account which locked the funds, and gets them back on a refund.
+    sender: AccountId,
+    /// The account which receives the funds on a claim.
+    recipient: AccountId,
+    /// What has been locked.
+    asset: Asset,
+    /// How much of the asset has been locked.
+    amount: Balance,
+    /// The hash of the preimage which unlocks the funds.
+    hashlock: Hash,
+    /// The block from which on the funds can no longer be claimed, only refunded.
+    timelock: BlockNumber,
+}
+
+/// Events deposited by the HTLC contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Locked {
+        id: SwapId,
+        sender: AccountId,
+        recipient: AccountId,
+        hashlock: Hash,
+        timelock: BlockNumber,
+    },
+    Claimed {
+        id: SwapId,
+        preimage: Hash,
+    },
+    Refunded {
+        id: SwapId,
+    },
+}
+
+/// Errors which can occur when calling the HTLC contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum HtlcError {
+    /// Nothing was locked.
+    ZeroAmount,
+    /// The timelock has already passed.
+    InvalidTimelock,
+    /// There is no locked swap with the given id.
+    UnknownSwap,
+    /// The preimage does not hash to the hashlock.
+    WrongPreimage,
+    /// The timelock has passed, so the swap can only be refunded.
+    Expired,
+    /// The timelock has not passed yet, so the swap can only be claimed.
+    NotExpired,
+    /// The token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits an HTLC event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Locks native balance or ERC20 tokens against a hash and a timeout.
+    ///
+    /// The recipient claims the funds by revealing the preimage of the hash
+    /// before the timeout. After the timeout, the sender can take them back.
+    /// Two swaps locked with the same hash, one in each direction, make an
+    /// atomic swap: claiming one reveals the preimage which claims the other.
+    struct Htlc {
+        /// The number of swaps locked so far.
+        swap_count: storage::Value<SwapId>,
+        /// The swaps which have been neither claimed nor refunded.
+        swaps: storage::HashMap<SwapId, Swap>,
+        /// The preimages revealed by claims.
+        preimages: storage::HashMap<SwapId, Hash>,
+    }
+
+    impl Deploy for Htlc {
+        fn deploy(&mut self) {
+            self.swap_count.set(0);
+        }
+    }
+
+    impl Htlc {
+        /// Returns the number of swaps locked so far.
+        pub(external) fn swap_count(&self) -> SwapId {
+            let swap_count = *self.swap_count;
+            env.println(&format!("Htlc::swap_count = {:?}", swap_count));
+            swap_count
+        }
+
+        /// Returns the swap with the given id, if it is still locked.
+        pub(external) fn swap(&self, id: SwapId) -> Option<Swap> {
+            let swap = self.swaps.get(&id).cloned();
+            env.println(&format!("Htlc::swap(id = {:?}) = {:?}", id, swap));
+            swap
+        }
+
+        /// Returns the preimage which claimed the swap with the given id, if
+        /// it has been claimed.
+        pub(external) fn preimage(&self, id: SwapId) -> Option<Hash> {
+            let preimage = self.preimages.get(&id).cloned();
+            env.println(&format!("Htlc::preimage(id = {:?}) = {:?}", id, preimage));
+            preimage
+        }
+
+        /// Locks the native balance sent along with the call for `recipient`,
+        /// and returns the id of the swap.
+        pub(external) fn lock_native(
+            &mut self,
+            recipient: AccountId,
+            hashlock: Hash,
+            timelock: BlockNumber,
+        ) -> Result<SwapId, HtlcError> {
+            let amount = value_transferred();
+            if amount == 0 {
+                return Err(HtlcError::ZeroAmount)
+            }
+            if timelock <= env.block_number() {
+                return Err(HtlcError::InvalidTimelock)
+            }
+            Ok(self.lock(env.caller(), recipient, Asset::Native, amount, hashlock, timelock))
+        }
+
+        /// Locks `amount` tokens of the caller for `recipient`, and returns
+        /// the id of the swap.
+        ///
+        /// The caller must have approved the contract to transfer the tokens.
+        pub(external) fn lock_tokens(
+            &mut self,
+            token: AccountId,
+            recipient: AccountId,
+            amount: Balance,
+            hashlock: Hash,
+            timelock: BlockNumber,
+        ) -> Result<SwapId, HtlcError> {
+            if amount == 0 {
+                return Err(HtlcError::ZeroAmount)
+            }
+            if timelock <= env.block_number() {
+                return Err(HtlcError::InvalidTimelock)
+            }
+            let sender = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(token, TRANSFER_FROM_SELECTOR, &(sender, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(HtlcError::TransferFailed),
+            }
+            Ok(self.lock(sender, recipient, Asset::Token(token), amount, hashlock, timelock))
+        }
+
+        /// Pays the swap out to its recipient, if `preimage` hashes to its hashlock.
+        ///
+        /// Anyone can claim, since the funds always go to the recipient. Only
+        /// possible before the timelock. If the payout fails, the whole call
+        /// is reverted.
+        pub(external) fn claim(&mut self, id: SwapId, preimage: Hash) -> Result<(), HtlcError> {
+            let swap = *self.swaps.get(&id).ok_or(HtlcError::UnknownSwap)?;
+            // ACTION: Return `Err(HtlcError::Expired)` if the `timelock` of the swap has been reached
+            // ACTION: Return `Err(HtlcError::WrongPreimage)` if the `hashlock_of` the `preimage` is
+            //         not the `hashlock` of the swap
+            // ACTION: `remove` the swap, and `insert` the `preimage` for everyone to see
+            self.pay_out(swap.asset, swap.recipient, swap.amount);
+            deposit_event(Event::Claimed {
+                id: id,
+                preimage: preimage
+            });
+            Ok(())
+        }
+
+        /// Pays the swap back to its sender.
+        ///
+        /// Anyone can refund, since the funds always go to the sender. Only
+        /// possible from the timelock on. If the payout fails, the whole call
+        /// is reverted.
+        pub(external) fn refund(&mut self, id: SwapId) -> Result<(), HtlcError> {
+            let swap = *self.swaps.get(&id).ok_or(HtlcError::UnknownSwap)?;
+            // ACTION: Return `Err(HtlcError::NotExpired)` if the `timelock` of the swap has not
+            //         been reached yet
+            // ACTION: `remove` the swap
+            self.pay_out(swap.asset, swap.sender, swap.amount);
+            deposit_event(Event::Refunded {
+                id: id
+            });
+            Ok(())
+        }
+    }
+
+    impl Htlc {
+        /// Stores a new swap which has already received its funds, and returns its id.
+        fn lock(
+            &mut self,
+            sender: AccountId,
+            recipient: AccountId,
+            asset: Asset,
+            amount: Balance,
+            hashlock: Hash,
+            timelock: BlockNumber,
+        ) -> SwapId {
+            let id = *self.swap_count;
+            self.swaps.insert(id, Swap {
+                sender: sender,
+                recipient: recipient,
+                asset: asset,
+                amount: amount,
+                hashlock: hashlock,
+                timelock: timelock
+            });
+            self.swap_count += 1;
+            deposit_event(Event::Locked {
+                id: id,
+                sender: sender,
+                recipient: recipient,
+                hashlock: hashlock,
+                timelock: timelock
+            });
+            id
+        }
+
+        /// Sends `amount` of `asset` held by the contract to `to`.
+        ///
+        /// Panics if the transfer fails.
+        fn pay_out(&self, asset: Asset, to: AccountId, amount: Balance) {
+            match asset {
+                Asset::Native => assert!(transfer_native(to, amount), "the payout failed"),
+                Asset::Token(token) => {
+                    // The token's `Erc20Error` is decoded as its variant index.
+                    let result: Option<Result<(), u8>> =
+                        call_contract(token, TRANSFER_SELECTOR, &(to, amount).encode());
+                    assert_eq!(result, Some(Ok(())), "the tokens could not be transferred");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the HTLC through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the HTLC first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Returns a preimage made of the byte `byte`.
+    fn preimage(byte: u8) -> Hash {
+        Hash::decode(&mut &[byte; 32][..]).unwrap()
+    }
+
+    /// Returns Alice, Bob and Charlie, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys an HTLC at block 0.
+    fn deploy_mock() -> Htlc {
+        env::test::set_block_number(0);
+        Htlc::deploy_mock()
+    }
+
+    /// Locks `value` of native balance from `sender` for `recipient` until
+    /// block 100, against the hashlock of `preimage(1)`.
+    fn lock_native(
+        htlc: &mut Htlc,
+        sender: AccountId,
+        recipient: AccountId,
+        value: Balance,
+    ) -> Result<SwapId, HtlcError> {
+        env::test::set_caller(sender);
+        set_value_transferred(value);
+        let result = htlc.lock_native(recipient, hashlock_of(preimage(1)), 100);
+        set_value_transferred(0);
+        result
+    }
+
+    #[test]
+    fn lock_checks_amount_and_timelock() {
+        let (alice, bob, _) = accounts();
+        let mut htlc = deploy_mock();
+        assert_eq!(lock_native(&mut htlc, alice, bob, 0), Err(HtlcError::ZeroAmount));
+        env::test::set_block_number(100);
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Err(HtlcError::InvalidTimelock));
+        env::test::set_block_number(99);
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Ok(0));
+        assert_eq!(htlc.swap_count(), 1);
+        assert_eq!(
+            htlc.swap(0),
+            Some(Swap {
+                sender: alice,
+                recipient: bob,
+                asset: Asset::Native,
+                amount: 10,
+                hashlock: hashlock_of(preimage(1)),
+                timelock: 100
+            })
+        );
+    }
+
+    #[test]
+    fn claim_works() {
+        let (alice, bob, charlie) = accounts();
+        let mut htlc = deploy_mock();
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Ok(0));
+        // Anyone can claim, but the funds go to the recipient
+        env::test::set_caller(charlie);
+        assert_eq!(htlc.claim(1, preimage(1)), Err(HtlcError::UnknownSwap));
+        env::test::set_block_number(99);
+        assert_eq!(htlc.claim(0, preimage(1)), Ok(()));
+        assert_eq!(payouts(), vec![(bob, 10)]);
+        assert_eq!(htlc.swap(0), None);
+        assert_eq!(htlc.preimage(0), Some(preimage(1)));
+        // Every swap can only be paid out once
+        assert_eq!(htlc.claim(0, preimage(1)), Err(HtlcError::UnknownSwap));
+        assert_eq!(htlc.refund(0), Err(HtlcError::UnknownSwap));
+    }
+
+    #[test]
+    fn claim_fails_on_wrong_preimage() {
+        let (alice, bob, _) = accounts();
+        let mut htlc = deploy_mock();
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Ok(0));
+        env::test::set_caller(bob);
+        assert_eq!(htlc.claim(0, preimage(2)), Err(HtlcError::WrongPreimage));
+        // The hashlock itself is no preimage either
+        assert_eq!(htlc.claim(0, hashlock_of(preimage(1))), Err(HtlcError::WrongPreimage));
+        assert_eq!(htlc.preimage(0), None);
+        assert_eq!(payouts(), vec![]);
+    }
+
+    #[test]
+    fn refund_works_after_timeout() {
+        let (alice, bob, _) = accounts();
+        let mut htlc = deploy_mock();
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Ok(0));
+        env::test::set_caller(alice);
+        env::test::set_block_number(99);
+        assert_eq!(htlc.refund(0), Err(HtlcError::NotExpired));
+        env::test::set_block_number(100);
+        // The recipient is too late now, even with the right preimage
+        assert_eq!(htlc.claim(0, preimage(1)), Err(HtlcError::Expired));
+        assert_eq!(htlc.refund(0), Ok(()));
+        assert_eq!(payouts(), vec![(alice, 10)]);
+        assert_eq!(htlc.refund(0), Err(HtlcError::UnknownSwap));
+    }
+
+    #[test]
+    fn tokens_can_be_claimed_and_refunded() {
+        let (alice, bob, _) = accounts();
+        let mut htlc = deploy_mock();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+        env::test::set_caller(alice);
+        let hashlock = hashlock_of(preimage(1));
+        assert_eq!(htlc.lock_tokens(token, bob, 101, hashlock, 100), Err(HtlcError::TransferFailed));
+        assert_eq!(htlc.lock_tokens(token, bob, 60, hashlock, 100), Ok(0));
+        assert_eq!(htlc.lock_tokens(token, bob, 40, hashlock, 100), Ok(1));
+        assert_eq!(balances.borrow()[&alice], 0);
+        assert_eq!(htlc.claim(0, preimage(1)), Ok(()));
+        assert_eq!(balances.borrow()[&bob], 60);
+        env::test::set_block_number(100);
+        assert_eq!(htlc.refund(1), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 40);
+    }
+
+    #[test]
+    fn atomic_swap_works() {
+        let (alice, bob, _) = accounts();
+        let mut htlc = deploy_mock();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(bob, 500);
+        // Alice picks the preimage, and locks native balance for Bob with a long timelock
+        assert_eq!(lock_native(&mut htlc, alice, bob, 10), Ok(0));
+        // Bob locks tokens for Alice against the same hashlock, with a shorter timelock
+        let hashlock = htlc.swap(0).unwrap().hashlock;
+        env::test::set_caller(bob);
+        assert_eq!(htlc.lock_tokens(token, alice, 500, hashlock, 50), Ok(1));
+        // Claiming the tokens reveals the preimage to Bob
+        env::test::set_caller(alice);
+        assert_eq!(htlc.claim(1, preimage(1)), Ok(()));
+        let revealed = htlc.preimage(1).unwrap();
+        env::test::set_caller(bob);
+        assert_eq!(htlc.claim(0, revealed), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 500);
+        assert_eq!(payouts(), vec![(bob, 10)]);
+    }
+}
@@ -0,0 +1,451 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the lockbox.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The number of a transfer over the bridge, counted separately in each direction.
+type Nonce = u64;
+
+/// A transfer over the bridge, which the relayer carries from one side to
+/// the other.
+///
+/// The lockbox and the mint gate must encode it the same way.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+struct BridgeMessage {
+    /// The number of the transfer on the side it was sent from.
+    nonce: Nonce,
+    /// The tokens transferred.
+    amount: Balance,
+    /// The SCALE encoded account which receives the tokens on the other side.
+    destination: Vec<u8>,
+}
+
+/// Events deposited by the lockbox.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Locked {
+        nonce: Nonce,
+        sender: AccountId,
+        amount: Balance,
+        destination: Vec<u8>,
+    },
+    Released {
+        nonce: Nonce,
+        recipient: AccountId,
+        amount: Balance,
+    },
+    RelayerChanged {
+        relayer: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the lockbox.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum LockboxError {
+    /// Nothing was locked.
+    ZeroAmount,
+    /// Only the relayer can release tokens.
+    NotRelayer,
+    /// The message has already been released.
+    AlreadyProcessed,
+    /// The destination of the message is not an account of this chain.
+    InvalidDestination,
+    /// The lockbox holds fewer tokens than the message releases.
+    InsufficientLocked,
+    /// The token refused to move the tokens into the lockbox.
+    TransferFailed,
+}
+
+/// Deposits a lockbox event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Holds ERC20 tokens while a copy of them exists on another chain.
+    ///
+    /// Users `lock` tokens together with a destination on the other chain.
+    /// The relayer carries the message of the lock over to the mint gate,
+    /// which mints the same amount of a mirrored token there. When the
+    /// mirrored tokens are burned, the relayer carries the message of the
+    /// burn back, and the lockbox releases the tokens.
+    struct Lockbox {
+        /// The ERC20 token which is locked.
+        token: storage::Value<AccountId>,
+        /// The account which carries messages between the chains.
+        relayer: storage::Value<AccountId>,
+        /// The number of locks so far, which is the nonce of the next lock.
+        lock_count: storage::Value<Nonce>,
+        /// The tokens locked and not released yet.
+        locked: storage::Value<Balance>,
+        /// The messages of all locks, for the relayer to carry over.
+        messages: storage::HashMap<Nonce, BridgeMessage>,
+        /// Whether the message of a burn with the given nonce has been released.
+        processed: storage::HashMap<Nonce, bool>,
+    }
+
+    impl Deploy for Lockbox {
+        fn deploy(&mut self, token: AccountId, relayer: AccountId) {
+            self.token.set(token);
+            self.relayer.set(relayer);
+            self.lock_count.set(0);
+            self.locked.set(0);
+        }
+    }
+
+    impl Lockbox {
+        /// Returns the account which carries messages between the chains.
+        pub(external) fn relayer(&self) -> AccountId {
+            let relayer = *self.relayer;
+            env.println(&format!("Lockbox::relayer = {:?}", relayer));
+            relayer
+        }
+
+        /// Returns the tokens locked and not released yet.
+        pub(external) fn locked(&self) -> Balance {
+            let locked = *self.locked;
+            env.println(&format!("Lockbox::locked = {:?}", locked));
+            locked
+        }
+
+        /// Returns the number of locks so far.
+        pub(external) fn lock_count(&self) -> Nonce {
+            let lock_count = *self.lock_count;
+            env.println(&format!("Lockbox::lock_count = {:?}", lock_count));
+            lock_count
+        }
+
+        /// Returns the message of the lock with the given nonce, if any.
+        pub(external) fn message(&self, nonce: Nonce) -> Option<BridgeMessage> {
+            let message = self.messages.get(&nonce).cloned();
+            env.println(&format!("Lockbox::message(nonce = {:?}) = {:?}", nonce, message));
+            message
+        }
+
+        /// Returns `true` if the message of the burn with the given nonce has
+        /// been released.
+        pub(external) fn is_processed(&self, nonce: Nonce) -> bool {
+            let processed = self.is_processed_or_false(nonce);
+            env.println(&format!("Lockbox::is_processed(nonce = {:?}) = {:?}", nonce, processed));
+            processed
+        }
+
+        /// Locks `amount` tokens of the caller, to be minted for `destination`
+        /// on the other chain, and returns the nonce of the lock.
+        ///
+        /// The caller must have approved the lockbox to transfer the tokens.
+        /// The lockbox does not check the destination, since it cannot know
+        /// what accounts on the other chain look like.
+        pub(external) fn lock(&mut self, amount: Balance, destination: Vec<u8>) -> Result<Nonce, LockboxError> {
+            if amount == 0 {
+                return Err(LockboxError::ZeroAmount)
+            }
+            let sender = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(sender, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(LockboxError::TransferFailed),
+            }
+            let nonce = *self.lock_count;
+            self.messages.insert(nonce, BridgeMessage {
+                nonce: nonce,
+                amount: amount,
+                destination: destination.clone()
+            });
+            self.lock_count += 1;
+            self.locked += amount;
+            deposit_event(Event::Locked {
+                nonce: nonce,
+                sender: sender,
+                amount: amount,
+                destination: destination
+            });
+            Ok(nonce)
+        }
+
+        /// Releases the tokens of the burn described by `message` to its destination.
+        ///
+        /// Only the relayer can release tokens, and every message only once.
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn release(&mut self, message: BridgeMessage) -> Result<(), LockboxError> {
+            if env.caller() != *self.relayer {
+                return Err(LockboxError::NotRelayer)
+            }
+            if self.is_processed_or_false(message.nonce) {
+                return Err(LockboxError::AlreadyProcessed)
+            }
+            let recipient = AccountId::decode(&mut &message.destination[..]).ok_or(LockboxError::InvalidDestination)?;
+            if message.amount > *self.locked {
+                return Err(LockboxError::InsufficientLocked)
+            }
+            self.processed.insert(message.nonce, true);
+            self.locked -= message.amount;
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(recipient, message.amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be transferred");
+            deposit_event(Event::Released {
+                nonce: message.nonce,
+                recipient: recipient,
+                amount: message.amount
+            });
+            Ok(())
+        }
+
+        /// Hands the relayer role to `relayer`.
+        ///
+        /// Only the current relayer can hand over its role.
+        pub(external) fn set_relayer(&mut self, relayer: AccountId) -> Result<(), LockboxError> {
+            if env.caller() != *self.relayer {
+                return Err(LockboxError::NotRelayer)
+            }
+            self.relayer.set(relayer);
+            deposit_event(Event::RelayerChanged {
+                relayer: relayer
+            });
+            Ok(())
+        }
+    }
+
+    impl Lockbox {
+        /// Returns whether the message with the nonce has been released or
+        /// `false` if it never was.
+        fn is_processed_or_false(&self, nonce: Nonce) -> bool {
+            *self.processed.get(&nonce).unwrap_or(&false)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the lockbox through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the lockbox first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Returns Alice, Bob and the relayer, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a lockbox for a token of which Alice and Bob hold 100 each.
+    fn deploy_mock() -> (Lockbox, Balances) {
+        let (alice, bob, relayer) = accounts();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+        balances.borrow_mut().insert(bob, 100);
+        env::test::set_caller(alice);
+        (Lockbox::deploy_mock(token, relayer), balances)
+    }
+
+    /// Returns the message of a burn on the other chain, as the mint gate
+    /// would send it back.
+    fn burn_message(nonce: Nonce, amount: Balance, recipient: AccountId) -> BridgeMessage {
+        BridgeMessage {
+            nonce: nonce,
+            amount: amount,
+            destination: recipient.encode()
+        }
+    }
+
+    #[test]
+    fn lock_works() {
+        let (alice, _, _) = accounts();
+        let (mut lockbox, balances) = deploy_mock();
+        // The destination is opaque to the lockbox
+        let destination = vec![0x42; 20];
+        assert_eq!(lockbox.lock(0, destination.clone()), Err(LockboxError::ZeroAmount));
+        assert_eq!(lockbox.lock(101, destination.clone()), Err(LockboxError::TransferFailed));
+        assert_eq!(lockbox.lock(60, destination.clone()), Ok(0));
+        assert_eq!(lockbox.lock(40, destination.clone()), Ok(1));
+        assert_eq!(balances.borrow()[&alice], 0);
+        assert_eq!(lockbox.locked(), 100);
+        assert_eq!(lockbox.lock_count(), 2);
+        assert_eq!(
+            lockbox.message(1),
+            Some(BridgeMessage {
+                nonce: 1,
+                amount: 40,
+                destination: destination
+            })
+        );
+    }
+
+    #[test]
+    fn only_the_relayer_can_release() {
+        let (alice, bob, relayer) = accounts();
+        let (mut lockbox, balances) = deploy_mock();
+        assert_eq!(lockbox.lock(100, bob.encode()), Ok(0));
+        assert_eq!(lockbox.release(burn_message(0, 100, alice)), Err(LockboxError::NotRelayer));
+        env::test::set_caller(relayer);
+        assert_eq!(lockbox.release(burn_message(0, 101, alice)), Err(LockboxError::InsufficientLocked));
+        assert_eq!(
+            lockbox.release(BridgeMessage {
+                nonce: 0,
+                amount: 100,
+                destination: vec![0x42; 20]
+            }),
+            Err(LockboxError::InvalidDestination)
+        );
+        assert_eq!(lockbox.release(burn_message(0, 100, alice)), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 100);
+        // Every message can only be released once
+        assert_eq!(lockbox.release(burn_message(0, 100, alice)), Err(LockboxError::AlreadyProcessed));
+        assert!(lockbox.is_processed(0));
+    }
+
+    #[test]
+    fn set_relayer_works() {
+        let (alice, bob, relayer) = accounts();
+        let (mut lockbox, _) = deploy_mock();
+        assert_eq!(lockbox.set_relayer(alice), Err(LockboxError::NotRelayer));
+        env::test::set_caller(relayer);
+        assert_eq!(lockbox.set_relayer(bob), Ok(()));
+        assert_eq!(lockbox.relayer(), bob);
+        assert_eq!(lockbox.set_relayer(relayer), Err(LockboxError::NotRelayer));
+    }
+
+    #[test]
+    fn relayer_round_trip_works() {
+        let (alice, bob, relayer) = accounts();
+        let (mut lockbox, balances) = deploy_mock();
+        // Alice bridges 70 tokens to Bob on the other chain
+        assert_eq!(lockbox.lock(70, bob.encode()), Ok(0));
+        // The relayer reads the lock and mints 70 mirrored tokens for Bob there
+        let lock = lockbox.message(0).unwrap();
+        assert_eq!(AccountId::decode(&mut &lock.destination[..]), Some(bob));
+        assert_eq!(lock.amount, 70);
+        // Bob burns 50 of them for Alice, and the relayer brings the burn back
+        env::test::set_caller(relayer);
+        assert_eq!(lockbox.release(burn_message(0, 50, alice)), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 80);
+        assert_eq!(lockbox.locked(), 20);
+        // The nonces of locks and burns are counted separately
+        env::test::set_caller(bob);
+        assert_eq!(lockbox.lock(10, alice.encode()), Ok(1));
+        env::test::set_caller(relayer);
+        assert_eq!(lockbox.release(burn_message(1, 30, bob)), Ok(()));
+        assert_eq!(balances.borrow()[&bob], 120);
+        assert_eq!(lockbox.locked(), 0);
+    }
+}
@@ -0,0 +1,433 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `mint` message of the ERC20 token.
+const MINT_SELECTOR: [u8; 4] = [0xCF, 0xDD, 0x9A, 0xA2];
+
+/// The selector of the `burn_from` message of the ERC20 token.
+const BURN_FROM_SELECTOR: [u8; 4] = [0x27, 0x21, 0x2B, 0xBB];
+
+/// The gas limit of cross-contract calls made by the mint gate.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The number of a transfer over the bridge, counted separately in each direction.
+type Nonce = u64;
+
+/// A transfer over the bridge, which the relayer carries from one side to
+/// the other.
+///
+/// The lockbox and the mint gate must encode it the same way.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+struct BridgeMessage {
+    /// The number of the transfer on the side it was sent from.
+    nonce: Nonce,
+    /// The tokens transferred.
+    amount: Balance,
+    /// The SCALE encoded account which receives the tokens on the other side.
+    destination: Vec<u8>,
+}
+
+/// Events deposited by the mint gate.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Minted {
+        nonce: Nonce,
+        recipient: AccountId,
+        amount: Balance,
+    },
+    Burned {
+        nonce: Nonce,
+        sender: AccountId,
+        amount: Balance,
+        destination: Vec<u8>,
+    },
+    RelayerChanged {
+        relayer: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the mint gate.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum MintGateError {
+    /// Nothing was burned.
+    ZeroAmount,
+    /// Only the relayer can mint tokens.
+    NotRelayer,
+    /// The message has already been minted.
+    AlreadyProcessed,
+    /// The destination of the message is not an account of this chain.
+    InvalidDestination,
+    /// The mirrored token refused to mint the tokens.
+    MintFailed,
+    /// The mirrored token refused to burn the tokens.
+    BurnFailed,
+}
+
+/// Deposits a mint gate event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Mints and burns a mirrored token for tokens locked in a lockbox on
+    /// another chain.
+    ///
+    /// The relayer carries the message of every lock over, and the mint
+    /// gate mints the same amount of the mirrored token for its destination.
+    /// Users `burn` mirrored tokens to get the locked tokens back, and the
+    /// relayer carries the message of the burn back to the lockbox. The
+    /// mint gate needs the `Minter` and `Burner` roles of the mirrored token.
+    struct MintGate {
+        /// The mirrored ERC20 token, which the mint gate mints and burns.
+        token: storage::Value<AccountId>,
+        /// The account which carries messages between the chains.
+        relayer: storage::Value<AccountId>,
+        /// The number of burns so far, which is the nonce of the next burn.
+        burn_count: storage::Value<Nonce>,
+        /// The mirrored tokens minted and not burned yet.
+        minted: storage::Value<Balance>,
+        /// The messages of all burns, for the relayer to carry back.
+        messages: storage::HashMap<Nonce, BridgeMessage>,
+        /// Whether the message of a lock with the given nonce has been minted.
+        processed: storage::HashMap<Nonce, bool>,
+    }
+
+    impl Deploy for MintGate {
+        fn deploy(&mut self, token: AccountId, relayer: AccountId) {
+            self.token.set(token);
+            self.relayer.set(relayer);
+            self.burn_count.set(0);
+            self.minted.set(0);
+        }
+    }
+
+    impl MintGate {
+        /// Returns the account which carries messages between the chains.
+        pub(external) fn relayer(&self) -> AccountId {
+            let relayer = *self.relayer;
+            env.println(&format!("MintGate::relayer = {:?}", relayer));
+            relayer
+        }
+
+        /// Returns the mirrored tokens minted and not burned yet.
+        pub(external) fn minted(&self) -> Balance {
+            let minted = *self.minted;
+            env.println(&format!("MintGate::minted = {:?}", minted));
+            minted
+        }
+
+        /// Returns the number of burns so far.
+        pub(external) fn burn_count(&self) -> Nonce {
+            let burn_count = *self.burn_count;
+            env.println(&format!("MintGate::burn_count = {:?}", burn_count));
+            burn_count
+        }
+
+        /// Returns the message of the burn with the given nonce, if any.
+        pub(external) fn message(&self, nonce: Nonce) -> Option<BridgeMessage> {
+            let message = self.messages.get(&nonce).cloned();
+            env.println(&format!("MintGate::message(nonce = {:?}) = {:?}", nonce, message));
+            message
+        }
+
+        /// Returns `true` if the message of the lock with the given nonce has
+        /// been minted.
+        pub(external) fn is_processed(&self, nonce: Nonce) -> bool {
+            let processed = self.is_processed_or_false(nonce);
+            env.println(&format!("MintGate::is_processed(nonce = {:?}) = {:?}", nonce, processed));
+            processed
+        }
+
+        /// Mints the mirrored tokens of the lock described by `message` for
+        /// its destination.
+        ///
+        /// Only the relayer can mint tokens, and every message only once.
+        pub(external) fn mint(&mut self, message: BridgeMessage) -> Result<(), MintGateError> {
+            if env.caller() != *self.relayer {
+                return Err(MintGateError::NotRelayer)
+            }
+            // ACTION: Return `Err(MintGateError::AlreadyProcessed)` if the message `is_processed_or_false`
+            // ACTION: Decode the `recipient` from the `destination` of the message, or return
+            //         `Err(MintGateError::InvalidDestination)`
+            // ACTION: Call `mint` on the mirrored token with `(recipient, message.amount)`
+            //   HINT: The token's `Erc20Error` is decoded as its variant index, so expect an
+            //         `Option<Result<(), u8>>`
+            // ACTION: Return `Err(MintGateError::MintFailed)` unless the token returned `Some(Ok(()))`
+            // ACTION: Mark the message as `processed`, and increase `minted` by its amount
+            deposit_event(Event::Minted {
+                nonce: message.nonce,
+                recipient: recipient,
+                amount: message.amount
+            });
+            Ok(())
+        }
+
+        /// Burns `amount` mirrored tokens of the caller, to be released for
+        /// `destination` on the other chain, and returns the nonce of the burn.
+        ///
+        /// The caller must have approved the mint gate to burn the tokens.
+        pub(external) fn burn(&mut self, amount: Balance, destination: Vec<u8>) -> Result<Nonce, MintGateError> {
+            if amount == 0 {
+                return Err(MintGateError::ZeroAmount)
+            }
+            let sender = env.caller();
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, BURN_FROM_SELECTOR, &(sender, amount).encode());
+            match result {
+                Some(Ok(())) => (),
+                _ => return Err(MintGateError::BurnFailed),
+            }
+            // ACTION: Take the current `burn_count` as the `nonce` of the burn
+            // ACTION: `insert` a `BridgeMessage` for the burn into `messages`
+            // ACTION: Increase `burn_count` by 1, and decrease `minted` by `amount`
+            deposit_event(Event::Burned {
+                nonce: nonce,
+                sender: sender,
+                amount: amount,
+                destination: destination
+            });
+            Ok(nonce)
+        }
+
+        /// Hands the relayer role to `relayer`.
+        ///
+        /// Only the current relayer can hand over its role.
+        pub(external) fn set_relayer(&mut self, relayer: AccountId) -> Result<(), MintGateError> {
+            if env.caller() != *self.relayer {
+                return Err(MintGateError::NotRelayer)
+            }
+            self.relayer.set(relayer);
+            deposit_event(Event::RelayerChanged {
+                relayer: relayer
+            });
+            Ok(())
+        }
+    }
+
+    impl MintGate {
+        /// Returns whether the message with the nonce has been minted or
+        /// `false` if it never was.
+        fn is_processed_or_false(&self, nonce: Nonce) -> bool {
+            *self.processed.get(&nonce).unwrap_or(&false)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the mint gate through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock mirrored token at `token` and returns its balances.
+    ///
+    /// The token lets anybody mint, and burn anybody's tokens, so the tests
+    /// do not have to grant roles and approve the mint gate first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                MINT_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    *balances.entry(to).or_insert(0) += value;
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                BURN_FROM_SELECTOR => {
+                    let (from, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    let balance = balances.get(&from).cloned().unwrap_or(0);
+                    if balance < value {
+                        return Some(Err::<(), u8>(0).encode())
+                    }
+                    balances.insert(from, balance - value);
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Returns Alice, Bob and the relayer, the accounts of most tests.
+    fn accounts() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a mint gate for a mirrored token nobody holds yet.
+    fn deploy_mock() -> (MintGate, Balances) {
+        let (_, _, relayer) = accounts();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        env::test::set_caller(relayer);
+        (MintGate::deploy_mock(token, relayer), balances)
+    }
+
+    /// Returns the message of a lock on the other chain, as the lockbox
+    /// would send it over.
+    fn lock_message(nonce: Nonce, amount: Balance, recipient: AccountId) -> BridgeMessage {
+        BridgeMessage {
+            nonce: nonce,
+            amount: amount,
+            destination: recipient.encode()
+        }
+    }
+
+    #[test]
+    fn only_the_relayer_can_mint() {
+        let (alice, bob, relayer) = accounts();
+        let (mut gate, balances) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Err(MintGateError::NotRelayer));
+        env::test::set_caller(relayer);
+        assert_eq!(
+            gate.mint(BridgeMessage {
+                nonce: 0,
+                amount: 100,
+                destination: vec![0x42; 20]
+            }),
+            Err(MintGateError::InvalidDestination)
+        );
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Ok(()));
+        assert_eq!(gate.mint(lock_message(1, 50, bob)), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 100);
+        assert_eq!(balances.borrow()[&bob], 50);
+        assert_eq!(gate.minted(), 150);
+        // Every message can only be minted once
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Err(MintGateError::AlreadyProcessed));
+        assert!(gate.is_processed(0));
+        assert!(!gate.is_processed(2));
+    }
+
+    #[test]
+    fn burn_works() {
+        let (alice, bob, relayer) = accounts();
+        let (mut gate, balances) = deploy_mock();
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(gate.burn(0, bob.encode()), Err(MintGateError::ZeroAmount));
+        assert_eq!(gate.burn(101, bob.encode()), Err(MintGateError::BurnFailed));
+        assert_eq!(gate.burn(60, bob.encode()), Ok(0));
+        assert_eq!(balances.borrow()[&alice], 40);
+        assert_eq!(gate.minted(), 40);
+        assert_eq!(gate.burn_count(), 1);
+        assert_eq!(
+            gate.message(0),
+            Some(BridgeMessage {
+                nonce: 0,
+                amount: 60,
+                destination: bob.encode()
+            })
+        );
+        assert_eq!(gate.relayer(), relayer);
+    }
+
+    #[test]
+    fn set_relayer_works() {
+        let (alice, bob, relayer) = accounts();
+        let (mut gate, _) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(gate.set_relayer(alice), Err(MintGateError::NotRelayer));
+        env::test::set_caller(relayer);
+        assert_eq!(gate.set_relayer(bob), Ok(()));
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Err(MintGateError::NotRelayer));
+        env::test::set_caller(bob);
+        assert_eq!(gate.mint(lock_message(0, 100, alice)), Ok(()));
+    }
+
+    #[test]
+    fn relayer_round_trip_works() {
+        let (alice, bob, relayer) = accounts();
+        let (mut gate, balances) = deploy_mock();
+        // Alice locked 70 tokens for Bob in the lockbox, and the relayer carries the lock over
+        assert_eq!(gate.mint(lock_message(0, 70, bob)), Ok(()));
+        assert_eq!(balances.borrow()[&bob], 70);
+        // Bob burns 50 mirrored tokens for Alice
+        env::test::set_caller(bob);
+        assert_eq!(gate.burn(50, alice.encode()), Ok(0));
+        // The relayer reads the burn, which releases 50 tokens to Alice in the lockbox
+        let burn = gate.message(0).unwrap();
+        assert_eq!(AccountId::decode(&mut &burn.destination[..]), Some(alice));
+        assert_eq!(burn.amount, 50);
+        // Minted tokens always match the tokens still locked
+        assert_eq!(gate.minted(), 20);
+        // A second lock arrives while the burn is on its way
+        env::test::set_caller(relayer);
+        assert_eq!(gate.mint(lock_message(1, 10, alice)), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(gate.burn(10, bob.encode()), Ok(1));
+        assert_eq!(gate.minted(), 20);
+        assert_eq!(balances.borrow()[&alice], 0);
+    }
+}
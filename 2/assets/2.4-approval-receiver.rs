@@ -0,0 +1,184 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the receiver.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+contract! {
+    /// An example approval receiver for the ERC20 token.
+    ///
+    /// Owners deposit tokens with a single `approve_and_call` on the token:
+    /// once notified of the approval, the receiver pulls the approved tokens
+    /// in and credits them to the owner.
+    struct ApprovalReceiver {
+        /// The ERC20 token this contract accepts deposits of.
+        token: storage::Value<AccountId>,
+        /// The tokens deposited by each owner.
+        deposits: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for ApprovalReceiver {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+        }
+    }
+
+    impl ApprovalReceiver {
+        /// Returns the tokens deposited by `owner`.
+        pub(external) fn deposit_of(&self, owner: AccountId) -> Balance {
+            let deposit = *self.deposits.get(&owner).unwrap_or(&0);
+            env.println(&format!("ApprovalReceiver::deposit_of(owner = {:?}) = {:?}", owner, deposit));
+            deposit
+        }
+
+        /// Called by the token once `owner` approved us to spend `value` tokens.
+        ///
+        /// Returns `true` if the approved tokens have been deposited. Returning
+        /// `false` makes the token revert the approval.
+        pub(external) fn on_approval_received(&mut self, owner: AccountId, value: Balance, data: Vec<u8>) -> bool {
+            let token = *self.token;
+            // Only the token we accept deposits of can notify us.
+            if env.caller() != token {
+                return false
+            }
+            env.println(&format!(
+                "ApprovalReceiver::on_approval_received(owner = {:?}, value = {:?}, data = {:?})",
+                owner, value, data
+            ));
+            // The token returns the remaining allowance, and its `Erc20Error`
+            // is decoded as its variant index.
+            let input = (owner, env.address(), value).encode();
+            let result: Option<Result<Balance, u8>> = call_contract(token, TRANSFER_FROM_SELECTOR, &input);
+            match result {
+                Some(Ok(_)) => (),
+                _ => return false,
+            }
+            let deposit = *self.deposits.get(&owner).unwrap_or(&0);
+            self.deposits.insert(owner, deposit + value);
+            true
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the receiver through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    #[test]
+    fn on_approval_received_deposits_the_tokens() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        // The token records every call made by the receiver
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        register_contract(token, move |selector, input| {
+            recorded.borrow_mut().push((selector, input.to_vec()));
+            Some(Ok::<Balance, u8>(0).encode())
+        });
+
+        let mut receiver = ApprovalReceiver::deploy_mock(token);
+        // The token notifies the receiver of Alice's approval
+        env::test::set_caller(token);
+        assert_eq!(receiver.on_approval_received(alice, 100, Vec::new()), true);
+        assert_eq!(receiver.deposit_of(alice), 100);
+        // The receiver pulled the approved tokens in
+        assert_eq!(*calls.borrow(), vec![
+            (TRANSFER_FROM_SELECTOR, (alice, env::address(), 100 as Balance).encode()),
+        ]);
+    }
+
+    #[test]
+    fn on_approval_received_rejects_other_callers() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        let mut receiver = ApprovalReceiver::deploy_mock(token);
+        // Alice pretends to be the token
+        env::test::set_caller(alice);
+        assert_eq!(receiver.on_approval_received(alice, 100, Vec::new()), false);
+        assert_eq!(receiver.deposit_of(alice), 0);
+    }
+
+    #[test]
+    fn on_approval_received_rejects_failed_deposits() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        // The token refuses every transfer
+        register_contract(token, move |_, _| Some(Err::<Balance, u8>(1).encode()));
+
+        let mut receiver = ApprovalReceiver::deploy_mock(token);
+        env::test::set_caller(token);
+        assert_eq!(receiver.on_approval_received(alice, 100, Vec::new()), false);
+        assert_eq!(receiver.deposit_of(alice), 0);
+    }
+}
@@ -0,0 +1,446 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The precision of the slope of the curve, which is a fixed-point number
+/// with 9 decimals.
+const SLOPE_PRECISION: Balance = 1_000_000_000;
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Events deposited by the bonding curve contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Bought {
+        buyer: AccountId,
+        amount: Balance,
+        cost: Balance,
+    },
+    Sold {
+        seller: AccountId,
+        amount: Balance,
+        refund: Balance,
+    },
+}
+
+/// Errors which can occur when calling the bonding curve contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum CurveError {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// Buying or selling zero tokens does nothing.
+    ZeroAmount,
+    /// The price does not fit into a `Balance`.
+    Overflow,
+    /// The cost is above the maximum or the refund below the minimum of the caller.
+    SlippageExceeded,
+    /// Less native balance was sent along with the call than the tokens cost.
+    InsufficientValue,
+}
+
+/// Deposits a bonding curve event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the area under the curve `base_price + slope * supply ^ exponent`
+/// from a supply of zero up to `supply`, rounded down.
+///
+/// This is the native balance the contract holds when `supply` tokens exist.
+/// Buying and selling only ever move between two of these areas, so the
+/// reserve always matches the supply, whatever order the trades came in.
+fn area(base_price: Balance, slope: Balance, exponent: u32, supply: Balance) -> Option<Balance> {
+    let flat = base_price.checked_mul(supply)?;
+    let curved = supply
+        .checked_pow(exponent + 1)?
+        .checked_mul(slope)?
+        / (Balance::from(exponent + 1) * SLOPE_PRECISION);
+    flat.checked_add(curved)
+}
+
+contract! {
+    /// A token which is bought from and sold back to a bonding curve.
+    ///
+    /// The price of the next token is `base_price + slope * supply ^ exponent`,
+    /// so every purchase makes the following ones more expensive. Buyers pay
+    /// native balance into a reserve held by the contract, and sellers burn
+    /// their tokens to be paid out of it along the same curve.
+    struct BondingCurve {
+        /// The price of the very first token.
+        base_price: storage::Value<Balance>,
+        /// How steeply the price rises with the supply.
+        slope: storage::Value<Balance>,
+        /// The power of the supply in the price: 1 for a linear curve, 2 for a
+        /// quadratic one and so on.
+        exponent: storage::Value<u32>,
+        /// The native balance paid for the tokens in existence.
+        reserve: storage::Value<Balance>,
+        /// The total supply.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for BondingCurve {
+        fn deploy(&mut self, base_price: Balance, slope: Balance, exponent: u32) {
+            self.base_price.set(base_price);
+            self.slope.set(slope);
+            self.exponent.set(exponent);
+            self.reserve.set(0);
+            self.total_supply.set(0);
+        }
+    }
+
+    impl BondingCurve {
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("BondingCurve::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("BondingCurve::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the native balance held for the tokens in existence.
+        pub(external) fn reserve(&self) -> Balance {
+            let reserve = *self.reserve;
+            env.println(&format!("BondingCurve::reserve = {:?}", reserve));
+            reserve
+        }
+
+        /// Returns the price of the next token at the current supply, rounded down.
+        pub(external) fn spot_price(&self) -> Result<Balance, CurveError> {
+            let price = self.spot_price_at(*self.total_supply);
+            env.println(&format!("BondingCurve::spot_price = {:?}", price));
+            price
+        }
+
+        /// Returns the native balance it costs to buy `amount` tokens right now.
+        pub(external) fn buy_cost(&self, amount: Balance) -> Result<Balance, CurveError> {
+            let cost = self.buy_cost_of(amount);
+            env.println(&format!("BondingCurve::buy_cost(amount = {:?}) = {:?}", amount, cost));
+            cost
+        }
+
+        /// Returns the native balance selling `amount` tokens pays out right now.
+        pub(external) fn sell_refund(&self, amount: Balance) -> Result<Balance, CurveError> {
+            let refund = self.sell_refund_of(amount);
+            env.println(&format!("BondingCurve::sell_refund(amount = {:?}) = {:?}", amount, refund));
+            refund
+        }
+
+        /// Transfers token from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), CurveError> {
+            let from = env.caller();
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(CurveError::InsufficientBalance)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Mints `amount` tokens to the caller for the native balance sent along
+        /// with the call.
+        ///
+        /// Fails if the tokens cost more than `max_cost`, in case other purchases
+        /// moved the price first. Whatever was sent on top of the cost is paid
+        /// back. If that payout fails, the whole call is reverted.
+        pub(external) fn buy(&mut self, amount: Balance, max_cost: Balance) -> Result<Balance, CurveError> {
+            let cost = self.buy_cost_of(amount)?;
+            if cost > max_cost {
+                return Err(CurveError::SlippageExceeded)
+            }
+            let value = value_transferred();
+            if value < cost {
+                return Err(CurveError::InsufficientValue)
+            }
+            let buyer = env.caller();
+            let balance = self.balance_of_or_zero(&buyer);
+            self.balances.insert(buyer, balance + amount);
+            self.total_supply += amount;
+            self.reserve += cost;
+            if value > cost {
+                assert!(transfer_native(buyer, value - cost), "the change could not be paid back");
+            }
+            deposit_event(Event::Bought {
+                buyer: buyer,
+                amount: amount,
+                cost: cost
+            });
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(buyer),
+                value: amount
+            });
+            Ok(cost)
+        }
+
+        /// Burns `amount` tokens of the caller and pays out their value along
+        /// the curve.
+        ///
+        /// Fails if the refund is less than `min_refund`, in case other sales
+        /// moved the price first. If the payout fails, the whole call is reverted.
+        pub(external) fn sell(&mut self, amount: Balance, min_refund: Balance) -> Result<Balance, CurveError> {
+            let seller = env.caller();
+            let balance = self.balance_of_or_zero(&seller);
+            if balance < amount {
+                return Err(CurveError::InsufficientBalance)
+            }
+            let refund = self.sell_refund_of(amount)?;
+            if refund < min_refund {
+                return Err(CurveError::SlippageExceeded)
+            }
+            self.balances.insert(seller, balance - amount);
+            self.total_supply -= amount;
+            self.reserve -= refund;
+            assert!(transfer_native(seller, refund), "the payout failed");
+            deposit_event(Event::Sold {
+                seller: seller,
+                amount: amount,
+                refund: refund
+            });
+            deposit_event(Event::Transfer {
+                from: Some(seller),
+                to: None,
+                value: amount
+            });
+            Ok(refund)
+        }
+    }
+
+    impl BondingCurve {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the area under the curve up to `supply`.
+        fn area_at(&self, supply: Balance) -> Result<Balance, CurveError> {
+            area(*self.base_price, *self.slope, *self.exponent, supply).ok_or(CurveError::Overflow)
+        }
+
+        /// Returns the price of the next token at `supply`, rounded down.
+        fn spot_price_at(&self, supply: Balance) -> Result<Balance, CurveError> {
+            supply
+                .checked_pow(*self.exponent)
+                .and_then(|power| power.checked_mul(*self.slope))
+                .and_then(|curved| self.base_price.checked_add(curved / SLOPE_PRECISION))
+                .ok_or(CurveError::Overflow)
+        }
+
+        /// Returns the cost of minting `amount` tokens on top of the current supply.
+        fn buy_cost_of(&self, amount: Balance) -> Result<Balance, CurveError> {
+            if amount == 0 {
+                return Err(CurveError::ZeroAmount)
+            }
+            let supply = *self.total_supply;
+            let new_supply = supply.checked_add(amount).ok_or(CurveError::Overflow)?;
+            Ok(self.area_at(new_supply)? - *self.reserve)
+        }
+
+        /// Returns the refund of burning `amount` tokens of the current supply.
+        fn sell_refund_of(&self, amount: Balance) -> Result<Balance, CurveError> {
+            if amount == 0 {
+                return Err(CurveError::ZeroAmount)
+            }
+            let supply = *self.total_supply;
+            let new_supply = supply.checked_sub(amount).ok_or(CurveError::InsufficientBalance)?;
+            Ok(*self.reserve - self.area_at(new_supply)?)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Buys `amount` tokens, sending exactly `value` of native balance along.
+    fn buy(curve: &mut BondingCurve, amount: Balance, value: Balance) -> Result<Balance, CurveError> {
+        set_value_transferred(value);
+        let result = curve.buy(amount, value);
+        set_value_transferred(0);
+        result
+    }
+
+    #[test]
+    fn linear_prices_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The price starts at 10 and rises by 2 with every token
+        let curve = BondingCurve::deploy_mock(10, 2 * SLOPE_PRECISION, 1);
+        assert_eq!(curve.spot_price(), Ok(10));
+        // 10 * 10 + 2 * 10 ^ 2 / 2
+        assert_eq!(curve.buy_cost(10), Ok(200));
+        assert_eq!(curve.buy_cost(20), Ok(600));
+        assert_eq!(curve.buy_cost(0), Err(CurveError::ZeroAmount));
+        assert_eq!(curve.sell_refund(1), Err(CurveError::InsufficientBalance));
+    }
+
+    #[test]
+    fn polynomial_prices_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The price is 3 * supply ^ 2, so the area is supply ^ 3
+        let mut curve = BondingCurve::deploy_mock(0, 3 * SLOPE_PRECISION, 2);
+        assert_eq!(curve.buy_cost(10), Ok(1000));
+        assert_eq!(buy(&mut curve, 10, 1000), Ok(1000));
+        assert_eq!(curve.spot_price(), Ok(300));
+        assert_eq!(curve.buy_cost(10), Ok(7000));
+        assert_eq!(curve.buy_cost(Balance::max_value()), Err(CurveError::Overflow));
+    }
+
+    #[test]
+    fn buy_and_sell_round_trip() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut curve = BondingCurve::deploy_mock(10, 2 * SLOPE_PRECISION, 1);
+        assert_eq!(buy(&mut curve, 10, 200), Ok(200));
+        env::test::set_caller(bob);
+        assert_eq!(buy(&mut curve, 10, 400), Ok(400));
+        assert_eq!(curve.total_supply(), 20);
+        assert_eq!(curve.reserve(), 600);
+        // Bob sells at the top of the curve and gets back what he paid
+        assert_eq!(curve.sell(10, 400), Ok(400));
+        env::test::set_caller(alice);
+        assert_eq!(curve.sell(10, 200), Ok(200));
+        assert_eq!(curve.total_supply(), 0);
+        assert_eq!(curve.reserve(), 0);
+        assert_eq!(payouts(), vec![(bob, 400), (alice, 200)]);
+    }
+
+    #[test]
+    fn rounding_keeps_the_reserve_solvent() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The price rises by a third with every token
+        let mut curve = BondingCurve::deploy_mock(0, SLOPE_PRECISION / 3, 1);
+        for _ in 0..5 {
+            let cost = curve.buy_cost(1).unwrap();
+            assert_eq!(buy(&mut curve, 1, cost), Ok(cost));
+        }
+        // Selling one by one pays out exactly what was paid in
+        let reserve = curve.reserve();
+        let mut refunds = 0;
+        for _ in 0..5 {
+            refunds += curve.sell(1, 0).unwrap();
+        }
+        assert_eq!(refunds, reserve);
+        assert_eq!(curve.reserve(), 0);
+    }
+
+    #[test]
+    fn slippage_protection_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut curve = BondingCurve::deploy_mock(10, 2 * SLOPE_PRECISION, 1);
+        assert_eq!(buy(&mut curve, 10, 199), Err(CurveError::SlippageExceeded));
+        // Sending more than the maximum cost does not help, the maximum counts
+        set_value_transferred(500);
+        assert_eq!(curve.buy(10, 199), Err(CurveError::SlippageExceeded));
+        // Sending too little fails even with a generous maximum
+        set_value_transferred(100);
+        assert_eq!(curve.buy(10, 500), Err(CurveError::InsufficientValue));
+        // The change is paid back
+        set_value_transferred(500);
+        assert_eq!(curve.buy(10, 500), Ok(200));
+        set_value_transferred(0);
+        assert_eq!(payouts(), vec![(alice, 300)]);
+        assert_eq!(curve.sell(10, 201), Err(CurveError::SlippageExceeded));
+        assert_eq!(curve.sell(11, 0), Err(CurveError::InsufficientBalance));
+        assert_eq!(curve.balance_of(alice), 10);
+    }
+}
@@ -0,0 +1,584 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of the ERC20 token.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The gas limit of cross-contract calls made by the crowdsale.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Events deposited by the crowdsale contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    TokensPurchased {
+        buyer: AccountId,
+        value: Balance,
+        tokens: Balance,
+    },
+    TokensClaimed {
+        buyer: AccountId,
+        tokens: Balance,
+    },
+    Refunded {
+        buyer: AccountId,
+        value: Balance,
+    },
+    Finalized {
+        raised: Balance,
+        unsold: Balance,
+    },
+}
+
+/// Errors which can occur when calling the crowdsale contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum CrowdsaleError {
+    /// The sale has ended.
+    SaleEnded,
+    /// The sale is still running.
+    SaleNotEnded,
+    /// No native balance was sent along with the purchase.
+    ZeroValue,
+    /// The purchase would raise more than the hard cap.
+    HardCapExceeded,
+    /// The purchase would take the buyer over the per-account limit.
+    PurchaseLimitExceeded,
+    /// The crowdsale does not hold enough tokens for the purchase.
+    InsufficientTokens,
+    /// The sale failed to raise the soft cap, so tokens are not delivered.
+    SoftCapNotReached,
+    /// The sale raised the soft cap, so contributions are not refunded.
+    SoftCapReached,
+    /// The caller has nothing to claim or to be refunded.
+    NothingToClaim,
+    /// Only the owner can finalize the sale.
+    NotOwner,
+    /// The sale has already been finalized.
+    AlreadyFinalized,
+}
+
+/// Deposits a crowdsale event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Sells an allocation of ERC20 tokens for native balance at a fixed rate.
+    ///
+    /// The owner transfers the allocation to the crowdsale before it starts.
+    /// Buyers contribute native balance until the deadline or until the hard
+    /// cap is raised, each up to `max_purchase`. If the sale raises at least
+    /// the soft cap, buyers claim their tokens and the owner receives the
+    /// proceeds. Otherwise, buyers get their contributions back.
+    struct Crowdsale {
+        /// The account which receives the proceeds and the unsold tokens.
+        owner: storage::Value<AccountId>,
+        /// The ERC20 token which is sold.
+        token: storage::Value<AccountId>,
+        /// The number of tokens a unit of native balance buys.
+        rate: storage::Value<Balance>,
+        /// The native balance the sale has to raise to succeed.
+        soft_cap: storage::Value<Balance>,
+        /// The most native balance the sale accepts.
+        hard_cap: storage::Value<Balance>,
+        /// The most native balance a single account can contribute.
+        max_purchase: storage::Value<Balance>,
+        /// The last block in which tokens can be bought.
+        deadline: storage::Value<BlockNumber>,
+        /// The native balance raised so far.
+        raised: storage::Value<Balance>,
+        /// The tokens bought which have not been claimed yet.
+        tokens_owed: storage::Value<Balance>,
+        /// The native balance contributed by each buyer which has not been
+        /// turned into tokens or refunded yet.
+        contributions: storage::HashMap<AccountId, Balance>,
+        /// The native balance contributed by each buyer over the whole sale.
+        purchased: storage::HashMap<AccountId, Balance>,
+        /// Whether the owner has been paid out.
+        finalized: storage::Value<bool>,
+    }
+
+    impl Deploy for Crowdsale {
+        fn deploy(
+            &mut self,
+            token: AccountId,
+            rate: Balance,
+            soft_cap: Balance,
+            hard_cap: Balance,
+            max_purchase: Balance,
+            duration: BlockNumber,
+        ) {
+            assert!(rate > 0, "tokens must not be given away for free");
+            assert!(soft_cap <= hard_cap, "the soft cap must be reachable");
+            self.owner.set(env.caller());
+            self.token.set(token);
+            self.rate.set(rate);
+            self.soft_cap.set(soft_cap);
+            self.hard_cap.set(hard_cap);
+            self.max_purchase.set(max_purchase);
+            self.deadline.set(env::block_number() + duration);
+            self.raised.set(0);
+            self.tokens_owed.set(0);
+            self.finalized.set(false);
+        }
+    }
+
+    impl Crowdsale {
+        /// Returns the native balance raised so far.
+        pub(external) fn raised(&self) -> Balance {
+            let raised = *self.raised;
+            env.println(&format!("Crowdsale::raised = {:?}", raised));
+            raised
+        }
+
+        /// Returns the last block in which tokens can be bought.
+        pub(external) fn deadline(&self) -> BlockNumber {
+            let deadline = *self.deadline;
+            env.println(&format!("Crowdsale::deadline = {:?}", deadline));
+            deadline
+        }
+
+        /// Returns the native balance `buyer` contributed which has not been
+        /// turned into tokens or refunded yet.
+        pub(external) fn contribution_of(&self, buyer: AccountId) -> Balance {
+            let contribution = self.contribution_of_or_zero(&buyer);
+            env.println(&format!("Crowdsale::contribution_of(buyer = {:?}) = {:?}", buyer, contribution));
+            contribution
+        }
+
+        /// Returns whether the sale no longer accepts purchases.
+        pub(external) fn has_ended(&self) -> bool {
+            let ended = self.has_ended_impl();
+            env.println(&format!("Crowdsale::has_ended = {:?}", ended));
+            ended
+        }
+
+        /// Buys tokens at the rate with the native balance sent along with the
+        /// call. The tokens can be claimed once the sale has succeeded.
+        pub(external) fn buy(&mut self) -> Result<Balance, CrowdsaleError> {
+            if self.has_ended_impl() {
+                return Err(CrowdsaleError::SaleEnded)
+            }
+            let value = value_transferred();
+            if value == 0 {
+                return Err(CrowdsaleError::ZeroValue)
+            }
+            let raised = self.raised.checked_add(value).ok_or(CrowdsaleError::HardCapExceeded)?;
+            if raised > *self.hard_cap {
+                return Err(CrowdsaleError::HardCapExceeded)
+            }
+            let buyer = env.caller();
+            let purchased = *self.purchased.get(&buyer).unwrap_or(&0) + value;
+            if purchased > *self.max_purchase {
+                return Err(CrowdsaleError::PurchaseLimitExceeded)
+            }
+            let tokens = value.checked_mul(*self.rate).ok_or(CrowdsaleError::InsufficientTokens)?;
+            let tokens_owed = *self.tokens_owed + tokens;
+            if self.token_balance() < tokens_owed {
+                return Err(CrowdsaleError::InsufficientTokens)
+            }
+            self.raised.set(raised);
+            self.tokens_owed.set(tokens_owed);
+            self.purchased.insert(buyer, purchased);
+            let contribution = self.contribution_of_or_zero(&buyer);
+            self.contributions.insert(buyer, contribution + value);
+            deposit_event(Event::TokensPurchased {
+                buyer: buyer,
+                value: value,
+                tokens: tokens
+            });
+            Ok(tokens)
+        }
+
+        /// Delivers the tokens bought by the caller once the sale has ended
+        /// and raised the soft cap.
+        ///
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn claim_tokens(&mut self) -> Result<Balance, CrowdsaleError> {
+            self.ensure_ended()?;
+            if *self.raised < *self.soft_cap {
+                return Err(CrowdsaleError::SoftCapNotReached)
+            }
+            let buyer = env.caller();
+            let contribution = self.contribution_of_or_zero(&buyer);
+            if contribution == 0 {
+                return Err(CrowdsaleError::NothingToClaim)
+            }
+            let tokens = contribution * *self.rate;
+            self.contributions.insert(buyer, 0);
+            self.tokens_owed -= tokens;
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(buyer, tokens).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be delivered");
+            deposit_event(Event::TokensClaimed {
+                buyer: buyer,
+                tokens: tokens
+            });
+            Ok(tokens)
+        }
+
+        /// Pays the contribution of the caller back once the sale has ended
+        /// without raising the soft cap.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn refund(&mut self) -> Result<Balance, CrowdsaleError> {
+            self.ensure_ended()?;
+            if *self.raised >= *self.soft_cap {
+                return Err(CrowdsaleError::SoftCapReached)
+            }
+            let buyer = env.caller();
+            let contribution = self.contribution_of_or_zero(&buyer);
+            if contribution == 0 {
+                return Err(CrowdsaleError::NothingToClaim)
+            }
+            self.contributions.insert(buyer, 0);
+            self.tokens_owed -= contribution * *self.rate;
+            assert!(transfer_native(buyer, contribution), "the refund failed");
+            deposit_event(Event::Refunded {
+                buyer: buyer,
+                value: contribution
+            });
+            Ok(contribution)
+        }
+
+        /// Pays the proceeds of a successful sale to the owner and returns the
+        /// unsold tokens.
+        ///
+        /// After a failed sale, only the tokens are returned, since the
+        /// contributions belong to the buyers. Only the owner can finalize.
+        pub(external) fn finalize(&mut self) -> Result<(), CrowdsaleError> {
+            let owner = env.caller();
+            if owner != *self.owner {
+                return Err(CrowdsaleError::NotOwner)
+            }
+            self.ensure_ended()?;
+            if *self.finalized {
+                return Err(CrowdsaleError::AlreadyFinalized)
+            }
+            self.finalized.set(true);
+            let successful = *self.raised >= *self.soft_cap;
+            // Tokens of a failed sale are never claimed, so none are owed.
+            let owed = if successful { *self.tokens_owed } else { 0 };
+            let unsold = self.token_balance() - owed;
+            if unsold > 0 {
+                let result: Option<Result<(), u8>> =
+                    call_contract(*self.token, TRANSFER_SELECTOR, &(owner, unsold).encode());
+                assert_eq!(result, Some(Ok(())), "the unsold tokens could not be returned");
+            }
+            let raised = if successful { *self.raised } else { 0 };
+            if raised > 0 {
+                assert!(transfer_native(owner, raised), "the payout failed");
+            }
+            deposit_event(Event::Finalized {
+                raised: raised,
+                unsold: unsold
+            });
+            Ok(())
+        }
+    }
+
+    impl Crowdsale {
+        /// Returns the contribution of the AccountId or 0 if there is none.
+        fn contribution_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.contributions.get(of).unwrap_or(&0)
+        }
+
+        /// Returns whether the deadline has passed or the hard cap has been raised.
+        fn has_ended_impl(&self) -> bool {
+            env::block_number() > *self.deadline || *self.raised >= *self.hard_cap
+        }
+
+        /// Returns an error while the sale is still running.
+        fn ensure_ended(&self) -> Result<(), CrowdsaleError> {
+            if !self.has_ended_impl() {
+                return Err(CrowdsaleError::SaleNotEnded)
+            }
+            Ok(())
+        }
+
+        /// Asks the token how many tokens the crowdsale holds.
+        fn token_balance(&self) -> Balance {
+            call_contract(*self.token, BALANCE_OF_SELECTOR, &env::address().encode())
+                .expect("the token reports the balance of the crowdsale")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the crowdsale through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock ERC20 token at `token` which holds `allocation` tokens
+    /// for the crowdsale, and returns its balances.
+    fn register_token(token: AccountId, allocation: Balance) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        balances.borrow_mut().insert(env::address(), allocation);
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..]).unwrap();
+                    Some(balances.get(&owner).cloned().unwrap_or(0).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    let balance = balances.get(&env::address()).cloned().unwrap_or(0);
+                    if balance < value {
+                        return Some(Err::<(), u8>(0).encode())
+                    }
+                    balances.insert(env::address(), balance - value);
+                    *balances.entry(to).or_insert(0) += value;
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Buys tokens for `buyer` with `value` of native balance.
+    fn buy(crowdsale: &mut Crowdsale, buyer: AccountId, value: Balance) -> Result<Balance, CrowdsaleError> {
+        env::test::set_caller(buyer);
+        set_value_transferred(value);
+        let result = crowdsale.buy();
+        set_value_transferred(0);
+        result
+    }
+
+    /// Deploys a crowdsale at block 0 which sells 10 tokens per unit until
+    /// block 100, with a soft cap of 500, a hard cap of 1000 and a limit of
+    /// 600 per account, and registers a token which allocates it 10000 tokens.
+    fn deploy_mock() -> (Crowdsale, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let balances = register_token(token, 10000);
+        (Crowdsale::deploy_mock(token, 10, 500, 1000, 600, 100), balances)
+    }
+
+    #[test]
+    fn buy_works() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut crowdsale, balances) = deploy_mock();
+        assert_eq!(buy(&mut crowdsale, bob, 0), Err(CrowdsaleError::ZeroValue));
+        assert_eq!(buy(&mut crowdsale, bob, 100), Ok(1000));
+        assert_eq!(crowdsale.contribution_of(bob), 100);
+        assert_eq!(crowdsale.raised(), 100);
+        // Tokens are only delivered once the sale has succeeded
+        assert_eq!(balances.borrow().get(&bob), None);
+        assert_eq!(crowdsale.claim_tokens(), Err(CrowdsaleError::SaleNotEnded));
+    }
+
+    #[test]
+    fn purchases_are_limited_per_account() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut crowdsale, _) = deploy_mock();
+        assert_eq!(buy(&mut crowdsale, bob, 601), Err(CrowdsaleError::PurchaseLimitExceeded));
+        assert_eq!(buy(&mut crowdsale, bob, 400), Ok(4000));
+        assert_eq!(buy(&mut crowdsale, bob, 201), Err(CrowdsaleError::PurchaseLimitExceeded));
+        assert_eq!(buy(&mut crowdsale, bob, 200), Ok(2000));
+        assert_eq!(crowdsale.contribution_of(bob), 600);
+    }
+
+    #[test]
+    fn hard_cap_ends_the_sale() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let (mut crowdsale, _) = deploy_mock();
+        assert_eq!(buy(&mut crowdsale, bob, 600), Ok(6000));
+        assert_eq!(buy(&mut crowdsale, charlie, 401), Err(CrowdsaleError::HardCapExceeded));
+        assert_eq!(buy(&mut crowdsale, charlie, 400), Ok(4000));
+        // The sale ends as soon as the hard cap is raised
+        assert!(crowdsale.has_ended());
+        assert_eq!(buy(&mut crowdsale, dave, 1), Err(CrowdsaleError::SaleEnded));
+    }
+
+    #[test]
+    fn purchases_are_limited_by_the_allocation() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        // The owner only handed over enough tokens for 300 units
+        register_token(token, 3000);
+        let mut crowdsale = Crowdsale::deploy_mock(token, 10, 500, 1000, 600, 100);
+        assert_eq!(buy(&mut crowdsale, bob, 301), Err(CrowdsaleError::InsufficientTokens));
+        assert_eq!(buy(&mut crowdsale, bob, 300), Ok(3000));
+    }
+
+    #[test]
+    fn successful_sales_deliver_tokens() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut crowdsale, balances) = deploy_mock();
+        assert_eq!(buy(&mut crowdsale, bob, 300), Ok(3000));
+        assert_eq!(buy(&mut crowdsale, charlie, 200), Ok(2000));
+        env::test::set_block_number(101);
+        assert_eq!(buy(&mut crowdsale, charlie, 100), Err(CrowdsaleError::SaleEnded));
+        // The soft cap has been raised, so there are no refunds
+        assert_eq!(crowdsale.refund(), Err(CrowdsaleError::SoftCapReached));
+        assert_eq!(crowdsale.claim_tokens(), Ok(2000));
+        assert_eq!(crowdsale.claim_tokens(), Err(CrowdsaleError::NothingToClaim));
+        assert_eq!(balances.borrow()[&charlie], 2000);
+        // The owner gets the proceeds and the unsold tokens, but not Bob's tokens
+        assert_eq!(crowdsale.finalize(), Err(CrowdsaleError::NotOwner));
+        env::test::set_caller(alice);
+        assert_eq!(crowdsale.finalize(), Ok(()));
+        assert_eq!(crowdsale.finalize(), Err(CrowdsaleError::AlreadyFinalized));
+        assert_eq!(payouts(), vec![(alice, 500)]);
+        assert_eq!(balances.borrow()[&alice], 5000);
+        env::test::set_caller(bob);
+        assert_eq!(crowdsale.claim_tokens(), Ok(3000));
+        assert_eq!(balances.borrow()[&bob], 3000);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+
+    #[test]
+    fn failed_sales_refund_buyers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut crowdsale, balances) = deploy_mock();
+        assert_eq!(buy(&mut crowdsale, bob, 300), Ok(3000));
+        assert_eq!(buy(&mut crowdsale, charlie, 199), Ok(1990));
+        assert_eq!(crowdsale.refund(), Err(CrowdsaleError::SaleNotEnded));
+        env::test::set_block_number(101);
+        // One unit short of the soft cap
+        assert_eq!(crowdsale.claim_tokens(), Err(CrowdsaleError::SoftCapNotReached));
+        assert_eq!(crowdsale.refund(), Ok(199));
+        assert_eq!(crowdsale.refund(), Err(CrowdsaleError::NothingToClaim));
+        // The owner gets every token back, but none of the contributions
+        env::test::set_caller(alice);
+        assert_eq!(crowdsale.finalize(), Ok(()));
+        assert_eq!(balances.borrow()[&alice], 10000);
+        env::test::set_caller(bob);
+        assert_eq!(crowdsale.refund(), Ok(300));
+        assert_eq!(payouts(), vec![(charlie, 199), (bob, 300)]);
+    }
+}
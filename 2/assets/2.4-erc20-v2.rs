@@ -0,0 +1,351 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::{
+        format,
+        string::String,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The version of the storage layout this code works with.
+const STORAGE_VERSION: u32 = 2;
+
+/// The storage version of the ERC20 token this code is upgraded from.
+const PREVIOUS_STORAGE_VERSION: u32 = 1;
+
+/// The semantic version of this contract.
+const VERSION: &str = "2.0.0";
+
+/// The roles of the previous version, which are part of its storage layout.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Role {
+    Admin,
+    Minter,
+    Pauser,
+    Burner,
+    MintSigner,
+    Locker,
+    Guardian,
+}
+
+/// The mint proposals of the previous version, which are part of its storage layout.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct MintProposal {
+    to: AccountId,
+    value: Balance,
+    approvals: u32,
+    executed: bool,
+}
+
+contract! {
+    /// An example second version of the ERC20 token, which the token can be
+    /// upgraded to with `upgrade`.
+    ///
+    /// Storage is laid out in the order the fields are declared, so the fields
+    /// below have to match the fields of the previous version exactly, in the
+    /// same order. Fields this version needs on top of them would have to be
+    /// appended at the end, so that the storage of the previous version stays
+    /// where it is.
+    ///
+    /// A real upgrade would carry every message of the previous version over.
+    /// To keep this example short, it only shows the storage check, the
+    /// migration and one new message.
+    struct Erc20 {
+        /// The name of the token.
+        name: storage::Value<String>,
+        /// The symbol of the token.
+        symbol: storage::Value<String>,
+        /// The number of decimals used to display token amounts.
+        decimals: storage::Value<u8>,
+        /// The account which is allowed to perform privileged operations.
+        owner: storage::Value<AccountId>,
+        /// The account which has been proposed as the next owner, if any.
+        pending_owner: storage::Value<Option<AccountId>>,
+        /// The total supply.
+        total_supply: storage::Value<Balance>,
+        /// The total number of tokens which have been burned.
+        total_burned: storage::Value<Balance>,
+        /// The maximum total supply that can ever be in existence.
+        cap: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+        /// Whether transfers and approvals are currently halted.
+        paused: storage::Value<bool>,
+        /// The roles held by each account: (role, account) -> granted
+        roles: storage::HashMap<(Role, AccountId), bool>,
+        /// Accounts which are barred from sending and receiving tokens.
+        frozen: storage::HashMap<AccountId, bool>,
+        /// The fee charged on every transfer in basis points of the transferred value.
+        fee_bps: storage::Value<u16>,
+        /// The account which collects the transfer fees.
+        fee_recipient: storage::Value<AccountId>,
+        /// The number of signed messages consumed on behalf of each account.
+        nonces: storage::HashMap<AccountId, u64>,
+        /// The account each account has delegated its voting power to.
+        delegates: storage::HashMap<AccountId, AccountId>,
+        /// The voting power history of each delegate: (delegate, index) -> (block, votes)
+        checkpoints: storage::HashMap<(AccountId, u32), (BlockNumber, Balance)>,
+        /// The number of checkpoints recorded for each delegate.
+        num_checkpoints: storage::HashMap<AccountId, u32>,
+        /// The id of the most recent snapshot, or 0 if none has been taken yet.
+        current_snapshot_id: storage::Value<u32>,
+        /// Values recorded for snapshots, keyed by account or `None` for the
+        /// total supply: (key, index) -> (snapshot id, value)
+        snapshots: storage::HashMap<(Option<AccountId>, u32), (u32, Balance)>,
+        /// The number of values recorded for each snapshot key.
+        snapshot_counts: storage::HashMap<Option<AccountId>, u32>,
+        /// Whether the contract is in the middle of a cross-contract call.
+        locked: storage::Value<bool>,
+        /// Whether `approve` refuses to change one non-zero allowance into another.
+        strict_approvals: storage::Value<bool>,
+        /// The share of every transfer which is burned in basis points of the transferred value.
+        burn_bps: storage::Value<u16>,
+        /// Every account with a non-zero balance, in no particular order.
+        holders: storage::Vec<AccountId>,
+        /// The position of each holder in `holders`.
+        holder_indices: storage::HashMap<AccountId, u32>,
+        /// The number of accounts with a non-zero balance.
+        holder_count: storage::Value<u32>,
+        /// The last block in which allowances with a deadline can be spent: (owner, spender) -> block
+        allowance_deadlines: storage::HashMap<(AccountId, AccountId), BlockNumber>,
+        /// The maximum amount any account may send within a window of blocks:
+        /// (max amount, window length), or `None` if transfers are not limited.
+        rate_limit: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The current window of each sender: account -> (first block, amount sent)
+        rate_windows: storage::HashMap<AccountId, (BlockNumber, Balance)>,
+        /// The number of signer approvals a mint proposal needs to be carried out.
+        mint_threshold: storage::Value<u32>,
+        /// The mint proposals made so far, by id.
+        mint_proposals: storage::HashMap<u32, MintProposal>,
+        /// The number of mint proposals made so far, which is also the next id.
+        mint_proposal_count: storage::Value<u32>,
+        /// The signers who approved each mint proposal: (id, signer) -> approved
+        mint_approvals: storage::HashMap<(u32, AccountId), bool>,
+        /// The portion of each account's balance which cannot be spent before a block:
+        /// account -> (locked amount, first block in which the tokens are free)
+        locks: storage::HashMap<AccountId, (Balance, BlockNumber)>,
+        /// The version of the storage layout, which stays in the same place across versions.
+        storage_version: storage::Value<u32>,
+        /// Whether `migrate_balances` has been closed for good.
+        migration_finalized: storage::Value<bool>,
+        /// Whether tokens may be sent to the address of the token contract itself.
+        allow_transfers_to_contract: storage::Value<bool>,
+        /// The tokens emitted per era and the length of an era in blocks,
+        /// or `None` if there is no inflation.
+        emission_schedule: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The block in which the current, not yet accrued era started.
+        last_accrual: storage::Value<BlockNumber>,
+        /// The emission which has been accrued but not claimed yet.
+        accrued_emission: storage::Value<Balance>,
+        /// The maximum amount a single transfer may move, or `None` if transfers are not limited.
+        max_transfer_amount: storage::Value<Option<Balance>>,
+        /// Accounts whose transfers are not subject to `max_transfer_amount`.
+        exempt: storage::HashMap<AccountId, bool>,
+        /// Whether transfers have been halted by the circuit breaker.
+        circuit_tripped: storage::Value<bool>,
+        /// The transfer volume which trips the circuit breaker within a window of blocks:
+        /// (max volume, window length), or `None` if it only trips manually.
+        circuit_threshold: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The current transfer volume window: (first block, volume)
+        volume_window: storage::Value<(BlockNumber, Balance)>,
+        /// The maximum fee the relayer of a permit can claim from the owner.
+        max_relayer_fee: storage::Value<Balance>,
+        /// The spenders with a non-zero allowance of each owner: (owner, index) -> spender
+        spenders: storage::HashMap<(AccountId, u32), AccountId>,
+        /// The position of each spender in the list of its owner: (owner, spender) -> index
+        spender_indices: storage::HashMap<(AccountId, AccountId), u32>,
+        /// The number of spenders with a non-zero allowance of each owner.
+        spender_counts: storage::HashMap<AccountId, u32>,
+        /// Whether rejected transfers and approvals deposit a `Failure` event.
+        report_failures: storage::Value<bool>,
+        /// Whether the owner has renounced ownership, leaving the contract without one.
+        ownership_renounced: storage::Value<bool>,
+        /// Whether approvals are limited to the spenders on the allow-list.
+        restrict_spenders: storage::Value<bool>,
+        /// The spenders which can be approved when `restrict_spenders` is set.
+        allowed_spenders: storage::HashMap<AccountId, bool>,
+        /// The account which received the treasury's share of the genesis supply, if any.
+        treasury: storage::Value<Option<AccountId>>,
+        /// The native balance set aside for buying back tokens from the treasury.
+        buyback_pool: storage::Value<Balance>,
+        /// The share of every balance which decays per era in basis points and
+        /// the length of an era in blocks, or `None` if balances do not decay.
+        demurrage: storage::Value<Option<(u16, BlockNumber)>>,
+        /// The block up to which the demurrage of each account has been settled.
+        last_touched: storage::HashMap<AccountId, BlockNumber>,
+        /// The operators of every holder, unless a holder revoked them.
+        default_operators: storage::Value<Vec<AccountId>>,
+        /// Default operators revoked by a holder: (holder, operator) -> revoked
+        revoked_default_operators: storage::HashMap<(AccountId, AccountId), bool>,
+        /// Operators authorized by a holder: (holder, operator) -> authorized
+        operators: storage::HashMap<(AccountId, AccountId), bool>,
+        /// The total number of tokens which have been minted after deployment.
+        total_minted: storage::Value<Balance>,
+    }
+
+    impl Deploy for Erc20 {
+        /// This version is usually reached through `upgrade`, but it can be
+        /// deployed on its own as well.
+        fn deploy(&mut self, init_value: Balance) {
+            self.storage_version.set(STORAGE_VERSION);
+            self.name.set(String::new());
+            self.symbol.set(String::new());
+            self.decimals.set(0);
+            self.owner.set(env.caller());
+            self.pending_owner.set(None);
+            self.total_supply.set(init_value);
+            self.total_burned.set(0);
+            self.cap.set(Balance::max_value());
+            self.balances.insert(env.caller(), init_value);
+            self.ownership_renounced.set(false);
+            self.total_minted.set(0);
+        }
+    }
+
+    impl Erc20 {
+        /// Returns the semantic version of the contract code.
+        pub(external) fn version(&self) -> String {
+            let version = String::from(VERSION);
+            env.println(&format!("Erc20::version = {:?}", version));
+            version
+        }
+
+        /// Returns the version of the storage layout.
+        pub(external) fn storage_version(&self) -> u32 {
+            let storage_version = *self.storage_version;
+            env.println(&format!("Erc20::storage_version = {:?}", storage_version));
+            storage_version
+        }
+
+        /// Migrates the storage left behind by the previous version.
+        ///
+        /// This has to be called once right after the upgrade, before any other
+        /// message of this version can be used. Only the owner can migrate, so
+        /// once ownership has been renounced, nobody can.
+        pub(external) fn migrate(&mut self) {
+            assert!(
+                !*self.ownership_renounced && env.caller() == *self.owner,
+                "only the owner can migrate the storage"
+            );
+            assert_eq!(
+                *self.storage_version,
+                PREVIOUS_STORAGE_VERSION,
+                "the storage has already been migrated or has an unknown version"
+            );
+            // Version 2 reads the storage of version 1 as it is.
+            self.storage_version.set(STORAGE_VERSION);
+        }
+
+        /// Returns the balances of all `owners` at once.
+        ///
+        /// This message is new in version 2.
+        pub(external) fn balance_of_batch(&self, owners: Vec<AccountId>) -> Vec<Balance> {
+            self.ensure_migrated();
+            let balances: Vec<Balance> = owners
+                .iter()
+                .map(|owner| *self.balances.get(owner).unwrap_or(&0))
+                .collect();
+            env.println(&format!("Erc20::balance_of_batch(owners = {:?}) = {:?}", owners, balances));
+            balances
+        }
+    }
+
+    impl Erc20 {
+        /// Panics unless the storage has the layout this code works with.
+        fn ensure_migrated(&self) {
+            assert_eq!(*self.storage_version, STORAGE_VERSION, "the storage has to be migrated first");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn balance_of_batch_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let erc20 = Erc20::deploy_mock(1234);
+        assert_eq!(erc20.version(), "2.0.0");
+        assert_eq!(erc20.storage_version(), 2);
+        assert_eq!(erc20.balance_of_batch(vec![alice, bob]), vec![1234, 0]);
+    }
+
+    #[test]
+    fn migrate_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc20 = Erc20::deploy_mock(1234);
+        // Pretend we found the storage of the previous version
+        erc20.storage_version.set(PREVIOUS_STORAGE_VERSION);
+        erc20.migrate();
+        assert_eq!(erc20.storage_version(), 2);
+        assert_eq!(erc20.balance_of_batch(vec![alice]), vec![1234]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the storage has to be migrated first")]
+    fn messages_require_the_migration() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc20 = Erc20::deploy_mock(1234);
+        erc20.storage_version.set(PREVIOUS_STORAGE_VERSION);
+        erc20.balance_of_batch(vec![alice]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the storage has already been migrated or has an unknown version")]
+    fn migrate_only_works_once() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc20 = Erc20::deploy_mock(1234);
+        erc20.migrate();
+    }
+
+    #[test]
+    #[should_panic(expected = "only the owner can migrate the storage")]
+    fn only_the_owner_can_migrate() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc20 = Erc20::deploy_mock(1234);
+        erc20.storage_version.set(PREVIOUS_STORAGE_VERSION);
+        env::test::set_caller(bob);
+        erc20.migrate();
+    }
+
+    #[test]
+    #[should_panic(expected = "only the owner can migrate the storage")]
+    fn nobody_can_migrate_after_ownership_was_renounced() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc20 = Erc20::deploy_mock(1234);
+        erc20.storage_version.set(PREVIOUS_STORAGE_VERSION);
+        erc20.ownership_renounced.set(true);
+        erc20.migrate();
+    }
+}
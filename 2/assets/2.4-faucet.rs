@@ -0,0 +1,421 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of the ERC20 token.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The number of blocks in a day, with one block every 6 seconds.
+const BLOCKS_PER_DAY: BlockNumber = 14_400;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the faucet.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Dripped {
+        to: AccountId,
+        amount: Balance,
+    },
+    Refilled {
+        amount: Balance,
+    },
+    Drained {
+        amount: Balance,
+    },
+}
+
+/// Errors which can occur when calling the faucet.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum FaucetError {
+    /// The caller has to wait for the cooldown to end.
+    CooldownActive,
+    /// The faucet has given out all it may give out today.
+    DailyCapReached,
+    /// The faucet does not hold enough tokens.
+    InsufficientFunds,
+    /// Only the admin can refill or drain the faucet.
+    NotAdmin,
+    /// The token refused to move the tokens into the faucet.
+    TransferFailed,
+}
+
+/// Deposits a faucet event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Gives out small amounts of an ERC20 token to anyone who asks.
+    ///
+    /// Every request is paid a fixed `drip`. An account has to wait
+    /// `cooldown` blocks between requests, and the faucet stops giving out
+    /// tokens for the rest of the day once it has given out `daily_cap`, so
+    /// nobody can empty it by asking from many accounts at once.
+    struct Faucet {
+        /// The account which refills and drains the faucet.
+        admin: storage::Value<AccountId>,
+        /// The ERC20 token given out.
+        token: storage::Value<AccountId>,
+        /// The tokens given out per request.
+        drip: storage::Value<Balance>,
+        /// The blocks an account has to wait between requests.
+        cooldown: storage::Value<BlockNumber>,
+        /// The most tokens given out per day.
+        daily_cap: storage::Value<Balance>,
+        /// The day `dispensed_today` counts the tokens of.
+        day: storage::Value<BlockNumber>,
+        /// The tokens given out on `day`.
+        dispensed_today: storage::Value<Balance>,
+        /// The block of the last request of each account.
+        last_request: storage::HashMap<AccountId, BlockNumber>,
+    }
+
+    impl Deploy for Faucet {
+        fn deploy(&mut self, token: AccountId, drip: Balance, cooldown: BlockNumber, daily_cap: Balance) {
+            self.admin.set(env.caller());
+            self.token.set(token);
+            self.drip.set(drip);
+            self.cooldown.set(cooldown);
+            self.daily_cap.set(daily_cap);
+            self.day.set(env::block_number() / BLOCKS_PER_DAY);
+            self.dispensed_today.set(0);
+        }
+    }
+
+    impl Faucet {
+        /// Returns the tokens given out per request.
+        pub(external) fn drip(&self) -> Balance {
+            let drip = *self.drip;
+            env.println(&format!("Faucet::drip = {:?}", drip));
+            drip
+        }
+
+        /// Returns the tokens the faucet can still give out today.
+        pub(external) fn remaining_today(&self) -> Balance {
+            let remaining = *self.daily_cap - self.dispensed_on(env.block_number() / BLOCKS_PER_DAY);
+            env.println(&format!("Faucet::remaining_today = {:?}", remaining));
+            remaining
+        }
+
+        /// Returns the first block in which `account` can request tokens again.
+        pub(external) fn next_request_at(&self, account: AccountId) -> BlockNumber {
+            let next_request_at = match self.last_request.get(&account) {
+                Some(last_request) => last_request + *self.cooldown,
+                None => 0,
+            };
+            env.println(&format!("Faucet::next_request_at(account = {:?}) = {:?}", account, next_request_at));
+            next_request_at
+        }
+
+        /// Gives the caller `drip` tokens.
+        ///
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn request(&mut self) -> Result<Balance, FaucetError> {
+            let caller = env.caller();
+            let now = env.block_number();
+            if let Some(last_request) = self.last_request.get(&caller) {
+                if now < last_request + *self.cooldown {
+                    return Err(FaucetError::CooldownActive)
+                }
+            }
+            let day = now / BLOCKS_PER_DAY;
+            let drip = *self.drip;
+            let dispensed = self.dispensed_on(day) + drip;
+            if dispensed > *self.daily_cap {
+                return Err(FaucetError::DailyCapReached)
+            }
+            if self.balance_of_faucet() < drip {
+                return Err(FaucetError::InsufficientFunds)
+            }
+            self.last_request.insert(caller, now);
+            self.day.set(day);
+            self.dispensed_today.set(dispensed);
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(caller, drip).encode());
+            assert_eq!(result, Some(Ok(())), "the drip could not be transferred");
+            deposit_event(Event::Dripped {
+                to: caller,
+                amount: drip
+            });
+            Ok(drip)
+        }
+
+        /// Moves `amount` tokens of the admin into the faucet.
+        ///
+        /// The admin must have approved the faucet to transfer the tokens.
+        pub(external) fn refill(&mut self, amount: Balance) -> Result<(), FaucetError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(FaucetError::NotAdmin)
+            }
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(admin, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(FaucetError::TransferFailed),
+            }
+            deposit_event(Event::Refilled {
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Sends every token the faucet holds back to the admin.
+        pub(external) fn drain(&mut self) -> Result<Balance, FaucetError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(FaucetError::NotAdmin)
+            }
+            let amount = self.balance_of_faucet();
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(admin, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be drained");
+            deposit_event(Event::Drained {
+                amount: amount
+            });
+            Ok(amount)
+        }
+    }
+
+    impl Faucet {
+        /// Returns the tokens given out on `day`, which is 0 for every day
+        /// after the last request.
+        fn dispensed_on(&self, day: BlockNumber) -> Balance {
+            if day == *self.day {
+                *self.dispensed_today
+            } else {
+                0
+            }
+        }
+
+        /// Returns the tokens the faucet holds.
+        fn balance_of_faucet(&self) -> Balance {
+            call_contract(*self.token, BALANCE_OF_SELECTOR, &env::address().encode())
+                .expect("the token reports the balance of the faucet")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the faucet through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the faucet first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..]).unwrap();
+                    Some(balances.get(&owner).cloned().unwrap_or(0).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Deploys a faucet at block 0 which drips 10 tokens with a cooldown of
+    /// 100 blocks and a daily cap of 30 tokens, and has Alice refill it with
+    /// 1000 tokens.
+    fn deploy_mock() -> (Faucet, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 1000);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut faucet = Faucet::deploy_mock(token, 10, 100, 30);
+        assert_eq!(faucet.refill(1000), Ok(()));
+        (faucet, balances)
+    }
+
+    /// Requests tokens for `account`.
+    fn request(faucet: &mut Faucet, account: AccountId) -> Result<Balance, FaucetError> {
+        env::test::set_caller(account);
+        faucet.request()
+    }
+
+    #[test]
+    fn request_drips_tokens() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut faucet, balances) = deploy_mock();
+        assert_eq!(faucet.drip(), 10);
+        assert_eq!(request(&mut faucet, bob), Ok(10));
+        assert_eq!(balances.borrow()[&bob], 10);
+        assert_eq!(balances.borrow()[&env::address()], 990);
+    }
+
+    #[test]
+    fn cooldown_is_enforced_per_account() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut faucet, balances) = deploy_mock();
+        env::test::set_block_number(50);
+        assert_eq!(request(&mut faucet, bob), Ok(10));
+        assert_eq!(faucet.next_request_at(bob), 150);
+        // Charlie does not have to wait for Bob's cooldown
+        assert_eq!(request(&mut faucet, charlie), Ok(10));
+        env::test::set_block_number(149);
+        assert_eq!(request(&mut faucet, bob), Err(FaucetError::CooldownActive));
+        env::test::set_block_number(150);
+        assert_eq!(request(&mut faucet, bob), Ok(10));
+        assert_eq!(request(&mut faucet, bob), Err(FaucetError::CooldownActive));
+        assert_eq!(faucet.next_request_at(bob), 250);
+        assert_eq!(balances.borrow()[&bob], 20);
+    }
+
+    #[test]
+    fn daily_cap_is_enforced_for_everyone() {
+        let accounts: Vec<AccountId> = (1..6).map(|byte| AccountId::try_from([byte; 32]).unwrap()).collect();
+        let (mut faucet, _) = deploy_mock();
+        env::test::set_block_number(BLOCKS_PER_DAY - 10);
+        for account in accounts.iter().take(3) {
+            assert_eq!(request(&mut faucet, *account), Ok(10));
+        }
+        assert_eq!(faucet.remaining_today(), 0);
+        assert_eq!(request(&mut faucet, accounts[3]), Err(FaucetError::DailyCapReached));
+        // The cap is reset on the next day
+        env::test::set_block_number(BLOCKS_PER_DAY);
+        assert_eq!(faucet.remaining_today(), 30);
+        assert_eq!(request(&mut faucet, accounts[3]), Ok(10));
+        assert_eq!(request(&mut faucet, accounts[4]), Ok(10));
+        assert_eq!(faucet.remaining_today(), 10);
+    }
+
+    #[test]
+    fn request_fails_when_empty() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut faucet, balances) = deploy_mock();
+        balances.borrow_mut().insert(env::address(), 5);
+        assert_eq!(request(&mut faucet, bob), Err(FaucetError::InsufficientFunds));
+        // A failed request does not start the cooldown
+        assert_eq!(faucet.next_request_at(bob), 0);
+        env::test::set_caller(alice);
+        balances.borrow_mut().insert(alice, 5);
+        assert_eq!(faucet.refill(5), Ok(()));
+        assert_eq!(request(&mut faucet, bob), Ok(10));
+    }
+
+    #[test]
+    fn only_admin_can_refill_and_drain() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut faucet, balances) = deploy_mock();
+        balances.borrow_mut().insert(bob, 100);
+        env::test::set_caller(bob);
+        assert_eq!(faucet.refill(100), Err(FaucetError::NotAdmin));
+        assert_eq!(faucet.drain(), Err(FaucetError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(faucet.refill(1), Err(FaucetError::TransferFailed));
+        assert_eq!(faucet.drain(), Ok(1000));
+        assert_eq!(balances.borrow()[&alice], 1000);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+}
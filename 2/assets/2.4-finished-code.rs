@@ -1,22 +1,33 @@
 #![cfg_attr(not(any(test, feature = "test-env")), no_std)]
 
+use core::ops::{
+    Add,
+    Sub,
+};
 use parity_codec::{
     Decode,
     Encode,
+    Input,
 };
 use ink_core::{
     env::{
         self,
         AccountId,
         Balance,
+        BlockNumber,
+        Hash,
+    },
+    memory::{
+        format,
+        string::String,
+        vec::Vec,
     },
-    memory::format,
     storage,
 };
 use ink_lang::contract;
 
 /// Events deposited by the ERC20 token contract.
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Debug, PartialEq)]
 enum Event {
     Transfer {
         from: Option<AccountId>,
@@ -28,37 +39,896 @@ enum Event {
         spender: AccountId,
         value: Balance,
     },
+    Mint {
+        to: AccountId,
+        value: Balance,
+    },
+    Burn {
+        from: AccountId,
+        value: Balance,
+    },
+    Paused {
+        account: AccountId,
+    },
+    Unpaused {
+        account: AccountId,
+    },
+    OwnershipTransferred {
+        previous_owner: AccountId,
+        new_owner: AccountId,
+    },
+    RoleGranted {
+        role: Role,
+        account: AccountId,
+        sender: AccountId,
+    },
+    RoleRevoked {
+        role: Role,
+        account: AccountId,
+        sender: AccountId,
+    },
+    FeeChanged {
+        fee_bps: u16,
+        fee_recipient: AccountId,
+    },
+    Frozen {
+        account: AccountId,
+    },
+    Unfrozen {
+        account: AccountId,
+    },
+    DelegateChanged {
+        delegator: AccountId,
+        from_delegate: Option<AccountId>,
+        to_delegate: AccountId,
+    },
+    DelegateVotesChanged {
+        delegate: AccountId,
+        previous_votes: Balance,
+        new_votes: Balance,
+    },
+    Snapshot {
+        id: u32,
+    },
+    RateLimitChanged {
+        limit: Option<(Balance, BlockNumber)>,
+    },
+    MintProposed {
+        id: u32,
+        proposer: AccountId,
+        to: AccountId,
+        value: Balance,
+    },
+    MintApproved {
+        id: u32,
+        approver: AccountId,
+    },
+    Locked {
+        account: AccountId,
+        value: Balance,
+        until: BlockNumber,
+    },
+    TransferWithMemo {
+        from: AccountId,
+        to: AccountId,
+        value: Balance,
+        memo: Vec<u8>,
+    },
+    Upgraded {
+        code_hash: Hash,
+    },
+    EmissionChanged {
+        schedule: Option<(Balance, BlockNumber)>,
+    },
+    MaxTransferChanged {
+        max_transfer_amount: Option<Balance>,
+    },
+    Exempted {
+        account: AccountId,
+    },
+    Unexempted {
+        account: AccountId,
+    },
+    CircuitTripped {
+        guardian: Option<AccountId>,
+    },
+    CircuitReset {
+        account: AccountId,
+    },
+    Failure {
+        reason: Erc20Error,
+        caller: AccountId,
+    },
+    OwnershipProposed {
+        owner: AccountId,
+        pending_owner: AccountId,
+    },
+    OwnershipRenounced {
+        previous_owner: AccountId,
+    },
+    SpenderAllowed {
+        spender: AccountId,
+    },
+    SpenderDenied {
+        spender: AccountId,
+    },
+    BuybackFunded {
+        account: AccountId,
+        value: Balance,
+    },
+    Buyback {
+        treasury: AccountId,
+        paid: Balance,
+        burned: Balance,
+    },
+    AuthorizedOperator {
+        operator: AccountId,
+        holder: AccountId,
+    },
+    RevokedOperator {
+        operator: AccountId,
+        holder: AccountId,
+    },
+}
+
+/// Roles which grant access to privileged operations of the token.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Role {
+    /// Allowed to grant and revoke roles.
+    Admin,
+    /// Allowed to create new tokens.
+    Minter,
+    /// Allowed to pause and unpause the contract.
+    Pauser,
+    /// Allowed to destroy tokens.
+    Burner,
+    /// Allowed to propose and approve mints which need several approvals.
+    MintSigner,
+    /// Allowed to lock the balances of other accounts, e.g. for vesting.
+    Locker,
+    /// Allowed to halt transfers with the circuit breaker.
+    Guardian,
+}
+
+/// A proposal to mint tokens, which is carried out once enough signers approved it.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct MintProposal {
+    /// The account receiving the new tokens.
+    to: AccountId,
+    /// The number of tokens to mint.
+    value: Balance,
+    /// The number of signers who approved the proposal so far.
+    approvals: u32,
+    /// Whether the tokens have been minted already.
+    executed: bool,
+}
+
+/// Errors which can occur when calling the ERC20 token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc20Error {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+    /// The caller is not permitted to perform the operation.
+    Unauthorized,
+    /// The contract is paused and does not accept the operation.
+    Paused,
+    /// The operation would raise the total supply above the cap.
+    CapExceeded,
+    /// One of the accounts involved in the operation is frozen.
+    AccountFrozen,
+    /// The transfer fee rate exceeds 100%.
+    InvalidFee,
+    /// The deadline of a signed message or of an allowance has passed.
+    Expired,
+    /// The signature does not match the signed message and signer.
+    InvalidSignature,
+    /// The contract is in the middle of a cross-contract call and cannot be re-entered.
+    ReentrantCall,
+    /// A non-zero allowance can only be set once the current allowance has been set to zero.
+    AllowanceNotZero,
+    /// A call to another contract failed or was rejected by it.
+    CallFailed,
+    /// The account has already sent as many tokens as it may within the current window.
+    RateLimited,
+    /// There is no proposal with the given id.
+    UnknownProposal,
+    /// The signer has already approved the proposal.
+    AlreadyApproved,
+    /// The proposal has already been carried out.
+    AlreadyExecuted,
+    /// The number of approvals required must be at least 1.
+    InvalidThreshold,
+    /// The tokens are locked and cannot be spent yet.
+    BalanceLocked,
+    /// The memo is longer than `MAX_MEMO_LEN` bytes.
+    MemoTooLong,
+    /// Balances can no longer be imported since the migration has been finalized.
+    MigrationFinalized,
+    /// The recipient is the token contract itself, where the tokens would be stuck.
+    TransferToContract,
+    /// An emission era must last at least one block.
+    InvalidEra,
+    /// The transfer exceeds the maximum amount which can be sent at once.
+    TransferTooLarge,
+    /// Transfers have been halted by the circuit breaker.
+    CircuitTripped,
+    /// The nonce of a signed message is not the current nonce of the signer.
+    InvalidNonce,
+    /// The fee claimed by a relayer exceeds the maximum relayer fee.
+    RelayerFeeTooHigh,
+    /// The storage has a layout version this code does not work with.
+    StorageVersionMismatch,
+    /// The spender is not on the allow-list of the contract.
+    SpenderNotAllowed,
+    /// No native balance has been sent along with the call.
+    ZeroValue,
+    /// The contract has been deployed without a treasury.
+    NoTreasury,
+    /// The buyback pool does not hold enough native balance.
+    InsufficientBuybackPool,
+    /// Holders are always operators for themselves.
+    SelfOperator,
+    /// The caller is not an operator for the holder.
+    NotOperator,
+    /// The contract is not paused.
+    NotPaused,
+    /// The operation would overflow a balance kept by the contract.
+    Overflow,
+}
+
+/// The denominator of the transfer fee rate, which is given in basis points.
+const FEE_DENOMINATOR: u16 = 10_000;
+
+/// The maximum number of bytes a transfer memo may have.
+const MAX_MEMO_LEN: usize = 256;
+
+/// The canonical burn address. Tokens transferred to it are burned instead of
+/// being credited to it, so it never holds a balance.
+///
+/// The all-zero account is a regular account in the test environment, so the
+/// burn address is spelled out as `0xDEAD...` instead.
+const BURN_ADDRESS: [u8; 32] = [
+    0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD,
+    0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD, 0xDE, 0xAD,
+];
+
+/// Returns `BURN_ADDRESS` as an AccountId.
+fn burn_address() -> AccountId {
+    AccountId::decode(&mut &BURN_ADDRESS[..]).expect("an AccountId has 32 bytes")
+}
+
+impl Event {
+    /// Returns the topics under which the event is indexed.
+    ///
+    /// Every account taking part in a transfer or approval becomes a topic,
+    /// so that off-chain tooling can subscribe to the events of an account.
+    fn topics(&self) -> Vec<Hash> {
+        match self {
+            Event::Transfer { from, to, .. } => {
+                from.iter().chain(to.iter()).map(account_topic).collect()
+            }
+            Event::Approval { owner, spender, .. } => {
+                [owner, spender].iter().map(|account| account_topic(account)).collect()
+            }
+            Event::TransferWithMemo { from, to, .. } => {
+                [from, to].iter().map(|account| account_topic(account)).collect()
+            }
+            Event::Failure { caller, .. } => vec![account_topic(caller)],
+            Event::OwnershipTransferred { previous_owner, new_owner } => {
+                [previous_owner, new_owner].iter().map(|account| account_topic(account)).collect()
+            }
+            Event::OwnershipProposed { owner, pending_owner } => {
+                [owner, pending_owner].iter().map(|account| account_topic(account)).collect()
+            }
+            Event::OwnershipRenounced { previous_owner } => vec![account_topic(previous_owner)],
+            Event::AuthorizedOperator { operator, holder } | Event::RevokedOperator { operator, holder } => {
+                [operator, holder].iter().map(|account| account_topic(account)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns the event topic for the given AccountId.
+fn account_topic(account: &AccountId) -> Hash {
+    Hash::decode(&mut &account.encode()[..])
+        .expect("an AccountId has the same encoded size as a Hash")
+}
+
+/// The signing context of messages signed off-chain for this contract.
+const SIGNING_CONTEXT: &[u8] = b"erc20";
+
+/// Returns `true` if `signature` is a valid sr25519 signature of `message` by `signer`.
+fn verify_signature(signer: &AccountId, message: &[u8], signature: &[u8; 64]) -> bool {
+    let public_key = match schnorrkel::PublicKey::from_bytes(&signer.encode()[..]) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match schnorrkel::Signature::from_bytes(&signature[..]) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify_simple(SIGNING_CONTEXT, message, &signature).is_ok()
+}
+
+/// The selector of the `on_flash_loan` message of flash loan receivers.
+const ON_FLASH_LOAN_SELECTOR: [u8; 4] = [0x08, 0x48, 0x1E, 0x7F];
+
+/// The value a flash loan receiver returns to acknowledge the loan,
+/// `blake2_256(b"ERC3156FlashBorrower.onFlashLoan")`.
+const FLASH_LOAN_CALLBACK_SUCCESS: [u8; 32] = [
+    0x64, 0xE7, 0xAD, 0x50, 0x62, 0xEB, 0x9B, 0x99, 0xE0, 0x72, 0x65, 0x4D, 0x89, 0xD5, 0x22, 0x9C,
+    0xCA, 0x32, 0xF7, 0x3F, 0x75, 0x1B, 0x7D, 0xAD, 0x27, 0x3F, 0x44, 0x9B, 0xBE, 0x13, 0xE4, 0x2A,
+];
+
+/// The fee charged on flash loans in basis points of the borrowed amount.
+const FLASH_LOAN_FEE_BPS: u16 = 9;
+
+/// The selector of the `on_approval_received` message of approval receivers.
+const ON_APPROVAL_RECEIVED_SELECTOR: [u8; 4] = [0xB9, 0x9F, 0xF6, 0x2D];
+
+/// The selector of the `on_token_transfer` message of transfer receivers.
+const ON_TOKEN_TRANSFER_SELECTOR: [u8; 4] = [0x24, 0xE8, 0x75, 0x9B];
+
+/// The selector of the `on_erc20_received` message of safe transfer receivers,
+/// which they also return to acknowledge the tokens.
+const ON_ERC20_RECEIVED_SELECTOR: [u8; 4] = [0x2A, 0xC3, 0xB7, 0x90];
+
+/// The answer of a recipient to being notified of the tokens it received.
+#[derive(Debug, PartialEq)]
+enum Acknowledgement<T> {
+    /// The recipient is a plain account, which returns nothing when called.
+    NoContract,
+    /// The recipient is a contract which answered with the given value.
+    Contract(T),
+}
+
+impl<T: Decode> Decode for Acknowledgement<T> {
+    fn decode<I: Input>(input: &mut I) -> Option<Self> {
+        let first = match input.read_byte() {
+            Some(first) => first,
+            None => return Some(Acknowledgement::NoContract),
+        };
+        // Put the byte we looked at back in front of the rest of the output.
+        let mut output = Vec::new();
+        output.push(first);
+        let mut buffer = [0u8; 32];
+        loop {
+            match input.read(&mut buffer) {
+                0 => break,
+                len => output.extend_from_slice(&buffer[..len]),
+            }
+        }
+        T::decode(&mut &output[..]).map(Acknowledgement::Contract)
+    }
+}
+
+/// The selector of the `transfer` message of ERC20 tokens.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The gas limit of cross-contract calls made by the token.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns `bps` basis points of `value`, rounded down.
+fn share_of(value: Balance, bps: u16) -> Balance {
+    let bps = Balance::from(bps);
+    let denominator = Balance::from(FEE_DENOMINATOR);
+    // Split `value` to avoid overflowing on large amounts.
+    value / denominator * bps + value % denominator * bps / denominator
+}
+
+/// The precision of the fixed-point numbers used to compute demurrage.
+const DEMURRAGE_PRECISION: Balance = 1_000_000_000_000_000_000;
+
+/// Returns what is left of `balance` after `eras` eras of demurrage at
+/// `rate_bps` basis points per era, rounded down.
+///
+/// The share which is retained over all eras is computed by squaring, so this
+/// takes the same few steps however long an account has not been touched.
+fn decayed_balance(balance: Balance, rate_bps: u16, eras: BlockNumber) -> Balance {
+    let denominator = Balance::from(FEE_DENOMINATOR);
+    let mut base = (denominator - Balance::from(rate_bps)) * (DEMURRAGE_PRECISION / denominator);
+    let mut retained = DEMURRAGE_PRECISION;
+    let mut eras = eras;
+    while eras > 0 {
+        if eras % 2 == 1 {
+            retained = retained * base / DEMURRAGE_PRECISION;
+        }
+        base = base * base / DEMURRAGE_PRECISION;
+        eras /= 2;
+    }
+    // Split `balance` to avoid overflowing on large amounts.
+    balance / DEMURRAGE_PRECISION * retained + balance % DEMURRAGE_PRECISION * retained / DEMURRAGE_PRECISION
+}
+
+/// Returns the fee charged for a flash loan of `amount` tokens, rounded up.
+fn flash_fee_for(amount: Balance) -> Balance {
+    let fee_bps = Balance::from(FLASH_LOAN_FEE_BPS);
+    let denominator = Balance::from(FEE_DENOMINATOR);
+    let fee = amount / denominator * fee_bps + amount % denominator * fee_bps / denominator;
+    if amount % denominator * fee_bps % denominator == 0 {
+        fee
+    } else {
+        fee + 1
+    }
+}
+
+/// Conversions between whole tokens and the raw `Balance` units balances are
+/// stored in, for a token with `decimals` decimals.
+mod units {
+    use ink_core::env::Balance;
+
+    /// Returns the number of raw units in one whole token, or `None` if it
+    /// does not fit into a `Balance`.
+    fn unit(decimals: u8) -> Option<Balance> {
+        (10 as Balance).checked_pow(u32::from(decimals))
+    }
+
+    /// Converts `amount` whole tokens into raw units, or returns `None` if the
+    /// result, or even a single whole token, does not fit into a `Balance`.
+    pub fn to_units(amount: Balance, decimals: u8) -> Option<Balance> {
+        unit(decimals)?.checked_mul(amount)
+    }
+
+    /// Splits `units` raw units into whole tokens and the raw units of the
+    /// remaining fraction of a token.
+    pub fn from_units(units: Balance, decimals: u8) -> (Balance, Balance) {
+        match unit(decimals) {
+            Some(unit) => (units / unit, units % unit),
+            // A whole token is more than any `Balance` can hold.
+            None => (0, units),
+        }
+    }
+}
+
+/// An amount of tokens, used for the arithmetic on balances, allowances and
+/// the total supply.
+///
+/// Adding or subtracting amounts returns `None` instead of overflowing, so
+/// callers have to decide what an overflow means before they can use the result.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+struct TokenAmount(Balance);
+
+impl From<Balance> for TokenAmount {
+    fn from(value: Balance) -> Self {
+        TokenAmount(value)
+    }
+}
+
+impl From<TokenAmount> for Balance {
+    fn from(amount: TokenAmount) -> Self {
+        amount.0
+    }
+}
+
+impl Add for TokenAmount {
+    type Output = Option<TokenAmount>;
+
+    fn add(self, other: TokenAmount) -> Option<TokenAmount> {
+        self.0.checked_add(other.0).map(TokenAmount)
+    }
+}
+
+impl Sub for TokenAmount {
+    type Output = Option<TokenAmount>;
+
+    fn sub(self, other: TokenAmount) -> Option<TokenAmount> {
+        self.0.checked_sub(other.0).map(TokenAmount)
+    }
+}
+
+/// The version of the storage layout of this contract.
+///
+/// Code which is swapped in with `upgrade` checks it to find out whether it
+/// has to migrate the storage first.
+const STORAGE_VERSION: u32 = 1;
+
+/// The semantic version of this contract.
+const VERSION: &str = "1.0.0";
+
+/// Replaces the code of this contract with the code stored under `code_hash`,
+/// keeping its storage.
+#[cfg(not(all(test, feature = "test-env")))]
+fn set_code_hash(code_hash: Hash) {
+    env::set_code_hash(&code_hash)
+}
+
+/// Records `code_hash` as the code of this contract in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn set_code_hash(code_hash: Hash) {
+    tests::set_mock_code_hash(code_hash)
+}
+
+/// Removes this contract from the chain and sends its remaining native
+/// balance to `beneficiary`.
+#[cfg(not(all(test, feature = "test-env")))]
+fn terminate_contract(beneficiary: AccountId) {
+    env::terminate_contract(beneficiary)
+}
+
+/// Records that this contract has been terminated in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn terminate_contract(beneficiary: AccountId) {
+    tests::set_mock_beneficiary(beneficiary)
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
 }
 
-/// Deposits an ERC20 token event.
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Deposits an ERC20 token event along with its topics.
 fn deposit_event(event: Event) {
-    env::deposit_raw_event(&event.encode()[..])
+    env::deposit_raw_event(&event.topics()[..], &event.encode()[..])
 }
 
 contract! {
     /// The storage items for a typical ERC20 token implementation.
     struct Erc20 {
+        /// The name of the token.
+        name: storage::Value<String>,
+        /// The symbol of the token.
+        symbol: storage::Value<String>,
+        /// The number of decimals used to display token amounts.
+        decimals: storage::Value<u8>,
+        /// The account which is allowed to perform privileged operations.
+        owner: storage::Value<AccountId>,
+        /// The account which has been proposed as the next owner, if any.
+        pending_owner: storage::Value<Option<AccountId>>,
         /// The total supply.
         total_supply: storage::Value<Balance>,
+        /// The total number of tokens which have been burned.
+        total_burned: storage::Value<Balance>,
+        /// The maximum total supply that can ever be in existence.
+        cap: storage::Value<Balance>,
         /// The balance of each user.
         balances: storage::HashMap<AccountId, Balance>,
         /// Balances that are spendable by non-owners: (owner, spender) -> allowed
         allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+        /// Whether transfers and approvals are currently halted.
+        paused: storage::Value<bool>,
+        /// The roles held by each account: (role, account) -> granted
+        roles: storage::HashMap<(Role, AccountId), bool>,
+        /// Accounts which are barred from sending and receiving tokens.
+        frozen: storage::HashMap<AccountId, bool>,
+        /// The fee charged on every transfer in basis points of the transferred value.
+        fee_bps: storage::Value<u16>,
+        /// The account which collects the transfer fees.
+        fee_recipient: storage::Value<AccountId>,
+        /// The number of signed messages consumed on behalf of each account.
+        nonces: storage::HashMap<AccountId, u64>,
+        /// The account each account has delegated its voting power to.
+        delegates: storage::HashMap<AccountId, AccountId>,
+        /// The voting power history of each delegate: (delegate, index) -> (block, votes)
+        checkpoints: storage::HashMap<(AccountId, u32), (BlockNumber, Balance)>,
+        /// The number of checkpoints recorded for each delegate.
+        num_checkpoints: storage::HashMap<AccountId, u32>,
+        /// The id of the most recent snapshot, or 0 if none has been taken yet.
+        current_snapshot_id: storage::Value<u32>,
+        /// Values recorded for snapshots, keyed by account or `None` for the
+        /// total supply: (key, index) -> (snapshot id, value)
+        snapshots: storage::HashMap<(Option<AccountId>, u32), (u32, Balance)>,
+        /// The number of values recorded for each snapshot key.
+        snapshot_counts: storage::HashMap<Option<AccountId>, u32>,
+        /// Whether the contract is in the middle of a cross-contract call.
+        locked: storage::Value<bool>,
+        /// Whether `approve` refuses to change one non-zero allowance into another.
+        strict_approvals: storage::Value<bool>,
+        /// The share of every transfer which is burned in basis points of the transferred value.
+        burn_bps: storage::Value<u16>,
+        /// Every account with a non-zero balance, in no particular order.
+        holders: storage::Vec<AccountId>,
+        /// The position of each holder in `holders`.
+        holder_indices: storage::HashMap<AccountId, u32>,
+        /// The number of accounts with a non-zero balance.
+        holder_count: storage::Value<u32>,
+        /// The last block in which allowances with a deadline can be spent: (owner, spender) -> block
+        allowance_deadlines: storage::HashMap<(AccountId, AccountId), BlockNumber>,
+        /// The maximum amount any account may send within a window of blocks:
+        /// (max amount, window length), or `None` if transfers are not limited.
+        rate_limit: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The current window of each sender: account -> (first block, amount sent)
+        rate_windows: storage::HashMap<AccountId, (BlockNumber, Balance)>,
+        /// The number of signer approvals a mint proposal needs to be carried out.
+        mint_threshold: storage::Value<u32>,
+        /// The mint proposals made so far, by id.
+        mint_proposals: storage::HashMap<u32, MintProposal>,
+        /// The number of mint proposals made so far, which is also the next id.
+        mint_proposal_count: storage::Value<u32>,
+        /// The signers who approved each mint proposal: (id, signer) -> approved
+        mint_approvals: storage::HashMap<(u32, AccountId), bool>,
+        /// The portion of each account's balance which cannot be spent before a block:
+        /// account -> (locked amount, first block in which the tokens are free)
+        locks: storage::HashMap<AccountId, (Balance, BlockNumber)>,
+        /// The version of the storage layout, see `STORAGE_VERSION`.
+        ///
+        /// Later versions of the code only append fields after the existing
+        /// ones, so they find it in the same place as long as they keep the
+        /// fields declared above it.
+        storage_version: storage::Value<u32>,
+        /// Whether `migrate_balances` has been closed for good.
+        migration_finalized: storage::Value<bool>,
+        /// Whether tokens may be sent to the address of the token contract itself.
+        allow_transfers_to_contract: storage::Value<bool>,
+        /// The tokens emitted per era and the length of an era in blocks,
+        /// or `None` if there is no inflation.
+        emission_schedule: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The block in which the current, not yet accrued era started.
+        last_accrual: storage::Value<BlockNumber>,
+        /// The emission which has been accrued but not claimed yet.
+        accrued_emission: storage::Value<Balance>,
+        /// The maximum amount a single transfer may move, or `None` if transfers are not limited.
+        max_transfer_amount: storage::Value<Option<Balance>>,
+        /// Accounts whose transfers are not subject to `max_transfer_amount`.
+        exempt: storage::HashMap<AccountId, bool>,
+        /// Whether transfers have been halted by the circuit breaker.
+        circuit_tripped: storage::Value<bool>,
+        /// The transfer volume which trips the circuit breaker within a window of blocks:
+        /// (max volume, window length), or `None` if it only trips manually.
+        circuit_threshold: storage::Value<Option<(Balance, BlockNumber)>>,
+        /// The current transfer volume window: (first block, volume)
+        volume_window: storage::Value<(BlockNumber, Balance)>,
+        /// The maximum fee the relayer of a permit can claim from the owner.
+        max_relayer_fee: storage::Value<Balance>,
+        /// The spenders with a non-zero allowance of each owner: (owner, index) -> spender
+        spenders: storage::HashMap<(AccountId, u32), AccountId>,
+        /// The position of each spender in the list of its owner: (owner, spender) -> index
+        spender_indices: storage::HashMap<(AccountId, AccountId), u32>,
+        /// The number of spenders with a non-zero allowance of each owner.
+        spender_counts: storage::HashMap<AccountId, u32>,
+        /// Whether rejected transfers and approvals deposit a `Failure` event.
+        report_failures: storage::Value<bool>,
+        /// Whether the owner has renounced ownership, leaving the contract without one.
+        ownership_renounced: storage::Value<bool>,
+        /// Whether approvals are limited to the spenders on the allow-list.
+        restrict_spenders: storage::Value<bool>,
+        /// The spenders which can be approved when `restrict_spenders` is set.
+        allowed_spenders: storage::HashMap<AccountId, bool>,
+        /// The account which received the treasury's share of the genesis supply, if any.
+        treasury: storage::Value<Option<AccountId>>,
+        /// The native balance set aside for buying back tokens from the treasury.
+        buyback_pool: storage::Value<Balance>,
+        /// The share of every balance which decays per era in basis points and
+        /// the length of an era in blocks, or `None` if balances do not decay.
+        demurrage: storage::Value<Option<(u16, BlockNumber)>>,
+        /// The block up to which the demurrage of each account has been settled.
+        last_touched: storage::HashMap<AccountId, BlockNumber>,
+        /// The operators of every holder, unless a holder revoked them.
+        default_operators: storage::Value<Vec<AccountId>>,
+        /// Default operators revoked by a holder: (holder, operator) -> revoked
+        revoked_default_operators: storage::HashMap<(AccountId, AccountId), bool>,
+        /// Operators authorized by a holder: (holder, operator) -> authorized
+        operators: storage::HashMap<(AccountId, AccountId), bool>,
+        /// The total number of tokens which have been minted after deployment.
+        total_minted: storage::Value<Balance>,
     }
 
     impl Deploy for Erc20 {
-        fn deploy(&mut self, init_value: Balance) {
+        /// Mints the genesis supply according to `initial_balances`, so that it
+        /// can be split across several accounts in a single instantiation.
+        ///
+        /// With a `treasury`, `treasury_amount` of the tokens assigned to the
+        /// caller go to the treasury instead. Without one, `treasury_amount`
+        /// is ignored.
+        ///
+        /// The `default_operators` are operators for every holder until the
+        /// holder revokes them.
+        fn deploy(
+            &mut self,
+            initial_balances: Vec<(AccountId, Balance)>,
+            cap: Option<Balance>,
+            name: String,
+            symbol: String,
+            decimals: u8,
+            fee_bps: u16,
+            fee_recipient: AccountId,
+            strict_approvals: bool,
+            burn_bps: u16,
+            allow_transfers_to_contract: bool,
+            treasury: Option<AccountId>,
+            treasury_amount: Balance,
+            max_relayer_fee: Balance,
+            report_failures: bool,
+            restrict_spenders: bool,
+            demurrage: Option<(u16, BlockNumber)>,
+            default_operators: Vec<AccountId>,
+        ) {
+            self.storage_version.set(STORAGE_VERSION);
+            self.name.set(name);
+            self.symbol.set(symbol);
+            self.decimals.set(decimals);
+            let cap = cap.unwrap_or(Balance::max_value());
+            let init_value = initial_balances
+                .iter()
+                .try_fold(0 as Balance, |total, (_, value)| total.checked_add(*value))
+                .expect("the initial supply must not overflow");
+            assert!(init_value <= cap, "the initial supply must not exceed the cap");
+            self.cap.set(cap);
+            let deployer = env.caller();
+            let deployer_value: Balance = initial_balances
+                .iter()
+                .filter(|(account, _)| *account == deployer)
+                .map(|(_, value)| *value)
+                .sum();
+            let treasury_amount = treasury.map(|_| treasury_amount).unwrap_or(0);
+            assert!(
+                treasury_amount <= deployer_value,
+                "the treasury amount must not exceed the initial balance of the caller"
+            );
+            assert!(fee_bps <= FEE_DENOMINATOR, "the transfer fee must not exceed 100%");
+            self.fee_bps.set(fee_bps);
+            assert!(
+                u32::from(fee_bps) + u32::from(burn_bps) <= u32::from(FEE_DENOMINATOR),
+                "the transfer fee and burn rate must not exceed 100% together"
+            );
+            self.burn_bps.set(burn_bps);
+            self.fee_recipient.set(fee_recipient);
+            self.owner.set(env.caller());
+            self.pending_owner.set(None);
+            self.paused.set(false);
+            self.locked.set(false);
+            self.strict_approvals.set(strict_approvals);
+            self.current_snapshot_id.set(0);
+            self.holder_count.set(0);
+            self.total_burned.set(0);
+            self.total_minted.set(0);
+            self.rate_limit.set(None);
+            self.mint_threshold.set(2);
+            self.mint_proposal_count.set(0);
+            self.migration_finalized.set(false);
+            self.allow_transfers_to_contract.set(allow_transfers_to_contract);
+            self.emission_schedule.set(None);
+            self.last_accrual.set(env::block_number());
+            self.accrued_emission.set(0);
+            self.max_transfer_amount.set(None);
+            self.circuit_tripped.set(false);
+            self.circuit_threshold.set(None);
+            self.volume_window.set((env::block_number(), 0));
+            self.max_relayer_fee.set(max_relayer_fee);
+            self.report_failures.set(report_failures);
+            self.ownership_renounced.set(false);
+            self.restrict_spenders.set(restrict_spenders);
+            self.treasury.set(treasury);
+            self.buyback_pool.set(0);
+            if let Some((rate_bps, era)) = demurrage {
+                assert!(rate_bps <= FEE_DENOMINATOR, "the demurrage rate must not exceed 100%");
+                assert!(era > 0, "a demurrage era must last at least one block");
+            }
+            self.demurrage.set(demurrage);
+            self.default_operators.set(default_operators);
+            for role in &[
+                Role::Admin,
+                Role::Minter,
+                Role::Pauser,
+                Role::Burner,
+                Role::MintSigner,
+                Role::Locker,
+                Role::Guardian,
+            ] {
+                self.grant_role_impl(*role, env.caller(), env.caller());
+            }
             self.total_supply.set(init_value);
-            self.balances.insert(env.caller(), init_value);
-            deposit_event(Event::Transfer { 
-                from: None,
-                to: Some(env.caller()),
-                value: init_value
-            });
+            // Take the treasury's share out of the caller's entries in order.
+            let mut treasury_left = treasury_amount;
+            let mut initial_balances: Vec<(AccountId, Balance)> = initial_balances
+                .into_iter()
+                .map(|(account, value)| {
+                    if account != deployer {
+                        return (account, value)
+                    }
+                    let premint = treasury_left.min(value);
+                    treasury_left -= premint;
+                    (account, value - premint)
+                })
+                .collect();
+            if let Some(treasury) = treasury {
+                initial_balances.push((treasury, treasury_amount));
+            }
+            for (account, value) in initial_balances {
+                let balance = self.balance_of_or_zero(&account);
+                self.set_balance(account, balance + value);
+                self.after_token_transfer(None, Some(account), value);
+                if demurrage.is_some() {
+                    self.last_touched.insert(account, env::block_number());
+                }
+            }
         }
     }
 
     impl Erc20 {
+        /// Returns the semantic version of the contract code.
+        pub(external) fn version(&self) -> String {
+            let version = String::from(VERSION);
+            env.println(&format!("Erc20::version = {:?}", version));
+            version
+        }
+
+        /// Returns the version of the storage layout.
+        pub(external) fn storage_version(&self) -> u32 {
+            let storage_version = *self.storage_version;
+            env.println(&format!("Erc20::storage_version = {:?}", storage_version));
+            storage_version
+        }
+
+        /// Returns the name of the token.
+        pub(external) fn name(&self) -> String {
+            let name = (*self.name).clone();
+            env.println(&format!("Erc20::name = {:?}", name));
+            name
+        }
+
+        /// Returns the symbol of the token.
+        pub(external) fn symbol(&self) -> String {
+            let symbol = (*self.symbol).clone();
+            env.println(&format!("Erc20::symbol = {:?}", symbol));
+            symbol
+        }
+
+        /// Returns the number of decimals used to display token amounts.
+        pub(external) fn decimals(&self) -> u8 {
+            let decimals = *self.decimals;
+            env.println(&format!("Erc20::decimals = {:?}", decimals));
+            decimals
+        }
+
+        /// Converts `amount` whole tokens into the raw units balances are given in,
+        /// or returns `None` if the result does not fit into a `Balance`.
+        pub(external) fn to_units(&self, amount: Balance) -> Option<Balance> {
+            let units = units::to_units(amount, *self.decimals);
+            env.println(&format!("Erc20::to_units(amount = {:?}) = {:?}", amount, units));
+            units
+        }
+
+        /// Splits `units` raw units into whole tokens and the raw units of the
+        /// remaining fraction of a token.
+        pub(external) fn from_units(&self, units: Balance) -> (Balance, Balance) {
+            let amount = units::from_units(units, *self.decimals);
+            env.println(&format!("Erc20::from_units(units = {:?}) = {:?}", units, amount));
+            amount
+        }
+
         /// Returns the total number of tokens in existence.
         pub(external) fn total_supply(&self) -> Balance {
             let total_supply = *self.total_supply;
@@ -66,6 +936,36 @@ contract! {
             total_supply
         }
 
+        /// Returns the total number of tokens which have been burned.
+        ///
+        /// This counts every burn, including the share burned on transfers and
+        /// flash loans, which are minted and burned again within the same call.
+        /// Together with `total_minted`, the initial supply always equals
+        /// `total_supply + total_burned - total_minted`.
+        pub(external) fn total_burned(&self) -> Balance {
+            let total_burned = *self.total_burned;
+            env.println(&format!("Erc20::total_burned = {:?}", total_burned));
+            total_burned
+        }
+
+        /// Returns the total number of tokens which have been minted after
+        /// deployment.
+        ///
+        /// This counts every mint, including emissions, imported balances and
+        /// flash loans.
+        pub(external) fn total_minted(&self) -> Balance {
+            let total_minted = *self.total_minted;
+            env.println(&format!("Erc20::total_minted = {:?}", total_minted));
+            total_minted
+        }
+
+        /// Returns the maximum total supply of the token.
+        pub(external) fn cap(&self) -> Balance {
+            let cap = *self.cap;
+            env.println(&format!("Erc20::cap = {:?}", cap));
+            cap
+        }
+
         /// Returns the balance of the given AccountId.
         pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
             let balance = self.balance_of_or_zero(&owner);
@@ -84,132 +984,6242 @@ contract! {
         }
 
         /// Transfers token from the sender to the `to` AccountId.
-        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> bool {
-            self.transfer_impl(env.caller(), to, value)
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let result = self.try_transfer(env.caller(), to, value);
+            self.report_failure(env.caller(), result.err());
+            result
+        }
+
+        /// Transfers tokens from the sender to the `to` AccountId and attaches
+        /// `memo` to the transfer, e.g. a deposit reference for an exchange.
+        ///
+        /// Besides the usual `Transfer` event, this emits a `TransferWithMemo`
+        /// event carrying the memo, which may be at most `MAX_MEMO_LEN` bytes long.
+        pub(external) fn transfer_with_memo(&mut self, to: AccountId, value: Balance, memo: Vec<u8>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            if memo.len() > MAX_MEMO_LEN {
+                return Err(Erc20Error::MemoTooLong)
+            }
+            let from = env.caller();
+            self.transfer_impl(from, to, value)?;
+            deposit_event(Event::TransferWithMemo {
+                from: from,
+                to: to,
+                value: value,
+                memo: memo
+            });
+            Ok(())
+        }
+
+        /// Transfers tokens from the sender to the `to` AccountId and then
+        /// notifies `to` by calling its `on_token_transfer(from, value, data)`.
+        ///
+        /// Plain accounts simply receive the tokens. Contracts have to return `true`
+        /// to accept them. If the call fails or the contract returns `false`, the
+        /// whole call is reverted, so tokens cannot get stuck in contracts which
+        /// do not know how to handle them.
+        pub(external) fn transfer_and_call(&mut self, to: AccountId, value: Balance, data: Vec<u8>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let from = env.caller();
+            self.transfer_impl(from, to, value)?;
+            self.locked.set(true);
+            let acknowledgement: Option<Acknowledgement<bool>> = call_contract(
+                to,
+                ON_TOKEN_TRANSFER_SELECTOR,
+                &(from, value, data).encode(),
+            );
+            self.locked.set(false);
+            match acknowledgement {
+                Some(Acknowledgement::NoContract) | Some(Acknowledgement::Contract(true)) => Ok(()),
+                _ => panic!("the recipient did not accept the transfer"),
+            }
+        }
+
+        /// Transfers tokens from the sender to the `to` AccountId, making sure
+        /// that `to` can handle them, like the safe transfers of ERC-721.
+        ///
+        /// If `to` is a contract, the token calls its
+        /// `on_erc20_received(operator, from, value, data)`, which has to return
+        /// `ON_ERC20_RECEIVED_SELECTOR`. Otherwise the whole call is reverted.
+        pub(external) fn safe_transfer(&mut self, to: AccountId, value: Balance, data: Vec<u8>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let from = env.caller();
+            self.transfer_impl(from, to, value)?;
+            self.locked.set(true);
+            self.ensure_received(from, from, to, value, data);
+            self.locked.set(false);
+            Ok(())
+        }
+
+        /// Transfers tokens from the sender to each of the `recipients`.
+        ///
+        /// Either all transfers succeed or none of them is applied: every leg is
+        /// validated before the first one is carried out.
+        pub(external) fn transfer_batch(&mut self, recipients: Vec<(AccountId, Balance)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let from = env.caller();
+            // Settle the demurrage first, so that the legs are validated against
+            // the balance they will actually be taken from.
+            self.settle(from);
+            let mut total: Balance = 0;
+            for (to, value) in &recipients {
+                self.ensure_transferable(&Some(from), &Some(*to))?;
+                self.ensure_valid_recipient(to)?;
+                self.ensure_within_max_transfer(&from, to, *value)?;
+                total = total.checked_add(*value).ok_or(Erc20Error::InsufficientBalance)?;
+            }
+            if self.balance_of_or_zero(&from) < total {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            if self.free_balance_of_or_zero(&from) < total {
+                return Err(Erc20Error::BalanceLocked)
+            }
+            if self.quota_of(&from) < total {
+                return Err(Erc20Error::RateLimited)
+            }
+            self.ensure_volume_available(total)?;
+            for (to, value) in recipients {
+                self.transfer_impl(from, to, value)
+                    .expect("every leg has been validated up front");
+            }
+            Ok(())
         }
 
         /// Approve the passed AccountId to spend the specified amount of tokens
         /// on the behalf of the message's sender.
-        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> bool {
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let result = self.try_approve(env.caller(), spender, value);
+            self.report_failure(env.caller(), result.err());
+            result
+        }
+
+        /// Approves `spender` to spend `value` tokens of the caller and then
+        /// notifies `spender` by calling its `on_approval_received(owner, value, data)`.
+        ///
+        /// This lets a contract act on the approval, for example by pulling the
+        /// tokens in with `transfer_from`, within the same transaction. The spender
+        /// has to return `true` to accept the approval. If the call fails or the
+        /// spender returns `false`, the whole call is reverted.
+        pub(external) fn approve_and_call(&mut self, spender: AccountId, value: Balance, data: Vec<u8>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
             let owner = env.caller();
-            self.allowances.insert((owner, spender), value);
-            deposit_event(Event::Approval {
-                owner: owner,
-                spender: spender,
-                value: value
-            });
-            true
+            self.ensure_allowed_spender(&spender, value)?;
+            self.ensure_safe_approval(&owner, &spender, value)?;
+            self.allowance_deadlines.remove(&(owner, spender));
+            self.approve_impl(owner, spender, value)?;
+            // The approval is complete at this point, so unlike a flash loan the
+            // spender may call back into the token to spend it.
+            let accepted: Option<bool> = call_contract(
+                spender,
+                ON_APPROVAL_RECEIVED_SELECTOR,
+                &(owner, value, data).encode(),
+            );
+            assert_eq!(accepted, Some(true), "the spender did not accept the approval");
+            Ok(())
         }
 
         /// Transfer tokens from one AccountId to another.
-        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> bool {
+        ///
+        /// Returns the allowance which remains after the transfer.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<Balance, Erc20Error> {
+            let result = self.try_transfer_from(env.caller(), from, to, value);
+            self.report_failure(env.caller(), result.err());
+            result
+        }
+
+        /// Transfers tokens on behalf of several owners in a single call.
+        ///
+        /// Each operation is a `(from, to, value)` triple which spends from the
+        /// allowance `from` granted to the caller. Either all operations succeed or
+        /// none of them is applied: the allowances and balances needed by the whole
+        /// batch are validated before the first operation is carried out.
+        pub(external) fn transfer_from_batch(&mut self, ops: Vec<(AccountId, AccountId, Balance)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            let spender = env.caller();
+            // The total amount spent from each owner within this batch.
+            let mut totals: Vec<(AccountId, Balance)> = Vec::new();
+            for (from, to, value) in &ops {
+                self.ensure_transferable(&Some(*from), &Some(*to))?;
+                self.ensure_valid_recipient(to)?;
+                self.ensure_within_max_transfer(from, to, *value)?;
+                match totals.iter_mut().find(|(owner, _)| owner == from) {
+                    Some((_, total)) => {
+                        *total = total.checked_add(*value).ok_or(Erc20Error::InsufficientBalance)?
+                    }
+                    None => totals.push((*from, *value)),
+                }
+            }
+            for (from, total) in &totals {
+                self.settle(*from);
+                self.ensure_allowance_not_expired(from, &spender)?;
+                if self.allowance_or_zero(from, &spender) < *total {
+                    return Err(Erc20Error::InsufficientAllowance)
+                }
+                if self.balance_of_or_zero(from) < *total {
+                    return Err(Erc20Error::InsufficientBalance)
+                }
+                if self.free_balance_of_or_zero(from) < *total {
+                    return Err(Erc20Error::BalanceLocked)
+                }
+                if self.quota_of(from) < *total {
+                    return Err(Erc20Error::RateLimited)
+                }
+            }
+            let volume = totals.iter().fold(0 as Balance, |volume, (_, total)| volume.saturating_add(*total));
+            self.ensure_volume_available(volume)?;
+            for (from, to, value) in ops {
+                self.transfer_impl(from, to, value)
+                    .expect("every operation has been validated up front");
+                self.spend_allowance(from, spender, value);
+            }
+            Ok(())
+        }
+
+        /// Increases the allowance granted to `spender` by the caller by `delta`.
+        ///
+        /// The allowance saturates at the maximum `Balance` instead of overflowing.
+        pub(external) fn increase_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            let owner = env.caller();
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            self.ensure_allowed_spender(&spender, allowance.saturating_add(delta))?;
+            self.approve_impl(owner, spender, allowance.saturating_add(delta))
+        }
+
+        /// Decreases the allowance granted to `spender` by the caller by `delta`.
+        ///
+        /// The allowance saturates at zero instead of underflowing.
+        pub(external) fn decrease_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            let owner = env.caller();
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            self.approve_impl(owner, spender, allowance.saturating_sub(delta))
+        }
+
+        /// Creates `value` new tokens and assigns them to the `to` AccountId.
+        ///
+        /// Only accounts with the `Minter` role are allowed to mint new tokens.
+        pub(external) fn mint(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Minter, &env.caller())?;
+            self.mint_impl(to, value)
+        }
+
+        /// Destroys `value` tokens from the balance of the caller.
+        ///
+        /// Only accounts with the `Burner` role are allowed to burn tokens.
+        pub(external) fn burn(&mut self, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Burner, &env.caller())?;
+            self.burn_impl(env.caller(), value)
+        }
+
+        /// Destroys `value` tokens from the `from` AccountId using the
+        /// allowance granted to the caller.
+        ///
+        /// Only accounts with the `Burner` role are allowed to burn tokens.
+        pub(external) fn burn_from(&mut self, from: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Burner, &env.caller())?;
+            self.ensure_allowance_not_expired(&from, &env.caller())?;
             let allowance = self.allowance_or_zero(&from, &env.caller());
             if allowance < value {
-                return false
+                return Err(Erc20Error::InsufficientAllowance)
             }
-            self.allowances.insert((from, env.caller()), allowance - value);
-            self.transfer_impl(from, to, value)
+            self.burn_impl(from, value)?;
+            self.spend_allowance(from, env.caller(), value);
+            Ok(())
         }
-    }
 
-    impl Erc20 {
-        /// Returns the balance of the AccountId or 0 if there is no balance.
-        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
-            let balance = self.balances.get(of).unwrap_or(&0);
-            *balance
+        /// Returns `true` if transfers and approvals are currently halted.
+        pub(external) fn paused(&self) -> bool {
+            let paused = *self.paused;
+            env.println(&format!("Erc20::paused = {:?}", paused));
+            paused
         }
 
-        /// Returns the allowance or 0 of there is no allowance.
-        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
-            let allowance = self.allowances.get(&(*owner, *spender)).unwrap_or(&0);
-            *allowance
+        /// Halts all transfers and approvals.
+        ///
+        /// Only accounts with the `Pauser` role are allowed to pause the contract.
+        pub(external) fn pause(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Pauser, &env.caller())?;
+            self.ensure_not_paused()?;
+            self.paused.set(true);
+            deposit_event(Event::Paused {
+                account: env.caller()
+            });
+            Ok(())
         }
 
-        /// Transfers token from a specified AccountId to another AccountId.
-        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> bool {
-            let balance_from = self.balance_of_or_zero(&from);
-            let balance_to = self.balance_of_or_zero(&to);
-            if balance_from < value {
-                return false
+        /// Resumes transfers and approvals after the contract has been paused.
+        ///
+        /// Only accounts with the `Pauser` role are allowed to unpause the contract.
+        pub(external) fn unpause(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Pauser, &env.caller())?;
+            if !*self.paused {
+                return Err(Erc20Error::NotPaused)
             }
-            self.balances.insert(from, balance_from - value);
-            self.balances.insert(to, balance_to + value);
-            deposit_event(Event::Transfer { 
-                from: Some(from),
-                to: Some(to),
-                value: value
+            self.paused.set(false);
+            deposit_event(Event::Unpaused {
+                account: env.caller()
             });
-            true
+            Ok(())
         }
-    }
-}
 
-#[cfg(all(test, feature = "test-env"))]
-mod tests {
-    use super::*;
-    use std::convert::TryFrom;
+        /// Proposes `new_owner` as the next owner of the contract.
+        ///
+        /// Ownership only moves once `new_owner` calls `accept_ownership`.
+        /// Proposing another account replaces any earlier proposal.
+        pub(external) fn propose_owner(&mut self, new_owner: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            self.pending_owner.set(Some(new_owner));
+            deposit_event(Event::OwnershipProposed {
+                owner: env.caller(),
+                pending_owner: new_owner
+            });
+            Ok(())
+        }
 
-    #[test]
-    fn deployment_works() {
-        let alice = AccountId::try_from([0x0; 32]).unwrap();
-        env::test::set_caller(alice);
+        /// Accepts a pending ownership proposal made to the caller.
+        pub(external) fn accept_ownership(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let caller = env.caller();
+            if *self.pending_owner != Some(caller) {
+                return Err(Erc20Error::Unauthorized)
+            }
+            let previous_owner = *self.owner;
+            self.owner.set(caller);
+            self.pending_owner.set(None);
+            deposit_event(Event::OwnershipTransferred {
+                previous_owner: previous_owner,
+                new_owner: caller
+            });
+            Ok(())
+        }
 
-        // Deploy the contract with some `init_value`
-        let erc20 = Erc20::deploy_mock(1234);
-        // Check that the `total_supply` is `init_value`
-        assert_eq!(erc20.total_supply(), 1234);
-        // Check that `balance_of` Alice is `init_value`
-        assert_eq!(erc20.balance_of(alice), 1234);
-    }
+        /// Returns the owner of the contract, or `None` once ownership has been renounced.
+        pub(external) fn owner(&self) -> Option<AccountId> {
+            let owner = if *self.ownership_renounced {
+                None
+            } else {
+                Some(*self.owner)
+            };
+            env.println(&format!("Erc20::owner = {:?}", owner));
+            owner
+        }
 
-    #[test]
-    fn transfer_works() {
-        let alice = AccountId::try_from([0x0; 32]).unwrap();
-        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        /// Leaves the contract without an owner for good.
+        ///
+        /// Any pending proposal is dropped, and the messages reserved for the
+        /// owner can no longer be called by anyone. Roles are not affected.
+        pub(external) fn renounce_ownership(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            self.ownership_renounced.set(true);
+            self.pending_owner.set(None);
+            deposit_event(Event::OwnershipRenounced {
+                previous_owner: env.caller()
+            });
+            Ok(())
+        }
+
+        /// Returns `true` if `account` has been granted `role`.
+        pub(external) fn has_role(&self, role: Role, account: AccountId) -> bool {
+            let has_role = self.has_role_or_false(role, &account);
+            env.println(&format!(
+                "Erc20::has_role(role = {:?}, account = {:?}) = {:?}",
+                role, account, has_role
+            ));
+            has_role
+        }
+
+        /// Grants `role` to `account`.
+        ///
+        /// Only accounts with the `Admin` role are allowed to grant roles.
+        pub(external) fn grant_role(&mut self, role: Role, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.grant_role_impl(role, account, env.caller());
+            Ok(())
+        }
+
+        /// Revokes `role` from `account`.
+        ///
+        /// Only accounts with the `Admin` role are allowed to revoke roles.
+        pub(external) fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if self.roles.remove(&(role, account)).is_some() {
+                deposit_event(Event::RoleRevoked {
+                    role: role,
+                    account: account,
+                    sender: env.caller()
+                });
+            }
+            Ok(())
+        }
+
+        /// Returns `true` if `account` is barred from sending and receiving tokens.
+        pub(external) fn is_frozen(&self, account: AccountId) -> bool {
+            let frozen = self.is_frozen_or_false(&account);
+            env.println(&format!("Erc20::is_frozen(account = {:?}) = {:?}", account, frozen));
+            frozen
+        }
+
+        /// Bars `account` from sending and receiving tokens.
+        ///
+        /// Only accounts with the `Admin` role are allowed to freeze accounts.
+        pub(external) fn freeze(&mut self, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if !self.is_frozen_or_false(&account) {
+                self.frozen.insert(account, true);
+                deposit_event(Event::Frozen {
+                    account: account
+                });
+            }
+            Ok(())
+        }
+
+        /// Allows a frozen `account` to send and receive tokens again.
+        ///
+        /// Only accounts with the `Admin` role are allowed to unfreeze accounts.
+        pub(external) fn unfreeze(&mut self, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if self.frozen.remove(&account).is_some() {
+                deposit_event(Event::Unfrozen {
+                    account: account
+                });
+            }
+            Ok(())
+        }
+
+        /// Returns the transfer fee rate in basis points and the fee recipient.
+        pub(external) fn fee(&self) -> (u16, AccountId) {
+            let fee = (*self.fee_bps, *self.fee_recipient);
+            env.println(&format!("Erc20::fee = {:?}", fee));
+            fee
+        }
+
+        /// Returns the share of every transfer which is burned, in basis points.
+        pub(external) fn burn_rate(&self) -> u16 {
+            let burn_bps = *self.burn_bps;
+            env.println(&format!("Erc20::burn_rate = {:?}", burn_bps));
+            burn_bps
+        }
+
+        /// Changes the transfer fee rate and the account collecting the fees.
+        ///
+        /// Only accounts with the `Admin` role are allowed to change the fee.
+        pub(external) fn set_fee(&mut self, fee_bps: u16, fee_recipient: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if u32::from(fee_bps) + u32::from(*self.burn_bps) > u32::from(FEE_DENOMINATOR) {
+                return Err(Erc20Error::InvalidFee)
+            }
+            self.fee_bps.set(fee_bps);
+            self.fee_recipient.set(fee_recipient);
+            deposit_event(Event::FeeChanged {
+                fee_bps: fee_bps,
+                fee_recipient: fee_recipient
+            });
+            Ok(())
+        }
+
+        /// Returns the nonce the next signed message of `owner` has to use.
+        pub(external) fn nonce_of(&self, owner: AccountId) -> u64 {
+            let nonce = self.nonce_of_or_zero(&owner);
+            env.println(&format!("Erc20::nonce_of(owner = {:?}) = {:?}", owner, nonce));
+            nonce
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`
+        /// using a signature of `owner` instead of a call made by `owner`.
+        ///
+        /// The signature must be an sr25519 signature by `owner` over the SCALE
+        /// encoded `(owner, spender, value, nonce, deadline)` tuple, where `nonce`
+        /// is the current `nonce_of(owner)`. The permit can no longer be used
+        /// after the `deadline` block or once it has been used.
+        pub(external) fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: BlockNumber,
+            signature: [u8; 64],
+        ) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            if env.block_number() > deadline {
+                return Err(Erc20Error::Expired)
+            }
+            self.ensure_allowed_spender(&spender, value)?;
+            let nonce = self.nonce_of_or_zero(&owner);
+            let message = (owner, spender, value, nonce, deadline).encode();
+            if !verify_signature(&owner, &message, &signature) {
+                return Err(Erc20Error::InvalidSignature)
+            }
+            self.nonces.insert(owner, nonce + 1);
+            self.allowance_deadlines.remove(&(owner, spender));
+            self.approve_impl(owner, spender, value)
+        }
+
+        /// Transfers `value` tokens from `from` to `to` using a signature of
+        /// `from` instead of a call made by `from`.
+        ///
+        /// This lets a relayer submit (and pay for) transfers of users who only
+        /// sign them off-chain. The signature must be an sr25519 signature by
+        /// `from` over the SCALE encoded `(from, to, value, nonce, deadline)`
+        /// tuple, where `nonce` is the current `nonce_of(from)`. The signature can
+        /// no longer be used after the `deadline` block. The nonce is only
+        /// consumed if the transfer succeeds, so a failed transfer can be
+        /// submitted again until the deadline.
+        pub(external) fn transfer_by_signature(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: BlockNumber,
+            signature: [u8; 64],
+        ) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            if env.block_number() > deadline {
+                return Err(Erc20Error::Expired)
+            }
+            if nonce != self.nonce_of_or_zero(&from) {
+                return Err(Erc20Error::InvalidNonce)
+            }
+            let message = (from, to, value, nonce, deadline).encode();
+            if !verify_signature(&from, &message, &signature) {
+                return Err(Erc20Error::InvalidSignature)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.nonces.insert(from, nonce + 1);
+            Ok(())
+        }
+
+        /// Returns the maximum fee the relayer of a permit can claim from the owner.
+        pub(external) fn max_relayer_fee(&self) -> Balance {
+            let max_relayer_fee = *self.max_relayer_fee;
+            env.println(&format!("Erc20::max_relayer_fee = {:?}", max_relayer_fee));
+            max_relayer_fee
+        }
+
+        /// Works like `permit`, but also pays `fee` tokens of `owner` to the caller.
+        ///
+        /// This lets owners without any native balance approve spenders: a relayer
+        /// submits the permit and is reimbursed in tokens. The signature covers the
+        /// fee as well, as the SCALE encoded `(owner, spender, value, nonce, deadline, fee)`
+        /// tuple, and the fee must not exceed `max_relayer_fee`. If the fee cannot
+        /// be paid, the permit is not used.
+        pub(external) fn permit_with_fee(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: BlockNumber,
+            fee: Balance,
+            signature: [u8; 64],
+        ) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            if env.block_number() > deadline {
+                return Err(Erc20Error::Expired)
+            }
+            if fee > *self.max_relayer_fee {
+                return Err(Erc20Error::RelayerFeeTooHigh)
+            }
+            self.ensure_allowed_spender(&spender, value)?;
+            let nonce = self.nonce_of_or_zero(&owner);
+            let message = (owner, spender, value, nonce, deadline, fee).encode();
+            if !verify_signature(&owner, &message, &signature) {
+                return Err(Erc20Error::InvalidSignature)
+            }
+            self.transfer_impl(owner, env.caller(), fee)?;
+            self.nonces.insert(owner, nonce + 1);
+            self.allowance_deadlines.remove(&(owner, spender));
+            self.approve_impl(owner, spender, value)
+        }
+
+        /// Returns the account `account` has delegated its voting power to, if any.
+        pub(external) fn delegates(&self, account: AccountId) -> Option<AccountId> {
+            let delegate = self.delegates.get(&account).cloned();
+            env.println(&format!("Erc20::delegates(account = {:?}) = {:?}", account, delegate));
+            delegate
+        }
+
+        /// Returns the current voting power of `account`.
+        pub(external) fn get_votes(&self, account: AccountId) -> Balance {
+            let votes = self.votes_at(&account, BlockNumber::max_value());
+            env.println(&format!("Erc20::get_votes(account = {:?}) = {:?}", account, votes));
+            votes
+        }
+
+        /// Returns the voting power `account` had at the end of `block`.
+        pub(external) fn get_past_votes(&self, account: AccountId, block: BlockNumber) -> Balance {
+            let votes = self.votes_at(&account, block);
+            env.println(&format!(
+                "Erc20::get_past_votes(account = {:?}, block = {:?}) = {:?}",
+                account, block, votes
+            ));
+            votes
+        }
+
+        /// Delegates the voting power of the caller's tokens to `to`.
+        ///
+        /// Tokens only count as votes once they have been delegated, so holders
+        /// who want to vote themselves have to delegate to their own account.
+        pub(external) fn delegate(&mut self, to: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let delegator = env.caller();
+            // Settle the demurrage first, so that the decayed tokens are taken from
+            // the votes of the current delegate and only the rest is delegated.
+            self.settle(delegator);
+            let from_delegate = self.delegates.insert(delegator, to);
+            deposit_event(Event::DelegateChanged {
+                delegator: delegator,
+                from_delegate: from_delegate,
+                to_delegate: to
+            });
+            let votes = self.balance_of_or_zero(&delegator);
+            self.move_voting_power(from_delegate, Some(to), votes);
+            Ok(())
+        }
+
+        /// Takes a snapshot of all balances and the total supply and returns its id.
+        ///
+        /// Only accounts with the `Admin` role are allowed to take snapshots.
+        pub(external) fn snapshot(&mut self) -> Result<u32, Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            let id = *self.current_snapshot_id + 1;
+            self.current_snapshot_id.set(id);
+            deposit_event(Event::Snapshot {
+                id: id
+            });
+            Ok(id)
+        }
+
+        /// Returns the balance `account` had when snapshot `id` was taken.
+        ///
+        /// Returns `None` if no snapshot with the given id has been taken.
+        pub(external) fn balance_of_at(&self, account: AccountId, id: u32) -> Option<Balance> {
+            let balance = self.value_at(Some(account), id, self.balance_of_or_zero(&account));
+            env.println(&format!(
+                "Erc20::balance_of_at(account = {:?}, id = {:?}) = {:?}",
+                account, id, balance
+            ));
+            balance
+        }
+
+        /// Returns the total supply when snapshot `id` was taken.
+        ///
+        /// Returns `None` if no snapshot with the given id has been taken.
+        pub(external) fn total_supply_at(&self, id: u32) -> Option<Balance> {
+            let total_supply = self.value_at(None, id, *self.total_supply);
+            env.println(&format!("Erc20::total_supply_at(id = {:?}) = {:?}", id, total_supply));
+            total_supply
+        }
+
+        /// Returns the maximum amount of tokens available for a flash loan.
+        pub(external) fn max_flash_loan(&self) -> Balance {
+            let max_flash_loan = *self.cap - *self.total_supply;
+            env.println(&format!("Erc20::max_flash_loan = {:?}", max_flash_loan));
+            max_flash_loan
+        }
+
+        /// Returns the fee charged for a flash loan of `amount` tokens.
+        pub(external) fn flash_fee(&self, amount: Balance) -> Balance {
+            let fee = flash_fee_for(amount);
+            env.println(&format!("Erc20::flash_fee(amount = {:?}) = {:?}", amount, fee));
+            fee
+        }
+
+        /// Lends `amount` freshly minted tokens to `receiver` for the duration of
+        /// a single call.
+        ///
+        /// After minting, the token calls `on_flash_loan(initiator, amount, fee, data)`
+        /// on `receiver`, which has to return `FLASH_LOAN_CALLBACK_SUCCESS`. Once it
+        /// returns, the token takes back `amount + fee` tokens from `receiver` and
+        /// burns them. Since the token cannot be re-entered during the callback,
+        /// `receiver` has to allow this contract to take them before the loan is
+        /// requested. If the callback fails or the loan is not repaid, the whole
+        /// call is reverted.
+        pub(external) fn flash_loan(&mut self, receiver: AccountId, amount: Balance, data: Vec<u8>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let fee = flash_fee_for(amount);
+            self.mint_impl(receiver, amount)?;
+            self.locked.set(true);
+            let acknowledgement: Option<[u8; 32]> = call_contract(
+                receiver,
+                ON_FLASH_LOAN_SELECTOR,
+                &(env.caller(), amount, fee, data).encode(),
+            );
+            self.locked.set(false);
+            assert_eq!(
+                acknowledgement,
+                Some(FLASH_LOAN_CALLBACK_SUCCESS),
+                "the flash loan receiver did not acknowledge the loan"
+            );
+            let this = env.address();
+            let repayment = amount + fee;
+            let allowance = self.allowance_or_zero(&receiver, &this);
+            assert!(allowance >= repayment, "the flash loan has not been approved for repayment");
+            self.burn_impl(receiver, repayment)
+                .expect("the flash loan has not been repaid");
+            self.spend_allowance(receiver, this, repayment);
+            Ok(())
+        }
+
+        /// Sends `value` tokens of the ERC20 `token` held by this contract to `to`.
+        ///
+        /// Tokens sent to the address of this contract by mistake would be lost
+        /// otherwise. Only the owner can rescue tokens.
+        pub(external) fn rescue_tokens(&mut self, token: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            let this = env.address();
+            if token == this {
+                // Our own tokens do not need a cross-contract call.
+                return self.transfer_impl(this, to, value)
+            }
+            self.locked.set(true);
+            // The other token's error is decoded as its variant index.
+            let result: Option<Result<(), u8>> = call_contract(token, TRANSFER_SELECTOR, &(to, value).encode());
+            self.locked.set(false);
+            match result {
+                Some(Ok(())) => Ok(()),
+                _ => Err(Erc20Error::CallFailed),
+            }
+        }
+
+        /// Returns up to `limit` accounts with a non-zero balance, starting at
+        /// position `start` of the list of holders.
+        ///
+        /// The order of the list changes whenever an account stops holding tokens,
+        /// so page through it without tokens moving in between.
+        pub(external) fn holders(&self, start: u32, limit: u32) -> Vec<AccountId> {
+            let end = start.saturating_add(limit).min(self.holders.len());
+            let holders: Vec<AccountId> = (start..end)
+                .filter_map(|index| self.holders.get(index).cloned())
+                .collect();
+            env.println(&format!("Erc20::holders(start = {:?}, limit = {:?}) = {:?}", start, limit, holders));
+            holders
+        }
+
+        /// Returns the number of accounts with a non-zero balance.
+        pub(external) fn holder_count(&self) -> u32 {
+            let holder_count = *self.holder_count;
+            env.println(&format!("Erc20::holder_count = {:?}", holder_count));
+            holder_count
+        }
+
+        /// Returns up to `limit` of the non-zero allowances `owner` has granted,
+        /// as (spender, allowance), starting at position `start` of the list of
+        /// spenders of `owner`.
+        ///
+        /// Allowances whose deadline has passed are listed until they are
+        /// changed. Like `holders`, the order changes whenever an allowance
+        /// drops to zero.
+        pub(external) fn allowances_of(&self, owner: AccountId, start: u32, limit: u32) -> Vec<(AccountId, Balance)> {
+            let end = start.saturating_add(limit).min(self.spender_count_or_zero(&owner));
+            let allowances: Vec<(AccountId, Balance)> = (start..end)
+                .filter_map(|index| self.spenders.get(&(owner, index)).cloned())
+                .map(|spender| (spender, self.allowance_or_zero(&owner, &spender)))
+                .collect();
+            env.println(&format!(
+                "Erc20::allowances_of(owner = {:?}, start = {:?}, limit = {:?}) = {:?}",
+                owner, start, limit, allowances
+            ));
+            allowances
+        }
+
+        /// Approves `spender` to spend `value` tokens of the caller until block
+        /// `expires_at_block`, after which the allowance can no longer be spent.
+        ///
+        /// Changing the allowance with `increase_allowance` or `decrease_allowance`
+        /// keeps the deadline, while `approve` replaces it with an allowance that
+        /// never expires.
+        pub(external) fn approve_with_deadline(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+            expires_at_block: BlockNumber,
+        ) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            let owner = env.caller();
+            self.ensure_allowed_spender(&spender, value)?;
+            self.ensure_safe_approval(&owner, &spender, value)?;
+            self.allowance_deadlines.insert((owner, spender), expires_at_block);
+            self.approve_impl(owner, spender, value)
+        }
+
+        /// Returns the last block in which `spender` can spend the allowance
+        /// granted by `owner`, or `None` if the allowance does not expire.
+        pub(external) fn allowance_deadline(&self, owner: AccountId, spender: AccountId) -> Option<BlockNumber> {
+            let deadline = self.allowance_deadlines.get(&(owner, spender)).cloned();
+            env.println(&format!(
+                "Erc20::allowance_deadline(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, deadline
+            ));
+            deadline
+        }
+
+        /// Returns how many more tokens `account` may send in its current window.
+        ///
+        /// Returns the maximum `Balance` if transfers are not rate limited.
+        pub(external) fn remaining_quota(&self, account: AccountId) -> Balance {
+            let quota = self.quota_of(&account);
+            env.println(&format!("Erc20::remaining_quota(account = {:?}) = {:?}", account, quota));
+            quota
+        }
+
+        /// Limits every account to sending at most `max_amount` tokens within
+        /// `window` blocks, or lifts the limit if `limit` is `None`.
+        ///
+        /// An account's window starts with its first transfer and lasts `window`
+        /// blocks, after which the next transfer starts a new window.
+        /// Only accounts with the `Admin` role are allowed to change the limit.
+        pub(external) fn set_rate_limit(&mut self, limit: Option<(Balance, BlockNumber)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.rate_limit.set(limit);
+            deposit_event(Event::RateLimitChanged {
+                limit: limit
+            });
+            Ok(())
+        }
+
+        /// Returns the number of signer approvals a mint proposal needs.
+        pub(external) fn mint_threshold(&self) -> u32 {
+            let mint_threshold = *self.mint_threshold;
+            env.println(&format!("Erc20::mint_threshold = {:?}", mint_threshold));
+            mint_threshold
+        }
+
+        /// Changes the number of signer approvals a mint proposal needs.
+        ///
+        /// Only accounts with the `Admin` role are allowed to change the threshold,
+        /// which has to be at least 1.
+        pub(external) fn set_mint_threshold(&mut self, threshold: u32) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if threshold == 0 {
+                return Err(Erc20Error::InvalidThreshold)
+            }
+            self.mint_threshold.set(threshold);
+            Ok(())
+        }
+
+        /// Returns the mint proposal with the given `id`, if any.
+        pub(external) fn mint_proposal(&self, id: u32) -> Option<MintProposal> {
+            let proposal = self.mint_proposals.get(&id).cloned();
+            env.println(&format!("Erc20::mint_proposal(id = {:?}) = {:?}", id, proposal));
+            proposal
+        }
+
+        /// Proposes to mint `value` new tokens for `to` and returns the id of the proposal.
+        ///
+        /// The proposal counts as approved by the caller, who must have the
+        /// `MintSigner` role. The tokens are minted as soon as the proposal has
+        /// been approved by `mint_threshold` signers.
+        pub(external) fn propose_mint(&mut self, to: AccountId, value: Balance) -> Result<u32, Erc20Error> {
+            self.ensure_not_locked()?;
+            let proposer = env.caller();
+            self.ensure_role(Role::MintSigner, &proposer)?;
+            let id = *self.mint_proposal_count;
+            self.mint_proposal_count.set(id + 1);
+            self.mint_proposals.insert(id, MintProposal {
+                to: to,
+                value: value,
+                approvals: 0,
+                executed: false
+            });
+            deposit_event(Event::MintProposed {
+                id: id,
+                proposer: proposer,
+                to: to,
+                value: value
+            });
+            self.approve_mint_impl(id, proposer)?;
+            Ok(id)
+        }
+
+        /// Approves the mint proposal with the given `id`.
+        ///
+        /// The caller must have the `MintSigner` role and can approve every
+        /// proposal only once.
+        pub(external) fn approve_mint(&mut self, id: u32) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let approver = env.caller();
+            self.ensure_role(Role::MintSigner, &approver)?;
+            self.approve_mint_impl(id, approver)
+        }
+
+        /// Returns the amount of tokens of `account` which are currently locked.
+        pub(external) fn locked_of(&self, account: AccountId) -> Balance {
+            let locked = self.locked_of_or_zero(&account);
+            env.println(&format!("Erc20::locked_of(account = {:?}) = {:?}", account, locked));
+            locked
+        }
+
+        /// Returns the amount of tokens `account` can currently transfer.
+        pub(external) fn free_balance_of(&self, account: AccountId) -> Balance {
+            let free_balance = self.free_balance_of_or_zero(&account);
+            env.println(&format!("Erc20::free_balance_of(account = {:?}) = {:?}", account, free_balance));
+            free_balance
+        }
+
+        /// Locks `value` tokens of `account` until block `until`, replacing any
+        /// earlier lock of the account.
+        ///
+        /// The lock may exceed the current balance, in which case tokens received
+        /// later are locked as well. Only accounts with the `Locker` role, such as
+        /// an admin or a vesting contract, are allowed to lock balances.
+        pub(external) fn lock(&mut self, account: AccountId, value: Balance, until: BlockNumber) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Locker, &env.caller())?;
+            self.locks.insert(account, (value, until));
+            deposit_event(Event::Locked {
+                account: account,
+                value: value,
+                until: until
+            });
+            Ok(())
+        }
+
+        /// Returns `true` if balances can no longer be imported with `migrate_balances`.
+        pub(external) fn migration_finalized(&self) -> bool {
+            let migration_finalized = *self.migration_finalized;
+            env.println(&format!("Erc20::migration_finalized = {:?}", migration_finalized));
+            migration_finalized
+        }
+
+        /// Imports the balances of a previous instance of the token, minting
+        /// `value` new tokens for each `(account, value)` of the `entries`.
+        ///
+        /// Large token instances can be imported in several chunks, until the
+        /// migration is finalized with `finalize_migration`. Either all entries
+        /// of a chunk are imported or none of them is. Only accounts with the
+        /// `Admin` role are allowed to import balances.
+        pub(external) fn migrate_balances(&mut self, entries: Vec<(AccountId, Balance)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if *self.migration_finalized {
+                return Err(Erc20Error::MigrationFinalized)
+            }
+            let mut total: Balance = 0;
+            for (account, value) in &entries {
+                self.ensure_transferable(&None, &Some(*account))?;
+                total = total.checked_add(*value).ok_or(Erc20Error::CapExceeded)?;
+            }
+            match self.total_supply.checked_add(total) {
+                Some(total_supply) if total_supply <= *self.cap => (),
+                _ => return Err(Erc20Error::CapExceeded),
+            }
+            for (account, value) in entries {
+                self.mint_impl(account, value)
+                    .expect("every entry has been validated up front");
+            }
+            Ok(())
+        }
+
+        /// Closes `migrate_balances` for good once all balances have been imported.
+        ///
+        /// Only accounts with the `Admin` role are allowed to finalize the migration.
+        pub(external) fn finalize_migration(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.migration_finalized.set(true);
+            Ok(())
+        }
+
+        /// Replaces the code of the contract with the code stored under `code_hash`.
+        ///
+        /// The storage is kept as it is, so the new code has to understand its
+        /// layout. It can tell which layout it finds by the storage version.
+        /// Upgrading fails unless the storage has the layout this code works
+        /// with, so that no migration can be skipped. Only the owner can
+        /// upgrade the contract.
+        pub(external) fn upgrade(&mut self, code_hash: Hash) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            if *self.storage_version != STORAGE_VERSION {
+                return Err(Erc20Error::StorageVersionMismatch)
+            }
+            set_code_hash(code_hash);
+            deposit_event(Event::Upgraded {
+                code_hash: code_hash
+            });
+            Ok(())
+        }
+
+        /// Removes the contract from the chain and sends its remaining native
+        /// balance to `beneficiary`.
+        ///
+        /// All storage of the contract is removed with it, including every token
+        /// balance, so this is meant for tokens which are no longer in use.
+        /// Only the owner can terminate the contract.
+        pub(external) fn terminate(&mut self, beneficiary: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            terminate_contract(beneficiary);
+            Ok(())
+        }
+
+        /// Returns the share of every balance which decays per era in basis
+        /// points and the length of an era in blocks, or `None` if balances do
+        /// not decay.
+        pub(external) fn demurrage(&self) -> Option<(u16, BlockNumber)> {
+            let demurrage = *self.demurrage;
+            env.println(&format!("Erc20::demurrage = {:?}", demurrage));
+            demurrage
+        }
+
+        /// Returns the balance of `owner` after the demurrage which has not
+        /// been settled yet.
+        ///
+        /// Demurrage is only settled when tokens of an account move, so
+        /// `balance_of` returns the balance as of the last settlement.
+        pub(external) fn effective_balance_of(&self, owner: AccountId) -> Balance {
+            let (decay, _) = self.pending_decay(&owner);
+            let balance = self.balance_of_or_zero(&owner) - decay;
+            env.println(&format!("Erc20::effective_balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the native balance available for buybacks.
+        pub(external) fn buyback_pool(&self) -> Balance {
+            let buyback_pool = *self.buyback_pool;
+            env.println(&format!("Erc20::buyback_pool = {:?}", buyback_pool));
+            buyback_pool
+        }
+
+        /// Adds the native balance sent along with the call to the buyback pool.
+        ///
+        /// Anyone can fund buybacks, for example with the revenue of a protocol
+        /// built on the token.
+        pub(external) fn fund_buyback(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let value = value_transferred();
+            if value == 0 {
+                return Err(Erc20Error::ZeroValue)
+            }
+            let buyback_pool = self.buyback_pool.checked_add(value).ok_or(Erc20Error::Overflow)?;
+            self.buyback_pool.set(buyback_pool);
+            deposit_event(Event::BuybackFunded {
+                account: env.caller(),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Buys `burned` tokens from the treasury for `paid` of the native balance
+        /// in the buyback pool, and burns them.
+        ///
+        /// The price is negotiated off-chain, which is why both amounts are passed
+        /// in. If paying the treasury fails, the whole call is reverted. Only the
+        /// owner can carry out buybacks.
+        pub(external) fn buyback_and_burn(&mut self, paid: Balance, burned: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            let treasury = (*self.treasury).ok_or(Erc20Error::NoTreasury)?;
+            if paid > *self.buyback_pool {
+                return Err(Erc20Error::InsufficientBuybackPool)
+            }
+            self.burn_impl(treasury, burned)?;
+            self.buyback_pool -= paid;
+            assert!(transfer_native(treasury, paid), "the payment of the treasury failed");
+            deposit_event(Event::Buyback {
+                treasury: treasury,
+                paid: paid,
+                burned: burned
+            });
+            Ok(())
+        }
+
+        /// Returns the operators every holder has unless it revoked them.
+        pub(external) fn default_operators(&self) -> Vec<AccountId> {
+            let default_operators = (*self.default_operators).clone();
+            env.println(&format!("Erc20::default_operators = {:?}", default_operators));
+            default_operators
+        }
+
+        /// Returns `true` if `operator` may move any amount of the tokens of `holder`.
+        ///
+        /// Every holder is an operator for itself.
+        pub(external) fn is_operator_for(&self, operator: AccountId, holder: AccountId) -> bool {
+            let is_operator = self.is_operator_for_or_false(&operator, &holder);
+            env.println(&format!(
+                "Erc20::is_operator_for(operator = {:?}, holder = {:?}) = {:?}",
+                operator, holder, is_operator
+            ));
+            is_operator
+        }
+
+        /// Makes `operator` an operator for the caller, or restores a default
+        /// operator the caller revoked.
+        pub(external) fn authorize_operator(&mut self, operator: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let holder = env.caller();
+            if operator == holder {
+                return Err(Erc20Error::SelfOperator)
+            }
+            if self.default_operators.contains(&operator) {
+                self.revoked_default_operators.remove(&(holder, operator));
+            } else {
+                self.operators.insert((holder, operator), true);
+            }
+            deposit_event(Event::AuthorizedOperator {
+                operator: operator,
+                holder: holder
+            });
+            Ok(())
+        }
+
+        /// Stops `operator` from moving the tokens of the caller, including
+        /// default operators.
+        pub(external) fn revoke_operator(&mut self, operator: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            let holder = env.caller();
+            if operator == holder {
+                return Err(Erc20Error::SelfOperator)
+            }
+            if self.default_operators.contains(&operator) {
+                self.revoked_default_operators.insert((holder, operator), true);
+            } else {
+                self.operators.remove(&(holder, operator));
+            }
+            deposit_event(Event::RevokedOperator {
+                operator: operator,
+                holder: holder
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens from `from` to `to` on behalf of `from`.
+        ///
+        /// The caller has to be an operator for `from`. Operators do not use
+        /// allowances: they can move any amount, and the allowances `from`
+        /// granted stay as they are.
+        pub(external) fn operator_transfer(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let result = self.try_operator_transfer(env.caller(), from, to, value);
+            self.report_failure(env.caller(), result.err());
+            result
+        }
+
+        /// Returns the tokens emitted per era and the length of an era in blocks,
+        /// or `None` if there is no inflation.
+        pub(external) fn emission_schedule(&self) -> Option<(Balance, BlockNumber)> {
+            let emission_schedule = *self.emission_schedule;
+            env.println(&format!("Erc20::emission_schedule = {:?}", emission_schedule));
+            emission_schedule
+        }
+
+        /// Returns the emission which can be claimed at the current block,
+        /// including the eras which have not been accrued yet.
+        pub(external) fn pending_emission(&self) -> Balance {
+            let (_, unaccrued) = self.unaccrued_emission();
+            let pending_emission = self.accrued_emission.saturating_add(unaccrued);
+            env.println(&format!("Erc20::pending_emission = {:?}", pending_emission));
+            pending_emission
+        }
+
+        /// Emits `per_era` new tokens every `era_length` blocks from now on,
+        /// or stops the inflation if `schedule` is `None`.
+        ///
+        /// The emission of the eras which completed under the previous schedule
+        /// is accrued first, so it can still be claimed. Only the owner can
+        /// change the schedule.
+        pub(external) fn set_emission_schedule(&mut self, schedule: Option<(Balance, BlockNumber)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            if let Some((_, 0)) = schedule {
+                return Err(Erc20Error::InvalidEra)
+            }
+            self.accrue_impl();
+            self.emission_schedule.set(schedule);
+            self.last_accrual.set(env.block_number());
+            deposit_event(Event::EmissionChanged {
+                schedule: schedule
+            });
+            Ok(())
+        }
+
+        /// Adds the emission of every era which completed since the last
+        /// accrual to the pending emission and returns the pending emission.
+        ///
+        /// Only complete eras are accrued. The blocks of the current era keep
+        /// counting towards it. Anybody can accrue the emission.
+        pub(external) fn accrue(&mut self) -> Result<Balance, Erc20Error> {
+            self.ensure_not_locked()?;
+            self.accrue_impl();
+            Ok(*self.accrued_emission)
+        }
+
+        /// Accrues the emission and mints all of it to `to`.
+        ///
+        /// The minted tokens count against the cap. If they would exceed it,
+        /// nothing is minted and the emission stays pending. Only the owner
+        /// can claim the emission.
+        pub(external) fn claim_emission(&mut self, to: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_owner(&env.caller())?;
+            self.accrue_impl();
+            let value = *self.accrued_emission;
+            self.mint_impl(to, value)?;
+            self.accrued_emission.set(0);
+            Ok(())
+        }
+
+        /// Returns the maximum amount a single transfer may move, or `None` if
+        /// transfers are not limited.
+        pub(external) fn max_transfer_amount(&self) -> Option<Balance> {
+            let max_transfer_amount = *self.max_transfer_amount;
+            env.println(&format!("Erc20::max_transfer_amount = {:?}", max_transfer_amount));
+            max_transfer_amount
+        }
+
+        /// Returns `true` if the transfers of `account` are not limited by
+        /// `max_transfer_amount`.
+        pub(external) fn is_exempt(&self, account: AccountId) -> bool {
+            let exempt = self.is_exempt_or_false(&account);
+            env.println(&format!("Erc20::is_exempt(account = {:?}) = {:?}", account, exempt));
+            exempt
+        }
+
+        /// Limits every transfer to at most `max_transfer_amount` tokens, or
+        /// lifts the limit if it is `None`.
+        ///
+        /// Transfers from or to exempt accounts are not limited. Only accounts
+        /// with the `Admin` role are allowed to change the limit.
+        pub(external) fn set_max_transfer(&mut self, max_transfer_amount: Option<Balance>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.max_transfer_amount.set(max_transfer_amount);
+            deposit_event(Event::MaxTransferChanged {
+                max_transfer_amount: max_transfer_amount
+            });
+            Ok(())
+        }
+
+        /// Exempts the transfers from and to `account` from the maximum transfer
+        /// amount, for example for the treasury or a DEX pair.
+        ///
+        /// Only accounts with the `Admin` role are allowed to exempt accounts.
+        pub(external) fn exempt(&mut self, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if !self.is_exempt_or_false(&account) {
+                self.exempt.insert(account, true);
+                deposit_event(Event::Exempted {
+                    account: account
+                });
+            }
+            Ok(())
+        }
+
+        /// Subjects the transfers of an exempt `account` to the maximum transfer
+        /// amount again.
+        ///
+        /// Only accounts with the `Admin` role are allowed to unexempt accounts.
+        pub(external) fn unexempt(&mut self, account: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if self.exempt.remove(&account).is_some() {
+                deposit_event(Event::Unexempted {
+                    account: account
+                });
+            }
+            Ok(())
+        }
+
+        /// Returns `true` if `spender` is on the allow-list of spenders.
+        ///
+        /// The allow-list only limits approvals if the contract has been
+        /// deployed with `restrict_spenders`.
+        pub(external) fn is_allowed_spender(&self, spender: AccountId) -> bool {
+            let allowed = self.is_allowed_spender_or_false(&spender);
+            env.println(&format!("Erc20::is_allowed_spender(spender = {:?}) = {:?}", spender, allowed));
+            allowed
+        }
+
+        /// Adds `spender`, usually an audited contract, to the allow-list of spenders.
+        ///
+        /// Only accounts with the `Admin` role are allowed to change the allow-list.
+        pub(external) fn allow_spender(&mut self, spender: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if !self.is_allowed_spender_or_false(&spender) {
+                self.allowed_spenders.insert(spender, true);
+                deposit_event(Event::SpenderAllowed {
+                    spender: spender
+                });
+            }
+            Ok(())
+        }
+
+        /// Removes `spender` from the allow-list of spenders.
+        ///
+        /// Allowances granted earlier can still be spent, but they can no longer
+        /// be raised. Only accounts with the `Admin` role are allowed to change
+        /// the allow-list.
+        pub(external) fn deny_spender(&mut self, spender: AccountId) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            if self.allowed_spenders.remove(&spender).is_some() {
+                deposit_event(Event::SpenderDenied {
+                    spender: spender
+                });
+            }
+            Ok(())
+        }
+
+        /// Returns `true` if transfers have been halted by the circuit breaker.
+        pub(external) fn circuit_tripped(&self) -> bool {
+            let circuit_tripped = *self.circuit_tripped;
+            env.println(&format!("Erc20::circuit_tripped = {:?}", circuit_tripped));
+            circuit_tripped
+        }
+
+        /// Returns the transfer volume which trips the circuit breaker within a
+        /// window of blocks, or `None` if it only trips manually.
+        pub(external) fn circuit_threshold(&self) -> Option<(Balance, BlockNumber)> {
+            let circuit_threshold = *self.circuit_threshold;
+            env.println(&format!("Erc20::circuit_threshold = {:?}", circuit_threshold));
+            circuit_threshold
+        }
+
+        /// Trips the circuit breaker automatically once more than `max_volume`
+        /// tokens are transferred within `window` blocks, or turns the automatic
+        /// trip off if `threshold` is `None`.
+        ///
+        /// A window starts with the first transfer after the previous window
+        /// ended. Only accounts with the `Admin` role are allowed to change the
+        /// threshold.
+        pub(external) fn set_circuit_threshold(&mut self, threshold: Option<(Balance, BlockNumber)>) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.circuit_threshold.set(threshold);
+            Ok(())
+        }
+
+        /// Halts all transfers at once, for example while an exploit is under way.
+        ///
+        /// Minting and burning keep working. Only accounts with the `Guardian`
+        /// role are allowed to trip the circuit breaker.
+        pub(external) fn trip_circuit(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Guardian, &env.caller())?;
+            if !*self.circuit_tripped {
+                self.trip_circuit_impl(Some(env.caller()));
+            }
+            Ok(())
+        }
+
+        /// Resumes transfers after the circuit breaker tripped and starts a new
+        /// volume window.
+        ///
+        /// Guardians can only halt transfers. Resuming them is up to accounts
+        /// with the `Admin` role.
+        pub(external) fn reset_circuit(&mut self) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_role(Role::Admin, &env.caller())?;
+            self.volume_window.set((env.block_number(), 0));
+            if !*self.circuit_tripped {
+                return Ok(())
+            }
+            self.circuit_tripped.set(false);
+            deposit_event(Event::CircuitReset {
+                account: env.caller()
+            });
+            Ok(())
+        }
+    }
+
+    impl Erc20 {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            let balance = self.balances.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns the balance of `account` as a `TokenAmount`.
+        fn amount_of(&self, account: &AccountId) -> TokenAmount {
+            TokenAmount::from(self.balance_of_or_zero(account))
+        }
+
+        /// Adds `value` to the balance of `account`.
+        ///
+        /// No balance can hold more than the total supply, which is a `Balance`
+        /// itself, so this cannot overflow once the supply has been accounted for.
+        fn credit(&mut self, account: AccountId, value: TokenAmount) {
+            let balance = (self.amount_of(&account) + value)
+                .expect("a balance never exceeds the total supply");
+            self.set_balance(account, balance.into());
+        }
+
+        /// Removes `value` burned tokens from the total supply.
+        fn reduce_total_supply(&mut self, value: TokenAmount) {
+            let total_supply = (TokenAmount::from(*self.total_supply) - value)
+                .expect("the total supply covers every balance");
+            self.total_supply.set(total_supply.into());
+        }
+
+        /// Sets the balance of `account`, keeping track of who holds tokens.
+        ///
+        /// Every change to a balance has to go through here so that `holders`
+        /// lists exactly the accounts with a non-zero balance. Zero balances are
+        /// removed from storage rather than stored, which frees up their storage.
+        fn set_balance(&mut self, account: AccountId, balance: Balance) {
+            if balance == 0 {
+                self.balances.remove(&account);
+            } else {
+                self.balances.insert(account, balance);
+            }
+            let index = self.holder_indices.get(&account).cloned();
+            match (index, balance) {
+                (None, balance) if balance > 0 => {
+                    self.holder_indices.insert(account, self.holders.len());
+                    self.holders.push(account);
+                    self.holder_count += 1;
+                }
+                (Some(index), 0) => {
+                    self.holder_count -= 1;
+                    self.holder_indices.remove(&account);
+                    self.holders.swap_remove(index);
+                    // The last holder has been moved into the freed position.
+                    if let Some(moved) = self.holders.get(index).cloned() {
+                        self.holder_indices.insert(moved, index);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        /// Returns the amount of tokens of `account` which are locked at the current block.
+        fn locked_of_or_zero(&self, account: &AccountId) -> Balance {
+            match self.locks.get(account) {
+                Some((value, until)) if env::block_number() < *until => *value,
+                _ => 0,
+            }
+        }
+
+        /// Returns the balance of `account` minus the tokens which are currently locked.
+        fn free_balance_of_or_zero(&self, account: &AccountId) -> Balance {
+            self.balance_of_or_zero(account).saturating_sub(self.locked_of_or_zero(account))
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            let allowance = self.allowances.get(&(*owner, *spender)).unwrap_or(&0);
+            *allowance
+        }
+
+        /// Returns the number of spenders with a non-zero allowance of `owner`.
+        fn spender_count_or_zero(&self, owner: &AccountId) -> u32 {
+            let count = self.spender_counts.get(owner).unwrap_or(&0);
+            *count
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`,
+        /// removing it from storage if it is zero.
+        ///
+        /// Every change to an allowance has to go through here so that the
+        /// spenders of each owner list exactly the non-zero allowances.
+        fn set_allowance(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            if value == 0 {
+                self.allowances.remove(&(owner, spender));
+            } else {
+                self.allowances.insert((owner, spender), value);
+            }
+            let count = self.spender_count_or_zero(&owner);
+            let index = self.spender_indices.get(&(owner, spender)).cloned();
+            match (index, value) {
+                (None, value) if value > 0 => {
+                    self.spenders.insert((owner, count), spender);
+                    self.spender_indices.insert((owner, spender), count);
+                    self.spender_counts.insert(owner, count + 1);
+                }
+                (Some(index), 0) => {
+                    // Move the last spender into the freed position.
+                    let last = count - 1;
+                    let moved = self.spenders.remove(&(owner, last)).expect("every index below the count is taken");
+                    self.spender_indices.remove(&(owner, spender));
+                    if index != last {
+                        self.spenders.insert((owner, index), moved);
+                        self.spender_indices.insert((owner, moved), index);
+                    }
+                    if last == 0 {
+                        self.spender_counts.remove(&owner);
+                    } else {
+                        self.spender_counts.insert(owner, last);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        /// Spends `value` of the allowance `owner` granted to `spender`.
+        ///
+        /// An allowance of `Balance::max_value()` counts as infinite and is left
+        /// as it is, so that owners do not have to top it up.
+        fn spend_allowance(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            if allowance == Balance::max_value() {
+                return
+            }
+            let remaining = (TokenAmount::from(allowance) - TokenAmount::from(value))
+                .expect("the caller has checked the allowance");
+            self.set_allowance(owner, spender, remaining.into());
+        }
+
+        /// Returns the number of complete eras since the last accrual and the
+        /// tokens emitted in them.
+        fn unaccrued_emission(&self) -> (BlockNumber, Balance) {
+            match *self.emission_schedule {
+                Some((per_era, era_length)) => {
+                    let eras = env::block_number().saturating_sub(*self.last_accrual) / era_length;
+                    (eras, per_era.saturating_mul(eras as Balance))
+                }
+                None => (0, 0),
+            }
+        }
+
+        /// Moves the emission of every complete era since the last accrual
+        /// into `accrued_emission`.
+        fn accrue_impl(&mut self) {
+            let (eras, value) = self.unaccrued_emission();
+            if let Some((_, era_length)) = *self.emission_schedule {
+                self.last_accrual += eras * era_length;
+            }
+            let accrued_emission = self.accrued_emission.saturating_add(value);
+            self.accrued_emission.set(accrued_emission);
+        }
+
+        /// Returns an error if `caller` is not the owner of the contract.
+        fn ensure_owner(&self, caller: &AccountId) -> Result<(), Erc20Error> {
+            if *self.ownership_renounced || *caller != *self.owner {
+                return Err(Erc20Error::Unauthorized)
+            }
+            Ok(())
+        }
+
+        /// Returns the nonce of the AccountId or 0 if it has not signed anything yet.
+        fn nonce_of_or_zero(&self, owner: &AccountId) -> u64 {
+            let nonce = self.nonces.get(owner).unwrap_or(&0);
+            *nonce
+        }
+
+        /// Returns `true` if the account is frozen or `false` otherwise.
+        fn is_frozen_or_false(&self, account: &AccountId) -> bool {
+            let frozen = self.frozen.get(account).unwrap_or(&false);
+            *frozen
+        }
+
+        /// Halts all transfers. `guardian` is `None` if the transfer volume
+        /// tripped the circuit breaker.
+        fn trip_circuit_impl(&mut self, guardian: Option<AccountId>) {
+            self.circuit_tripped.set(true);
+            deposit_event(Event::CircuitTripped {
+                guardian: guardian
+            });
+        }
+
+        /// Returns the volume window which applies at the current block, as
+        /// (first block, volume), starting a new one if the last has ended.
+        fn current_volume_window(&self, window: BlockNumber) -> (BlockNumber, Balance) {
+            let now = env::block_number();
+            let (start, volume) = *self.volume_window;
+            if now < start.saturating_add(window) {
+                (start, volume)
+            } else {
+                (now, 0)
+            }
+        }
+
+        /// Trips the circuit breaker and returns an error if transferring
+        /// another `value` tokens would exceed the threshold.
+        fn ensure_volume_available(&mut self, value: Balance) -> Result<(), Erc20Error> {
+            if let Some((max_volume, window)) = *self.circuit_threshold {
+                let (_, volume) = self.current_volume_window(window);
+                if max_volume.saturating_sub(volume) < value {
+                    self.trip_circuit_impl(None);
+                    return Err(Erc20Error::CircuitTripped)
+                }
+            }
+            Ok(())
+        }
+
+        /// Counts `value` against the transfer volume of the current window.
+        ///
+        /// Call `ensure_volume_available` first, so that the threshold is not exceeded.
+        fn record_volume(&mut self, value: Balance) {
+            if let Some((_, window)) = *self.circuit_threshold {
+                let (start, volume) = self.current_volume_window(window);
+                self.volume_window.set((start, volume + value));
+            }
+        }
+
+        /// Returns `true` if `account` is exempt from the maximum transfer amount.
+        fn is_exempt_or_false(&self, account: &AccountId) -> bool {
+            let exempt = self.exempt.get(account).unwrap_or(&false);
+            *exempt
+        }
+
+        /// Returns an error if `value` exceeds the maximum transfer amount and
+        /// neither `from` nor `to` is exempt from it.
+        fn ensure_within_max_transfer(&self, from: &AccountId, to: &AccountId, value: Balance) -> Result<(), Erc20Error> {
+            match *self.max_transfer_amount {
+                Some(max_transfer_amount)
+                    if value > max_transfer_amount
+                        && !self.is_exempt_or_false(from)
+                        && !self.is_exempt_or_false(to) =>
+                {
+                    Err(Erc20Error::TransferTooLarge)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Returns `true` if the account has the role or `false` otherwise.
+        fn has_role_or_false(&self, role: Role, account: &AccountId) -> bool {
+            let has_role = self.roles.get(&(role, *account)).unwrap_or(&false);
+            *has_role
+        }
+
+        /// Returns an error if `account` has not been granted `role`.
+        fn ensure_role(&self, role: Role, account: &AccountId) -> Result<(), Erc20Error> {
+            if !self.has_role_or_false(role, account) {
+                return Err(Erc20Error::Unauthorized)
+            }
+            Ok(())
+        }
+
+        /// Grants `role` to `account` on behalf of `sender`.
+        fn grant_role_impl(&mut self, role: Role, account: AccountId, sender: AccountId) {
+            if self.has_role_or_false(role, &account) {
+                return
+            }
+            self.roles.insert((role, account), true);
+            deposit_event(Event::RoleGranted {
+                role: role,
+                account: account,
+                sender: sender
+            });
+        }
+
+        /// Returns an error if the contract is in the middle of a cross-contract call.
+        ///
+        /// Every message which changes the state of the contract calls this first,
+        /// so that the contracts we call cannot call back into us to change state
+        /// we are still relying on.
+        fn ensure_not_locked(&self) -> Result<(), Erc20Error> {
+            if *self.locked {
+                return Err(Erc20Error::ReentrantCall)
+            }
+            Ok(())
+        }
+
+        /// Returns an error if the allowance `owner` granted to `spender` has expired.
+        fn ensure_allowance_not_expired(&self, owner: &AccountId, spender: &AccountId) -> Result<(), Erc20Error> {
+            match self.allowance_deadlines.get(&(*owner, *spender)) {
+                Some(deadline) if env::block_number() > *deadline => Err(Erc20Error::Expired),
+                _ => Ok(()),
+            }
+        }
+
+        /// Returns an error if the contract is currently paused.
+        fn ensure_not_paused(&self) -> Result<(), Erc20Error> {
+            if *self.paused {
+                return Err(Erc20Error::Paused)
+            }
+            Ok(())
+        }
+
+        /// Returns an error if the contract has been deployed with strict approvals
+        /// and the allowance of `spender` would change from one non-zero value to another.
+        ///
+        /// Changing a non-zero allowance directly lets the spender race the change and
+        /// spend both the old and the new allowance. In strict mode, owners have to set
+        /// the allowance to zero first and check what has been spent in the meantime.
+        fn ensure_safe_approval(&self, owner: &AccountId, spender: &AccountId, value: Balance) -> Result<(), Erc20Error> {
+            if *self.strict_approvals && value != 0 && self.allowance_or_zero(owner, spender) != 0 {
+                return Err(Erc20Error::AllowanceNotZero)
+            }
+            Ok(())
+        }
+
+        /// Returns the demurrage `account` owes but has not settled yet, and the
+        /// block up to which settling it accounts for.
+        ///
+        /// Only whole eras decay, so the block does not move past the start of
+        /// the current era and the era which has begun is not lost.
+        fn pending_decay(&self, account: &AccountId) -> (Balance, BlockNumber) {
+            let now = env::block_number();
+            let (rate_bps, era) = match *self.demurrage {
+                Some(demurrage) => demurrage,
+                None => return (0, now),
+            };
+            let last_touched = match self.last_touched.get(account) {
+                Some(last_touched) => *last_touched,
+                None => return (0, now),
+            };
+            let eras = (now - last_touched) / era;
+            let balance = self.balance_of_or_zero(account);
+            (balance - decayed_balance(balance, rate_bps, eras), last_touched + eras * era)
+        }
+
+        /// Burns the demurrage `account` owes and records the block it has been
+        /// settled up to.
+        fn settle(&mut self, account: AccountId) {
+            if self.demurrage.is_none() {
+                return
+            }
+            let (decay, settled_until) = self.pending_decay(&account);
+            if decay > 0 {
+                let balance = self.balance_of_or_zero(&account);
+                self.update_snapshot(Some(account), balance);
+                self.update_snapshot(None, *self.total_supply);
+                self.set_balance(account, balance - decay);
+                self.reduce_total_supply(TokenAmount::from(decay));
+                self.total_burned += decay;
+                self.after_token_transfer(Some(account), None, decay);
+            }
+            self.last_touched.insert(account, settled_until);
+        }
+
+        /// Returns `true` if `operator` is an operator for `holder`, or `false` otherwise.
+        fn is_operator_for_or_false(&self, operator: &AccountId, holder: &AccountId) -> bool {
+            if *operator == *holder {
+                return true
+            }
+            if self.default_operators.contains(operator) {
+                return !*self.revoked_default_operators.get(&(*holder, *operator)).unwrap_or(&false)
+            }
+            *self.operators.get(&(*holder, *operator)).unwrap_or(&false)
+        }
+
+        /// Returns `true` if `spender` is on the allow-list of spenders.
+        fn is_allowed_spender_or_false(&self, spender: &AccountId) -> bool {
+            let allowed = self.allowed_spenders.get(spender).unwrap_or(&false);
+            *allowed
+        }
+
+        /// Returns an error if the contract only accepts spenders on its allow-list
+        /// and `spender` is not one of them.
+        ///
+        /// Allowances of zero are always accepted, so that owners can revoke the
+        /// allowances of spenders which have been removed from the list.
+        fn ensure_allowed_spender(&self, spender: &AccountId, value: Balance) -> Result<(), Erc20Error> {
+            if *self.restrict_spenders && value != 0 && !self.is_allowed_spender_or_false(spender) {
+                return Err(Erc20Error::SpenderNotAllowed)
+            }
+            Ok(())
+        }
+
+        /// Returns an error if `to` is the address of the token contract itself,
+        /// unless the contract has been deployed to accept such transfers.
+        ///
+        /// The contract cannot spend tokens it holds on its own, so tokens sent
+        /// there by pasting the token address as the recipient would be stuck.
+        fn ensure_valid_recipient(&self, to: &AccountId) -> Result<(), Erc20Error> {
+            if !*self.allow_transfers_to_contract && *to == env::address() {
+                return Err(Erc20Error::TransferToContract)
+            }
+            Ok(())
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`.
+        fn approve_impl(&mut self, owner: AccountId, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.set_allowance(owner, spender, value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Deposits a `Failure` event with `error` as the reason if there is an
+        /// error and the contract has been deployed to report failures.
+        ///
+        /// Rejected calls only return their error to the caller, which off-chain
+        /// indexers do not see without decoding the call results.
+        fn report_failure(&self, caller: AccountId, error: Option<Erc20Error>) {
+            if let (Some(reason), true) = (error, *self.report_failures) {
+                deposit_event(Event::Failure {
+                    reason: reason,
+                    caller: caller
+                });
+            }
+        }
+
+        /// Transfers `value` tokens from `from` to `to`, see `transfer`.
+        fn try_transfer(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.transfer_impl(from, to, value)
+        }
+
+        /// Approves `spender` to spend `value` tokens of `owner`, see `approve`.
+        fn try_approve(&mut self, owner: AccountId, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            self.ensure_allowed_spender(&spender, value)?;
+            self.ensure_safe_approval(&owner, &spender, value)?;
+            self.set_allowance(owner, spender, value);
+            self.allowance_deadlines.remove(&(owner, spender));
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens from `from` to `to` on behalf of `operator`,
+        /// see `operator_transfer`.
+        fn try_operator_transfer(&mut self, operator: AccountId, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            if !self.is_operator_for_or_false(&operator, &from) {
+                return Err(Erc20Error::NotOperator)
+            }
+            self.transfer_impl(from, to, value)
+        }
+
+        /// Transfers `value` tokens from `from` to `to` on behalf of `spender`,
+        /// see `transfer_from`.
+        fn try_transfer_from(&mut self, spender: AccountId, from: AccountId, to: AccountId, value: Balance) -> Result<Balance, Erc20Error> {
+            self.ensure_not_locked()?;
+            self.ensure_not_paused()?;
+            self.ensure_allowance_not_expired(&from, &spender)?;
+            let allowance = self.allowance_or_zero(&from, &spender);
+            if allowance < value {
+                return Err(Erc20Error::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.spend_allowance(from, spender, value);
+            Ok(self.allowance_or_zero(&from, &spender))
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        ///
+        /// The transfer fee and the burned share are deducted from `value`, so `to`
+        /// receives `value - fee - burned`, the fee recipient receives the fee and
+        /// the burned tokens are removed from the total supply. The fee leg emits
+        /// a `Transfer` event and the burned share a `Burn` and a `Transfer` event.
+        ///
+        /// Transfers to the `BURN_ADDRESS` burn all of `value` without any fee.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let fee_recipient = *self.fee_recipient;
+            for account in &[from, to, fee_recipient] {
+                self.settle(*account);
+            }
+            if to == burn_address() {
+                // Locked tokens cannot be burned by sending them away either.
+                if self.balance_of_or_zero(&from) >= value && self.free_balance_of_or_zero(&from) < value {
+                    return Err(Erc20Error::BalanceLocked)
+                }
+                return self.burn_impl(from, value)
+            }
+            self.ensure_valid_recipient(&to)?;
+            self.ensure_within_max_transfer(&from, &to, value)?;
+            self.before_token_transfer(Some(from), Some(to), value)?;
+            let remaining = (self.amount_of(&from) - TokenAmount::from(value))
+                .ok_or(Erc20Error::InsufficientBalance)?;
+            if self.free_balance_of_or_zero(&from) < value {
+                return Err(Erc20Error::BalanceLocked)
+            }
+            if self.quota_of(&from) < value {
+                return Err(Erc20Error::RateLimited)
+            }
+            self.ensure_volume_available(value)?;
+            // Only count the transfer once every check has passed.
+            self.consume_quota(from, value);
+            self.record_volume(value);
+            let fee = self.fee_for(value);
+            let burned = self.burned_for(value);
+            let received = (TokenAmount::from(value) - TokenAmount::from(fee))
+                .and_then(|rest| rest - TokenAmount::from(burned))
+                .expect("the fee and the burned share never exceed the value");
+            self.set_balance(from, remaining.into());
+            self.credit(to, received);
+            self.after_token_transfer(Some(from), Some(to), received.into());
+            if fee > 0 {
+                let fee_recipient = *self.fee_recipient;
+                self.credit(fee_recipient, TokenAmount::from(fee));
+                self.after_token_transfer(Some(from), Some(fee_recipient), fee);
+            }
+            if burned > 0 {
+                self.reduce_total_supply(TokenAmount::from(burned));
+                self.total_burned += burned;
+                deposit_event(Event::Burn {
+                    from: from,
+                    value: burned
+                });
+                self.after_token_transfer(Some(from), None, burned);
+            }
+            Ok(())
+        }
+
+        /// Reverts the call unless `to` is a plain account or a contract which
+        /// acknowledges the `value` tokens it received from `from`.
+        fn ensure_received(&self, operator: AccountId, from: AccountId, to: AccountId, value: Balance, data: Vec<u8>) {
+            let acknowledgement: Option<Acknowledgement<[u8; 4]>> = call_contract(
+                to,
+                ON_ERC20_RECEIVED_SELECTOR,
+                &(operator, from, value, data).encode(),
+            );
+            match acknowledgement {
+                Some(Acknowledgement::NoContract) => (),
+                Some(Acknowledgement::Contract(ON_ERC20_RECEIVED_SELECTOR)) => (),
+                _ => panic!("the recipient cannot receive tokens"),
+            }
+        }
+
+        /// Returns the window of `account` which applies at the current block,
+        /// as (first block, amount sent), starting a new one if the last has ended.
+        fn current_window(&self, account: &AccountId, window: BlockNumber) -> (BlockNumber, Balance) {
+            let now = env::block_number();
+            match self.rate_windows.get(account) {
+                Some((start, sent)) if now < start.saturating_add(window) => (*start, *sent),
+                _ => (now, 0),
+            }
+        }
+
+        /// Returns how many more tokens `account` may send in its current window.
+        fn quota_of(&self, account: &AccountId) -> Balance {
+            match *self.rate_limit {
+                Some((max_amount, window)) => {
+                    let (_, sent) = self.current_window(account, window);
+                    max_amount.saturating_sub(sent)
+                }
+                None => Balance::max_value(),
+            }
+        }
+
+        /// Counts `value` against the quota of `from`.
+        ///
+        /// Check `quota_of` first, so that the quota is not exceeded.
+        fn consume_quota(&mut self, from: AccountId, value: Balance) {
+            if let Some((_, window)) = *self.rate_limit {
+                let (start, sent) = self.current_window(&from, window);
+                self.rate_windows.insert(from, (start, sent + value));
+            }
+        }
+
+        /// Returns the transfer fee charged on `value`, rounded down.
+        fn fee_for(&self, value: Balance) -> Balance {
+            share_of(value, *self.fee_bps)
+        }
+
+        /// Returns the share of `value` burned on transfers, rounded down.
+        fn burned_for(&self, value: Balance) -> Balance {
+            share_of(value, *self.burn_bps)
+        }
+
+        /// Creates new tokens for the specified AccountId, raising the total supply.
+        fn mint_impl(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.settle(to);
+            self.before_token_transfer(None, Some(to), value)?;
+            let total_supply = (TokenAmount::from(*self.total_supply) + TokenAmount::from(value))
+                .filter(|total_supply| *total_supply <= TokenAmount::from(*self.cap))
+                .ok_or(Erc20Error::CapExceeded)?;
+            self.total_supply.set(total_supply.into());
+            self.total_minted += value;
+            self.credit(to, TokenAmount::from(value));
+            deposit_event(Event::Mint {
+                to: to,
+                value: value
+            });
+            self.after_token_transfer(None, Some(to), value);
+            Ok(())
+        }
+
+        /// Destroys tokens of the specified AccountId, reducing the total supply.
+        fn burn_impl(&mut self, from: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.settle(from);
+            self.before_token_transfer(Some(from), None, value)?;
+            let remaining = (self.amount_of(&from) - TokenAmount::from(value))
+                .ok_or(Erc20Error::InsufficientBalance)?;
+            self.set_balance(from, remaining.into());
+            self.reduce_total_supply(TokenAmount::from(value));
+            self.total_burned += value;
+            deposit_event(Event::Burn {
+                from: from,
+                value: value
+            });
+            self.after_token_transfer(Some(from), None, value);
+            Ok(())
+        }
+
+        /// Hook which runs before any tokens are moved, minted or burned.
+        ///
+        /// A `from` of `None` denotes minting and a `to` of `None` denotes burning.
+        /// Returning an error aborts the operation before any balance is touched,
+        /// which makes this the place for extensions to veto token movements.
+        fn before_token_transfer(
+            &mut self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            _value: Balance,
+        ) -> Result<(), Erc20Error> {
+            self.ensure_transferable(&from, &to)?;
+            // Record the values as they were before this operation.
+            match (from, to) {
+                (Some(from), Some(to)) => {
+                    let fee_recipient = *self.fee_recipient;
+                    for account in &[from, to, fee_recipient] {
+                        self.update_snapshot(Some(*account), self.balance_of_or_zero(account));
+                    }
+                    if *self.burn_bps > 0 {
+                        self.update_snapshot(None, *self.total_supply);
+                    }
+                }
+                (from, to) => {
+                    for account in from.iter().chain(to.iter()) {
+                        self.update_snapshot(Some(*account), self.balance_of_or_zero(account));
+                    }
+                    self.update_snapshot(None, *self.total_supply);
+                }
+            }
+            Ok(())
+        }
+
+        /// Returns an error if tokens cannot currently move from `from` to `to`.
+        fn ensure_transferable(&self, from: &Option<AccountId>, to: &Option<AccountId>) -> Result<(), Erc20Error> {
+            self.ensure_not_paused()?;
+            if from.is_some() && to.is_some() && *self.circuit_tripped {
+                return Err(Erc20Error::CircuitTripped)
+            }
+            if from.iter().chain(to.iter()).any(|account| self.is_frozen_or_false(account)) {
+                return Err(Erc20Error::AccountFrozen)
+            }
+            Ok(())
+        }
+
+        /// Hook which runs after tokens have been moved, minted or burned.
+        ///
+        /// Balances and the total supply already reflect the operation at this
+        /// point, which makes this the place for extensions to record them.
+        fn after_token_transfer(&mut self, from: Option<AccountId>, to: Option<AccountId>, value: Balance) {
+            deposit_event(Event::Transfer {
+                from: from,
+                to: to,
+                value: value
+            });
+            let from_delegate = from.and_then(|from| self.delegates.get(&from).cloned());
+            let to_delegate = to.and_then(|to| self.delegates.get(&to).cloned());
+            self.move_voting_power(from_delegate, to_delegate, value);
+        }
+
+        /// Returns the voting power of `account` at the end of `block`.
+        fn votes_at(&self, account: &AccountId, block: BlockNumber) -> Balance {
+            let num_checkpoints = *self.num_checkpoints.get(account).unwrap_or(&0);
+            // Binary search for the last checkpoint recorded at or before `block`.
+            let (mut low, mut high) = (0, num_checkpoints);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let (checkpoint_block, _) = self.checkpoint(account, mid);
+                if checkpoint_block > block {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+            if low == 0 {
+                return 0
+            }
+            let (_, votes) = self.checkpoint(account, low - 1);
+            votes
+        }
+
+        /// Returns the checkpoint of `delegate` at `index`.
+        fn checkpoint(&self, delegate: &AccountId, index: u32) -> (BlockNumber, Balance) {
+            *self.checkpoints
+                .get(&(*delegate, index))
+                .expect("checkpoints are stored for every index below num_checkpoints")
+        }
+
+        /// Moves `value` votes from the delegate `from` to the delegate `to`.
+        fn move_voting_power(&mut self, from: Option<AccountId>, to: Option<AccountId>, value: Balance) {
+            if from == to || value == 0 {
+                return
+            }
+            if let Some(from) = from {
+                let votes = self.votes_at(&from, BlockNumber::max_value());
+                self.write_checkpoint(from, votes, votes - value);
+            }
+            if let Some(to) = to {
+                let votes = self.votes_at(&to, BlockNumber::max_value());
+                self.write_checkpoint(to, votes, votes + value);
+            }
+        }
+
+        /// Records that `delegate` has `new_votes` votes as of the current block.
+        fn write_checkpoint(&mut self, delegate: AccountId, previous_votes: Balance, new_votes: Balance) {
+            let block = env::block_number();
+            let num_checkpoints = *self.num_checkpoints.get(&delegate).unwrap_or(&0);
+            let last = num_checkpoints.checked_sub(1).map(|index| self.checkpoint(&delegate, index));
+            match last {
+                // Several changes within one block only keep the final value.
+                Some((last_block, _)) if last_block == block => {
+                    self.checkpoints.insert((delegate, num_checkpoints - 1), (block, new_votes));
+                }
+                _ => {
+                    self.checkpoints.insert((delegate, num_checkpoints), (block, new_votes));
+                    self.num_checkpoints.insert(delegate, num_checkpoints + 1);
+                }
+            }
+            deposit_event(Event::DelegateVotesChanged {
+                delegate: delegate,
+                previous_votes: previous_votes,
+                new_votes: new_votes
+            });
+        }
+
+        /// Returns the number of values recorded for the snapshot `key`.
+        fn snapshot_count(&self, key: &Option<AccountId>) -> u32 {
+            *self.snapshot_counts.get(key).unwrap_or(&0)
+        }
+
+        /// Returns the value recorded for the snapshot `key` at `index`.
+        fn snapshot_entry(&self, key: &Option<AccountId>, index: u32) -> (u32, Balance) {
+            *self.snapshots
+                .get(&(*key, index))
+                .expect("snapshots are stored for every index below the snapshot count")
+        }
+
+        /// Records `current_value` for `key` unless a value has already been
+        /// recorded since the most recent snapshot was taken.
+        ///
+        /// This is called right before the value changes, so the recorded value
+        /// is the one the snapshot saw.
+        fn update_snapshot(&mut self, key: Option<AccountId>, current_value: Balance) {
+            let id = *self.current_snapshot_id;
+            if id == 0 {
+                return
+            }
+            let count = self.snapshot_count(&key);
+            let last_id = match count {
+                0 => 0,
+                _ => self.snapshot_entry(&key, count - 1).0,
+            };
+            if last_id < id {
+                self.snapshots.insert((key, count), (id, current_value));
+                self.snapshot_counts.insert(key, count + 1);
+            }
+        }
+
+        /// Returns the value `key` had at snapshot `id`, given its `current_value`.
+        fn value_at(&self, key: Option<AccountId>, id: u32, current_value: Balance) -> Option<Balance> {
+            if id == 0 || id > *self.current_snapshot_id {
+                return None
+            }
+            // The first value recorded at or after snapshot `id` is the value the
+            // snapshot saw. Without one, the value has not changed since.
+            let count = self.snapshot_count(&key);
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if self.snapshot_entry(&key, mid).0 < id {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low == count {
+                return Some(current_value)
+            }
+            Some(self.snapshot_entry(&key, low).1)
+        }
+
+        /// Records the approval of mint proposal `id` by `approver` and mints the
+        /// tokens once the proposal reaches the threshold.
+        fn approve_mint_impl(&mut self, id: u32, approver: AccountId) -> Result<(), Erc20Error> {
+            let mut proposal = *self.mint_proposals.get(&id).ok_or(Erc20Error::UnknownProposal)?;
+            if proposal.executed {
+                return Err(Erc20Error::AlreadyExecuted)
+            }
+            if *self.mint_approvals.get(&(id, approver)).unwrap_or(&false) {
+                return Err(Erc20Error::AlreadyApproved)
+            }
+            proposal.approvals += 1;
+            if proposal.approvals >= *self.mint_threshold {
+                // Mint first, so that a failed mint does not record the approval.
+                self.mint_impl(proposal.to, proposal.value)?;
+                proposal.executed = true;
+            }
+            self.mint_approvals.insert((id, approver), true);
+            self.mint_proposals.insert(id, proposal);
+            deposit_event(Event::MintApproved {
+                id: id,
+                approver: approver
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the token through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The code hash the contract has been upgraded to, if any.
+        static CODE_HASH: RefCell<Option<Hash>> = RefCell::new(None);
+        /// The beneficiary of the contract's termination, if it has been terminated.
+        static BENEFICIARY: RefCell<Option<AccountId>> = RefCell::new(None);
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Records that the contract has been terminated in favour of `beneficiary`.
+    pub(super) fn set_mock_beneficiary(beneficiary: AccountId) {
+        BENEFICIARY.with(|current| *current.borrow_mut() = Some(beneficiary))
+    }
+
+    /// Returns the beneficiary of the contract's termination, if it has been terminated.
+    fn mock_beneficiary() -> Option<AccountId> {
+        BENEFICIARY.with(|current| *current.borrow())
+    }
+
+    /// Records the code hash the contract has been upgraded to.
+    pub(super) fn set_mock_code_hash(code_hash: Hash) {
+        CODE_HASH.with(|current| *current.borrow_mut() = Some(code_hash))
+    }
+
+    /// Returns the code hash the contract has been upgraded to, if any.
+    fn mock_code_hash() -> Option<Hash> {
+        CODE_HASH.with(|current| *current.borrow())
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    ///
+    /// Like on-chain, calling an account without a contract succeeds and returns nothing.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            match contracts.iter_mut().find(|(account, _)| account == callee) {
+                Some((_, contract)) => contract(selector, input),
+                None => Some(Vec::new()),
+            }
+        })
+    }
+
+    /// The account which collects transfer fees unless a test says otherwise.
+    fn fee_collector() -> AccountId {
+        AccountId::try_from([0xFF; 32]).unwrap()
+    }
+
+    /// The deployment parameters of the contract.
+    struct Deployment {
+        initial_balances: Vec<(AccountId, Balance)>,
+        cap: Option<Balance>,
+        name: String,
+        symbol: String,
+        decimals: u8,
+        fee_bps: u16,
+        fee_recipient: AccountId,
+        strict_approvals: bool,
+        burn_bps: u16,
+        allow_transfers_to_contract: bool,
+        treasury: Option<AccountId>,
+        treasury_amount: Balance,
+        max_relayer_fee: Balance,
+        report_failures: bool,
+        restrict_spenders: bool,
+        demurrage: Option<(u16, BlockNumber)>,
+        default_operators: Vec<AccountId>,
+    }
+
+    impl Default for Deployment {
+        fn default() -> Self {
+            Deployment {
+                initial_balances: vec![(env::caller(), 1234)],
+                cap: None,
+                name: "Workshop Token".into(),
+                symbol: "WST".into(),
+                decimals: 18,
+                fee_bps: 0,
+                fee_recipient: fee_collector(),
+                strict_approvals: false,
+                burn_bps: 0,
+                allow_transfers_to_contract: false,
+                treasury: None,
+                treasury_amount: 0,
+                max_relayer_fee: 0,
+                report_failures: false,
+                restrict_spenders: false,
+                demurrage: None,
+                default_operators: Vec::new(),
+            }
+        }
+    }
+
+    impl Deployment {
+        /// Deploys the contract with these parameters.
+        fn deploy(self) -> Erc20 {
+            Erc20::deploy_mock(
+                self.initial_balances,
+                self.cap,
+                self.name,
+                self.symbol,
+                self.decimals,
+                self.fee_bps,
+                self.fee_recipient,
+                self.strict_approvals,
+                self.burn_bps,
+                self.allow_transfers_to_contract,
+                self.treasury,
+                self.treasury_amount,
+                self.max_relayer_fee,
+                self.report_failures,
+                self.restrict_spenders,
+                self.demurrage,
+                self.default_operators,
+            )
+        }
+    }
+
+    /// Deploys the contract with `init_value` minted to the caller and the
+    /// default configuration.
+    fn deploy_mock(init_value: Balance) -> Erc20 {
+        Deployment { initial_balances: vec![(env::caller(), init_value)], ..Default::default() }.deploy()
+    }
+
+    /// Returns the sr25519 key pair derived from `seed`.
+    fn keypair(seed: u8) -> schnorrkel::Keypair {
+        schnorrkel::MiniSecretKey::from_bytes(&[seed; 32])
+            .unwrap()
+            .expand_to_keypair(schnorrkel::ExpansionMode::Uniform)
+    }
+
+    /// Returns the AccountId controlled by `keypair`.
+    fn account_of(keypair: &schnorrkel::Keypair) -> AccountId {
+        AccountId::try_from(keypair.public.to_bytes()).unwrap()
+    }
+
+    /// Signs `message` with `keypair` the way the contract expects it.
+    fn sign(keypair: &schnorrkel::Keypair, message: &[u8]) -> [u8; 64] {
+        keypair.sign_simple(SIGNING_CONTEXT, message).to_bytes()
+    }
+
+    /// Signs a permit with `keypair`.
+    fn sign_permit(
+        keypair: &schnorrkel::Keypair,
+        spender: AccountId,
+        value: Balance,
+        nonce: u64,
+        deadline: BlockNumber,
+    ) -> [u8; 64] {
+        let message = (account_of(keypair), spender, value, nonce, deadline).encode();
+        sign(keypair, &message)
+    }
+
+    /// Signs a permit which pays `fee` to the relayer with `keypair`.
+    fn sign_permit_with_fee(
+        keypair: &schnorrkel::Keypair,
+        spender: AccountId,
+        value: Balance,
+        nonce: u64,
+        deadline: BlockNumber,
+        fee: Balance,
+    ) -> [u8; 64] {
+        let message = (account_of(keypair), spender, value, nonce, deadline, fee).encode();
+        sign(keypair, &message)
+    }
+
+    /// Signs a transfer for `transfer_by_signature` with `keypair`.
+    fn sign_transfer(
+        keypair: &schnorrkel::Keypair,
+        to: AccountId,
+        value: Balance,
+        nonce: u64,
+        deadline: BlockNumber,
+    ) -> [u8; 64] {
+        let message = (account_of(keypair), to, value, nonce, deadline).encode();
+        sign(keypair, &message)
+    }
+
+    /// The flash loans received by a mock borrower: (initiator, amount, fee, data)
+    type FlashLoans = Rc<RefCell<Vec<(AccountId, Balance, Balance, Vec<u8>)>>>;
+
+    /// Registers a flash loan receiver at `account` which acknowledges every loan
+    /// with `acknowledgement` and records the loans it received.
+    fn register_flash_borrower(account: AccountId, acknowledgement: [u8; 32]) -> FlashLoans {
+        let loans = Rc::new(RefCell::new(Vec::new()));
+        let recorded = loans.clone();
+        register_contract(account, move |selector, input| {
+            assert_eq!(selector, ON_FLASH_LOAN_SELECTOR);
+            let loan = Decode::decode(&mut &input[..]).expect("a valid flash loan");
+            recorded.borrow_mut().push(loan);
+            Some(acknowledgement.encode())
+        });
+        loans
+    }
+
+    /// The approvals received by a mock spender: (owner, value, data)
+    type ReceivedApprovals = Rc<RefCell<Vec<(AccountId, Balance, Vec<u8>)>>>;
+
+    /// Registers an approval receiver at `account` which answers every
+    /// approval with `accepted` and records the approvals it received.
+    fn register_approval_receiver(account: AccountId, accepted: bool) -> ReceivedApprovals {
+        let approvals = Rc::new(RefCell::new(Vec::new()));
+        let recorded = approvals.clone();
+        register_contract(account, move |selector, input| {
+            assert_eq!(selector, ON_APPROVAL_RECEIVED_SELECTOR);
+            let approval = Decode::decode(&mut &input[..]).expect("a valid approval");
+            recorded.borrow_mut().push(approval);
+            Some(accepted.encode())
+        });
+        approvals
+    }
+
+    /// The transfers received by a mock recipient: (from, value, data)
+    type ReceivedTransfers = Rc<RefCell<Vec<(AccountId, Balance, Vec<u8>)>>>;
+
+    /// Registers a transfer receiver at `account` which answers every
+    /// transfer with `accepted` and records the transfers it received.
+    fn register_transfer_receiver(account: AccountId, accepted: bool) -> ReceivedTransfers {
+        let transfers = Rc::new(RefCell::new(Vec::new()));
+        let recorded = transfers.clone();
+        register_contract(account, move |selector, input| {
+            assert_eq!(selector, ON_TOKEN_TRANSFER_SELECTOR);
+            let transfer = Decode::decode(&mut &input[..]).expect("a valid transfer");
+            recorded.borrow_mut().push(transfer);
+            Some(accepted.encode())
+        });
+        transfers
+    }
+
+    /// The safe transfers received by a mock recipient: (operator, from, value, data)
+    type ReceivedSafeTransfers = Rc<RefCell<Vec<(AccountId, AccountId, Balance, Vec<u8>)>>>;
+
+    /// Registers a safe transfer receiver at `account` which answers every
+    /// transfer with `acknowledgement` and records the transfers it received.
+    fn register_erc20_receiver(account: AccountId, acknowledgement: [u8; 4]) -> ReceivedSafeTransfers {
+        let transfers = Rc::new(RefCell::new(Vec::new()));
+        let recorded = transfers.clone();
+        register_contract(account, move |selector, input| {
+            assert_eq!(selector, ON_ERC20_RECEIVED_SELECTOR);
+            let transfer = Decode::decode(&mut &input[..]).expect("a valid transfer");
+            recorded.borrow_mut().push(transfer);
+            Some(acknowledgement.encode())
+        });
+        transfers
+    }
+
+    /// The balances of a mock ERC20 token: account -> balance
+    type MockBalances = Rc<RefCell<Vec<(AccountId, Balance)>>>;
+
+    /// Registers a mock ERC20 token at `account` in which `holder` owns `balance`.
+    ///
+    /// The mock only supports `transfer`, which moves tokens away from the caller.
+    fn register_mock_token(account: AccountId, holder: AccountId, balance: Balance) -> MockBalances {
+        let balances = Rc::new(RefCell::new(vec![(holder, balance)]));
+        let token = balances.clone();
+        register_contract(account, move |selector, input| {
+            assert_eq!(selector, TRANSFER_SELECTOR);
+            let (to, value): (AccountId, Balance) = Decode::decode(&mut &input[..]).expect("a valid transfer");
+            let mut balances = token.borrow_mut();
+            let from = env::address();
+            let balance_of = |balances: &Vec<(AccountId, Balance)>, of| {
+                balances.iter().find(|(account, _)| *account == of).map(|(_, balance)| *balance).unwrap_or(0)
+            };
+            let balance_from = balance_of(&balances, from);
+            if balance_from < value {
+                // Index of `Erc20Error::InsufficientBalance`
+                return Some(Err::<(), u8>(0).encode())
+            }
+            let balance_to = balance_of(&balances, to);
+            balances.retain(|(account, _)| *account != from && *account != to);
+            balances.push((from, balance_from - value));
+            balances.push((to, balance_to + value));
+            Some(Ok::<(), u8>(()).encode())
+        });
+        balances
+    }
+
+    /// Returns the balance of `of` in the mock token with `balances`.
+    fn mock_balance_of(balances: &MockBalances, of: AccountId) -> Balance {
+        balances.borrow().iter().find(|(account, _)| *account == of).map(|(_, balance)| *balance).unwrap_or(0)
+    }
+
+    /// Returns all events deposited so far.
+    fn emitted_events() -> Vec<Event> {
+        env::test::emitted_events()
+            .map(|event| Event::decode(&mut &event.data[..]).expect("a valid Event"))
+            .collect()
+    }
+
+    /// Returns the ownership events deposited so far, in order.
+    fn ownership_events() -> Vec<Event> {
+        emitted_events()
+            .into_iter()
+            .filter(|event| match event {
+                Event::OwnershipProposed { .. }
+                | Event::OwnershipTransferred { .. }
+                | Event::OwnershipRenounced { .. } => true,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Returns the operator events deposited so far, in order.
+    fn operator_events() -> Vec<Event> {
+        emitted_events()
+            .into_iter()
+            .filter(|event| match event {
+                Event::AuthorizedOperator { .. } | Event::RevokedOperator { .. } => true,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Returns the topics of the most recently deposited event.
+    fn last_event_topics() -> Vec<Hash> {
+        env::test::emitted_events()
+            .last()
+            .expect("at least one event has been deposited")
+            .topics
+    }
+
+    #[test]
+    fn deployment_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // Deploy the contract with some `init_value`
+        let erc20 = deploy_mock(1234);
+        // Check that the `total_supply` is `init_value`
+        assert_eq!(erc20.total_supply(), 1234);
+        // Check that `balance_of` Alice is `init_value`
+        assert_eq!(erc20.balance_of(alice), 1234);
+    }
+
+    #[test]
+    fn deployment_with_initial_balances_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // Split the genesis supply between investors, treasury and team
+        let erc20 = Deployment {
+            initial_balances: vec![(bob, 600), (charlie, 300), (bob, 34), (alice, 300)],
+            ..Default::default()
+        }.deploy();
+        assert_eq!(erc20.total_supply(), 1234);
+        assert_eq!(erc20.balance_of(alice), 300);
+        assert_eq!(erc20.balance_of(bob), 634);
+        assert_eq!(erc20.balance_of(charlie), 300);
+        // Every entry is minted with its own `Transfer` event
+        let mints: Vec<Event> = emitted_events()
+            .into_iter()
+            .filter(|event| match event {
+                Event::Transfer { from: None, .. } => true,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(mints, vec![
+            Event::Transfer { from: None, to: Some(bob), value: 600 },
+            Event::Transfer { from: None, to: Some(charlie), value: 300 },
+            Event::Transfer { from: None, to: Some(bob), value: 34 },
+            Event::Transfer { from: None, to: Some(alice), value: 300 },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the initial supply must not exceed the cap")]
+    fn deployment_with_initial_balances_above_the_cap_fails() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        Deployment {
+            initial_balances: vec![(alice, 600), (bob, 601)],
+            cap: Some(1200),
+            ..Default::default()
+        }.deploy();
+    }
+
+    #[test]
+    fn treasury_premint_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let treasury = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // Deploy the contract with some `init_value`, part of which goes to the treasury
+        let erc20 = Deployment { treasury: Some(treasury), treasury_amount: 234, ..Default::default() }.deploy();
+        assert_eq!(erc20.total_supply(), 1234);
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.balance_of(treasury), 234);
+        let events = emitted_events();
+        assert_eq!(&events[events.len() - 2..], &[
+            Event::Transfer { from: None, to: Some(alice), value: 1000 },
+            Event::Transfer { from: None, to: Some(treasury), value: 234 },
+        ][..]);
+    }
+
+    #[test]
+    fn treasury_amount_is_ignored_without_a_treasury() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // Deploy the contract with some `init_value` and no treasury
+        let erc20 = Deployment { treasury: None, treasury_amount: 234, ..Default::default() }.deploy();
+        assert_eq!(erc20.total_supply(), 1234);
+        assert_eq!(erc20.balance_of(alice), 1234);
+    }
+
+    #[test]
+    #[should_panic(expected = "the treasury amount must not exceed the initial balance of the caller")]
+    fn treasury_amount_above_the_initial_balance_fails() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let treasury = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // Tokens assigned to others cannot go to the treasury
+        Deployment {
+            initial_balances: vec![(alice, 1000), (bob, 234)],
+            treasury: Some(treasury),
+            treasury_amount: 1001,
+            ..Default::default()
+        }.deploy();
+    }
+
+    #[test]
+    fn transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice does not have enough funds for this
+        assert_eq!(erc20.transfer(bob, 4321), Err(Erc20Error::InsufficientBalance));
+        // Alice can do this though
+        assert_eq!(erc20.transfer(bob, 234), Ok(()));
+        // Check Alice and Bob have the expected balance
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.balance_of(bob), 234);
+    }
+
+    #[test]
+    fn allowance_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob does not have an allowance from Alice's balance
+        assert_eq!(erc20.allowance(alice, bob), 0);
+        // Thus, Bob cannot transfer out of Alice's account
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 1), Err(Erc20Error::InsufficientAllowance));
+        // Alice can approve bob for some of her funds
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        // And the allowance reflects that correctly
+        assert_eq!(erc20.allowance(alice, bob), 20);
+
+        // Charlie cannot send on behalf of Bob
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.transfer_from(alice, bob, 10), Err(Erc20Error::InsufficientAllowance));
+        // Bob cannot transfer more than he is allowed
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 25), Err(Erc20Error::InsufficientAllowance));
+        // A smaller amount should work though
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Ok(10));
+        // Check that the allowance is updated
+        assert_eq!(erc20.allowance(alice, bob), 10);
+        // and the balance transferred to the right person
+        assert_eq!(erc20.balance_of(charlie), 10);
+    }
+
+    #[test]
+    fn mint_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice deployed the contract, so she is allowed to mint
+        assert_eq!(erc20.mint(bob, 100), Ok(()));
+        // The new tokens are credited to Bob
+        assert_eq!(erc20.balance_of(bob), 100);
+        // and the total supply grows accordingly
+        assert_eq!(erc20.total_supply(), 1334);
+    }
+
+    #[test]
+    fn mint_fails_for_unauthorized_callers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not the owner, so he cannot mint tokens
+        env::test::set_caller(bob);
+        assert_eq!(erc20.mint(bob, 100), Err(Erc20Error::Unauthorized));
+        // Nothing has changed
+        assert_eq!(erc20.balance_of(bob), 0);
+        assert_eq!(erc20.total_supply(), 1234);
+    }
+
+    #[test]
+    fn burn_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice cannot burn more than she owns
+        assert_eq!(erc20.burn(1235), Err(Erc20Error::InsufficientBalance));
+        // But she can burn part of her balance
+        assert_eq!(erc20.burn(234), Ok(()));
+        // Both her balance and the total supply shrink
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.total_supply(), 1000);
+    }
+
+    #[test]
+    fn burn_from_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.grant_role(Role::Burner, bob), Ok(()));
+        // Bob has no allowance, so he cannot burn Alice's tokens
+        env::test::set_caller(bob);
+        assert_eq!(erc20.burn_from(alice, 10), Err(Erc20Error::InsufficientAllowance));
+        // Alice approves Bob for some of her funds
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        // Bob cannot burn more than he is allowed
+        env::test::set_caller(bob);
+        assert_eq!(erc20.burn_from(alice, 25), Err(Erc20Error::InsufficientAllowance));
+        // A smaller amount should work though
+        assert_eq!(erc20.burn_from(alice, 15), Ok(()));
+        // Check that the allowance, balance and supply are updated
+        assert_eq!(erc20.allowance(alice, bob), 5);
+        assert_eq!(erc20.balance_of(alice), 1219);
+        assert_eq!(erc20.total_supply(), 1219);
+    }
+
+    #[test]
+    fn burn_from_fails_on_insufficient_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(100);
+        assert_eq!(erc20.grant_role(Role::Burner, bob), Ok(()));
+        // Alice approves Bob for more than she owns
+        assert_eq!(erc20.approve(bob, 500), Ok(()));
+        // Bob cannot burn more than Alice's balance
+        env::test::set_caller(bob);
+        assert_eq!(erc20.burn_from(alice, 200), Err(Erc20Error::InsufficientBalance));
+        // and the allowance is left untouched
+        assert_eq!(erc20.allowance(alice, bob), 500);
+        assert_eq!(erc20.total_supply(), 100);
+    }
+
+    #[test]
+    fn increase_allowance_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice starts Bob off with a small allowance
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        // and then raises it on top of the existing value
+        assert_eq!(erc20.increase_allowance(bob, 30), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 50);
+        // Increasing past the maximum saturates instead of overflowing
+        assert_eq!(erc20.increase_allowance(bob, Balance::max_value()), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), Balance::max_value());
+    }
+
+    #[test]
+    fn decrease_allowance_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 50), Ok(()));
+        // Alice lowers Bob's allowance
+        assert_eq!(erc20.decrease_allowance(bob, 20), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 30);
+    }
+
+    #[test]
+    fn decrease_allowance_saturates_at_zero() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        // Decreasing by more than the allowance does not underflow
+        assert_eq!(erc20.decrease_allowance(bob, 25), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 0);
+        // Decreasing a non-existing allowance leaves it at zero as well
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        assert_eq!(erc20.decrease_allowance(charlie, 1), Ok(()));
+        assert_eq!(erc20.allowance(alice, charlie), 0);
+    }
+
+    #[test]
+    fn pause_and_unpause_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // A freshly deployed contract is not paused
+        assert_eq!(erc20.paused(), false);
+        // The owner can pause the contract
+        assert_eq!(erc20.pause(), Ok(()));
+        assert_eq!(erc20.paused(), true);
+        // Pausing twice is rejected
+        assert_eq!(erc20.pause(), Err(Erc20Error::Paused));
+        // The owner can unpause it again
+        assert_eq!(erc20.unpause(), Ok(()));
+        assert_eq!(erc20.paused(), false);
+        // Unpausing twice is rejected as well
+        assert_eq!(erc20.unpause(), Err(Erc20Error::NotPaused));
+        assert_eq!(erc20.paused(), false);
+    }
+
+    #[test]
+    fn pause_fails_for_unauthorized_callers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not the owner, so Bob can neither pause
+        env::test::set_caller(bob);
+        assert_eq!(erc20.pause(), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.paused(), false);
+        // nor unpause the contract
+        env::test::set_caller(alice);
+        assert_eq!(erc20.pause(), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.unpause(), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.paused(), true);
+    }
+
+    #[test]
+    fn transfers_fail_while_paused() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        assert_eq!(erc20.pause(), Ok(()));
+        // Nothing can move while the contract is paused
+        assert_eq!(erc20.transfer(bob, 10), Err(Erc20Error::Paused));
+        assert_eq!(erc20.approve(charlie, 10), Err(Erc20Error::Paused));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Err(Erc20Error::Paused));
+        // Balances and allowances are left untouched
+        assert_eq!(erc20.balance_of(alice), 1234);
+        assert_eq!(erc20.allowance(alice, bob), 20);
+        // Once unpaused, transfers go through again
+        env::test::set_caller(alice);
+        assert_eq!(erc20.unpause(), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Ok(10));
+        assert_eq!(erc20.balance_of(charlie), 10);
+    }
+
+    #[test]
+    fn ownership_transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice proposes Bob as the new owner
+        assert_eq!(erc20.propose_owner(bob), Ok(()));
+        // Until Bob accepts, Alice is still the owner
+        env::test::set_caller(bob);
+        assert_eq!(erc20.propose_owner(bob), Err(Erc20Error::Unauthorized));
+        // Bob accepts the proposal and takes over
+        assert_eq!(erc20.accept_ownership(), Ok(()));
+        assert_eq!(erc20.propose_owner(alice), Ok(()));
+        // Alice has lost her privileges
+        env::test::set_caller(alice);
+        assert_eq!(erc20.propose_owner(alice), Err(Erc20Error::Unauthorized));
+    }
+
+    #[test]
+    fn only_the_pending_owner_can_accept() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Nobody can accept while there is no pending proposal
+        env::test::set_caller(bob);
+        assert_eq!(erc20.accept_ownership(), Err(Erc20Error::Unauthorized));
+        // Only the owner can propose a new owner
+        assert_eq!(erc20.propose_owner(bob), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.propose_owner(bob), Ok(()));
+        // Charlie cannot snatch the pending proposal
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.accept_ownership(), Err(Erc20Error::Unauthorized));
+        // A new proposal replaces the old one
+        env::test::set_caller(alice);
+        assert_eq!(erc20.propose_owner(charlie), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.accept_ownership(), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.accept_ownership(), Ok(()));
+        // The proposal is consumed once accepted
+        assert_eq!(erc20.accept_ownership(), Err(Erc20Error::Unauthorized));
+    }
+
+    #[test]
+    fn deployer_holds_all_roles() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let erc20 = deploy_mock(1234);
+        // Alice starts out with every role
+        for role in &[
+            Role::Admin,
+            Role::Minter,
+            Role::Pauser,
+            Role::Burner,
+            Role::MintSigner,
+            Role::Locker,
+            Role::Guardian,
+        ] {
+            assert_eq!(erc20.has_role(*role, alice), true);
+            assert_eq!(erc20.has_role(*role, bob), false);
+        }
+    }
+
+    #[test]
+    fn roles_gate_privileged_operations() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice makes Bob a minter and Charlie a pauser
+        assert_eq!(erc20.grant_role(Role::Minter, bob), Ok(()));
+        assert_eq!(erc20.grant_role(Role::Pauser, charlie), Ok(()));
+        // Bob can mint, but neither pause nor burn
+        env::test::set_caller(bob);
+        assert_eq!(erc20.mint(bob, 10), Ok(()));
+        assert_eq!(erc20.pause(), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.burn(5), Err(Erc20Error::Unauthorized));
+        // Charlie can pause, but not mint
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.mint(charlie, 10), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.pause(), Ok(()));
+        assert_eq!(erc20.unpause(), Ok(()));
+        // Once Alice revokes Bob's role, Bob can no longer mint
+        env::test::set_caller(alice);
+        assert_eq!(erc20.revoke_role(Role::Minter, bob), Ok(()));
+        assert_eq!(erc20.has_role(Role::Minter, bob), false);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.mint(bob, 10), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.balance_of(bob), 10);
+    }
+
+    #[test]
+    fn only_admins_can_manage_roles() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not an admin, so he cannot hand out roles
+        env::test::set_caller(bob);
+        assert_eq!(erc20.grant_role(Role::Minter, bob), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.revoke_role(Role::Minter, alice), Err(Erc20Error::Unauthorized));
+        // Alice makes Bob an admin, who then makes Charlie a minter
+        env::test::set_caller(alice);
+        assert_eq!(erc20.grant_role(Role::Admin, bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.grant_role(Role::Minter, charlie), Ok(()));
+        assert_eq!(erc20.has_role(Role::Minter, charlie), true);
+        // Bob can even revoke Alice's admin role
+        assert_eq!(erc20.revoke_role(Role::Admin, alice), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.grant_role(Role::Minter, alice), Err(Erc20Error::Unauthorized));
+        // Alice keeps the other roles she was given
+        assert_eq!(erc20.has_role(Role::Minter, alice), true);
+    }
+
+    #[test]
+    fn uncapped_deployment_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without a cap
+        let erc20 = deploy_mock(1234);
+        // The cap defaults to the maximum balance
+        assert_eq!(erc20.cap(), Balance::max_value());
+    }
+
+    #[test]
+    fn mint_respects_the_cap() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a cap slightly above the `init_value`
+        let mut erc20 = Deployment { cap: Some(1300), ..Default::default() }.deploy();
+        assert_eq!(erc20.cap(), 1300);
+        // Alice can mint right up to the cap
+        assert_eq!(erc20.mint(alice, 66), Ok(()));
+        assert_eq!(erc20.total_supply(), 1300);
+        // but not a single token beyond it
+        assert_eq!(erc20.mint(alice, 1), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.total_supply(), 1300);
+        // Burning frees up room below the cap again
+        assert_eq!(erc20.burn(10), Ok(()));
+        assert_eq!(erc20.mint(alice, 11), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.mint(alice, 10), Ok(()));
+    }
+
+    #[test]
+    fn mint_fails_on_overflow() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without a cap
+        let mut erc20 = deploy_mock(1234);
+        // Minting beyond the maximum balance is treated as exceeding the cap
+        assert_eq!(erc20.mint(alice, Balance::max_value()), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.total_supply(), 1234);
+    }
+
+    #[test]
+    #[should_panic]
+    fn deployment_above_the_cap_fails() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // The initial supply alone already exceeds the cap
+        Deployment { cap: Some(1000), ..Default::default() }.deploy();
+    }
+
+    #[test]
+    fn metadata_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some token metadata
+        let erc20 = Deployment {
+            name: "Kitty Coin".into(),
+            symbol: "KTY".into(),
+            decimals: 12,
+            ..Default::default()
+        }.deploy();
+        // Check that the metadata is returned as given
+        assert_eq!(erc20.name(), "Kitty Coin");
+        assert_eq!(erc20.symbol(), "KTY");
+        assert_eq!(erc20.decimals(), 12);
+    }
+
+    #[test]
+    fn transfer_topics_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Minted tokens have no sender, so only the receiver is a topic
+        assert_eq!(last_event_topics(), vec![account_topic(&alice)]);
+        // A transfer is indexed under both the sender and the receiver
+        assert_eq!(erc20.transfer(bob, 10), Ok(()));
+        assert_eq!(last_event_topics(), vec![account_topic(&alice), account_topic(&bob)]);
+        // Burned tokens have no receiver, so only the sender is a topic
+        assert_eq!(erc20.burn(10), Ok(()));
+        assert_eq!(last_event_topics(), vec![account_topic(&alice)]);
+    }
+
+    #[test]
+    fn approval_topics_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // An approval is indexed under both the owner and the spender
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(last_event_topics(), vec![account_topic(&alice), account_topic(&bob)]);
+    }
+
+    #[test]
+    fn hooks_veto_before_balances_change() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.pause(), Ok(()));
+        let events_before = emitted_events().len();
+        // The pause check lives in `before_token_transfer`, so it covers
+        // transfers, mints and burns alike
+        assert_eq!(erc20.transfer(bob, 10), Err(Erc20Error::Paused));
+        assert_eq!(erc20.mint(bob, 10), Err(Erc20Error::Paused));
+        assert_eq!(erc20.burn(10), Err(Erc20Error::Paused));
+        // The hook aborted every operation before anything was written
+        assert_eq!(erc20.balance_of(alice), 1234);
+        assert_eq!(erc20.balance_of(bob), 0);
+        assert_eq!(erc20.total_supply(), 1234);
+        assert_eq!(emitted_events().len(), events_before);
+    }
+
+    #[test]
+    fn hooks_run_around_balance_updates() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let events_before = emitted_events().len();
+        assert_eq!(erc20.mint(bob, 10), Ok(()));
+        assert_eq!(erc20.burn(4), Ok(()));
+        // `after_token_transfer` deposits the `Transfer` event once the
+        // operation itself has completed
+        let events = emitted_events();
+        assert_eq!(&events[events_before..], &[
+            Event::Mint { to: bob, value: 10 },
+            Event::Transfer { from: None, to: Some(bob), value: 10 },
+            Event::Burn { from: alice, value: 4 },
+            Event::Transfer { from: Some(alice), to: None, value: 4 },
+        ][..]);
+    }
+
+    #[test]
+    fn freeze_and_unfreeze_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.is_frozen(bob), false);
+        // Bob is not an admin, so he cannot freeze anybody
+        env::test::set_caller(bob);
+        assert_eq!(erc20.freeze(alice), Err(Erc20Error::Unauthorized));
+        // Alice freezes and unfreezes Bob
+        env::test::set_caller(alice);
+        assert_eq!(erc20.freeze(bob), Ok(()));
+        assert_eq!(erc20.is_frozen(bob), true);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.unfreeze(bob), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.unfreeze(bob), Ok(()));
+        assert_eq!(erc20.is_frozen(bob), false);
+    }
+
+    #[test]
+    fn frozen_accounts_cannot_send() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve(charlie, 50), Ok(()));
+        // Once frozen, Bob can no longer move his tokens
+        env::test::set_caller(alice);
+        assert_eq!(erc20.freeze(bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(charlie, 10), Err(Erc20Error::AccountFrozen));
+        // and neither can anybody he approved
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.transfer_from(bob, charlie, 10), Err(Erc20Error::AccountFrozen));
+        // The failed attempts did not consume the allowance
+        assert_eq!(erc20.allowance(bob, charlie), 50);
+        assert_eq!(erc20.balance_of(bob), 100);
+        assert_eq!(erc20.balance_of(charlie), 0);
+    }
+
+    #[test]
+    fn frozen_accounts_cannot_receive() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(charlie, 50), Ok(()));
+        assert_eq!(erc20.freeze(bob), Ok(()));
+        // Nobody can send tokens to a frozen account
+        assert_eq!(erc20.transfer(bob, 10), Err(Erc20Error::AccountFrozen));
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.transfer_from(alice, bob, 10), Err(Erc20Error::AccountFrozen));
+        assert_eq!(erc20.balance_of(bob), 0);
+        // After unfreezing, Bob can receive tokens again
+        env::test::set_caller(alice);
+        assert_eq!(erc20.unfreeze(bob), Ok(()));
+        assert_eq!(erc20.transfer(bob, 10), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 10);
+    }
+
+    #[test]
+    fn transfer_fee_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 2.5% transfer fee
+        let mut erc20 = Deployment { fee_bps: 250, ..Default::default() }.deploy();
+        assert_eq!(erc20.fee(), (250, fee_collector()));
+        // Alice sends 1000 tokens, of which Bob receives 975
+        assert_eq!(erc20.transfer(bob, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 234);
+        assert_eq!(erc20.balance_of(bob), 975);
+        assert_eq!(erc20.balance_of(fee_collector()), 25);
+        // Both legs of the transfer are reported
+        let events = emitted_events();
+        assert_eq!(&events[events.len() - 2..], &[
+            Event::Transfer { from: Some(alice), to: Some(bob), value: 975 },
+            Event::Transfer { from: Some(alice), to: Some(fee_collector()), value: 25 },
+        ][..]);
+        // Fees never change the total supply
+        assert_eq!(erc20.total_supply(), 1234);
+    }
+
+    #[test]
+    fn transfer_fee_rounds_down() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 2.5% transfer fee
+        let mut erc20 = Deployment { fee_bps: 250, ..Default::default() }.deploy();
+        // 2.5% of 39 is 0.975, which rounds down to no fee at all
+        assert_eq!(erc20.transfer(bob, 39), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 39);
+        assert_eq!(erc20.balance_of(fee_collector()), 0);
+        // 2.5% of 81 is 2.025, which rounds down to 2
+        assert_eq!(erc20.transfer(bob, 81), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 118);
+        assert_eq!(erc20.balance_of(fee_collector()), 2);
+    }
+
+    #[test]
+    fn zero_fee_emits_a_single_transfer() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without a transfer fee
+        let mut erc20 = deploy_mock(1234);
+        let events_before = emitted_events().len();
+        assert_eq!(erc20.transfer(bob, 1000), Ok(()));
+        // Bob receives everything and no fee leg is reported
+        assert_eq!(erc20.balance_of(bob), 1000);
+        assert_eq!(emitted_events().len(), events_before + 1);
+    }
+
+    #[test]
+    fn set_fee_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without a transfer fee
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not an admin, so he cannot change the fee
+        env::test::set_caller(bob);
+        assert_eq!(erc20.set_fee(100, bob), Err(Erc20Error::Unauthorized));
+        // Fees above 100% are rejected
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_fee(10_001, charlie), Err(Erc20Error::InvalidFee));
+        // Alice introduces a 1% fee collected by Charlie
+        assert_eq!(erc20.set_fee(100, charlie), Ok(()));
+        assert_eq!(erc20.fee(), (100, charlie));
+        assert_eq!(erc20.transfer(bob, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 990);
+        assert_eq!(erc20.balance_of(charlie), 10);
+    }
+
+    #[test]
+    fn transfer_to_self_keeps_the_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without a transfer fee
+        let mut erc20 = deploy_mock(1234);
+        // Sending tokens to oneself neither creates nor destroys any
+        assert_eq!(erc20.transfer(alice, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 1234);
+    }
+
+    #[test]
+    fn transfer_batch_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let events_before = emitted_events().len();
+        // Alice pays Bob and Charlie in a single call
+        assert_eq!(erc20.transfer_batch(vec![(bob, 100), (charlie, 200), (bob, 34)]), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 900);
+        assert_eq!(erc20.balance_of(bob), 134);
+        assert_eq!(erc20.balance_of(charlie), 200);
+        // Every leg is reported with its own `Transfer` event
+        assert_eq!(&emitted_events()[events_before..], &[
+            Event::Transfer { from: Some(alice), to: Some(bob), value: 100 },
+            Event::Transfer { from: Some(alice), to: Some(charlie), value: 200 },
+            Event::Transfer { from: Some(alice), to: Some(bob), value: 34 },
+        ][..]);
+    }
+
+    #[test]
+    fn transfer_batch_is_all_or_nothing() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice can afford each leg on its own, but not all of them together
+        assert_eq!(
+            erc20.transfer_batch(vec![(bob, 1000), (charlie, 1000)]),
+            Err(Erc20Error::InsufficientBalance)
+        );
+        // A frozen recipient at the end of the batch fails the whole batch
+        assert_eq!(erc20.freeze(charlie), Ok(()));
+        assert_eq!(
+            erc20.transfer_batch(vec![(bob, 10), (charlie, 10)]),
+            Err(Erc20Error::AccountFrozen)
+        );
+        // So does a total which does not fit into a `Balance`
+        assert_eq!(
+            erc20.transfer_batch(vec![(bob, Balance::max_value()), (bob, 1)]),
+            Err(Erc20Error::InsufficientBalance)
+        );
+        // None of the failed batches moved any tokens
+        assert_eq!(erc20.balance_of(alice), 1234);
+        assert_eq!(erc20.balance_of(bob), 0);
+        assert_eq!(erc20.balance_of(charlie), 0);
+    }
+
+    #[test]
+    fn transfer_from_batch_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 200), Ok(()));
+        // Alice and Bob both allow Charlie to pull their tokens
+        assert_eq!(erc20.approve(charlie, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve(charlie, 50), Ok(()));
+        // Charlie pulls from both of them in one call
+        env::test::set_caller(charlie);
+        assert_eq!(
+            erc20.transfer_from_batch(vec![(alice, dave, 60), (bob, dave, 50), (alice, charlie, 40)]),
+            Ok(())
+        );
+        assert_eq!(erc20.balance_of(alice), 934);
+        assert_eq!(erc20.balance_of(bob), 150);
+        assert_eq!(erc20.balance_of(charlie), 40);
+        assert_eq!(erc20.balance_of(dave), 110);
+        // Each allowance is reduced by everything spent from it
+        assert_eq!(erc20.allowance(alice, charlie), 0);
+        assert_eq!(erc20.allowance(bob, charlie), 0);
+    }
+
+    #[test]
+    fn transfer_from_batch_rejects_mixed_validity() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 20), Ok(()));
+        assert_eq!(erc20.approve(charlie, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve(charlie, 50), Ok(()));
+        env::test::set_caller(charlie);
+        // Each of Alice's operations fits her allowance, but not both together
+        assert_eq!(
+            erc20.transfer_from_batch(vec![(alice, dave, 60), (alice, dave, 60)]),
+            Err(Erc20Error::InsufficientAllowance)
+        );
+        // Bob's allowance is fine, but he does not own enough tokens
+        assert_eq!(
+            erc20.transfer_from_batch(vec![(alice, dave, 10), (bob, dave, 30)]),
+            Err(Erc20Error::InsufficientBalance)
+        );
+        // Dave never approved Charlie at all
+        assert_eq!(
+            erc20.transfer_from_batch(vec![(alice, dave, 10), (dave, charlie, 1)]),
+            Err(Erc20Error::InsufficientAllowance)
+        );
+        // None of the failed batches moved any tokens or touched any allowance
+        assert_eq!(erc20.balance_of(alice), 1214);
+        assert_eq!(erc20.balance_of(bob), 20);
+        assert_eq!(erc20.balance_of(dave), 0);
+        assert_eq!(erc20.allowance(alice, charlie), 100);
+        assert_eq!(erc20.allowance(bob, charlie), 50);
+    }
+
+    #[test]
+    fn permit_works() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_block_number(10);
+        // Alice signs a permit for Bob off-chain, which Bob submits himself
+        let signature = sign_permit(&owner, bob, 100, 0, 20);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Ok(()));
+        // Bob is now approved and Alice's nonce has moved on
+        assert_eq!(erc20.allowance(alice, bob), 100);
+        assert_eq!(erc20.nonce_of(alice), 1);
+        assert_eq!(erc20.transfer_from(alice, bob, 100), Ok(0));
+        assert_eq!(erc20.balance_of(bob), 100);
+    }
+
+    #[test]
+    fn permit_cannot_be_replayed() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let signature = sign_permit(&owner, bob, 100, 0, 20);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Ok(()));
+        assert_eq!(erc20.transfer_from(alice, bob, 100), Ok(0));
+        // Submitting the same permit again does not restore the allowance
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Err(Erc20Error::InvalidSignature));
+        assert_eq!(erc20.allowance(alice, bob), 0);
+    }
+
+    #[test]
+    fn permit_rejects_expired_deadlines() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let signature = sign_permit(&owner, bob, 100, 0, 20);
+        env::test::set_caller(bob);
+        // The permit can no longer be used one block after its deadline
+        env::test::set_block_number(21);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Err(Erc20Error::Expired));
+        assert_eq!(erc20.nonce_of(alice), 0);
+        // but it is still valid within its deadline block
+        env::test::set_block_number(20);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Ok(()));
+    }
+
+    #[test]
+    fn permit_rejects_forged_signatures() {
+        let owner = keypair(0x10);
+        let mallory = keypair(0x20);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        // A permit signed by somebody other than Alice is rejected
+        let signature = sign_permit(&mallory, bob, 100, 0, 20);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, signature), Err(Erc20Error::InvalidSignature));
+        // So is a permit whose value was tampered with
+        let signature = sign_permit(&owner, bob, 100, 0, 20);
+        assert_eq!(erc20.permit(alice, bob, 1000, 20, signature), Err(Erc20Error::InvalidSignature));
+        assert_eq!(erc20.allowance(alice, bob), 0);
+    }
+
+    #[test]
+    fn permit_with_fee_reimburses_the_relayer() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let relayer = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value` and relayer fees of up to 10 tokens
+        let mut erc20 = Deployment { max_relayer_fee: 10, ..Default::default() }.deploy();
+        assert_eq!(erc20.max_relayer_fee(), 10);
+        // Alice signs a permit for Bob which pays the relayer 5 tokens
+        let signature = sign_permit_with_fee(&owner, bob, 100, 0, 20, 5);
+        env::test::set_caller(relayer);
+        assert_eq!(erc20.permit_with_fee(alice, bob, 100, 20, 5, signature), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 100);
+        assert_eq!(erc20.nonce_of(alice), 1);
+        assert_eq!(erc20.balance_of(relayer), 5);
+        assert_eq!(erc20.balance_of(alice), 1229);
+    }
+
+    #[test]
+    fn permit_with_fee_rejects_over_charging() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let relayer = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value` and relayer fees of up to 10 tokens
+        let mut erc20 = Deployment { max_relayer_fee: 10, ..Default::default() }.deploy();
+        env::test::set_caller(relayer);
+        // Fees above the maximum are rejected, even if Alice signed them
+        let signature = sign_permit_with_fee(&owner, bob, 100, 0, 20, 11);
+        assert_eq!(erc20.permit_with_fee(alice, bob, 100, 20, 11, signature), Err(Erc20Error::RelayerFeeTooHigh));
+        // The relayer cannot raise the fee Alice signed either
+        let signature = sign_permit_with_fee(&owner, bob, 100, 0, 20, 5);
+        assert_eq!(erc20.permit_with_fee(alice, bob, 100, 20, 10, signature), Err(Erc20Error::InvalidSignature));
+        // Nothing has been paid or approved
+        assert_eq!(erc20.balance_of(relayer), 0);
+        assert_eq!(erc20.allowance(alice, bob), 0);
+        assert_eq!(erc20.nonce_of(alice), 0);
+    }
+
+    #[test]
+    fn relayer_fees_are_disabled_by_default() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.max_relayer_fee(), 0);
+        let signature = sign_permit_with_fee(&owner, bob, 100, 0, 20, 1);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.permit_with_fee(alice, bob, 100, 20, 1, signature), Err(Erc20Error::RelayerFeeTooHigh));
+        // A fee of zero works like a plain permit
+        let signature = sign_permit_with_fee(&owner, bob, 100, 0, 20, 0);
+        assert_eq!(erc20.permit_with_fee(alice, bob, 100, 20, 0, signature), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 100);
+    }
+
+    #[test]
+    fn transfer_by_signature_works() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let relayer = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice signs a transfer to Bob off-chain, which a relayer submits
+        let signature = sign_transfer(&owner, bob, 100, 0, 20);
+        env::test::set_caller(relayer);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 1134);
+        assert_eq!(erc20.balance_of(bob), 100);
+        assert_eq!(erc20.balance_of(relayer), 0);
+        assert_eq!(erc20.nonce_of(alice), 1);
+    }
+
+    #[test]
+    fn transfer_by_signature_cannot_be_replayed() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let signature = sign_transfer(&owner, bob, 100, 0, 20);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Ok(()));
+        // The nonce has been consumed
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Err(Erc20Error::InvalidNonce));
+        // Claiming the new nonce does not make the old signature valid
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 1, 20, signature), Err(Erc20Error::InvalidSignature));
+        // Permits and signed transfers share the nonce
+        let permit = sign_permit(&owner, bob, 100, 0, 20);
+        assert_eq!(erc20.permit(alice, bob, 100, 20, permit), Err(Erc20Error::InvalidSignature));
+        assert_eq!(erc20.balance_of(bob), 100);
+    }
+
+    #[test]
+    fn transfer_by_signature_rejects_forged_signatures() {
+        let owner = keypair(0x10);
+        let mallory = keypair(0x20);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        // A transfer signed by somebody other than Alice is rejected
+        let signature = sign_transfer(&mallory, bob, 100, 0, 20);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Err(Erc20Error::InvalidSignature));
+        // So are transfers whose recipient or value were tampered with
+        let signature = sign_transfer(&owner, bob, 100, 0, 20);
+        assert_eq!(erc20.transfer_by_signature(alice, account_of(&mallory), 100, 0, 20, signature), Err(Erc20Error::InvalidSignature));
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 1000, 0, 20, signature), Err(Erc20Error::InvalidSignature));
+        assert_eq!(erc20.balance_of(bob), 0);
+        assert_eq!(erc20.nonce_of(alice), 0);
+    }
+
+    #[test]
+    fn transfer_by_signature_rejects_expired_signatures() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let signature = sign_transfer(&owner, bob, 100, 0, 20);
+        env::test::set_caller(bob);
+        // The signature can no longer be used after the deadline
+        env::test::set_block_number(21);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Err(Erc20Error::Expired));
+        assert_eq!(erc20.nonce_of(alice), 0);
+        // Moving the deadline does not make the old signature valid
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 30, signature), Err(Erc20Error::InvalidSignature));
+        // But it can be used up to and including the deadline
+        env::test::set_block_number(20);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 100, 0, 20, signature), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 100);
+    }
+
+    #[test]
+    fn failed_transfer_by_signature_keeps_the_nonce() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let signature = sign_transfer(&owner, bob, 1000, 0, 20);
+        // Alice spends most of her tokens before the relayer submits the transfer
+        assert_eq!(erc20.transfer(bob, 500), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 1000, 0, 20, signature), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.nonce_of(alice), 0);
+        // Once she has enough again, the same signature goes through
+        assert_eq!(erc20.transfer(alice, 500), Ok(()));
+        assert_eq!(erc20.transfer_by_signature(alice, bob, 1000, 0, 20, signature), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 1000);
+    }
+
+    #[test]
+    fn delegation_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Undelegated tokens do not count as votes
+        assert_eq!(erc20.get_votes(alice), 0);
+        // Alice delegates to herself to activate her votes
+        assert_eq!(erc20.delegate(alice), Ok(()));
+        assert_eq!(erc20.delegates(alice), Some(alice));
+        assert_eq!(erc20.get_votes(alice), 1234);
+        // Moving her delegation moves all of her votes to Charlie
+        assert_eq!(erc20.delegate(charlie), Ok(()));
+        assert_eq!(erc20.get_votes(alice), 0);
+        assert_eq!(erc20.get_votes(charlie), 1234);
+        // Bob's tokens follow his own delegation once he receives them
+        env::test::set_caller(bob);
+        assert_eq!(erc20.delegate(bob), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer(bob, 234), Ok(()));
+        assert_eq!(erc20.get_votes(charlie), 1000);
+        assert_eq!(erc20.get_votes(bob), 234);
+    }
+
+    #[test]
+    fn votes_follow_mints_and_burns() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.delegate(alice), Ok(()));
+        assert_eq!(erc20.mint(alice, 66), Ok(()));
+        assert_eq!(erc20.get_votes(alice), 1300);
+        assert_eq!(erc20.burn(300), Ok(()));
+        assert_eq!(erc20.get_votes(alice), 1000);
+    }
+
+    #[test]
+    fn past_votes_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_block_number(10);
+        assert_eq!(erc20.delegate(alice), Ok(()));
+        env::test::set_block_number(20);
+        assert_eq!(erc20.transfer(bob, 200), Ok(()));
+        // Several changes within one block result in a single checkpoint
+        assert_eq!(erc20.transfer(bob, 34), Ok(()));
+        env::test::set_block_number(30);
+        assert_eq!(erc20.mint(alice, 500), Ok(()));
+        // The history reflects the votes at the end of every block
+        assert_eq!(erc20.get_past_votes(alice, 9), 0);
+        assert_eq!(erc20.get_past_votes(alice, 10), 1234);
+        assert_eq!(erc20.get_past_votes(alice, 19), 1234);
+        assert_eq!(erc20.get_past_votes(alice, 20), 1000);
+        assert_eq!(erc20.get_past_votes(alice, 29), 1000);
+        assert_eq!(erc20.get_past_votes(alice, 30), 1500);
+        assert_eq!(erc20.get_votes(alice), 1500);
+        // Bob never delegated, so he has no votes at any point in time
+        assert_eq!(erc20.get_past_votes(bob, 30), 0);
+    }
+
+    #[test]
+    fn snapshots_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // There are no snapshots yet
+        assert_eq!(erc20.balance_of_at(alice, 1), None);
+        assert_eq!(erc20.snapshot(), Ok(1));
+        assert_eq!(erc20.transfer(bob, 234), Ok(()));
+        assert_eq!(erc20.snapshot(), Ok(2));
+        assert_eq!(erc20.mint(bob, 100), Ok(()));
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        // The second transfer does not overwrite what snapshot 2 saw
+        assert_eq!(erc20.snapshot(), Ok(3));
+        // Every snapshot reports the values from the moment it was taken
+        assert_eq!(erc20.balance_of_at(alice, 1), Some(1234));
+        assert_eq!(erc20.balance_of_at(bob, 1), Some(0));
+        assert_eq!(erc20.total_supply_at(1), Some(1234));
+        assert_eq!(erc20.balance_of_at(alice, 2), Some(1000));
+        assert_eq!(erc20.balance_of_at(bob, 2), Some(234));
+        assert_eq!(erc20.total_supply_at(2), Some(1234));
+        assert_eq!(erc20.balance_of_at(alice, 3), Some(900));
+        assert_eq!(erc20.balance_of_at(bob, 3), Some(434));
+        assert_eq!(erc20.total_supply_at(3), Some(1334));
+        // Snapshots which have not been taken yet do not exist
+        assert_eq!(erc20.total_supply_at(4), None);
+    }
+
+    #[test]
+    fn snapshots_are_unaffected_by_later_changes() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.snapshot(), Ok(1));
+        assert_eq!(erc20.burn(234), Ok(()));
+        assert_eq!(erc20.transfer(bob, 500), Ok(()));
+        // Snapshot 1 still sees the original distribution
+        assert_eq!(erc20.balance_of_at(alice, 1), Some(1234));
+        assert_eq!(erc20.balance_of_at(bob, 1), Some(0));
+        assert_eq!(erc20.total_supply_at(1), Some(1234));
+        // while the current values have moved on
+        assert_eq!(erc20.balance_of(alice), 500);
+        assert_eq!(erc20.total_supply(), 1000);
+    }
+
+    #[test]
+    fn only_admins_can_take_snapshots() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.snapshot(), Err(Erc20Error::Unauthorized));
+    }
+
+    #[test]
+    fn flash_loan_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let loans = register_flash_borrower(borrower, FLASH_LOAN_CALLBACK_SUCCESS);
+        // The borrower already holds enough tokens to pay the fee
+        assert_eq!(erc20.flash_fee(10_000), 9);
+        assert_eq!(erc20.transfer(borrower, 9), Ok(()));
+        // and has allowed the token to take back the loan plus the fee
+        env::test::set_caller(borrower);
+        assert_eq!(erc20.approve(env::address(), 10_009), Ok(()));
+        // Alice initiates a flash loan for the borrower
+        env::test::set_caller(alice);
+        assert_eq!(erc20.flash_loan(borrower, 10_000, vec![1, 2, 3]), Ok(()));
+        // The borrower was called back with the details of the loan
+        assert_eq!(*loans.borrow(), vec![(alice, 10_000, 9, vec![1, 2, 3])]);
+        // The loan has been repaid and the fee has been burned
+        assert_eq!(erc20.balance_of(borrower), 0);
+        assert_eq!(erc20.allowance(borrower, env::address()), 0);
+        assert_eq!(erc20.total_supply(), 1225);
+    }
+
+    #[test]
+    fn flash_fee_rounds_up() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let erc20 = deploy_mock(1234);
+        assert_eq!(erc20.flash_fee(0), 0);
+        assert_eq!(erc20.flash_fee(1), 1);
+        assert_eq!(erc20.flash_fee(20_000), 18);
+        assert_eq!(erc20.flash_fee(20_001), 19);
+    }
+
+    #[test]
+    fn flash_loan_respects_the_cap() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a cap slightly above the `init_value`
+        let mut erc20 = Deployment { cap: Some(1300), ..Default::default() }.deploy();
+        register_flash_borrower(borrower, FLASH_LOAN_CALLBACK_SUCCESS);
+        assert_eq!(erc20.max_flash_loan(), 66);
+        assert_eq!(erc20.flash_loan(borrower, 67, Vec::new()), Err(Erc20Error::CapExceeded));
+    }
+
+    #[test]
+    #[should_panic(expected = "the flash loan has not been approved for repayment")]
+    fn flash_loan_without_repayment_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        register_flash_borrower(borrower, FLASH_LOAN_CALLBACK_SUCCESS);
+        // The borrower never allows the token to take the loan back
+        let _ = erc20.flash_loan(borrower, 10_000, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the flash loan has not been repaid")]
+    fn flash_loan_without_the_fee_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        register_flash_borrower(borrower, FLASH_LOAN_CALLBACK_SUCCESS);
+        // The borrower approves the repayment, but cannot afford the fee
+        env::test::set_caller(borrower);
+        assert_eq!(erc20.approve(env::address(), 10_009), Ok(()));
+        env::test::set_caller(alice);
+        let _ = erc20.flash_loan(borrower, 10_000, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the flash loan receiver did not acknowledge the loan")]
+    fn flash_loan_to_an_unaware_receiver_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        register_flash_borrower(borrower, [0x0; 32]);
+        let _ = erc20.flash_loan(borrower, 10_000, Vec::new());
+    }
+
+    #[test]
+    fn reentrant_calls_are_rejected() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let attacker = AccountId::try_from([0xBA; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice has approved the attacker contract for some of her tokens
+        assert_eq!(erc20.approve(attacker, 100), Ok(()));
+        assert_eq!(erc20.transfer(attacker, 9), Ok(()));
+        env::test::set_caller(attacker);
+        assert_eq!(erc20.approve(env::address(), 10_009), Ok(()));
+        // The attacker tries to spend Alice's allowance twice by calling back into
+        // the token from within the flash loan callback. On-chain this would be a
+        // second call into the same contract instance, which we simulate here.
+        let token: *mut Erc20 = &mut erc20;
+        let attempts = Rc::new(RefCell::new(Vec::new()));
+        let recorded = attempts.clone();
+        register_contract(attacker, move |_, _| {
+            env::test::set_caller(attacker);
+            let token = unsafe { &mut *token };
+            recorded.borrow_mut().push(token.transfer_from(alice, attacker, 100).map(|_| ()));
+            recorded.borrow_mut().push(token.approve(alice, 1));
+            Some(FLASH_LOAN_CALLBACK_SUCCESS.encode())
+        });
+        assert_eq!(erc20.flash_loan(attacker, 10_000, Vec::new()), Ok(()));
+        // Every attempt to re-enter the token was rejected
+        assert_eq!(
+            *attempts.borrow(),
+            vec![Err(Erc20Error::ReentrantCall), Err(Erc20Error::ReentrantCall)]
+        );
+        assert_eq!(erc20.allowance(alice, attacker), 100);
+        assert_eq!(erc20.balance_of(alice), 1225);
+        // Once the flash loan is over, the token can be used as usual again
+        assert_eq!(erc20.transfer_from(alice, attacker, 100), Ok(0));
+    }
+
+    #[test]
+    fn reentrant_calls_from_transfer_receivers_are_rejected() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let attacker = AccountId::try_from([0xBA; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice has approved the attacker contract for some of her tokens
+        assert_eq!(erc20.approve(attacker, 100), Ok(()));
+        // The attacker tries to spend Alice's allowance from within the
+        // `on_token_transfer` callback of a transfer Alice makes to it.
+        let token: *mut Erc20 = &mut erc20;
+        let attempts = Rc::new(RefCell::new(Vec::new()));
+        let recorded = attempts.clone();
+        register_contract(attacker, move |_, _| {
+            env::test::set_caller(attacker);
+            let token = unsafe { &mut *token };
+            recorded.borrow_mut().push(token.transfer_from(alice, attacker, 100).map(|_| ()));
+            Some(true.encode())
+        });
+        assert_eq!(erc20.transfer_and_call(attacker, 10, Vec::new()), Ok(()));
+        assert_eq!(*attempts.borrow(), vec![Err(Erc20Error::ReentrantCall)]);
+        assert_eq!(erc20.allowance(alice, attacker), 100);
+        assert_eq!(erc20.balance_of(attacker), 10);
+    }
+
+    #[test]
+    fn lenient_approvals_overwrite_the_allowance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        // By default, Alice can change the allowance directly
+        assert_eq!(erc20.approve(bob, 30), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 30);
+    }
+
+    #[test]
+    fn strict_approvals_require_resetting_to_zero() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value` in strict mode
+        let mut erc20 = Deployment { strict_approvals: true, ..Default::default() }.deploy();
+        // Setting the first allowance works as usual
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        // Alice cannot change the allowance to another non-zero value
+        assert_eq!(erc20.approve(bob, 30), Err(Erc20Error::AllowanceNotZero));
+        assert_eq!(erc20.allowance(alice, bob), 20);
+        // She has to reset it to zero first
+        assert_eq!(erc20.approve(bob, 0), Ok(()));
+        assert_eq!(erc20.approve(bob, 30), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 30);
+    }
+
+    #[test]
+    fn rescue_tokens_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let other_token = AccountId::try_from([0xC0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Somebody sent 100 tokens of another ERC20 to our contract
+        let balances = register_mock_token(other_token, env::address(), 100);
+        // Alice sends them on to Bob
+        assert_eq!(erc20.rescue_tokens(other_token, bob, 60), Ok(()));
+        assert_eq!(mock_balance_of(&balances, env::address()), 40);
+        assert_eq!(mock_balance_of(&balances, bob), 60);
+        // The other token rejects rescuing more than the contract holds
+        assert_eq!(erc20.rescue_tokens(other_token, bob, 41), Err(Erc20Error::CallFailed));
+        assert_eq!(mock_balance_of(&balances, env::address()), 40);
+        // Our own balances are left alone
+        assert_eq!(erc20.balance_of(alice), 1234);
+    }
+
+    #[test]
+    fn transfers_to_the_contract_are_rejected() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice pasted the token address as the recipient
+        assert_eq!(erc20.transfer(env::address(), 34), Err(Erc20Error::TransferToContract));
+        assert_eq!(erc20.transfer_batch(vec![(bob, 1), (env::address(), 34)]), Err(Erc20Error::TransferToContract));
+        // Bob cannot send her tokens there either
+        assert_eq!(erc20.approve(bob, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, env::address(), 34), Err(Erc20Error::TransferToContract));
+        assert_eq!(erc20.allowance(alice, bob), 100);
+        // No tokens have been moved
+        assert_eq!(erc20.balance_of(env::address()), 0);
+        assert_eq!(erc20.balance_of(alice), 1234);
+        assert_eq!(erc20.balance_of(bob), 0);
+    }
+
+    #[test]
+    fn transfers_to_the_contract_can_be_allowed() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, accepting transfers to itself
+        let mut erc20 = Deployment { allow_transfers_to_contract: true, ..Default::default() }.deploy();
+        assert_eq!(erc20.transfer(env::address(), 34), Ok(()));
+        assert_eq!(erc20.balance_of(env::address()), 34);
+        assert_eq!(erc20.balance_of(alice), 1200);
+    }
+
+    #[test]
+    fn rescue_tokens_works_for_our_own_token() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, accepting transfers to itself
+        let mut erc20 = Deployment { allow_transfers_to_contract: true, ..Default::default() }.deploy();
+        // Alice sent some tokens to the contract itself by mistake
+        assert_eq!(erc20.transfer(env::address(), 34), Ok(()));
+        assert_eq!(erc20.rescue_tokens(env::address(), bob, 34), Ok(()));
+        assert_eq!(erc20.balance_of(env::address()), 0);
+        assert_eq!(erc20.balance_of(bob), 34);
+    }
+
+    #[test]
+    fn only_the_owner_can_rescue_tokens() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let other_token = AccountId::try_from([0xC0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let balances = register_mock_token(other_token, env::address(), 100);
+        // Bob tries to take the tokens for himself
+        env::test::set_caller(bob);
+        assert_eq!(erc20.rescue_tokens(other_token, bob, 100), Err(Erc20Error::Unauthorized));
+        assert_eq!(mock_balance_of(&balances, env::address()), 100);
+    }
+
+    #[test]
+    fn burn_on_transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 1% burn rate
+        let mut erc20 = Deployment { burn_bps: 100, ..Default::default() }.deploy();
+        assert_eq!(erc20.burn_rate(), 100);
+        // Alice sends 1000 tokens, of which Bob receives 990
+        assert_eq!(erc20.transfer(bob, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 234);
+        assert_eq!(erc20.balance_of(bob), 990);
+        // The other 10 tokens are gone for good
+        assert_eq!(erc20.total_supply(), 1224);
+        let events = emitted_events();
+        assert_eq!(&events[events.len() - 3..], &[
+            Event::Transfer { from: Some(alice), to: Some(bob), value: 990 },
+            Event::Burn { from: alice, value: 10 },
+            Event::Transfer { from: Some(alice), to: None, value: 10 },
+        ][..]);
+    }
+
+    #[test]
+    fn burn_on_transfer_conserves_the_sent_value() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 2.5% transfer fee and a 3.33% burn rate
+        let mut erc20 = Deployment {
+            initial_balances: vec![(alice, 1_000_000)],
+            fee_bps: 250,
+            burn_bps: 333,
+            ..Default::default()
+        }.deploy();
+        for value in &[0, 1, 99, 1234, 10_001, 333_333] {
+            let supply_before = erc20.total_supply();
+            let alice_before = erc20.balance_of(alice);
+            let bob_before = erc20.balance_of(bob);
+            let fees_before = erc20.balance_of(fee_collector());
+            assert_eq!(erc20.transfer(bob, *value), Ok(()));
+            let sent = alice_before - erc20.balance_of(alice);
+            let received = erc20.balance_of(bob) - bob_before;
+            let fee = erc20.balance_of(fee_collector()) - fees_before;
+            let burned = supply_before - erc20.total_supply();
+            // Every token Alice sent has been received, collected or burned
+            assert_eq!(sent, *value);
+            assert_eq!(sent, received + fee + burned);
+            assert_eq!(burned, *value * 333 / 10_000);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the transfer fee and burn rate must not exceed 100% together")]
+    fn deployment_with_excessive_burn_rate_fails() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        Deployment { fee_bps: 5_000, burn_bps: 5_001, ..Default::default() }.deploy();
+    }
+
+    #[test]
+    fn set_fee_respects_the_burn_rate() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 40% burn rate
+        let mut erc20 = Deployment { burn_bps: 4_000, ..Default::default() }.deploy();
+        // The fee cannot take more than what is left after burning
+        assert_eq!(erc20.set_fee(6_001, alice), Err(Erc20Error::InvalidFee));
+        assert_eq!(erc20.set_fee(6_000, alice), Ok(()));
+    }
+
+    #[test]
+    fn holders_are_tracked_through_transfers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Only Alice holds tokens at first
+        assert_eq!(erc20.holders(0, 10), vec![alice]);
+        // Bob and Charlie receive some tokens
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        assert_eq!(erc20.transfer(charlie, 100), Ok(()));
+        assert_eq!(erc20.holders(0, 10), vec![alice, bob, charlie]);
+        // Alice gives away the rest of her tokens and drops out of the list,
+        // with Charlie taking her place
+        assert_eq!(erc20.transfer(bob, 1034), Ok(()));
+        assert_eq!(erc20.holders(0, 10), vec![charlie, bob]);
+        // Sending zero tokens does not make Alice a holder again
+        assert_eq!(erc20.transfer(bob, 0), Ok(()));
+        assert_eq!(erc20.holders(0, 10), vec![charlie, bob]);
+        // Bob sends all his tokens to Alice
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 1134), Ok(()));
+        assert_eq!(erc20.holders(0, 10), vec![charlie, alice]);
+    }
+
+    #[test]
+    fn holders_can_be_paged() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let accounts: Vec<AccountId> = (1..6)
+            .map(|seed| AccountId::try_from([seed; 32]).unwrap())
+            .collect();
+        for account in &accounts {
+            assert_eq!(erc20.transfer(*account, 1), Ok(()));
+        }
+        // Page through all six holders, two at a time
+        let mut listed = Vec::new();
+        let mut start = 0;
+        loop {
+            let page = erc20.holders(start, 2);
+            if page.is_empty() {
+                break
+            }
+            assert!(page.len() <= 2);
+            start += page.len() as u32;
+            listed.extend(page);
+        }
+        assert_eq!(listed[0], alice);
+        assert_eq!(&listed[1..], &accounts[..]);
+        // Pages past the end are empty, even if `start + limit` overflows
+        assert_eq!(erc20.holders(6, 2), vec![]);
+        assert_eq!(erc20.holders(1, u32::max_value()).len(), 5);
+    }
+
+    #[test]
+    fn holder_count_follows_transfers() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.holder_count(), 1);
+        // Sending nothing does not create a holder
+        assert_eq!(erc20.transfer(bob, 0), Ok(()));
+        assert_eq!(erc20.holder_count(), 1);
+        // Bob goes from zero to a non-zero balance
+        assert_eq!(erc20.transfer(bob, 34), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        // More tokens for an existing holder do not count twice
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        // Sending tokens to yourself changes nothing
+        assert_eq!(erc20.transfer(alice, 1100), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        // Alice goes from a non-zero balance to zero
+        assert_eq!(erc20.transfer(bob, 1100), Ok(()));
+        assert_eq!(erc20.holder_count(), 1);
+        // A failed transfer changes nothing
+        assert_eq!(erc20.transfer(bob, 1), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.holder_count(), 1);
+    }
+
+    #[test]
+    fn holder_count_follows_mint_and_burn() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract without any tokens
+        let mut erc20 = deploy_mock(0);
+        assert_eq!(erc20.holder_count(), 0);
+        // Minting nothing does not create a holder
+        assert_eq!(erc20.mint(bob, 0), Ok(()));
+        assert_eq!(erc20.holder_count(), 0);
+        // Minting to a new account does
+        assert_eq!(erc20.mint(alice, 100), Ok(()));
+        assert_eq!(erc20.holder_count(), 1);
+        assert_eq!(erc20.mint(alice, 100), Ok(()));
+        assert_eq!(erc20.holder_count(), 1);
+        // Burning part of the balance keeps Alice a holder
+        assert_eq!(erc20.burn(150), Ok(()));
+        assert_eq!(erc20.holder_count(), 1);
+        // Burning the rest does not
+        assert_eq!(erc20.burn(50), Ok(()));
+        assert_eq!(erc20.holder_count(), 0);
+    }
+
+    #[test]
+    fn holder_count_includes_the_fee_recipient() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 1% transfer fee and a 1% burn rate
+        let mut erc20 = Deployment { fee_bps: 100, burn_bps: 100, ..Default::default() }.deploy();
+        // Transfers too small to pay a fee do not make the collector a holder
+        assert_eq!(erc20.transfer(bob, 99), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        // Larger transfers do
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        assert_eq!(erc20.holder_count(), 3);
+        // Bob sends all his tokens back and drops out
+        env::test::set_caller(bob);
+        let balance_bob = erc20.balance_of(bob);
+        assert_eq!(erc20.transfer(alice, balance_bob), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        assert_eq!(erc20.holders(0, 10).len(), 2);
+    }
+
+    #[test]
+    fn allowances_expire_after_the_deadline() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(10);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice allows Bob to spend 100 tokens until block 20
+        assert_eq!(erc20.approve_with_deadline(bob, 100, 20), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 100);
+        assert_eq!(erc20.allowance_deadline(alice, bob), Some(20));
+        // Bob can spend it up to and including block 20
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Ok(90));
+        env::test::set_block_number(20);
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Ok(80));
+        // After that, the allowance can no longer be spent
+        env::test::set_block_number(21);
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Err(Erc20Error::Expired));
+        assert_eq!(erc20.transfer_from_batch(vec![(alice, charlie, 10)]), Err(Erc20Error::Expired));
+        assert_eq!(erc20.balance_of(charlie), 20);
+        assert_eq!(erc20.allowance(alice, bob), 80);
+    }
+
+    #[test]
+    fn approve_removes_the_deadline() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(10);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve_with_deadline(bob, 100, 20), Ok(()));
+        // Adjusting the allowance keeps the deadline
+        assert_eq!(erc20.increase_allowance(bob, 50), Ok(()));
+        assert_eq!(erc20.allowance_deadline(alice, bob), Some(20));
+        // A plain approval never expires
+        assert_eq!(erc20.approve(bob, 100), Ok(()));
+        assert_eq!(erc20.allowance_deadline(alice, bob), None);
+        env::test::set_block_number(21);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 100), Ok(0));
+    }
+
+    #[test]
+    fn rate_limit_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Without a limit, Alice can send everything she has
+        assert_eq!(erc20.remaining_quota(alice), Balance::max_value());
+        // Every account may send 100 tokens per 10 blocks
+        assert_eq!(erc20.set_rate_limit(Some((100, 10))), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), 100);
+        // Alice's window starts with her first transfer in block 5
+        env::test::set_block_number(5);
+        assert_eq!(erc20.transfer(bob, 60), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), 40);
+        env::test::set_block_number(14);
+        assert_eq!(erc20.transfer(bob, 41), Err(Erc20Error::RateLimited));
+        assert_eq!(erc20.transfer(bob, 40), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), 0);
+        assert_eq!(erc20.balance_of(bob), 100);
+        // Bob's quota is his own
+        assert_eq!(erc20.remaining_quota(bob), 100);
+    }
+
+    #[test]
+    fn rate_limit_window_restarts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(5);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_rate_limit(Some((100, 10))), Ok(()));
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        // The window of blocks 5 to 14 is used up
+        env::test::set_block_number(14);
+        assert_eq!(erc20.transfer(bob, 1), Err(Erc20Error::RateLimited));
+        // Block 15 starts a new window
+        env::test::set_block_number(15);
+        assert_eq!(erc20.remaining_quota(alice), 100);
+        assert_eq!(erc20.transfer(bob, 70), Ok(()));
+        // which lasts until block 24
+        env::test::set_block_number(24);
+        assert_eq!(erc20.transfer(bob, 31), Err(Erc20Error::RateLimited));
+        env::test::set_block_number(25);
+        assert_eq!(erc20.transfer(bob, 31), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 201);
+    }
+
+    #[test]
+    fn rate_limit_applies_to_every_kind_of_transfer() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_rate_limit(Some((100, 10))), Ok(()));
+        assert_eq!(erc20.approve(bob, 1000), Ok(()));
+        // Bob spends Alice's quota when he spends her tokens
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 50), Ok(950));
+        assert_eq!(erc20.transfer_from_batch(vec![(alice, charlie, 30), (alice, bob, 30)]), Err(Erc20Error::RateLimited));
+        // Batches are rejected as a whole
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer_batch(vec![(bob, 30), (charlie, 30)]), Err(Erc20Error::RateLimited));
+        assert_eq!(erc20.remaining_quota(alice), 50);
+        assert_eq!(erc20.transfer_batch(vec![(bob, 25), (charlie, 25)]), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), 0);
+    }
+
+    #[test]
+    fn only_admins_can_set_the_rate_limit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.set_rate_limit(Some((1, 1))), Err(Erc20Error::Unauthorized));
+        // Alice sets and lifts the limit
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_rate_limit(Some((1, 1))), Ok(()));
+        assert_eq!(emitted_events().last(), Some(&Event::RateLimitChanged { limit: Some((1, 1)) }));
+        assert_eq!(erc20.set_rate_limit(None), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), Balance::max_value());
+    }
+
+    #[test]
+    fn mint_proposals_execute_at_the_threshold() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Alice, Bob and Charlie are signers, and 2 of them have to agree
+        assert_eq!(erc20.grant_role(Role::MintSigner, bob), Ok(()));
+        assert_eq!(erc20.grant_role(Role::MintSigner, charlie), Ok(()));
+        assert_eq!(erc20.mint_threshold(), 2);
+        // Bob proposes to mint 100 tokens for Dave
+        env::test::set_caller(bob);
+        assert_eq!(erc20.propose_mint(dave, 100), Ok(0));
+        assert_eq!(erc20.mint_proposal(0), Some(MintProposal { to: dave, value: 100, approvals: 1, executed: false }));
+        // Nothing is minted until a second signer agrees
+        assert_eq!(erc20.balance_of(dave), 0);
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.approve_mint(0), Ok(()));
+        assert_eq!(erc20.balance_of(dave), 100);
+        assert_eq!(erc20.total_supply(), 1334);
+        assert_eq!(erc20.mint_proposal(0), Some(MintProposal { to: dave, value: 100, approvals: 2, executed: true }));
+        // Late approvals do not mint the tokens again
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve_mint(0), Err(Erc20Error::AlreadyExecuted));
+        assert_eq!(erc20.balance_of(dave), 100);
+    }
+
+    #[test]
+    fn mint_proposals_cannot_be_approved_twice() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.propose_mint(bob, 100), Ok(0));
+        // Alice's proposal already counts as her approval
+        assert_eq!(erc20.approve_mint(0), Err(Erc20Error::AlreadyApproved));
+        assert_eq!(erc20.mint_proposal(0).unwrap().approvals, 1);
+        assert_eq!(erc20.balance_of(bob), 0);
+        // Proposals which do not exist cannot be approved either
+        assert_eq!(erc20.approve_mint(1), Err(Erc20Error::UnknownProposal));
+    }
+
+    #[test]
+    fn only_signers_can_propose_and_approve_mints() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.propose_mint(alice, 100), Ok(0));
+        // Bob is not a signer
+        env::test::set_caller(bob);
+        assert_eq!(erc20.propose_mint(bob, 100), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.approve_mint(0), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.mint_proposal(0).unwrap().approvals, 1);
+    }
+
+    #[test]
+    fn mint_threshold_can_be_changed() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not an admin
+        env::test::set_caller(bob);
+        assert_eq!(erc20.set_mint_threshold(1), Err(Erc20Error::Unauthorized));
+        // Proposals need at least one approval
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_mint_threshold(0), Err(Erc20Error::InvalidThreshold));
+        // With a threshold of 1, proposals are carried out right away
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_mint_threshold(1), Ok(()));
+        assert_eq!(erc20.propose_mint(bob, 100), Ok(0));
+        assert_eq!(erc20.balance_of(bob), 100);
+    }
+
+    #[test]
+    fn failed_mints_do_not_count_the_approval() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a cap of 1300
+        let mut erc20 = Deployment { cap: Some(1300), ..Default::default() }.deploy();
+        assert_eq!(erc20.grant_role(Role::MintSigner, bob), Ok(()));
+        assert_eq!(erc20.propose_mint(alice, 100), Ok(0));
+        // The mint would exceed the cap, so Bob's approval is rejected
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve_mint(0), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.mint_proposal(0).unwrap().approvals, 1);
+        // Once some tokens are burned, Bob can approve again
+        env::test::set_caller(alice);
+        assert_eq!(erc20.burn(34), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve_mint(0), Ok(()));
+        assert_eq!(erc20.total_supply(), 1300);
+    }
+
+    #[test]
+    fn locked_tokens_cannot_be_transferred() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 500), Ok(()));
+        // Alice locks 400 of Bob's tokens until block 10
+        assert_eq!(erc20.lock(bob, 400, 10), Ok(()));
+        assert_eq!(erc20.locked_of(bob), 400);
+        assert_eq!(erc20.free_balance_of(bob), 100);
+        // Bob can only spend the unlocked portion
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 101), Err(Erc20Error::BalanceLocked));
+        assert_eq!(erc20.transfer_batch(vec![(alice, 50), (alice, 51)]), Err(Erc20Error::BalanceLocked));
+        assert_eq!(erc20.transfer(alice, 100), Ok(()));
+        assert_eq!(erc20.free_balance_of(bob), 0);
+        // Tokens he receives while locked are free to spend
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer(bob, 50), Ok(()));
+        assert_eq!(erc20.free_balance_of(bob), 50);
+        // The lock still holds in block 9
+        env::test::set_block_number(9);
+        assert_eq!(erc20.locked_of(bob), 400);
+        // and ends with block 10
+        env::test::set_block_number(10);
+        assert_eq!(erc20.locked_of(bob), 0);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 450), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 0);
+    }
+
+    #[test]
+    fn locks_cover_spending_through_allowances() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.lock(alice, 1000, 10), Ok(()));
+        assert_eq!(erc20.approve(bob, 1234), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 235), Err(Erc20Error::BalanceLocked));
+        assert_eq!(erc20.transfer_from_batch(vec![(alice, bob, 235)]), Err(Erc20Error::BalanceLocked));
+        assert_eq!(erc20.transfer_from(alice, bob, 234), Ok(1000));
+        // The failed attempts did not use up the allowance
+        assert_eq!(erc20.allowance(alice, bob), 1000);
+    }
+
+    #[test]
+    fn only_lockers_can_lock_balances() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let vesting = AccountId::try_from([0xEE; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.lock(alice, 1234, 10), Err(Erc20Error::Unauthorized));
+        // Alice lets a vesting contract lock balances
+        env::test::set_caller(alice);
+        assert_eq!(erc20.grant_role(Role::Locker, vesting), Ok(()));
+        env::test::set_caller(vesting);
+        assert_eq!(erc20.lock(bob, 100, 10), Ok(()));
+        assert_eq!(emitted_events().last(), Some(&Event::Locked { account: bob, value: 100, until: 10 }));
+        // A new lock replaces the old one
+        assert_eq!(erc20.lock(bob, 50, 5), Ok(()));
+        assert_eq!(erc20.locked_of(bob), 50);
+    }
+
+    #[test]
+    fn transfer_with_memo_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer_with_memo(bob, 234, b"deposit #42".to_vec()), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 234);
+        // The memo is part of the encoded event
+        let event = env::test::emitted_events().last().expect("at least one event has been deposited");
+        let expected = Event::TransferWithMemo { from: alice, to: bob, value: 234, memo: b"deposit #42".to_vec() };
+        assert_eq!(event.data, expected.encode());
+        assert_eq!(event.topics, vec![account_topic(&alice), account_topic(&bob)]);
+        // The regular `Transfer` event is emitted right before it
+        let events = emitted_events();
+        assert_eq!(events[events.len() - 2], Event::Transfer { from: Some(alice), to: Some(bob), value: 234 });
+    }
+
+    #[test]
+    fn transfer_with_memo_limits_the_memo_size() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer_with_memo(bob, 1, vec![0x2A; MAX_MEMO_LEN + 1]), Err(Erc20Error::MemoTooLong));
+        assert_eq!(erc20.balance_of(bob), 0);
+        assert_eq!(erc20.transfer_with_memo(bob, 1, vec![0x2A; MAX_MEMO_LEN]), Ok(()));
+        // A failed transfer does not emit the memo
+        assert_eq!(erc20.transfer_with_memo(bob, 4321, Vec::new()), Err(Erc20Error::InsufficientBalance));
+        let expected = Event::TransferWithMemo { from: alice, to: bob, value: 1, memo: vec![0x2A; MAX_MEMO_LEN] };
+        assert_eq!(emitted_events().last(), Some(&expected));
+    }
+
+    #[test]
+    fn approve_and_call_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let approvals = register_approval_receiver(receiver, true);
+        assert_eq!(erc20.approve_and_call(receiver, 100, vec![1, 2, 3]), Ok(()));
+        // The receiver was notified after the allowance had been set
+        assert_eq!(*approvals.borrow(), vec![(alice, 100, vec![1, 2, 3])]);
+        assert_eq!(erc20.allowance(alice, receiver), 100);
+        assert_eq!(emitted_events().last(), Some(&Event::Approval { owner: alice, spender: receiver, value: 100 }));
+    }
+
+    #[test]
+    fn approve_and_call_lets_the_receiver_spend_the_allowance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // The receiver pulls the approved tokens in from within the callback.
+        // On-chain this would be a call back into the same contract instance,
+        // which we simulate here.
+        let token: *mut Erc20 = &mut erc20;
+        register_contract(receiver, move |_, input| {
+            let (owner, value, _): (AccountId, Balance, Vec<u8>) =
+                Decode::decode(&mut &input[..]).expect("a valid approval");
+            env::test::set_caller(receiver);
+            let token = unsafe { &mut *token };
+            Some(token.transfer_from(owner, receiver, value).is_ok().encode())
+        });
+        assert_eq!(erc20.approve_and_call(receiver, 100, Vec::new()), Ok(()));
+        assert_eq!(erc20.balance_of(receiver), 100);
+        assert_eq!(erc20.allowance(alice, receiver), 0);
+    }
+
+    #[test]
+    fn approve_and_call_checks_the_approval_first() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value` in strict mode
+        let mut erc20 = Deployment { strict_approvals: true, ..Default::default() }.deploy();
+        let approvals = register_approval_receiver(receiver, true);
+        assert_eq!(erc20.approve(receiver, 20), Ok(()));
+        assert_eq!(erc20.approve_and_call(receiver, 30, Vec::new()), Err(Erc20Error::AllowanceNotZero));
+        // The receiver is only called once the approval went through
+        assert!(approvals.borrow().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "the spender did not accept the approval")]
+    fn approve_and_call_reverts_if_the_receiver_rejects() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        register_approval_receiver(receiver, false);
+        let _ = erc20.approve_and_call(receiver, 100, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the spender did not accept the approval")]
+    fn approve_and_call_to_a_non_contract_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not a contract, so he cannot accept the approval
+        let _ = erc20.approve_and_call(bob, 100, Vec::new());
+    }
+
+    #[test]
+    fn transfer_and_call_notifies_contracts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let transfers = register_transfer_receiver(receiver, true);
+        assert_eq!(erc20.transfer_and_call(receiver, 234, vec![1, 2, 3]), Ok(()));
+        // The receiver was notified after the tokens had arrived
+        assert_eq!(*transfers.borrow(), vec![(alice, 234, vec![1, 2, 3])]);
+        assert_eq!(erc20.balance_of(receiver), 234);
+        assert_eq!(erc20.balance_of(alice), 1000);
+    }
+
+    #[test]
+    fn transfer_and_call_to_a_plain_account_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Bob is not a contract, so he receives the tokens like with `transfer`
+        assert_eq!(erc20.transfer_and_call(bob, 234, vec![1, 2, 3]), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 234);
+        assert_eq!(emitted_events().last(), Some(&Event::Transfer { from: Some(alice), to: Some(bob), value: 234 }));
+    }
+
+    #[test]
+    fn transfer_and_call_fails_without_notifying_on_insufficient_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let transfers = register_transfer_receiver(receiver, true);
+        assert_eq!(erc20.transfer_and_call(receiver, 4321, Vec::new()), Err(Erc20Error::InsufficientBalance));
+        assert!(transfers.borrow().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient did not accept the transfer")]
+    fn transfer_and_call_reverts_if_the_receiver_rejects() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        register_transfer_receiver(receiver, false);
+        let _ = erc20.transfer_and_call(receiver, 234, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient did not accept the transfer")]
+    fn transfer_and_call_reverts_if_the_callback_fails() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // The receiver is a contract without an `on_token_transfer` message
+        register_contract(receiver, |_, _| None);
+        let _ = erc20.transfer_and_call(receiver, 234, Vec::new());
+    }
+
+    #[test]
+    fn safe_transfer_to_a_compliant_receiver_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let transfers = register_erc20_receiver(receiver, ON_ERC20_RECEIVED_SELECTOR);
+        assert_eq!(erc20.safe_transfer(receiver, 234, vec![1, 2, 3]), Ok(()));
+        assert_eq!(*transfers.borrow(), vec![(alice, alice, 234, vec![1, 2, 3])]);
+        assert_eq!(erc20.balance_of(receiver), 234);
+    }
+
+    #[test]
+    fn safe_transfer_to_a_plain_account_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.safe_transfer(bob, 234, Vec::new()), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 234);
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient cannot receive tokens")]
+    fn safe_transfer_to_a_non_compliant_receiver_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0xA0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // The receiver answers, but not with the expected acknowledgement
+        register_erc20_receiver(receiver, [0x0; 4]);
+        let _ = erc20.safe_transfer(receiver, 234, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient cannot receive tokens")]
+    fn safe_transfer_to_an_unaware_contract_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, accepting transfers to itself
+        let mut erc20 = Deployment { allow_transfers_to_contract: true, ..Default::default() }.deploy();
+        // The token itself does not know about `on_erc20_received`
+        register_contract(env::address(), |_, _| None);
+        let _ = erc20.safe_transfer(env::address(), 234, Vec::new());
+    }
+
+    #[test]
+    fn drained_balances_are_removed_from_storage() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 1234), Ok(()));
+        // Alice's balance is gone rather than stored as 0
+        assert_eq!(erc20.balances.get(&alice), None);
+        assert_eq!(erc20.balance_of(alice), 0);
+        // Bob burns everything he has
+        env::test::set_caller(alice);
+        assert_eq!(erc20.grant_role(Role::Burner, bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.burn(1234), Ok(()));
+        assert_eq!(erc20.balances.get(&bob), None);
+    }
+
+    #[test]
+    fn zero_allowances_are_removed_from_storage() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        assert_eq!(erc20.approve(charlie, 20), Ok(()));
+        // Spending the whole allowance removes it
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 20), Ok(0));
+        assert_eq!(erc20.allowances.get(&(alice, bob)), None);
+        assert_eq!(erc20.allowance(alice, bob), 0);
+        // and so does revoking it
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve(charlie, 0), Ok(()));
+        assert_eq!(erc20.allowances.get(&(alice, charlie)), None);
+        // Allowances are only stored while they are non-zero
+        assert_eq!(erc20.decrease_allowance(charlie, 10), Ok(()));
+        assert_eq!(erc20.allowances.get(&(alice, charlie)), None);
+    }
+
+    #[test]
+    fn total_burned_tracks_explicit_burns() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.total_burned(), 0);
+        assert_eq!(erc20.burn(34), Ok(()));
+        assert_eq!(erc20.approve(bob, 100), Ok(()));
+        assert_eq!(erc20.grant_role(Role::Burner, bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.burn_from(alice, 100), Ok(()));
+        // A failed burn does not count
+        assert_eq!(erc20.burn(1), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.total_burned(), 134);
+        // Every token is either still in circulation or has been burned
+        assert_eq!(erc20.total_supply() + erc20.total_burned(), 1234);
+    }
+
+    #[test]
+    fn total_burned_tracks_burns_on_transfer() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 1% burn rate
+        let mut erc20 = Deployment { burn_bps: 100, ..Default::default() }.deploy();
+        assert_eq!(erc20.transfer(bob, 1000), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 500), Ok(()));
+        assert_eq!(erc20.total_burned(), 15);
+        assert_eq!(erc20.total_supply() + erc20.total_burned(), 1234);
+    }
+
+    #[test]
+    fn supply_reconciles_across_mints_and_burns() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let borrower = AccountId::try_from([0xB0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with a 1% burn rate
+        let mut erc20 = Deployment { burn_bps: 100, ..Default::default() }.deploy();
+        assert_eq!(erc20.total_minted(), 0);
+        let reconciles = |erc20: &Erc20| {
+            1234 + erc20.total_minted() == erc20.total_supply() + erc20.total_burned()
+        };
+        // Minters mint directly
+        assert_eq!(erc20.mint(bob, 100), Ok(()));
+        assert!(reconciles(&erc20));
+        // Mint proposals mint once they have been approved
+        assert_eq!(erc20.grant_role(Role::MintSigner, bob), Ok(()));
+        assert_eq!(erc20.propose_mint(charlie, 50), Ok(0));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.approve_mint(0), Ok(()));
+        assert!(reconciles(&erc20));
+        // Emissions are minted when they are claimed
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_emission_schedule(Some((10, 10))), Ok(()));
+        env::test::set_block_number(21);
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        assert!(reconciles(&erc20));
+        // Imported balances are minted as well
+        assert_eq!(erc20.migrate_balances(vec![(charlie, 30)]), Ok(()));
+        assert!(reconciles(&erc20));
+        // Flash loans mint the loan and burn the repayment
+        register_flash_borrower(borrower, FLASH_LOAN_CALLBACK_SUCCESS);
+        assert_eq!(erc20.transfer(borrower, 100), Ok(()));
+        env::test::set_caller(borrower);
+        assert_eq!(erc20.approve(env::address(), 10_009), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.flash_loan(borrower, 10_000, Vec::new()), Ok(()));
+        assert!(reconciles(&erc20));
+        // Burns on transfer and explicit burns
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(charlie, 100), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.burn(34), Ok(()));
+        assert!(reconciles(&erc20));
+        assert_eq!(erc20.total_minted(), 100 + 50 + 20 + 30 + 10_000);
+    }
+
+    #[test]
+    fn version_getters_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let erc20 = deploy_mock(1234);
+        assert_eq!(erc20.version(), "1.0.0");
+        assert_eq!(erc20.storage_version(), 1);
+    }
+
+    #[test]
+    fn upgrade_requires_a_known_storage_version() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let code_hash = Hash::decode(&mut &[0x42u8; 32][..]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Pretend the storage has been left behind by another version
+        erc20.storage_version.set(STORAGE_VERSION + 1);
+        assert_eq!(erc20.upgrade(code_hash), Err(Erc20Error::StorageVersionMismatch));
+        assert_eq!(mock_code_hash(), None);
+    }
+
+    #[test]
+    fn upgrade_keeps_the_storage() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let code_hash = Hash::decode(&mut &[0x42u8; 32][..]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(bob, 234), Ok(()));
+        assert_eq!(erc20.approve(bob, 20), Ok(()));
+        assert_eq!(erc20.upgrade(code_hash), Ok(()));
+        assert_eq!(mock_code_hash(), Some(code_hash));
+        assert_eq!(emitted_events().last(), Some(&Event::Upgraded { code_hash }));
+        // The new code finds the storage just as it was left
+        assert_eq!(erc20.storage_version(), STORAGE_VERSION);
+        assert_eq!(erc20.total_supply(), 1234);
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.balance_of(bob), 234);
+        assert_eq!(erc20.allowance(alice, bob), 20);
+    }
+
+    #[test]
+    fn only_the_owner_can_upgrade() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let code_hash = Hash::decode(&mut &[0x42u8; 32][..]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.upgrade(code_hash), Err(Erc20Error::Unauthorized));
+        assert_eq!(mock_code_hash(), None);
+    }
+
+    #[test]
+    fn balances_can_be_migrated_in_chunks() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Redeploy the token without an initial supply
+        let mut erc20 = deploy_mock(0);
+        // Alice imports the balances of the old token in two chunks
+        assert_eq!(erc20.migrate_balances(vec![(alice, 1000), (bob, 200)]), Ok(()));
+        assert_eq!(erc20.migrate_balances(vec![(charlie, 34), (bob, 100)]), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.balance_of(bob), 300);
+        assert_eq!(erc20.balance_of(charlie), 34);
+        assert_eq!(erc20.total_supply(), 1334);
+        assert_eq!(erc20.holder_count(), 3);
+    }
+
+    #[test]
+    fn migration_chunks_are_all_or_nothing() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Redeploy the token with a cap
+        let mut erc20 = Deployment { initial_balances: Vec::new(), cap: Some(1000), ..Default::default() }.deploy();
+        assert_eq!(erc20.migrate_balances(vec![(alice, 900), (bob, 101)]), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.total_supply(), 0);
+        // Frozen accounts cannot receive imported balances either
+        assert_eq!(erc20.freeze(bob), Ok(()));
+        assert_eq!(erc20.migrate_balances(vec![(alice, 900), (bob, 100)]), Err(Erc20Error::AccountFrozen));
+        assert_eq!(erc20.balance_of(alice), 0);
+    }
+
+    #[test]
+    fn migration_is_locked_after_finalizing() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Redeploy the token without an initial supply
+        let mut erc20 = deploy_mock(0);
+        // Only admins can import balances
+        env::test::set_caller(bob);
+        assert_eq!(erc20.migrate_balances(vec![(bob, 1000)]), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.finalize_migration(), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.migrate_balances(vec![(bob, 100)]), Ok(()));
+        assert_eq!(erc20.migration_finalized(), false);
+        assert_eq!(erc20.finalize_migration(), Ok(()));
+        assert_eq!(erc20.migration_finalized(), true);
+        // No more balances can be imported from now on
+        assert_eq!(erc20.migrate_balances(vec![(alice, 1000)]), Err(Erc20Error::MigrationFinalized));
+        assert_eq!(erc20.balance_of(alice), 0);
+        assert_eq!(erc20.total_supply(), 100);
+    }
+
+    #[test]
+    fn only_the_owner_can_terminate() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.terminate(bob), Err(Erc20Error::Unauthorized));
+        assert_eq!(mock_beneficiary(), None);
+        // Alice terminates the contract and sends its balance to Bob
+        env::test::set_caller(alice);
+        assert_eq!(erc20.terminate(bob), Ok(()));
+        assert_eq!(mock_beneficiary(), Some(bob));
+    }
+
+    #[test]
+    fn max_allowances_are_infinite() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, Balance::max_value()), Ok(()));
+        assert_eq!(erc20.grant_role(Role::Burner, bob), Ok(()));
+        // Bob pulls from Alice again and again without the allowance shrinking
+        env::test::set_caller(bob);
+        for _ in 0..3 {
+            assert_eq!(erc20.transfer_from(alice, bob, 100), Ok(Balance::max_value()));
+            assert_eq!(erc20.allowance(alice, bob), Balance::max_value());
+        }
+        assert_eq!(erc20.transfer_from_batch(vec![(alice, bob, 100), (alice, bob, 100)]), Ok(()));
+        assert_eq!(erc20.burn_from(alice, 100), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), Balance::max_value());
+        assert_eq!(erc20.balance_of(alice), 534);
+    }
+
+    #[test]
+    fn finite_allowances_shrink() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Just below the maximum, the allowance is spent as usual
+        assert_eq!(erc20.approve(bob, Balance::max_value() - 1), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 100), Ok(Balance::max_value() - 101));
+        assert_eq!(erc20.allowance(alice, bob), Balance::max_value() - 101);
+    }
+
+    #[test]
+    fn transfer_from_returns_the_remaining_allowance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 30), Ok(70));
+        assert_eq!(erc20.transfer_from(alice, charlie, 70), Ok(0));
+        // The remainder matches what `allowance` reports
+        assert_eq!(erc20.allowance(alice, bob), 0);
+    }
+
+    #[test]
+    fn transfer_from_returns_the_remaining_allowance_after_fees() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 1% transfer fee
+        let mut erc20 = Deployment { fee_bps: 100, ..Default::default() }.deploy();
+        assert_eq!(erc20.approve(bob, 1000), Ok(()));
+        // The fee is part of the spent value, so it comes out of the allowance too
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, charlie, 500), Ok(500));
+        assert_eq!(erc20.balance_of(charlie), 495);
+    }
+
+    #[test]
+    fn emission_accrues_per_era() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Without a schedule, nothing is emitted
+        env::test::set_block_number(50);
+        assert_eq!(erc20.accrue(), Ok(0));
+        // 100 tokens are emitted every 10 blocks from block 50 on
+        assert_eq!(erc20.set_emission_schedule(Some((100, 10))), Ok(()));
+        assert_eq!(erc20.emission_schedule(), Some((100, 10)));
+        // Only complete eras count
+        env::test::set_block_number(59);
+        assert_eq!(erc20.pending_emission(), 0);
+        env::test::set_block_number(75);
+        assert_eq!(erc20.pending_emission(), 200);
+        assert_eq!(erc20.accrue(), Ok(200));
+        // The blocks of the current era keep counting after an accrual
+        env::test::set_block_number(80);
+        assert_eq!(erc20.accrue(), Ok(300));
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 300);
+        assert_eq!(erc20.total_supply(), 1534);
+        assert_eq!(erc20.pending_emission(), 0);
+    }
+
+    #[test]
+    fn accruing_twice_in_a_block_emits_nothing_more() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_emission_schedule(Some((100, 10))), Ok(()));
+        // No time has passed since the schedule was set
+        assert_eq!(erc20.accrue(), Ok(0));
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 0);
+        env::test::set_block_number(21);
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        // Claiming again in the same block finds nothing new
+        assert_eq!(erc20.accrue(), Ok(0));
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 200);
+    }
+
+    #[test]
+    fn changing_the_emission_keeps_completed_eras() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_emission_schedule(Some((100, 10))), Ok(()));
+        // Two eras complete under the first schedule
+        env::test::set_block_number(25);
+        assert_eq!(erc20.set_emission_schedule(Some((10, 5))), Ok(()));
+        assert_eq!(erc20.pending_emission(), 200);
+        // Three eras complete under the second one
+        env::test::set_block_number(40);
+        assert_eq!(erc20.pending_emission(), 230);
+        // Stopping the inflation keeps what has been accrued
+        assert_eq!(erc20.set_emission_schedule(None), Ok(()));
+        env::test::set_block_number(100);
+        assert_eq!(erc20.claim_emission(bob), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 230);
+        // Eras must last at least one block
+        assert_eq!(erc20.set_emission_schedule(Some((10, 0))), Err(Erc20Error::InvalidEra));
+    }
+
+    #[test]
+    fn only_the_owner_can_claim_emission() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_emission_schedule(Some((100, 10))), Ok(()));
+        env::test::set_block_number(11);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.set_emission_schedule(None), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.claim_emission(bob), Err(Erc20Error::Unauthorized));
+        // Anybody can accrue, but the emission stays pending
+        assert_eq!(erc20.accrue(), Ok(100));
+        assert_eq!(erc20.balance_of(bob), 0);
+    }
+
+    #[test]
+    fn emission_respects_the_cap() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with a cap slightly above the `init_value`
+        let mut erc20 = Deployment { cap: Some(1300), ..Default::default() }.deploy();
+        assert_eq!(erc20.set_emission_schedule(Some((100, 10))), Ok(()));
+        env::test::set_block_number(11);
+        assert_eq!(erc20.claim_emission(alice), Err(Erc20Error::CapExceeded));
+        // The emission is kept for later
+        assert_eq!(erc20.pending_emission(), 100);
+        assert_eq!(erc20.total_supply(), 1234);
+    }
+
+    #[test]
+    fn max_transfer_amount_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.max_transfer_amount(), None);
+        // No transfer may move more than 100 tokens
+        assert_eq!(erc20.set_max_transfer(Some(100)), Ok(()));
+        assert_eq!(erc20.max_transfer_amount(), Some(100));
+        assert_eq!(erc20.transfer(bob, 101), Err(Erc20Error::TransferTooLarge));
+        assert_eq!(erc20.transfer(bob, 100), Ok(()));
+        // Batches are checked leg by leg, before any of them is carried out
+        assert_eq!(erc20.transfer_batch(vec![(bob, 100), (bob, 101)]), Err(Erc20Error::TransferTooLarge));
+        assert_eq!(erc20.transfer_batch(vec![(bob, 100), (bob, 100)]), Ok(()));
+        // Spenders are limited as well
+        assert_eq!(erc20.approve(bob, 500), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 101), Err(Erc20Error::TransferTooLarge));
+        assert_eq!(erc20.balance_of(bob), 300);
+        // Lifting the limit allows large transfers again
+        env::test::set_caller(alice);
+        assert_eq!(erc20.set_max_transfer(None), Ok(()));
+        assert_eq!(erc20.transfer(bob, 934), Ok(()));
+    }
+
+    #[test]
+    fn exempt_accounts_bypass_the_max_transfer_amount() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let treasury = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_max_transfer(Some(100)), Ok(()));
+        assert_eq!(erc20.exempt(treasury), Ok(()));
+        assert_eq!(erc20.is_exempt(treasury), true);
+        // Transfers to an exempt account are not limited
+        assert_eq!(erc20.transfer(treasury, 1000), Ok(()));
+        // Neither are transfers from it
+        env::test::set_caller(treasury);
+        assert_eq!(erc20.transfer(bob, 500), Ok(()));
+        // Bob is not exempt
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 101), Err(Erc20Error::TransferTooLarge));
+        // Once unexempted, the treasury is limited like everybody else
+        env::test::set_caller(alice);
+        assert_eq!(erc20.unexempt(treasury), Ok(()));
+        assert_eq!(erc20.is_exempt(treasury), false);
+        env::test::set_caller(treasury);
+        assert_eq!(erc20.transfer(bob, 101), Err(Erc20Error::TransferTooLarge));
+    }
+
+    #[test]
+    fn only_admins_can_configure_the_max_transfer_amount() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.set_max_transfer(Some(100)), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.exempt(bob), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.unexempt(alice), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.max_transfer_amount(), None);
+    }
+
+    #[test]
+    fn guardians_can_trip_the_circuit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let guardian = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.grant_role(Role::Guardian, guardian), Ok(()));
+        // Bob is no guardian
+        env::test::set_caller(bob);
+        assert_eq!(erc20.trip_circuit(), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(guardian);
+        assert_eq!(erc20.trip_circuit(), Ok(()));
+        assert_eq!(erc20.circuit_tripped(), true);
+        assert_eq!(emitted_events().last(), Some(&Event::CircuitTripped { guardian: Some(guardian) }));
+        // Transfers are halted, but minting still works
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer(bob, 10), Err(Erc20Error::CircuitTripped));
+        assert_eq!(erc20.mint(bob, 10), Ok(()));
+        // Guardians cannot resume transfers on their own
+        env::test::set_caller(guardian);
+        assert_eq!(erc20.reset_circuit(), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.reset_circuit(), Ok(()));
+        assert_eq!(erc20.circuit_tripped(), false);
+        assert_eq!(emitted_events().last(), Some(&Event::CircuitReset { account: alice }));
+        assert_eq!(erc20.transfer(bob, 10), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 20);
+    }
+
+    #[test]
+    fn abnormal_volume_trips_the_circuit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // At most 500 tokens may be transferred within 10 blocks
+        assert_eq!(erc20.set_circuit_threshold(Some((500, 10))), Ok(()));
+        assert_eq!(erc20.circuit_threshold(), Some((500, 10)));
+        assert_eq!(erc20.transfer(bob, 300), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, 200), Ok(()));
+        // The volume of a new window starts from zero
+        env::test::set_block_number(11);
+        assert_eq!(erc20.transfer(alice, 100), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer_batch(vec![(bob, 200), (bob, 200)]), Ok(()));
+        // The next token over the threshold trips the circuit
+        assert_eq!(erc20.transfer(bob, 1), Err(Erc20Error::CircuitTripped));
+        assert_eq!(erc20.circuit_tripped(), true);
+        assert_eq!(emitted_events().last(), Some(&Event::CircuitTripped { guardian: None }));
+        // Every transfer is halted until an admin resets the circuit
+        env::test::set_block_number(30);
+        assert_eq!(erc20.transfer(bob, 1), Err(Erc20Error::CircuitTripped));
+        assert_eq!(erc20.reset_circuit(), Ok(()));
+        assert_eq!(erc20.transfer(bob, 1), Ok(()));
+        assert_eq!(erc20.balance_of(bob), 401);
+    }
+
+    #[test]
+    fn batches_over_the_volume_threshold_trip_the_circuit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_circuit_threshold(Some((500, 10))), Ok(()));
+        // The batch is checked as a whole before any leg is carried out
+        assert_eq!(erc20.transfer_batch(vec![(bob, 300), (bob, 201)]), Err(Erc20Error::CircuitTripped));
+        assert_eq!(erc20.circuit_tripped(), true);
+        assert_eq!(erc20.balance_of(bob), 0);
+    }
+
+    #[test]
+    fn transfers_rejected_by_the_circuit_do_not_use_up_the_quota() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
 
         env::test::set_caller(alice);
+        env::test::set_block_number(1);
         // Deploy the contract with some `init_value`
-        let mut erc20 = Erc20::deploy_mock(1234);
-        // Alice does not have enough funds for this
-        assert_eq!(erc20.transfer(bob, 4321), false);
-        // Alice can do this though
-        assert_eq!(erc20.transfer(bob, 234), true);
-        // Check Alice and Bob have the expected balance
-        assert_eq!(erc20.balance_of(alice), 1000);
-        assert_eq!(erc20.balance_of(bob), 234);
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.set_rate_limit(Some((100, 10))), Ok(()));
+        assert_eq!(erc20.set_circuit_threshold(Some((50, 10))), Ok(()));
+        // The circuit rejects the transfer before Alice's quota is touched
+        assert_eq!(erc20.transfer(bob, 60), Err(Erc20Error::CircuitTripped));
+        assert_eq!(erc20.remaining_quota(alice), 100);
+        assert_eq!(erc20.reset_circuit(), Ok(()));
+        // A transfer which passes every check counts against both limits
+        assert_eq!(erc20.transfer(bob, 40), Ok(()));
+        assert_eq!(erc20.remaining_quota(alice), 60);
+        // A rate limited transfer does not count against the volume either
+        assert_eq!(erc20.transfer(bob, 61), Err(Erc20Error::RateLimited));
+        assert_eq!(erc20.transfer(bob, 10), Ok(()));
+        assert_eq!(erc20.circuit_tripped(), false);
+        assert_eq!(erc20.balance_of(bob), 50);
     }
 
     #[test]
-    fn allowance_works() {
+    fn allowances_of_lists_approved_spenders() {
         let alice = AccountId::try_from([0x0; 32]).unwrap();
         let bob = AccountId::try_from([0x1; 32]).unwrap();
         let charlie = AccountId::try_from([0x2; 32]).unwrap();
 
         env::test::set_caller(alice);
         // Deploy the contract with some `init_value`
-        let mut erc20 = Erc20::deploy_mock(1234);
-        // Bob does not have an allowance from Alice's balance
-        assert_eq!(erc20.allowance(alice, bob), 0);
-        // Thus, Bob cannot transfer out of Alice's account
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![]);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(erc20.approve(charlie, 20), Ok(()));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![(bob, 10), (charlie, 20)]);
+        // Updating an allowance keeps its position
+        assert_eq!(erc20.approve(bob, 30), Ok(()));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![(bob, 30), (charlie, 20)]);
+        // Allowances are listed per owner
+        assert_eq!(erc20.allowances_of(bob, 0, 10), vec![]);
+    }
+
+    #[test]
+    fn allowances_of_drops_zero_allowances() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(erc20.approve(charlie, 20), Ok(()));
+        assert_eq!(erc20.approve(dave, 30), Ok(()));
+        // Revoking Bob moves the last spender into his position
+        assert_eq!(erc20.approve(bob, 0), Ok(()));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![(dave, 30), (charlie, 20)]);
+        // Spending an allowance completely drops it as well
+        env::test::set_caller(charlie);
+        assert_eq!(erc20.transfer_from(alice, charlie, 20), Ok(0));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![(dave, 30)]);
+        // Approving again appends the spender
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve(bob, 40), Ok(()));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![(dave, 30), (bob, 40)]);
+        assert_eq!(erc20.approve(bob, 0), Ok(()));
+        assert_eq!(erc20.approve(dave, 0), Ok(()));
+        assert_eq!(erc20.allowances_of(alice, 0, 10), vec![]);
+    }
+
+    #[test]
+    fn allowances_of_pages_through_spenders() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let spenders: Vec<AccountId> = (1..6u8).map(|seed| AccountId::try_from([seed; 32]).unwrap()).collect();
+        for (value, spender) in spenders.iter().enumerate() {
+            assert_eq!(erc20.approve(*spender, value as Balance + 1), Ok(()));
+        }
+        assert_eq!(erc20.allowances_of(alice, 0, 2), vec![(spenders[0], 1), (spenders[1], 2)]);
+        assert_eq!(erc20.allowances_of(alice, 2, 2), vec![(spenders[2], 3), (spenders[3], 4)]);
+        assert_eq!(erc20.allowances_of(alice, 4, 2), vec![(spenders[4], 5)]);
+        // Pages past the end are empty, even if `start + limit` overflows
+        assert_eq!(erc20.allowances_of(alice, 5, 2), vec![]);
+        assert_eq!(erc20.allowances_of(alice, 1, u32::max_value()).len(), 4);
+    }
+
+    #[test]
+    fn rejected_calls_report_failures() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, reporting failures
+        let mut erc20 = Deployment { report_failures: true, ..Default::default() }.deploy();
+        assert_eq!(erc20.transfer(bob, 1235), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(emitted_events().last(), Some(&Event::Failure {
+            reason: Erc20Error::InsufficientBalance,
+            caller: alice
+        }));
+        // Failures are indexed by the caller
+        assert_eq!(last_event_topics(), vec![account_topic(&alice)]);
         env::test::set_caller(bob);
-        assert_eq!(erc20.transfer_from(alice, bob, 1), false);
-        // Alice can approve bob for some of her funds
+        assert_eq!(erc20.transfer_from(alice, bob, 1), Err(Erc20Error::InsufficientAllowance));
+        assert_eq!(emitted_events().last(), Some(&Event::Failure {
+            reason: Erc20Error::InsufficientAllowance,
+            caller: bob
+        }));
         env::test::set_caller(alice);
-        assert_eq!(erc20.approve(bob, 20), true);
-        // And the allowance reflects that correctly
+        assert_eq!(erc20.pause(), Ok(()));
+        assert_eq!(erc20.approve(bob, 10), Err(Erc20Error::Paused));
+        assert_eq!(emitted_events().last(), Some(&Event::Failure {
+            reason: Erc20Error::Paused,
+            caller: alice
+        }));
+    }
+
+    #[test]
+    fn successful_calls_do_not_report_failures() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, reporting failures
+        let mut erc20 = Deployment { report_failures: true, ..Default::default() }.deploy();
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(erc20.transfer(bob, 10), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, bob, 10), Ok(0));
+        let failures = emitted_events()
+            .into_iter()
+            .filter(|event| match event {
+                Event::Failure { .. } => true,
+                _ => false,
+            })
+            .count();
+        assert_eq!(failures, 0);
+    }
+
+    #[test]
+    fn failures_are_not_reported_by_default() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        let events_before = emitted_events().len();
+        assert_eq!(erc20.transfer(bob, 1235), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(emitted_events().len(), events_before);
+    }
+
+    #[test]
+    fn transfers_to_the_burn_address_burn_tokens() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.transfer(burn_address(), 234), Ok(()));
+        let events = emitted_events();
+        assert_eq!(&events[events.len() - 2..], &[
+            Event::Burn { from: alice, value: 234 },
+            Event::Transfer { from: Some(alice), to: None, value: 234 },
+        ][..]);
+        // Spenders and batches burn the same way
+        assert_eq!(erc20.approve(bob, 100), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer_from(alice, burn_address(), 100), Ok(0));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.transfer_batch(vec![(bob, 50), (burn_address(), 50)]), Ok(()));
+        // The supply shrinks and the burn address never holds anything
+        assert_eq!(erc20.total_supply(), 850);
+        assert_eq!(erc20.total_burned(), 384);
+        assert_eq!(erc20.balance_of(alice), 800);
+        assert_eq!(erc20.balance_of(burn_address()), 0);
+        assert_eq!(erc20.holder_count(), 2);
+    }
+
+    #[test]
+    fn transfers_to_the_burn_address_skip_the_fee() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a 1% transfer fee
+        let mut erc20 = Deployment { fee_bps: 100, ..Default::default() }.deploy();
+        assert_eq!(erc20.transfer(burn_address(), 1000), Ok(()));
+        assert_eq!(erc20.balance_of(fee_collector()), 0);
+        assert_eq!(erc20.total_supply(), 234);
+        // Burning more than the balance fails like any other transfer
+        assert_eq!(erc20.transfer(burn_address(), 235), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.total_supply(), 234);
+    }
+
+    #[test]
+    fn unit_conversions_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value` and 6 decimals
+        let erc20 = Deployment { decimals: 6, ..Default::default() }.deploy();
+        assert_eq!(erc20.to_units(12), Some(12_000_000));
+        assert_eq!(erc20.from_units(12_345_678), (12, 345_678));
+        assert_eq!(erc20.from_units(999_999), (0, 999_999));
+        // Amounts which do not fit into a `Balance` are rejected
+        assert_eq!(erc20.to_units(Balance::max_value()), None);
+    }
+
+    #[test]
+    fn unit_conversions_handle_every_decimals() {
+        // Without decimals, units are whole tokens
+        assert_eq!(units::to_units(1234, 0), Some(1234));
+        assert_eq!(units::from_units(1234, 0), (1234, 0));
+        // The largest unit that fits into a `Balance` is 10^38
+        assert_eq!(units::to_units(1, 38), Some(10u128.pow(38)));
+        assert_eq!(units::to_units(4, 38), None);
+        // Beyond that, every `Balance` is a fraction of a token
+        assert_eq!(units::to_units(0, 39), None);
+        assert_eq!(units::from_units(Balance::max_value(), 39), (0, Balance::max_value()));
+        assert_eq!(units::from_units(Balance::max_value(), 255), (0, Balance::max_value()));
+    }
+
+    #[test]
+    fn whole_tokens_round_trip_through_units() {
+        for decimals in &[0u8, 1, 6, 12, 18, 24, 38] {
+            let largest = Balance::max_value() / 10u128.pow(u32::from(*decimals));
+            let amounts = [0, 1, 7, 1234, 999_999_999, largest / 3, largest - 1, largest];
+            for amount in amounts.iter().filter(|amount| **amount <= largest) {
+                let units = units::to_units(*amount, *decimals).expect("the amount fits into a `Balance`");
+                assert_eq!(units::from_units(units, *decimals), (*amount, 0));
+            }
+            // One more whole token overflows
+            if let Some(too_large) = largest.checked_add(1) {
+                assert_eq!(units::to_units(too_large, *decimals), None);
+            }
+        }
+    }
+
+    #[test]
+    fn raw_units_round_trip_through_whole_tokens() {
+        for decimals in &[0u8, 1, 6, 12, 18, 24, 38, 39] {
+            for units in &[0, 1, 9, 10, 1_234_567, 10u128.pow(18) + 1, Balance::max_value() - 1, Balance::max_value()] {
+                let (amount, fraction) = units::from_units(*units, *decimals);
+                // Splitting loses nothing: the whole tokens and the fraction add up again
+                let whole = units::to_units(amount, *decimals).unwrap_or(0);
+                assert_eq!(whole + fraction, *units);
+                // The fraction is always less than one whole token
+                if let Some(unit) = units::to_units(1, *decimals) {
+                    assert!(fraction < unit);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn token_amounts_check_their_arithmetic() {
+        let zero = TokenAmount::default();
+        let one = TokenAmount::from(1);
+        let max = TokenAmount::from(Balance::max_value());
+        assert_eq!(one + one, Some(TokenAmount::from(2)));
+        assert_eq!(max + zero, Some(max));
+        assert_eq!(max + one, None);
+        assert_eq!(one - one, Some(zero));
+        assert_eq!(max - max, Some(zero));
+        assert_eq!(zero - one, None);
+        assert_eq!(Balance::from(TokenAmount::from(1234)), 1234);
+    }
+
+    #[test]
+    fn balances_can_reach_the_largest_amount() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Without a cap, the supply can grow up to the largest `Balance`
+        assert_eq!(erc20.mint(bob, Balance::max_value() - 1234), Ok(()));
+        assert_eq!(erc20.mint(bob, 1), Err(Erc20Error::CapExceeded));
+        assert_eq!(erc20.total_supply(), Balance::max_value());
+        // Moving everything into one account does not overflow it
+        assert_eq!(erc20.transfer(bob, 1234), Ok(()));
+        assert_eq!(erc20.balance_of(bob), Balance::max_value());
+        assert_eq!(erc20.transfer(alice, 1), Err(Erc20Error::InsufficientBalance));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.transfer(alice, Balance::max_value()), Ok(()));
+        assert_eq!(erc20.burn(Balance::max_value()), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.burn(Balance::max_value()), Ok(()));
+        assert_eq!(erc20.total_supply(), 0);
+        assert_eq!(erc20.burn(1), Err(Erc20Error::InsufficientBalance));
+    }
+
+    #[test]
+    fn ownership_changes_deposit_events() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.owner(), Some(alice));
+        assert_eq!(erc20.propose_owner(bob), Ok(()));
+        assert_eq!(last_event_topics(), vec![account_topic(&alice), account_topic(&bob)]);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.accept_ownership(), Ok(()));
+        assert_eq!(erc20.owner(), Some(bob));
+        assert_eq!(erc20.renounce_ownership(), Ok(()));
+        assert_eq!(erc20.owner(), None);
+        assert_eq!(ownership_events(), vec![
+            Event::OwnershipProposed {
+                owner: alice,
+                pending_owner: bob
+            },
+            Event::OwnershipTransferred {
+                previous_owner: alice,
+                new_owner: bob
+            },
+            Event::OwnershipRenounced {
+                previous_owner: bob
+            },
+        ]);
+    }
+
+    #[test]
+    fn renounced_ownership_cannot_be_reclaimed() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Only the owner can renounce
+        env::test::set_caller(bob);
+        assert_eq!(erc20.renounce_ownership(), Err(Erc20Error::Unauthorized));
+        // Renouncing drops the pending proposal
+        env::test::set_caller(alice);
+        assert_eq!(erc20.propose_owner(bob), Ok(()));
+        assert_eq!(erc20.renounce_ownership(), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.accept_ownership(), Err(Erc20Error::Unauthorized));
+        // The previous owner has lost her privileges as well
+        env::test::set_caller(alice);
+        assert_eq!(erc20.propose_owner(alice), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.renounce_ownership(), Err(Erc20Error::Unauthorized));
+        assert_eq!(erc20.owner(), None);
+    }
+
+    #[test]
+    fn any_spender_can_be_approved_by_default() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.is_allowed_spender(bob), false);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(erc20.increase_allowance(bob, 10), Ok(()));
         assert_eq!(erc20.allowance(alice, bob), 20);
+    }
 
-        // Charlie cannot send on behalf of Bob
+    #[test]
+    fn restricted_approvals_require_an_allowed_spender() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = Deployment { restrict_spenders: true, ..Default::default() }.deploy();
+        assert_eq!(erc20.approve(bob, 10), Err(Erc20Error::SpenderNotAllowed));
+        assert_eq!(erc20.increase_allowance(bob, 10), Err(Erc20Error::SpenderNotAllowed));
+        assert_eq!(erc20.approve_with_deadline(bob, 10, 100), Err(Erc20Error::SpenderNotAllowed));
+        // Only admins can change the allow-list
         env::test::set_caller(charlie);
-        assert_eq!(erc20.transfer_from(alice, bob, 10), false);
-        // Bob cannot transfer more than he is allowed
+        assert_eq!(erc20.allow_spender(bob), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.allow_spender(bob), Ok(()));
+        assert_eq!(erc20.is_allowed_spender(bob), true);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        assert_eq!(erc20.approve(charlie, 10), Err(Erc20Error::SpenderNotAllowed));
+        // Once denied, Bob can still spend what he has been approved, but
+        // Alice cannot approve him again
+        assert_eq!(erc20.deny_spender(bob), Ok(()));
+        assert_eq!(erc20.is_allowed_spender(bob), false);
+        assert_eq!(erc20.approve(bob, 20), Err(Erc20Error::SpenderNotAllowed));
         env::test::set_caller(bob);
-        assert_eq!(erc20.transfer_from(alice, charlie, 25), false);
-        // A smaller amount should work though
-        assert_eq!(erc20.transfer_from(alice, charlie, 10), true);
-        // Check that the allowance is updated
+        assert_eq!(erc20.transfer_from(alice, charlie, 5), Ok(5));
+        // Revoking the allowance always works
+        env::test::set_caller(alice);
+        assert_eq!(erc20.approve(bob, 0), Ok(()));
+        assert_eq!(erc20.allowance(alice, bob), 0);
+    }
+
+    #[test]
+    fn fund_buyback_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        // Anyone can fund the pool with native balance
+        env::test::set_caller(bob);
+        set_value_transferred(100);
+        assert_eq!(erc20.fund_buyback(), Ok(()));
+        set_value_transferred(20);
+        assert_eq!(erc20.fund_buyback(), Ok(()));
+        assert_eq!(erc20.buyback_pool(), 120);
+        // Calls without native balance are rejected
+        set_value_transferred(0);
+        assert_eq!(erc20.fund_buyback(), Err(Erc20Error::ZeroValue));
+        assert_eq!(erc20.buyback_pool(), 120);
+        // The pool cannot overflow
+        set_value_transferred(Balance::max_value() - 120);
+        assert_eq!(erc20.fund_buyback(), Ok(()));
+        set_value_transferred(1);
+        assert_eq!(erc20.fund_buyback(), Err(Erc20Error::Overflow));
+        assert_eq!(erc20.buyback_pool(), Balance::max_value());
+    }
+
+    #[test]
+    fn buyback_and_burn_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let treasury = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, part of which goes to the treasury
+        let mut erc20 = Deployment { treasury: Some(treasury), treasury_amount: 234, ..Default::default() }.deploy();
+        set_value_transferred(100);
+        assert_eq!(erc20.fund_buyback(), Ok(()));
+        set_value_transferred(0);
+        // Only the owner can buy back tokens
+        env::test::set_caller(bob);
+        assert_eq!(erc20.buyback_and_burn(60, 200), Err(Erc20Error::Unauthorized));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.buyback_and_burn(60, 200), Ok(()));
+        assert_eq!(erc20.balance_of(treasury), 34);
+        assert_eq!(erc20.total_supply(), 1034);
+        assert_eq!(erc20.buyback_pool(), 40);
+        // The treasury has been paid for its tokens
+        assert_eq!(payouts(), vec![(treasury, 60)]);
+        assert_eq!(emitted_events().pop(), Some(Event::Buyback {
+            treasury: treasury,
+            paid: 60,
+            burned: 200
+        }));
+    }
+
+    #[test]
+    fn buyback_and_burn_fails_on_insufficient_funds() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let treasury = AccountId::try_from([0x7; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`, part of which goes to the treasury
+        let mut erc20 = Deployment { treasury: Some(treasury), treasury_amount: 234, ..Default::default() }.deploy();
+        set_value_transferred(100);
+        assert_eq!(erc20.fund_buyback(), Ok(()));
+        set_value_transferred(0);
+        // The treasury cannot sell more tokens than it holds
+        assert_eq!(erc20.buyback_and_burn(60, 235), Err(Erc20Error::InsufficientBalance));
+        // The pool cannot pay more than it holds
+        assert_eq!(erc20.buyback_and_burn(101, 200), Err(Erc20Error::InsufficientBuybackPool));
+        // Nothing has been burned or paid out
+        assert_eq!(erc20.balance_of(treasury), 234);
+        assert_eq!(erc20.buyback_pool(), 100);
+        assert_eq!(payouts(), vec![]);
+    }
+
+    #[test]
+    fn buyback_and_burn_requires_a_treasury() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.buyback_and_burn(0, 100), Err(Erc20Error::NoTreasury));
+        assert_eq!(erc20.balance_of(alice), 1234);
+    }
+
+    #[test]
+    fn decayed_balance_works() {
+        // Nothing decays without eras or without a rate
+        assert_eq!(decayed_balance(1000, 1000, 0), 1000);
+        assert_eq!(decayed_balance(Balance::max_value(), 0, 1000), Balance::max_value());
+        // 10% per era compounds
+        assert_eq!(decayed_balance(1000, 1000, 1), 900);
+        assert_eq!(decayed_balance(1000, 1000, 2), 810);
+        assert_eq!(decayed_balance(1000, 1000, 3), 729);
+        // Everything decays at 100% per era
+        assert_eq!(decayed_balance(1000, 10_000, 1), 0);
+        // Large balances do not overflow
+        assert!(decayed_balance(Balance::max_value(), 1, 1) < Balance::max_value());
+    }
+
+    #[test]
+    fn balances_decay_with_demurrage() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        // Deploy the contract with 10% demurrage every 10 blocks
+        let mut erc20 = Deployment {
+            initial_balances: vec![(alice, 1000)],
+            demurrage: Some((1000, 10)),
+            ..Default::default()
+        }
+        .deploy();
+        assert_eq!(erc20.demurrage(), Some((1000, 10)));
+        // Only whole eras decay
+        env::test::set_block_number(9);
+        assert_eq!(erc20.effective_balance_of(alice), 1000);
+        env::test::set_block_number(25);
+        assert_eq!(erc20.effective_balance_of(alice), 810);
+        env::test::set_block_number(30);
+        assert_eq!(erc20.effective_balance_of(alice), 729);
+        // Until it is settled, the stored balance is left untouched
+        assert_eq!(erc20.balance_of(alice), 1000);
+        assert_eq!(erc20.total_supply(), 1000);
+        // A transfer settles the demurrage first
+        assert_eq!(erc20.transfer(bob, 29), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 700);
+        assert_eq!(erc20.balance_of(bob), 29);
+        assert_eq!(erc20.total_supply(), 729);
+        assert_eq!(erc20.transfer(bob, 701), Err(Erc20Error::InsufficientBalance));
+        // The next era starts where the last settled one ended
+        env::test::set_block_number(39);
+        assert_eq!(erc20.effective_balance_of(alice), 700);
+        assert_eq!(erc20.effective_balance_of(bob), 29);
+        env::test::set_block_number(40);
+        assert_eq!(erc20.effective_balance_of(alice), 630);
+        assert_eq!(erc20.effective_balance_of(bob), 26);
+    }
+
+    #[test]
+    fn batches_are_validated_against_the_settled_balances() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        // Deploy the contract with 10% demurrage every 10 blocks
+        let mut erc20 = Deployment {
+            initial_balances: vec![(alice, 1000)],
+            demurrage: Some((1000, 10)),
+            ..Default::default()
+        }
+        .deploy();
+        assert_eq!(erc20.approve(bob, 1000), Ok(()));
+        // Alice's stored balance still covers the batches, but only 900 of her
+        // tokens are left once the demurrage has been settled
+        env::test::set_block_number(10);
+        assert_eq!(erc20.transfer_batch(vec![(charlie, 500), (charlie, 450)]), Err(Erc20Error::InsufficientBalance));
+        env::test::set_caller(bob);
+        let ops = vec![(alice, charlie, 500), (alice, charlie, 401)];
+        assert_eq!(erc20.transfer_from_batch(ops), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.balance_of(charlie), 0);
+        // A batch within the settled balance goes through
+        let ops = vec![(alice, charlie, 500), (alice, charlie, 400)];
+        assert_eq!(erc20.transfer_from_batch(ops), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 0);
+        assert_eq!(erc20.balance_of(charlie), 900);
+    }
+
+    #[test]
+    fn delegation_settles_the_demurrage_first() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        // Deploy the contract with 10% demurrage every 10 blocks
+        let mut erc20 = Deployment {
+            initial_balances: vec![(alice, 1000)],
+            demurrage: Some((1000, 10)),
+            ..Default::default()
+        }
+        .deploy();
+        // Only the tokens left after the demurrage are delegated
+        env::test::set_block_number(10);
+        assert_eq!(erc20.delegate(bob), Ok(()));
+        assert_eq!(erc20.balance_of(alice), 900);
+        assert_eq!(erc20.get_votes(bob), 900);
+        // Moving the delegation takes the decay from the old delegate's votes
+        env::test::set_block_number(20);
+        assert_eq!(erc20.delegate(charlie), Ok(()));
+        assert_eq!(erc20.get_votes(bob), 0);
+        assert_eq!(erc20.get_votes(charlie), 810);
+        assert_eq!(erc20.total_supply(), 810);
+    }
+
+    #[test]
+    fn balances_do_not_decay_without_demurrage() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let erc20 = deploy_mock(1234);
+        assert_eq!(erc20.demurrage(), None);
+        env::test::set_block_number(1_000_000);
+        assert_eq!(erc20.effective_balance_of(alice), 1234);
+    }
+
+    #[test]
+    fn operators_can_move_any_amount() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.is_operator_for(alice, alice), true);
+        assert_eq!(erc20.authorize_operator(alice), Err(Erc20Error::SelfOperator));
+        // Bob is not an operator yet
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 10), Err(Erc20Error::NotOperator));
+        env::test::set_caller(alice);
+        assert_eq!(erc20.authorize_operator(bob), Ok(()));
+        assert_eq!(erc20.is_operator_for(bob, alice), true);
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 1000), Ok(()));
+        assert_eq!(erc20.operator_transfer(alice, charlie, 235), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(erc20.balance_of(charlie), 1000);
+        // Once revoked, Bob cannot move Alice's tokens anymore
+        env::test::set_caller(alice);
+        assert_eq!(erc20.revoke_operator(bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 1), Err(Erc20Error::NotOperator));
+        assert_eq!(
+            operator_events(),
+            vec![
+                Event::AuthorizedOperator { operator: bob, holder: alice },
+                Event::RevokedOperator { operator: bob, holder: alice },
+            ]
+        );
+    }
+
+    #[test]
+    fn default_operators_can_be_revoked() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let operator = AccountId::try_from([0x5; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with a default operator
+        let mut erc20 = Deployment {
+            initial_balances: vec![(alice, 100), (bob, 100)],
+            default_operators: vec![operator],
+            ..Default::default()
+        }
+        .deploy();
+        assert_eq!(erc20.default_operators(), vec![operator]);
+        assert_eq!(erc20.is_operator_for(operator, alice), true);
+        assert_eq!(erc20.is_operator_for(operator, bob), true);
+        // Alice opts out, Bob does not
+        assert_eq!(erc20.revoke_operator(operator), Ok(()));
+        env::test::set_caller(operator);
+        assert_eq!(erc20.operator_transfer(alice, operator, 10), Err(Erc20Error::NotOperator));
+        assert_eq!(erc20.operator_transfer(bob, operator, 10), Ok(()));
+        // Alice can opt back in
+        env::test::set_caller(alice);
+        assert_eq!(erc20.authorize_operator(operator), Ok(()));
+        env::test::set_caller(operator);
+        assert_eq!(erc20.operator_transfer(alice, operator, 10), Ok(()));
+        assert_eq!(erc20.balance_of(operator), 20);
+    }
+
+    #[test]
+    fn operators_and_allowances_are_independent() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+
+        env::test::set_caller(alice);
+        // Deploy the contract with some `init_value`
+        let mut erc20 = deploy_mock(1234);
+        assert_eq!(erc20.approve(bob, 10), Ok(()));
+        // An allowance does not make Bob an operator
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 5), Err(Erc20Error::NotOperator));
+        // Operator transfers leave the allowance untouched
+        env::test::set_caller(alice);
+        assert_eq!(erc20.authorize_operator(bob), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 100), Ok(()));
         assert_eq!(erc20.allowance(alice, bob), 10);
-        // and the balance transferred to the right person
-        assert_eq!(erc20.balance_of(charlie), 10);
+        // Being an operator does not raise the allowance
+        assert_eq!(erc20.transfer_from(alice, charlie, 11), Err(Erc20Error::InsufficientAllowance));
+        assert_eq!(erc20.transfer_from(alice, charlie, 10), Ok(0));
+        assert_eq!(erc20.balance_of(charlie), 110);
+        // Operator transfers obey the same rules as every other transfer
+        env::test::set_caller(alice);
+        assert_eq!(erc20.pause(), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc20.operator_transfer(alice, charlie, 1), Err(Erc20Error::Paused));
     }
 }
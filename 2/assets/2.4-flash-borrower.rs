@@ -0,0 +1,231 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `flash_loan` message of the ERC20 token.
+const FLASH_LOAN_SELECTOR: [u8; 4] = [0x90, 0x25, 0x7A, 0x58];
+
+/// The selector of the `flash_fee` message of the ERC20 token.
+const FLASH_FEE_SELECTOR: [u8; 4] = [0x73, 0x56, 0x93, 0xB6];
+
+/// The selector of the `approve` message of the ERC20 token.
+const APPROVE_SELECTOR: [u8; 4] = [0x68, 0x12, 0x66, 0xA0];
+
+/// The value a flash loan receiver returns to acknowledge the loan,
+/// `blake2_256(b"ERC3156FlashBorrower.onFlashLoan")`.
+const FLASH_LOAN_CALLBACK_SUCCESS: [u8; 32] = [
+    0x64, 0xE7, 0xAD, 0x50, 0x62, 0xEB, 0x9B, 0x99, 0xE0, 0x72, 0x65, 0x4D, 0x89, 0xD5, 0x22, 0x9C,
+    0xCA, 0x32, 0xF7, 0x3F, 0x75, 0x1B, 0x7D, 0xAD, 0x27, 0x3F, 0x44, 0x9B, 0xBE, 0x13, 0xE4, 0x2A,
+];
+
+/// The gas limit of cross-contract calls made by the borrower.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+contract! {
+    /// An example flash loan receiver for the ERC20 token.
+    ///
+    /// The borrower does nothing with the borrowed tokens besides paying them
+    /// back, which makes it a good starting point for your own strategies.
+    struct FlashBorrower {
+        /// The ERC20 token this contract borrows from.
+        token: storage::Value<AccountId>,
+        /// The number of flash loans this contract has received.
+        loans: storage::Value<u32>,
+    }
+
+    impl Deploy for FlashBorrower {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.loans.set(0);
+        }
+    }
+
+    impl FlashBorrower {
+        /// Returns the number of flash loans this contract has received.
+        pub(external) fn loans(&self) -> u32 {
+            let loans = *self.loans;
+            env.println(&format!("FlashBorrower::loans = {:?}", loans));
+            loans
+        }
+
+        /// Asks the token for a flash loan of `amount` tokens.
+        ///
+        /// This contract needs to hold enough tokens to pay the flash loan fee.
+        pub(external) fn borrow(&mut self, amount: Balance) -> bool {
+            let token = *self.token;
+            // The token cannot be called while it waits for `on_flash_loan` to
+            // return, so we allow it to take back the loan plus the fee up front.
+            let fee: Option<Balance> = call_contract(token, FLASH_FEE_SELECTOR, &amount.encode());
+            let fee = match fee {
+                Some(fee) => fee,
+                None => return false,
+            };
+            // The token's `Erc20Error` is decoded as its variant index.
+            let approved: Option<Result<(), u8>> =
+                call_contract(token, APPROVE_SELECTOR, &(token, amount + fee).encode());
+            if approved != Some(Ok(())) {
+                return false
+            }
+            let input = (env.address(), amount, Vec::<u8>::new()).encode();
+            let result: Option<Result<(), u8>> = call_contract(token, FLASH_LOAN_SELECTOR, &input);
+            result == Some(Ok(()))
+        }
+
+        /// Called by the token once the borrowed tokens have been minted to us.
+        ///
+        /// This is where the borrowed tokens would be put to use. Once we return,
+        /// the token takes back the loan plus the fee.
+        pub(external) fn on_flash_loan(
+            &mut self,
+            initiator: AccountId,
+            amount: Balance,
+            fee: Balance,
+            data: Vec<u8>,
+        ) -> [u8; 32] {
+            let token = *self.token;
+            // Only the token we borrow from can hand out loans to us.
+            assert_eq!(env.caller(), token, "flash loans are only accepted from the token");
+            env.println(&format!(
+                "FlashBorrower::on_flash_loan(initiator = {:?}, amount = {:?}, fee = {:?}, data = {:?})",
+                initiator, amount, fee, data
+            ));
+            self.loans += 1;
+            FLASH_LOAN_CALLBACK_SUCCESS
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the borrower through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    #[test]
+    fn on_flash_loan_acknowledges_the_loan() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        let mut borrower = FlashBorrower::deploy_mock(token);
+        // The token calls back the borrower during a flash loan
+        env::test::set_caller(token);
+        assert_eq!(borrower.on_flash_loan(alice, 1000, 1, Vec::new()), FLASH_LOAN_CALLBACK_SUCCESS);
+        assert_eq!(borrower.loans(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "flash loans are only accepted from the token")]
+    fn on_flash_loan_rejects_other_callers() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        let mut borrower = FlashBorrower::deploy_mock(token);
+        // Alice pretends to be the token
+        env::test::set_caller(alice);
+        borrower.on_flash_loan(alice, 1000, 1, Vec::new());
+    }
+
+    #[test]
+    fn borrow_approves_the_repayment_up_front() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+
+        // The token records every call made by the borrower
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        register_contract(token, move |selector, input| {
+            recorded.borrow_mut().push((selector, input.to_vec()));
+            match selector {
+                FLASH_FEE_SELECTOR => Some((1 as Balance).encode()),
+                _ => Some(Ok::<(), u8>(()).encode()),
+            }
+        });
+
+        let mut borrower = FlashBorrower::deploy_mock(token);
+        assert_eq!(borrower.borrow(500), true);
+        // The borrower looked up the fee, approved the repayment and only then
+        // asked for the loan to be paid out to itself
+        assert_eq!(*calls.borrow(), vec![
+            (FLASH_FEE_SELECTOR, (500 as Balance).encode()),
+            (APPROVE_SELECTOR, (token, 501 as Balance).encode()),
+            (FLASH_LOAN_SELECTOR, (env::address(), 500 as Balance, Vec::<u8>::new()).encode()),
+        ]);
+    }
+
+    #[test]
+    fn borrow_fails_if_the_repayment_cannot_be_approved() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+
+        // The token refuses every approval
+        register_contract(token, move |selector, _| {
+            match selector {
+                FLASH_FEE_SELECTOR => Some((1 as Balance).encode()),
+                _ => Some(Err::<(), u8>(0).encode()),
+            }
+        });
+
+        let mut borrower = FlashBorrower::deploy_mock(token);
+        assert_eq!(borrower.borrow(500), false);
+    }
+}
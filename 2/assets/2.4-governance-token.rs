@@ -0,0 +1,573 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the governance token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+    },
+    DelegateChanged {
+        delegator: AccountId,
+        from_delegate: Option<AccountId>,
+        to_delegate: AccountId,
+    },
+    DelegateVotesChanged {
+        delegate: AccountId,
+        previous_votes: Balance,
+        new_votes: Balance,
+    },
+    Snapshot {
+        id: u32,
+    },
+}
+
+/// Errors which can occur when calling the governance token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum GovernanceError {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+    /// Only the owner can take snapshots.
+    NotOwner,
+    /// The deadline of a signed message has passed.
+    Expired,
+    /// The signature does not match the signer and the signed message.
+    InvalidSignature,
+}
+
+/// Deposits a governance token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// The signing context of messages signed off-chain for this contract.
+const SIGNING_CONTEXT: &[u8] = b"erc20";
+
+/// Returns `true` if `signature` is a valid sr25519 signature of `message` by `signer`.
+fn verify_signature(signer: &AccountId, message: &[u8], signature: &[u8; 64]) -> bool {
+    let public_key = match schnorrkel::PublicKey::from_bytes(&signer.encode()[..]) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match schnorrkel::Signature::from_bytes(&signature[..]) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify_simple(SIGNING_CONTEXT, message, &signature).is_ok()
+}
+
+contract! {
+    /// An ERC20 token with everything a DAO needs from its governance token.
+    ///
+    /// This preset brings together three extensions of our main token:
+    /// delegated votes with a checkpointed history, so proposals can count the
+    /// votes from the block they were created in; snapshots of balances, for
+    /// example to pay out dividends; and permits, so holders can approve the
+    /// DAO without paying for a transaction. The whole supply is minted to the
+    /// deployer, who also takes the snapshots.
+    struct GovernanceToken {
+        /// The account which takes snapshots.
+        owner: storage::Value<AccountId>,
+        /// The total supply.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+        /// The nonce the next signed message of each account has to use.
+        nonces: storage::HashMap<AccountId, u64>,
+        /// The account each account has delegated its voting power to.
+        delegates: storage::HashMap<AccountId, AccountId>,
+        /// The voting power history of each delegate: (delegate, index) -> (block, votes)
+        checkpoints: storage::HashMap<(AccountId, u32), (BlockNumber, Balance)>,
+        /// The number of checkpoints recorded for each delegate.
+        num_checkpoints: storage::HashMap<AccountId, u32>,
+        /// The id of the most recent snapshot, or 0 if none has been taken yet.
+        current_snapshot_id: storage::Value<u32>,
+        /// Balances recorded for snapshots: (account, index) -> (snapshot id, balance)
+        snapshots: storage::HashMap<(AccountId, u32), (u32, Balance)>,
+        /// The number of balances recorded for each account.
+        snapshot_counts: storage::HashMap<AccountId, u32>,
+    }
+
+    impl Deploy for GovernanceToken {
+        fn deploy(&mut self, init_value: Balance) {
+            let owner = env.caller();
+            self.owner.set(owner);
+            self.total_supply.set(init_value);
+            self.balances.insert(owner, init_value);
+            self.current_snapshot_id.set(0);
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(owner),
+                value: init_value
+            });
+        }
+    }
+
+    impl GovernanceToken {
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("GovernanceToken::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("GovernanceToken::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the amount of tokens that an owner allowed to a spender.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            env.println(&format!(
+                "GovernanceToken::allowance(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, allowance
+            ));
+            allowance
+        }
+
+        /// Transfers token from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), GovernanceError> {
+            self.transfer_impl(env.caller(), to, value)
+        }
+
+        /// Approve the passed AccountId to spend the specified amount of tokens
+        /// on the behalf of the message's sender.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), GovernanceError> {
+            self.approve_impl(env.caller(), spender, value);
+            Ok(())
+        }
+
+        /// Transfer tokens from one AccountId to another.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), GovernanceError> {
+            let spender = env.caller();
+            let allowance = self.allowance_or_zero(&from, &spender);
+            if allowance < value {
+                return Err(GovernanceError::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.allowances.insert((from, spender), allowance - value);
+            Ok(())
+        }
+
+        /// Returns the nonce the next signed message of `owner` has to use.
+        pub(external) fn nonce_of(&self, owner: AccountId) -> u64 {
+            let nonce = self.nonce_of_or_zero(&owner);
+            env.println(&format!("GovernanceToken::nonce_of(owner = {:?}) = {:?}", owner, nonce));
+            nonce
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`
+        /// using a signature of `owner` instead of a call made by `owner`.
+        ///
+        /// The signature must be an sr25519 signature by `owner` over the SCALE
+        /// encoded `(owner, spender, value, nonce, deadline)` tuple, where `nonce`
+        /// is the current `nonce_of(owner)`. The permit can no longer be used
+        /// after the `deadline` block or once it has been used.
+        pub(external) fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: BlockNumber,
+            signature: [u8; 64],
+        ) -> Result<(), GovernanceError> {
+            if env.block_number() > deadline {
+                return Err(GovernanceError::Expired)
+            }
+            let nonce = self.nonce_of_or_zero(&owner);
+            let message = (owner, spender, value, nonce, deadline).encode();
+            if !verify_signature(&owner, &message, &signature) {
+                return Err(GovernanceError::InvalidSignature)
+            }
+            self.nonces.insert(owner, nonce + 1);
+            self.approve_impl(owner, spender, value);
+            Ok(())
+        }
+
+        /// Returns the account `account` has delegated its voting power to, if any.
+        pub(external) fn delegates(&self, account: AccountId) -> Option<AccountId> {
+            let delegate = self.delegates.get(&account).cloned();
+            env.println(&format!("GovernanceToken::delegates(account = {:?}) = {:?}", account, delegate));
+            delegate
+        }
+
+        /// Returns the current voting power of `account`.
+        pub(external) fn get_votes(&self, account: AccountId) -> Balance {
+            let votes = self.votes_at(&account, BlockNumber::max_value());
+            env.println(&format!("GovernanceToken::get_votes(account = {:?}) = {:?}", account, votes));
+            votes
+        }
+
+        /// Returns the voting power `account` had at the end of `block`.
+        pub(external) fn get_past_votes(&self, account: AccountId, block: BlockNumber) -> Balance {
+            let votes = self.votes_at(&account, block);
+            env.println(&format!(
+                "GovernanceToken::get_past_votes(account = {:?}, block = {:?}) = {:?}",
+                account, block, votes
+            ));
+            votes
+        }
+
+        /// Delegates the voting power of the caller's tokens to `to`.
+        ///
+        /// Tokens only count as votes once they have been delegated, so holders
+        /// who want to vote themselves have to delegate to their own account.
+        pub(external) fn delegate(&mut self, to: AccountId) -> Result<(), GovernanceError> {
+            let delegator = env.caller();
+            let from_delegate = self.delegates.insert(delegator, to);
+            deposit_event(Event::DelegateChanged {
+                delegator: delegator,
+                from_delegate: from_delegate,
+                to_delegate: to
+            });
+            let votes = self.balance_of_or_zero(&delegator);
+            self.move_voting_power(from_delegate, Some(to), votes);
+            Ok(())
+        }
+
+        /// Takes a snapshot of all balances and returns its id.
+        ///
+        /// Only the owner can take snapshots.
+        pub(external) fn snapshot(&mut self) -> Result<u32, GovernanceError> {
+            if env.caller() != *self.owner {
+                return Err(GovernanceError::NotOwner)
+            }
+            let id = *self.current_snapshot_id + 1;
+            self.current_snapshot_id.set(id);
+            deposit_event(Event::Snapshot {
+                id: id
+            });
+            Ok(id)
+        }
+
+        /// Returns the balance `account` had when snapshot `id` was taken.
+        ///
+        /// Returns `None` if no snapshot with the given id has been taken.
+        pub(external) fn balance_of_at(&self, account: AccountId, id: u32) -> Option<Balance> {
+            let balance = self.value_at(account, id);
+            env.println(&format!(
+                "GovernanceToken::balance_of_at(account = {:?}, id = {:?}) = {:?}",
+                account, id, balance
+            ));
+            balance
+        }
+    }
+
+    impl GovernanceToken {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            *self.allowances.get(&(*owner, *spender)).unwrap_or(&0)
+        }
+
+        /// Returns the nonce of the AccountId or 0 if it has not signed anything yet.
+        fn nonce_of_or_zero(&self, owner: &AccountId) -> u64 {
+            *self.nonces.get(owner).unwrap_or(&0)
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner`.
+        fn approve_impl(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            self.allowances.insert((owner, spender), value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        ///
+        /// The balances are recorded for the latest snapshot before they change,
+        /// and the votes follow the tokens to the delegate of the recipient.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), GovernanceError> {
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(GovernanceError::InsufficientBalance)
+            }
+            let balance_to = self.balance_of_or_zero(&to);
+            self.update_snapshot(from, balance_from);
+            self.update_snapshot(to, balance_to);
+            self.balances.insert(from, balance_from - value);
+            self.balances.insert(to, self.balance_of_or_zero(&to) + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            let from_delegate = self.delegates.get(&from).cloned();
+            let to_delegate = self.delegates.get(&to).cloned();
+            self.move_voting_power(from_delegate, to_delegate, value);
+            Ok(())
+        }
+
+        /// Returns the voting power of `account` at the end of `block`.
+        fn votes_at(&self, account: &AccountId, block: BlockNumber) -> Balance {
+            let num_checkpoints = *self.num_checkpoints.get(account).unwrap_or(&0);
+            // Binary search for the last checkpoint recorded at or before `block`.
+            let (mut low, mut high) = (0, num_checkpoints);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let (checkpoint_block, _) = self.checkpoint(account, mid);
+                if checkpoint_block > block {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+            if low == 0 {
+                return 0
+            }
+            let (_, votes) = self.checkpoint(account, low - 1);
+            votes
+        }
+
+        /// Returns the checkpoint of `delegate` at `index`.
+        fn checkpoint(&self, delegate: &AccountId, index: u32) -> (BlockNumber, Balance) {
+            *self.checkpoints
+                .get(&(*delegate, index))
+                .expect("checkpoints are stored for every index below num_checkpoints")
+        }
+
+        /// Moves `value` votes from the delegate `from` to the delegate `to`.
+        fn move_voting_power(&mut self, from: Option<AccountId>, to: Option<AccountId>, value: Balance) {
+            if from == to || value == 0 {
+                return
+            }
+            if let Some(from) = from {
+                let votes = self.votes_at(&from, BlockNumber::max_value());
+                self.write_checkpoint(from, votes, votes - value);
+            }
+            if let Some(to) = to {
+                let votes = self.votes_at(&to, BlockNumber::max_value());
+                self.write_checkpoint(to, votes, votes + value);
+            }
+        }
+
+        /// Records that `delegate` has `new_votes` votes as of the current block.
+        fn write_checkpoint(&mut self, delegate: AccountId, previous_votes: Balance, new_votes: Balance) {
+            let block = env::block_number();
+            let num_checkpoints = *self.num_checkpoints.get(&delegate).unwrap_or(&0);
+            let last = num_checkpoints.checked_sub(1).map(|index| self.checkpoint(&delegate, index));
+            match last {
+                // Several changes within one block only keep the final value.
+                Some((last_block, _)) if last_block == block => {
+                    self.checkpoints.insert((delegate, num_checkpoints - 1), (block, new_votes));
+                }
+                _ => {
+                    self.checkpoints.insert((delegate, num_checkpoints), (block, new_votes));
+                    self.num_checkpoints.insert(delegate, num_checkpoints + 1);
+                }
+            }
+            deposit_event(Event::DelegateVotesChanged {
+                delegate: delegate,
+                previous_votes: previous_votes,
+                new_votes: new_votes
+            });
+        }
+
+        /// Returns the number of balances recorded for `account`.
+        fn snapshot_count(&self, account: &AccountId) -> u32 {
+            *self.snapshot_counts.get(account).unwrap_or(&0)
+        }
+
+        /// Returns the balance recorded for `account` at `index`.
+        fn snapshot_entry(&self, account: &AccountId, index: u32) -> (u32, Balance) {
+            *self.snapshots
+                .get(&(*account, index))
+                .expect("snapshots are stored for every index below the snapshot count")
+        }
+
+        /// Records `current_balance` for `account` unless a balance has already
+        /// been recorded since the most recent snapshot was taken.
+        ///
+        /// This is called right before the balance changes, so the recorded
+        /// balance is the one the snapshot saw.
+        fn update_snapshot(&mut self, account: AccountId, current_balance: Balance) {
+            let id = *self.current_snapshot_id;
+            if id == 0 {
+                return
+            }
+            let count = self.snapshot_count(&account);
+            let last_id = match count {
+                0 => 0,
+                _ => self.snapshot_entry(&account, count - 1).0,
+            };
+            if last_id < id {
+                self.snapshots.insert((account, count), (id, current_balance));
+                self.snapshot_counts.insert(account, count + 1);
+            }
+        }
+
+        /// Returns the balance `account` had at snapshot `id`.
+        fn value_at(&self, account: AccountId, id: u32) -> Option<Balance> {
+            if id == 0 || id > *self.current_snapshot_id {
+                return None
+            }
+            // The first balance recorded at or after snapshot `id` is the balance
+            // the snapshot saw. Without one, the balance has not changed since.
+            let count = self.snapshot_count(&account);
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if self.snapshot_entry(&account, mid).0 < id {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            if low == count {
+                return Some(self.balance_of_or_zero(&account))
+            }
+            Some(self.snapshot_entry(&account, low).1)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns the sr25519 key pair derived from `seed`.
+    fn keypair(seed: u8) -> schnorrkel::Keypair {
+        schnorrkel::MiniSecretKey::from_bytes(&[seed; 32])
+            .unwrap()
+            .expand_to_keypair(schnorrkel::ExpansionMode::Uniform)
+    }
+
+    /// Returns the AccountId controlled by `keypair`.
+    fn account_of(keypair: &schnorrkel::Keypair) -> AccountId {
+        AccountId::try_from(keypair.public.to_bytes()).unwrap()
+    }
+
+    /// Signs a permit with `keypair`.
+    fn sign_permit(
+        keypair: &schnorrkel::Keypair,
+        spender: AccountId,
+        value: Balance,
+        nonce: u64,
+        deadline: BlockNumber,
+    ) -> [u8; 64] {
+        let message = (account_of(keypair), spender, value, nonce, deadline).encode();
+        keypair.sign_simple(SIGNING_CONTEXT, &message).to_bytes()
+    }
+
+    #[test]
+    fn governance_lifecycle_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(1);
+
+        let mut token = GovernanceToken::deploy_mock(1000);
+        // Alice activates her votes
+        assert_eq!(token.delegate(alice), Ok(()));
+        assert_eq!(token.get_votes(alice), 1000);
+        // Bob lets Charlie vote for him before he holds any tokens
+        env::test::set_caller(bob);
+        assert_eq!(token.delegate(charlie), Ok(()));
+        env::test::set_caller(alice);
+        env::test::set_block_number(2);
+        assert_eq!(token.snapshot(), Ok(1));
+        env::test::set_block_number(3);
+        assert_eq!(token.transfer(bob, 300), Ok(()));
+        env::test::set_block_number(4);
+        assert_eq!(token.snapshot(), Ok(2));
+        // Bob takes his votes back
+        env::test::set_caller(bob);
+        assert_eq!(token.delegate(bob), Ok(()));
+        env::test::set_block_number(5);
+        assert_eq!(token.transfer(alice, 100), Ok(()));
+
+        // A proposal created at block 2 counts the votes from before the transfer
+        assert_eq!(token.get_past_votes(alice, 2), 1000);
+        assert_eq!(token.get_past_votes(charlie, 2), 0);
+        // The transfer moved votes from Alice to Bob's delegate
+        assert_eq!(token.get_past_votes(alice, 3), 700);
+        assert_eq!(token.get_past_votes(charlie, 3), 300);
+        assert_eq!(token.get_past_votes(bob, 3), 0);
+        assert_eq!(token.get_past_votes(charlie, 4), 0);
+        assert_eq!(token.get_past_votes(bob, 4), 300);
+        assert_eq!(token.get_votes(alice), 800);
+        assert_eq!(token.get_votes(bob), 200);
+        // Snapshots keep the balances independently of delegation
+        assert_eq!(token.balance_of_at(alice, 1), Some(1000));
+        assert_eq!(token.balance_of_at(bob, 1), Some(0));
+        assert_eq!(token.balance_of_at(alice, 2), Some(700));
+        assert_eq!(token.balance_of_at(bob, 2), Some(300));
+        assert_eq!(token.balance_of_at(bob, 3), None);
+        assert_eq!(token.balance_of(bob), 200);
+    }
+
+    #[test]
+    fn only_the_owner_can_take_snapshots() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = GovernanceToken::deploy_mock(1000);
+        env::test::set_caller(bob);
+        assert_eq!(token.snapshot(), Err(GovernanceError::NotOwner));
+        assert_eq!(token.balance_of_at(alice, 1), None);
+    }
+
+    #[test]
+    fn permits_move_votes_with_the_tokens() {
+        let owner = keypair(0x10);
+        let alice = account_of(&owner);
+        let dao = AccountId::try_from([0xD0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(10);
+
+        let mut token = GovernanceToken::deploy_mock(1000);
+        assert_eq!(token.delegate(alice), Ok(()));
+        // Alice lets the DAO pull her tokens without sending a transaction
+        let signature = sign_permit(&owner, dao, 400, 0, 20);
+        env::test::set_caller(dao);
+        assert_eq!(token.permit(alice, dao, 400, 20, signature), Ok(()));
+        assert_eq!(token.permit(alice, dao, 400, 20, signature), Err(GovernanceError::InvalidSignature));
+        assert_eq!(token.nonce_of(alice), 1);
+        assert_eq!(token.transfer_from(alice, dao, 400), Ok(()));
+        assert_eq!(token.allowance(alice, dao), 0);
+        // The DAO never delegated, so the votes are gone
+        assert_eq!(token.get_votes(alice), 600);
+        assert_eq!(token.get_votes(dao), 0);
+        env::test::set_block_number(21);
+        let signature = sign_permit(&owner, dao, 100, 1, 20);
+        assert_eq!(token.permit(alice, dao, 100, 20, signature), Err(GovernanceError::Expired));
+    }
+}
@@ -0,0 +1,521 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The precision of the exchange rate and the interest rate, which are
+/// fixed-point numbers with 18 decimals.
+const RATE_PRECISION: Balance = 1_000_000_000_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the interest-bearing token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Deposit {
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    },
+    Redeem {
+        account: AccountId,
+        amount: Balance,
+        shares: Balance,
+    },
+    InterestAccrued {
+        exchange_rate: Balance,
+    },
+    InterestRateChanged {
+        interest_rate: Balance,
+    },
+}
+
+/// Errors which can occur when calling the interest-bearing token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum InterestBearingError {
+    /// The account does not hold enough shares.
+    InsufficientBalance,
+    /// Only the admin can change the interest rate.
+    NotAdmin,
+    /// The deposit is worth less than a single share.
+    ZeroShares,
+    /// The shares are worth less than a single underlying token.
+    ZeroAmount,
+    /// The underlying token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits an interest-bearing token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns `value * rate`, where `rate` has `RATE_PRECISION`, rounded down.
+///
+/// `value` is split to avoid overflowing on large amounts.
+fn mul_rate(value: Balance, rate: Balance) -> Balance {
+    value / RATE_PRECISION * rate + value % RATE_PRECISION * rate / RATE_PRECISION
+}
+
+/// Returns `value / rate`, where `rate` has `RATE_PRECISION`, rounded down.
+///
+/// `value` is split to avoid overflowing on large amounts.
+fn div_rate(value: Balance, rate: Balance) -> Balance {
+    value / rate * RATE_PRECISION + value % rate * RATE_PRECISION / rate
+}
+
+contract! {
+    /// A token which represents shares of deposited ERC20 tokens that earn interest.
+    ///
+    /// Every share is worth `exchange_rate` underlying tokens. The exchange rate
+    /// starts at `initial_exchange_rate` and grows by `interest_rate` every
+    /// block, so the same shares redeem for more underlying tokens over time.
+    /// The interest itself has to be paid into the contract, for example by
+    /// borrowers of the deposits.
+    struct InterestBearingToken {
+        /// The account which may change the interest rate.
+        admin: storage::Value<AccountId>,
+        /// The ERC20 token which is deposited.
+        underlying: storage::Value<AccountId>,
+        /// The total number of shares.
+        total_supply: storage::Value<Balance>,
+        /// The shares of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// The underlying tokens a share was worth at `accrual_block`.
+        exchange_rate: storage::Value<Balance>,
+        /// The growth of the exchange rate per block.
+        interest_rate: storage::Value<Balance>,
+        /// The block up to which interest has been accrued.
+        accrual_block: storage::Value<BlockNumber>,
+    }
+
+    impl Deploy for InterestBearingToken {
+        fn deploy(&mut self, underlying: AccountId, initial_exchange_rate: Balance, interest_rate: Balance) {
+            assert!(initial_exchange_rate > 0, "the exchange rate must not be zero");
+            self.admin.set(env.caller());
+            self.underlying.set(underlying);
+            self.total_supply.set(0);
+            self.exchange_rate.set(initial_exchange_rate);
+            self.interest_rate.set(interest_rate);
+            self.accrual_block.set(env::block_number());
+        }
+    }
+
+    impl InterestBearingToken {
+        /// Returns the total number of shares.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("InterestBearingToken::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the shares of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("InterestBearingToken::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the underlying tokens the shares of `owner` are worth right now.
+        pub(external) fn underlying_of(&self, owner: AccountId) -> Balance {
+            let amount = mul_rate(self.balance_of_or_zero(&owner), self.current_exchange_rate());
+            env.println(&format!("InterestBearingToken::underlying_of(owner = {:?}) = {:?}", owner, amount));
+            amount
+        }
+
+        /// Returns the underlying tokens a share is worth right now, including
+        /// interest which has not been accrued yet.
+        pub(external) fn exchange_rate(&self) -> Balance {
+            let exchange_rate = self.current_exchange_rate();
+            env.println(&format!("InterestBearingToken::exchange_rate = {:?}", exchange_rate));
+            exchange_rate
+        }
+
+        /// Returns the growth of the exchange rate per block.
+        pub(external) fn interest_rate(&self) -> Balance {
+            let interest_rate = *self.interest_rate;
+            env.println(&format!("InterestBearingToken::interest_rate = {:?}", interest_rate));
+            interest_rate
+        }
+
+        /// Brings the stored exchange rate up to date with the current block.
+        ///
+        /// Anyone can call this. Every deposit and redemption accrues first, so
+        /// it is only needed to make the accrued rate visible on-chain.
+        pub(external) fn accrue_interest(&mut self) -> Balance {
+            self.accrue();
+            *self.exchange_rate
+        }
+
+        /// Changes the growth of the exchange rate per block.
+        ///
+        /// Interest up to the current block is accrued at the old rate first.
+        /// Only the admin can change the interest rate.
+        pub(external) fn set_interest_rate(&mut self, interest_rate: Balance) -> Result<(), InterestBearingError> {
+            if env.caller() != *self.admin {
+                return Err(InterestBearingError::NotAdmin)
+            }
+            self.accrue();
+            self.interest_rate.set(interest_rate);
+            deposit_event(Event::InterestRateChanged {
+                interest_rate: interest_rate
+            });
+            Ok(())
+        }
+
+        /// Pulls `amount` underlying tokens of the caller into the contract and
+        /// mints shares at the current exchange rate, rounded down.
+        ///
+        /// The caller has to approve the contract to spend `amount` first.
+        pub(external) fn deposit(&mut self, amount: Balance) -> Result<Balance, InterestBearingError> {
+            self.accrue();
+            let shares = div_rate(amount, *self.exchange_rate);
+            if shares == 0 {
+                return Err(InterestBearingError::ZeroShares)
+            }
+            let account = env.caller();
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.underlying, TRANSFER_FROM_SELECTOR, &(account, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(InterestBearingError::TransferFailed),
+            }
+            let balance = self.balance_of_or_zero(&account);
+            self.balances.insert(account, balance + shares);
+            self.total_supply += shares;
+            deposit_event(Event::Deposit {
+                account: account,
+                amount: amount,
+                shares: shares
+            });
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(account),
+                value: shares
+            });
+            Ok(shares)
+        }
+
+        /// Burns `shares` of the caller and pays out the underlying tokens they
+        /// are worth at the current exchange rate, rounded down.
+        ///
+        /// If the contract cannot pay, the whole call is reverted.
+        pub(external) fn redeem(&mut self, shares: Balance) -> Result<Balance, InterestBearingError> {
+            self.accrue();
+            let account = env.caller();
+            let balance = self.balance_of_or_zero(&account);
+            if balance < shares {
+                return Err(InterestBearingError::InsufficientBalance)
+            }
+            let amount = mul_rate(shares, *self.exchange_rate);
+            if amount == 0 {
+                return Err(InterestBearingError::ZeroAmount)
+            }
+            self.balances.insert(account, balance - shares);
+            self.total_supply -= shares;
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.underlying, TRANSFER_SELECTOR, &(account, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the underlying tokens could not be paid out");
+            deposit_event(Event::Redeem {
+                account: account,
+                amount: amount,
+                shares: shares
+            });
+            deposit_event(Event::Transfer {
+                from: Some(account),
+                to: None,
+                value: shares
+            });
+            Ok(amount)
+        }
+    }
+
+    impl InterestBearingToken {
+        /// Returns the shares of the AccountId or 0 if there are none.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the exchange rate including the interest since `accrual_block`.
+        ///
+        /// Interest is simple between two accruals and compounds with every
+        /// accrual. While there are no shares, nobody earns interest and the
+        /// rate stays where it is, so the first depositor after a quiet period
+        /// gets the same rate as the last one.
+        fn current_exchange_rate(&self) -> Balance {
+            let exchange_rate = *self.exchange_rate;
+            if *self.total_supply == 0 {
+                return exchange_rate
+            }
+            let blocks = Balance::from(env::block_number() - *self.accrual_block);
+            let growth = self.interest_rate.saturating_mul(blocks);
+            exchange_rate.saturating_add(mul_rate(exchange_rate, growth))
+        }
+
+        /// Stores the current exchange rate and records the block it belongs to.
+        fn accrue(&mut self) {
+            let exchange_rate = self.current_exchange_rate();
+            self.accrual_block.set(env::block_number());
+            if exchange_rate != *self.exchange_rate {
+                self.exchange_rate.set(exchange_rate);
+                deposit_event(Event::InterestAccrued {
+                    exchange_rate: exchange_rate
+                });
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the token through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    /// An exchange rate of 0.02 underlying tokens per share.
+    const TWO_CENTS: Balance = RATE_PRECISION / 50;
+
+    /// An interest rate of 0.1% per block.
+    const TENTH_OF_A_PERCENT: Balance = RATE_PRECISION / 1000;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens of a mock token from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    #[test]
+    fn deposit_mints_at_the_exchange_rate() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+
+        let mut shares = InterestBearingToken::deploy_mock(token, TWO_CENTS, TENTH_OF_A_PERCENT);
+        assert_eq!(shares.deposit(100), Ok(5000));
+        assert_eq!(shares.balance_of(alice), 5000);
+        assert_eq!(shares.underlying_of(alice), 100);
+        assert_eq!(balances.borrow()[&env::address()], 100);
+        // Without interest, the shares redeem for what was deposited
+        assert_eq!(shares.redeem(5001), Err(InterestBearingError::InsufficientBalance));
+        assert_eq!(shares.redeem(5000), Ok(100));
+        assert_eq!(balances.borrow()[&alice], 100);
+    }
+
+    #[test]
+    fn interest_raises_the_exchange_rate() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+        // The interest has been paid into the contract by borrowers
+        balances.borrow_mut().insert(env::address(), 1);
+
+        let mut shares = InterestBearingToken::deploy_mock(token, TWO_CENTS, TENTH_OF_A_PERCENT);
+        assert_eq!(shares.deposit(100), Ok(5000));
+        // 1% of interest after 10 blocks
+        env::test::set_block_number(10);
+        assert_eq!(shares.exchange_rate(), TWO_CENTS / 100 * 101);
+        assert_eq!(shares.underlying_of(alice), 101);
+        assert_eq!(shares.accrue_interest(), TWO_CENTS / 100 * 101);
+        assert_eq!(shares.redeem(5000), Ok(101));
+        assert_eq!(balances.borrow()[&alice], 101);
+    }
+
+    #[test]
+    fn changing_the_interest_rate_accrues_first() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+
+        let mut shares = InterestBearingToken::deploy_mock(token, RATE_PRECISION, TENTH_OF_A_PERCENT);
+        assert_eq!(shares.deposit(100), Ok(100));
+        env::test::set_block_number(10);
+        assert_eq!(shares.set_interest_rate(0), Ok(()));
+        assert_eq!(shares.interest_rate(), 0);
+        // The first ten blocks earned interest, the next ten do not
+        env::test::set_block_number(20);
+        assert_eq!(shares.exchange_rate(), RATE_PRECISION / 100 * 101);
+        env::test::set_caller(bob);
+        assert_eq!(shares.set_interest_rate(1), Err(InterestBearingError::NotAdmin));
+    }
+
+    #[test]
+    fn conversions_round_in_favor_of_the_contract() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+
+        // A share is worth a third of a token, which is not exact in 18 decimals
+        let mut shares = InterestBearingToken::deploy_mock(token, RATE_PRECISION / 3, 0);
+        assert_eq!(shares.exchange_rate(), 333_333_333_333_333_333);
+        assert_eq!(shares.deposit(1), Ok(3));
+        // Three shares are worth slightly less than the token deposited
+        assert_eq!(shares.underlying_of(alice), 0);
+        assert_eq!(shares.redeem(3), Err(InterestBearingError::ZeroAmount));
+        assert_eq!(shares.deposit(2), Ok(6));
+        assert_eq!(shares.redeem(9), Ok(2));
+    }
+
+    #[test]
+    fn large_amounts_do_not_overflow() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        let amount: Balance = 1_000_000_000_000_000_000_000_000_000_000;
+        balances.borrow_mut().insert(alice, amount);
+
+        let mut shares = InterestBearingToken::deploy_mock(token, TWO_CENTS, 0);
+        assert_eq!(shares.deposit(amount), Ok(amount * 50));
+        assert_eq!(shares.underlying_of(alice), amount);
+    }
+
+    #[test]
+    fn first_depositor_edge_cases() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+        balances.borrow_mut().insert(bob, 100);
+
+        // A share starts out worth two tokens
+        let mut shares = InterestBearingToken::deploy_mock(token, 2 * RATE_PRECISION, TENTH_OF_A_PERCENT);
+        // A deposit worth less than a share fails and takes nothing
+        assert_eq!(shares.deposit(1), Err(InterestBearingError::ZeroShares));
+        assert_eq!(balances.borrow()[&alice], 100);
+        // Nobody earns interest while there are no shares
+        env::test::set_block_number(1000);
+        assert_eq!(shares.accrue_interest(), 2 * RATE_PRECISION);
+        assert_eq!(shares.deposit(10), Ok(5));
+        // Tokens sent to the contract directly do not change the exchange rate
+        balances.borrow_mut().insert(env::address(), 1000);
+        env::test::set_caller(bob);
+        assert_eq!(shares.exchange_rate(), 2 * RATE_PRECISION);
+        assert_eq!(shares.deposit(10), Ok(5));
+    }
+}
@@ -0,0 +1,465 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of the ERC20 token.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The number of claims tracked by a single word of the claimed bitmap.
+const BITS_PER_WORD: u32 = 128;
+
+/// A node of the Merkle tree, which is a BLAKE2b-256 hash.
+type Node = [u8; 32];
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the airdrop.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Claimed {
+        index: u32,
+        account: AccountId,
+        amount: Balance,
+    },
+    Swept {
+        amount: Balance,
+    },
+}
+
+/// Errors which can occur when calling the airdrop.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum AirdropError {
+    /// The tokens of the leaf have already been claimed.
+    AlreadyClaimed,
+    /// The proof does not lead from the leaf to the Merkle root.
+    InvalidProof,
+    /// The deadline for claims has passed.
+    ClaimPeriodEnded,
+    /// Tokens can only be swept after the deadline.
+    ClaimPeriodNotEnded,
+    /// Only the admin can sweep the unclaimed tokens.
+    NotAdmin,
+}
+
+/// Deposits an airdrop event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the BLAKE2b-256 hash of `data`.
+fn hash_of(data: &[u8]) -> Node {
+    let mut node = [0u8; 32];
+    node.copy_from_slice(blake2_rfc::blake2b::blake2b(32, &[], data).as_bytes());
+    node
+}
+
+/// Returns the leaf which grants `amount` tokens to `account`.
+///
+/// The `index` is the position of the leaf in the tree, and identifies its
+/// bit in the claimed bitmap.
+fn leaf_of(index: u32, account: AccountId, amount: Balance) -> Node {
+    hash_of(&(index, account, amount).encode())
+}
+
+/// Returns the parent of the nodes `a` and `b`.
+///
+/// The nodes are sorted before they are hashed, so a proof does not need to
+/// say on which side each of its nodes is.
+fn parent_of(a: &Node, b: &Node) -> Node {
+    if a <= b {
+        hash_of(&(*a, *b).encode())
+    } else {
+        hash_of(&(*b, *a).encode())
+    }
+}
+
+/// Returns whether `proof` leads from `leaf` to `root`.
+fn verify(proof: &[Node], root: &Node, leaf: Node) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| parent_of(&node, sibling));
+    computed == *root
+}
+
+contract! {
+    /// Hands out ERC20 tokens to a list of accounts fixed up front.
+    ///
+    /// Storing every account and amount would be expensive, so the airdrop
+    /// only stores the root of a Merkle tree of them. Every account claims
+    /// its own tokens with a proof that its leaf is part of the tree. Tokens
+    /// nobody claimed before the deadline can be swept by the admin.
+    struct MerkleAirdrop {
+        /// The account which sweeps the unclaimed tokens.
+        admin: storage::Value<AccountId>,
+        /// The ERC20 token handed out.
+        token: storage::Value<AccountId>,
+        /// The root of the Merkle tree of (index, account, amount) leaves.
+        merkle_root: storage::Value<Node>,
+        /// The last block in which tokens can be claimed.
+        deadline: storage::Value<BlockNumber>,
+        /// One bit for every leaf, which is set once it has been claimed.
+        claimed: storage::HashMap<u32, u128>,
+    }
+
+    impl Deploy for MerkleAirdrop {
+        fn deploy(&mut self, token: AccountId, merkle_root: Node, deadline: BlockNumber) {
+            self.admin.set(env.caller());
+            self.token.set(token);
+            self.merkle_root.set(merkle_root);
+            self.deadline.set(deadline);
+        }
+    }
+
+    impl MerkleAirdrop {
+        /// Returns the root of the Merkle tree.
+        pub(external) fn merkle_root(&self) -> Node {
+            let merkle_root = *self.merkle_root;
+            env.println(&format!("MerkleAirdrop::merkle_root = {:?}", merkle_root));
+            merkle_root
+        }
+
+        /// Returns the last block in which tokens can be claimed.
+        pub(external) fn deadline(&self) -> BlockNumber {
+            let deadline = *self.deadline;
+            env.println(&format!("MerkleAirdrop::deadline = {:?}", deadline));
+            deadline
+        }
+
+        /// Returns whether the leaf at `index` has been claimed.
+        pub(external) fn is_claimed(&self, index: u32) -> bool {
+            let is_claimed = self.is_claimed_or_false(index);
+            env.println(&format!("MerkleAirdrop::is_claimed(index = {:?}) = {:?}", index, is_claimed));
+            is_claimed
+        }
+
+        /// Transfers `amount` tokens to the caller, if `proof` shows that the
+        /// leaf at `index` grants them to the caller.
+        ///
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn claim(&mut self, index: u32, amount: Balance, proof: Vec<Node>) -> Result<(), AirdropError> {
+            if env.block_number() > *self.deadline {
+                return Err(AirdropError::ClaimPeriodEnded)
+            }
+            if self.is_claimed_or_false(index) {
+                return Err(AirdropError::AlreadyClaimed)
+            }
+            let account = env.caller();
+            if !verify(&proof, &*self.merkle_root, leaf_of(index, account, amount)) {
+                return Err(AirdropError::InvalidProof)
+            }
+            self.set_claimed(index);
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(account, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the airdrop could not be transferred");
+            deposit_event(Event::Claimed {
+                index: index,
+                account: account,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Transfers the tokens nobody claimed to the admin once the deadline
+        /// has passed.
+        pub(external) fn sweep(&mut self) -> Result<Balance, AirdropError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(AirdropError::NotAdmin)
+            }
+            if env.block_number() <= *self.deadline {
+                return Err(AirdropError::ClaimPeriodNotEnded)
+            }
+            let amount: Balance = call_contract(*self.token, BALANCE_OF_SELECTOR, &env.address().encode())
+                .expect("the token reports the balance of the airdrop");
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(admin, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the unclaimed tokens could not be swept");
+            deposit_event(Event::Swept {
+                amount: amount
+            });
+            Ok(amount)
+        }
+    }
+
+    impl MerkleAirdrop {
+        /// Returns whether the bit of `index` is set in the claimed bitmap.
+        fn is_claimed_or_false(&self, index: u32) -> bool {
+            let word = *self.claimed.get(&(index / BITS_PER_WORD)).unwrap_or(&0);
+            word & (1 << (index % BITS_PER_WORD)) != 0
+        }
+
+        /// Sets the bit of `index` in the claimed bitmap.
+        fn set_claimed(&mut self, index: u32) {
+            let word = *self.claimed.get(&(index / BITS_PER_WORD)).unwrap_or(&0);
+            self.claimed.insert(index / BITS_PER_WORD, word | (1 << (index % BITS_PER_WORD)));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the airdrop through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..]).unwrap();
+                    Some(balances.get(&owner).cloned().unwrap_or(0).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    let balance = balances.get(&env::address()).cloned().unwrap_or(0);
+                    if balance < value {
+                        return Some(Err::<(), u8>(0).encode())
+                    }
+                    balances.insert(env::address(), balance - value);
+                    *balances.entry(to).or_insert(0) += value;
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Returns the layers of the Merkle tree of `leaves`, from the leaves up
+    /// to the root.
+    ///
+    /// A node without a sibling is moved up to the next layer unchanged.
+    fn build_tree(leaves: Vec<Node>) -> Vec<Vec<Node>> {
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers.last().unwrap();
+            let parents = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => parent_of(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(parents);
+        }
+        layers
+    }
+
+    /// Returns the proof for the leaf at `index` in the tree with `layers`.
+    fn proof_of(layers: &[Vec<Node>], index: usize) -> Vec<Node> {
+        let mut proof = Vec::new();
+        let mut index = index;
+        for layer in &layers[..layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+
+    /// Returns `count` accounts, where account `i` is granted `(i + 1) * 100` tokens.
+    fn grants(count: u32) -> Vec<(AccountId, Balance)> {
+        (0..count)
+            .map(|i| {
+                let mut bytes = [0u8; 32];
+                bytes[..4].copy_from_slice(&i.to_le_bytes());
+                (AccountId::try_from(bytes).unwrap(), Balance::from(i + 1) * 100)
+            })
+            .collect()
+    }
+
+    /// Deploys an airdrop of `grants` at block 0 which runs until block 100,
+    /// funds it with all granted tokens, and returns the tree of the airdrop.
+    fn deploy_mock(grants: &[(AccountId, Balance)]) -> (MerkleAirdrop, Balances, Vec<Vec<Node>>) {
+        let token = AccountId::try_from([0xFF; 32]).unwrap();
+        let admin = AccountId::try_from([0xFE; 32]).unwrap();
+        let balances = register_token(token);
+        let leaves = grants
+            .iter()
+            .enumerate()
+            .map(|(index, (account, amount))| leaf_of(index as u32, *account, *amount))
+            .collect();
+        let layers = build_tree(leaves);
+        let root = layers.last().unwrap()[0];
+        balances.borrow_mut().insert(env::address(), grants.iter().map(|(_, amount)| amount).sum());
+        env::test::set_caller(admin);
+        env::test::set_block_number(0);
+        (MerkleAirdrop::deploy_mock(token, root, 100), balances, layers)
+    }
+
+    /// Claims the grant at `index` with `proof`, as the account of the grant.
+    fn claim(
+        airdrop: &mut MerkleAirdrop,
+        grants: &[(AccountId, Balance)],
+        index: u32,
+        proof: Vec<Node>,
+    ) -> Result<(), AirdropError> {
+        let (account, amount) = grants[index as usize];
+        env::test::set_caller(account);
+        airdrop.claim(index, amount, proof)
+    }
+
+    #[test]
+    fn every_grant_can_be_claimed() {
+        let grants = grants(5);
+        let (mut airdrop, balances, layers) = deploy_mock(&grants);
+        for index in 0..5 {
+            assert_eq!(airdrop.is_claimed(index), false);
+            let proof = proof_of(&layers, index as usize);
+            assert_eq!(claim(&mut airdrop, &grants, index, proof), Ok(()));
+            assert_eq!(airdrop.is_claimed(index), true);
+        }
+        for (account, amount) in grants.iter() {
+            assert_eq!(balances.borrow()[account], *amount);
+        }
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+
+    #[test]
+    fn double_claims_fail() {
+        let grants = grants(4);
+        let (mut airdrop, balances, layers) = deploy_mock(&grants);
+        assert_eq!(claim(&mut airdrop, &grants, 2, proof_of(&layers, 2)), Ok(()));
+        assert_eq!(claim(&mut airdrop, &grants, 2, proof_of(&layers, 2)), Err(AirdropError::AlreadyClaimed));
+        assert_eq!(balances.borrow()[&grants[2].0], 300);
+        // Other leaves are not affected
+        assert_eq!(airdrop.is_claimed(1), false);
+        assert_eq!(airdrop.is_claimed(3), false);
+    }
+
+    #[test]
+    fn claimed_bitmap_spans_several_words() {
+        let grants = grants(300);
+        let (mut airdrop, _, layers) = deploy_mock(&grants);
+        for index in [0, 127, 128, 255, 256, 299].iter() {
+            let proof = proof_of(&layers, *index as usize);
+            assert_eq!(claim(&mut airdrop, &grants, *index, proof), Ok(()));
+        }
+        for index in 0..300 {
+            let expected = [0, 127, 128, 255, 256, 299].contains(&index);
+            assert_eq!(airdrop.is_claimed(index), expected, "the claimed bit of {}", index);
+        }
+    }
+
+    #[test]
+    fn invalid_proofs_fail() {
+        let grants = grants(5);
+        let (mut airdrop, balances, layers) = deploy_mock(&grants);
+        let (bob, amount) = grants[1];
+        env::test::set_caller(bob);
+        // The wrong amount
+        assert_eq!(airdrop.claim(1, amount + 1, proof_of(&layers, 1)), Err(AirdropError::InvalidProof));
+        // The wrong index
+        assert_eq!(airdrop.claim(0, amount, proof_of(&layers, 1)), Err(AirdropError::InvalidProof));
+        // The proof of another leaf
+        assert_eq!(airdrop.claim(1, amount, proof_of(&layers, 0)), Err(AirdropError::InvalidProof));
+        // A proof missing its last node
+        let mut truncated = proof_of(&layers, 1);
+        truncated.pop();
+        assert_eq!(airdrop.claim(1, amount, truncated), Err(AirdropError::InvalidProof));
+        // No proof at all
+        assert_eq!(airdrop.claim(1, amount, vec![]), Err(AirdropError::InvalidProof));
+        // Somebody else cannot claim Bob's tokens with his proof
+        let (charlie, _) = grants[2];
+        env::test::set_caller(charlie);
+        assert_eq!(airdrop.claim(1, amount, proof_of(&layers, 1)), Err(AirdropError::InvalidProof));
+        assert_eq!(airdrop.is_claimed(1), false);
+        assert_eq!(balances.borrow().get(&bob), None);
+        assert_eq!(claim(&mut airdrop, &grants, 1, proof_of(&layers, 1)), Ok(()));
+    }
+
+    #[test]
+    fn unclaimed_tokens_are_swept_after_the_deadline() {
+        let admin = AccountId::try_from([0xFE; 32]).unwrap();
+        let grants = grants(3);
+        let (mut airdrop, balances, layers) = deploy_mock(&grants);
+        env::test::set_block_number(100);
+        assert_eq!(claim(&mut airdrop, &grants, 0, proof_of(&layers, 0)), Ok(()));
+        env::test::set_caller(admin);
+        assert_eq!(airdrop.sweep(), Err(AirdropError::ClaimPeriodNotEnded));
+        env::test::set_block_number(101);
+        assert_eq!(claim(&mut airdrop, &grants, 1, proof_of(&layers, 1)), Err(AirdropError::ClaimPeriodEnded));
+        env::test::set_caller(grants[1].0);
+        assert_eq!(airdrop.sweep(), Err(AirdropError::NotAdmin));
+        env::test::set_caller(admin);
+        assert_eq!(airdrop.sweep(), Ok(500));
+        assert_eq!(balances.borrow()[&admin], 500);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+}
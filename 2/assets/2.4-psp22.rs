@@ -0,0 +1,304 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        string::String,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the PSP22 token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+    },
+}
+
+/// The errors defined by the PSP22 standard.
+///
+/// The order of the variants is part of the standard, since callers decode
+/// errors by their index.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+enum PSP22Error {
+    /// An error which is specific to the implementation of the token.
+    Custom(String),
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+    /// The recipient is the zero address.
+    ZeroRecipientAddress,
+    /// The sender is the zero address.
+    ZeroSenderAddress,
+    /// The recipient is a contract which rejected the tokens.
+    SafeTransferCheckFailed(String),
+}
+
+/// Deposits a PSP22 token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// An ERC20 token which follows the PSP22 standard of the ink! ecosystem.
+    ///
+    /// The messages have the names, arguments and errors PSP22 requires, so
+    /// wallets and contracts written against PSP22 can use the token. Unlike
+    /// our main ERC20 token, it has none of the extensions beyond the standard.
+    struct Psp22 {
+        /// The total supply.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+    }
+
+    impl Deploy for Psp22 {
+        fn deploy(&mut self, init_value: Balance) {
+            self.total_supply.set(init_value);
+            self.balances.insert(env.caller(), init_value);
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(env.caller()),
+                value: init_value
+            });
+        }
+    }
+
+    impl Psp22 {
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("Psp22::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Psp22::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the amount of tokens that an owner allowed to a spender.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            env.println(&format!(
+                "Psp22::allowance(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, allowance
+            ));
+            allowance
+        }
+
+        /// Transfers `value` tokens from the caller to `to`.
+        ///
+        /// PSP22 passes `data` along for recipients which want to know what the
+        /// tokens are for. This token does not use it.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance, data: Vec<u8>) -> Result<(), PSP22Error> {
+            let _ = data;
+            self.transfer_impl(env.caller(), to, value)
+        }
+
+        /// Transfers `value` tokens from `from` to `to` using the allowance
+        /// granted to the caller.
+        pub(external) fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let _ = data;
+            let spender = env.caller();
+            let allowance = self.allowance_or_zero(&from, &spender);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.approve_impl(from, spender, allowance - value);
+            Ok(())
+        }
+
+        /// Approves `spender` to spend `value` tokens of the caller.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), PSP22Error> {
+            self.approve_impl(env.caller(), spender, value);
+            Ok(())
+        }
+
+        /// Increases the allowance granted to `spender` by the caller by `delta_value`.
+        pub(external) fn increase_allowance(&mut self, spender: AccountId, delta_value: Balance) -> Result<(), PSP22Error> {
+            let owner = env.caller();
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            self.approve_impl(owner, spender, allowance.saturating_add(delta_value));
+            Ok(())
+        }
+
+        /// Decreases the allowance granted to `spender` by the caller by `delta_value`.
+        ///
+        /// Unlike our main ERC20 token, PSP22 fails instead of saturating at zero.
+        pub(external) fn decrease_allowance(&mut self, spender: AccountId, delta_value: Balance) -> Result<(), PSP22Error> {
+            let owner = env.caller();
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            if allowance < delta_value {
+                return Err(PSP22Error::InsufficientAllowance)
+            }
+            self.approve_impl(owner, spender, allowance - delta_value);
+            Ok(())
+        }
+    }
+
+    impl Psp22 {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            let balance = self.balances.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            let allowance = self.allowances.get(&(*owner, *spender)).unwrap_or(&0);
+            *allowance
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`.
+        fn approve_impl(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            self.allowances.insert((owner, spender), value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), PSP22Error> {
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(PSP22Error::InsufficientBalance)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// The selectors the PSP22 standard lists for its messages.
+    ///
+    /// Other contracts call a PSP22 token with these selectors, whichever
+    /// implementation of the token they talk to.
+    const PSP22_SELECTORS: [(&str, [u8; 4]); 8] = [
+        ("total_supply", [0x16, 0x2D, 0xF8, 0xC2]),
+        ("balance_of", [0x65, 0x68, 0x38, 0x2F]),
+        ("allowance", [0x4D, 0x47, 0xD9, 0x21]),
+        ("transfer", [0xDB, 0x20, 0xF9, 0xF5]),
+        ("transfer_from", [0x54, 0xB3, 0xC7, 0x6E]),
+        ("approve", [0xB2, 0x0F, 0x1B, 0xBD]),
+        ("increase_allowance", [0x96, 0xD6, 0xB5, 0x7A]),
+        ("decrease_allowance", [0xFE, 0xCB, 0x57, 0xD5]),
+    ];
+
+    /// Returns the selector of the PSP22 message `name`, the first four bytes
+    /// of the BLAKE2b-256 hash of `PSP22::<name>`.
+    fn selector_of(name: &str) -> [u8; 4] {
+        let hash = blake2_rfc::blake2b::blake2b(32, &[], format!("PSP22::{}", name).as_bytes());
+        let mut selector = [0u8; 4];
+        selector.copy_from_slice(&hash.as_bytes()[..4]);
+        selector
+    }
+
+    #[test]
+    fn selectors_follow_the_standard() {
+        for (name, selector) in PSP22_SELECTORS.iter() {
+            assert_eq!(selector_of(name), *selector, "the selector of {}", name);
+        }
+    }
+
+    #[test]
+    fn errors_follow_the_standard() {
+        // Callers decode errors by the index of their variant
+        assert_eq!(PSP22Error::Custom(String::from("x")).encode()[0], 0);
+        assert_eq!(PSP22Error::InsufficientBalance.encode(), vec![1]);
+        assert_eq!(PSP22Error::InsufficientAllowance.encode(), vec![2]);
+        assert_eq!(PSP22Error::ZeroRecipientAddress.encode(), vec![3]);
+        assert_eq!(PSP22Error::ZeroSenderAddress.encode(), vec![4]);
+        assert_eq!(PSP22Error::SafeTransferCheckFailed(String::from("x")).encode()[0], 5);
+    }
+
+    #[test]
+    fn transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp22 = Psp22::deploy_mock(1234);
+        assert_eq!(psp22.total_supply(), 1234);
+        assert_eq!(psp22.transfer(bob, 1235, Vec::new()), Err(PSP22Error::InsufficientBalance));
+        assert_eq!(psp22.transfer(bob, 234, vec![1, 2, 3]), Ok(()));
+        assert_eq!(psp22.balance_of(alice), 1000);
+        assert_eq!(psp22.balance_of(bob), 234);
+    }
+
+    #[test]
+    fn transfer_from_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp22 = Psp22::deploy_mock(1234);
+        assert_eq!(psp22.approve(bob, 20), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(psp22.transfer_from(alice, charlie, 21, Vec::new()), Err(PSP22Error::InsufficientAllowance));
+        assert_eq!(psp22.transfer_from(alice, charlie, 15, Vec::new()), Ok(()));
+        assert_eq!(psp22.allowance(alice, bob), 5);
+        assert_eq!(psp22.balance_of(charlie), 15);
+    }
+
+    #[test]
+    fn allowance_changes_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp22 = Psp22::deploy_mock(1234);
+        assert_eq!(psp22.increase_allowance(bob, 20), Ok(()));
+        assert_eq!(psp22.decrease_allowance(bob, 5), Ok(()));
+        assert_eq!(psp22.allowance(alice, bob), 15);
+        // Decreasing below zero fails instead of saturating
+        assert_eq!(psp22.decrease_allowance(bob, 16), Err(PSP22Error::InsufficientAllowance));
+        assert_eq!(psp22.allowance(alice, bob), 15);
+    }
+}
@@ -0,0 +1,361 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The largest total supply a rebase can reach.
+///
+/// Keeping the supply far below `Balance::max_value()` leaves every token
+/// worth at least 2^64 gons, so balances stay precise across many rebases.
+const MAX_SUPPLY: Balance = u64::max_value() as Balance;
+
+/// Events deposited by the rebasing token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+    },
+    Rebase {
+        epoch: u64,
+        total_supply: Balance,
+    },
+}
+
+/// Errors which can occur when calling the rebasing token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc20Error {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+    /// Only the owner can rebase the token.
+    NotOwner,
+    /// The rebase would shrink the supply to zero or grow it beyond `MAX_SUPPLY`.
+    InvalidSupply,
+}
+
+/// Deposits a rebasing token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// An ERC20 token with an elastic supply.
+    ///
+    /// Internally, every account holds a number of _gons_, and the total number
+    /// of gons never changes. The balances users see are their gons divided by
+    /// `gons_per_token`. A rebase changes the total supply and with it
+    /// `gons_per_token`, which grows or shrinks every balance by the same
+    /// factor without touching a single account.
+    struct RebasingToken {
+        /// The account which may rebase the token.
+        owner: storage::Value<AccountId>,
+        /// The total supply as users see it.
+        total_supply: storage::Value<Balance>,
+        /// The number of gons, which stays the same across rebases.
+        total_gons: storage::Value<Balance>,
+        /// The number of gons a single token is worth.
+        gons_per_token: storage::Value<Balance>,
+        /// The gons of each user.
+        gon_balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        ///
+        /// Allowances are given in tokens, so they do not change with rebases.
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+        /// The number of rebases so far.
+        epoch: storage::Value<u64>,
+    }
+
+    impl Deploy for RebasingToken {
+        fn deploy(&mut self, init_value: Balance) {
+            assert!(init_value > 0, "the initial supply must not be zero");
+            assert!(init_value <= MAX_SUPPLY, "the initial supply must not exceed MAX_SUPPLY");
+            // The largest number of gons which splits evenly into the initial supply.
+            let total_gons = Balance::max_value() - Balance::max_value() % init_value;
+            self.owner.set(env.caller());
+            self.total_supply.set(init_value);
+            self.total_gons.set(total_gons);
+            self.gons_per_token.set(total_gons / init_value);
+            self.gon_balances.insert(env.caller(), total_gons);
+            self.epoch.set(0);
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(env.caller()),
+                value: init_value
+            });
+        }
+    }
+
+    impl RebasingToken {
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("RebasingToken::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId, rounded down.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.gons_of(&owner) / *self.gons_per_token;
+            env.println(&format!("RebasingToken::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the number of gons of the given AccountId.
+        pub(external) fn scaled_balance_of(&self, owner: AccountId) -> Balance {
+            let gons = self.gons_of(&owner);
+            env.println(&format!("RebasingToken::scaled_balance_of(owner = {:?}) = {:?}", owner, gons));
+            gons
+        }
+
+        /// Returns the amount of tokens that an owner allowed to a spender.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            env.println(&format!(
+                "RebasingToken::allowance(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, allowance
+            ));
+            allowance
+        }
+
+        /// Returns the number of rebases so far.
+        pub(external) fn epoch(&self) -> u64 {
+            let epoch = *self.epoch;
+            env.println(&format!("RebasingToken::epoch = {:?}", epoch));
+            epoch
+        }
+
+        /// Grows the total supply by `supply_delta`, or shrinks it if
+        /// `supply_delta` is negative, and every balance along with it.
+        ///
+        /// Returns the new total supply. Only the owner can rebase the token.
+        pub(external) fn rebase(&mut self, supply_delta: i128) -> Result<Balance, Erc20Error> {
+            if env.caller() != *self.owner {
+                return Err(Erc20Error::NotOwner)
+            }
+            let total_supply = *self.total_supply;
+            // `i128::min_value()` is the only delta whose magnitude does not fit in an i128.
+            let magnitude = supply_delta.checked_abs().map(|delta| delta as Balance).unwrap_or(1 << 127);
+            let total_supply = if supply_delta < 0 {
+                total_supply.checked_sub(magnitude)
+            } else {
+                total_supply.checked_add(magnitude)
+            };
+            let total_supply = match total_supply {
+                Some(total_supply) if total_supply > 0 && total_supply <= MAX_SUPPLY => total_supply,
+                _ => return Err(Erc20Error::InvalidSupply),
+            };
+            self.total_supply.set(total_supply);
+            self.gons_per_token.set(*self.total_gons / total_supply);
+            self.epoch += 1;
+            deposit_event(Event::Rebase {
+                epoch: *self.epoch,
+                total_supply: total_supply
+            });
+            Ok(total_supply)
+        }
+
+        /// Transfers token from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.transfer_impl(env.caller(), to, value)
+        }
+
+        /// Approve the passed AccountId to spend the specified amount of tokens
+        /// on the behalf of the message's sender.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let owner = env.caller();
+            self.allowances.insert((owner, spender), value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfer tokens from one AccountId to another.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let allowance = self.allowance_or_zero(&from, &env.caller());
+            if allowance < value {
+                return Err(Erc20Error::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.allowances.insert((from, env.caller()), allowance - value);
+            Ok(())
+        }
+    }
+
+    impl RebasingToken {
+        /// Returns the gons of the AccountId or 0 if there are none.
+        fn gons_of(&self, of: &AccountId) -> Balance {
+            *self.gon_balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            *self.allowances.get(&(*owner, *spender)).unwrap_or(&0)
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        ///
+        /// `value` is converted into gons first, which cannot overflow since
+        /// `value` never exceeds the total supply when the balance check passes.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            if value > *self.total_supply {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            let gon_value = value * *self.gons_per_token;
+            let gons_from = self.gons_of(&from);
+            if gons_from < gon_value {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            self.gon_balances.insert(from, gons_from - gon_value);
+            let gons_to = self.gons_of(&to);
+            self.gon_balances.insert(to, gons_to + gon_value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn deployment_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let token = RebasingToken::deploy_mock(1000);
+        assert_eq!(token.total_supply(), 1000);
+        assert_eq!(token.balance_of(alice), 1000);
+        assert_eq!(token.epoch(), 0);
+    }
+
+    #[test]
+    fn rebase_up_grows_every_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = RebasingToken::deploy_mock(1000);
+        assert_eq!(token.transfer(bob, 250), Ok(()));
+        let gons = token.scaled_balance_of(bob);
+        assert_eq!(token.rebase(1000), Ok(2000));
+        assert_eq!(token.epoch(), 1);
+        assert_eq!(token.balance_of(alice), 1500);
+        assert_eq!(token.balance_of(bob), 500);
+        // Nobody's gons changed
+        assert_eq!(token.scaled_balance_of(bob), gons);
+    }
+
+    #[test]
+    fn rebase_down_shrinks_every_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = RebasingToken::deploy_mock(1000);
+        assert_eq!(token.transfer(bob, 250), Ok(()));
+        assert_eq!(token.rebase(-500), Ok(500));
+        assert_eq!(token.balance_of(alice), 375);
+        assert_eq!(token.balance_of(bob), 125);
+    }
+
+    #[test]
+    fn balances_stay_proportional() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = RebasingToken::deploy_mock(3_000_000);
+        assert_eq!(token.transfer(bob, 1_000_000), Ok(()));
+        assert_eq!(token.transfer(charlie, 500_000), Ok(()));
+        for delta in &[1_234_567, -2_000_001, 7, -1, 999_999_999] {
+            assert!(token.rebase(*delta).is_ok());
+            let supply = token.total_supply();
+            let balances = [token.balance_of(alice), token.balance_of(bob), token.balance_of(charlie)];
+            // Rounding down never hands out more than the total supply
+            assert!(balances.iter().sum::<Balance>() <= supply);
+            assert!(balances.iter().sum::<Balance>() + 3 > supply);
+            // Bob still holds a third and Charlie a sixth of the supply
+            assert!((balances[1] as i128 - (supply / 3) as i128).abs() <= 1);
+            assert!((balances[2] as i128 - (supply / 6) as i128).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn transfers_work_after_rebases() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = RebasingToken::deploy_mock(1000);
+        assert_eq!(token.transfer(bob, 250), Ok(()));
+        assert_eq!(token.rebase(1000), Ok(2000));
+        // Bob can send his whole grown balance, but not more
+        env::test::set_caller(bob);
+        assert_eq!(token.transfer(charlie, 501), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(token.transfer(charlie, 500), Ok(()));
+        assert_eq!(token.balance_of(bob), 0);
+        assert_eq!(token.balance_of(charlie), 500);
+        assert_eq!(token.balance_of(alice), 1500);
+        // Allowances are in tokens and do not grow with rebases
+        assert_eq!(token.approve(alice, 100), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(token.rebase(-1000), Ok(1000));
+        assert_eq!(token.allowance(bob, alice), 100);
+        // Balances are rounded down, which can cost a holder a fraction of a token
+        assert_eq!(token.balance_of(alice), 750);
+        assert_eq!(token.balance_of(charlie), 249);
+        env::test::set_caller(charlie);
+        assert_eq!(token.approve(alice, 100), Ok(()));
+        env::test::set_caller(alice);
+        assert_eq!(token.transfer_from(charlie, alice, 100), Ok(()));
+        assert_eq!(token.balance_of(charlie), 149);
+        assert_eq!(token.balance_of(alice), 850);
+    }
+
+    #[test]
+    fn only_the_owner_can_rebase_within_bounds() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut token = RebasingToken::deploy_mock(1000);
+        assert_eq!(token.rebase(-1000), Err(Erc20Error::InvalidSupply));
+        assert_eq!(token.rebase(-1001), Err(Erc20Error::InvalidSupply));
+        assert_eq!(token.rebase(MAX_SUPPLY as i128), Err(Erc20Error::InvalidSupply));
+        assert_eq!(token.rebase(i128::min_value()), Err(Erc20Error::InvalidSupply));
+        assert_eq!(token.total_supply(), 1000);
+        env::test::set_caller(bob);
+        assert_eq!(token.rebase(1), Err(Erc20Error::NotOwner));
+    }
+}
@@ -0,0 +1,225 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the soulbound token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the soulbound token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum SoulboundError {
+    /// Soulbound tokens can never change hands.
+    NonTransferable,
+    /// Only the issuer can mint and revoke tokens.
+    NotIssuer,
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+}
+
+/// Deposits a soulbound token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// A token whose balances are bound to the account they were minted to.
+    ///
+    /// The issuer hands out tokens as credentials, for example a badge for
+    /// attending the workshop, and can revoke them again. The token keeps the
+    /// messages of an ERC20 token so wallets can display it, but every attempt
+    /// to move tokens fails with `SoulboundError::NonTransferable`.
+    struct Soulbound {
+        /// The account which mints and revokes tokens.
+        issuer: storage::Value<AccountId>,
+        /// The total supply.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for Soulbound {
+        fn deploy(&mut self) {
+            self.issuer.set(env.caller());
+            self.total_supply.set(0);
+        }
+    }
+
+    impl Soulbound {
+        /// Returns the account which mints and revokes tokens.
+        pub(external) fn issuer(&self) -> AccountId {
+            let issuer = *self.issuer;
+            env.println(&format!("Soulbound::issuer = {:?}", issuer));
+            issuer
+        }
+
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("Soulbound::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Soulbound::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns 0, since nobody can spend the tokens of somebody else.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            env.println(&format!("Soulbound::allowance(owner = {:?}, spender = {:?}) = 0", owner, spender));
+            0
+        }
+
+        /// Always fails, since soulbound tokens cannot be transferred.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), SoulboundError> {
+            let _ = (to, value);
+            Err(SoulboundError::NonTransferable)
+        }
+
+        /// Always fails, since soulbound tokens cannot be spent by others.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), SoulboundError> {
+            let _ = (spender, value);
+            Err(SoulboundError::NonTransferable)
+        }
+
+        /// Always fails, since soulbound tokens cannot be transferred.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<Balance, SoulboundError> {
+            let _ = (from, to, value);
+            Err(SoulboundError::NonTransferable)
+        }
+
+        /// Mints `value` tokens to `to`.
+        ///
+        /// Only the issuer can mint tokens.
+        pub(external) fn mint(&mut self, to: AccountId, value: Balance) -> Result<(), SoulboundError> {
+            self.ensure_issuer(&env.caller())?;
+            let balance = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance + value);
+            self.total_supply += value;
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Burns `value` tokens of `from`, for example when a credential
+        /// turns out to have been issued by mistake.
+        ///
+        /// Only the issuer can revoke tokens.
+        pub(external) fn revoke(&mut self, from: AccountId, value: Balance) -> Result<(), SoulboundError> {
+            self.ensure_issuer(&env.caller())?;
+            let balance = self.balance_of_or_zero(&from);
+            if balance < value {
+                return Err(SoulboundError::InsufficientBalance)
+            }
+            self.balances.insert(from, balance - value);
+            self.total_supply -= value;
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: None,
+                value: value
+            });
+            Ok(())
+        }
+    }
+
+    impl Soulbound {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            let balance = self.balances.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns an error unless `caller` is the issuer.
+        fn ensure_issuer(&self, caller: &AccountId) -> Result<(), SoulboundError> {
+            if *caller != *self.issuer {
+                return Err(SoulboundError::NotIssuer)
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn issuer_can_mint() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut soulbound = Soulbound::deploy_mock();
+        assert_eq!(soulbound.issuer(), alice);
+        assert_eq!(soulbound.mint(bob, 1), Ok(()));
+        assert_eq!(soulbound.balance_of(bob), 1);
+        assert_eq!(soulbound.total_supply(), 1);
+        // Bob cannot issue badges to himself
+        env::test::set_caller(bob);
+        assert_eq!(soulbound.mint(bob, 1), Err(SoulboundError::NotIssuer));
+    }
+
+    #[test]
+    fn tokens_cannot_be_transferred() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut soulbound = Soulbound::deploy_mock();
+        assert_eq!(soulbound.mint(bob, 1), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(soulbound.transfer(charlie, 1), Err(SoulboundError::NonTransferable));
+        assert_eq!(soulbound.approve(charlie, 1), Err(SoulboundError::NonTransferable));
+        assert_eq!(soulbound.allowance(bob, charlie), 0);
+        // Not even the issuer can move tokens around
+        env::test::set_caller(alice);
+        assert_eq!(soulbound.transfer_from(bob, charlie, 1), Err(SoulboundError::NonTransferable));
+        assert_eq!(soulbound.balance_of(bob), 1);
+        assert_eq!(soulbound.balance_of(charlie), 0);
+    }
+
+    #[test]
+    fn issuer_can_revoke() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut soulbound = Soulbound::deploy_mock();
+        assert_eq!(soulbound.mint(bob, 2), Ok(()));
+        // Bob cannot get rid of his tokens on his own
+        env::test::set_caller(bob);
+        assert_eq!(soulbound.revoke(bob, 1), Err(SoulboundError::NotIssuer));
+        env::test::set_caller(alice);
+        assert_eq!(soulbound.revoke(bob, 3), Err(SoulboundError::InsufficientBalance));
+        assert_eq!(soulbound.revoke(bob, 1), Ok(()));
+        assert_eq!(soulbound.balance_of(bob), 1);
+        assert_eq!(soulbound.total_supply(), 1);
+    }
+}
@@ -0,0 +1,593 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `price` message of the price source.
+const PRICE_SELECTOR: [u8; 4] = [0xD4, 0xBD, 0x7B, 0xC1];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The precision of prices, which are fixed-point numbers with 18 decimals.
+const PRICE_PRECISION: Balance = 1_000_000_000_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Events deposited by the stablecoin contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    CollateralDeposited {
+        owner: AccountId,
+        value: Balance,
+    },
+    CollateralWithdrawn {
+        owner: AccountId,
+        value: Balance,
+    },
+    Liquidated {
+        owner: AccountId,
+        debt: Balance,
+        collateral: Balance,
+    },
+}
+
+/// Errors which can occur when calling the stablecoin contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum StablecoinError {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The position does not hold enough collateral.
+    InsufficientCollateral,
+    /// The position would fall below the collateral ratio.
+    Undercollateralized,
+    /// The repayment is larger than the debt of the position.
+    ExceedsDebt,
+    /// The price source did not return a usable price.
+    PriceUnavailable,
+    /// The position is above the liquidation ratio.
+    NotLiquidatable,
+    /// Only the admin can liquidate positions.
+    NotAdmin,
+}
+
+/// Deposits a stablecoin event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the value of `collateral` in stable tokens at `price`, rounded down.
+///
+/// `collateral` is split to avoid overflowing on large amounts.
+fn collateral_value(collateral: Balance, price: Balance) -> Balance {
+    collateral / PRICE_PRECISION * price + collateral % PRICE_PRECISION * price / PRICE_PRECISION
+}
+
+/// Returns whether `collateral` covers at least `ratio` percent of `debt`.
+fn covers(collateral: Balance, debt: Balance, price: Balance, ratio: u32) -> bool {
+    collateral_value(collateral, price).saturating_mul(100) >= debt.saturating_mul(Balance::from(ratio))
+}
+
+contract! {
+    /// A stable token which is minted against native balance locked as collateral.
+    ///
+    /// Every account has a position: the native balance it locked and the
+    /// stable tokens it minted, its debt. A position may only mint while its
+    /// collateral is worth at least `collateral_ratio` percent of its debt.
+    /// When the price of the collateral falls and a position drops below
+    /// `liquidation_ratio` percent, the admin repays its debt and takes its
+    /// collateral.
+    struct Stablecoin {
+        /// The account which liquidates positions.
+        admin: storage::Value<AccountId>,
+        /// The contract which reports the price of the collateral in stable tokens.
+        price_source: storage::Value<AccountId>,
+        /// The ratio in percent a position needs to mint or withdraw.
+        collateral_ratio: storage::Value<u32>,
+        /// The ratio in percent below which a position can be liquidated.
+        liquidation_ratio: storage::Value<u32>,
+        /// The total supply of stable tokens.
+        total_supply: storage::Value<Balance>,
+        /// The stable tokens of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// The native balance locked by each user.
+        collateral: storage::HashMap<AccountId, Balance>,
+        /// The stable tokens minted by each user.
+        debts: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for Stablecoin {
+        fn deploy(&mut self, price_source: AccountId, collateral_ratio: u32, liquidation_ratio: u32) {
+            assert!(liquidation_ratio >= 100, "positions must be liquidated before they are underwater");
+            assert!(collateral_ratio >= liquidation_ratio, "new positions must not be liquidatable");
+            self.admin.set(env.caller());
+            self.price_source.set(price_source);
+            self.collateral_ratio.set(collateral_ratio);
+            self.liquidation_ratio.set(liquidation_ratio);
+            self.total_supply.set(0);
+        }
+    }
+
+    impl Stablecoin {
+        /// Returns the total number of stable tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("Stablecoin::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the stable tokens of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Stablecoin::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the native balance locked by the given AccountId.
+        pub(external) fn collateral_of(&self, owner: AccountId) -> Balance {
+            let collateral = self.collateral_of_or_zero(&owner);
+            env.println(&format!("Stablecoin::collateral_of(owner = {:?}) = {:?}", owner, collateral));
+            collateral
+        }
+
+        /// Returns the stable tokens minted by the given AccountId.
+        pub(external) fn debt_of(&self, owner: AccountId) -> Balance {
+            let debt = self.debt_of_or_zero(&owner);
+            env.println(&format!("Stablecoin::debt_of(owner = {:?}) = {:?}", owner, debt));
+            debt
+        }
+
+        /// Returns the value of the collateral of the given AccountId in percent
+        /// of its debt, or `None` if it has no debt or there is no price.
+        pub(external) fn ratio_of(&self, owner: AccountId) -> Option<Balance> {
+            let debt = self.debt_of_or_zero(&owner);
+            let ratio = match self.price() {
+                Ok(price) if debt > 0 => {
+                    let value = collateral_value(self.collateral_of_or_zero(&owner), price);
+                    Some(value.saturating_mul(100) / debt)
+                }
+                _ => None,
+            };
+            env.println(&format!("Stablecoin::ratio_of(owner = {:?}) = {:?}", owner, ratio));
+            ratio
+        }
+
+        /// Transfers stable tokens from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), StablecoinError> {
+            let from = env.caller();
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(StablecoinError::InsufficientBalance)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Locks the native balance sent along with the call as collateral of
+        /// the caller.
+        pub(external) fn deposit_collateral(&mut self) {
+            let owner = env.caller();
+            let value = value_transferred();
+            let collateral = self.collateral_of_or_zero(&owner);
+            self.collateral.insert(owner, collateral + value);
+            deposit_event(Event::CollateralDeposited {
+                owner: owner,
+                value: value
+            });
+        }
+
+        /// Mints `value` stable tokens to the caller against its collateral.
+        pub(external) fn mint(&mut self, value: Balance) -> Result<(), StablecoinError> {
+            let owner = env.caller();
+            let debt = self.debt_of_or_zero(&owner) + value;
+            let price = self.price()?;
+            if !covers(self.collateral_of_or_zero(&owner), debt, price, *self.collateral_ratio) {
+                return Err(StablecoinError::Undercollateralized)
+            }
+            self.debts.insert(owner, debt);
+            let balance = self.balance_of_or_zero(&owner);
+            self.balances.insert(owner, balance + value);
+            self.total_supply += value;
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(owner),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Burns `repay` stable tokens of the caller to pay back its debt, then
+        /// unlocks `withdraw` of its collateral and pays it out.
+        ///
+        /// The position has to stay above the collateral ratio unless it is
+        /// repaid in full. If the payout fails, the whole call is reverted.
+        pub(external) fn repay_and_withdraw(&mut self, repay: Balance, withdraw: Balance) -> Result<(), StablecoinError> {
+            let owner = env.caller();
+            let balance = self.balance_of_or_zero(&owner);
+            let debt = self.debt_of_or_zero(&owner);
+            let collateral = self.collateral_of_or_zero(&owner);
+            if repay > debt {
+                return Err(StablecoinError::ExceedsDebt)
+            }
+            if repay > balance {
+                return Err(StablecoinError::InsufficientBalance)
+            }
+            if withdraw > collateral {
+                return Err(StablecoinError::InsufficientCollateral)
+            }
+            // Fully repaid positions do not need a price to withdraw
+            if debt > repay {
+                let price = self.price()?;
+                if !covers(collateral - withdraw, debt - repay, price, *self.collateral_ratio) {
+                    return Err(StablecoinError::Undercollateralized)
+                }
+            }
+            self.balances.insert(owner, balance - repay);
+            self.debts.insert(owner, debt - repay);
+            self.total_supply -= repay;
+            self.collateral.insert(owner, collateral - withdraw);
+            deposit_event(Event::Transfer {
+                from: Some(owner),
+                to: None,
+                value: repay
+            });
+            if withdraw > 0 {
+                assert!(transfer_native(owner, withdraw), "the payout failed");
+                deposit_event(Event::CollateralWithdrawn {
+                    owner: owner,
+                    value: withdraw
+                });
+            }
+            Ok(())
+        }
+
+        /// Repays the whole debt of `owner` with stable tokens of the admin and
+        /// pays out all of its collateral to the admin.
+        ///
+        /// Only the admin can liquidate, and only positions below the
+        /// liquidation ratio. If the payout fails, the whole call is reverted.
+        pub(external) fn liquidate(&mut self, owner: AccountId) -> Result<(), StablecoinError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(StablecoinError::NotAdmin)
+            }
+            let debt = self.debt_of_or_zero(&owner);
+            let collateral = self.collateral_of_or_zero(&owner);
+            let price = self.price()?;
+            if covers(collateral, debt, price, *self.liquidation_ratio) {
+                return Err(StablecoinError::NotLiquidatable)
+            }
+            let balance = self.balance_of_or_zero(&admin);
+            if balance < debt {
+                return Err(StablecoinError::InsufficientBalance)
+            }
+            self.balances.insert(admin, balance - debt);
+            self.total_supply -= debt;
+            self.debts.insert(owner, 0);
+            self.collateral.insert(owner, 0);
+            assert!(transfer_native(admin, collateral), "the payout failed");
+            deposit_event(Event::Transfer {
+                from: Some(admin),
+                to: None,
+                value: debt
+            });
+            deposit_event(Event::Liquidated {
+                owner: owner,
+                debt: debt,
+                collateral: collateral
+            });
+            Ok(())
+        }
+    }
+
+    impl Stablecoin {
+        /// Returns the stable tokens of the AccountId or 0 if there are none.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the collateral of the AccountId or 0 if there is none.
+        fn collateral_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.collateral.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the debt of the AccountId or 0 if there is none.
+        fn debt_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.debts.get(of).unwrap_or(&0)
+        }
+
+        /// Asks the price source for the price of one unit of collateral in
+        /// stable tokens.
+        ///
+        /// A price of zero is treated as missing, so a broken price source
+        /// cannot make every position liquidatable.
+        fn price(&self) -> Result<Balance, StablecoinError> {
+            match call_contract(*self.price_source, PRICE_SELECTOR, &[]) {
+                Some(price) if price > 0 => Ok(price),
+                _ => Err(StablecoinError::PriceUnavailable),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the stablecoin through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock price source at `account` and returns its price,
+    /// which the tests can change at will.
+    fn register_price_source(account: AccountId, price: Balance) -> Rc<RefCell<Balance>> {
+        let price = Rc::new(RefCell::new(price));
+        let state = price.clone();
+        register_contract(account, move |selector, _| {
+            match selector {
+                PRICE_SELECTOR => Some(state.borrow().encode()),
+                _ => None,
+            }
+        });
+        price
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Locks `value` of native balance as collateral of the caller.
+    fn deposit_collateral(stablecoin: &mut Stablecoin, value: Balance) {
+        set_value_transferred(value);
+        stablecoin.deposit_collateral();
+        set_value_transferred(0);
+    }
+
+    #[test]
+    fn mint_respects_the_collateral_ratio() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        // One unit of collateral is worth two stable tokens
+        register_price_source(oracle, 2 * PRICE_PRECISION);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.collateral_of(alice), 300);
+        // 300 units are worth 600 tokens, which cover 150% of 400 tokens
+        assert_eq!(stablecoin.mint(401), Err(StablecoinError::Undercollateralized));
+        assert_eq!(stablecoin.mint(400), Ok(()));
+        assert_eq!(stablecoin.balance_of(alice), 400);
+        assert_eq!(stablecoin.debt_of(alice), 400);
+        assert_eq!(stablecoin.total_supply(), 400);
+        assert_eq!(stablecoin.ratio_of(alice), Some(150));
+        assert_eq!(stablecoin.mint(1), Err(StablecoinError::Undercollateralized));
+    }
+
+    #[test]
+    fn repay_and_withdraw_works() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        register_price_source(oracle, 2 * PRICE_PRECISION);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.mint(200), Ok(()));
+        // 200 tokens of debt need 150 units of collateral
+        assert_eq!(stablecoin.repay_and_withdraw(0, 151), Err(StablecoinError::Undercollateralized));
+        assert_eq!(stablecoin.repay_and_withdraw(0, 150), Ok(()));
+        assert_eq!(stablecoin.repay_and_withdraw(201, 0), Err(StablecoinError::ExceedsDebt));
+        assert_eq!(stablecoin.repay_and_withdraw(200, 151), Err(StablecoinError::InsufficientCollateral));
+        // Repaying everything frees the rest of the collateral
+        assert_eq!(stablecoin.repay_and_withdraw(200, 150), Ok(()));
+        assert_eq!(stablecoin.balance_of(alice), 0);
+        assert_eq!(stablecoin.debt_of(alice), 0);
+        assert_eq!(stablecoin.collateral_of(alice), 0);
+        assert_eq!(stablecoin.total_supply(), 0);
+        assert_eq!(payouts(), vec![(alice, 150), (alice, 150)]);
+    }
+
+    #[test]
+    fn repay_needs_the_tokens() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        register_price_source(oracle, 2 * PRICE_PRECISION);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.mint(200), Ok(()));
+        assert_eq!(stablecoin.transfer(bob, 50), Ok(()));
+        assert_eq!(stablecoin.repay_and_withdraw(200, 0), Err(StablecoinError::InsufficientBalance));
+        assert_eq!(stablecoin.repay_and_withdraw(150, 0), Ok(()));
+        assert_eq!(stablecoin.debt_of(alice), 50);
+        assert_eq!(payouts(), vec![]);
+    }
+
+    #[test]
+    fn missing_prices_block_minting() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let price = register_price_source(oracle, 0);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.mint(1), Err(StablecoinError::PriceUnavailable));
+        *price.borrow_mut() = PRICE_PRECISION;
+        assert_eq!(stablecoin.mint(1), Ok(()));
+        // A price source which cannot be reached does not work either
+        let other = Stablecoin::deploy_mock(AccountId::try_from([0x8; 32]).unwrap(), 150, 120);
+        assert_eq!(other.ratio_of(alice), None);
+    }
+
+    #[test]
+    fn admin_liquidates_positions_below_the_liquidation_ratio() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let price = register_price_source(oracle, 2 * PRICE_PRECISION);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        // Alice, the admin, mints the tokens she needs to liquidate
+        deposit_collateral(&mut stablecoin, 1000);
+        assert_eq!(stablecoin.mint(400), Ok(()));
+        env::test::set_caller(bob);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.mint(400), Ok(()));
+        // Bob cannot liquidate positions himself
+        assert_eq!(stablecoin.liquidate(bob), Err(StablecoinError::NotAdmin));
+        env::test::set_caller(alice);
+        // At 127.5%, Bob's position is unsafe but not liquidatable yet
+        *price.borrow_mut() = PRICE_PRECISION / 10 * 17;
+        assert_eq!(stablecoin.ratio_of(bob), Some(127));
+        assert_eq!(stablecoin.liquidate(bob), Err(StablecoinError::NotLiquidatable));
+        // At 112.5%, it is
+        *price.borrow_mut() = PRICE_PRECISION / 10 * 15;
+        assert_eq!(stablecoin.liquidate(bob), Ok(()));
+        assert_eq!(stablecoin.debt_of(bob), 0);
+        assert_eq!(stablecoin.collateral_of(bob), 0);
+        assert_eq!(stablecoin.balance_of(alice), 0);
+        // Bob keeps the tokens he minted, Alice gets his collateral
+        assert_eq!(stablecoin.balance_of(bob), 400);
+        assert_eq!(stablecoin.total_supply(), 400);
+        assert_eq!(payouts(), vec![(alice, 300)]);
+    }
+
+    #[test]
+    fn liquidation_needs_the_tokens() {
+        let oracle = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let price = register_price_source(oracle, 2 * PRICE_PRECISION);
+
+        let mut stablecoin = Stablecoin::deploy_mock(oracle, 150, 120);
+        env::test::set_caller(bob);
+        deposit_collateral(&mut stablecoin, 300);
+        assert_eq!(stablecoin.mint(400), Ok(()));
+        env::test::set_caller(alice);
+        *price.borrow_mut() = PRICE_PRECISION;
+        assert_eq!(stablecoin.liquidate(bob), Err(StablecoinError::InsufficientBalance));
+        assert_eq!(stablecoin.debt_of(bob), 400);
+        assert_eq!(stablecoin.collateral_of(bob), 300);
+        assert_eq!(payouts(), vec![]);
+    }
+}
@@ -0,0 +1,575 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The precision of the reward per token, which is a fixed-point number with
+/// 18 decimals.
+const REWARD_PRECISION: Balance = 1_000_000_000_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the staking contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Staked {
+        account: AccountId,
+        amount: Balance,
+    },
+    Unstaked {
+        account: AccountId,
+        amount: Balance,
+    },
+    Funded {
+        funder: AccountId,
+        amount: Balance,
+    },
+    RewardPaid {
+        account: AccountId,
+        reward: Balance,
+    },
+    RewardRateChanged {
+        reward_rate: Balance,
+    },
+}
+
+/// Errors which can occur when calling the staking contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum StakingError {
+    /// Nothing was staked, unstaked or earned.
+    ZeroAmount,
+    /// The account has not staked enough tokens.
+    InsufficientStake,
+    /// The contract has not been funded with enough tokens for the reward.
+    InsufficientRewards,
+    /// Only the admin can change the reward rate.
+    NotAdmin,
+    /// The token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits a staking event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns `value * reward_per_token`, where `reward_per_token` has
+/// `REWARD_PRECISION`, rounded down.
+///
+/// `value` is split to avoid overflowing on large amounts.
+fn mul_reward(value: Balance, reward_per_token: Balance) -> Balance {
+    value / REWARD_PRECISION * reward_per_token + value % REWARD_PRECISION * reward_per_token / REWARD_PRECISION
+}
+
+/// Returns `reward / total_staked` with `REWARD_PRECISION`, rounded down.
+///
+/// `reward` is split to avoid overflowing on large amounts.
+fn div_reward(reward: Balance, total_staked: Balance) -> Balance {
+    reward / total_staked * REWARD_PRECISION + reward % total_staked * REWARD_PRECISION / total_staked
+}
+
+contract! {
+    /// Pays rewards in ERC20 tokens to the accounts which stake them.
+    ///
+    /// Every block, `reward_rate` tokens are shared among the stakers in
+    /// proportion to their stake. Instead of paying every staker every block,
+    /// the contract keeps track of the reward a single staked token has earned
+    /// since the start, and settles an account whenever its stake changes. The
+    /// rewards themselves have to be funded separately from the stakes, so
+    /// that claims can never pay out staked tokens.
+    struct StakingRewards {
+        /// The account which may change the reward rate.
+        admin: storage::Value<AccountId>,
+        /// The ERC20 token which is staked and paid as a reward.
+        token: storage::Value<AccountId>,
+        /// The tokens paid out as rewards every block.
+        reward_rate: storage::Value<Balance>,
+        /// The total number of staked tokens.
+        total_staked: storage::Value<Balance>,
+        /// The tokens funded for rewards which have not been paid out yet.
+        reward_pool: storage::Value<Balance>,
+        /// The tokens staked by each account.
+        staked: storage::HashMap<AccountId, Balance>,
+        /// The reward earned by a single staked token up to `last_update`.
+        reward_per_token_stored: storage::Value<Balance>,
+        /// The block up to which `reward_per_token_stored` has been updated.
+        last_update: storage::Value<BlockNumber>,
+        /// The reward per token each account has already been settled for.
+        reward_per_token_paid: storage::HashMap<AccountId, Balance>,
+        /// The rewards settled for each account but not claimed yet.
+        rewards: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for StakingRewards {
+        fn deploy(&mut self, token: AccountId, reward_rate: Balance) {
+            self.admin.set(env.caller());
+            self.token.set(token);
+            self.reward_rate.set(reward_rate);
+            self.total_staked.set(0);
+            self.reward_pool.set(0);
+            self.reward_per_token_stored.set(0);
+            self.last_update.set(env::block_number());
+        }
+    }
+
+    impl StakingRewards {
+        /// Returns the total number of staked tokens.
+        pub(external) fn total_staked(&self) -> Balance {
+            let total_staked = *self.total_staked;
+            env.println(&format!("StakingRewards::total_staked = {:?}", total_staked));
+            total_staked
+        }
+
+        /// Returns the tokens staked by `account`.
+        pub(external) fn staked_of(&self, account: AccountId) -> Balance {
+            let staked = self.staked_of_or_zero(&account);
+            env.println(&format!("StakingRewards::staked_of(account = {:?}) = {:?}", account, staked));
+            staked
+        }
+
+        /// Returns the tokens funded for rewards which have not been paid out yet.
+        pub(external) fn reward_pool(&self) -> Balance {
+            let reward_pool = *self.reward_pool;
+            env.println(&format!("StakingRewards::reward_pool = {:?}", reward_pool));
+            reward_pool
+        }
+
+        /// Returns the tokens paid out as rewards every block.
+        pub(external) fn reward_rate(&self) -> Balance {
+            let reward_rate = *self.reward_rate;
+            env.println(&format!("StakingRewards::reward_rate = {:?}", reward_rate));
+            reward_rate
+        }
+
+        /// Returns the reward a single staked token has earned so far, with
+        /// 18 decimals.
+        pub(external) fn reward_per_token(&self) -> Balance {
+            let reward_per_token = self.current_reward_per_token();
+            env.println(&format!("StakingRewards::reward_per_token = {:?}", reward_per_token));
+            reward_per_token
+        }
+
+        /// Returns the reward `account` can claim right now.
+        pub(external) fn earned(&self, account: AccountId) -> Balance {
+            let earned = self.earned_impl(&account, self.current_reward_per_token());
+            env.println(&format!("StakingRewards::earned(account = {:?}) = {:?}", account, earned));
+            earned
+        }
+
+        /// Stakes `amount` tokens of the caller, who must have approved the
+        /// contract to transfer them.
+        pub(external) fn stake(&mut self, amount: Balance) -> Result<(), StakingError> {
+            if amount == 0 {
+                return Err(StakingError::ZeroAmount)
+            }
+            let account = env.caller();
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(account, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(StakingError::TransferFailed),
+            }
+            self.update_reward(&account);
+            let staked = self.staked_of_or_zero(&account);
+            self.staked.insert(account, staked + amount);
+            self.total_staked += amount;
+            deposit_event(Event::Staked {
+                account: account,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Adds `amount` tokens of the caller to the reward pool. The caller
+        /// must have approved the contract to transfer them.
+        pub(external) fn fund(&mut self, amount: Balance) -> Result<(), StakingError> {
+            if amount == 0 {
+                return Err(StakingError::ZeroAmount)
+            }
+            let funder = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(funder, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(StakingError::TransferFailed),
+            }
+            self.reward_pool += amount;
+            deposit_event(Event::Funded {
+                funder: funder,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Pays `amount` staked tokens back to the caller.
+        ///
+        /// The rewards earned so far stay claimable. If the token refuses the
+        /// transfer, the whole call is reverted.
+        pub(external) fn unstake(&mut self, amount: Balance) -> Result<(), StakingError> {
+            if amount == 0 {
+                return Err(StakingError::ZeroAmount)
+            }
+            let account = env.caller();
+            let staked = self.staked_of_or_zero(&account);
+            if staked < amount {
+                return Err(StakingError::InsufficientStake)
+            }
+            self.update_reward(&account);
+            self.staked.insert(account, staked - amount);
+            self.total_staked -= amount;
+            self.pay_out(account, amount);
+            deposit_event(Event::Unstaked {
+                account: account,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Pays the rewards earned by the caller out of the reward pool.
+        pub(external) fn claim(&mut self) -> Result<Balance, StakingError> {
+            let account = env.caller();
+            self.update_reward(&account);
+            let reward = *self.rewards.get(&account).unwrap_or(&0);
+            if reward == 0 {
+                return Err(StakingError::ZeroAmount)
+            }
+            if reward > *self.reward_pool {
+                return Err(StakingError::InsufficientRewards)
+            }
+            self.rewards.insert(account, 0);
+            self.reward_pool -= reward;
+            self.pay_out(account, reward);
+            deposit_event(Event::RewardPaid {
+                account: account,
+                reward: reward
+            });
+            Ok(reward)
+        }
+
+        /// Changes the tokens paid out as rewards every block.
+        ///
+        /// The rewards up to the current block are accrued at the old rate.
+        /// Only the admin can change the reward rate.
+        pub(external) fn set_reward_rate(&mut self, reward_rate: Balance) -> Result<(), StakingError> {
+            if env.caller() != *self.admin {
+                return Err(StakingError::NotAdmin)
+            }
+            let reward_per_token = self.current_reward_per_token();
+            self.reward_per_token_stored.set(reward_per_token);
+            self.last_update.set(env.block_number());
+            self.reward_rate.set(reward_rate);
+            deposit_event(Event::RewardRateChanged {
+                reward_rate: reward_rate
+            });
+            Ok(())
+        }
+    }
+
+    impl StakingRewards {
+        /// Returns the stake of the AccountId or 0 if there is none.
+        fn staked_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.staked.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the reward per token including the blocks since `last_update`.
+        ///
+        /// While nothing is staked, the rewards of those blocks go to nobody.
+        fn current_reward_per_token(&self) -> Balance {
+            let stored = *self.reward_per_token_stored;
+            if *self.total_staked == 0 {
+                return stored
+            }
+            let blocks = Balance::from(env::block_number() - *self.last_update);
+            stored + div_reward(*self.reward_rate * blocks, *self.total_staked)
+        }
+
+        /// Returns the rewards of `account` at `reward_per_token`.
+        fn earned_impl(&self, account: &AccountId, reward_per_token: Balance) -> Balance {
+            let paid = *self.reward_per_token_paid.get(account).unwrap_or(&0);
+            let reward = *self.rewards.get(account).unwrap_or(&0);
+            reward + mul_reward(self.staked_of_or_zero(account), reward_per_token - paid)
+        }
+
+        /// Accrues the rewards up to the current block, and settles `account`.
+        ///
+        /// Must be called before the stake of `account` changes.
+        fn update_reward(&mut self, account: &AccountId) {
+            let reward_per_token = self.current_reward_per_token();
+            self.reward_per_token_stored.set(reward_per_token);
+            self.last_update.set(env::block_number());
+            let earned = self.earned_impl(account, reward_per_token);
+            self.rewards.insert(*account, earned);
+            self.reward_per_token_paid.insert(*account, reward_per_token);
+        }
+
+        /// Transfers `amount` tokens from the contract to `to`.
+        fn pay_out(&self, to: AccountId, amount: Balance) {
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(to, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be paid out");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the staking contract through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Deploys a staking contract at block 0 which pays 10 tokens per block,
+    /// has Alice fund it with 10000 reward tokens, and gives Bob and Charlie
+    /// 1000 tokens each.
+    fn deploy_mock() -> (StakingRewards, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 10000);
+        balances.borrow_mut().insert(bob, 1000);
+        balances.borrow_mut().insert(charlie, 1000);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut staking = StakingRewards::deploy_mock(token, 10);
+        assert_eq!(staking.fund(10000), Ok(()));
+        (staking, balances)
+    }
+
+    #[test]
+    fn stake_and_unstake_work() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let (mut staking, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(0), Err(StakingError::ZeroAmount));
+        assert_eq!(staking.stake(1001), Err(StakingError::TransferFailed));
+        assert_eq!(staking.stake(400), Ok(()));
+        assert_eq!(staking.staked_of(bob), 400);
+        assert_eq!(staking.total_staked(), 400);
+        assert_eq!(balances.borrow()[&bob], 600);
+        assert_eq!(staking.unstake(401), Err(StakingError::InsufficientStake));
+        assert_eq!(staking.unstake(150), Ok(()));
+        assert_eq!(staking.staked_of(bob), 250);
+        assert_eq!(balances.borrow()[&bob], 750);
+        env::test::set_caller(dave);
+        assert_eq!(staking.unstake(1), Err(StakingError::InsufficientStake));
+    }
+
+    #[test]
+    fn a_single_staker_earns_every_reward() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut staking, balances) = deploy_mock();
+        // Nobody staked during the first 5 blocks, so their rewards are lost
+        env::test::set_block_number(5);
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(100), Ok(()));
+        assert_eq!(staking.earned(bob), 0);
+        env::test::set_block_number(15);
+        assert_eq!(staking.earned(bob), 100);
+        assert_eq!(staking.reward_per_token(), REWARD_PRECISION);
+        assert_eq!(staking.claim(), Ok(100));
+        assert_eq!(staking.claim(), Err(StakingError::ZeroAmount));
+        assert_eq!(balances.borrow()[&bob], 1000);
+        env::test::set_block_number(16);
+        assert_eq!(staking.earned(bob), 10);
+    }
+
+    #[test]
+    fn rewards_are_shared_by_stake() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut staking, balances) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(100), Ok(()));
+        // Charlie joins after 10 blocks with four times Bob's stake
+        env::test::set_block_number(10);
+        env::test::set_caller(charlie);
+        assert_eq!(staking.stake(400), Ok(()));
+        env::test::set_block_number(20);
+        assert_eq!(staking.earned(bob), 100 + 20);
+        assert_eq!(staking.earned(charlie), 80);
+        // Bob leaves, but keeps what he earned
+        env::test::set_caller(bob);
+        assert_eq!(staking.unstake(100), Ok(()));
+        env::test::set_block_number(30);
+        assert_eq!(staking.earned(bob), 120);
+        assert_eq!(staking.earned(charlie), 80 + 100);
+        assert_eq!(staking.claim(), Ok(120));
+        env::test::set_caller(charlie);
+        assert_eq!(staking.unstake(400), Ok(()));
+        assert_eq!(staking.claim(), Ok(180));
+        assert_eq!(balances.borrow()[&bob], 1120);
+        assert_eq!(balances.borrow()[&charlie], 1180);
+        assert_eq!(staking.reward_pool(), 10000 - 300);
+        assert_eq!(balances.borrow()[&env::address()], 10000 - 300);
+    }
+
+    #[test]
+    fn rewards_round_down() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut staking, _) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(100), Ok(()));
+        env::test::set_caller(charlie);
+        assert_eq!(staking.stake(200), Ok(()));
+        // 10 tokens cannot be shared in thirds, so a token stays behind
+        env::test::set_block_number(1);
+        assert_eq!(staking.earned(bob), 3);
+        assert_eq!(staking.earned(charlie), 6);
+        // Large stakes do not overflow
+        assert_eq!(mul_reward(Balance::max_value(), REWARD_PRECISION), Balance::max_value());
+        assert_eq!(div_reward(Balance::max_value() / REWARD_PRECISION, 1), Balance::max_value() / REWARD_PRECISION * REWARD_PRECISION);
+    }
+
+    #[test]
+    fn reward_rate_changes_apply_from_now_on() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut staking, _) = deploy_mock();
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(100), Ok(()));
+        env::test::set_block_number(10);
+        assert_eq!(staking.set_reward_rate(20), Err(StakingError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(staking.set_reward_rate(20), Ok(()));
+        assert_eq!(staking.reward_rate(), 20);
+        env::test::set_block_number(15);
+        assert_eq!(staking.earned(bob), 100 + 100);
+    }
+
+    #[test]
+    fn claims_never_pay_out_stakes() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        register_token(token).borrow_mut().insert(bob, 1000);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        // Nobody funds the rewards
+        let mut staking = StakingRewards::deploy_mock(token, 10);
+        env::test::set_caller(bob);
+        assert_eq!(staking.stake(100), Ok(()));
+        env::test::set_block_number(20);
+        assert_eq!(staking.claim(), Err(StakingError::InsufficientRewards));
+        // Bob's stake is still there for him to take back, and he keeps his reward
+        assert_eq!(staking.unstake(100), Ok(()));
+        assert_eq!(staking.earned(bob), 200);
+        env::test::set_caller(alice);
+        assert_eq!(staking.fund(0), Err(StakingError::ZeroAmount));
+        assert_eq!(staking.fund(1), Err(StakingError::TransferFailed));
+    }
+}
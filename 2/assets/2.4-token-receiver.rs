@@ -0,0 +1,93 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+contract! {
+    /// An example receiver of `transfer_and_call` for the ERC20 token.
+    ///
+    /// The receiver keeps track of how many tokens each account has sent it,
+    /// which a real contract would use to credit deposits, pay for services
+    /// and so on.
+    struct TokenReceiver {
+        /// The ERC20 token this contract accepts.
+        token: storage::Value<AccountId>,
+        /// The tokens received from each account.
+        received: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for TokenReceiver {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+        }
+    }
+
+    impl TokenReceiver {
+        /// Returns the tokens received from `from`.
+        pub(external) fn received_from(&self, from: AccountId) -> Balance {
+            let received = *self.received.get(&from).unwrap_or(&0);
+            env.println(&format!("TokenReceiver::received_from(from = {:?}) = {:?}", from, received));
+            received
+        }
+
+        /// Called by the token once `from` transferred `value` tokens to us.
+        ///
+        /// Returns `true` to accept the tokens. Returning `false` makes the
+        /// token revert the transfer.
+        pub(external) fn on_token_transfer(&mut self, from: AccountId, value: Balance, data: Vec<u8>) -> bool {
+            // Tokens of other contracts would get stuck here.
+            if env.caller() != *self.token {
+                return false
+            }
+            env.println(&format!(
+                "TokenReceiver::on_token_transfer(from = {:?}, value = {:?}, data = {:?})",
+                from, value, data
+            ));
+            let received = *self.received.get(&from).unwrap_or(&0);
+            self.received.insert(from, received + value);
+            true
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn on_token_transfer_accepts_the_token() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        let mut receiver = TokenReceiver::deploy_mock(token);
+        // The token notifies the receiver of Alice's transfers
+        env::test::set_caller(token);
+        assert_eq!(receiver.on_token_transfer(alice, 100, Vec::new()), true);
+        assert_eq!(receiver.on_token_transfer(alice, 20, vec![1, 2, 3]), true);
+        assert_eq!(receiver.received_from(alice), 120);
+    }
+
+    #[test]
+    fn on_token_transfer_rejects_other_tokens() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let other_token = AccountId::try_from([0x8; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+
+        let mut receiver = TokenReceiver::deploy_mock(token);
+        env::test::set_caller(other_token);
+        assert_eq!(receiver.on_token_transfer(alice, 100, Vec::new()), false);
+        assert_eq!(receiver.received_from(alice), 0);
+    }
+}
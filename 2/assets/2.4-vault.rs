@@ -0,0 +1,555 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of the ERC20 token.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the vault.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The shares the vault pretends to have issued on top of the real ones.
+///
+/// Together with a single virtual asset, they make the first depositor unable
+/// to inflate the value of a share so far that later deposits round to zero.
+const VIRTUAL_SHARES: Balance = 1000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Events deposited by the vault.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Deposit {
+        sender: AccountId,
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    },
+    Withdraw {
+        sender: AccountId,
+        receiver: AccountId,
+        owner: AccountId,
+        assets: Balance,
+        shares: Balance,
+    },
+}
+
+/// Errors which can occur when calling the vault.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum VaultError {
+    /// The call would deposit or withdraw no assets.
+    ZeroAssets,
+    /// The call would mint or burn no shares.
+    ZeroShares,
+    /// The account does not hold enough shares.
+    InsufficientShares,
+    /// The asset refused to move the tokens into the vault.
+    TransferFailed,
+    /// The share math overflowed.
+    Overflow,
+}
+
+/// Deposits a vault event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// The direction in which a division rounds.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Rounding {
+    Down,
+    Up,
+}
+
+/// Returns `x * y / denominator`, rounded in the given direction, or `None`
+/// if the product overflows.
+fn mul_div(x: Balance, y: Balance, denominator: Balance, rounding: Rounding) -> Option<Balance> {
+    let product = x.checked_mul(y)?;
+    let quotient = product / denominator;
+    if rounding == Rounding::Up && product % denominator != 0 {
+        return Some(quotient + 1)
+    }
+    Some(quotient)
+}
+
+contract! {
+    /// A tokenized vault in the style of ERC-4626 for our ERC20 token.
+    ///
+    /// Depositors hand their tokens (the _assets_) to the vault and receive
+    /// _shares_ of everything the vault holds. Whenever the vault gains assets
+    /// without minting shares, for example from yield, every share is worth
+    /// more. All conversions round in favor of the vault.
+    struct Vault {
+        /// The ERC20 token the vault holds.
+        asset: storage::Value<AccountId>,
+        /// The total number of shares.
+        total_supply: storage::Value<Balance>,
+        /// The shares of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for Vault {
+        fn deploy(&mut self, asset: AccountId) {
+            self.asset.set(asset);
+            self.total_supply.set(0);
+        }
+    }
+
+    impl Vault {
+        /// Returns the ERC20 token the vault holds.
+        pub(external) fn asset(&self) -> AccountId {
+            let asset = *self.asset;
+            env.println(&format!("Vault::asset = {:?}", asset));
+            asset
+        }
+
+        /// Returns the assets held by the vault.
+        pub(external) fn total_assets(&self) -> Balance {
+            let total_assets = self.total_assets_of_vault();
+            env.println(&format!("Vault::total_assets = {:?}", total_assets));
+            total_assets
+        }
+
+        /// Returns the total number of shares.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("Vault::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the shares of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Vault::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the shares a deposit of `assets` mints.
+        pub(external) fn preview_deposit(&self, assets: Balance) -> Result<Balance, VaultError> {
+            let shares = self.to_shares(assets, self.total_assets_of_vault(), Rounding::Down);
+            env.println(&format!("Vault::preview_deposit(assets = {:?}) = {:?}", assets, shares));
+            shares
+        }
+
+        /// Returns the shares a withdrawal of `assets` burns.
+        pub(external) fn preview_withdraw(&self, assets: Balance) -> Result<Balance, VaultError> {
+            let shares = self.to_shares(assets, self.total_assets_of_vault(), Rounding::Up);
+            env.println(&format!("Vault::preview_withdraw(assets = {:?}) = {:?}", assets, shares));
+            shares
+        }
+
+        /// Returns the assets redeeming `shares` pays out.
+        pub(external) fn preview_redeem(&self, shares: Balance) -> Result<Balance, VaultError> {
+            let assets = self.to_assets(shares, self.total_assets_of_vault(), Rounding::Down);
+            env.println(&format!("Vault::preview_redeem(shares = {:?}) = {:?}", shares, assets));
+            assets
+        }
+
+        /// Returns the assets `owner` can withdraw with all of its shares.
+        pub(external) fn max_withdraw(&self, owner: AccountId) -> Result<Balance, VaultError> {
+            let shares = self.balance_of_or_zero(&owner);
+            let assets = self.to_assets(shares, self.total_assets_of_vault(), Rounding::Down);
+            env.println(&format!("Vault::max_withdraw(owner = {:?}) = {:?}", owner, assets));
+            assets
+        }
+
+        /// Transfers `value` shares from the caller to `to`.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), VaultError> {
+            let from = env.caller();
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(VaultError::InsufficientShares)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Moves `assets` of the caller into the vault and mints the shares they
+        /// are worth to `receiver`, returning the number of shares.
+        ///
+        /// The caller has to approve the vault to spend `assets` first. Shares
+        /// are minted for the assets the vault actually received, which can be
+        /// less than `assets` if the token takes a fee on transfers.
+        pub(external) fn deposit(&mut self, assets: Balance, receiver: AccountId) -> Result<Balance, VaultError> {
+            if assets == 0 {
+                return Err(VaultError::ZeroAssets)
+            }
+            let total_assets = self.total_assets_of_vault();
+            if self.to_shares(assets, total_assets, Rounding::Down)? == 0 {
+                return Err(VaultError::ZeroShares)
+            }
+            let caller = env.caller();
+            let input = (caller, env.address(), assets).encode();
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> = call_contract(*self.asset, TRANSFER_FROM_SELECTOR, &input);
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(VaultError::TransferFailed),
+            }
+            let received = self.total_assets_of_vault().saturating_sub(total_assets);
+            // The assets have already moved, so failing from here on has to revert them.
+            let shares = self
+                .to_shares(received, total_assets, Rounding::Down)
+                .expect("the shares of the received assets fit in a Balance");
+            assert!(shares > 0, "the received assets are not worth a single share");
+            self.mint_shares(receiver, shares);
+            deposit_event(Event::Deposit {
+                sender: caller,
+                owner: receiver,
+                assets: received,
+                shares: shares
+            });
+            Ok(shares)
+        }
+
+        /// Burns the shares of the caller worth `assets` and sends `assets` to
+        /// `receiver`, returning the number of burned shares.
+        pub(external) fn withdraw(&mut self, assets: Balance, receiver: AccountId) -> Result<Balance, VaultError> {
+            if assets == 0 {
+                return Err(VaultError::ZeroAssets)
+            }
+            let shares = self.to_shares(assets, self.total_assets_of_vault(), Rounding::Up)?;
+            self.withdraw_impl(env.caller(), receiver, assets, shares)?;
+            Ok(shares)
+        }
+
+        /// Burns `shares` of the caller and sends the assets they are worth to
+        /// `receiver`, returning the number of assets.
+        pub(external) fn redeem(&mut self, shares: Balance, receiver: AccountId) -> Result<Balance, VaultError> {
+            if shares == 0 {
+                return Err(VaultError::ZeroShares)
+            }
+            let assets = self.to_assets(shares, self.total_assets_of_vault(), Rounding::Down)?;
+            if assets == 0 {
+                return Err(VaultError::ZeroAssets)
+            }
+            self.withdraw_impl(env.caller(), receiver, assets, shares)?;
+            Ok(assets)
+        }
+    }
+
+    impl Vault {
+        /// Returns the shares of the AccountId or 0 if there are none.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            let balance = self.balances.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Asks the asset how many tokens the vault holds.
+        ///
+        /// This includes tokens which were sent to the vault without a deposit.
+        fn total_assets_of_vault(&self) -> Balance {
+            call_contract(*self.asset, BALANCE_OF_SELECTOR, &env::address().encode())
+                .expect("the asset reports the balance of the vault")
+        }
+
+        /// Converts `assets` into shares at the rate given by `total_assets`.
+        fn to_shares(&self, assets: Balance, total_assets: Balance, rounding: Rounding) -> Result<Balance, VaultError> {
+            let total_supply = self.total_supply.checked_add(VIRTUAL_SHARES).ok_or(VaultError::Overflow)?;
+            let total_assets = total_assets.checked_add(1).ok_or(VaultError::Overflow)?;
+            mul_div(assets, total_supply, total_assets, rounding).ok_or(VaultError::Overflow)
+        }
+
+        /// Converts `shares` into assets at the rate given by `total_assets`.
+        fn to_assets(&self, shares: Balance, total_assets: Balance, rounding: Rounding) -> Result<Balance, VaultError> {
+            let total_supply = self.total_supply.checked_add(VIRTUAL_SHARES).ok_or(VaultError::Overflow)?;
+            let total_assets = total_assets.checked_add(1).ok_or(VaultError::Overflow)?;
+            mul_div(shares, total_assets, total_supply, rounding).ok_or(VaultError::Overflow)
+        }
+
+        /// Mints `shares` to `to`.
+        fn mint_shares(&mut self, to: AccountId, shares: Balance) {
+            let balance = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance + shares);
+            self.total_supply += shares;
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(to),
+                value: shares
+            });
+        }
+
+        /// Burns `shares` of `owner` and sends `assets` to `receiver`.
+        fn withdraw_impl(
+            &mut self,
+            owner: AccountId,
+            receiver: AccountId,
+            assets: Balance,
+            shares: Balance,
+        ) -> Result<(), VaultError> {
+            let balance = self.balance_of_or_zero(&owner);
+            if balance < shares {
+                return Err(VaultError::InsufficientShares)
+            }
+            // Burn the shares before the assets leave, so the asset cannot see
+            // the vault in between.
+            self.balances.insert(owner, balance - shares);
+            self.total_supply -= shares;
+            deposit_event(Event::Transfer {
+                from: Some(owner),
+                to: None,
+                value: shares
+            });
+            let result: Option<Result<(), u8>> = call_contract(*self.asset, TRANSFER_SELECTOR, &(receiver, assets).encode());
+            // Panic so that the burned shares are reverted as well.
+            assert_eq!(result, Some(Ok(())), "the asset could not be sent");
+            deposit_event(Event::Withdraw {
+                sender: env::caller(),
+                receiver: receiver,
+                owner: owner,
+                assets: assets,
+                shares: shares
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the vault through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens of a mock token from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the vault first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..]).unwrap();
+                    Some(balances.get(&owner).cloned().unwrap_or(0).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    #[test]
+    fn deposit_and_redeem_work() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 1000);
+
+        let mut vault = Vault::deploy_mock(token);
+        assert_eq!(vault.deposit(0, alice), Err(VaultError::ZeroAssets));
+        assert_eq!(vault.deposit(1000, alice), Ok(1_000_000));
+        assert_eq!(vault.balance_of(alice), 1_000_000);
+        assert_eq!(vault.total_assets(), 1000);
+        assert_eq!(balances.borrow()[&alice], 0);
+        assert_eq!(vault.redeem(1_000_001, alice), Err(VaultError::InsufficientShares));
+        assert_eq!(vault.redeem(1_000_000, alice), Ok(1000));
+        assert_eq!(vault.total_supply(), 0);
+        assert_eq!(balances.borrow()[&alice], 1000);
+    }
+
+    #[test]
+    fn shares_grow_with_the_assets() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 1000);
+
+        let mut vault = Vault::deploy_mock(token);
+        assert_eq!(vault.deposit(1000, alice), Ok(1_000_000));
+        // The vault earns 1000 tokens of yield
+        balances.borrow_mut().insert(env::address(), 2000);
+        // The virtual share keeps a tiny part of the yield
+        assert_eq!(vault.max_withdraw(alice), Ok(1999));
+        assert_eq!(vault.withdraw(1999, alice), Ok(1_000_000));
+        assert_eq!(balances.borrow()[&alice], 1999);
+    }
+
+    #[test]
+    fn conversions_round_in_favor_of_the_vault() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 1000);
+
+        let mut vault = Vault::deploy_mock(token);
+        assert_eq!(vault.deposit(1000, alice), Ok(1_000_000));
+        balances.borrow_mut().insert(env::address(), 2000);
+        // A single token is worth 500.25 shares
+        assert_eq!(vault.preview_deposit(1), Ok(500));
+        assert_eq!(vault.preview_withdraw(1), Ok(501));
+        assert_eq!(vault.preview_redeem(501), Ok(1));
+        assert_eq!(vault.preview_redeem(500), Ok(0));
+        assert_eq!(vault.redeem(500, alice), Err(VaultError::ZeroAssets));
+        assert_eq!(vault.withdraw(1, alice), Ok(501));
+        assert_eq!(vault.balance_of(alice), 999_499);
+    }
+
+    #[test]
+    fn donations_do_not_steal_later_deposits() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 10_000);
+        balances.borrow_mut().insert(bob, 10_001);
+
+        let mut vault = Vault::deploy_mock(token);
+        // Bob deposits a single token and then sends 10,000 tokens to the vault
+        // directly, hoping that Alice's deposit rounds down to zero shares.
+        env::test::set_caller(bob);
+        assert_eq!(vault.deposit(1, bob), Ok(1000));
+        balances.borrow_mut().insert(bob, 0);
+        balances.borrow_mut().insert(env::address(), 10_001);
+        // Tiny deposits fail instead of minting nothing
+        env::test::set_caller(alice);
+        assert_eq!(vault.deposit(1, alice), Err(VaultError::ZeroShares));
+        assert_eq!(balances.borrow()[&alice], 10_000);
+        // Alice still receives her share and loses only a rounding error
+        assert_eq!(vault.deposit(10_000, alice), Ok(1999));
+        assert_eq!(vault.redeem(1999, alice), Ok(9998));
+        // Bob loses half of his donation
+        env::test::set_caller(bob);
+        assert_eq!(vault.redeem(1000, bob), Ok(5002));
+    }
+
+    #[test]
+    fn deposit_fails_if_the_asset_refuses_the_transfer() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+
+        let mut vault = Vault::deploy_mock(token);
+        assert_eq!(vault.deposit(101, alice), Err(VaultError::TransferFailed));
+        assert_eq!(vault.total_supply(), 0);
+    }
+
+    #[test]
+    fn shares_can_be_transferred() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 10);
+
+        let mut vault = Vault::deploy_mock(token);
+        assert_eq!(vault.deposit(10, alice), Ok(10_000));
+        assert_eq!(vault.transfer(bob, 10_001), Err(VaultError::InsufficientShares));
+        assert_eq!(vault.transfer(bob, 4000), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(vault.redeem(4000, bob), Ok(4));
+        assert_eq!(balances.borrow()[&bob], 4);
+    }
+}
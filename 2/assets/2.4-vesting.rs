@@ -0,0 +1,495 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Tokens granted to a beneficiary which vest over time.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Grant {
+    /// The tokens granted in total.
+    amount: Balance,
+    /// The tokens which have already been released.
+    released: Balance,
+    /// The block in which the tokens start to vest.
+    start: BlockNumber,
+    /// The number of blocks after `start` before any tokens can be released.
+    cliff: BlockNumber,
+    /// The number of blocks after `start` until all tokens have vested.
+    duration: BlockNumber,
+    /// Whether the admin can revoke the tokens which have not vested yet.
+    revocable: bool,
+    /// Whether the grant has been revoked.
+    revoked: bool,
+}
+
+/// Events deposited by the vesting contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    GrantCreated {
+        beneficiary: AccountId,
+        amount: Balance,
+    },
+    TokensReleased {
+        beneficiary: AccountId,
+        amount: Balance,
+    },
+    GrantRevoked {
+        beneficiary: AccountId,
+        refund: Balance,
+    },
+}
+
+/// Errors which can occur when calling the vesting contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum VestingError {
+    /// Only the admin can create and revoke grants.
+    NotAdmin,
+    /// The beneficiary already has a grant.
+    GrantExists,
+    /// The beneficiary has no grant.
+    NoGrant,
+    /// The grant is empty, never vests, or has a cliff after its end.
+    InvalidGrant,
+    /// No tokens can be released right now.
+    NothingToRelease,
+    /// The grant cannot be revoked, or has been revoked already.
+    NotRevocable,
+    /// The token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits a vesting event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the tokens of `grant` which have vested by block `now`.
+///
+/// Nothing vests before the cliff. After that, the tokens vest linearly from
+/// `start`, so at the cliff all the tokens of the blocks before it vest at once.
+fn vested_amount(grant: &Grant, now: BlockNumber) -> Balance {
+    if now < grant.start + grant.cliff {
+        return 0
+    }
+    let elapsed = now - grant.start;
+    if grant.revoked || elapsed >= grant.duration {
+        return grant.amount
+    }
+    let (duration, elapsed) = (Balance::from(grant.duration), Balance::from(elapsed));
+    // Split `amount` to avoid overflowing on large grants.
+    grant.amount / duration * elapsed + grant.amount % duration * elapsed / duration
+}
+
+contract! {
+    /// Holds ERC20 tokens granted to beneficiaries, and releases them as they vest.
+    ///
+    /// The admin funds every grant when creating it. Nothing can be released
+    /// before the cliff of a grant, and all of it once its duration has
+    /// passed. Revocable grants can be cut short by the admin, who gets back
+    /// the tokens which have not vested yet.
+    struct TokenVesting {
+        /// The account which creates and revokes grants.
+        admin: storage::Value<AccountId>,
+        /// The ERC20 token which is vested.
+        token: storage::Value<AccountId>,
+        /// The grant of each beneficiary.
+        grants: storage::HashMap<AccountId, Grant>,
+    }
+
+    impl Deploy for TokenVesting {
+        fn deploy(&mut self, token: AccountId) {
+            self.admin.set(env.caller());
+            self.token.set(token);
+        }
+    }
+
+    impl TokenVesting {
+        /// Returns the grant of `beneficiary`, if there is one.
+        pub(external) fn grant_of(&self, beneficiary: AccountId) -> Option<Grant> {
+            let grant = self.grants.get(&beneficiary).cloned();
+            env.println(&format!("TokenVesting::grant_of(beneficiary = {:?}) = {:?}", beneficiary, grant));
+            grant
+        }
+
+        /// Returns the tokens `beneficiary` can release right now.
+        pub(external) fn releasable(&self, beneficiary: AccountId) -> Balance {
+            let releasable = self.releasable_impl(&beneficiary);
+            env.println(&format!("TokenVesting::releasable(beneficiary = {:?}) = {:?}", beneficiary, releasable));
+            releasable
+        }
+
+        /// Grants `amount` tokens to `beneficiary`, which vest over `duration`
+        /// blocks from `start`, but not before `cliff` blocks have passed.
+        ///
+        /// The tokens are transferred from the admin, who must have approved
+        /// the contract to transfer them. Only the admin can create grants.
+        pub(external) fn create_grant(
+            &mut self,
+            beneficiary: AccountId,
+            amount: Balance,
+            start: BlockNumber,
+            cliff: BlockNumber,
+            duration: BlockNumber,
+            revocable: bool,
+        ) -> Result<(), VestingError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(VestingError::NotAdmin)
+            }
+            if self.grants.get(&beneficiary).is_some() {
+                return Err(VestingError::GrantExists)
+            }
+            if amount == 0 || duration == 0 || cliff > duration {
+                return Err(VestingError::InvalidGrant)
+            }
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(admin, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(VestingError::TransferFailed),
+            }
+            self.grants.insert(beneficiary, Grant {
+                amount: amount,
+                released: 0,
+                start: start,
+                cliff: cliff,
+                duration: duration,
+                revocable: revocable,
+                revoked: false
+            });
+            deposit_event(Event::GrantCreated {
+                beneficiary: beneficiary,
+                amount: amount
+            });
+            Ok(())
+        }
+
+        /// Transfers the vested tokens of the caller which have not been
+        /// released yet.
+        ///
+        /// If the token refuses the transfer, the whole call is reverted.
+        pub(external) fn release(&mut self) -> Result<Balance, VestingError> {
+            let beneficiary = env.caller();
+            let mut grant = self.grants.get(&beneficiary).cloned().ok_or(VestingError::NoGrant)?;
+            let amount = vested_amount(&grant, env.block_number()) - grant.released;
+            if amount == 0 {
+                return Err(VestingError::NothingToRelease)
+            }
+            grant.released += amount;
+            self.grants.insert(beneficiary, grant);
+            self.transfer_tokens(beneficiary, amount);
+            deposit_event(Event::TokensReleased {
+                beneficiary: beneficiary,
+                amount: amount
+            });
+            Ok(amount)
+        }
+
+        /// Revokes the grant of `beneficiary`, and pays the tokens which have
+        /// not vested yet back to the admin.
+        ///
+        /// The tokens which have vested stay with the beneficiary, who can
+        /// still release them. Only the admin can revoke grants, and only if
+        /// they were created as revocable.
+        pub(external) fn revoke(&mut self, beneficiary: AccountId) -> Result<Balance, VestingError> {
+            let admin = env.caller();
+            if admin != *self.admin {
+                return Err(VestingError::NotAdmin)
+            }
+            let mut grant = self.grants.get(&beneficiary).cloned().ok_or(VestingError::NoGrant)?;
+            if !grant.revocable || grant.revoked {
+                return Err(VestingError::NotRevocable)
+            }
+            let vested = vested_amount(&grant, env.block_number());
+            let refund = grant.amount - vested;
+            grant.amount = vested;
+            grant.revoked = true;
+            self.grants.insert(beneficiary, grant);
+            if refund > 0 {
+                self.transfer_tokens(admin, refund);
+            }
+            deposit_event(Event::GrantRevoked {
+                beneficiary: beneficiary,
+                refund: refund
+            });
+            Ok(refund)
+        }
+    }
+
+    impl TokenVesting {
+        /// Returns the tokens the AccountId can release right now or 0 if it
+        /// has no grant.
+        fn releasable_impl(&self, beneficiary: &AccountId) -> Balance {
+            match self.grants.get(beneficiary) {
+                Some(grant) => vested_amount(grant, env::block_number()) - grant.released,
+                None => 0,
+            }
+        }
+
+        /// Transfers `amount` tokens from the contract to `to`.
+        fn transfer_tokens(&self, to: AccountId, amount: Balance) {
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(to, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be transferred");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the vesting contract through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Deploys a vesting contract at block 0 for a mock token, and grants Bob
+    /// 1000 of Alice's tokens which vest from block 100 over 400 blocks, with
+    /// a cliff of 100 blocks.
+    fn deploy_mock(revocable: bool) -> (TokenVesting, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 10000);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut vesting = TokenVesting::deploy_mock(token);
+        assert_eq!(vesting.create_grant(bob, 1000, 100, 100, 400, revocable), Ok(()));
+        (vesting, balances)
+    }
+
+    #[test]
+    fn create_grant_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut vesting, balances) = deploy_mock(false);
+        assert_eq!(balances.borrow()[&alice], 9000);
+        assert_eq!(balances.borrow()[&env::address()], 1000);
+        assert_eq!(vesting.grant_of(bob).unwrap().amount, 1000);
+        assert_eq!(vesting.create_grant(bob, 1000, 0, 0, 10, false), Err(VestingError::GrantExists));
+        assert_eq!(vesting.create_grant(charlie, 0, 0, 0, 10, false), Err(VestingError::InvalidGrant));
+        assert_eq!(vesting.create_grant(charlie, 100, 0, 0, 0, false), Err(VestingError::InvalidGrant));
+        assert_eq!(vesting.create_grant(charlie, 100, 0, 11, 10, false), Err(VestingError::InvalidGrant));
+        assert_eq!(vesting.create_grant(charlie, 9001, 0, 0, 10, false), Err(VestingError::TransferFailed));
+        assert_eq!(vesting.grant_of(charlie), None);
+        env::test::set_caller(bob);
+        assert_eq!(vesting.create_grant(charlie, 100, 0, 0, 10, false), Err(VestingError::NotAdmin));
+    }
+
+    #[test]
+    fn nothing_vests_before_the_cliff() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut vesting, _) = deploy_mock(false);
+        env::test::set_caller(bob);
+        env::test::set_block_number(50);
+        assert_eq!(vesting.releasable(bob), 0);
+        assert_eq!(vesting.release(), Err(VestingError::NothingToRelease));
+        // The last block before the cliff
+        env::test::set_block_number(199);
+        assert_eq!(vesting.releasable(bob), 0);
+        // At the cliff, a quarter of the duration has passed
+        env::test::set_block_number(200);
+        assert_eq!(vesting.releasable(bob), 250);
+    }
+
+    #[test]
+    fn tokens_vest_linearly_after_the_cliff() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut vesting, balances) = deploy_mock(false);
+        env::test::set_caller(bob);
+        env::test::set_block_number(200);
+        assert_eq!(vesting.release(), Ok(250));
+        assert_eq!(vesting.release(), Err(VestingError::NothingToRelease));
+        env::test::set_block_number(301);
+        assert_eq!(vesting.releasable(bob), 252);
+        assert_eq!(vesting.release(), Ok(252));
+        assert_eq!(balances.borrow()[&bob], 502);
+        assert_eq!(vesting.grant_of(bob).unwrap().released, 502);
+    }
+
+    #[test]
+    fn everything_vests_at_the_end() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut vesting, balances) = deploy_mock(false);
+        env::test::set_caller(bob);
+        env::test::set_block_number(499);
+        assert_eq!(vesting.releasable(bob), 997);
+        env::test::set_block_number(500);
+        assert_eq!(vesting.releasable(bob), 1000);
+        env::test::set_block_number(10000);
+        assert_eq!(vesting.release(), Ok(1000));
+        assert_eq!(balances.borrow()[&bob], 1000);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+
+    #[test]
+    fn vested_amount_does_not_overflow() {
+        let grant = Grant {
+            amount: Balance::max_value(),
+            released: 0,
+            start: 0,
+            cliff: 0,
+            duration: 2,
+            revocable: false,
+            revoked: false,
+        };
+        assert_eq!(vested_amount(&grant, 1), Balance::max_value() / 2);
+    }
+
+    #[test]
+    fn revoke_refunds_unvested_tokens() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut vesting, balances) = deploy_mock(true);
+        env::test::set_block_number(300);
+        env::test::set_caller(bob);
+        assert_eq!(vesting.revoke(bob), Err(VestingError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(vesting.revoke(bob), Ok(500));
+        assert_eq!(vesting.revoke(bob), Err(VestingError::NotRevocable));
+        assert_eq!(balances.borrow()[&alice], 9500);
+        // Bob keeps what vested before the grant was revoked, and nothing more
+        env::test::set_block_number(500);
+        assert_eq!(vesting.releasable(bob), 500);
+        env::test::set_caller(bob);
+        assert_eq!(vesting.release(), Ok(500));
+        assert_eq!(balances.borrow()[&env::address()], 0);
+    }
+
+    #[test]
+    fn revoke_before_the_cliff_refunds_everything() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut vesting, balances) = deploy_mock(true);
+        env::test::set_block_number(199);
+        assert_eq!(vesting.revoke(bob), Ok(1000));
+        assert_eq!(balances.borrow()[&alice], 10000);
+        env::test::set_block_number(500);
+        assert_eq!(vesting.releasable(bob), 0);
+        env::test::set_caller(bob);
+        assert_eq!(vesting.release(), Err(VestingError::NothingToRelease));
+    }
+
+    #[test]
+    fn irrevocable_grants_cannot_be_revoked() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut vesting, _) = deploy_mock(false);
+        assert_eq!(vesting.revoke(bob), Err(VestingError::NotRevocable));
+        assert_eq!(vesting.revoke(charlie), Err(VestingError::NoGrant));
+    }
+}
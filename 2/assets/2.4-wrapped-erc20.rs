@@ -0,0 +1,440 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `balance_of` message of ERC20 tokens.
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x0F, 0x75, 0x5A, 0x56];
+
+/// The selector of the `transfer` message of ERC20 tokens.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of ERC20 tokens.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the wrapper.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Events deposited by the wrapped token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+    },
+    Wrapped {
+        account: AccountId,
+        value: Balance,
+    },
+    Unwrapped {
+        account: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the wrapped token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc20Error {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+    /// The underlying token refused to move the tokens into the wrapper.
+    TransferFailed,
+}
+
+/// Deposits a wrapped token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+contract! {
+    /// An ERC20 token which wraps another ERC20 token 1:1.
+    ///
+    /// Every wrapped token is backed by one token of the underlying ERC20 held
+    /// by this contract. Wrapping lets the wrapped token add behavior the
+    /// underlying token lacks, without touching the underlying token itself.
+    struct WrappedErc20 {
+        /// The ERC20 token which is wrapped.
+        underlying: storage::Value<AccountId>,
+        /// The total supply, which equals the underlying tokens held for holders.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+    }
+
+    impl Deploy for WrappedErc20 {
+        fn deploy(&mut self, underlying: AccountId) {
+            self.underlying.set(underlying);
+            self.total_supply.set(0);
+        }
+    }
+
+    impl WrappedErc20 {
+        /// Returns the ERC20 token which is wrapped.
+        pub(external) fn underlying(&self) -> AccountId {
+            let underlying = *self.underlying;
+            env.println(&format!("WrappedErc20::underlying = {:?}", underlying));
+            underlying
+        }
+
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("WrappedErc20::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("WrappedErc20::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the amount of tokens that an owner allowed to a spender.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            env.println(&format!(
+                "WrappedErc20::allowance(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, allowance
+            ));
+            allowance
+        }
+
+        /// Pulls `value` underlying tokens of the caller into the wrapper and
+        /// mints a wrapped token for every underlying token received.
+        ///
+        /// The caller has to approve the wrapper to spend `value` underlying
+        /// tokens first. If the underlying token takes a fee on transfers, fewer
+        /// wrapped tokens than `value` are minted.
+        pub(external) fn wrap(&mut self, value: Balance) -> Result<(), Erc20Error> {
+            let account = env.caller();
+            let underlying = *self.underlying;
+            let held = self.underlying_held();
+            // The token's error is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(underlying, TRANSFER_FROM_SELECTOR, &(account, env.address(), value).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(Erc20Error::TransferFailed),
+            }
+            let received = self.underlying_held().saturating_sub(held);
+            let balance = self.balance_of_or_zero(&account);
+            self.balances.insert(account, balance + received);
+            self.total_supply += received;
+            deposit_event(Event::Wrapped {
+                account: account,
+                value: received
+            });
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(account),
+                value: received
+            });
+            Ok(())
+        }
+
+        /// Burns `value` wrapped tokens of the caller and sends the same amount
+        /// of underlying tokens to the caller.
+        ///
+        /// If the underlying token refuses the transfer, the whole call is reverted.
+        pub(external) fn unwrap(&mut self, value: Balance) -> Result<(), Erc20Error> {
+            let account = env.caller();
+            let balance = self.balance_of_or_zero(&account);
+            if balance < value {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            self.balances.insert(account, balance - value);
+            self.total_supply -= value;
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.underlying, TRANSFER_SELECTOR, &(account, value).encode());
+            assert_eq!(result, Some(Ok(())), "the underlying tokens could not be sent");
+            deposit_event(Event::Unwrapped {
+                account: account,
+                value: value
+            });
+            deposit_event(Event::Transfer {
+                from: Some(account),
+                to: None,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers token from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.transfer_impl(env.caller(), to, value)
+        }
+
+        /// Approve the passed AccountId to spend the specified amount of tokens
+        /// on the behalf of the message's sender.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let owner = env.caller();
+            self.allowances.insert((owner, spender), value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfer tokens from one AccountId to another.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let allowance = self.allowance_or_zero(&from, &env.caller());
+            if allowance < value {
+                return Err(Erc20Error::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.allowances.insert((from, env.caller()), allowance - value);
+            Ok(())
+        }
+    }
+
+    impl WrappedErc20 {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            *self.allowances.get(&(*owner, *spender)).unwrap_or(&0)
+        }
+
+        /// Asks the underlying token how many tokens the wrapper holds.
+        fn underlying_held(&self) -> Balance {
+            call_contract(*self.underlying, BALANCE_OF_SELECTOR, &env::address().encode())
+                .expect("the underlying token reports the balance of the wrapper")
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the wrapper through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// A second ERC20 token living next to the wrapper, which the tests use as
+    /// the underlying token.
+    ///
+    /// The test environment runs a single contract, so calls to this token
+    /// are dispatched by `call_mock_contract`. Calls made by the wrapper have
+    /// the address of the wrapper as their caller, like on-chain.
+    #[derive(Default)]
+    struct UnderlyingToken {
+        balances: HashMap<AccountId, Balance>,
+        allowances: HashMap<(AccountId, AccountId), Balance>,
+        /// The share of every transfer the token burns, in percent.
+        burn_percent: Balance,
+    }
+
+    impl UnderlyingToken {
+        fn balance_of(&self, owner: &AccountId) -> Balance {
+            *self.balances.get(owner).unwrap_or(&0)
+        }
+
+        fn approve(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            self.allowances.insert((owner, spender), value);
+        }
+
+        fn transfer(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), u8> {
+            let balance_from = self.balance_of(&from);
+            if balance_from < value {
+                return Err(0)
+            }
+            self.balances.insert(from, balance_from - value);
+            let received = value - value * self.burn_percent / 100;
+            *self.balances.entry(to).or_insert(0) += received;
+            Ok(())
+        }
+
+        fn transfer_from(&mut self, spender: AccountId, from: AccountId, to: AccountId, value: Balance) -> Result<Balance, u8> {
+            let allowance = *self.allowances.get(&(from, spender)).unwrap_or(&0);
+            if allowance < value {
+                return Err(1)
+            }
+            self.transfer(from, to, value)?;
+            self.allowances.insert((from, spender), allowance - value);
+            Ok(allowance - value)
+        }
+
+        /// Handles a call made by `caller` with `selector` and `input`.
+        fn call(&mut self, caller: AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+            match selector {
+                BALANCE_OF_SELECTOR => {
+                    let owner = AccountId::decode(&mut &input[..])?;
+                    Some(self.balance_of(&owner).encode())
+                }
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..])?;
+                    Some(self.transfer(caller, to, value).encode())
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..])?;
+                    Some(self.transfer_from(caller, from, to, value).encode())
+                }
+                _ => None,
+            }
+        }
+    }
+
+    /// Deploys `token` at `account` and returns a handle to call it directly.
+    fn deploy_underlying(account: AccountId, token: UnderlyingToken) -> Rc<RefCell<UnderlyingToken>> {
+        let token = Rc::new(RefCell::new(token));
+        let deployed = token.clone();
+        register_contract(account, move |selector, input| {
+            deployed.borrow_mut().call(env::address(), selector, input)
+        });
+        token
+    }
+
+    #[test]
+    fn wrap_and_unwrap_work() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+        let underlying = deploy_underlying(token, UnderlyingToken::default());
+        underlying.borrow_mut().balances.insert(alice, 100);
+
+        let mut wrapped = WrappedErc20::deploy_mock(token);
+        assert_eq!(wrapped.underlying(), token);
+        // Alice has to approve the wrapper first
+        assert_eq!(wrapped.wrap(60), Err(Erc20Error::TransferFailed));
+        underlying.borrow_mut().approve(alice, env::address(), 60);
+        assert_eq!(wrapped.wrap(60), Ok(()));
+        assert_eq!(wrapped.balance_of(alice), 60);
+        assert_eq!(wrapped.total_supply(), 60);
+        assert_eq!(underlying.borrow().balance_of(&alice), 40);
+        assert_eq!(underlying.borrow().balance_of(&env::address()), 60);
+        // Wrapped tokens move around on their own
+        assert_eq!(wrapped.transfer(bob, 25), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(wrapped.unwrap(26), Err(Erc20Error::InsufficientBalance));
+        assert_eq!(wrapped.unwrap(25), Ok(()));
+        assert_eq!(wrapped.total_supply(), 35);
+        assert_eq!(underlying.borrow().balance_of(&bob), 25);
+        assert_eq!(underlying.borrow().balance_of(&env::address()), 35);
+    }
+
+    #[test]
+    fn wrap_mints_what_was_received() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        // The underlying token burns 10% of every transfer
+        let underlying = deploy_underlying(token, UnderlyingToken { burn_percent: 10, ..Default::default() });
+        underlying.borrow_mut().balances.insert(alice, 100);
+        underlying.borrow_mut().approve(alice, env::address(), 100);
+
+        let mut wrapped = WrappedErc20::deploy_mock(token);
+        assert_eq!(wrapped.wrap(100), Ok(()));
+        // Every wrapped token stays backed
+        assert_eq!(wrapped.balance_of(alice), 90);
+        assert_eq!(underlying.borrow().balance_of(&env::address()), 90);
+    }
+
+    #[test]
+    #[should_panic(expected = "the underlying tokens could not be sent")]
+    fn unwrap_reverts_if_the_underlying_tokens_cannot_be_sent() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        let underlying = deploy_underlying(token, UnderlyingToken::default());
+        underlying.borrow_mut().balances.insert(alice, 100);
+        underlying.borrow_mut().approve(alice, env::address(), 100);
+
+        let mut wrapped = WrappedErc20::deploy_mock(token);
+        assert_eq!(wrapped.wrap(100), Ok(()));
+        // The underlying tokens disappear from the wrapper
+        underlying.borrow_mut().balances.insert(env::address(), 0);
+        let _ = wrapped.unwrap(100);
+    }
+}
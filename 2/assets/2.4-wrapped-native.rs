@@ -0,0 +1,321 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the wrapped native token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        value: Balance,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        value: Balance,
+    },
+    Deposit {
+        account: AccountId,
+        value: Balance,
+    },
+    Withdrawal {
+        account: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the wrapped native token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc20Error {
+    /// The account does not hold enough tokens.
+    InsufficientBalance,
+    /// The caller has not been allowed to spend enough tokens of the owner.
+    InsufficientAllowance,
+}
+
+/// Deposits a wrapped native token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+contract! {
+    /// An ERC20 token which wraps the native balance of the chain 1:1.
+    ///
+    /// Every token is backed by exactly one unit of native balance held by this
+    /// contract, so unlike our main ERC20 token there is no initial supply, no
+    /// minting and no fees.
+    struct WrappedNative {
+        /// The total supply, which equals the native balance held for depositors.
+        total_supply: storage::Value<Balance>,
+        /// The balance of each user.
+        balances: storage::HashMap<AccountId, Balance>,
+        /// Balances that are spendable by non-owners: (owner, spender) -> allowed
+        allowances: storage::HashMap<(AccountId, AccountId), Balance>,
+    }
+
+    impl Deploy for WrappedNative {
+        fn deploy(&mut self) {
+            self.total_supply.set(0);
+        }
+    }
+
+    impl WrappedNative {
+        /// Returns the total number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("WrappedNative::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the balance of the given AccountId.
+        pub(external) fn balance_of(&self, owner: AccountId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("WrappedNative::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the amount of tokens that an owner allowed to a spender.
+        pub(external) fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &spender);
+            env.println(&format!(
+                "WrappedNative::allowance(owner = {:?}, spender = {:?}) = {:?}",
+                owner, spender, allowance
+            ));
+            allowance
+        }
+
+        /// Mints one token to the caller for every unit of native balance sent
+        /// along with the call.
+        pub(external) fn deposit(&mut self) {
+            let account = env.caller();
+            let value = value_transferred();
+            let balance = self.balance_of_or_zero(&account);
+            self.balances.insert(account, balance + value);
+            self.total_supply += value;
+            deposit_event(Event::Deposit {
+                account: account,
+                value: value
+            });
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(account),
+                value: value
+            });
+        }
+
+        /// Burns `value` tokens of the caller and pays out the same amount of
+        /// native balance to the caller.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn withdraw(&mut self, value: Balance) -> Result<(), Erc20Error> {
+            let account = env.caller();
+            let balance = self.balance_of_or_zero(&account);
+            if balance < value {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            self.balances.insert(account, balance - value);
+            self.total_supply -= value;
+            assert!(transfer_native(account, value), "the payout failed");
+            deposit_event(Event::Withdrawal {
+                account: account,
+                value: value
+            });
+            deposit_event(Event::Transfer {
+                from: Some(account),
+                to: None,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers token from the sender to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            self.transfer_impl(env.caller(), to, value)
+        }
+
+        /// Approve the passed AccountId to spend the specified amount of tokens
+        /// on the behalf of the message's sender.
+        pub(external) fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let owner = env.caller();
+            self.allowances.insert((owner, spender), value);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfer tokens from one AccountId to another.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let allowance = self.allowance_or_zero(&from, &env.caller());
+            if allowance < value {
+                return Err(Erc20Error::InsufficientAllowance)
+            }
+            self.transfer_impl(from, to, value)?;
+            self.allowances.insert((from, env.caller()), allowance - value);
+            Ok(())
+        }
+    }
+
+    impl WrappedNative {
+        /// Returns the balance of the AccountId or 0 if there is no balance.
+        fn balance_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.balances.get(of).unwrap_or(&0)
+        }
+
+        /// Returns the allowance or 0 of there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            *self.allowances.get(&(*owner, *spender)).unwrap_or(&0)
+        }
+
+        /// Transfers token from a specified AccountId to another AccountId.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<(), Erc20Error> {
+            let balance_from = self.balance_of_or_zero(&from);
+            if balance_from < value {
+                return Err(Erc20Error::InsufficientBalance)
+            }
+            self.balances.insert(from, balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.balances.insert(to, balance_to + value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                value: value
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    #[test]
+    fn deposit_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut wrapped = WrappedNative::deploy_mock();
+        // Alice sends 100 units of native balance along with `deposit`
+        set_value_transferred(100);
+        wrapped.deposit();
+        assert_eq!(wrapped.balance_of(alice), 100);
+        assert_eq!(wrapped.total_supply(), 100);
+        // A second deposit adds to her balance
+        set_value_transferred(20);
+        wrapped.deposit();
+        assert_eq!(wrapped.balance_of(alice), 120);
+        assert_eq!(wrapped.total_supply(), 120);
+    }
+
+    #[test]
+    fn withdraw_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut wrapped = WrappedNative::deploy_mock();
+        set_value_transferred(100);
+        wrapped.deposit();
+        set_value_transferred(0);
+        // Wrapped tokens can be moved around like any other token
+        assert_eq!(wrapped.transfer(bob, 30), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(wrapped.withdraw(30), Ok(()));
+        assert_eq!(wrapped.balance_of(bob), 0);
+        assert_eq!(wrapped.total_supply(), 70);
+        // Bob has been paid out in native balance
+        assert_eq!(payouts(), vec![(bob, 30)]);
+    }
+
+    #[test]
+    fn withdraw_fails_on_insufficient_balance() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut wrapped = WrappedNative::deploy_mock();
+        set_value_transferred(100);
+        wrapped.deposit();
+        set_value_transferred(0);
+        assert_eq!(wrapped.withdraw(101), Err(Erc20Error::InsufficientBalance));
+        // Nothing has been burned or paid out
+        assert_eq!(wrapped.balance_of(alice), 100);
+        assert_eq!(wrapped.total_supply(), 100);
+        assert_eq!(payouts(), vec![]);
+    }
+}
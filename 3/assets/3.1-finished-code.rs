@@ -0,0 +1,349 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a token.
+type TokenId = u32;
+
+/// Events deposited by the ERC721 token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        id: TokenId,
+    },
+    Approval {
+        owner: AccountId,
+        spender: AccountId,
+        id: TokenId,
+    },
+    ApprovalForAll {
+        owner: AccountId,
+        operator: AccountId,
+        approved: bool,
+    },
+}
+
+/// Errors which can occur when calling the ERC721 token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc721Error {
+    /// There is no token with the given id.
+    TokenNotFound,
+    /// A token with the given id already exists.
+    TokenExists,
+    /// The account does not own the token.
+    NotOwner,
+    /// The caller is neither the owner of the token nor approved to move it.
+    NotApproved,
+}
+
+/// Deposits an ERC721 token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// The storage items for a typical ERC721 token implementation.
+    struct Erc721 {
+        /// The owner of each token.
+        token_owner: storage::HashMap<TokenId, AccountId>,
+        /// The account approved to move each token, if any.
+        token_approvals: storage::HashMap<TokenId, AccountId>,
+        /// The number of tokens owned by each account.
+        owned_tokens_count: storage::HashMap<AccountId, u32>,
+        /// Operators which may move all tokens of an owner: (owner, operator) -> approved
+        operator_approvals: storage::HashMap<(AccountId, AccountId), bool>,
+    }
+
+    impl Deploy for Erc721 {
+        fn deploy(&mut self) {}
+    }
+
+    impl Erc721 {
+        /// Returns the owner of the token `id`, or `None` if it does not exist.
+        pub(external) fn owner_of(&self, id: TokenId) -> Option<AccountId> {
+            let owner = self.owner_of_or_none(id);
+            env.println(&format!("Erc721::owner_of(id = {:?}) = {:?}", id, owner));
+            owner
+        }
+
+        /// Returns the number of tokens owned by `owner`.
+        pub(external) fn balance_of(&self, owner: AccountId) -> u32 {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Erc721::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the account approved to move the token `id`, if any.
+        pub(external) fn get_approved(&self, id: TokenId) -> Option<AccountId> {
+            let approved = self.token_approvals.get(&id).cloned();
+            env.println(&format!("Erc721::get_approved(id = {:?}) = {:?}", id, approved));
+            approved
+        }
+
+        /// Returns `true` if `operator` may move all tokens of `owner`.
+        pub(external) fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            let approved = self.is_approved_for_all_or_false(&owner, &operator);
+            env.println(&format!(
+                "Erc721::is_approved_for_all(owner = {:?}, operator = {:?}) = {:?}",
+                owner, operator, approved
+            ));
+            approved
+        }
+
+        /// Creates the token `id` and gives it to the caller.
+        pub(external) fn mint(&mut self, id: TokenId) -> Result<(), Erc721Error> {
+            if self.token_owner.get(&id).is_some() {
+                return Err(Erc721Error::TokenExists)
+            }
+            let caller = env.caller();
+            self.token_owner.insert(id, caller);
+            let balance = self.balance_of_or_zero(&caller);
+            self.owned_tokens_count.insert(caller, balance + 1);
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(caller),
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Transfers the token `id` from the caller to the `to` AccountId.
+        pub(external) fn transfer(&mut self, to: AccountId, id: TokenId) -> Result<(), Erc721Error> {
+            let owner = self.owner_of_or_none(id).ok_or(Erc721Error::TokenNotFound)?;
+            if owner != env.caller() {
+                return Err(Erc721Error::NotOwner)
+            }
+            self.transfer_impl(owner, to, id)
+        }
+
+        /// Approves `spender` to move the token `id` on behalf of its owner.
+        ///
+        /// Only one account can be approved per token, so this replaces any
+        /// earlier approval. The owner and its operators can approve.
+        pub(external) fn approve(&mut self, spender: AccountId, id: TokenId) -> Result<(), Erc721Error> {
+            let owner = self.owner_of_or_none(id).ok_or(Erc721Error::TokenNotFound)?;
+            let caller = env.caller();
+            if caller != owner && !self.is_approved_for_all_or_false(&owner, &caller) {
+                return Err(Erc721Error::NotApproved)
+            }
+            self.token_approvals.insert(id, spender);
+            deposit_event(Event::Approval {
+                owner: owner,
+                spender: spender,
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Allows or disallows `operator` to move all tokens of the caller.
+        pub(external) fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> Result<(), Erc721Error> {
+            let owner = env.caller();
+            if approved {
+                self.operator_approvals.insert((owner, operator), true);
+            } else {
+                self.operator_approvals.remove(&(owner, operator));
+            }
+            deposit_event(Event::ApprovalForAll {
+                owner: owner,
+                operator: operator,
+                approved: approved
+            });
+            Ok(())
+        }
+
+        /// Transfers the token `id` from `from` to `to` on behalf of `from`.
+        ///
+        /// The caller has to be the owner of the token, approved for the token
+        /// or an operator of the owner.
+        pub(external) fn transfer_from(&mut self, from: AccountId, to: AccountId, id: TokenId) -> Result<(), Erc721Error> {
+            let owner = self.owner_of_or_none(id).ok_or(Erc721Error::TokenNotFound)?;
+            if owner != from {
+                return Err(Erc721Error::NotOwner)
+            }
+            if !self.is_approved_or_owner(&env.caller(), id, &owner) {
+                return Err(Erc721Error::NotApproved)
+            }
+            self.transfer_impl(from, to, id)
+        }
+    }
+
+    impl Erc721 {
+        /// Returns the owner of the token or `None` if it does not exist.
+        fn owner_of_or_none(&self, id: TokenId) -> Option<AccountId> {
+            self.token_owner.get(&id).cloned()
+        }
+
+        /// Returns the number of tokens of the AccountId or 0 if it owns none.
+        fn balance_of_or_zero(&self, of: &AccountId) -> u32 {
+            let balance = self.owned_tokens_count.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns `true` if `operator` may move all tokens of `owner`, or `false` otherwise.
+        fn is_approved_for_all_or_false(&self, owner: &AccountId, operator: &AccountId) -> bool {
+            let approved = self.operator_approvals.get(&(*owner, *operator)).unwrap_or(&false);
+            *approved
+        }
+
+        /// Returns `true` if `spender` may move the token `id` owned by `owner`.
+        fn is_approved_or_owner(&self, spender: &AccountId, id: TokenId, owner: &AccountId) -> bool {
+            *spender == *owner
+                || self.token_approvals.get(&id) == Some(spender)
+                || self.is_approved_for_all_or_false(owner, spender)
+        }
+
+        /// Moves the token `id` from `from` to `to` without any authorization checks.
+        ///
+        /// The approval of the token is cleared, so that the previous owner's
+        /// approval cannot be used to take it from the new owner.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, id: TokenId) -> Result<(), Erc721Error> {
+            self.token_approvals.remove(&id);
+            let balance_from = self.balance_of_or_zero(&from);
+            self.owned_tokens_count.insert(from, balance_from - 1);
+            let balance_to = self.balance_of_or_zero(&to);
+            self.owned_tokens_count.insert(to, balance_to + 1);
+            self.token_owner.insert(id, to);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                id: id
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns the events deposited so far, in order.
+    fn emitted_events() -> Vec<Event> {
+        env::test::emitted_events()
+            .map(|event| Event::decode(&mut &event.data[..]).expect("a valid Event"))
+            .collect()
+    }
+
+    #[test]
+    fn mint_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc721 = Erc721::deploy_mock();
+        assert_eq!(erc721.owner_of(1), None);
+        assert_eq!(erc721.balance_of(alice), 0);
+        // Alice mints two tokens
+        assert_eq!(erc721.mint(1), Ok(()));
+        assert_eq!(erc721.mint(2), Ok(()));
+        assert_eq!(erc721.owner_of(1), Some(alice));
+        assert_eq!(erc721.balance_of(alice), 2);
+        assert_eq!(emitted_events(), vec![
+            Event::Transfer { from: None, to: Some(alice), id: 1 },
+            Event::Transfer { from: None, to: Some(alice), id: 2 },
+        ]);
+    }
+
+    #[test]
+    fn mint_fails_for_existing_tokens() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc721 = Erc721::deploy_mock();
+        assert_eq!(erc721.mint(1), Ok(()));
+        // Bob cannot mint the token Alice already owns
+        env::test::set_caller(bob);
+        assert_eq!(erc721.mint(1), Err(Erc721Error::TokenExists));
+        assert_eq!(erc721.owner_of(1), Some(alice));
+        assert_eq!(erc721.balance_of(bob), 0);
+    }
+
+    #[test]
+    fn transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc721 = Erc721::deploy_mock();
+        assert_eq!(erc721.mint(1), Ok(()));
+        // Alice cannot transfer a token which does not exist
+        assert_eq!(erc721.transfer(bob, 2), Err(Erc721Error::TokenNotFound));
+        assert_eq!(erc721.transfer(bob, 1), Ok(()));
+        assert_eq!(erc721.owner_of(1), Some(bob));
+        assert_eq!(erc721.balance_of(alice), 0);
+        assert_eq!(erc721.balance_of(bob), 1);
+        // The token is Bob's now, so Alice cannot transfer it again
+        assert_eq!(erc721.transfer(alice, 1), Err(Erc721Error::NotOwner));
+    }
+
+    #[test]
+    fn transfer_from_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc721 = Erc721::deploy_mock();
+        assert_eq!(erc721.mint(1), Ok(()));
+        // Bob has not been approved yet
+        env::test::set_caller(bob);
+        assert_eq!(erc721.transfer_from(alice, charlie, 1), Err(Erc721Error::NotApproved));
+        // Only the owner can approve Bob
+        assert_eq!(erc721.approve(bob, 1), Err(Erc721Error::NotApproved));
+        env::test::set_caller(alice);
+        assert_eq!(erc721.approve(bob, 1), Ok(()));
+        assert_eq!(erc721.get_approved(1), Some(bob));
+        // `from` has to be the owner of the token
+        env::test::set_caller(bob);
+        assert_eq!(erc721.transfer_from(charlie, bob, 1), Err(Erc721Error::NotOwner));
+        assert_eq!(erc721.transfer_from(alice, charlie, 1), Ok(()));
+        assert_eq!(erc721.owner_of(1), Some(charlie));
+        // The approval has been cleared by the transfer
+        assert_eq!(erc721.get_approved(1), None);
+        assert_eq!(erc721.transfer_from(charlie, bob, 1), Err(Erc721Error::NotApproved));
+    }
+
+    #[test]
+    fn operator_approvals_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc721 = Erc721::deploy_mock();
+        assert_eq!(erc721.mint(1), Ok(()));
+        assert_eq!(erc721.mint(2), Ok(()));
+        // Alice makes Bob an operator of all her tokens
+        assert_eq!(erc721.set_approval_for_all(bob, true), Ok(()));
+        assert_eq!(erc721.is_approved_for_all(alice, bob), true);
+        env::test::set_caller(bob);
+        assert_eq!(erc721.transfer_from(alice, charlie, 1), Ok(()));
+        // Operators can approve others to move single tokens
+        assert_eq!(erc721.approve(charlie, 2), Ok(()));
+        assert_eq!(erc721.get_approved(2), Some(charlie));
+        // Once Alice revokes the operator, Bob cannot move her tokens anymore
+        env::test::set_caller(alice);
+        assert_eq!(erc721.set_approval_for_all(bob, false), Ok(()));
+        assert_eq!(erc721.is_approved_for_all(alice, bob), false);
+        env::test::set_caller(bob);
+        assert_eq!(erc721.transfer_from(alice, bob, 2), Err(Erc721Error::NotApproved));
+        assert_eq!(erc721.owner_of(2), Some(alice));
+        assert_eq!(erc721.balance_of(alice), 1);
+        assert_eq!(erc721.balance_of(charlie), 1);
+    }
+}
@@ -0,0 +1,427 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        string::String,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a token, which PSP34 lets every collection choose the
+/// type of.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+enum Id {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Bytes(Vec<u8>),
+}
+
+/// Events deposited by the PSP34 token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        id: Id,
+    },
+    Approval {
+        owner: AccountId,
+        operator: AccountId,
+        id: Option<Id>,
+        approved: bool,
+    },
+    AttributeSet {
+        id: Id,
+        key: Vec<u8>,
+        data: Vec<u8>,
+    },
+}
+
+/// The errors defined by the PSP34 standard.
+///
+/// The order of the variants is part of the standard, since callers decode
+/// errors by their index.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+enum PSP34Error {
+    /// An error which is specific to the implementation of the token.
+    Custom(String),
+    /// The caller tried to approve itself.
+    SelfApprove,
+    /// The caller is neither the owner of the token nor approved to move it.
+    NotApproved,
+    /// A token with the given id already exists.
+    TokenExists,
+    /// There is no token with the given id.
+    TokenNotExists,
+    /// The recipient is a contract which rejected the token.
+    SafeTransferCheckFailed(String),
+}
+
+/// Deposits a PSP34 token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// An NFT collection which follows the PSP34 standard of the ink! ecosystem,
+    /// including its metadata, mintable and burnable extensions.
+    struct Psp34 {
+        /// The account which may mint tokens and set their attributes.
+        owner: storage::Value<AccountId>,
+        /// The owner of each token.
+        token_owner: storage::HashMap<Id, AccountId>,
+        /// The number of tokens owned by each account.
+        owned_tokens_count: storage::HashMap<AccountId, u32>,
+        /// The approvals of each owner: (owner, operator, id) -> approved
+        ///
+        /// An id of `None` approves the operator for every token of the owner.
+        operator_approvals: storage::HashMap<(AccountId, AccountId, Option<Id>), bool>,
+        /// The number of tokens in existence.
+        total_supply: storage::Value<Balance>,
+        /// The attributes of each token: (id, key) -> data
+        attributes: storage::HashMap<(Id, Vec<u8>), Vec<u8>>,
+    }
+
+    impl Deploy for Psp34 {
+        fn deploy(&mut self) {
+            self.owner.set(env.caller());
+            self.total_supply.set(0);
+        }
+    }
+
+    impl Psp34 {
+        /// Returns the id of the collection, which is the address of the contract.
+        pub(external) fn collection_id(&self) -> Id {
+            let collection_id = Id::Bytes(env.address().encode());
+            env.println(&format!("Psp34::collection_id = {:?}", collection_id));
+            collection_id
+        }
+
+        /// Returns the number of tokens owned by `owner`.
+        pub(external) fn balance_of(&self, owner: AccountId) -> u32 {
+            let balance = self.balance_of_or_zero(&owner);
+            env.println(&format!("Psp34::balance_of(owner = {:?}) = {:?}", owner, balance));
+            balance
+        }
+
+        /// Returns the owner of the token `id`, or `None` if it does not exist.
+        pub(external) fn owner_of(&self, id: Id) -> Option<AccountId> {
+            let owner = self.token_owner.get(&id).cloned();
+            env.println(&format!("Psp34::owner_of(id = {:?}) = {:?}", id, owner));
+            owner
+        }
+
+        /// Returns `true` if `operator` may move the token `id` of `owner`, or
+        /// every token of `owner` if `id` is `None`.
+        pub(external) fn allowance(&self, owner: AccountId, operator: AccountId, id: Option<Id>) -> bool {
+            let allowed = self.allowance_or_false(&owner, &operator, &id);
+            env.println(&format!(
+                "Psp34::allowance(owner = {:?}, operator = {:?}, id = {:?}) = {:?}",
+                owner, operator, id, allowed
+            ));
+            allowed
+        }
+
+        /// Returns the number of tokens in existence.
+        pub(external) fn total_supply(&self) -> Balance {
+            let total_supply = *self.total_supply;
+            env.println(&format!("Psp34::total_supply = {:?}", total_supply));
+            total_supply
+        }
+
+        /// Returns the attribute `key` of the token `id`, if it has been set.
+        pub(external) fn get_attribute(&self, id: Id, key: Vec<u8>) -> Option<Vec<u8>> {
+            let data = self.attributes.get(&(id.clone(), key.clone())).cloned();
+            env.println(&format!("Psp34::get_attribute(id = {:?}, key = {:?}) = {:?}", id, key, data));
+            data
+        }
+
+        /// Allows or disallows `operator` to move the token `id` of the caller,
+        /// or every token of the caller if `id` is `None`.
+        pub(external) fn approve(&mut self, operator: AccountId, id: Option<Id>, approved: bool) -> Result<(), PSP34Error> {
+            let caller = env.caller();
+            if operator == caller {
+                return Err(PSP34Error::SelfApprove)
+            }
+            if let Some(id) = &id {
+                let owner = self.token_owner.get(id).cloned().ok_or(PSP34Error::TokenNotExists)?;
+                if owner != caller {
+                    return Err(PSP34Error::NotApproved)
+                }
+            }
+            if approved {
+                self.operator_approvals.insert((caller, operator, id.clone()), true);
+            } else {
+                self.operator_approvals.remove(&(caller, operator, id.clone()));
+            }
+            deposit_event(Event::Approval {
+                owner: caller,
+                operator: operator,
+                id: id,
+                approved: approved
+            });
+            Ok(())
+        }
+
+        /// Transfers the token `id` to `to`.
+        ///
+        /// PSP34 has no separate `transfer_from`: the caller has to be the owner
+        /// of the token or approved to move it. The approval of the caller for
+        /// the token is used up by the transfer. This token does not use `data`.
+        pub(external) fn transfer(&mut self, to: AccountId, id: Id, data: Vec<u8>) -> Result<(), PSP34Error> {
+            let _ = data;
+            let caller = env.caller();
+            let owner = self.token_owner.get(&id).cloned().ok_or(PSP34Error::TokenNotExists)?;
+            if !self.is_approved_or_owner(&caller, &owner, &id) {
+                return Err(PSP34Error::NotApproved)
+            }
+            self.operator_approvals.remove(&(owner, caller, Some(id.clone())));
+            self.remove_token(owner, &id);
+            self.add_token(to, id.clone());
+            deposit_event(Event::Transfer {
+                from: Some(owner),
+                to: Some(to),
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Creates the token `id` and gives it to `account`.
+        ///
+        /// Only the owner of the collection can mint tokens.
+        pub(external) fn mint(&mut self, account: AccountId, id: Id) -> Result<(), PSP34Error> {
+            self.ensure_owner(&env.caller())?;
+            if self.token_owner.get(&id).is_some() {
+                return Err(PSP34Error::TokenExists)
+            }
+            self.add_token(account, id.clone());
+            self.total_supply += 1;
+            deposit_event(Event::Transfer {
+                from: None,
+                to: Some(account),
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Destroys the token `id` of `account`.
+        ///
+        /// The caller has to be `account` or approved to move the token.
+        pub(external) fn burn(&mut self, account: AccountId, id: Id) -> Result<(), PSP34Error> {
+            let owner = self.token_owner.get(&id).cloned().ok_or(PSP34Error::TokenNotExists)?;
+            if owner != account || !self.is_approved_or_owner(&env.caller(), &owner, &id) {
+                return Err(PSP34Error::NotApproved)
+            }
+            self.remove_token(owner, &id);
+            self.total_supply -= 1;
+            deposit_event(Event::Transfer {
+                from: Some(owner),
+                to: None,
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Sets the attribute `key` of the token `id` to `data`.
+        ///
+        /// Only the owner of the collection can set attributes.
+        pub(external) fn set_attribute(&mut self, id: Id, key: Vec<u8>, data: Vec<u8>) -> Result<(), PSP34Error> {
+            self.ensure_owner(&env.caller())?;
+            if self.token_owner.get(&id).is_none() {
+                return Err(PSP34Error::TokenNotExists)
+            }
+            self.attributes.insert((id.clone(), key.clone()), data.clone());
+            deposit_event(Event::AttributeSet {
+                id: id,
+                key: key,
+                data: data
+            });
+            Ok(())
+        }
+    }
+
+    impl Psp34 {
+        /// Returns the number of tokens of the AccountId or 0 if it owns none.
+        fn balance_of_or_zero(&self, of: &AccountId) -> u32 {
+            let balance = self.owned_tokens_count.get(of).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns `true` if `operator` has been approved for `id`, or `false` otherwise.
+        fn allowance_or_false(&self, owner: &AccountId, operator: &AccountId, id: &Option<Id>) -> bool {
+            let allowed = self.operator_approvals.get(&(*owner, *operator, id.clone())).unwrap_or(&false);
+            *allowed
+        }
+
+        /// Returns `true` if `caller` may move the token `id` owned by `owner`.
+        fn is_approved_or_owner(&self, caller: &AccountId, owner: &AccountId, id: &Id) -> bool {
+            *caller == *owner
+                || self.allowance_or_false(owner, caller, &Some(id.clone()))
+                || self.allowance_or_false(owner, caller, &None)
+        }
+
+        /// Returns an error unless `caller` is the owner of the collection.
+        fn ensure_owner(&self, caller: &AccountId) -> Result<(), PSP34Error> {
+            if *caller != *self.owner {
+                return Err(PSP34Error::Custom(String::from("only the owner can do this")))
+            }
+            Ok(())
+        }
+
+        /// Gives the token `id` to `to`.
+        fn add_token(&mut self, to: AccountId, id: Id) {
+            let balance = self.balance_of_or_zero(&to);
+            self.owned_tokens_count.insert(to, balance + 1);
+            self.token_owner.insert(id, to);
+        }
+
+        /// Takes the token `id` away from its owner `from`.
+        fn remove_token(&mut self, from: AccountId, id: &Id) {
+            let balance = self.balance_of_or_zero(&from);
+            self.owned_tokens_count.insert(from, balance - 1);
+            self.token_owner.remove(id);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn errors_follow_the_standard() {
+        // Callers decode errors by the index of their variant
+        assert_eq!(PSP34Error::Custom(String::from("x")).encode()[0], 0);
+        assert_eq!(PSP34Error::SelfApprove.encode(), vec![1]);
+        assert_eq!(PSP34Error::NotApproved.encode(), vec![2]);
+        assert_eq!(PSP34Error::TokenExists.encode(), vec![3]);
+        assert_eq!(PSP34Error::TokenNotExists.encode(), vec![4]);
+        assert_eq!(PSP34Error::SafeTransferCheckFailed(String::from("x")).encode()[0], 5);
+    }
+
+    #[test]
+    fn mint_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp34 = Psp34::deploy_mock();
+        assert_eq!(psp34.mint(bob, Id::U8(1)), Ok(()));
+        assert_eq!(psp34.mint(bob, Id::Bytes(vec![1, 2, 3])), Ok(()));
+        assert_eq!(psp34.mint(alice, Id::U8(1)), Err(PSP34Error::TokenExists));
+        assert_eq!(psp34.owner_of(Id::U8(1)), Some(bob));
+        assert_eq!(psp34.balance_of(bob), 2);
+        assert_eq!(psp34.total_supply(), 2);
+        // Ids of different types are different tokens
+        assert_eq!(psp34.owner_of(Id::U32(1)), None);
+        // Only the owner of the collection can mint
+        env::test::set_caller(bob);
+        assert_eq!(
+            psp34.mint(bob, Id::U8(2)),
+            Err(PSP34Error::Custom(String::from("only the owner can do this")))
+        );
+    }
+
+    #[test]
+    fn transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp34 = Psp34::deploy_mock();
+        assert_eq!(psp34.mint(alice, Id::U8(1)), Ok(()));
+        assert_eq!(psp34.transfer(bob, Id::U8(2), Vec::new()), Err(PSP34Error::TokenNotExists));
+        assert_eq!(psp34.transfer(bob, Id::U8(1), Vec::new()), Ok(()));
+        assert_eq!(psp34.owner_of(Id::U8(1)), Some(bob));
+        assert_eq!(psp34.balance_of(alice), 0);
+        assert_eq!(psp34.balance_of(bob), 1);
+        assert_eq!(psp34.transfer(alice, Id::U8(1), Vec::new()), Err(PSP34Error::NotApproved));
+    }
+
+    #[test]
+    fn approvals_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp34 = Psp34::deploy_mock();
+        assert_eq!(psp34.mint(alice, Id::U8(1)), Ok(()));
+        assert_eq!(psp34.mint(alice, Id::U8(2)), Ok(()));
+        assert_eq!(psp34.approve(alice, None, true), Err(PSP34Error::SelfApprove));
+        // Bob may move one token, and only once
+        assert_eq!(psp34.approve(bob, Some(Id::U8(1)), true), Ok(()));
+        assert_eq!(psp34.allowance(alice, bob, Some(Id::U8(1))), true);
+        env::test::set_caller(bob);
+        assert_eq!(psp34.approve(charlie, Some(Id::U8(1)), true), Err(PSP34Error::NotApproved));
+        assert_eq!(psp34.transfer(bob, Id::U8(2), Vec::new()), Err(PSP34Error::NotApproved));
+        assert_eq!(psp34.transfer(charlie, Id::U8(1), Vec::new()), Ok(()));
+        assert_eq!(psp34.allowance(alice, bob, Some(Id::U8(1))), false);
+        // Charlie may move all of Alice's tokens
+        env::test::set_caller(alice);
+        assert_eq!(psp34.approve(charlie, None, true), Ok(()));
+        env::test::set_caller(charlie);
+        assert_eq!(psp34.transfer(charlie, Id::U8(2), Vec::new()), Ok(()));
+        assert_eq!(psp34.balance_of(charlie), 2);
+        assert_eq!(psp34.balance_of(alice), 0);
+    }
+
+    #[test]
+    fn burn_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp34 = Psp34::deploy_mock();
+        assert_eq!(psp34.mint(bob, Id::U8(1)), Ok(()));
+        // Alice owns the collection, but not Bob's token
+        assert_eq!(psp34.burn(bob, Id::U8(1)), Err(PSP34Error::NotApproved));
+        env::test::set_caller(bob);
+        assert_eq!(psp34.burn(alice, Id::U8(1)), Err(PSP34Error::NotApproved));
+        assert_eq!(psp34.burn(bob, Id::U8(1)), Ok(()));
+        assert_eq!(psp34.owner_of(Id::U8(1)), None);
+        assert_eq!(psp34.balance_of(bob), 0);
+        assert_eq!(psp34.total_supply(), 0);
+        assert_eq!(psp34.burn(bob, Id::U8(1)), Err(PSP34Error::TokenNotExists));
+    }
+
+    #[test]
+    fn attributes_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp34 = Psp34::deploy_mock();
+        assert_eq!(
+            psp34.set_attribute(Id::U8(1), b"name".to_vec(), b"Sword".to_vec()),
+            Err(PSP34Error::TokenNotExists)
+        );
+        assert_eq!(psp34.mint(bob, Id::U8(1)), Ok(()));
+        assert_eq!(psp34.set_attribute(Id::U8(1), b"name".to_vec(), b"Sword".to_vec()), Ok(()));
+        assert_eq!(psp34.get_attribute(Id::U8(1), b"name".to_vec()), Some(b"Sword".to_vec()));
+        assert_eq!(psp34.get_attribute(Id::U8(1), b"image".to_vec()), None);
+        // Only the owner of the collection sets attributes, not the owner of the token
+        env::test::set_caller(bob);
+        assert_eq!(
+            psp34.set_attribute(Id::U8(1), b"name".to_vec(), b"Stick".to_vec()),
+            Err(PSP34Error::Custom(String::from("only the owner can do this")))
+        );
+        assert_eq!(psp34.collection_id(), Id::Bytes(env::address().encode()));
+    }
+}
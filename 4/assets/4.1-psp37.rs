@@ -0,0 +1,511 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        string::String,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a token type, which PSP37 lets every contract choose the
+/// type of.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+enum Id {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Bytes(Vec<u8>),
+}
+
+/// Events deposited by the PSP37 token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Transfer {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        id: Id,
+        value: Balance,
+    },
+    TransferBatch {
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        ids_amounts: Vec<(Id, Balance)>,
+    },
+    Approval {
+        owner: AccountId,
+        operator: AccountId,
+        id: Option<Id>,
+        value: Balance,
+    },
+}
+
+/// The errors defined by the PSP37 standard.
+///
+/// The order of the variants is part of the standard, since callers decode
+/// errors by their index.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+enum PSP37Error {
+    /// An error which is specific to the implementation of the token.
+    Custom(String),
+    /// The account does not hold enough tokens of the given id.
+    InsufficientBalance,
+    /// The recipient is the zero address.
+    TransferToZeroAddress,
+    /// The caller has not been allowed to move enough tokens of the owner.
+    NotAllowed,
+    /// The recipient is a contract which rejected the tokens.
+    SafeTransferCheckFailed(String),
+}
+
+/// Deposits a PSP37 token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Adds up the amounts of every id in `ids_amounts`, since the same id may
+/// appear several times in a batch.
+fn totals_of(ids_amounts: &[(Id, Balance)]) -> Result<Vec<(Id, Balance)>, PSP37Error> {
+    let mut totals: Vec<(Id, Balance)> = Vec::new();
+    for (id, value) in ids_amounts {
+        match totals.iter_mut().find(|(total_id, _)| total_id == id) {
+            Some((_, total)) => *total = total.checked_add(*value).ok_or(PSP37Error::InsufficientBalance)?,
+            None => totals.push((id.clone(), *value)),
+        }
+    }
+    Ok(totals)
+}
+
+contract! {
+    /// A multi-token contract which follows the PSP37 standard of the ink!
+    /// ecosystem, including its batch and mintable extensions.
+    ///
+    /// Balances and supplies stored under the id `None` are the sums over all
+    /// ids, which is what PSP37 returns when it is asked about no particular id.
+    struct Psp37 {
+        /// The account which may mint tokens.
+        owner: storage::Value<AccountId>,
+        /// The balance of each user for each token id: (owner, id) -> balance
+        balances: storage::HashMap<(AccountId, Option<Id>), Balance>,
+        /// The number of tokens in existence of each id.
+        supply: storage::HashMap<Option<Id>, Balance>,
+        /// Tokens that are movable by operators: (owner, operator, id) -> allowed
+        ///
+        /// An id of `None` approves the operator for every token of the owner.
+        allowances: storage::HashMap<(AccountId, AccountId, Option<Id>), Balance>,
+    }
+
+    impl Deploy for Psp37 {
+        fn deploy(&mut self) {
+            self.owner.set(env.caller());
+        }
+    }
+
+    impl Psp37 {
+        /// Returns the balance of `owner` for the token `id`, or of all tokens
+        /// if `id` is `None`.
+        pub(external) fn balance_of(&self, owner: AccountId, id: Option<Id>) -> Balance {
+            let balance = self.balance_of_or_zero(&owner, &id);
+            env.println(&format!("Psp37::balance_of(owner = {:?}, id = {:?}) = {:?}", owner, id, balance));
+            balance
+        }
+
+        /// Returns the number of tokens in existence of the token `id`, or of all
+        /// tokens if `id` is `None`.
+        pub(external) fn total_supply(&self, id: Option<Id>) -> Balance {
+            let total_supply = *self.supply.get(&id).unwrap_or(&0);
+            env.println(&format!("Psp37::total_supply(id = {:?}) = {:?}", id, total_supply));
+            total_supply
+        }
+
+        /// Returns how many tokens `id` of `owner` that `operator` may move.
+        ///
+        /// Operators which are approved for every token may move all of them.
+        pub(external) fn allowance(&self, owner: AccountId, operator: AccountId, id: Option<Id>) -> Balance {
+            let allowance = self.allowance_or_zero(&owner, &operator, &id);
+            env.println(&format!(
+                "Psp37::allowance(owner = {:?}, operator = {:?}, id = {:?}) = {:?}",
+                owner, operator, id, allowance
+            ));
+            allowance
+        }
+
+        /// Allows `operator` to move `value` tokens `id` of the caller.
+        ///
+        /// With an `id` of `None`, any non-zero `value` allows `operator` to move
+        /// every token of the caller, and a `value` of zero takes this back.
+        pub(external) fn approve(&mut self, operator: AccountId, id: Option<Id>, value: Balance) -> Result<(), PSP37Error> {
+            let owner = env.caller();
+            let value = match id {
+                None if value > 0 => Balance::max_value(),
+                _ => value,
+            };
+            if value == 0 {
+                self.allowances.remove(&(owner, operator, id.clone()));
+            } else {
+                self.allowances.insert((owner, operator, id.clone()), value);
+            }
+            deposit_event(Event::Approval {
+                owner: owner,
+                operator: operator,
+                id: id,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens `id` from the caller to `to`.
+        ///
+        /// PSP37 passes `data` along for recipients which want to know what the
+        /// tokens are for. This token does not use it.
+        pub(external) fn transfer(&mut self, to: AccountId, id: Id, value: Balance, data: Vec<u8>) -> Result<(), PSP37Error> {
+            let _ = data;
+            let from = env.caller();
+            self.ensure_transferable(&from, &from, &[(id.clone(), value)])?;
+            self.transfer_impl(from, to, id.clone(), value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                id: id,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens `id` from `from` to `to`.
+        ///
+        /// The caller has to be `from`, or allowed to move enough of its tokens.
+        pub(external) fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: Id,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), PSP37Error> {
+            let _ = data;
+            let operator = env.caller();
+            self.ensure_transferable(&operator, &from, &[(id.clone(), value)])?;
+            self.spend_allowance(&operator, &from, &id, value);
+            self.transfer_impl(from, to, id.clone(), value);
+            deposit_event(Event::Transfer {
+                from: Some(from),
+                to: Some(to),
+                id: id,
+                value: value
+            });
+            Ok(())
+        }
+
+        /// Transfers the amount of every id in `ids_amounts` from the caller to `to`.
+        ///
+        /// Either all transfers succeed or none of them is applied.
+        pub(external) fn batch_transfer(
+            &mut self,
+            to: AccountId,
+            ids_amounts: Vec<(Id, Balance)>,
+            data: Vec<u8>,
+        ) -> Result<(), PSP37Error> {
+            let from = env.caller();
+            self.batch_transfer_impl(from, from, to, ids_amounts, data)
+        }
+
+        /// Transfers the amount of every id in `ids_amounts` from `from` to `to`.
+        ///
+        /// Either all transfers succeed or none of them is applied.
+        pub(external) fn batch_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            ids_amounts: Vec<(Id, Balance)>,
+            data: Vec<u8>,
+        ) -> Result<(), PSP37Error> {
+            let operator = env.caller();
+            self.batch_transfer_impl(operator, from, to, ids_amounts, data)
+        }
+
+        /// Creates the amount of every id in `ids_amounts` and gives them to `to`.
+        ///
+        /// Only the owner of the contract can mint tokens.
+        pub(external) fn mint(&mut self, to: AccountId, ids_amounts: Vec<(Id, Balance)>) -> Result<(), PSP37Error> {
+            if env.caller() != *self.owner {
+                return Err(PSP37Error::Custom(String::from("only the owner can do this")))
+            }
+            self.ensure_mintable(&to, &ids_amounts)?;
+            for (id, value) in &ids_amounts {
+                for id in [Some(id.clone()), None].iter() {
+                    let balance = self.balance_of_or_zero(&to, id);
+                    self.balances.insert((to, id.clone()), balance + value);
+                    let supply = *self.supply.get(id).unwrap_or(&0);
+                    self.supply.insert(id.clone(), supply + value);
+                }
+            }
+            deposit_event(Event::TransferBatch {
+                from: None,
+                to: Some(to),
+                ids_amounts: ids_amounts
+            });
+            Ok(())
+        }
+    }
+
+    impl Psp37 {
+        /// Returns the balance of the AccountId for the id or 0 if there is no balance.
+        fn balance_of_or_zero(&self, owner: &AccountId, id: &Option<Id>) -> Balance {
+            let balance = self.balances.get(&(*owner, id.clone())).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns the allowance or 0 if there is no allowance.
+        fn allowance_or_zero(&self, owner: &AccountId, operator: &AccountId, id: &Option<Id>) -> Balance {
+            let for_all = self.allowances.get(&(*owner, *operator, None)).unwrap_or(&0);
+            if *for_all > 0 {
+                return *for_all
+            }
+            let allowance = self.allowances.get(&(*owner, *operator, id.clone())).unwrap_or(&0);
+            *allowance
+        }
+
+        /// Returns an error unless `operator` may move the amount of every id in
+        /// `ids_amounts` of `from`, and `from` holds them.
+        fn ensure_transferable(&self, operator: &AccountId, from: &AccountId, ids_amounts: &[(Id, Balance)]) -> Result<(), PSP37Error> {
+            for (id, total) in totals_of(ids_amounts)? {
+                let id = Some(id);
+                if *operator != *from && self.allowance_or_zero(from, operator, &id) < total {
+                    return Err(PSP37Error::NotAllowed)
+                }
+                if self.balance_of_or_zero(from, &id) < total {
+                    return Err(PSP37Error::InsufficientBalance)
+                }
+            }
+            Ok(())
+        }
+
+        /// Uses up `value` of the allowance of `operator` for the tokens `id` of `from`.
+        ///
+        /// Owners and operators approved for every token have nothing to use up.
+        fn spend_allowance(&mut self, operator: &AccountId, from: &AccountId, id: &Id, value: Balance) {
+            let id = Some(id.clone());
+            if *operator == *from || self.allowances.get(&(*from, *operator, None)).is_some() {
+                return
+            }
+            let allowance = self.allowance_or_zero(from, operator, &id);
+            self.allowances.insert((*from, *operator, id), allowance - value);
+        }
+
+        /// Checks and applies a batch transfer made by `operator`.
+        fn batch_transfer_impl(
+            &mut self,
+            operator: AccountId,
+            from: AccountId,
+            to: AccountId,
+            ids_amounts: Vec<(Id, Balance)>,
+            data: Vec<u8>,
+        ) -> Result<(), PSP37Error> {
+            let _ = data;
+            self.ensure_transferable(&operator, &from, &ids_amounts)?;
+            for (id, value) in &ids_amounts {
+                self.spend_allowance(&operator, &from, id, *value);
+                self.transfer_impl(from, to, id.clone(), *value);
+            }
+            deposit_event(Event::TransferBatch {
+                from: Some(from),
+                to: Some(to),
+                ids_amounts: ids_amounts
+            });
+            Ok(())
+        }
+
+        /// Moves `value` tokens `id` from `from` to `to`, along with their share of
+        /// the balances over all ids.
+        ///
+        /// The caller has to make sure that `from` holds enough tokens.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, id: Id, value: Balance) {
+            for id in [Some(id), None].iter() {
+                let balance_from = self.balance_of_or_zero(&from, id);
+                self.balances.insert((from, id.clone()), balance_from - value);
+                let balance_to = self.balance_of_or_zero(&to, id);
+                self.balances.insert((to, id.clone()), balance_to + value);
+            }
+        }
+
+        /// Returns an error if minting `ids_amounts` to `to` would overflow a
+        /// balance or a supply.
+        fn ensure_mintable(&self, to: &AccountId, ids_amounts: &[(Id, Balance)]) -> Result<(), PSP37Error> {
+            let overflow = PSP37Error::Custom(String::from("overflow"));
+            let mut grand_total: Balance = 0;
+            for (id, total) in totals_of(ids_amounts)? {
+                let id = Some(id);
+                grand_total = grand_total.checked_add(total).ok_or(overflow.clone())?;
+                self.balance_of_or_zero(to, &id).checked_add(total).ok_or(overflow.clone())?;
+                self.supply.get(&id).unwrap_or(&0).checked_add(total).ok_or(overflow.clone())?;
+            }
+            self.balance_of_or_zero(to, &None).checked_add(grand_total).ok_or(overflow.clone())?;
+            self.supply.get(&None).unwrap_or(&0).checked_add(grand_total).ok_or(overflow)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    /// Returns the events deposited so far, in order.
+    fn emitted_events() -> Vec<Event> {
+        env::test::emitted_events()
+            .map(|event| Event::decode(&mut &event.data[..]).expect("a valid Event"))
+            .collect()
+    }
+
+    #[test]
+    fn errors_follow_the_standard() {
+        // Callers decode errors by the index of their variant
+        assert_eq!(PSP37Error::Custom(String::from("x")).encode()[0], 0);
+        assert_eq!(PSP37Error::InsufficientBalance.encode(), vec![1]);
+        assert_eq!(PSP37Error::TransferToZeroAddress.encode(), vec![2]);
+        assert_eq!(PSP37Error::NotAllowed.encode(), vec![3]);
+        assert_eq!(PSP37Error::SafeTransferCheckFailed(String::from("x")).encode()[0], 4);
+    }
+
+    #[test]
+    fn mint_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(bob, vec![(Id::U8(0), 1000), (Id::U8(1), 1)]), Ok(()));
+        assert_eq!(psp37.balance_of(bob, Some(Id::U8(0))), 1000);
+        assert_eq!(psp37.balance_of(bob, Some(Id::U8(1))), 1);
+        assert_eq!(psp37.balance_of(bob, None), 1001);
+        assert_eq!(psp37.total_supply(Some(Id::U8(0))), 1000);
+        assert_eq!(psp37.total_supply(None), 1001);
+        // Only the owner of the contract can mint
+        env::test::set_caller(bob);
+        assert_eq!(
+            psp37.mint(bob, vec![(Id::U8(0), 1)]),
+            Err(PSP37Error::Custom(String::from("only the owner can do this")))
+        );
+    }
+
+    #[test]
+    fn transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(alice, vec![(Id::U8(0), 100)]), Ok(()));
+        assert_eq!(psp37.transfer(bob, Id::U8(0), 101, Vec::new()), Err(PSP37Error::InsufficientBalance));
+        assert_eq!(psp37.transfer(bob, Id::U8(0), 30, vec![1, 2, 3]), Ok(()));
+        assert_eq!(psp37.balance_of(alice, Some(Id::U8(0))), 70);
+        assert_eq!(psp37.balance_of(bob, Some(Id::U8(0))), 30);
+        assert_eq!(psp37.balance_of(bob, None), 30);
+        assert_eq!(
+            emitted_events().last(),
+            Some(&Event::Transfer {
+                from: Some(alice),
+                to: Some(bob),
+                id: Id::U8(0),
+                value: 30
+            })
+        );
+    }
+
+    #[test]
+    fn batch_transfer_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(alice, vec![(Id::U8(0), 100), (Id::U8(1), 10)]), Ok(()));
+        let batch = vec![(Id::U8(0), 40), (Id::U8(1), 10), (Id::U8(0), 20)];
+        assert_eq!(psp37.batch_transfer(bob, batch.clone(), Vec::new()), Ok(()));
+        assert_eq!(psp37.balance_of(alice, Some(Id::U8(0))), 40);
+        assert_eq!(psp37.balance_of(bob, Some(Id::U8(0))), 60);
+        assert_eq!(psp37.balance_of(bob, Some(Id::U8(1))), 10);
+        assert_eq!(psp37.balance_of(bob, None), 70);
+        assert_eq!(
+            emitted_events().last(),
+            Some(&Event::TransferBatch {
+                from: Some(alice),
+                to: Some(bob),
+                ids_amounts: batch
+            })
+        );
+    }
+
+    #[test]
+    fn batch_transfer_is_all_or_nothing() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(alice, vec![(Id::U8(0), 100), (Id::U8(1), 10)]), Ok(()));
+        // Each amount is covered on its own, but not the two of id 0 together
+        let batch = vec![(Id::U8(0), 60), (Id::U8(1), 10), (Id::U8(0), 60)];
+        assert_eq!(psp37.batch_transfer(bob, batch, Vec::new()), Err(PSP37Error::InsufficientBalance));
+        assert_eq!(psp37.balance_of(alice, None), 110);
+        assert_eq!(psp37.balance_of(bob, None), 0);
+    }
+
+    #[test]
+    fn approvals_for_one_id_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(alice, vec![(Id::U8(0), 100), (Id::U8(1), 10)]), Ok(()));
+        assert_eq!(psp37.approve(bob, Some(Id::U8(0)), 50), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(psp37.transfer_from(alice, charlie, Id::U8(1), 1, Vec::new()), Err(PSP37Error::NotAllowed));
+        assert_eq!(psp37.transfer_from(alice, charlie, Id::U8(0), 51, Vec::new()), Err(PSP37Error::NotAllowed));
+        assert_eq!(psp37.transfer_from(alice, charlie, Id::U8(0), 20, Vec::new()), Ok(()));
+        assert_eq!(psp37.allowance(alice, bob, Some(Id::U8(0))), 30);
+        // The allowance is used up across the whole batch
+        let batch = vec![(Id::U8(0), 20), (Id::U8(0), 20)];
+        assert_eq!(psp37.batch_transfer_from(alice, charlie, batch, Vec::new()), Err(PSP37Error::NotAllowed));
+        assert_eq!(psp37.batch_transfer_from(alice, charlie, vec![(Id::U8(0), 30)], Vec::new()), Ok(()));
+        assert_eq!(psp37.allowance(alice, bob, Some(Id::U8(0))), 0);
+        assert_eq!(psp37.balance_of(charlie, Some(Id::U8(0))), 50);
+    }
+
+    #[test]
+    fn approvals_for_all_ids_work() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut psp37 = Psp37::deploy_mock();
+        assert_eq!(psp37.mint(alice, vec![(Id::U8(0), 100), (Id::U8(1), 10)]), Ok(()));
+        assert_eq!(psp37.approve(bob, None, 1), Ok(()));
+        assert_eq!(psp37.allowance(alice, bob, Some(Id::U8(1))), Balance::max_value());
+        env::test::set_caller(bob);
+        let batch = vec![(Id::U8(0), 100), (Id::U8(1), 10)];
+        assert_eq!(psp37.batch_transfer_from(alice, bob, batch, Vec::new()), Ok(()));
+        assert_eq!(psp37.balance_of(bob, None), 110);
+        // Approving zero takes the approval back
+        env::test::set_caller(alice);
+        assert_eq!(psp37.approve(bob, None, 0), Ok(()));
+        assert_eq!(psp37.allowance(alice, bob, Some(Id::U8(1))), 0);
+    }
+}
@@ -0,0 +1,555 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+    Input,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a token type.
+type TokenId = u32;
+
+/// Events deposited by the ERC1155 token contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    TransferSingle {
+        operator: AccountId,
+        from: Option<AccountId>,
+        to: Option<AccountId>,
+        id: TokenId,
+        value: Balance,
+    },
+    // ACTION: Create a `TransferBatch` event with:
+    //         * operator: AccountId
+    //         * from: Option<AccountId>
+    //         * to: Option<AccountId>
+    //         * ids: Vec<TokenId>
+    //         * values: Vec<Balance>
+    ApprovalForAll {
+        owner: AccountId,
+        operator: AccountId,
+        approved: bool,
+    },
+}
+
+/// Errors which can occur when calling the ERC1155 token contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Erc1155Error {
+    /// The account does not hold enough tokens of the given id.
+    InsufficientBalance,
+    /// The caller is neither the owner of the tokens nor one of its operators.
+    NotApproved,
+    /// Lists which are passed together have different lengths.
+    LengthMismatch,
+}
+
+/// Deposits an ERC1155 token event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// The selector of the `on_erc1155_received` message of token receivers,
+/// which they also return to acknowledge the tokens.
+const ON_ERC1155_RECEIVED_SELECTOR: [u8; 4] = [0xF2, 0x3A, 0x6E, 0x61];
+
+/// The selector of the `on_erc1155_batch_received` message of token receivers,
+/// which they also return to acknowledge the tokens.
+const ON_ERC1155_BATCH_RECEIVED_SELECTOR: [u8; 4] = [0xBC, 0x19, 0x7C, 0x81];
+
+/// The answer of a recipient to being notified of the tokens it received.
+#[derive(Debug, PartialEq)]
+enum Acknowledgement<T> {
+    /// The recipient is a plain account, which returns nothing when called.
+    NoContract,
+    /// The recipient is a contract which answered with the given value.
+    Contract(T),
+}
+
+impl<T: Decode> Decode for Acknowledgement<T> {
+    fn decode<I: Input>(input: &mut I) -> Option<Self> {
+        let first = match input.read_byte() {
+            Some(first) => first,
+            None => return Some(Acknowledgement::NoContract),
+        };
+        // Put the byte we looked at back in front of the rest of the output.
+        let mut output = Vec::new();
+        output.push(first);
+        let mut buffer = [0u8; 32];
+        loop {
+            match input.read(&mut buffer) {
+                0 => break,
+                len => output.extend_from_slice(&buffer[..len]),
+            }
+        }
+        T::decode(&mut &output[..]).map(Acknowledgement::Contract)
+    }
+}
+
+/// The gas limit of cross-contract calls made by the token.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Reverts the call unless `to` is a plain account or a contract which answers
+/// the call of `selector` with `input` by returning `selector`.
+fn ensure_received(to: AccountId, selector: [u8; 4], input: &[u8]) {
+    let acknowledgement: Option<Acknowledgement<[u8; 4]>> = call_contract(to, selector, input);
+    match acknowledgement {
+        Some(Acknowledgement::NoContract) => (),
+        Some(Acknowledgement::Contract(answer)) if answer == selector => (),
+        _ => panic!("the recipient cannot receive tokens"),
+    }
+}
+
+contract! {
+    /// The storage items for a typical ERC1155 multi-token implementation.
+    ///
+    /// Every token id is a token of its own. An id created with a supply of 1
+    /// behaves like an ERC721 token, larger supplies like ERC20 tokens.
+    struct Erc1155 {
+        /// The number of token ids which have been created, which is also the next id.
+        token_count: storage::Value<TokenId>,
+        /// The balance of each user for each token id: (owner, id) -> balance
+        balances: storage::HashMap<(AccountId, TokenId), Balance>,
+        /// Operators which may move all tokens of an owner: (owner, operator) -> approved
+        operator_approvals: storage::HashMap<(AccountId, AccountId), bool>,
+    }
+
+    impl Deploy for Erc1155 {
+        fn deploy(&mut self) {
+            self.token_count.set(0);
+        }
+    }
+
+    impl Erc1155 {
+        /// Returns the balance of `owner` for the token `id`.
+        pub(external) fn balance_of(&self, owner: AccountId, id: TokenId) -> Balance {
+            let balance = self.balance_of_or_zero(&owner, id);
+            env.println(&format!("Erc1155::balance_of(owner = {:?}, id = {:?}) = {:?}", owner, id, balance));
+            balance
+        }
+
+        /// Returns the balance of each of the `owners` for the token at the same
+        /// position in `ids`.
+        pub(external) fn balance_of_batch(&self, owners: Vec<AccountId>, ids: Vec<TokenId>) -> Result<Vec<Balance>, Erc1155Error> {
+            // ACTION: `if` the lengths of `owners` and `ids` differ, exit early and return
+            //         `Err(Erc1155Error::LengthMismatch)`
+            // ACTION: Collect the `balance_of_or_zero` of each owner and id into `balances`
+            //   HINT: `zip` the iterators of `owners` and `ids`
+            env.println(&format!(
+                "Erc1155::balance_of_batch(owners = {:?}, ids = {:?}) = {:?}",
+                owners, ids, balances
+            ));
+            Ok(balances)
+        }
+
+        /// Returns `true` if `operator` may move all tokens of `owner`.
+        pub(external) fn is_approved_for_all(&self, owner: AccountId, operator: AccountId) -> bool {
+            let approved = self.is_approved_for_all_or_false(&owner, &operator);
+            env.println(&format!(
+                "Erc1155::is_approved_for_all(owner = {:?}, operator = {:?}) = {:?}",
+                owner, operator, approved
+            ));
+            approved
+        }
+
+        /// Creates a new token id with `initial_supply` tokens, which are given
+        /// to the caller, and returns the id.
+        pub(external) fn create(&mut self, initial_supply: Balance) -> TokenId {
+            let id = *self.token_count;
+            self.token_count.set(id + 1);
+            let caller = env.caller();
+            self.balances.insert((caller, id), initial_supply);
+            deposit_event(Event::TransferSingle {
+                operator: caller,
+                from: None,
+                to: Some(caller),
+                id: id,
+                value: initial_supply
+            });
+            id
+        }
+
+        /// Allows or disallows `operator` to move all tokens of the caller.
+        pub(external) fn set_approval_for_all(&mut self, operator: AccountId, approved: bool) -> Result<(), Erc1155Error> {
+            let owner = env.caller();
+            if approved {
+                self.operator_approvals.insert((owner, operator), true);
+            } else {
+                self.operator_approvals.remove(&(owner, operator));
+            }
+            deposit_event(Event::ApprovalForAll {
+                owner: owner,
+                operator: operator,
+                approved: approved
+            });
+            Ok(())
+        }
+
+        /// Transfers `value` tokens of the token `id` from `from` to `to`.
+        ///
+        /// The caller has to be `from` or one of its operators. If `to` is a
+        /// contract, the token calls its
+        /// `on_erc1155_received(operator, from, id, value, data)`, which has to
+        /// return `ON_ERC1155_RECEIVED_SELECTOR`. Otherwise the whole call is reverted.
+        pub(external) fn safe_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            id: TokenId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), Erc1155Error> {
+            let operator = env.caller();
+            self.ensure_approved(&operator, &from)?;
+            if self.balance_of_or_zero(&from, id) < value {
+                return Err(Erc1155Error::InsufficientBalance)
+            }
+            self.transfer_impl(from, to, id, value);
+            deposit_event(Event::TransferSingle {
+                operator: operator,
+                from: Some(from),
+                to: Some(to),
+                id: id,
+                value: value
+            });
+            ensure_received(to, ON_ERC1155_RECEIVED_SELECTOR, &(operator, from, id, value, data).encode());
+            Ok(())
+        }
+
+        /// Transfers `values[i]` tokens of the token `ids[i]` from `from` to `to`
+        /// for every `i`.
+        ///
+        /// Either all transfers succeed or none of them is applied. Contract
+        /// recipients are notified once with
+        /// `on_erc1155_batch_received(operator, from, ids, values, data)`, which
+        /// has to return `ON_ERC1155_BATCH_RECEIVED_SELECTOR`.
+        pub(external) fn safe_batch_transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            ids: Vec<TokenId>,
+            values: Vec<Balance>,
+            data: Vec<u8>,
+        ) -> Result<(), Erc1155Error> {
+            let operator = env.caller();
+            self.ensure_approved(&operator, &from)?;
+            if ids.len() != values.len() {
+                return Err(Erc1155Error::LengthMismatch)
+            }
+            // The same id may appear several times, so add up what is sent of
+            // each id before comparing it with the balance.
+            let mut totals: Vec<(TokenId, Balance)> = Vec::new();
+            for (id, value) in ids.iter().zip(values.iter()) {
+                match totals.iter_mut().find(|(total_id, _)| total_id == id) {
+                    Some((_, total)) => {
+                        *total = total.checked_add(*value).ok_or(Erc1155Error::InsufficientBalance)?
+                    }
+                    None => totals.push((*id, *value)),
+                }
+            }
+            // ACTION: `for` each `(id, total)` in `totals`, exit early with
+            //         `Err(Erc1155Error::InsufficientBalance)` if `from` holds less than `total`
+            // ACTION: Only then call `transfer_impl` for every id and value
+            //   HINT: Checking everything first means no transfer is applied if one fails
+            let input = (operator, from, ids.clone(), values.clone(), data).encode();
+            // ACTION: Deposit the `TransferBatch` event you created
+            // ACTION: Call `ensure_received` with `ON_ERC1155_BATCH_RECEIVED_SELECTOR` and `input`
+            // ACTION: Return `Ok(())`
+        }
+    }
+
+    impl Erc1155 {
+        /// Returns the balance of the AccountId for the token id or 0 if there is no balance.
+        fn balance_of_or_zero(&self, owner: &AccountId, id: TokenId) -> Balance {
+            let balance = self.balances.get(&(*owner, id)).unwrap_or(&0);
+            *balance
+        }
+
+        /// Returns `true` if `operator` may move all tokens of `owner`, or `false` otherwise.
+        fn is_approved_for_all_or_false(&self, owner: &AccountId, operator: &AccountId) -> bool {
+            let approved = self.operator_approvals.get(&(*owner, *operator)).unwrap_or(&false);
+            *approved
+        }
+
+        /// Returns an error unless `operator` is `owner` or one of its operators.
+        fn ensure_approved(&self, operator: &AccountId, owner: &AccountId) -> Result<(), Erc1155Error> {
+            if *operator != *owner && !self.is_approved_for_all_or_false(owner, operator) {
+                return Err(Erc1155Error::NotApproved)
+            }
+            Ok(())
+        }
+
+        /// Moves `value` tokens of the token `id` from `from` to `to`.
+        ///
+        /// The caller has to make sure that `from` holds enough tokens.
+        fn transfer_impl(&mut self, from: AccountId, to: AccountId, id: TokenId, value: Balance) {
+            let balance_from = self.balance_of_or_zero(&from, id);
+            self.balances.insert((from, id), balance_from - value);
+            let balance_to = self.balance_of_or_zero(&to, id);
+            self.balances.insert((to, id), balance_to + value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the token through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    ///
+    /// Like on-chain, calling an account without a contract succeeds and returns nothing.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            match contracts.iter_mut().find(|(account, _)| account == callee) {
+                Some((_, contract)) => contract(selector, input),
+                None => Some(Vec::new()),
+            }
+        })
+    }
+
+    /// Returns the events deposited so far, in order.
+    fn emitted_events() -> Vec<Event> {
+        env::test::emitted_events()
+            .map(|event| Event::decode(&mut &event.data[..]).expect("a valid Event"))
+            .collect()
+    }
+
+    #[test]
+    fn create_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        // A fungible token and a unique one
+        assert_eq!(erc1155.create(1000), 0);
+        assert_eq!(erc1155.create(1), 1);
+        assert_eq!(erc1155.balance_of(alice, 0), 1000);
+        assert_eq!(erc1155.balance_of(alice, 1), 1);
+        assert_eq!(erc1155.balance_of(alice, 2), 0);
+    }
+
+    #[test]
+    fn safe_transfer_from_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let id = erc1155.create(1000);
+        assert_eq!(erc1155.safe_transfer_from(alice, bob, id, 1001, Vec::new()), Err(Erc1155Error::InsufficientBalance));
+        assert_eq!(erc1155.safe_transfer_from(alice, bob, id, 100, Vec::new()), Ok(()));
+        assert_eq!(erc1155.balance_of(alice, id), 900);
+        assert_eq!(erc1155.balance_of(bob, id), 100);
+        // Alice cannot move Bob's tokens
+        assert_eq!(erc1155.safe_transfer_from(bob, alice, id, 100, Vec::new()), Err(Erc1155Error::NotApproved));
+        assert_eq!(emitted_events().pop(), Some(Event::TransferSingle {
+            operator: alice,
+            from: Some(alice),
+            to: Some(bob),
+            id: id,
+            value: 100
+        }));
+    }
+
+    #[test]
+    fn balance_of_batch_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        let sword = erc1155.create(1);
+        assert_eq!(erc1155.safe_transfer_from(alice, bob, sword, 1, Vec::new()), Ok(()));
+        assert_eq!(
+            erc1155.balance_of_batch(vec![alice, alice, bob, bob], vec![gold, sword, gold, sword]),
+            Ok(vec![1000, 0, 0, 1])
+        );
+        assert_eq!(erc1155.balance_of_batch(vec![alice], vec![gold, sword]), Err(Erc1155Error::LengthMismatch));
+    }
+
+    #[test]
+    fn safe_batch_transfer_from_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        let sword = erc1155.create(1);
+        assert_eq!(erc1155.safe_batch_transfer_from(alice, bob, vec![gold, sword], vec![100, 1], Vec::new()), Ok(()));
+        assert_eq!(erc1155.balance_of(alice, gold), 900);
+        assert_eq!(erc1155.balance_of(bob, gold), 100);
+        assert_eq!(erc1155.balance_of(bob, sword), 1);
+        // A single event covers the whole batch
+        assert_eq!(emitted_events().pop(), Some(Event::TransferBatch {
+            operator: alice,
+            from: Some(alice),
+            to: Some(bob),
+            ids: vec![gold, sword],
+            values: vec![100, 1]
+        }));
+    }
+
+    #[test]
+    fn safe_batch_transfer_from_is_all_or_nothing() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        let sword = erc1155.create(1);
+        // The sword is only there once
+        assert_eq!(
+            erc1155.safe_batch_transfer_from(alice, bob, vec![gold, sword], vec![100, 2], Vec::new()),
+            Err(Erc1155Error::InsufficientBalance)
+        );
+        // Repeated ids are added up
+        assert_eq!(
+            erc1155.safe_batch_transfer_from(alice, bob, vec![gold, gold], vec![600, 600], Vec::new()),
+            Err(Erc1155Error::InsufficientBalance)
+        );
+        assert_eq!(
+            erc1155.safe_batch_transfer_from(alice, bob, vec![gold], vec![1, 2], Vec::new()),
+            Err(Erc1155Error::LengthMismatch)
+        );
+        // Nothing has been moved
+        assert_eq!(erc1155.balance_of(alice, gold), 1000);
+        assert_eq!(erc1155.balance_of(bob, gold), 0);
+        assert_eq!(erc1155.balance_of(bob, sword), 0);
+    }
+
+    #[test]
+    fn operators_can_transfer() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        env::test::set_caller(bob);
+        assert_eq!(erc1155.safe_transfer_from(alice, charlie, gold, 100, Vec::new()), Err(Erc1155Error::NotApproved));
+        // Alice makes Bob an operator of all her tokens
+        env::test::set_caller(alice);
+        assert_eq!(erc1155.set_approval_for_all(bob, true), Ok(()));
+        assert_eq!(erc1155.is_approved_for_all(alice, bob), true);
+        env::test::set_caller(bob);
+        assert_eq!(erc1155.safe_transfer_from(alice, charlie, gold, 100, Vec::new()), Ok(()));
+        assert_eq!(erc1155.safe_batch_transfer_from(alice, charlie, vec![gold], vec![100], Vec::new()), Ok(()));
+        assert_eq!(erc1155.balance_of(charlie, gold), 200);
+        // Once revoked, Bob cannot move her tokens anymore
+        env::test::set_caller(alice);
+        assert_eq!(erc1155.set_approval_for_all(bob, false), Ok(()));
+        env::test::set_caller(bob);
+        assert_eq!(erc1155.safe_transfer_from(alice, charlie, gold, 100, Vec::new()), Err(Erc1155Error::NotApproved));
+    }
+
+    #[test]
+    fn receivers_are_notified() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The receiver records every call and acknowledges the tokens
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let recorded = calls.clone();
+        register_contract(receiver, move |selector, input| {
+            recorded.borrow_mut().push((selector, input.to_vec()));
+            Some(selector.encode())
+        });
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        assert_eq!(erc1155.safe_transfer_from(alice, receiver, gold, 100, vec![1]), Ok(()));
+        assert_eq!(erc1155.safe_batch_transfer_from(alice, receiver, vec![gold], vec![50], vec![2]), Ok(()));
+        assert_eq!(erc1155.balance_of(receiver, gold), 150);
+        assert_eq!(*calls.borrow(), vec![
+            (ON_ERC1155_RECEIVED_SELECTOR, (alice, alice, gold, 100 as Balance, vec![1u8]).encode()),
+            (ON_ERC1155_BATCH_RECEIVED_SELECTOR, (alice, alice, vec![gold], vec![50 as Balance], vec![2u8]).encode()),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient cannot receive tokens")]
+    fn safe_transfer_from_to_an_unaware_contract_reverts() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The receiver does not know about `on_erc1155_received`
+        register_contract(receiver, |_, _| None);
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        let _ = erc1155.safe_transfer_from(alice, receiver, gold, 100, Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "the recipient cannot receive tokens")]
+    fn safe_batch_transfer_from_requires_the_batch_acknowledgement() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let receiver = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(alice);
+
+        // The receiver answers batches with the selector of single transfers
+        register_contract(receiver, |_, _| Some(ON_ERC1155_RECEIVED_SELECTOR.encode()));
+
+        let mut erc1155 = Erc1155::deploy_mock();
+        let gold = erc1155.create(1000);
+        let _ = erc1155.safe_batch_transfer_from(alice, receiver, vec![gold], vec![100], Vec::new());
+    }
+}
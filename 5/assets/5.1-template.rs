@@ -0,0 +1,667 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::{
+        format,
+        vec::Vec,
+    },
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of a transaction submitted to the wallet.
+type TransactionId = u32;
+
+/// A call the owners of the wallet want to make.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+struct Transaction {
+    /// The account or contract which is called.
+    to: AccountId,
+    /// The native balance sent along with the call.
+    value: Balance,
+    /// The selector and SCALE encoded arguments of the called message, or
+    /// nothing for a plain transfer of native balance.
+    data: Vec<u8>,
+    /// Whether the call has been made.
+    executed: bool,
+}
+
+/// Events deposited by the multisig wallet contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Submission {
+        id: TransactionId,
+    },
+    Confirmation {
+        owner: AccountId,
+        id: TransactionId,
+    },
+    Revocation {
+        owner: AccountId,
+        id: TransactionId,
+    },
+    Execution {
+        id: TransactionId,
+    },
+    ExecutionFailure {
+        id: TransactionId,
+    },
+    OwnerAddition {
+        owner: AccountId,
+    },
+    OwnerRemoval {
+        owner: AccountId,
+    },
+    ThresholdChange {
+        threshold: u32,
+    },
+}
+
+/// Errors which can occur when calling the multisig wallet contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum MultisigError {
+    /// The caller is not one of the owners.
+    NotOwner,
+    /// Owners can only be managed by transactions of the wallet itself.
+    NotWallet,
+    /// No transaction with the given id has been submitted.
+    UnknownTransaction,
+    /// The transaction has already been executed.
+    AlreadyExecuted,
+    /// The owner has already confirmed the transaction.
+    AlreadyConfirmed,
+    /// The owner has not confirmed the transaction.
+    NotConfirmed,
+    /// The transaction does not have enough confirmations to be executed.
+    ThresholdNotReached,
+    /// The call of the transaction failed.
+    ExecutionFailed,
+    /// The account is already one of the owners.
+    AlreadyOwner,
+    /// The threshold must be at least 1 and at most the number of owners.
+    InvalidThreshold,
+}
+
+/// Deposits a multisig wallet event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// The gas limit of calls made by the wallet.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Makes the call of `transaction`, sending its value along.
+///
+/// Returns `false` if the call failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn make_call(transaction: &Transaction) -> bool {
+    if transaction.data.is_empty() {
+        return env::transfer(transaction.to, transaction.value).is_ok()
+    }
+    env::call_invoke(transaction.to, CALL_GAS_LIMIT, transaction.value, &transaction.data[..]).is_ok()
+}
+
+/// Records the call of `transaction` in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn make_call(transaction: &Transaction) -> bool {
+    tests::mock_make_call(transaction)
+}
+
+/// Returns `true` if `threshold` confirmations can be reached by `owners` owners.
+fn is_valid_threshold(threshold: u32, owners: usize) -> bool {
+    threshold > 0 && threshold as usize <= owners
+}
+
+contract! {
+    /// A wallet which makes calls once enough of its owners agree.
+    ///
+    /// Any owner can submit a transaction, a call to an account or contract
+    /// with some native balance sent along. The other owners confirm it, and
+    /// once `threshold` of them have, the wallet makes the call. The owners and
+    /// the threshold can only be changed by transactions the wallet sends to
+    /// itself, so they need the same agreement as everything else.
+    struct Multisig {
+        /// The accounts which submit and confirm transactions.
+        owners: storage::Value<Vec<AccountId>>,
+        /// The number of confirmations a transaction needs.
+        threshold: storage::Value<u32>,
+        /// The number of transactions submitted, which is also the next id.
+        transaction_count: storage::Value<TransactionId>,
+        /// The transactions by id.
+        transactions: storage::HashMap<TransactionId, Transaction>,
+        /// The confirmations of transactions: (id, owner) -> confirmed
+        confirmations: storage::HashMap<(TransactionId, AccountId), bool>,
+    }
+
+    impl Deploy for Multisig {
+        fn deploy(&mut self, owners: Vec<AccountId>, threshold: u32) {
+            assert!(is_valid_threshold(threshold, owners.len()), "the threshold cannot be reached");
+            for (index, owner) in owners.iter().enumerate() {
+                assert!(!owners[..index].contains(owner), "every owner must be unique");
+            }
+            self.owners.set(owners);
+            self.threshold.set(threshold);
+            self.transaction_count.set(0);
+        }
+    }
+
+    impl Multisig {
+        /// Returns the owners of the wallet.
+        pub(external) fn owners(&self) -> Vec<AccountId> {
+            let owners = (*self.owners).clone();
+            env.println(&format!("Multisig::owners = {:?}", owners));
+            owners
+        }
+
+        /// Returns the number of confirmations a transaction needs.
+        pub(external) fn threshold(&self) -> u32 {
+            let threshold = *self.threshold;
+            env.println(&format!("Multisig::threshold = {:?}", threshold));
+            threshold
+        }
+
+        /// Returns the number of transactions submitted so far.
+        pub(external) fn transaction_count(&self) -> TransactionId {
+            let transaction_count = *self.transaction_count;
+            env.println(&format!("Multisig::transaction_count = {:?}", transaction_count));
+            transaction_count
+        }
+
+        /// Returns the transaction with the given id, if it has been submitted.
+        pub(external) fn transaction(&self, id: TransactionId) -> Option<Transaction> {
+            let transaction = self.transactions.get(&id).cloned();
+            env.println(&format!("Multisig::transaction(id = {:?}) = {:?}", id, transaction));
+            transaction
+        }
+
+        /// Returns the number of current owners who confirmed the transaction.
+        pub(external) fn confirmation_count(&self, id: TransactionId) -> u32 {
+            let count = self.confirmation_count_of(id);
+            env.println(&format!("Multisig::confirmation_count(id = {:?}) = {:?}", id, count));
+            count
+        }
+
+        /// Returns whether `owner` has confirmed the transaction.
+        pub(external) fn is_confirmed_by(&self, id: TransactionId, owner: AccountId) -> bool {
+            let confirmed = self.is_confirmed_by_or_false(id, &owner);
+            env.println(&format!(
+                "Multisig::is_confirmed_by(id = {:?}, owner = {:?}) = {:?}",
+                id, owner, confirmed
+            ));
+            confirmed
+        }
+
+        /// Submits a call of `to` with `data`, sending `value` along, and
+        /// confirms it for the caller. Returns the id of the transaction.
+        ///
+        /// An empty `data` only sends `value`. Only owners can submit transactions.
+        pub(external) fn submit_transaction(&mut self, to: AccountId, value: Balance, data: Vec<u8>) -> Result<TransactionId, MultisigError> {
+            let owner = env.caller();
+            self.ensure_owner(&owner)?;
+            let id = *self.transaction_count;
+            self.transactions.insert(id, Transaction {
+                to: to,
+                value: value,
+                data: data,
+                executed: false
+            });
+            self.transaction_count += 1;
+            deposit_event(Event::Submission {
+                id: id
+            });
+            self.confirm_impl(id, owner)?;
+            Ok(id)
+        }
+
+        /// Confirms the transaction for the caller, and executes it if this
+        /// confirmation reaches the threshold.
+        ///
+        /// A failed call does not fail the confirmation. The transaction stays
+        /// unexecuted and can be retried with `execute`.
+        pub(external) fn confirm(&mut self, id: TransactionId) -> Result<(), MultisigError> {
+            let owner = env.caller();
+            self.ensure_owner(&owner)?;
+            self.confirm_impl(id, owner)
+        }
+
+        /// Takes back the confirmation of the caller, as long as the transaction
+        /// has not been executed.
+        pub(external) fn revoke_confirmation(&mut self, id: TransactionId) -> Result<(), MultisigError> {
+            let owner = env.caller();
+            self.ensure_owner(&owner)?;
+            self.ensure_pending(id)?;
+            // ACTION: `if` the caller has not confirmed the transaction, exit early and return
+            //         `Err(MultisigError::NotConfirmed)`
+            // ACTION: Set the confirmation of the caller to `false`
+            // ACTION: Deposit a `Revocation` event and return `Ok(())`
+        }
+
+        /// Executes a confirmed transaction whose call failed before.
+        pub(external) fn execute(&mut self, id: TransactionId) -> Result<(), MultisigError> {
+            self.ensure_owner(&env.caller())?;
+            self.ensure_pending(id)?;
+            if self.confirmation_count_of(id) < *self.threshold {
+                return Err(MultisigError::ThresholdNotReached)
+            }
+            self.execute_impl(id)
+        }
+
+        /// Adds `owner` to the owners.
+        ///
+        /// Only the wallet itself can add owners.
+        pub(external) fn add_owner(&mut self, owner: AccountId) -> Result<(), MultisigError> {
+            self.ensure_wallet(&env.caller())?;
+            if self.owners.contains(&owner) {
+                return Err(MultisigError::AlreadyOwner)
+            }
+            let mut owners = (*self.owners).clone();
+            owners.push(owner);
+            self.owners.set(owners);
+            deposit_event(Event::OwnerAddition {
+                owner: owner
+            });
+            Ok(())
+        }
+
+        /// Removes `owner` from the owners, lowering the threshold if there are
+        /// no longer enough owners to reach it.
+        ///
+        /// Confirmations of removed owners no longer count. Only the wallet
+        /// itself can remove owners, and never the last one.
+        pub(external) fn remove_owner(&mut self, owner: AccountId) -> Result<(), MultisigError> {
+            self.ensure_wallet(&env.caller())?;
+            let index = self.owners.iter().position(|o| *o == owner).ok_or(MultisigError::NotOwner)?;
+            if self.owners.len() == 1 {
+                return Err(MultisigError::InvalidThreshold)
+            }
+            let mut owners = (*self.owners).clone();
+            owners.remove(index);
+            self.owners.set(owners);
+            deposit_event(Event::OwnerRemoval {
+                owner: owner
+            });
+            let owner_count = self.owners.len() as u32;
+            if *self.threshold > owner_count {
+                self.change_threshold_impl(owner_count);
+            }
+            Ok(())
+        }
+
+        /// Replaces `owner` with `new_owner`, keeping the threshold.
+        ///
+        /// Only the wallet itself can replace owners.
+        pub(external) fn replace_owner(&mut self, owner: AccountId, new_owner: AccountId) -> Result<(), MultisigError> {
+            self.ensure_wallet(&env.caller())?;
+            let index = self.owners.iter().position(|o| *o == owner).ok_or(MultisigError::NotOwner)?;
+            if self.owners.contains(&new_owner) {
+                return Err(MultisigError::AlreadyOwner)
+            }
+            let mut owners = (*self.owners).clone();
+            owners[index] = new_owner;
+            self.owners.set(owners);
+            deposit_event(Event::OwnerRemoval {
+                owner: owner
+            });
+            deposit_event(Event::OwnerAddition {
+                owner: new_owner
+            });
+            Ok(())
+        }
+
+        /// Changes the number of confirmations a transaction needs.
+        ///
+        /// Only the wallet itself can change the threshold.
+        pub(external) fn change_threshold(&mut self, threshold: u32) -> Result<(), MultisigError> {
+            self.ensure_wallet(&env.caller())?;
+            if !is_valid_threshold(threshold, self.owners.len()) {
+                return Err(MultisigError::InvalidThreshold)
+            }
+            self.change_threshold_impl(threshold);
+            Ok(())
+        }
+    }
+
+    impl Multisig {
+        /// Returns an error unless `caller` is one of the owners.
+        fn ensure_owner(&self, caller: &AccountId) -> Result<(), MultisigError> {
+            if !self.owners.contains(caller) {
+                return Err(MultisigError::NotOwner)
+            }
+            Ok(())
+        }
+
+        /// Returns an error unless `caller` is the wallet itself.
+        fn ensure_wallet(&self, caller: &AccountId) -> Result<(), MultisigError> {
+            if *caller != env::address() {
+                return Err(MultisigError::NotWallet)
+            }
+            Ok(())
+        }
+
+        /// Returns an error unless the transaction exists and has not been executed.
+        fn ensure_pending(&self, id: TransactionId) -> Result<(), MultisigError> {
+            match self.transactions.get(&id) {
+                None => Err(MultisigError::UnknownTransaction),
+                Some(transaction) if transaction.executed => Err(MultisigError::AlreadyExecuted),
+                Some(_) => Ok(()),
+            }
+        }
+
+        /// Returns whether `owner` has confirmed the transaction, or `false`
+        /// if there is no confirmation.
+        fn is_confirmed_by_or_false(&self, id: TransactionId, owner: &AccountId) -> bool {
+            *self.confirmations.get(&(id, *owner)).unwrap_or(&false)
+        }
+
+        /// Returns the number of current owners who confirmed the transaction.
+        fn confirmation_count_of(&self, id: TransactionId) -> u32 {
+            // ACTION: Count the `owners` who confirmed the transaction
+            //   HINT: `filter` the iterator of `owners` with `is_confirmed_by_or_false`
+            //   HINT: Counting only current owners means removed owners no longer count
+        }
+
+        /// Confirms the transaction for `owner`, and executes it once the
+        /// threshold is reached.
+        fn confirm_impl(&mut self, id: TransactionId, owner: AccountId) -> Result<(), MultisigError> {
+            self.ensure_pending(id)?;
+            // ACTION: `if` `owner` has already confirmed the transaction, exit early and return
+            //         `Err(MultisigError::AlreadyConfirmed)`
+            // ACTION: Record the confirmation and deposit a `Confirmation` event
+            // ACTION: `if` the `confirmation_count_of` the transaction reached the `threshold`,
+            //         call `execute_impl`
+            //   HINT: The confirmation counts even if the call fails, so ignore its result
+            // ACTION: Return `Ok(())`
+        }
+
+        /// Makes the call of the transaction.
+        ///
+        /// The transaction is marked as executed before the call, so that the
+        /// called contract cannot execute it a second time by calling back.
+        fn execute_impl(&mut self, id: TransactionId) -> Result<(), MultisigError> {
+            let mut transaction = self.transactions
+                .get(&id)
+                .cloned()
+                .expect("transactions are checked to exist before they are executed");
+            transaction.executed = true;
+            self.transactions.insert(id, transaction.clone());
+            if make_call(&transaction) {
+                deposit_event(Event::Execution {
+                    id: id
+                });
+                return Ok(())
+            }
+            transaction.executed = false;
+            self.transactions.insert(id, transaction);
+            deposit_event(Event::ExecutionFailure {
+                id: id
+            });
+            Err(MultisigError::ExecutionFailed)
+        }
+
+        /// Sets the threshold without any checks.
+        fn change_threshold_impl(&mut self, threshold: u32) {
+            self.threshold.set(threshold);
+            deposit_event(Event::ThresholdChange {
+                threshold: threshold
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The calls made by the wallet so far: (to, value, data)
+        static CALLS: RefCell<Vec<(AccountId, Balance, Vec<u8>)>> = RefCell::new(Vec::new());
+        /// The accounts whose calls fail.
+        static FAILING: RefCell<Vec<AccountId>> = RefCell::new(Vec::new());
+    }
+
+    /// Records the call of `transaction`, unless its recipient has been made to fail.
+    pub(super) fn mock_make_call(transaction: &Transaction) -> bool {
+        if FAILING.with(|failing| failing.borrow().contains(&transaction.to)) {
+            return false
+        }
+        CALLS.with(|calls| {
+            calls.borrow_mut().push((transaction.to, transaction.value, transaction.data.clone()))
+        });
+        true
+    }
+
+    /// Makes calls to `account` fail, or succeed again.
+    fn set_failing(account: AccountId, fails: bool) {
+        FAILING.with(|failing| {
+            let mut failing = failing.borrow_mut();
+            failing.retain(|other| *other != account);
+            if fails {
+                failing.push(account);
+            }
+        })
+    }
+
+    /// Returns the calls made by the wallet so far.
+    fn calls() -> Vec<(AccountId, Balance, Vec<u8>)> {
+        CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    /// Returns Alice, Bob and Charlie, the owners of most tests.
+    fn owners() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a wallet owned by Alice, Bob and Charlie which needs 2 confirmations.
+    fn deploy_mock() -> Multisig {
+        let (alice, bob, charlie) = owners();
+        env::test::set_caller(alice);
+        Multisig::deploy_mock(vec![alice, bob, charlie], 2)
+    }
+
+    #[test]
+    fn deploy_works() {
+        let (alice, bob, charlie) = owners();
+        let multisig = deploy_mock();
+        assert_eq!(multisig.owners(), vec![alice, bob, charlie]);
+        assert_eq!(multisig.threshold(), 2);
+        assert_eq!(multisig.transaction_count(), 0);
+        assert_eq!(multisig.transaction(0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "the threshold cannot be reached")]
+    fn deploy_fails_on_unreachable_threshold() {
+        let (alice, bob, _) = owners();
+        Multisig::deploy_mock(vec![alice, bob], 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "every owner must be unique")]
+    fn deploy_fails_on_duplicate_owners() {
+        let (alice, bob, _) = owners();
+        Multisig::deploy_mock(vec![alice, bob, alice], 2);
+    }
+
+    #[test]
+    fn submit_confirms_for_the_submitter() {
+        let (alice, _, _) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        assert_eq!(multisig.transaction_count(), 1);
+        assert_eq!(multisig.transaction(0), Some(Transaction {
+            to: dave,
+            value: 10,
+            data: vec![],
+            executed: false
+        }));
+        assert!(multisig.is_confirmed_by(0, alice));
+        assert_eq!(multisig.confirmation_count(0), 1);
+        // One confirmation is not enough
+        assert_eq!(calls(), vec![]);
+    }
+
+    #[test]
+    fn only_owners_can_submit_and_confirm() {
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        env::test::set_caller(dave);
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Err(MultisigError::NotOwner));
+        assert_eq!(multisig.confirm(0), Err(MultisigError::NotOwner));
+        assert_eq!(multisig.confirmation_count(0), 1);
+    }
+
+    #[test]
+    fn threshold_executes_native_transfers() {
+        let (alice, bob, charlie) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        assert_eq!(multisig.confirm(0), Err(MultisigError::AlreadyConfirmed));
+        env::test::set_caller(bob);
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(calls(), vec![(dave, 10, vec![])]);
+        assert!(multisig.transaction(0).unwrap().executed);
+        // Executed transactions cannot be confirmed or revoked anymore
+        env::test::set_caller(charlie);
+        assert_eq!(multisig.confirm(0), Err(MultisigError::AlreadyExecuted));
+        env::test::set_caller(alice);
+        assert_eq!(multisig.revoke_confirmation(0), Err(MultisigError::AlreadyExecuted));
+        assert_eq!(calls().len(), 1);
+    }
+
+    #[test]
+    fn threshold_executes_contract_calls() {
+        let (_, bob, _) = owners();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        // Call `transfer(dave, 100)` of an ERC20 token held by the wallet
+        let mut data = vec![0x84, 0xA1, 0x5D, 0xA1];
+        data.extend((dave, 100 as Balance).encode());
+        assert_eq!(multisig.submit_transaction(token, 0, data.clone()), Ok(0));
+        env::test::set_caller(bob);
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(calls(), vec![(token, 0, data)]);
+    }
+
+    #[test]
+    fn revoked_confirmations_do_not_count() {
+        let (alice, bob, charlie) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        env::test::set_caller(bob);
+        assert_eq!(multisig.revoke_confirmation(0), Err(MultisigError::NotConfirmed));
+        env::test::set_caller(alice);
+        assert_eq!(multisig.revoke_confirmation(0), Ok(()));
+        assert!(!multisig.is_confirmed_by(0, alice));
+        assert_eq!(multisig.confirmation_count(0), 0);
+        env::test::set_caller(bob);
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(calls(), vec![]);
+        assert_eq!(multisig.execute(0), Err(MultisigError::ThresholdNotReached));
+        env::test::set_caller(charlie);
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(calls(), vec![(dave, 10, vec![])]);
+    }
+
+    #[test]
+    fn failed_calls_can_be_retried() {
+        let (_, bob, _) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        set_failing(dave, true);
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        env::test::set_caller(bob);
+        // The confirmation is recorded although the call fails
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(multisig.confirmation_count(0), 2);
+        assert!(!multisig.transaction(0).unwrap().executed);
+        assert_eq!(multisig.execute(0), Err(MultisigError::ExecutionFailed));
+        set_failing(dave, false);
+        assert_eq!(multisig.execute(0), Ok(()));
+        assert_eq!(multisig.execute(0), Err(MultisigError::AlreadyExecuted));
+        assert_eq!(multisig.execute(1), Err(MultisigError::UnknownTransaction));
+        assert_eq!(calls(), vec![(dave, 10, vec![])]);
+    }
+
+    #[test]
+    fn owners_are_managed_by_the_wallet_itself() {
+        let (alice, bob, charlie) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        // Not even an owner can change the owners directly
+        assert_eq!(multisig.add_owner(dave), Err(MultisigError::NotWallet));
+        assert_eq!(multisig.remove_owner(bob), Err(MultisigError::NotWallet));
+        assert_eq!(multisig.replace_owner(bob, dave), Err(MultisigError::NotWallet));
+        assert_eq!(multisig.change_threshold(3), Err(MultisigError::NotWallet));
+        // The owners submit a transaction which calls `add_owner(dave)` on the wallet
+        let mut data = vec![0xAD, 0x6D, 0x43, 0x58];
+        data.extend(dave.encode());
+        assert_eq!(multisig.submit_transaction(env::address(), 0, data.clone()), Ok(0));
+        env::test::set_caller(charlie);
+        assert_eq!(multisig.confirm(0), Ok(()));
+        assert_eq!(calls(), vec![(env::address(), 0, data)]);
+        // which the wallet then makes as the caller
+        env::test::set_caller(env::address());
+        assert_eq!(multisig.add_owner(dave), Ok(()));
+        assert_eq!(multisig.add_owner(dave), Err(MultisigError::AlreadyOwner));
+        assert_eq!(multisig.owners(), vec![alice, bob, charlie, dave]);
+    }
+
+    #[test]
+    fn removing_owners_lowers_the_threshold() {
+        let (alice, bob, charlie) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        assert_eq!(multisig.submit_transaction(dave, 10, vec![]), Ok(0));
+        env::test::set_caller(env::address());
+        assert_eq!(multisig.change_threshold(4), Err(MultisigError::InvalidThreshold));
+        assert_eq!(multisig.change_threshold(0), Err(MultisigError::InvalidThreshold));
+        assert_eq!(multisig.change_threshold(3), Ok(()));
+        assert_eq!(multisig.remove_owner(dave), Err(MultisigError::NotOwner));
+        assert_eq!(multisig.remove_owner(alice), Ok(()));
+        assert_eq!(multisig.owners(), vec![bob, charlie]);
+        assert_eq!(multisig.threshold(), 2);
+        // Alice's confirmation no longer counts
+        assert_eq!(multisig.confirmation_count(0), 0);
+        assert_eq!(multisig.remove_owner(bob), Ok(()));
+        assert_eq!(multisig.threshold(), 1);
+        assert_eq!(multisig.remove_owner(charlie), Err(MultisigError::InvalidThreshold));
+    }
+
+    #[test]
+    fn replace_owner_works() {
+        let (alice, bob, charlie) = owners();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let mut multisig = deploy_mock();
+        env::test::set_caller(env::address());
+        assert_eq!(multisig.replace_owner(bob, charlie), Err(MultisigError::AlreadyOwner));
+        assert_eq!(multisig.replace_owner(dave, bob), Err(MultisigError::NotOwner));
+        assert_eq!(multisig.replace_owner(bob, dave), Ok(()));
+        assert_eq!(multisig.owners(), vec![alice, dave, charlie]);
+        assert_eq!(multisig.threshold(), 2);
+        // Bob is no longer an owner
+        env::test::set_caller(bob);
+        assert_eq!(multisig.submit_transaction(bob, 10, vec![]), Err(MultisigError::NotOwner));
+    }
+}
@@ -0,0 +1,470 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The stages an escrow goes through.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum State {
+    /// The buyer has not paid yet.
+    AwaitingPayment,
+    /// The buyer has paid and waits for the seller to ship.
+    AwaitingShipment,
+    /// The seller has shipped and waits for the buyer to release the payment.
+    Shipped,
+    /// One of them disputed the deal and the arbiter has to decide.
+    Disputed,
+    /// The seller has been paid.
+    Completed,
+    /// The buyer has been paid back.
+    Refunded,
+}
+
+/// Events deposited by the escrow contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Deposited {
+        buyer: AccountId,
+        value: Balance,
+        deadline: BlockNumber,
+    },
+    Shipped {
+        seller: AccountId,
+    },
+    Disputed {
+        by: AccountId,
+    },
+    Released {
+        seller: AccountId,
+        value: Balance,
+    },
+    Refunded {
+        buyer: AccountId,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the escrow contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum EscrowError {
+    /// Only the buyer can do this.
+    NotBuyer,
+    /// Only the seller can do this.
+    NotSeller,
+    /// Only the arbiter can do this.
+    NotArbiter,
+    /// Only the buyer or the seller can do this.
+    NotParty,
+    /// The escrow is not in a state which allows this.
+    InvalidState,
+    /// No native balance was sent along with the deposit.
+    ZeroValue,
+    /// The seller still has time to ship.
+    DeadlineNotReached,
+}
+
+/// Deposits an escrow event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+contract! {
+    /// Holds the payment of a buyer until the seller has delivered.
+    ///
+    /// The buyer deploys the escrow and deposits the payment. The seller ships,
+    /// and the buyer releases the payment to the seller once the goods have
+    /// arrived. If either of them disputes the deal, the arbiter decides who
+    /// gets the payment. If the seller does not ship within `timeout` blocks,
+    /// the buyer can take the payment back.
+    struct Escrow {
+        /// The account which pays.
+        buyer: storage::Value<AccountId>,
+        /// The account which ships and gets paid.
+        seller: storage::Value<AccountId>,
+        /// The account which resolves disputes.
+        arbiter: storage::Value<AccountId>,
+        /// The number of blocks the seller has to ship after the deposit.
+        timeout: storage::Value<BlockNumber>,
+        /// The block after which the buyer can take an unshipped payment back.
+        deadline: storage::Value<BlockNumber>,
+        /// The native balance held by the escrow.
+        amount: storage::Value<Balance>,
+        /// The current stage of the escrow.
+        state: storage::Value<State>,
+    }
+
+    impl Deploy for Escrow {
+        fn deploy(&mut self, seller: AccountId, arbiter: AccountId, timeout: BlockNumber) {
+            self.buyer.set(env.caller());
+            self.seller.set(seller);
+            self.arbiter.set(arbiter);
+            self.timeout.set(timeout);
+            self.deadline.set(0);
+            self.amount.set(0);
+            self.state.set(State::AwaitingPayment);
+        }
+    }
+
+    impl Escrow {
+        /// Returns the current stage of the escrow.
+        pub(external) fn state(&self) -> State {
+            let state = *self.state;
+            env.println(&format!("Escrow::state = {:?}", state));
+            state
+        }
+
+        /// Returns the native balance held by the escrow.
+        pub(external) fn amount(&self) -> Balance {
+            let amount = *self.amount;
+            env.println(&format!("Escrow::amount = {:?}", amount));
+            amount
+        }
+
+        /// Returns the block after which the buyer can take an unshipped
+        /// payment back.
+        pub(external) fn deadline(&self) -> BlockNumber {
+            let deadline = *self.deadline;
+            env.println(&format!("Escrow::deadline = {:?}", deadline));
+            deadline
+        }
+
+        /// Holds the native balance sent along with the call as the payment,
+        /// and gives the seller `timeout` blocks to ship.
+        pub(external) fn deposit(&mut self) -> Result<(), EscrowError> {
+            let buyer = env.caller();
+            if buyer != *self.buyer {
+                return Err(EscrowError::NotBuyer)
+            }
+            self.ensure_state(State::AwaitingPayment)?;
+            let value = value_transferred();
+            if value == 0 {
+                return Err(EscrowError::ZeroValue)
+            }
+            let deadline = env.block_number() + *self.timeout;
+            self.amount.set(value);
+            self.deadline.set(deadline);
+            self.state.set(State::AwaitingShipment);
+            deposit_event(Event::Deposited {
+                buyer: buyer,
+                value: value,
+                deadline: deadline
+            });
+            Ok(())
+        }
+
+        /// Records that the seller has shipped the goods.
+        pub(external) fn confirm_shipment(&mut self) -> Result<(), EscrowError> {
+            let seller = env.caller();
+            if seller != *self.seller {
+                return Err(EscrowError::NotSeller)
+            }
+            self.ensure_state(State::AwaitingShipment)?;
+            self.state.set(State::Shipped);
+            deposit_event(Event::Shipped {
+                seller: seller
+            });
+            Ok(())
+        }
+
+        /// Pays the seller once the buyer has received the goods.
+        pub(external) fn release(&mut self) -> Result<(), EscrowError> {
+            if env.caller() != *self.buyer {
+                return Err(EscrowError::NotBuyer)
+            }
+            self.ensure_state(State::Shipped)?;
+            self.complete();
+            Ok(())
+        }
+
+        /// Hands the decision over to the arbiter.
+        ///
+        /// The buyer or the seller can dispute the deal any time after the
+        /// deposit and before the payment has been released.
+        pub(external) fn dispute(&mut self) -> Result<(), EscrowError> {
+            let caller = env.caller();
+            if caller != *self.buyer && caller != *self.seller {
+                return Err(EscrowError::NotParty)
+            }
+            if *self.state != State::AwaitingShipment && *self.state != State::Shipped {
+                return Err(EscrowError::InvalidState)
+            }
+            self.state.set(State::Disputed);
+            deposit_event(Event::Disputed {
+                by: caller
+            });
+            Ok(())
+        }
+
+        /// Settles a dispute by paying the seller, or by paying the buyer back
+        /// if `refund_buyer` is `true`.
+        pub(external) fn resolve(&mut self, refund_buyer: bool) -> Result<(), EscrowError> {
+            if env.caller() != *self.arbiter {
+                return Err(EscrowError::NotArbiter)
+            }
+            self.ensure_state(State::Disputed)?;
+            if refund_buyer {
+                self.refund_impl();
+            } else {
+                self.complete();
+            }
+            Ok(())
+        }
+
+        /// Pays the buyer back if the seller has not shipped by the deadline.
+        pub(external) fn refund(&mut self) -> Result<(), EscrowError> {
+            if env.caller() != *self.buyer {
+                return Err(EscrowError::NotBuyer)
+            }
+            self.ensure_state(State::AwaitingShipment)?;
+            if env.block_number() <= *self.deadline {
+                return Err(EscrowError::DeadlineNotReached)
+            }
+            self.refund_impl();
+            Ok(())
+        }
+    }
+
+    impl Escrow {
+        /// Returns an error unless the escrow is in `state`.
+        fn ensure_state(&self, state: State) -> Result<(), EscrowError> {
+            if *self.state != state {
+                return Err(EscrowError::InvalidState)
+            }
+            Ok(())
+        }
+
+        /// Pays the held amount to the seller.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        fn complete(&mut self) {
+            let seller = *self.seller;
+            let value = *self.amount;
+            self.amount.set(0);
+            self.state.set(State::Completed);
+            assert!(transfer_native(seller, value), "the payout failed");
+            deposit_event(Event::Released {
+                seller: seller,
+                value: value
+            });
+        }
+
+        /// Pays the held amount back to the buyer.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        fn refund_impl(&mut self) {
+            let buyer = *self.buyer;
+            let value = *self.amount;
+            self.amount.set(0);
+            self.state.set(State::Refunded);
+            assert!(transfer_native(buyer, value), "the payout failed");
+            deposit_event(Event::Refunded {
+                buyer: buyer,
+                value: value
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Returns the buyer, the seller and the arbiter of the tests.
+    fn parties() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys an escrow at block 10 with a timeout of 100 blocks, into which
+    /// the buyer has deposited 1000.
+    fn deploy_funded() -> Escrow {
+        let (buyer, seller, arbiter) = parties();
+        env::test::set_caller(buyer);
+        env::test::set_block_number(10);
+        let mut escrow = Escrow::deploy_mock(seller, arbiter, 100);
+        set_value_transferred(1000);
+        assert_eq!(escrow.deposit(), Ok(()));
+        set_value_transferred(0);
+        escrow
+    }
+
+    #[test]
+    fn deposit_works() {
+        let (buyer, seller, arbiter) = parties();
+        env::test::set_caller(buyer);
+        env::test::set_block_number(10);
+        let mut escrow = Escrow::deploy_mock(seller, arbiter, 100);
+        assert_eq!(escrow.state(), State::AwaitingPayment);
+        assert_eq!(escrow.deposit(), Err(EscrowError::ZeroValue));
+        set_value_transferred(1000);
+        // Only the buyer pays into the escrow
+        env::test::set_caller(seller);
+        assert_eq!(escrow.deposit(), Err(EscrowError::NotBuyer));
+        env::test::set_caller(buyer);
+        assert_eq!(escrow.deposit(), Ok(()));
+        assert_eq!(escrow.deposit(), Err(EscrowError::InvalidState));
+        assert_eq!(escrow.state(), State::AwaitingShipment);
+        assert_eq!(escrow.amount(), 1000);
+        assert_eq!(escrow.deadline(), 110);
+    }
+
+    #[test]
+    fn happy_path_pays_the_seller() {
+        let (buyer, seller, _) = parties();
+        let mut escrow = deploy_funded();
+        // The buyer cannot release before the seller has shipped
+        assert_eq!(escrow.release(), Err(EscrowError::InvalidState));
+        assert_eq!(escrow.confirm_shipment(), Err(EscrowError::NotSeller));
+        env::test::set_caller(seller);
+        assert_eq!(escrow.confirm_shipment(), Ok(()));
+        assert_eq!(escrow.state(), State::Shipped);
+        assert_eq!(escrow.release(), Err(EscrowError::NotBuyer));
+        env::test::set_caller(buyer);
+        assert_eq!(escrow.release(), Ok(()));
+        assert_eq!(escrow.state(), State::Completed);
+        assert_eq!(escrow.amount(), 0);
+        assert_eq!(payouts(), vec![(seller, 1000)]);
+        // Nothing can happen after the deal is done
+        assert_eq!(escrow.dispute(), Err(EscrowError::InvalidState));
+        assert_eq!(escrow.refund(), Err(EscrowError::InvalidState));
+    }
+
+    #[test]
+    fn arbiter_resolves_disputes_for_the_seller() {
+        let (buyer, seller, arbiter) = parties();
+        let mut escrow = deploy_funded();
+        env::test::set_caller(seller);
+        assert_eq!(escrow.confirm_shipment(), Ok(()));
+        // The buyer claims the goods never arrived
+        env::test::set_caller(buyer);
+        assert_eq!(escrow.dispute(), Ok(()));
+        assert_eq!(escrow.state(), State::Disputed);
+        assert_eq!(escrow.release(), Err(EscrowError::InvalidState));
+        assert_eq!(escrow.resolve(true), Err(EscrowError::NotArbiter));
+        env::test::set_caller(arbiter);
+        assert_eq!(escrow.dispute(), Err(EscrowError::NotParty));
+        assert_eq!(escrow.resolve(false), Ok(()));
+        assert_eq!(escrow.state(), State::Completed);
+        assert_eq!(payouts(), vec![(seller, 1000)]);
+        assert_eq!(escrow.resolve(true), Err(EscrowError::InvalidState));
+    }
+
+    #[test]
+    fn arbiter_resolves_disputes_for_the_buyer() {
+        let (buyer, seller, arbiter) = parties();
+        let mut escrow = deploy_funded();
+        // The seller can dispute as well, for example over a changed order
+        env::test::set_caller(seller);
+        assert_eq!(escrow.dispute(), Ok(()));
+        assert_eq!(escrow.confirm_shipment(), Err(EscrowError::InvalidState));
+        env::test::set_caller(arbiter);
+        assert_eq!(escrow.resolve(true), Ok(()));
+        assert_eq!(escrow.state(), State::Refunded);
+        assert_eq!(payouts(), vec![(buyer, 1000)]);
+    }
+
+    #[test]
+    fn timeout_refunds_the_buyer() {
+        let (buyer, seller, _) = parties();
+        let mut escrow = deploy_funded();
+        env::test::set_block_number(110);
+        assert_eq!(escrow.refund(), Err(EscrowError::DeadlineNotReached));
+        env::test::set_block_number(111);
+        env::test::set_caller(seller);
+        assert_eq!(escrow.refund(), Err(EscrowError::NotBuyer));
+        env::test::set_caller(buyer);
+        assert_eq!(escrow.refund(), Ok(()));
+        assert_eq!(escrow.state(), State::Refunded);
+        assert_eq!(payouts(), vec![(buyer, 1000)]);
+        // The seller is too late to ship
+        env::test::set_caller(seller);
+        assert_eq!(escrow.confirm_shipment(), Err(EscrowError::InvalidState));
+    }
+
+    #[test]
+    fn shipped_goods_cannot_be_refunded_by_timeout() {
+        let (buyer, seller, _) = parties();
+        let mut escrow = deploy_funded();
+        env::test::set_caller(seller);
+        assert_eq!(escrow.confirm_shipment(), Ok(()));
+        env::test::set_block_number(1000);
+        env::test::set_caller(buyer);
+        assert_eq!(escrow.refund(), Err(EscrowError::InvalidState));
+        assert_eq!(payouts(), vec![]);
+    }
+}
@@ -0,0 +1,336 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// Events deposited by the English auction contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    BidPlaced {
+        bidder: AccountId,
+        value: Balance,
+    },
+    AuctionExtended {
+        end: BlockNumber,
+    },
+    AuctionFinalized {
+        winner: Option<AccountId>,
+        value: Balance,
+    },
+}
+
+/// Errors which can occur when calling the English auction contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum AuctionError {
+    /// Bids are no longer accepted.
+    AuctionEnded,
+    /// The auction is still accepting bids.
+    AuctionNotEnded,
+    /// The bid is below the reserve price or not above the highest bid.
+    BidTooLow,
+    /// The seller cannot bid on their own item.
+    SellerCannotBid,
+    /// The proceeds have already been paid out.
+    AlreadyFinalized,
+}
+
+/// Deposits an English auction event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+contract! {
+    /// An auction in which the price goes up until nobody bids any higher.
+    ///
+    /// Bids are paid in native balance and held by the contract. Every new
+    /// highest bid pays the previous one back. Once the auction has ended, the
+    /// highest bid goes to the seller, and the highest bidder wins the item.
+    struct EnglishAuction {
+        /// The account which sells the item and receives the proceeds.
+        seller: storage::Value<AccountId>,
+        /// The lowest bid the seller accepts.
+        reserve_price: storage::Value<Balance>,
+        /// The last block in which bids are accepted.
+        end: storage::Value<BlockNumber>,
+        /// How close to the end a bid pushes the end back.
+        extension: storage::Value<BlockNumber>,
+        /// The highest bid so far and who placed it.
+        highest_bid: storage::Value<Option<(AccountId, Balance)>>,
+        /// Whether the proceeds have been paid out.
+        finalized: storage::Value<bool>,
+    }
+
+    impl Deploy for EnglishAuction {
+        fn deploy(&mut self, reserve_price: Balance, duration: BlockNumber, extension: BlockNumber) {
+            self.seller.set(env.caller());
+            self.reserve_price.set(reserve_price);
+            self.end.set(env::block_number() + duration);
+            self.extension.set(extension);
+            self.highest_bid.set(None);
+            self.finalized.set(false);
+        }
+    }
+
+    impl EnglishAuction {
+        /// Returns the account which sells the item.
+        pub(external) fn seller(&self) -> AccountId {
+            let seller = *self.seller;
+            env.println(&format!("EnglishAuction::seller = {:?}", seller));
+            seller
+        }
+
+        /// Returns the last block in which bids are accepted.
+        pub(external) fn end(&self) -> BlockNumber {
+            let end = *self.end;
+            env.println(&format!("EnglishAuction::end = {:?}", end));
+            end
+        }
+
+        /// Returns the highest bid so far and who placed it, if anyone did.
+        pub(external) fn highest_bid(&self) -> Option<(AccountId, Balance)> {
+            let highest_bid = *self.highest_bid;
+            env.println(&format!("EnglishAuction::highest_bid = {:?}", highest_bid));
+            highest_bid
+        }
+
+        /// Returns whether the proceeds have been paid out.
+        pub(external) fn finalized(&self) -> bool {
+            let finalized = *self.finalized;
+            env.println(&format!("EnglishAuction::finalized = {:?}", finalized));
+            finalized
+        }
+
+        /// Bids the native balance sent along with the call and pays the
+        /// previous highest bid back to its bidder.
+        ///
+        /// A bid within `extension` blocks of the end moves the end to
+        /// `extension` blocks after the bid, so that others can still answer.
+        pub(external) fn bid(&mut self) -> Result<(), AuctionError> {
+            let now = env.block_number();
+            if now > *self.end {
+                return Err(AuctionError::AuctionEnded)
+            }
+            let bidder = env.caller();
+            if bidder == *self.seller {
+                return Err(AuctionError::SellerCannotBid)
+            }
+            let value = value_transferred();
+            if value < *self.reserve_price {
+                return Err(AuctionError::BidTooLow)
+            }
+            let previous = *self.highest_bid;
+            // ACTION: `if` there is a `previous` highest bid and `value` is not above it,
+            //         exit early and return `Err(AuctionError::BidTooLow)`
+            // ACTION: Store the new `highest_bid`
+            // ACTION: `if` there is a `previous` highest bid, pay it back to its bidder
+            //   HINT: `assert!` that `transfer_native` succeeds, so a failed refund reverts the bid
+            deposit_event(Event::BidPlaced {
+                bidder: bidder,
+                value: value
+            });
+            // ACTION: `if` fewer than `extension` blocks are left until the `end`, move the
+            //         `end` to `extension` blocks after `now` and deposit an `AuctionExtended` event
+            Ok(())
+        }
+
+        /// Pays the highest bid to the seller once the auction has ended.
+        ///
+        /// Anyone can finalize the auction. Without any bids, there is nothing
+        /// to pay out.
+        pub(external) fn finalize(&mut self) -> Result<(), AuctionError> {
+            if env.block_number() <= *self.end {
+                return Err(AuctionError::AuctionNotEnded)
+            }
+            if *self.finalized {
+                return Err(AuctionError::AlreadyFinalized)
+            }
+            self.finalized.set(true);
+            let (winner, value) = match *self.highest_bid {
+                Some((bidder, value)) => (Some(bidder), value),
+                None => (None, 0),
+            };
+            if value > 0 {
+                assert!(transfer_native(*self.seller, value), "the payout failed");
+            }
+            deposit_event(Event::AuctionFinalized {
+                winner: winner,
+                value: value
+            });
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Places a bid of `value` for `bidder`.
+    fn bid(auction: &mut EnglishAuction, bidder: AccountId, value: Balance) -> Result<(), AuctionError> {
+        env::test::set_caller(bidder);
+        set_value_transferred(value);
+        let result = auction.bid();
+        set_value_transferred(0);
+        result
+    }
+
+    /// Deploys an auction at block 0 with a reserve price of 100 which runs
+    /// until block 100 and extends by 10 blocks.
+    fn deploy_mock() -> EnglishAuction {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        EnglishAuction::deploy_mock(100, 100, 10)
+    }
+
+    #[test]
+    fn bids_must_increase() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut auction = deploy_mock();
+        assert_eq!(auction.seller(), alice);
+        assert_eq!(auction.end(), 100);
+        assert_eq!(bid(&mut auction, bob, 99), Err(AuctionError::BidTooLow));
+        assert_eq!(bid(&mut auction, alice, 100), Err(AuctionError::SellerCannotBid));
+        assert_eq!(bid(&mut auction, bob, 100), Ok(()));
+        assert_eq!(auction.highest_bid(), Some((bob, 100)));
+        assert_eq!(bid(&mut auction, charlie, 100), Err(AuctionError::BidTooLow));
+        assert_eq!(auction.highest_bid(), Some((bob, 100)));
+        assert_eq!(payouts(), vec![]);
+    }
+
+    #[test]
+    fn outbid_bidders_are_refunded() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut auction = deploy_mock();
+        assert_eq!(bid(&mut auction, bob, 100), Ok(()));
+        assert_eq!(bid(&mut auction, charlie, 150), Ok(()));
+        assert_eq!(payouts(), vec![(bob, 100)]);
+        // Bob can come back with a higher bid
+        assert_eq!(bid(&mut auction, bob, 200), Ok(()));
+        assert_eq!(payouts(), vec![(bob, 100), (charlie, 150)]);
+        assert_eq!(auction.highest_bid(), Some((bob, 200)));
+    }
+
+    #[test]
+    fn late_bids_extend_the_auction() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut auction = deploy_mock();
+        // Bids well before the end leave it where it is
+        env::test::set_block_number(90);
+        assert_eq!(bid(&mut auction, bob, 100), Ok(()));
+        assert_eq!(auction.end(), 100);
+        // A bid in the last 10 blocks gives others 10 more blocks to answer
+        env::test::set_block_number(95);
+        assert_eq!(bid(&mut auction, charlie, 150), Ok(()));
+        assert_eq!(auction.end(), 105);
+        env::test::set_block_number(105);
+        assert_eq!(bid(&mut auction, bob, 200), Ok(()));
+        assert_eq!(auction.end(), 115);
+        env::test::set_block_number(116);
+        assert_eq!(bid(&mut auction, charlie, 250), Err(AuctionError::AuctionEnded));
+        assert_eq!(auction.highest_bid(), Some((bob, 200)));
+    }
+
+    #[test]
+    fn finalize_pays_the_seller() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut auction = deploy_mock();
+        assert_eq!(bid(&mut auction, bob, 100), Ok(()));
+        assert_eq!(bid(&mut auction, charlie, 150), Ok(()));
+        env::test::set_block_number(100);
+        assert_eq!(auction.finalize(), Err(AuctionError::AuctionNotEnded));
+        env::test::set_block_number(101);
+        // Anyone can finalize, not just the seller
+        assert_eq!(auction.finalize(), Ok(()));
+        assert!(auction.finalized());
+        assert_eq!(auction.finalize(), Err(AuctionError::AlreadyFinalized));
+        assert_eq!(payouts(), vec![(bob, 100), (alice, 150)]);
+    }
+
+    #[test]
+    fn finalize_without_bids_pays_nothing() {
+        let mut auction = deploy_mock();
+        env::test::set_block_number(101);
+        assert_eq!(auction.finalize(), Ok(()));
+        assert_eq!(auction.highest_bid(), None);
+        assert_eq!(payouts(), vec![]);
+    }
+}
@@ -0,0 +1,347 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The stages a Dutch auction goes through.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Status {
+    /// The item is for sale.
+    Open,
+    /// The item has been bought by the given account.
+    Sold(AccountId),
+    /// The seller has taken back the unsold item.
+    Withdrawn,
+}
+
+/// Events deposited by the Dutch auction contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Sold {
+        buyer: AccountId,
+        price: Balance,
+    },
+    Withdrawn {
+        seller: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the Dutch auction contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum AuctionError {
+    /// Less than the current price was sent along with the call.
+    PriceNotMet,
+    /// The auction has run out of time.
+    Expired,
+    /// The auction is still running.
+    NotExpired,
+    /// Only the seller can do this.
+    NotSeller,
+    /// The item has already been sold or withdrawn.
+    NotOpen,
+}
+
+/// Deposits a Dutch auction event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Returns the price `elapsed` blocks into an auction which drops linearly
+/// from `start_price` to `floor_price` over `duration` blocks.
+///
+/// The drop is rounded down, so the price never falls below the curve.
+fn price_at(start_price: Balance, floor_price: Balance, duration: BlockNumber, elapsed: BlockNumber) -> Balance {
+    if elapsed >= duration {
+        return floor_price
+    }
+    // ACTION: Compute the `range` the price drops over the whole auction
+    // ACTION: Compute the `drop` after `elapsed` blocks, `range * elapsed / duration`
+    //   HINT: Convert `duration` and `elapsed` to `Balance` with `Balance::from`
+    //   HINT: `range / duration * elapsed + range % duration * elapsed / duration` cannot overflow
+    // ACTION: Return `start_price - drop`
+}
+
+contract! {
+    /// An auction in which the price drops until somebody buys.
+    ///
+    /// The price starts at `start_price` and falls with every block until it
+    /// reaches `floor_price` after `duration` blocks. The first buyer to pay
+    /// the current price wins the item, and the payment goes straight to the
+    /// seller. If nobody buys before the auction expires, the seller withdraws
+    /// the item.
+    struct DutchAuction {
+        /// The account which sells the item and receives the payment.
+        seller: storage::Value<AccountId>,
+        /// The price at the start of the auction.
+        start_price: storage::Value<Balance>,
+        /// The lowest price the seller accepts.
+        floor_price: storage::Value<Balance>,
+        /// The block in which the auction started.
+        start: storage::Value<BlockNumber>,
+        /// The number of blocks the price takes to reach the floor price,
+        /// after which the auction expires.
+        duration: storage::Value<BlockNumber>,
+        /// Whether the item is still for sale.
+        status: storage::Value<Status>,
+    }
+
+    impl Deploy for DutchAuction {
+        fn deploy(&mut self, start_price: Balance, floor_price: Balance, duration: BlockNumber) {
+            assert!(floor_price <= start_price, "the price can only drop");
+            assert!(duration > 0, "the auction must last at least one block");
+            self.seller.set(env.caller());
+            self.start_price.set(start_price);
+            self.floor_price.set(floor_price);
+            self.start.set(env::block_number());
+            self.duration.set(duration);
+            self.status.set(Status::Open);
+        }
+    }
+
+    impl DutchAuction {
+        /// Returns whether the item is still for sale.
+        pub(external) fn status(&self) -> Status {
+            let status = *self.status;
+            env.println(&format!("DutchAuction::status = {:?}", status));
+            status
+        }
+
+        /// Returns the last block in which the item can be bought.
+        pub(external) fn end(&self) -> BlockNumber {
+            let end = *self.start + *self.duration;
+            env.println(&format!("DutchAuction::end = {:?}", end));
+            end
+        }
+
+        /// Returns the price of the item in the current block.
+        pub(external) fn current_price(&self) -> Balance {
+            let price = self.current_price_impl();
+            env.println(&format!("DutchAuction::current_price = {:?}", price));
+            price
+        }
+
+        /// Buys the item at the current price with the native balance sent
+        /// along with the call, and pays back whatever was sent on top.
+        ///
+        /// If a payout fails, the whole call is reverted.
+        pub(external) fn buy(&mut self) -> Result<Balance, AuctionError> {
+            if *self.status != Status::Open {
+                return Err(AuctionError::NotOpen)
+            }
+            if self.expired() {
+                return Err(AuctionError::Expired)
+            }
+            let price = self.current_price_impl();
+            let value = value_transferred();
+            // ACTION: `if` `value` is less than `price`, exit early and return
+            //         `Err(AuctionError::PriceNotMet)`
+            let buyer = env.caller();
+            // ACTION: Set the `status` to `Status::Sold(buyer)`
+            // ACTION: Pay the `price` to the `seller`, and whatever is left of `value` back to `buyer`
+            //   HINT: `assert!` that every `transfer_native` succeeds
+            deposit_event(Event::Sold {
+                buyer: buyer,
+                price: price
+            });
+            Ok(price)
+        }
+
+        /// Takes the unsold item off sale once the auction has expired.
+        ///
+        /// Only the seller can withdraw the item.
+        pub(external) fn withdraw(&mut self) -> Result<(), AuctionError> {
+            let seller = env.caller();
+            if seller != *self.seller {
+                return Err(AuctionError::NotSeller)
+            }
+            if *self.status != Status::Open {
+                return Err(AuctionError::NotOpen)
+            }
+            if !self.expired() {
+                return Err(AuctionError::NotExpired)
+            }
+            self.status.set(Status::Withdrawn);
+            deposit_event(Event::Withdrawn {
+                seller: seller
+            });
+            Ok(())
+        }
+    }
+
+    impl DutchAuction {
+        /// Returns the price of the item in the current block.
+        fn current_price_impl(&self) -> Balance {
+            let elapsed = env::block_number() - *self.start;
+            price_at(*self.start_price, *self.floor_price, *self.duration, elapsed)
+        }
+
+        /// Returns whether the last block of the auction has passed.
+        fn expired(&self) -> bool {
+            env::block_number() > *self.start + *self.duration
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Buys the item for `buyer`, sending `value` along.
+    fn buy(auction: &mut DutchAuction, buyer: AccountId, value: Balance) -> Result<Balance, AuctionError> {
+        env::test::set_caller(buyer);
+        set_value_transferred(value);
+        let result = auction.buy();
+        set_value_transferred(0);
+        result
+    }
+
+    /// Deploys an auction at block 10 whose price drops from 1000 to 100 over
+    /// 90 blocks.
+    fn deploy_mock() -> DutchAuction {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(10);
+        DutchAuction::deploy_mock(1000, 100, 90)
+    }
+
+    #[test]
+    fn price_drops_linearly() {
+        assert_eq!(price_at(1000, 100, 90, 0), 1000);
+        assert_eq!(price_at(1000, 100, 90, 1), 990);
+        assert_eq!(price_at(1000, 100, 90, 45), 550);
+        assert_eq!(price_at(1000, 100, 90, 89), 110);
+        assert_eq!(price_at(1000, 100, 90, 90), 100);
+    }
+
+    #[test]
+    fn price_stays_at_the_floor() {
+        assert_eq!(price_at(1000, 100, 90, 91), 100);
+        assert_eq!(price_at(1000, 100, 90, BlockNumber::max_value()), 100);
+        // A fixed price never drops
+        assert_eq!(price_at(500, 500, 10, 5), 500);
+    }
+
+    #[test]
+    fn price_rounds_up() {
+        // A third of the range is dropped with every block
+        assert_eq!(price_at(100, 0, 3, 1), 67);
+        assert_eq!(price_at(100, 0, 3, 2), 34);
+        assert_eq!(price_at(100, 0, 3, 3), 0);
+        // Large prices do not overflow
+        let start = Balance::max_value();
+        assert_eq!(price_at(start, 0, 2, 1), start - start / 2);
+    }
+
+    #[test]
+    fn first_buyer_at_the_price_wins() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let mut auction = deploy_mock();
+        assert_eq!(auction.current_price(), 1000);
+        assert_eq!(auction.end(), 100);
+        // Halfway through, the price has dropped to 550
+        env::test::set_block_number(55);
+        assert_eq!(auction.current_price(), 550);
+        assert_eq!(buy(&mut auction, bob, 500), Err(AuctionError::PriceNotMet));
+        // Bob sends a little more to be safe and gets the change back
+        assert_eq!(buy(&mut auction, bob, 600), Ok(550));
+        assert_eq!(auction.status(), Status::Sold(bob));
+        assert_eq!(payouts(), vec![(alice, 550), (bob, 50)]);
+        // Charlie is too late
+        assert_eq!(buy(&mut auction, charlie, 1000), Err(AuctionError::NotOpen));
+        env::test::set_caller(alice);
+        assert_eq!(auction.withdraw(), Err(AuctionError::NotOpen));
+    }
+
+    #[test]
+    fn unsold_items_are_withdrawn_after_expiry() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let mut auction = deploy_mock();
+        // The floor price is still available in the last block
+        env::test::set_block_number(100);
+        assert_eq!(auction.current_price(), 100);
+        env::test::set_caller(alice);
+        assert_eq!(auction.withdraw(), Err(AuctionError::NotExpired));
+        env::test::set_block_number(101);
+        assert_eq!(buy(&mut auction, bob, 100), Err(AuctionError::Expired));
+        assert_eq!(auction.withdraw(), Err(AuctionError::NotSeller));
+        env::test::set_caller(alice);
+        assert_eq!(auction.withdraw(), Ok(()));
+        assert_eq!(auction.status(), Status::Withdrawn);
+        assert_eq!(payouts(), vec![]);
+    }
+}
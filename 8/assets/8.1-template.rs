@@ -0,0 +1,646 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the exchange.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// The share of every swap input which is kept by the pool as a fee,
+/// `FEE_DENOMINATOR - FEE_NUMERATOR` in `FEE_DENOMINATOR`, or 0.3%.
+const FEE_NUMERATOR: Balance = 997;
+const FEE_DENOMINATOR: Balance = 1000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// Events deposited by the exchange contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    LiquidityAdded {
+        provider: AccountId,
+        native: Balance,
+        tokens: Balance,
+        shares: Balance,
+    },
+    LiquidityRemoved {
+        provider: AccountId,
+        native: Balance,
+        tokens: Balance,
+        shares: Balance,
+    },
+    TokensBought {
+        buyer: AccountId,
+        native_sold: Balance,
+        tokens_bought: Balance,
+    },
+    NativeBought {
+        buyer: AccountId,
+        tokens_sold: Balance,
+        native_bought: Balance,
+    },
+}
+
+/// Errors which can occur when calling the exchange contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum ExchangeError {
+    /// Nothing was sent, deposited or withdrawn.
+    ZeroAmount,
+    /// The pool has no liquidity to swap against.
+    NoLiquidity,
+    /// The provider does not hold enough shares.
+    InsufficientShares,
+    /// The price moved beyond the limit set by the caller.
+    SlippageExceeded,
+    /// The token refused to transfer tokens to the exchange.
+    TransferFailed,
+}
+
+/// Deposits an exchange event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns how much of the output reserve a swap of `input` pays out, keeping
+/// `input_reserve * output_reserve` constant after the fee.
+///
+/// The output is rounded down, so the product of the reserves never shrinks.
+fn output_amount(input: Balance, input_reserve: Balance, output_reserve: Balance) -> Balance {
+    // ACTION: `if` `input` is 0, exit early and return 0
+    // ACTION: Compute `input_with_fee`, which is `input * FEE_NUMERATOR`
+    // ACTION: Return `input_with_fee * output_reserve` divided by
+    //         `input_reserve * FEE_DENOMINATOR + input_with_fee`
+}
+
+contract! {
+    /// Swaps native balance for an ERC20 token and back at the price set by a
+    /// liquidity pool.
+    ///
+    /// Liquidity providers deposit both assets at the current ratio and
+    /// receive shares of the pool in return. Swaps keep the product of the two
+    /// reserves constant, so every trade moves the price, and the fee they pay
+    /// is left in the pool for the providers.
+    struct Exchange {
+        /// The ERC20 token traded against native balance.
+        token: storage::Value<AccountId>,
+        /// The native balance held by the pool.
+        native_reserve: storage::Value<Balance>,
+        /// The tokens held by the pool.
+        token_reserve: storage::Value<Balance>,
+        /// The total number of pool shares.
+        total_shares: storage::Value<Balance>,
+        /// The pool shares held by each provider.
+        shares: storage::HashMap<AccountId, Balance>,
+    }
+
+    impl Deploy for Exchange {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.native_reserve.set(0);
+            self.token_reserve.set(0);
+            self.total_shares.set(0);
+        }
+    }
+
+    impl Exchange {
+        /// Returns the ERC20 token traded by the exchange.
+        pub(external) fn token(&self) -> AccountId {
+            let token = *self.token;
+            env.println(&format!("Exchange::token = {:?}", token));
+            token
+        }
+
+        /// Returns the native balance and the tokens held by the pool.
+        pub(external) fn reserves(&self) -> (Balance, Balance) {
+            let reserves = (*self.native_reserve, *self.token_reserve);
+            env.println(&format!("Exchange::reserves = {:?}", reserves));
+            reserves
+        }
+
+        /// Returns the total number of pool shares.
+        pub(external) fn total_shares(&self) -> Balance {
+            let total_shares = *self.total_shares;
+            env.println(&format!("Exchange::total_shares = {:?}", total_shares));
+            total_shares
+        }
+
+        /// Returns the pool shares held by `provider`.
+        pub(external) fn shares_of(&self, provider: AccountId) -> Balance {
+            let shares = self.shares_of_or_zero(&provider);
+            env.println(&format!("Exchange::shares_of(provider = {:?}) = {:?}", provider, shares));
+            shares
+        }
+
+        /// Returns the tokens a swap of `native_sold` would currently buy.
+        pub(external) fn native_to_token_price(&self, native_sold: Balance) -> Balance {
+            let tokens = output_amount(native_sold, *self.native_reserve, *self.token_reserve);
+            env.println(&format!("Exchange::native_to_token_price(native_sold = {:?}) = {:?}", native_sold, tokens));
+            tokens
+        }
+
+        /// Returns the native balance a swap of `tokens_sold` would currently buy.
+        pub(external) fn token_to_native_price(&self, tokens_sold: Balance) -> Balance {
+            let native = output_amount(tokens_sold, *self.token_reserve, *self.native_reserve);
+            env.println(&format!("Exchange::token_to_native_price(tokens_sold = {:?}) = {:?}", tokens_sold, native));
+            native
+        }
+
+        /// Deposits the native balance sent along with the call and the tokens
+        /// at the current ratio into the pool, and mints pool shares for them.
+        ///
+        /// The first provider sets the ratio by depositing `max_tokens`. Later
+        /// providers deposit whatever the ratio asks for, but at most
+        /// `max_tokens`, and receive at least `min_shares`. The exchange must
+        /// be approved to transfer the tokens.
+        pub(external) fn add_liquidity(&mut self, min_shares: Balance, max_tokens: Balance) -> Result<Balance, ExchangeError> {
+            let native = value_transferred();
+            if native == 0 || max_tokens == 0 {
+                return Err(ExchangeError::ZeroAmount)
+            }
+            // ACTION: Compute the `(tokens, shares)` of the deposit
+            //   HINT: The first provider deposits `max_tokens` and receives `native` shares
+            //   HINT: Later providers deposit `native * token_reserve / native_reserve + 1` tokens
+            //         and receive `native * total_shares / native_reserve` shares
+            if shares == 0 {
+                return Err(ExchangeError::ZeroAmount)
+            }
+            if tokens > max_tokens || shares < min_shares {
+                return Err(ExchangeError::SlippageExceeded)
+            }
+            let provider = env.caller();
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(provider, env.address(), tokens).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(ExchangeError::TransferFailed),
+            }
+            // ACTION: Add `native` and `tokens` to the reserves
+            // ACTION: Mint `shares` for the `provider`, and add them to `total_shares`
+            deposit_event(Event::LiquidityAdded {
+                provider: provider,
+                native: native,
+                tokens: tokens,
+                shares: shares
+            });
+            Ok(shares)
+        }
+
+        /// Burns `shares` of the caller and pays out their part of both
+        /// reserves, which must be at least `min_native` and `min_tokens`.
+        ///
+        /// If a payout fails, the whole call is reverted.
+        pub(external) fn remove_liquidity(
+            &mut self,
+            shares: Balance,
+            min_native: Balance,
+            min_tokens: Balance,
+        ) -> Result<(Balance, Balance), ExchangeError> {
+            if shares == 0 {
+                return Err(ExchangeError::ZeroAmount)
+            }
+            let provider = env.caller();
+            let provider_shares = self.shares_of_or_zero(&provider);
+            if provider_shares < shares {
+                return Err(ExchangeError::InsufficientShares)
+            }
+            // ACTION: Compute the `native` and `tokens` the `shares` are worth
+            //   HINT: `shares` are worth `shares * reserve / total_shares` of each reserve
+            if native < min_native || tokens < min_tokens {
+                return Err(ExchangeError::SlippageExceeded)
+            }
+            self.shares.insert(provider, provider_shares - shares);
+            self.total_shares -= shares;
+            self.native_reserve -= native;
+            self.token_reserve -= tokens;
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(provider, tokens).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be paid out");
+            assert!(transfer_native(provider, native), "the payout failed");
+            deposit_event(Event::LiquidityRemoved {
+                provider: provider,
+                native: native,
+                tokens: tokens,
+                shares: shares
+            });
+            Ok((native, tokens))
+        }
+
+        /// Swaps the native balance sent along with the call for at least
+        /// `min_tokens` tokens.
+        ///
+        /// If the tokens cannot be paid out, the whole call is reverted.
+        pub(external) fn swap_native_for_tokens(&mut self, min_tokens: Balance) -> Result<Balance, ExchangeError> {
+            let native_sold = value_transferred();
+            if native_sold == 0 {
+                return Err(ExchangeError::ZeroAmount)
+            }
+            if *self.total_shares == 0 {
+                return Err(ExchangeError::NoLiquidity)
+            }
+            // ACTION: Compute the `tokens_bought` with `output_amount`
+            // ACTION: `if` `tokens_bought` is less than `min_tokens`, exit early and return
+            //         `Err(ExchangeError::SlippageExceeded)`
+            // ACTION: Add `native_sold` to the native reserve and remove `tokens_bought`
+            //         from the token reserve
+            let buyer = env.caller();
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(buyer, tokens_bought).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be paid out");
+            deposit_event(Event::TokensBought {
+                buyer: buyer,
+                native_sold: native_sold,
+                tokens_bought: tokens_bought
+            });
+            Ok(tokens_bought)
+        }
+
+        /// Swaps `tokens_sold` tokens of the caller for at least `min_native`
+        /// native balance. The exchange must be approved to transfer the tokens.
+        ///
+        /// If the payout fails, the whole call is reverted.
+        pub(external) fn swap_tokens_for_native(&mut self, tokens_sold: Balance, min_native: Balance) -> Result<Balance, ExchangeError> {
+            if tokens_sold == 0 {
+                return Err(ExchangeError::ZeroAmount)
+            }
+            if *self.total_shares == 0 {
+                return Err(ExchangeError::NoLiquidity)
+            }
+            // ACTION: Compute the `native_bought` with `output_amount`
+            // ACTION: `if` `native_bought` is less than `min_native`, exit early and return
+            //         `Err(ExchangeError::SlippageExceeded)`
+            let buyer = env.caller();
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(buyer, env.address(), tokens_sold).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(ExchangeError::TransferFailed),
+            }
+            // ACTION: Add `tokens_sold` to the token reserve and remove `native_bought`
+            //         from the native reserve
+            assert!(transfer_native(buyer, native_bought), "the payout failed");
+            deposit_event(Event::NativeBought {
+                buyer: buyer,
+                tokens_sold: tokens_sold,
+                native_bought: native_bought
+            });
+            Ok(native_bought)
+        }
+    }
+
+    impl Exchange {
+        /// Returns the shares of the AccountId or 0 if there are none.
+        fn shares_of_or_zero(&self, of: &AccountId) -> Balance {
+            *self.shares.get(of).unwrap_or(&0)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the exchange through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the exchange first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Adds `native` and up to `max_tokens` of liquidity for `provider`.
+    fn add_liquidity(
+        exchange: &mut Exchange,
+        provider: AccountId,
+        native: Balance,
+        max_tokens: Balance,
+    ) -> Result<Balance, ExchangeError> {
+        env::test::set_caller(provider);
+        set_value_transferred(native);
+        let result = exchange.add_liquidity(0, max_tokens);
+        set_value_transferred(0);
+        result
+    }
+
+    /// Swaps `native` for tokens for `buyer`.
+    fn swap_native(exchange: &mut Exchange, buyer: AccountId, native: Balance) -> Result<Balance, ExchangeError> {
+        env::test::set_caller(buyer);
+        set_value_transferred(native);
+        let result = exchange.swap_native_for_tokens(0);
+        set_value_transferred(0);
+        result
+    }
+
+    /// Returns a stream of pseudo-random numbers below `limit`, which is the
+    /// same in every run so that failures can be reproduced.
+    fn pseudo_random(limit: Balance) -> impl FnMut() -> Balance {
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Balance::from(state) % limit + 1
+        }
+    }
+
+    /// Deploys an exchange for a mock token, and gives Alice, Bob and Charlie
+    /// 1_000_000 tokens each.
+    fn deploy_mock() -> (Exchange, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let balances = register_token(token);
+        for account in 0..3 {
+            balances.borrow_mut().insert(AccountId::try_from([account; 32]).unwrap(), 1_000_000);
+        }
+        (Exchange::deploy_mock(token), balances)
+    }
+
+    #[test]
+    fn output_amount_keeps_the_product() {
+        // Without the fee, 100 of 1000 would buy 10000 * 100 / 1100 = 909
+        assert_eq!(output_amount(100, 1000, 10000), 906);
+        assert_eq!(output_amount(0, 1000, 10000), 0);
+        // The output can never drain the reserve
+        assert_eq!(output_amount(1_000_000_000, 1000, 10000), 9999);
+    }
+
+    #[test]
+    fn first_provider_sets_the_price() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let (mut exchange, balances) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, alice, 0, 1000), Err(ExchangeError::ZeroAmount));
+        assert_eq!(add_liquidity(&mut exchange, alice, 1000, 0), Err(ExchangeError::ZeroAmount));
+        assert_eq!(add_liquidity(&mut exchange, alice, 1000, 10000), Ok(1000));
+        assert_eq!(exchange.reserves(), (1000, 10000));
+        assert_eq!(exchange.shares_of(alice), 1000);
+        assert_eq!(exchange.total_shares(), 1000);
+        assert_eq!(balances.borrow()[&alice], 990_000);
+        assert_eq!(balances.borrow()[&env::address()], 10000);
+    }
+
+    #[test]
+    fn later_providers_follow_the_ratio() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut exchange, balances) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, alice, 1000, 10000), Ok(1000));
+        // Half the native reserve asks for half the token reserve, rounded up
+        assert_eq!(add_liquidity(&mut exchange, bob, 500, 5000), Err(ExchangeError::SlippageExceeded));
+        assert_eq!(add_liquidity(&mut exchange, bob, 500, 5001), Ok(500));
+        assert_eq!(exchange.reserves(), (1500, 15001));
+        assert_eq!(balances.borrow()[&bob], 994_999);
+        env::test::set_caller(bob);
+        set_value_transferred(500);
+        assert_eq!(exchange.add_liquidity(501, 6000), Err(ExchangeError::SlippageExceeded));
+    }
+
+    #[test]
+    fn add_liquidity_needs_tokens() {
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let (mut exchange, _) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, dave, 1000, 10000), Err(ExchangeError::TransferFailed));
+        assert_eq!(exchange.total_shares(), 0);
+    }
+
+    #[test]
+    fn swaps_move_the_price() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut exchange, balances) = deploy_mock();
+        assert_eq!(swap_native(&mut exchange, bob, 100), Err(ExchangeError::NoLiquidity));
+        assert_eq!(add_liquidity(&mut exchange, alice, 1000, 10000), Ok(1000));
+        assert_eq!(exchange.native_to_token_price(100), 906);
+        assert_eq!(swap_native(&mut exchange, bob, 100), Ok(906));
+        assert_eq!(exchange.reserves(), (1100, 9094));
+        assert_eq!(balances.borrow()[&bob], 1_000_906);
+        // The same swap now buys fewer tokens
+        assert_eq!(exchange.native_to_token_price(100), 755);
+        // Selling the tokens back returns a little less than Bob paid
+        env::test::set_caller(bob);
+        assert_eq!(exchange.swap_tokens_for_native(906, 100), Err(ExchangeError::SlippageExceeded));
+        assert_eq!(exchange.swap_tokens_for_native(906, 99), Ok(99));
+        assert_eq!(exchange.reserves(), (1001, 10000));
+        assert_eq!(payouts(), vec![(bob, 99)]);
+        assert_eq!(exchange.swap_native_for_tokens(0), Err(ExchangeError::ZeroAmount));
+    }
+
+    #[test]
+    fn providers_earn_the_fees() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut exchange, balances) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, alice, 1000, 10000), Ok(1000));
+        assert_eq!(swap_native(&mut exchange, bob, 100), Ok(906));
+        env::test::set_caller(bob);
+        assert_eq!(exchange.swap_tokens_for_native(906, 0), Ok(99));
+        env::test::set_caller(alice);
+        assert_eq!(exchange.remove_liquidity(1001, 0, 0), Err(ExchangeError::InsufficientShares));
+        assert_eq!(exchange.remove_liquidity(1000, 1002, 0), Err(ExchangeError::SlippageExceeded));
+        assert_eq!(exchange.remove_liquidity(1000, 1001, 10000), Ok((1001, 10000)));
+        assert_eq!(exchange.reserves(), (0, 0));
+        assert_eq!(exchange.total_shares(), 0);
+        assert_eq!(balances.borrow()[&alice], 1_000_000);
+        assert_eq!(payouts(), vec![(bob, 99), (alice, 1001)]);
+    }
+
+    #[test]
+    fn swaps_never_shrink_the_product() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let (mut exchange, _) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, alice, 10000, 100_000), Ok(10000));
+        let mut random = pseudo_random(1000);
+        for _ in 0..200 {
+            let (native_before, tokens_before) = exchange.reserves();
+            let amount = random();
+            if amount % 2 == 0 {
+                assert!(swap_native(&mut exchange, bob, amount).is_ok());
+            } else {
+                env::test::set_caller(bob);
+                assert!(exchange.swap_tokens_for_native(amount, 0).is_ok());
+            }
+            let (native_after, tokens_after) = exchange.reserves();
+            assert!(native_after * tokens_after >= native_before * tokens_before);
+            assert!(native_after > 0 && tokens_after > 0);
+        }
+    }
+
+    #[test]
+    fn liquidity_round_trips_never_profit() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut exchange, balances) = deploy_mock();
+        assert_eq!(add_liquidity(&mut exchange, alice, 10000, 100_000), Ok(10000));
+        let mut random = pseudo_random(5000);
+        for _ in 0..100 {
+            let native = random() + 10;
+            let tokens_before = balances.borrow()[&charlie];
+            let shares = add_liquidity(&mut exchange, charlie, native, 1_000_000).unwrap();
+            let tokens_paid = tokens_before - balances.borrow()[&charlie];
+            env::test::set_caller(charlie);
+            let (native_back, tokens_back) = exchange.remove_liquidity(shares, 0, 0).unwrap();
+            assert!(native_back <= native && tokens_back <= tokens_paid);
+            // No share is ever backed by less than when Alice created it
+            let (native_reserve, tokens_reserve) = exchange.reserves();
+            let total_shares = exchange.total_shares();
+            assert!(native_reserve >= total_shares);
+            assert!(native_reserve * tokens_reserve >= 10 * total_shares * total_shares);
+            // Swaps in between keep the price moving
+            if native % 2 == 0 {
+                assert!(swap_native(&mut exchange, bob, native / 10 + 1).is_ok());
+            }
+        }
+    }
+}
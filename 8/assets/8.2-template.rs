@@ -0,0 +1,594 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the order book.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// Returns the native balance transferred along with the current call.
+#[cfg(not(all(test, feature = "test-env")))]
+fn value_transferred() -> Balance {
+    env::value_transferred()
+}
+
+/// Returns the native balance transferred along with the current call in the
+/// test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn value_transferred() -> Balance {
+    tests::mock_value_transferred()
+}
+
+/// Sends `value` of the contract's native balance to `to`.
+///
+/// Returns `false` if the transfer failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    env::transfer(to, value).is_ok()
+}
+
+/// Records a transfer of the contract's native balance in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn transfer_native(to: AccountId, value: Balance) -> bool {
+    tests::mock_transfer_native(to, value)
+}
+
+/// The identifier of an order placed in the order book.
+type OrderId = u32;
+
+/// Which way an order trades the token.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum Side {
+    /// The maker buys tokens and pays native balance.
+    Buy,
+    /// The maker sells tokens for native balance.
+    Sell,
+}
+
+/// A limit order waiting to be filled.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Order {
+    /// The account which placed the order.
+    maker: AccountId,
+    /// Whether the maker buys or sells tokens.
+    side: Side,
+    /// The native balance paid for every token.
+    price: Balance,
+    /// The tokens which are still left to be traded.
+    amount: Balance,
+}
+
+/// Events deposited by the order book contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    OrderPlaced {
+        id: OrderId,
+        maker: AccountId,
+        side: Side,
+        price: Balance,
+        amount: Balance,
+    },
+    OrderFilled {
+        id: OrderId,
+        taker: AccountId,
+        amount: Balance,
+        remaining: Balance,
+    },
+    OrderCancelled {
+        id: OrderId,
+    },
+}
+
+/// Errors which can occur when calling the order book contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum OrderBookError {
+    /// Orders must trade at least one token at a price above zero.
+    ZeroAmount,
+    /// The whole cost of the order does not fit into a `Balance`.
+    CostOverflow,
+    /// The native balance sent along with the call does not match the price.
+    IncorrectValue,
+    /// There is no open order with this id.
+    OrderNotFound,
+    /// Only the maker can cancel an order.
+    NotMaker,
+    /// The fill is larger than what is left of the order.
+    AmountExceedsOrder,
+    /// Only a buy order can be matched with a sell order.
+    WrongSide,
+    /// The buy order does not pay the price of the sell order.
+    PricesDoNotCross,
+    /// The token refused to transfer tokens.
+    TransferFailed,
+}
+
+/// Deposits an order book event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Trades an ERC20 token against native balance with limit orders.
+    ///
+    /// Makers place orders to buy or sell tokens at a fixed price, and the
+    /// order book holds what they offer in escrow: the tokens of a sell order,
+    /// or the native balance of a buy order. Takers fill orders in part or in
+    /// full, and crossing buy and sell orders can be matched with each other.
+    /// Makers cancel what is left of their orders to get their escrow back.
+    struct OrderBook {
+        /// The ERC20 token traded against native balance.
+        token: storage::Value<AccountId>,
+        /// The number of orders placed, which is also the next id.
+        order_count: storage::Value<OrderId>,
+        /// The open orders by id.
+        orders: storage::HashMap<OrderId, Order>,
+    }
+
+    impl Deploy for OrderBook {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.order_count.set(0);
+        }
+    }
+
+    impl OrderBook {
+        /// Returns the number of orders placed so far.
+        pub(external) fn order_count(&self) -> OrderId {
+            let order_count = *self.order_count;
+            env.println(&format!("OrderBook::order_count = {:?}", order_count));
+            order_count
+        }
+
+        /// Returns the order with the given id, if it is still open.
+        pub(external) fn order(&self, id: OrderId) -> Option<Order> {
+            let order = self.orders.get(&id).cloned();
+            env.println(&format!("OrderBook::order(id = {:?}) = {:?}", id, order));
+            order
+        }
+
+        /// Places an order to sell `amount` tokens for `price` each.
+        ///
+        /// The tokens are held in escrow until the order is filled or
+        /// cancelled, so the order book must be approved to transfer them.
+        pub(external) fn place_sell_order(&mut self, amount: Balance, price: Balance) -> Result<OrderId, OrderBookError> {
+            if amount == 0 || price == 0 {
+                return Err(OrderBookError::ZeroAmount)
+            }
+            amount.checked_mul(price).ok_or(OrderBookError::CostOverflow)?;
+            let maker = env.caller();
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(maker, env.address(), amount).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(OrderBookError::TransferFailed),
+            }
+            Ok(self.place_order(maker, Side::Sell, price, amount))
+        }
+
+        /// Places an order to buy `amount` tokens for `price` each.
+        ///
+        /// The whole cost of the order must be sent along with the call, and
+        /// is held in escrow until the order is filled or cancelled.
+        pub(external) fn place_buy_order(&mut self, amount: Balance, price: Balance) -> Result<OrderId, OrderBookError> {
+            if amount == 0 || price == 0 {
+                return Err(OrderBookError::ZeroAmount)
+            }
+            let cost = amount.checked_mul(price).ok_or(OrderBookError::CostOverflow)?;
+            if value_transferred() != cost {
+                return Err(OrderBookError::IncorrectValue)
+            }
+            Ok(self.place_order(env.caller(), Side::Buy, price, amount))
+        }
+
+        /// Trades `amount` tokens with the order `id` at its price.
+        ///
+        /// Filling a sell order buys its tokens, and the price of the tokens
+        /// must be sent along with the call. Filling a buy order sells tokens
+        /// to its maker, so the order book must be approved to transfer them.
+        ///
+        /// If a payout fails, the whole call is reverted.
+        pub(external) fn fill_order(&mut self, id: OrderId, amount: Balance) -> Result<(), OrderBookError> {
+            let mut order = self.orders.get(&id).cloned().ok_or(OrderBookError::OrderNotFound)?;
+            if amount == 0 {
+                return Err(OrderBookError::ZeroAmount)
+            }
+            if amount > order.amount {
+                return Err(OrderBookError::AmountExceedsOrder)
+            }
+            let cost = amount * order.price;
+            let value = value_transferred();
+            let taker = env.caller();
+            // ACTION: `match` on the `side` of the `order`
+            //   HINT: A taker filling a sell order must send exactly `cost`
+            //   HINT: A taker filling a buy order sends nothing, and `transfer_from`s the tokens
+            //         straight to the `maker`
+            // ACTION: Subtract `amount` from the `order` and store it with `update_order`
+            // ACTION: Pay out the trade
+            //   HINT: For a sell order, `pay_tokens` to the `taker` and `transfer_native` the `cost`
+            //         to the `maker`
+            //   HINT: For a buy order, `transfer_native` the `cost` to the `taker` from the escrow
+            deposit_event(Event::OrderFilled {
+                id: id,
+                taker: taker,
+                amount: amount,
+                remaining: order.amount
+            });
+            Ok(())
+        }
+
+        /// Trades as many tokens as possible between the buy order `buy_id`
+        /// and the sell order `sell_id`, if the buyer pays at least the price
+        /// of the seller.
+        ///
+        /// The trade happens at the price of the sell order, and the buyer gets
+        /// back the difference from the escrow. Anyone can match orders.
+        pub(external) fn match_orders(&mut self, buy_id: OrderId, sell_id: OrderId) -> Result<Balance, OrderBookError> {
+            let mut buy = self.orders.get(&buy_id).cloned().ok_or(OrderBookError::OrderNotFound)?;
+            let mut sell = self.orders.get(&sell_id).cloned().ok_or(OrderBookError::OrderNotFound)?;
+            if buy.side != Side::Buy || sell.side != Side::Sell {
+                return Err(OrderBookError::WrongSide)
+            }
+            if buy.price < sell.price {
+                return Err(OrderBookError::PricesDoNotCross)
+            }
+            // ACTION: The `amount` traded is the smaller `amount` of both orders
+            // ACTION: Subtract `amount` from both orders and store them with `update_order`
+            // ACTION: Pay `amount` tokens to the buyer, and `amount * sell.price` to the seller
+            // ACTION: Pay the `surplus`, `amount * (buy.price - sell.price)`, back to the buyer
+            let taker = env.caller();
+            deposit_event(Event::OrderFilled {
+                id: buy_id,
+                taker: taker,
+                amount: amount,
+                remaining: buy.amount
+            });
+            deposit_event(Event::OrderFilled {
+                id: sell_id,
+                taker: taker,
+                amount: amount,
+                remaining: sell.amount
+            });
+            Ok(amount)
+        }
+
+        /// Cancels what is left of the order `id` and pays its escrow back.
+        ///
+        /// Only the maker can cancel an order. If the payout fails, the whole
+        /// call is reverted.
+        pub(external) fn cancel_order(&mut self, id: OrderId) -> Result<(), OrderBookError> {
+            let order = self.orders.get(&id).cloned().ok_or(OrderBookError::OrderNotFound)?;
+            if env.caller() != order.maker {
+                return Err(OrderBookError::NotMaker)
+            }
+            self.orders.remove(&id);
+            match order.side {
+                Side::Sell => self.pay_tokens(order.maker, order.amount),
+                Side::Buy => assert!(transfer_native(order.maker, order.amount * order.price), "the refund failed"),
+            }
+            deposit_event(Event::OrderCancelled {
+                id: id
+            });
+            Ok(())
+        }
+    }
+
+    impl OrderBook {
+        /// Stores a new order and returns its id.
+        fn place_order(&mut self, maker: AccountId, side: Side, price: Balance, amount: Balance) -> OrderId {
+            let id = *self.order_count;
+            self.order_count += 1;
+            self.orders.insert(id, Order {
+                maker: maker,
+                side: side,
+                price: price,
+                amount: amount
+            });
+            deposit_event(Event::OrderPlaced {
+                id: id,
+                maker: maker,
+                side: side,
+                price: price,
+                amount: amount
+            });
+            id
+        }
+
+        /// Stores what is left of an order, or removes it once it is filled.
+        fn update_order(&mut self, id: OrderId, order: Order) {
+            // ACTION: `remove` the order if its `amount` is 0, and `insert` it otherwise
+        }
+
+        /// Pays `amount` tokens from the escrow to `to`.
+        fn pay_tokens(&self, to: AccountId, amount: Balance) {
+            let result: Option<Result<(), u8>> =
+                call_contract(*self.token, TRANSFER_SELECTOR, &(to, amount).encode());
+            assert_eq!(result, Some(Ok(())), "the tokens could not be paid out");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the order book through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The native balance transferred along with the next call.
+        static VALUE_TRANSFERRED: RefCell<Balance> = RefCell::new(0);
+        /// The native balance paid out by the contract so far: (recipient, value)
+        static PAYOUTS: RefCell<Vec<(AccountId, Balance)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the order book first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Sends `value` of native balance along with the following calls.
+    fn set_value_transferred(value: Balance) {
+        VALUE_TRANSFERRED.with(|current| *current.borrow_mut() = value)
+    }
+
+    /// Returns the native balance transferred along with the current call.
+    pub(super) fn mock_value_transferred() -> Balance {
+        VALUE_TRANSFERRED.with(|current| *current.borrow())
+    }
+
+    /// Records a payout of native balance made by the contract.
+    pub(super) fn mock_transfer_native(to: AccountId, value: Balance) -> bool {
+        PAYOUTS.with(|payouts| payouts.borrow_mut().push((to, value)));
+        true
+    }
+
+    /// Returns the native balance paid out by the contract so far.
+    fn payouts() -> Vec<(AccountId, Balance)> {
+        PAYOUTS.with(|payouts| payouts.borrow().clone())
+    }
+
+    /// Places a buy order for `maker`, sending the whole cost along.
+    fn place_buy_order(book: &mut OrderBook, maker: AccountId, amount: Balance, price: Balance) -> Result<OrderId, OrderBookError> {
+        env::test::set_caller(maker);
+        set_value_transferred(amount * price);
+        let result = book.place_buy_order(amount, price);
+        set_value_transferred(0);
+        result
+    }
+
+    /// Fills `amount` of the order `id` for `taker`, sending `value` along.
+    fn fill_order(book: &mut OrderBook, taker: AccountId, id: OrderId, amount: Balance, value: Balance) -> Result<(), OrderBookError> {
+        env::test::set_caller(taker);
+        set_value_transferred(value);
+        let result = book.fill_order(id, amount);
+        set_value_transferred(0);
+        result
+    }
+
+    /// Deploys an order book for a mock token, and gives Alice and Bob 1000
+    /// tokens each.
+    fn deploy_mock() -> (OrderBook, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 1000);
+        balances.borrow_mut().insert(bob, 1000);
+        (OrderBook::deploy_mock(token), balances)
+    }
+
+    #[test]
+    fn sell_orders_hold_tokens_in_escrow() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, balances) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(book.place_sell_order(0, 5), Err(OrderBookError::ZeroAmount));
+        assert_eq!(book.place_sell_order(1001, 5), Err(OrderBookError::TransferFailed));
+        assert_eq!(book.place_sell_order(2, Balance::max_value()), Err(OrderBookError::CostOverflow));
+        assert_eq!(book.place_sell_order(100, 5), Ok(0));
+        assert_eq!(book.order(0), Some(Order { maker: alice, side: Side::Sell, price: 5, amount: 100 }));
+        assert_eq!(balances.borrow()[&alice], 900);
+        assert_eq!(balances.borrow()[&env::address()], 100);
+        // Charlie has no tokens to sell
+        env::test::set_caller(charlie);
+        assert_eq!(book.place_sell_order(100, 5), Err(OrderBookError::TransferFailed));
+        assert_eq!(book.order_count(), 1);
+    }
+
+    #[test]
+    fn buy_orders_hold_native_balance_in_escrow() {
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, _) = deploy_mock();
+        env::test::set_caller(charlie);
+        set_value_transferred(499);
+        assert_eq!(book.place_buy_order(100, 5), Err(OrderBookError::IncorrectValue));
+        set_value_transferred(0);
+        assert_eq!(place_buy_order(&mut book, charlie, 100, 0), Err(OrderBookError::ZeroAmount));
+        assert_eq!(place_buy_order(&mut book, charlie, 100, 5), Ok(0));
+        assert_eq!(book.order(0), Some(Order { maker: charlie, side: Side::Buy, price: 5, amount: 100 }));
+    }
+
+    #[test]
+    fn sell_orders_fill_partially() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, balances) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(book.place_sell_order(100, 5), Ok(0));
+        assert_eq!(fill_order(&mut book, charlie, 0, 30, 149), Err(OrderBookError::IncorrectValue));
+        assert_eq!(fill_order(&mut book, charlie, 0, 30, 150), Ok(()));
+        assert_eq!(book.order(0).unwrap().amount, 70);
+        assert_eq!(balances.borrow()[&charlie], 30);
+        assert_eq!(fill_order(&mut book, charlie, 0, 71, 355), Err(OrderBookError::AmountExceedsOrder));
+        // Filling the rest closes the order
+        assert_eq!(fill_order(&mut book, charlie, 0, 70, 350), Ok(()));
+        assert_eq!(book.order(0), None);
+        assert_eq!(balances.borrow()[&charlie], 100);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+        assert_eq!(payouts(), vec![(alice, 150), (alice, 350)]);
+        assert_eq!(fill_order(&mut book, charlie, 0, 1, 5), Err(OrderBookError::OrderNotFound));
+    }
+
+    #[test]
+    fn buy_orders_fill_partially() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, balances) = deploy_mock();
+        assert_eq!(place_buy_order(&mut book, charlie, 100, 5), Ok(0));
+        // Bob sells tokens, so he does not send any native balance
+        assert_eq!(fill_order(&mut book, bob, 0, 40, 200), Err(OrderBookError::IncorrectValue));
+        assert_eq!(fill_order(&mut book, bob, 0, 40, 0), Ok(()));
+        assert_eq!(balances.borrow()[&charlie], 40);
+        assert_eq!(balances.borrow()[&bob], 960);
+        assert_eq!(book.order(0).unwrap().amount, 60);
+        assert_eq!(payouts(), vec![(bob, 200)]);
+    }
+
+    #[test]
+    fn cancel_pays_back_the_rest() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, balances) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(book.place_sell_order(100, 5), Ok(0));
+        assert_eq!(place_buy_order(&mut book, charlie, 50, 2), Ok(1));
+        assert_eq!(fill_order(&mut book, charlie, 0, 30, 150), Ok(()));
+        assert_eq!(book.cancel_order(0), Err(OrderBookError::NotMaker));
+        env::test::set_caller(alice);
+        assert_eq!(book.cancel_order(0), Ok(()));
+        assert_eq!(book.cancel_order(0), Err(OrderBookError::OrderNotFound));
+        assert_eq!(balances.borrow()[&alice], 970);
+        env::test::set_caller(charlie);
+        assert_eq!(book.cancel_order(1), Ok(()));
+        assert_eq!(payouts(), vec![(alice, 150), (charlie, 100)]);
+    }
+
+    #[test]
+    fn crossing_orders_match_at_the_sell_price() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut book, balances) = deploy_mock();
+        env::test::set_caller(alice);
+        assert_eq!(book.place_sell_order(100, 5), Ok(0));
+        assert_eq!(place_buy_order(&mut book, charlie, 60, 4), Ok(1));
+        assert_eq!(book.match_orders(1, 0), Err(OrderBookError::PricesDoNotCross));
+        assert_eq!(place_buy_order(&mut book, charlie, 60, 6), Ok(2));
+        assert_eq!(book.match_orders(0, 2), Err(OrderBookError::WrongSide));
+        // Bob matches the orders; Charlie gets the tokens at Alice's price
+        env::test::set_caller(bob);
+        assert_eq!(book.match_orders(2, 0), Ok(60));
+        assert_eq!(book.order(2), None);
+        assert_eq!(book.order(0).unwrap().amount, 40);
+        assert_eq!(balances.borrow()[&charlie], 60);
+        assert_eq!(payouts(), vec![(alice, 300), (charlie, 60)]);
+        // The rest of the sell order matches the next buy order
+        assert_eq!(place_buy_order(&mut book, charlie, 50, 5), Ok(3));
+        assert_eq!(book.match_orders(3, 0), Ok(40));
+        assert_eq!(book.order(0), None);
+        assert_eq!(book.order(3).unwrap().amount, 10);
+        assert_eq!(balances.borrow()[&charlie], 100);
+    }
+}
@@ -0,0 +1,432 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The identifier of an operation queued in the timelock.
+type OperationId = u32;
+
+/// The number of blocks after its eta in which an operation can still be
+/// executed, about a week with 6 second blocks.
+const GRACE_PERIOD: BlockNumber = 100_800;
+
+/// A call which is waiting for its delay to pass.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+struct Operation {
+    /// The account or contract which is called.
+    target: AccountId,
+    /// The native balance sent along with the call.
+    value: Balance,
+    /// The selector of the called message.
+    selector: [u8; 4],
+    /// The SCALE encoded arguments of the called message.
+    input: Vec<u8>,
+    /// The first block in which the call can be made.
+    eta: BlockNumber,
+}
+
+/// Events deposited by the timelock contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    Queued {
+        id: OperationId,
+        target: AccountId,
+        selector: [u8; 4],
+        eta: BlockNumber,
+    },
+    Executed {
+        id: OperationId,
+    },
+    Cancelled {
+        id: OperationId,
+    },
+    DelayChanged {
+        delay: BlockNumber,
+    },
+    AdminChanged {
+        admin: AccountId,
+    },
+}
+
+/// Errors which can occur when calling the timelock contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum TimelockError {
+    /// Only the admin can queue and cancel operations.
+    NotAdmin,
+    /// The settings of the timelock can only be changed by its own operations.
+    NotTimelock,
+    /// The eta does not leave at least `delay` blocks.
+    EtaTooEarly,
+    /// No operation with the given id is queued.
+    UnknownOperation,
+    /// The eta of the operation has not been reached yet.
+    NotReady,
+    /// The grace period of the operation has passed.
+    Expired,
+    /// The call of the operation failed.
+    ExecutionFailed,
+}
+
+/// Deposits a timelock event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// The gas limit of calls made by the timelock.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Makes the call of `operation`, sending its value along.
+///
+/// Returns `false` if the call failed.
+#[cfg(not(all(test, feature = "test-env")))]
+fn make_call(operation: &Operation) -> bool {
+    let mut call_data = operation.selector.to_vec();
+    call_data.extend_from_slice(&operation.input);
+    env::call_invoke(operation.target, CALL_GAS_LIMIT, operation.value, &call_data[..]).is_ok()
+}
+
+/// Records the call of `operation` in the test environment.
+#[cfg(all(test, feature = "test-env"))]
+fn make_call(operation: &Operation) -> bool {
+    tests::mock_make_call(operation)
+}
+
+contract! {
+    /// Delays privileged calls, so that everyone can see them coming.
+    ///
+    /// The admin queues calls with an eta at least `delay` blocks in the
+    /// future, and can cancel them until they are executed. Once the eta has
+    /// been reached, anyone can execute the call, until its grace period has
+    /// passed. Contracts which hand their admin role to the timelock give their
+    /// users time to react to every change, or to leave.
+    struct Timelock {
+        /// The account which queues and cancels operations.
+        admin: storage::Value<AccountId>,
+        /// The least number of blocks between queueing and executing.
+        delay: storage::Value<BlockNumber>,
+        /// The number of operations queued, which is also the next id.
+        operation_count: storage::Value<OperationId>,
+        /// The queued operations by id.
+        operations: storage::HashMap<OperationId, Operation>,
+    }
+
+    impl Deploy for Timelock {
+        fn deploy(&mut self, admin: AccountId, delay: BlockNumber) {
+            self.admin.set(admin);
+            self.delay.set(delay);
+            self.operation_count.set(0);
+        }
+    }
+
+    impl Timelock {
+        /// Returns the account which queues and cancels operations.
+        pub(external) fn admin(&self) -> AccountId {
+            let admin = *self.admin;
+            env.println(&format!("Timelock::admin = {:?}", admin));
+            admin
+        }
+
+        /// Returns the least number of blocks between queueing and executing.
+        pub(external) fn delay(&self) -> BlockNumber {
+            let delay = *self.delay;
+            env.println(&format!("Timelock::delay = {:?}", delay));
+            delay
+        }
+
+        /// Returns the number of operations queued so far.
+        pub(external) fn operation_count(&self) -> OperationId {
+            let operation_count = *self.operation_count;
+            env.println(&format!("Timelock::operation_count = {:?}", operation_count));
+            operation_count
+        }
+
+        /// Returns the operation with the given id, if it is still queued.
+        pub(external) fn operation(&self, id: OperationId) -> Option<Operation> {
+            let operation = self.operations.get(&id).cloned();
+            env.println(&format!("Timelock::operation(id = {:?}) = {:?}", id, operation));
+            operation
+        }
+
+        /// Queues a call of the message `selector` of `target` with the SCALE
+        /// encoded `input`, sending `value` along, which can be executed from
+        /// block `eta` on.
+        ///
+        /// Only the admin can queue operations.
+        pub(external) fn queue(
+            &mut self,
+            target: AccountId,
+            value: Balance,
+            selector: [u8; 4],
+            input: Vec<u8>,
+            eta: BlockNumber,
+        ) -> Result<OperationId, TimelockError> {
+            // ACTION: Use `ensure_admin` to check the caller
+            // ACTION: `if` `eta` is earlier than the current block plus `delay`, exit early
+            //         and return `Err(TimelockError::EtaTooEarly)`
+            let id = *self.operation_count;
+            self.operation_count += 1;
+            self.operations.insert(id, Operation {
+                target: target,
+                value: value,
+                selector: selector,
+                input: input,
+                eta: eta
+            });
+            deposit_event(Event::Queued {
+                id: id,
+                target: target,
+                selector: selector,
+                eta: eta
+            });
+            Ok(id)
+        }
+
+        /// Removes a queued operation, so it can never be executed.
+        ///
+        /// Only the admin can cancel operations.
+        pub(external) fn cancel(&mut self, id: OperationId) -> Result<(), TimelockError> {
+            self.ensure_admin(&env.caller())?;
+            if self.operations.remove(&id).is_none() {
+                return Err(TimelockError::UnknownOperation)
+            }
+            deposit_event(Event::Cancelled {
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Makes the call of a queued operation whose eta has been reached.
+        ///
+        /// Anyone can execute an operation. If the call fails, the operation
+        /// stays queued, so it can be tried again until it expires.
+        pub(external) fn execute(&mut self, id: OperationId) -> Result<(), TimelockError> {
+            let operation = self.operations.get(&id).cloned().ok_or(TimelockError::UnknownOperation)?;
+            let now = env.block_number();
+            // ACTION: Return `Err(TimelockError::NotReady)` before the `eta` of the `operation`
+            // ACTION: Return `Err(TimelockError::Expired)` after `eta + GRACE_PERIOD`
+            // ACTION: `remove` the operation, so the call cannot execute it again
+            // ACTION: Call `make_call`, and if it fails, `insert` the operation again and
+            //         return `Err(TimelockError::ExecutionFailed)`
+            deposit_event(Event::Executed {
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Changes the least number of blocks between queueing and executing.
+        ///
+        /// Only the timelock itself can change its delay, so the change has to
+        /// wait for the current delay.
+        pub(external) fn set_delay(&mut self, delay: BlockNumber) -> Result<(), TimelockError> {
+            // ACTION: Use `ensure_timelock` to check the caller
+            // ACTION: Set the new `delay`
+            deposit_event(Event::DelayChanged {
+                delay: delay
+            });
+            Ok(())
+        }
+
+        /// Hands the admin role to `admin`.
+        ///
+        /// Only the timelock itself can change its admin.
+        pub(external) fn set_admin(&mut self, admin: AccountId) -> Result<(), TimelockError> {
+            self.ensure_timelock(&env.caller())?;
+            self.admin.set(admin);
+            deposit_event(Event::AdminChanged {
+                admin: admin
+            });
+            Ok(())
+        }
+    }
+
+    impl Timelock {
+        /// Returns an error if `caller` is not the admin.
+        fn ensure_admin(&self, caller: &AccountId) -> Result<(), TimelockError> {
+            if *caller != *self.admin {
+                return Err(TimelockError::NotAdmin)
+            }
+            Ok(())
+        }
+
+        /// Returns an error if `caller` is not the timelock itself.
+        fn ensure_timelock(&self, caller: &AccountId) -> Result<(), TimelockError> {
+            // ACTION: `if` `caller` is not `env::address()`, exit early and return
+            //         `Err(TimelockError::NotTimelock)`
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        convert::TryFrom,
+    };
+
+    thread_local! {
+        /// The calls made by the timelock so far: (target, value, selector, input)
+        static CALLS: RefCell<Vec<(AccountId, Balance, [u8; 4], Vec<u8>)>> = RefCell::new(Vec::new());
+        /// The accounts whose calls fail.
+        static FAILING: RefCell<Vec<AccountId>> = RefCell::new(Vec::new());
+    }
+
+    /// Records the call of `operation`, unless its target has been made to fail.
+    pub(super) fn mock_make_call(operation: &Operation) -> bool {
+        if FAILING.with(|failing| failing.borrow().contains(&operation.target)) {
+            return false
+        }
+        CALLS.with(|calls| {
+            calls.borrow_mut().push((operation.target, operation.value, operation.selector, operation.input.clone()))
+        });
+        true
+    }
+
+    /// Makes calls to `account` fail, or succeed again.
+    fn set_failing(account: AccountId, fails: bool) {
+        FAILING.with(|failing| {
+            let mut failing = failing.borrow_mut();
+            failing.retain(|other| *other != account);
+            if fails {
+                failing.push(account);
+            }
+        })
+    }
+
+    /// Returns the calls made by the timelock so far.
+    fn calls() -> Vec<(AccountId, Balance, [u8; 4], Vec<u8>)> {
+        CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    /// The selector of the `mint` message of the ERC20 token.
+    const MINT_SELECTOR: [u8; 4] = [0xCF, 0xDD, 0x9A, 0xA2];
+
+    /// Deploys a timelock at block 0 with Alice as the admin and a delay of
+    /// 100 blocks.
+    fn deploy_mock() -> Timelock {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        Timelock::deploy_mock(alice, 100)
+    }
+
+    /// Queues a call of `mint(alice, 1000)` on the token, with an eta of `eta`.
+    fn queue_mint(timelock: &mut Timelock, eta: BlockNumber) -> Result<OperationId, TimelockError> {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        timelock.queue(token, 0, MINT_SELECTOR, (alice, 1000 as Balance).encode(), eta)
+    }
+
+    #[test]
+    fn queue_works() {
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let mut timelock = deploy_mock();
+        assert_eq!(queue_mint(&mut timelock, 99), Err(TimelockError::EtaTooEarly));
+        assert_eq!(queue_mint(&mut timelock, 100), Ok(0));
+        assert_eq!(timelock.operation_count(), 1);
+        assert_eq!(timelock.operation(0).unwrap().target, token);
+        assert_eq!(timelock.operation(0).unwrap().eta, 100);
+        // The delay counts from the block the operation is queued in
+        env::test::set_block_number(50);
+        assert_eq!(queue_mint(&mut timelock, 149), Err(TimelockError::EtaTooEarly));
+        assert_eq!(queue_mint(&mut timelock, 150), Ok(1));
+        env::test::set_caller(bob);
+        assert_eq!(queue_mint(&mut timelock, 1000), Err(TimelockError::NotAdmin));
+    }
+
+    #[test]
+    fn execute_waits_for_the_eta() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let mut timelock = deploy_mock();
+        assert_eq!(queue_mint(&mut timelock, 100), Ok(0));
+        env::test::set_block_number(99);
+        assert_eq!(timelock.execute(0), Err(TimelockError::NotReady));
+        assert_eq!(calls(), vec![]);
+        // Anyone can execute the operation once it is ready
+        env::test::set_block_number(100);
+        env::test::set_caller(bob);
+        assert_eq!(timelock.execute(0), Ok(()));
+        assert_eq!(calls(), vec![(token, 0, MINT_SELECTOR, (alice, 1000 as Balance).encode())]);
+        assert_eq!(timelock.operation(0), None);
+        assert_eq!(timelock.execute(0), Err(TimelockError::UnknownOperation));
+    }
+
+    #[test]
+    fn operations_expire_after_the_grace_period() {
+        let mut timelock = deploy_mock();
+        assert_eq!(queue_mint(&mut timelock, 100), Ok(0));
+        env::test::set_block_number(100 + GRACE_PERIOD + 1);
+        assert_eq!(timelock.execute(0), Err(TimelockError::Expired));
+        // The last block of the grace period is still fine
+        env::test::set_block_number(100 + GRACE_PERIOD);
+        assert_eq!(timelock.execute(0), Ok(()));
+    }
+
+    #[test]
+    fn failed_operations_stay_queued() {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let mut timelock = deploy_mock();
+        assert_eq!(queue_mint(&mut timelock, 100), Ok(0));
+        env::test::set_block_number(100);
+        set_failing(token, true);
+        assert_eq!(timelock.execute(0), Err(TimelockError::ExecutionFailed));
+        assert!(timelock.operation(0).is_some());
+        set_failing(token, false);
+        assert_eq!(timelock.execute(0), Ok(()));
+        assert_eq!(calls().len(), 1);
+    }
+
+    #[test]
+    fn cancel_works() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let mut timelock = deploy_mock();
+        assert_eq!(queue_mint(&mut timelock, 100), Ok(0));
+        env::test::set_caller(bob);
+        assert_eq!(timelock.cancel(0), Err(TimelockError::NotAdmin));
+        env::test::set_caller(alice);
+        assert_eq!(timelock.cancel(0), Ok(()));
+        assert_eq!(timelock.cancel(0), Err(TimelockError::UnknownOperation));
+        env::test::set_block_number(100);
+        assert_eq!(timelock.execute(0), Err(TimelockError::UnknownOperation));
+        assert_eq!(calls(), vec![]);
+    }
+
+    #[test]
+    fn settings_are_changed_by_the_timelock() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let mut timelock = deploy_mock();
+        // Not even the admin can skip the delay
+        assert_eq!(timelock.set_delay(0), Err(TimelockError::NotTimelock));
+        assert_eq!(timelock.set_admin(bob), Err(TimelockError::NotTimelock));
+        // The admin queues the changes as calls of the timelock to itself
+        env::test::set_caller(env::address());
+        assert_eq!(timelock.set_delay(200), Ok(()));
+        assert_eq!(timelock.set_admin(bob), Ok(()));
+        assert_eq!(timelock.delay(), 200);
+        assert_eq!(timelock.admin(), bob);
+        env::test::set_caller(alice);
+        assert_eq!(queue_mint(&mut timelock, 200), Err(TimelockError::NotAdmin));
+        env::test::set_caller(bob);
+        assert_eq!(queue_mint(&mut timelock, 199), Err(TimelockError::EtaTooEarly));
+        assert_eq!(queue_mint(&mut timelock, 200), Ok(0));
+    }
+}
@@ -0,0 +1,718 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `get_past_votes` message of the governance token.
+const GET_PAST_VOTES_SELECTOR: [u8; 4] = [0x79, 0x95, 0xD5, 0xFC];
+
+/// The selector of the `delay` message of the timelock.
+const DELAY_SELECTOR: [u8; 4] = [0xC8, 0x3E, 0xB8, 0x16];
+
+/// The selector of the `queue` message of the timelock.
+const QUEUE_SELECTOR: [u8; 4] = [0x3E, 0x16, 0x1D, 0xDE];
+
+/// The selector of the `execute` message of the timelock.
+const EXECUTE_SELECTOR: [u8; 4] = [0xD0, 0x0E, 0xC8, 0x94];
+
+/// The selector of the `cancel` message of the timelock.
+const CANCEL_SELECTOR: [u8; 4] = [0x97, 0x96, 0xE9, 0xA7];
+
+/// The gas limit of cross-contract calls made by the governor.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The identifier of a proposal.
+type ProposalId = u32;
+
+/// The identifier of an operation queued in the timelock.
+type OperationId = u32;
+
+/// A call the governor makes through the timelock if the token holders agree.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+struct Proposal {
+    /// The account which made the proposal.
+    proposer: AccountId,
+    /// The account or contract which is called.
+    target: AccountId,
+    /// The native balance the timelock sends along with the call.
+    value: Balance,
+    /// The selector of the called message.
+    selector: [u8; 4],
+    /// The SCALE encoded arguments of the called message.
+    input: Vec<u8>,
+    /// The block whose voting power counts, after which voting starts.
+    start: BlockNumber,
+    /// The last block in which votes are accepted.
+    end: BlockNumber,
+    /// The votes in favor of the proposal.
+    for_votes: Balance,
+    /// The votes against the proposal.
+    against_votes: Balance,
+    /// The votes which only count towards the quorum.
+    abstain_votes: Balance,
+    /// The operation of the timelock, once the proposal has been queued.
+    operation: Option<OperationId>,
+    /// Whether the proposal has been executed.
+    executed: bool,
+    /// Whether the proposer has cancelled the proposal.
+    canceled: bool,
+}
+
+/// The stages a proposal goes through.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum ProposalState {
+    /// Voting has not started yet.
+    Pending,
+    /// Votes are being accepted.
+    Active,
+    /// Voting has ended, and the proposal did not pass.
+    Defeated,
+    /// Voting has ended, and the proposal passed.
+    Succeeded,
+    /// The proposal waits in the timelock.
+    Queued,
+    /// The call of the proposal has been made.
+    Executed,
+    /// The proposer has cancelled the proposal.
+    Canceled,
+}
+
+/// How a vote is cast.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum VoteType {
+    Against,
+    For,
+    Abstain,
+}
+
+/// Events deposited by the governor contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    ProposalCreated {
+        id: ProposalId,
+        proposer: AccountId,
+        start: BlockNumber,
+        end: BlockNumber,
+    },
+    VoteCast {
+        voter: AccountId,
+        id: ProposalId,
+        support: VoteType,
+        weight: Balance,
+    },
+    ProposalQueued {
+        id: ProposalId,
+        eta: BlockNumber,
+    },
+    ProposalExecuted {
+        id: ProposalId,
+    },
+    ProposalCanceled {
+        id: ProposalId,
+    },
+}
+
+/// Errors which can occur when calling the governor contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum GovernorError {
+    /// The proposer does not have enough votes to make a proposal.
+    BelowProposalThreshold,
+    /// No proposal with the given id has been made.
+    UnknownProposal,
+    /// The proposal is not accepting votes.
+    NotActive,
+    /// The voter has already voted on the proposal.
+    AlreadyVoted,
+    /// The voter had no voting power when voting started.
+    NoVotingPower,
+    /// Only proposals which passed can be queued.
+    NotSucceeded,
+    /// Only queued proposals can be executed.
+    NotQueued,
+    /// Only the proposer can cancel a proposal.
+    NotProposer,
+    /// The proposal has already been executed or cancelled.
+    AlreadyFinished,
+    /// The timelock refused to queue, execute or cancel the proposal.
+    TimelockFailed,
+}
+
+/// Deposits a governor event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+contract! {
+    /// Lets the holders of a governance token decide which calls are made.
+    ///
+    /// Anyone with at least `proposal_threshold` votes can propose a call.
+    /// After `voting_delay` blocks, the holders vote with the voting power
+    /// they had when voting started, for `voting_period` blocks. A proposal
+    /// passes if more votes are in favor than against, and at least `quorum`
+    /// votes were cast in favor or abstaining. Passed proposals are queued in
+    /// the timelock, which makes the call once its delay has passed.
+    struct Governor {
+        /// The governance token whose checkpoints hold the voting power.
+        token: storage::Value<AccountId>,
+        /// The timelock which makes the calls, and whose admin is the governor.
+        timelock: storage::Value<AccountId>,
+        /// The number of blocks between a proposal and the start of voting.
+        voting_delay: storage::Value<BlockNumber>,
+        /// The number of blocks votes are accepted for.
+        voting_period: storage::Value<BlockNumber>,
+        /// The votes an account needs to make a proposal.
+        proposal_threshold: storage::Value<Balance>,
+        /// The votes in favor or abstaining a proposal needs to pass.
+        quorum: storage::Value<Balance>,
+        /// The number of proposals made, which is also the next id.
+        proposal_count: storage::Value<ProposalId>,
+        /// The proposals by id.
+        proposals: storage::HashMap<ProposalId, Proposal>,
+        /// Whether an account has voted on a proposal: (id, voter) -> voted
+        has_voted: storage::HashMap<(ProposalId, AccountId), bool>,
+    }
+
+    impl Deploy for Governor {
+        fn deploy(
+            &mut self,
+            token: AccountId,
+            timelock: AccountId,
+            voting_delay: BlockNumber,
+            voting_period: BlockNumber,
+            proposal_threshold: Balance,
+            quorum: Balance,
+        ) {
+            assert!(voting_period > 0, "voting must last at least one block");
+            self.token.set(token);
+            self.timelock.set(timelock);
+            self.voting_delay.set(voting_delay);
+            self.voting_period.set(voting_period);
+            self.proposal_threshold.set(proposal_threshold);
+            self.quorum.set(quorum);
+            self.proposal_count.set(0);
+        }
+    }
+
+    impl Governor {
+        /// Returns the number of proposals made so far.
+        pub(external) fn proposal_count(&self) -> ProposalId {
+            let proposal_count = *self.proposal_count;
+            env.println(&format!("Governor::proposal_count = {:?}", proposal_count));
+            proposal_count
+        }
+
+        /// Returns the proposal with the given id, if it has been made.
+        pub(external) fn proposal(&self, id: ProposalId) -> Option<Proposal> {
+            let proposal = self.proposals.get(&id).cloned();
+            env.println(&format!("Governor::proposal(id = {:?}) = {:?}", id, proposal));
+            proposal
+        }
+
+        /// Returns the stage the proposal with the given id is in.
+        pub(external) fn state(&self, id: ProposalId) -> Option<ProposalState> {
+            let state = self.proposals.get(&id).map(|proposal| self.state_of(proposal));
+            env.println(&format!("Governor::state(id = {:?}) = {:?}", id, state));
+            state
+        }
+
+        /// Returns whether `voter` has voted on the proposal with the given id.
+        pub(external) fn has_voted(&self, id: ProposalId, voter: AccountId) -> bool {
+            let voted = *self.has_voted.get(&(id, voter)).unwrap_or(&false);
+            env.println(&format!("Governor::has_voted(id = {:?}, voter = {:?}) = {:?}", id, voter, voted));
+            voted
+        }
+
+        /// Proposes that the timelock calls the message `selector` of `target`
+        /// with the SCALE encoded `input`, sending `value` along.
+        ///
+        /// The caller must have had at least `proposal_threshold` votes in the
+        /// previous block.
+        pub(external) fn propose(
+            &mut self,
+            target: AccountId,
+            value: Balance,
+            selector: [u8; 4],
+            input: Vec<u8>,
+        ) -> Result<ProposalId, GovernorError> {
+            let proposer = env.caller();
+            let now = env.block_number();
+            if self.votes_at(proposer, now.saturating_sub(1)) < *self.proposal_threshold {
+                return Err(GovernorError::BelowProposalThreshold)
+            }
+            let start = now + *self.voting_delay;
+            let end = start + *self.voting_period;
+            let id = *self.proposal_count;
+            self.proposal_count += 1;
+            self.proposals.insert(id, Proposal {
+                proposer: proposer,
+                target: target,
+                value: value,
+                selector: selector,
+                input: input,
+                start: start,
+                end: end,
+                for_votes: 0,
+                against_votes: 0,
+                abstain_votes: 0,
+                operation: None,
+                executed: false,
+                canceled: false
+            });
+            deposit_event(Event::ProposalCreated {
+                id: id,
+                proposer: proposer,
+                start: start,
+                end: end
+            });
+            Ok(id)
+        }
+
+        /// Votes on an active proposal with the voting power the caller had
+        /// when voting started.
+        pub(external) fn cast_vote(&mut self, id: ProposalId, support: VoteType) -> Result<Balance, GovernorError> {
+            let mut proposal = self.proposals.get(&id).cloned().ok_or(GovernorError::UnknownProposal)?;
+            if self.state_of(&proposal) != ProposalState::Active {
+                return Err(GovernorError::NotActive)
+            }
+            let voter = env.caller();
+            if *self.has_voted.get(&(id, voter)).unwrap_or(&false) {
+                return Err(GovernorError::AlreadyVoted)
+            }
+            // ACTION: Get the `weight` of the vote with `votes_at` the `start` of the proposal
+            // ACTION: `if` `weight` is 0, exit early and return `Err(GovernorError::NoVotingPower)`
+            // ACTION: `match` on `support` to add `weight` to the right votes of the `proposal`
+            // ACTION: Store the `proposal`, and record that the `voter` has voted
+            deposit_event(Event::VoteCast {
+                voter: voter,
+                id: id,
+                support: support,
+                weight: weight
+            });
+            Ok(weight)
+        }
+
+        /// Queues a proposal which passed in the timelock.
+        ///
+        /// Anyone can queue a proposal which passed.
+        pub(external) fn queue(&mut self, id: ProposalId) -> Result<BlockNumber, GovernorError> {
+            let mut proposal = self.proposals.get(&id).cloned().ok_or(GovernorError::UnknownProposal)?;
+            if self.state_of(&proposal) != ProposalState::Succeeded {
+                return Err(GovernorError::NotSucceeded)
+            }
+            // ACTION: Ask the timelock for its `delay` with `call_contract` and `DELAY_SELECTOR`
+            // ACTION: The `eta` is the current block plus the `delay`
+            // ACTION: Queue the call of the proposal with `call_contract` and `QUEUE_SELECTOR`
+            //   HINT: The input is `(target, value, selector, input, eta)`, SCALE encoded
+            //   HINT: The timelock returns a `Result<OperationId, u8>`
+            // ACTION: Return `Err(GovernorError::TimelockFailed)` unless the timelock returned
+            //         the `operation`
+            proposal.operation = Some(operation);
+            self.proposals.insert(id, proposal);
+            deposit_event(Event::ProposalQueued {
+                id: id,
+                eta: eta
+            });
+            Ok(eta)
+        }
+
+        /// Executes a queued proposal once the delay of the timelock has passed.
+        ///
+        /// Anyone can execute a queued proposal.
+        pub(external) fn execute(&mut self, id: ProposalId) -> Result<(), GovernorError> {
+            let mut proposal = self.proposals.get(&id).cloned().ok_or(GovernorError::UnknownProposal)?;
+            let operation = match (self.state_of(&proposal), proposal.operation) {
+                (ProposalState::Queued, Some(operation)) => operation,
+                _ => return Err(GovernorError::NotQueued),
+            };
+            let result: Option<Result<(), u8>> = call_contract(*self.timelock, EXECUTE_SELECTOR, &operation.encode());
+            if result != Some(Ok(())) {
+                return Err(GovernorError::TimelockFailed)
+            }
+            proposal.executed = true;
+            self.proposals.insert(id, proposal);
+            deposit_event(Event::ProposalExecuted {
+                id: id
+            });
+            Ok(())
+        }
+
+        /// Cancels a proposal which has not been executed yet, and removes it
+        /// from the timelock if it has been queued.
+        ///
+        /// Only the proposer can cancel a proposal.
+        pub(external) fn cancel(&mut self, id: ProposalId) -> Result<(), GovernorError> {
+            let mut proposal = self.proposals.get(&id).cloned().ok_or(GovernorError::UnknownProposal)?;
+            if env.caller() != proposal.proposer {
+                return Err(GovernorError::NotProposer)
+            }
+            if proposal.executed || proposal.canceled {
+                return Err(GovernorError::AlreadyFinished)
+            }
+            if let Some(operation) = proposal.operation {
+                let result: Option<Result<(), u8>> =
+                    call_contract(*self.timelock, CANCEL_SELECTOR, &operation.encode());
+                if result != Some(Ok(())) {
+                    return Err(GovernorError::TimelockFailed)
+                }
+            }
+            proposal.canceled = true;
+            self.proposals.insert(id, proposal);
+            deposit_event(Event::ProposalCanceled {
+                id: id
+            });
+            Ok(())
+        }
+    }
+
+    impl Governor {
+        /// Asks the governance token for the votes `account` had at the end of `block`.
+        fn votes_at(&self, account: AccountId, block: BlockNumber) -> Balance {
+            call_contract(*self.token, GET_PAST_VOTES_SELECTOR, &(account, block).encode())
+                .expect("the token reports past votes")
+        }
+
+        /// Returns the stage `proposal` is in at the current block.
+        fn state_of(&self, proposal: &Proposal) -> ProposalState {
+            // ACTION: Return the `ProposalState` of the `proposal`
+            //   HINT: Check `canceled`, `executed` and `operation` first
+            //   HINT: Compared to `env::block_number()`, the proposal is `Pending` up to its
+            //         `start`, and `Active` up to its `end`
+            //   HINT: After that, it has `Succeeded` if `for_votes > against_votes` and
+            //         `for_votes + abstain_votes` reach the `quorum`
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the governor through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The changes of voting power recorded by a mock token: (account, block, votes)
+    type Checkpoints = Rc<RefCell<Vec<(AccountId, BlockNumber, Balance)>>>;
+
+    /// The operations queued in a mock timelock: id -> (operation input, eta)
+    type Operations = Rc<RefCell<HashMap<OperationId, (Vec<u8>, BlockNumber)>>>;
+
+    /// The selector of the `mint` message of the ERC20 token.
+    const MINT_SELECTOR: [u8; 4] = [0xCF, 0xDD, 0x9A, 0xA2];
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+        /// The calls made by the mock timelock so far: (target, value, selector, input)
+        static CALLS: RefCell<Vec<(AccountId, Balance, [u8; 4], Vec<u8>)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Registers a mock governance token at `token` and returns its checkpoints.
+    ///
+    /// The token returns the votes of the latest checkpoint of an account at
+    /// or before the block asked for.
+    fn register_token(token: AccountId) -> Checkpoints {
+        let checkpoints: Checkpoints = Rc::new(RefCell::new(Vec::new()));
+        let state = checkpoints.clone();
+        register_contract(token, move |selector, input| {
+            match selector {
+                GET_PAST_VOTES_SELECTOR => {
+                    let (account, block) = <(AccountId, BlockNumber)>::decode(&mut &input[..]).unwrap();
+                    let votes = state
+                        .borrow()
+                        .iter()
+                        .filter(|(owner, at, _)| *owner == account && *at <= block)
+                        .last()
+                        .map(|(_, _, votes)| *votes)
+                        .unwrap_or(0);
+                    Some(votes.encode())
+                }
+                _ => None,
+            }
+        });
+        checkpoints
+    }
+
+    /// Registers a mock timelock at `timelock` with a delay of `delay` blocks,
+    /// and returns its queued operations.
+    ///
+    /// Like the real timelock, it checks the eta of operations, and makes
+    /// their calls once the eta has been reached. It does not check who calls it.
+    fn register_timelock(timelock: AccountId, delay: BlockNumber) -> Operations {
+        let operations: Operations = Rc::new(RefCell::new(HashMap::new()));
+        let state = operations.clone();
+        let mut operation_count: OperationId = 0;
+        register_contract(timelock, move |selector, input| {
+            let mut operations = state.borrow_mut();
+            match selector {
+                DELAY_SELECTOR => Some(delay.encode()),
+                QUEUE_SELECTOR => {
+                    let (_, _, _, _, eta) =
+                        <(AccountId, Balance, [u8; 4], Vec<u8>, BlockNumber)>::decode(&mut &input[..]).unwrap();
+                    if eta < env::block_number() + delay {
+                        return Some(Err::<OperationId, u8>(2).encode())
+                    }
+                    let id = operation_count;
+                    operation_count += 1;
+                    operations.insert(id, (input.to_vec(), eta));
+                    Some(Ok::<OperationId, u8>(id).encode())
+                }
+                EXECUTE_SELECTOR => {
+                    let id = OperationId::decode(&mut &input[..]).unwrap();
+                    let ready = match operations.get(&id) {
+                        Some((_, eta)) => *eta <= env::block_number(),
+                        None => return Some(Err::<(), u8>(3).encode()),
+                    };
+                    if !ready {
+                        return Some(Err::<(), u8>(4).encode())
+                    }
+                    let (call, _) = operations.remove(&id).unwrap();
+                    let (target, value, selector, input, _) =
+                        <(AccountId, Balance, [u8; 4], Vec<u8>, BlockNumber)>::decode(&mut &call[..]).unwrap();
+                    CALLS.with(|calls| calls.borrow_mut().push((target, value, selector, input)));
+                    Some(Ok::<(), u8>(()).encode())
+                }
+                CANCEL_SELECTOR => {
+                    let id = OperationId::decode(&mut &input[..]).unwrap();
+                    match operations.remove(&id) {
+                        Some(_) => Some(Ok::<(), u8>(()).encode()),
+                        None => Some(Err::<(), u8>(3).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        operations
+    }
+
+    /// Returns the calls made by the mock timelock so far.
+    fn calls() -> Vec<(AccountId, Balance, [u8; 4], Vec<u8>)> {
+        CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    /// Returns Alice, Bob and Charlie, the voters of most tests.
+    fn voters() -> (AccountId, AccountId, AccountId) {
+        (
+            AccountId::try_from([0x0; 32]).unwrap(),
+            AccountId::try_from([0x1; 32]).unwrap(),
+            AccountId::try_from([0x2; 32]).unwrap(),
+        )
+    }
+
+    /// Deploys a governor at block 10 with a voting delay of 5 blocks, a voting
+    /// period of 20 blocks, a proposal threshold of 100 and a quorum of 400,
+    /// and a mock timelock with a delay of 50 blocks.
+    ///
+    /// Alice has 100 votes, Bob 300 and Charlie 200 from block 0 on.
+    fn deploy_mock() -> (Governor, Checkpoints, Operations) {
+        let (alice, bob, charlie) = voters();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let timelock = AccountId::try_from([0x8; 32]).unwrap();
+        let checkpoints = register_token(token);
+        checkpoints.borrow_mut().extend(vec![(alice, 0, 100), (bob, 0, 300), (charlie, 0, 200)]);
+        let operations = register_timelock(timelock, 50);
+        env::test::set_caller(alice);
+        env::test::set_block_number(10);
+        (Governor::deploy_mock(token, timelock, 5, 20, 100, 400), checkpoints, operations)
+    }
+
+    /// Proposes to mint 1000 tokens for `to` as `proposer`.
+    fn propose_mint(governor: &mut Governor, proposer: AccountId, to: AccountId) -> Result<ProposalId, GovernorError> {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        env::test::set_caller(proposer);
+        governor.propose(token, 0, MINT_SELECTOR, (to, 1000 as Balance).encode())
+    }
+
+    /// Votes on the proposal `id` as `voter`.
+    fn vote(governor: &mut Governor, voter: AccountId, id: ProposalId, support: VoteType) -> Result<Balance, GovernorError> {
+        env::test::set_caller(voter);
+        governor.cast_vote(id, support)
+    }
+
+    #[test]
+    fn propose_needs_the_threshold() {
+        let (alice, _, _) = voters();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let (mut governor, _, _) = deploy_mock();
+        assert_eq!(propose_mint(&mut governor, dave, dave), Err(GovernorError::BelowProposalThreshold));
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(0));
+        let proposal = governor.proposal(0).unwrap();
+        assert_eq!((proposal.start, proposal.end), (15, 35));
+        assert_eq!(governor.state(0), Some(ProposalState::Pending));
+        assert_eq!(governor.state(1), None);
+        assert_eq!(governor.proposal_count(), 1);
+    }
+
+    #[test]
+    fn votes_are_accepted_while_active() {
+        let (alice, bob, _) = voters();
+        let (mut governor, _, _) = deploy_mock();
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(0));
+        env::test::set_block_number(15);
+        assert_eq!(vote(&mut governor, bob, 0, VoteType::For), Err(GovernorError::NotActive));
+        env::test::set_block_number(16);
+        assert_eq!(governor.state(0), Some(ProposalState::Active));
+        assert_eq!(vote(&mut governor, bob, 0, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, bob, 0, VoteType::Against), Err(GovernorError::AlreadyVoted));
+        assert!(governor.has_voted(0, bob));
+        env::test::set_block_number(35);
+        assert_eq!(vote(&mut governor, alice, 0, VoteType::Against), Ok(100));
+        env::test::set_block_number(36);
+        assert_eq!(vote(&mut governor, alice, 0, VoteType::Abstain), Err(GovernorError::NotActive));
+        let proposal = governor.proposal(0).unwrap();
+        assert_eq!((proposal.for_votes, proposal.against_votes), (300, 100));
+        assert_eq!(vote(&mut governor, alice, 1, VoteType::For), Err(GovernorError::UnknownProposal));
+    }
+
+    #[test]
+    fn votes_count_from_the_start_of_voting() {
+        let (alice, _, _) = voters();
+        let dave = AccountId::try_from([0x3; 32]).unwrap();
+        let (mut governor, checkpoints, _) = deploy_mock();
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(0));
+        // Dave gets votes after voting started, which do not count
+        checkpoints.borrow_mut().push((dave, 16, 1000));
+        env::test::set_block_number(20);
+        assert_eq!(vote(&mut governor, dave, 0, VoteType::For), Err(GovernorError::NoVotingPower));
+    }
+
+    #[test]
+    fn proposals_need_a_majority_and_quorum() {
+        let (alice, bob, charlie) = voters();
+        let (mut governor, _, _) = deploy_mock();
+        // 300 for and 100 abstaining reach the quorum
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(0));
+        // 300 for, but short of the quorum
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(1));
+        // 300 for and 300 against is no majority
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(2));
+        env::test::set_block_number(16);
+        assert_eq!(vote(&mut governor, bob, 0, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, alice, 0, VoteType::Abstain), Ok(100));
+        assert_eq!(vote(&mut governor, bob, 1, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, bob, 2, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, alice, 2, VoteType::Against), Ok(100));
+        assert_eq!(vote(&mut governor, charlie, 2, VoteType::Against), Ok(200));
+        env::test::set_block_number(36);
+        assert_eq!(governor.state(0), Some(ProposalState::Succeeded));
+        assert_eq!(governor.state(1), Some(ProposalState::Defeated));
+        assert_eq!(governor.state(2), Some(ProposalState::Defeated));
+        assert_eq!(governor.queue(1), Err(GovernorError::NotSucceeded));
+        assert_eq!(governor.queue(2), Err(GovernorError::NotSucceeded));
+    }
+
+    #[test]
+    fn cancel_works() {
+        let (alice, bob, _) = voters();
+        let (mut governor, _, operations) = deploy_mock();
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(0));
+        assert_eq!(propose_mint(&mut governor, alice, alice), Ok(1));
+        env::test::set_block_number(16);
+        assert_eq!(vote(&mut governor, bob, 1, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, alice, 1, VoteType::For), Ok(100));
+        env::test::set_block_number(36);
+        assert_eq!(governor.queue(1), Ok(86));
+        assert_eq!(operations.borrow().len(), 1);
+        env::test::set_caller(bob);
+        assert_eq!(governor.cancel(0), Err(GovernorError::NotProposer));
+        env::test::set_caller(alice);
+        assert_eq!(governor.cancel(0), Ok(()));
+        assert_eq!(governor.cancel(0), Err(GovernorError::AlreadyFinished));
+        // Cancelling a queued proposal removes it from the timelock
+        assert_eq!(governor.cancel(1), Ok(()));
+        assert_eq!(operations.borrow().len(), 0);
+        assert_eq!(governor.state(1), Some(ProposalState::Canceled));
+        env::test::set_block_number(86);
+        assert_eq!(governor.execute(1), Err(GovernorError::NotQueued));
+    }
+
+    #[test]
+    fn full_lifecycle_works() {
+        let (alice, bob, charlie) = voters();
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let (mut governor, _, _) = deploy_mock();
+        // Charlie proposes to mint tokens for himself
+        assert_eq!(propose_mint(&mut governor, charlie, charlie), Ok(0));
+        assert_eq!(governor.queue(0), Err(GovernorError::NotSucceeded));
+        // Voting starts after the voting delay
+        env::test::set_block_number(16);
+        assert_eq!(vote(&mut governor, alice, 0, VoteType::Against), Ok(100));
+        assert_eq!(vote(&mut governor, bob, 0, VoteType::For), Ok(300));
+        assert_eq!(vote(&mut governor, charlie, 0, VoteType::For), Ok(200));
+        // The proposal cannot be queued while voting goes on
+        env::test::set_block_number(35);
+        assert_eq!(governor.queue(0), Err(GovernorError::NotSucceeded));
+        env::test::set_block_number(36);
+        assert_eq!(governor.state(0), Some(ProposalState::Succeeded));
+        assert_eq!(governor.execute(0), Err(GovernorError::NotQueued));
+        // Anyone can queue the proposal in the timelock
+        env::test::set_caller(alice);
+        assert_eq!(governor.queue(0), Ok(86));
+        assert_eq!(governor.state(0), Some(ProposalState::Queued));
+        assert_eq!(governor.queue(0), Err(GovernorError::NotSucceeded));
+        // The timelock makes the call once its delay has passed
+        env::test::set_block_number(85);
+        assert_eq!(governor.execute(0), Err(GovernorError::TimelockFailed));
+        assert_eq!(calls(), vec![]);
+        env::test::set_block_number(86);
+        assert_eq!(governor.execute(0), Ok(()));
+        assert_eq!(governor.state(0), Some(ProposalState::Executed));
+        assert_eq!(calls(), vec![(token, 0, MINT_SELECTOR, (charlie, 1000 as Balance).encode())]);
+        assert_eq!(governor.execute(0), Err(GovernorError::NotQueued));
+        env::test::set_caller(charlie);
+        assert_eq!(governor.cancel(0), Err(GovernorError::AlreadyFinished));
+    }
+}
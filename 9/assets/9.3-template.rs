@@ -0,0 +1,467 @@
+#![cfg_attr(not(any(test, feature = "test-env")), no_std)]
+
+use parity_codec::{
+    Decode,
+    Encode,
+};
+use ink_core::{
+    env::{
+        self,
+        AccountId,
+        Balance,
+        BlockNumber,
+    },
+    memory::format,
+    storage,
+};
+use ink_lang::contract;
+
+/// The selector of the `transfer` message of the ERC20 token.
+const TRANSFER_SELECTOR: [u8; 4] = [0x84, 0xA1, 0x5D, 0xA1];
+
+/// The selector of the `transfer_from` message of the ERC20 token.
+const TRANSFER_FROM_SELECTOR: [u8; 4] = [0x0B, 0x39, 0x6F, 0x18];
+
+/// The gas limit of cross-contract calls made by the contract.
+const CALL_GAS_LIMIT: u64 = 5_000_000_000;
+
+/// Calls the message with `selector` on the contract `callee`, passing the
+/// SCALE encoded `input`, and decodes the value it returns.
+///
+/// Returns `None` if the call failed or returned something unexpected.
+#[cfg(not(all(test, feature = "test-env")))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let mut call_data = selector.to_vec();
+    call_data.extend_from_slice(input);
+    env::call_evaluate(callee, CALL_GAS_LIMIT, 0, &call_data[..]).ok()
+}
+
+/// Calls the message with `selector` on the mock contract registered for `callee`.
+#[cfg(all(test, feature = "test-env"))]
+fn call_contract<R: Decode>(callee: AccountId, selector: [u8; 4], input: &[u8]) -> Option<R> {
+    let output = tests::call_mock_contract(&callee, selector, input)?;
+    R::decode(&mut &output[..])
+}
+
+/// The identifier of a poll.
+type PollId = u32;
+
+/// A yes or no question the token holders vote on.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Poll {
+    /// The last block in which votes are accepted.
+    end: BlockNumber,
+    /// The votes in favor.
+    yes_votes: Balance,
+    /// The votes against.
+    no_votes: Balance,
+}
+
+/// The votes a voter has cast on a poll.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+struct Ballot {
+    /// Whether the votes are in favor.
+    support: bool,
+    /// The number of votes cast.
+    votes: Balance,
+    /// The tokens locked for the votes, until they are withdrawn.
+    locked: Balance,
+}
+
+/// Events deposited by the quadratic voting contract.
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Event {
+    PollCreated {
+        id: PollId,
+        end: BlockNumber,
+    },
+    VoteCast {
+        id: PollId,
+        voter: AccountId,
+        support: bool,
+        votes: Balance,
+        cost: Balance,
+    },
+    Withdrawn {
+        id: PollId,
+        voter: AccountId,
+        amount: Balance,
+    },
+}
+
+/// Errors which can occur when calling the quadratic voting contract.
+#[derive(Encode, Decode, Debug, PartialEq, Eq, Clone, Copy)]
+enum QuadraticVotingError {
+    /// No poll with the given id has been created.
+    UnknownPoll,
+    /// The poll no longer accepts votes.
+    PollClosed,
+    /// Tokens can only be withdrawn once the poll has closed.
+    PollOpen,
+    /// At least one vote must be cast.
+    ZeroVotes,
+    /// All votes of a voter on a poll must be on the same side.
+    SideChanged,
+    /// The cost of the votes does not fit into a `Balance`.
+    CostOverflow,
+    /// The voter has no locked tokens on the poll.
+    NothingToWithdraw,
+    /// The token refused to move the tokens into the contract.
+    TransferFailed,
+}
+
+/// Deposits a quadratic voting event.
+fn deposit_event(event: Event) {
+    env::deposit_raw_event(&[], &event.encode()[..])
+}
+
+/// Returns the tokens `votes` votes cost in total, `votes * votes`, or `None`
+/// if that does not fit into a `Balance`.
+fn cost_of(votes: Balance) -> Option<Balance> {
+    // ACTION: Return `votes` multiplied by itself with `checked_mul`
+    None
+}
+
+contract! {
+    /// Polls in which every additional vote costs more than the last.
+    ///
+    /// Voters lock ERC20 tokens to cast votes, and `n` votes on a poll cost
+    /// `n * n` tokens. A voter who cares a lot about a poll can outvote many
+    /// who care a little, but only at a steep price, which makes it expensive
+    /// for a single large holder to decide every poll. The tokens are only
+    /// locked, and are returned once the poll has closed.
+    struct QuadraticVoting {
+        /// The ERC20 token which is locked to vote.
+        token: storage::Value<AccountId>,
+        /// The number of polls created, which is also the next id.
+        poll_count: storage::Value<PollId>,
+        /// The polls by id.
+        polls: storage::HashMap<PollId, Poll>,
+        /// The ballot of each voter on each poll: (id, voter) -> ballot
+        ballots: storage::HashMap<(PollId, AccountId), Ballot>,
+    }
+
+    impl Deploy for QuadraticVoting {
+        fn deploy(&mut self, token: AccountId) {
+            self.token.set(token);
+            self.poll_count.set(0);
+        }
+    }
+
+    impl QuadraticVoting {
+        /// Returns the number of polls created so far.
+        pub(external) fn poll_count(&self) -> PollId {
+            let poll_count = *self.poll_count;
+            env.println(&format!("QuadraticVoting::poll_count = {:?}", poll_count));
+            poll_count
+        }
+
+        /// Returns the poll with the given id, if it has been created.
+        pub(external) fn poll(&self, id: PollId) -> Option<Poll> {
+            let poll = self.polls.get(&id).cloned();
+            env.println(&format!("QuadraticVoting::poll(id = {:?}) = {:?}", id, poll));
+            poll
+        }
+
+        /// Returns the ballot of `voter` on the poll with the given id, if
+        /// they have voted.
+        pub(external) fn ballot_of(&self, id: PollId, voter: AccountId) -> Option<Ballot> {
+            let ballot = self.ballots.get(&(id, voter)).cloned();
+            env.println(&format!("QuadraticVoting::ballot_of(id = {:?}, voter = {:?}) = {:?}", id, voter, ballot));
+            ballot
+        }
+
+        /// Creates a poll which accepts votes for `duration` blocks.
+        pub(external) fn create_poll(&mut self, duration: BlockNumber) -> PollId {
+            let id = *self.poll_count;
+            let end = env.block_number() + duration;
+            self.poll_count += 1;
+            self.polls.insert(id, Poll {
+                end: end,
+                yes_votes: 0,
+                no_votes: 0
+            });
+            deposit_event(Event::PollCreated {
+                id: id,
+                end: end
+            });
+            id
+        }
+
+        /// Casts `votes` more votes on the poll with the given id, and locks
+        /// the tokens they cost.
+        ///
+        /// The votes of a voter are added up, and the total costs its square:
+        /// casting 2 votes and then 1 more costs `3 * 3 = 9` tokens, just like
+        /// casting 3 at once. The caller must have approved the contract to
+        /// transfer the tokens.
+        pub(external) fn vote(&mut self, id: PollId, votes: Balance, support: bool) -> Result<Balance, QuadraticVotingError> {
+            let mut poll = self.polls.get(&id).cloned().ok_or(QuadraticVotingError::UnknownPoll)?;
+            if env.block_number() > poll.end {
+                return Err(QuadraticVotingError::PollClosed)
+            }
+            if votes == 0 {
+                return Err(QuadraticVotingError::ZeroVotes)
+            }
+            let voter = env.caller();
+            let mut ballot = self.ballots.get(&(id, voter)).cloned().unwrap_or(Ballot {
+                support: support,
+                votes: 0,
+                locked: 0
+            });
+            if ballot.support != support {
+                return Err(QuadraticVotingError::SideChanged)
+            }
+            // ACTION: Compute the `total` votes of the `ballot` after this vote with `checked_add`
+            // ACTION: The `cost` of this vote is the `cost_of` the `total`, minus the tokens the
+            //         `ballot` has `locked` already
+            //   HINT: Return `Err(QuadraticVotingError::CostOverflow)` if either does not fit
+            // The token's `Erc20Error` is decoded as its variant index.
+            let result: Option<Result<Balance, u8>> =
+                call_contract(*self.token, TRANSFER_FROM_SELECTOR, &(voter, env.address(), cost).encode());
+            match result {
+                Some(Ok(_)) => (),
+                _ => return Err(QuadraticVotingError::TransferFailed),
+            }
+            // ACTION: Update the `votes` and `locked` tokens of the `ballot`, and store it
+            // ACTION: Add `votes` to the `yes_votes` or `no_votes` of the `poll`, and store it
+            deposit_event(Event::VoteCast {
+                id: id,
+                voter: voter,
+                support: support,
+                votes: votes,
+                cost: cost
+            });
+            Ok(cost)
+        }
+
+        /// Returns the tokens the caller locked on a closed poll.
+        ///
+        /// The votes stay counted. If the token refuses the transfer, the
+        /// whole call is reverted.
+        pub(external) fn withdraw(&mut self, id: PollId) -> Result<Balance, QuadraticVotingError> {
+            let poll = self.polls.get(&id).cloned().ok_or(QuadraticVotingError::UnknownPoll)?;
+            if env.block_number() <= poll.end {
+                return Err(QuadraticVotingError::PollOpen)
+            }
+            let voter = env.caller();
+            let mut ballot = match self.ballots.get(&(id, voter)) {
+                Some(ballot) if ballot.locked > 0 => *ballot,
+                _ => return Err(QuadraticVotingError::NothingToWithdraw),
+            };
+            // ACTION: Set the `locked` tokens of the `ballot` to 0, store it, and remember the `amount`
+            // ACTION: Transfer the `amount` back to the `voter` with `call_contract` and `TRANSFER_SELECTOR`
+            //   HINT: The token returns a `Result<(), u8>`; `assert_eq!` that it is `Some(Ok(()))`
+            deposit_event(Event::Withdrawn {
+                id: id,
+                voter: voter,
+                amount: amount
+            });
+            Ok(amount)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-env"))]
+mod tests {
+    use super::*;
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        convert::TryFrom,
+        rc::Rc,
+    };
+
+    /// A contract which can be called by the voting contract through `call_contract`.
+    type MockContract = Box<dyn FnMut([u8; 4], &[u8]) -> Option<Vec<u8>>>;
+
+    /// The balances of a mock token.
+    type Balances = Rc<RefCell<HashMap<AccountId, Balance>>>;
+
+    thread_local! {
+        /// The mock contracts registered for the current test.
+        static CONTRACTS: RefCell<Vec<(AccountId, MockContract)>> = RefCell::new(Vec::new());
+    }
+
+    /// Registers `contract` to handle cross-contract calls made to `account`.
+    fn register_contract<F>(account: AccountId, contract: F)
+    where
+        F: FnMut([u8; 4], &[u8]) -> Option<Vec<u8>> + 'static,
+    {
+        CONTRACTS.with(|contracts| contracts.borrow_mut().push((account, Box::new(contract))))
+    }
+
+    /// Dispatches a cross-contract call to the mock contract registered for `callee`.
+    pub(super) fn call_mock_contract(callee: &AccountId, selector: [u8; 4], input: &[u8]) -> Option<Vec<u8>> {
+        CONTRACTS.with(|contracts| {
+            let mut contracts = contracts.borrow_mut();
+            let (_, contract) = contracts.iter_mut().find(|(account, _)| account == callee)?;
+            contract(selector, input)
+        })
+    }
+
+    /// Moves `value` tokens from `from` to `to`, if `from` holds enough.
+    fn move_tokens(balances: &mut HashMap<AccountId, Balance>, from: AccountId, to: AccountId, value: Balance) -> bool {
+        let balance_from = *balances.get(&from).unwrap_or(&0);
+        if balance_from < value {
+            return false
+        }
+        balances.insert(from, balance_from - value);
+        *balances.entry(to).or_insert(0) += value;
+        true
+    }
+
+    /// Registers a mock ERC20 token at `token` and returns its balances.
+    ///
+    /// The token lets anybody move anybody's tokens with `transfer_from`, so
+    /// the tests do not have to approve the contract first.
+    fn register_token(token: AccountId) -> Balances {
+        let balances: Balances = Rc::new(RefCell::new(HashMap::new()));
+        let state = balances.clone();
+        register_contract(token, move |selector, input| {
+            let mut balances = state.borrow_mut();
+            match selector {
+                TRANSFER_SELECTOR => {
+                    let (to, value) = <(AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, env::address(), to, value) {
+                        true => Some(Ok::<(), u8>(()).encode()),
+                        false => Some(Err::<(), u8>(0).encode()),
+                    }
+                }
+                TRANSFER_FROM_SELECTOR => {
+                    let (from, to, value) = <(AccountId, AccountId, Balance)>::decode(&mut &input[..]).unwrap();
+                    match move_tokens(&mut balances, from, to, value) {
+                        true => Some(Ok::<Balance, u8>(value).encode()),
+                        false => Some(Err::<Balance, u8>(0).encode()),
+                    }
+                }
+                _ => None,
+            }
+        });
+        balances
+    }
+
+    /// Deploys a voting contract for a mock token at block 0, creates a poll
+    /// which runs until block 100, and gives Alice 100 tokens and Bob and
+    /// Charlie 25 each.
+    fn deploy_mock() -> (QuadraticVoting, Balances) {
+        let token = AccountId::try_from([0x7; 32]).unwrap();
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let balances = register_token(token);
+        balances.borrow_mut().insert(alice, 100);
+        balances.borrow_mut().insert(bob, 25);
+        balances.borrow_mut().insert(charlie, 25);
+        env::test::set_caller(alice);
+        env::test::set_block_number(0);
+        let mut voting = QuadraticVoting::deploy_mock(token);
+        assert_eq!(voting.create_poll(100), 0);
+        (voting, balances)
+    }
+
+    /// Casts `votes` votes on the poll `id` as `voter`.
+    fn vote(
+        voting: &mut QuadraticVoting,
+        voter: AccountId,
+        id: PollId,
+        votes: Balance,
+        support: bool,
+    ) -> Result<Balance, QuadraticVotingError> {
+        env::test::set_caller(voter);
+        voting.vote(id, votes, support)
+    }
+
+    #[test]
+    fn cost_is_quadratic() {
+        assert_eq!(cost_of(0), Some(0));
+        assert_eq!(cost_of(1), Some(1));
+        assert_eq!(cost_of(3), Some(9));
+        assert_eq!(cost_of(10), Some(100));
+        // The largest number of votes whose cost still fits
+        let max_votes = 1 << 64;
+        assert_eq!(cost_of(max_votes - 1), Some((max_votes - 1) * (max_votes - 1)));
+        assert_eq!(cost_of(max_votes), None);
+    }
+
+    #[test]
+    fn votes_lock_their_cost() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let (mut voting, balances) = deploy_mock();
+        assert_eq!(vote(&mut voting, alice, 0, 3, true), Ok(9));
+        assert_eq!(balances.borrow()[&alice], 91);
+        assert_eq!(balances.borrow()[&env::address()], 9);
+        assert_eq!(voting.ballot_of(0, alice), Some(Ballot { support: true, votes: 3, locked: 9 }));
+        assert_eq!(voting.poll(0).unwrap().yes_votes, 3);
+    }
+
+    #[test]
+    fn more_votes_cost_the_difference() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let (mut voting, balances) = deploy_mock();
+        // 2 votes and then 1 more cost as much as 3 votes at once
+        assert_eq!(vote(&mut voting, alice, 0, 2, true), Ok(4));
+        assert_eq!(vote(&mut voting, alice, 0, 1, true), Ok(5));
+        assert_eq!(voting.ballot_of(0, alice).unwrap().locked, 9);
+        // Going from 3 to 10 votes costs 100 - 9
+        assert_eq!(vote(&mut voting, alice, 0, 7, true), Ok(91));
+        assert_eq!(balances.borrow()[&alice], 0);
+        assert_eq!(vote(&mut voting, alice, 0, 1, true), Err(QuadraticVotingError::TransferFailed));
+        assert_eq!(voting.ballot_of(0, alice).unwrap().votes, 10);
+    }
+
+    #[test]
+    fn vote_fails_for_invalid_votes() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let (mut voting, _) = deploy_mock();
+        assert_eq!(vote(&mut voting, alice, 1, 1, true), Err(QuadraticVotingError::UnknownPoll));
+        assert_eq!(vote(&mut voting, alice, 0, 0, true), Err(QuadraticVotingError::ZeroVotes));
+        assert_eq!(vote(&mut voting, alice, 0, 1 << 64, true), Err(QuadraticVotingError::CostOverflow));
+        assert_eq!(vote(&mut voting, alice, 0, 1, true), Ok(1));
+        assert_eq!(vote(&mut voting, alice, 0, 1, false), Err(QuadraticVotingError::SideChanged));
+        env::test::set_block_number(101);
+        assert_eq!(vote(&mut voting, alice, 0, 1, true), Err(QuadraticVotingError::PollClosed));
+    }
+
+    #[test]
+    fn many_small_voters_outvote_one_large_voter() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut voting, _) = deploy_mock();
+        // Alice holds twice as many tokens as Bob and Charlie together
+        assert_eq!(vote(&mut voting, alice, 0, 10, true), Ok(100));
+        assert_eq!(vote(&mut voting, bob, 0, 5, false), Ok(25));
+        assert_eq!(vote(&mut voting, charlie, 0, 5, false), Ok(25));
+        let poll = voting.poll(0).unwrap();
+        assert_eq!((poll.yes_votes, poll.no_votes), (10, 10));
+    }
+
+    #[test]
+    fn withdraw_refunds_every_voter() {
+        let alice = AccountId::try_from([0x0; 32]).unwrap();
+        let bob = AccountId::try_from([0x1; 32]).unwrap();
+        let charlie = AccountId::try_from([0x2; 32]).unwrap();
+        let (mut voting, balances) = deploy_mock();
+        assert_eq!(vote(&mut voting, alice, 0, 2, true), Ok(4));
+        assert_eq!(vote(&mut voting, alice, 0, 5, true), Ok(45));
+        assert_eq!(vote(&mut voting, bob, 0, 4, false), Ok(16));
+        env::test::set_block_number(100);
+        assert_eq!(voting.withdraw(0), Err(QuadraticVotingError::PollOpen));
+        env::test::set_block_number(101);
+        assert_eq!(voting.withdraw(0), Ok(16));
+        assert_eq!(voting.withdraw(0), Err(QuadraticVotingError::NothingToWithdraw));
+        env::test::set_caller(alice);
+        assert_eq!(voting.withdraw(0), Ok(49));
+        env::test::set_caller(charlie);
+        assert_eq!(voting.withdraw(0), Err(QuadraticVotingError::NothingToWithdraw));
+        assert_eq!(voting.withdraw(1), Err(QuadraticVotingError::UnknownPoll));
+        assert_eq!(balances.borrow()[&alice], 100);
+        assert_eq!(balances.borrow()[&bob], 25);
+        assert_eq!(balances.borrow()[&env::address()], 0);
+        // The votes still count after the tokens have been withdrawn
+        let poll = voting.poll(0).unwrap();
+        assert_eq!((poll.yes_votes, poll.no_votes), (7, 4));
+        assert_eq!(voting.ballot_of(0, alice), Some(Ballot { support: true, votes: 7, locked: 0 }));
+    }
+}